@@ -0,0 +1,133 @@
+use crate::delivery::{split_content, MESSAGE_MAX_CHARS};
+
+/// 一定是表情符號的字元 (象形符號、雜項符號、裝飾符號、國旗、膚色)
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF
+            | 0x2600..=0x27BF
+            | 0x2B00..=0x2BFF
+            | 0x231A..=0x231B
+            | 0x2328
+            | 0x23CF
+            | 0x23E9..=0x23FA
+            | 0xE0020..=0xE007F)
+}
+
+/// 只有後接 U+FE0F 時才以表情符號呈現的字元 (箭頭、©、™ 等)，單獨出現時保留
+fn is_emoji_with_selector(c: char) -> bool {
+    matches!(c as u32,
+        0x2190..=0x21FF | 0x2934..=0x2935 | 0x00A9 | 0x00AE | 0x2122 | 0x2139 | 0x3030 | 0x303D)
+}
+
+/// 組合用字元：變體選擇符、零寬連接符、鍵帽
+fn is_emoji_joiner(c: char) -> bool {
+    matches!(c as u32, 0xFE0E | 0xFE0F | 0x200D | 0x20E3)
+}
+
+fn strip_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    let mut removed = false;
+    while let Some(c) = chars.next() {
+        let decorative = is_emoji(c)
+            || is_emoji_joiner(c)
+            || (is_emoji_with_selector(c) && chars.peek() == Some(&'\u{FE0F}'));
+        if decorative {
+            removed = true;
+            continue;
+        }
+        // 移除表情後留下的多餘空白 (行首或與前一個空白相連)
+        if removed && c == ' ' && (out.is_empty() || out.ends_with(' ')) {
+            removed = false;
+            continue;
+        }
+        removed = false;
+        out.push(c);
+    }
+    out.trim_end().to_string()
+}
+
+/// 移除裝飾用的表情符號；程式碼區塊內容原樣保留
+pub fn strip_decorative_emoji(text: &str) -> String {
+    let mut in_code = false;
+    text.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                return line.to_string();
+            }
+            if in_code {
+                line.to_string()
+            } else {
+                strip_line(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 無障礙模式的純文字回應：每段以不加裝飾的標題開頭，超過單則上限時分段
+pub fn plain_chunks(views: &[(String, u32, String)], footer: Option<&str>) -> Vec<String> {
+    let mut sections: Vec<String> = views
+        .iter()
+        .map(|(title, _, body)| {
+            format!(
+                "{}\n{}",
+                strip_decorative_emoji(title).trim(),
+                strip_decorative_emoji(body)
+            )
+        })
+        .collect();
+    if let Some(footer) = footer {
+        sections.push(strip_decorative_emoji(footer));
+    }
+    split_content(&sections.join("\n\n"), MESSAGE_MAX_CHARS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_decorative_emoji_keeps_words_and_code() {
+        assert_eq!(
+            strip_decorative_emoji("✅ Agent's Response"),
+            "Agent's Response"
+        );
+        assert_eq!(strip_decorative_emoji("🛠️ Tool Activity"), "Tool Activity");
+        assert_eq!(
+            strip_decorative_emoji("Deploy done 🎉🎉 next step ⏱️ soon"),
+            "Deploy done next step soon"
+        );
+        assert_eq!(strip_decorative_emoji("👨‍👩‍👧 family"), "family");
+        assert_eq!(strip_decorative_emoji("a → b ↪️ c"), "a → b c");
+        assert_eq!(
+            strip_decorative_emoji("```\necho '✅'\n```\n> 💭 hmm"),
+            "```\necho '✅'\n```\n> hmm"
+        );
+        assert_eq!(strip_decorative_emoji("你好 世界"), "你好 世界");
+    }
+
+    #[test]
+    fn test_plain_chunks_use_plain_headers() {
+        let views = vec![
+            ("🛠️ Tool Activity".to_string(), 0, "ran `ls`".to_string()),
+            (
+                "✅ Bot's Response".to_string(),
+                0,
+                "All good ✨".to_string(),
+            ),
+        ];
+        let chunks = plain_chunks(&views, Some("🖼️ Images sent as files"));
+        assert_eq!(
+            chunks,
+            vec!["Tool Activity\nran `ls`\n\nBot's Response\nAll good\n\nImages sent as files"]
+        );
+        let long = vec![("Title".to_string(), 0, "line\n".repeat(600))];
+        let chunks = plain_chunks(&long, None);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks
+            .iter()
+            .all(|c| c.chars().count() <= MESSAGE_MAX_CHARS));
+    }
+}
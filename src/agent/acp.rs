@@ -0,0 +1,1646 @@
+use super::{AgentEvent, AgentState, AiAgent, Citation, ModelInfo, ThinkingSupport};
+use crate::agent::runtime;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::sync::RwLock as StdRwLock;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
+use tracing::{error, info, warn};
+
+// 常駐進程依 (agent_type, 進程編號) 共用；編號由 AcpProcessMode 決定
+type RuntimeKey = (&'static str, u64);
+static RUNTIMES: OnceLock<Mutex<HashMap<RuntimeKey, Arc<AcpRuntime>>>> = OnceLock::new();
+
+/// 同一種 ACP 代理的進程配置：全部頻道共用一個、每頻道一個，或固定大小的進程池
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcpProcessMode {
+    #[default]
+    Shared,
+    PerChannel,
+    Pool,
+}
+
+impl AcpProcessMode {
+    /// 頻道使用的進程編號；同一頻道永遠對應同一進程，session 才能延續
+    pub fn slot(self, channel_id: u64, pool_size: usize) -> u64 {
+        match self {
+            Self::Shared => 0,
+            Self::PerChannel => channel_id,
+            Self::Pool => spread(channel_id) % pool_size.max(1) as u64,
+        }
+    }
+}
+
+/// 打散頻道 ID 的位元 (splitmix64)：snowflake 的低位元多半是 0 或很小的序號，
+/// 直接取餘數幾乎都會落在進程 0；結果固定，重啟後同一頻道仍對應同一進程
+fn spread(id: u64) -> u64 {
+    let mut z = id.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// 啟動 ACP (Agent Client Protocol) 相容代理所需的設定；Copilot 為其中一個預設
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcpProfile {
+    /// 對應 AgentType 的名稱，同時作為模型清單的 provider
+    pub agent_type: &'static str,
+    pub binary: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub process_mode: AcpProcessMode,
+    /// 僅在 `AcpProcessMode::Pool` 時使用
+    pub pool_size: usize,
+}
+
+impl AcpProfile {
+    /// 由 config.toml 的 `[acp]` 區塊建立通用 ACP 後端
+    pub fn from_config(config: &crate::config::AcpConfig) -> anyhow::Result<Self> {
+        if config.binary.trim().is_empty() {
+            anyhow::bail!("ACP backend is not configured: set [acp] binary in config.toml");
+        }
+        Ok(Self {
+            agent_type: "acp",
+            binary: runtime::resolve_binary_path(config.binary.trim()),
+            args: config.args.clone(),
+            env: config.env.clone(),
+            process_mode: AcpProcessMode::Shared,
+            pool_size: 1,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct SessionInfoCache {
+    models: Vec<ModelInfo>,
+    current_model: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct SessionBootstrap {
+    session_id: String,
+    info: SessionInfoCache,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SessionUpdateAction {
+    MessageUpdate {
+        thinking: String,
+        text: String,
+        is_delta: bool,
+        id: Option<String>,
+    },
+    ToolStart {
+        id: String,
+        name: String,
+    },
+    ToolUpdate {
+        id: String,
+        output: String,
+    },
+    /// 工具已完成或失敗
+    ToolEnd {
+        id: String,
+        name: String,
+        output: String,
+    },
+    /// 回答中附上的連結 (`resource_link` 內容)
+    Citation {
+        url: String,
+        title: Option<String>,
+    },
+    Ignore,
+}
+
+impl SessionUpdateAction {
+    /// 轉成送給 writer 的事件；完成的工具先送最終輸出再送結束
+    fn into_events(self) -> Vec<AgentEvent> {
+        match self {
+            SessionUpdateAction::MessageUpdate {
+                thinking,
+                text,
+                is_delta,
+                id,
+            } => vec![AgentEvent::MessageUpdate {
+                thinking,
+                text,
+                is_delta,
+                id,
+            }],
+            SessionUpdateAction::ToolStart { id, name } => {
+                vec![AgentEvent::ToolExecutionStart { id, name }]
+            }
+            SessionUpdateAction::ToolUpdate { id, output } => {
+                vec![AgentEvent::ToolExecutionUpdate { id, output }]
+            }
+            SessionUpdateAction::ToolEnd { id, name, output } => {
+                let mut events = Vec::new();
+                if !output.is_empty() {
+                    events.push(AgentEvent::ToolExecutionUpdate {
+                        id: id.clone(),
+                        output,
+                    });
+                }
+                events.push(AgentEvent::ToolExecutionEnd { id, name });
+                events
+            }
+            SessionUpdateAction::Citation { url, title } => vec![AgentEvent::Citations {
+                items: vec![Citation { url, title }],
+            }],
+            SessionUpdateAction::Ignore => Vec::new(),
+        }
+    }
+}
+
+struct AcpRuntime {
+    agent_type: &'static str,
+    slot: u64,
+    /// `AcpProcessMode::PerChannel` 的專屬進程：頻道的 session 釋放時一併結束
+    dedicated: bool,
+    stdin: Mutex<ChildStdin>,
+    child: Mutex<Child>,
+    pending: Mutex<HashMap<u64, oneshot::Sender<anyhow::Result<Value>>>>,
+    session_senders: RwLock<HashMap<String, broadcast::Sender<AgentEvent>>>,
+    /// session 所屬的頻道，用於在頻道中詢問工具核准
+    session_channels: RwLock<HashMap<String, u64>>,
+    session_info: RwLock<HashMap<String, SessionInfoCache>>,
+    next_id: AtomicU64,
+    /// Ensures only one session/prompt ACP call is in-flight at a time.
+    prompt_lock: Mutex<()>,
+    /// ID of the currently in-flight session/prompt request (if any).
+    /// Used by cancel() to force-resolve the oneshot from our side,
+    /// guaranteeing prompt_lock is released immediately on abort.
+    active_prompt_id: Mutex<Option<u64>>,
+}
+
+impl AcpRuntime {
+    async fn get(profile: &AcpProfile, channel_id: u64) -> anyhow::Result<Arc<Self>> {
+        let key = (
+            profile.agent_type,
+            profile.process_mode.slot(channel_id, profile.pool_size),
+        );
+        let mut runtimes = RUNTIMES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .await;
+        if let Some(runtime) = runtimes.get(&key) {
+            if runtime.ensure_alive().await.is_ok() {
+                return Ok(Arc::clone(runtime));
+            }
+            // 已結束的進程不再沿用，改為重新啟動
+            runtimes.remove(&key);
+        }
+        let runtime = Self::spawn(profile, key.1).await?;
+        runtime
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": 1,
+                    "clientCapabilities": {
+                        "fs": { "readTextFile": false, "writeTextFile": false }
+                    }
+                }),
+            )
+            .await?;
+        runtimes.insert(key, Arc::clone(&runtime));
+        Ok(runtime)
+    }
+
+    async fn spawn(profile: &AcpProfile, slot: u64) -> anyhow::Result<Arc<Self>> {
+        let current_path = std::env::var("PATH").unwrap_or_default();
+        let mut cmd = Command::new(&profile.binary);
+        cmd.args(&profile.args)
+            .envs(&profile.env)
+            .env("PATH", runtime::build_augmented_path(&current_path))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd.spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("ACP stdin not available"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("ACP stdout not available"))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("ACP stderr not available"))?;
+
+        let runtime = Arc::new(Self {
+            agent_type: profile.agent_type,
+            slot,
+            dedicated: profile.process_mode == AcpProcessMode::PerChannel,
+            stdin: Mutex::new(stdin),
+            child: Mutex::new(child),
+            pending: Mutex::new(HashMap::new()),
+            session_senders: RwLock::new(HashMap::new()),
+            session_channels: RwLock::new(HashMap::new()),
+            session_info: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            prompt_lock: Mutex::new(()),
+            active_prompt_id: Mutex::new(None),
+        });
+
+        Self::spawn_stdout_reader(Arc::clone(&runtime), stdout);
+        Self::spawn_stderr_logger(profile.agent_type, stderr);
+        info!(
+            "✅ ACP backend {} (process {}) started from {}",
+            profile.agent_type, slot, profile.binary
+        );
+        Ok(runtime)
+    }
+
+    fn spawn_stdout_reader(runtime: Arc<Self>, stdout: ChildStdout) {
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stdout);
+            let mut line = String::new();
+            while let Ok(n) = reader.read_line(&mut line).await {
+                if n == 0 {
+                    break;
+                }
+                let trimmed = line.trim();
+                if !trimmed.is_empty() {
+                    match serde_json::from_str::<Value>(trimmed) {
+                        Ok(msg) => runtime.handle_message(msg).await,
+                        Err(e) => warn!("{}(acp) invalid JSON: {}", runtime.agent_type, e),
+                    }
+                }
+                line.clear();
+            }
+            error!(
+                "❌ {}(acp) process {} stdout closed",
+                runtime.agent_type, runtime.slot
+            );
+            runtime.evict().await;
+            for (_, tx) in runtime.pending.lock().await.drain() {
+                let _ = tx.send(Err(anyhow::anyhow!(
+                    "{} ACP exited: stdout closed",
+                    runtime.agent_type
+                )));
+            }
+        });
+    }
+
+    /// 從共用表移除 (若仍是表中的那個進程)，下次 `get` 會重新啟動
+    async fn evict(self: &Arc<Self>) {
+        let Some(runtimes) = RUNTIMES.get() else {
+            return;
+        };
+        let mut runtimes = runtimes.lock().await;
+        let key = (self.agent_type, self.slot);
+        if runtimes
+            .get(&key)
+            .is_some_and(|cached| Arc::ptr_eq(cached, self))
+        {
+            runtimes.remove(&key);
+        }
+    }
+
+    /// 移除並結束進程
+    async fn shutdown(self: &Arc<Self>) {
+        self.evict().await;
+        if let Err(e) = self.child.lock().await.kill().await {
+            warn!(
+                "⚠️ Failed to stop {}(acp) process {}: {}",
+                self.agent_type, self.slot, e
+            );
+        }
+    }
+
+    fn spawn_stderr_logger(agent_type: &'static str, stderr: ChildStderr) {
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut line = String::new();
+            while let Ok(n) = reader.read_line(&mut line).await {
+                if n == 0 {
+                    break;
+                }
+                let msg = line.trim();
+                if !msg.is_empty() {
+                    warn!("{}(acp): {}", agent_type, msg);
+                    super::backend_logs::record(agent_type, msg);
+                }
+                line.clear();
+            }
+        });
+    }
+
+    async fn ensure_alive(&self) -> anyhow::Result<()> {
+        let mut child = self.child.lock().await;
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("{} ACP exited: {}", self.agent_type, status);
+        }
+        Ok(())
+    }
+
+    async fn handle_message(self: &Arc<Self>, msg: Value) {
+        if let Some(method) = msg.get("method").and_then(Value::as_str) {
+            match method {
+                "session/update" => self.handle_session_update(&msg).await,
+                "session/request_permission" => self.handle_permission_request(&msg).await,
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(id) = msg.get("id").and_then(Value::as_u64) {
+            let tx = self.pending.lock().await.remove(&id);
+            if let Some(tx) = tx {
+                if let Some(err) = msg.get("error") {
+                    let _ = tx.send(Err(anyhow::anyhow!(Self::error_text(err))));
+                } else {
+                    let _ = tx.send(Ok(msg.get("result").cloned().unwrap_or(Value::Null)));
+                }
+            }
+        }
+    }
+
+    async fn handle_permission_request(self: &Arc<Self>, msg: &Value) {
+        let id = match msg.get("id").and_then(Value::as_u64) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let Some(approver) = super::approval::approver() else {
+            if let Some(option_id) = Self::permission_option_id(msg) {
+                self.respond_permission(id, Some(option_id)).await;
+            }
+            return;
+        };
+
+        // 等待核准可能很久，不能卡住讀取 stdout 的迴圈
+        let session_id = msg["params"]["sessionId"].as_str().unwrap_or_default();
+        let channel_id = self
+            .session_channels
+            .read()
+            .await
+            .get(session_id)
+            .copied()
+            .unwrap_or(0);
+        let tool_call = &msg["params"]["toolCall"];
+        let request = super::approval::ToolRequest {
+            channel_id,
+            backend: self.agent_type,
+            tool: tool_call["title"].as_str().unwrap_or("tool").to_string(),
+            detail: if tool_call["rawInput"].is_null() {
+                String::new()
+            } else {
+                tool_call["rawInput"].to_string()
+            },
+        };
+        let runtime = Arc::clone(self);
+        let msg = msg.clone();
+        tokio::spawn(async move {
+            let approved = approver.approve(request).await;
+            let option_id = Self::asked_option_id(&msg, approved);
+            runtime.respond_permission(id, option_id).await;
+        });
+    }
+
+    /// 回覆選擇的選項；沒有可選的選項時視為取消
+    async fn respond_permission(&self, id: u64, option_id: Option<String>) {
+        let result = match option_id {
+            Some(option_id) => json!({ "optionId": option_id }),
+            None => json!({ "outcome": { "outcome": "cancelled" } }),
+        };
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result
+        });
+        if let Err(e) = self.send_raw(&response).await {
+            warn!("Failed to respond to permission request: {}", e);
+        }
+    }
+
+    /// 詢問後的選項：核准時只允許這一次，拒絕時選 reject 類選項
+    fn asked_option_id(msg: &Value, approved: bool) -> Option<String> {
+        let (kind, keyword) = if approved {
+            ("allow_once", "allow")
+        } else {
+            ("reject_once", "reject")
+        };
+        let options = msg["params"]["options"].as_array()?;
+        let option_id = |opt: &Value| opt["optionId"].as_str().map(str::to_string);
+        options
+            .iter()
+            .find(|opt| opt["kind"].as_str() == Some(kind))
+            .and_then(option_id)
+            .or_else(|| {
+                options
+                    .iter()
+                    .filter_map(option_id)
+                    .find(|id| id.contains(keyword))
+            })
+    }
+
+    fn permission_option_id(msg: &Value) -> Option<String> {
+        msg["params"]["options"].as_array().and_then(|options| {
+            options
+                .iter()
+                .find_map(|opt| {
+                    let id = opt.get("optionId")?.as_str()?;
+                    if id.contains("allow_always") {
+                        Some(id.to_string())
+                    } else {
+                        None
+                    }
+                })
+                .or_else(|| {
+                    options
+                        .iter()
+                        .find_map(|opt| opt.get("optionId")?.as_str().map(|s| s.to_string()))
+                })
+        })
+    }
+
+    async fn handle_session_update(&self, msg: &Value) {
+        let session_id = match msg["params"]["sessionId"].as_str() {
+            Some(v) => v,
+            None => return,
+        };
+
+        let tx = {
+            let sessions = self.session_senders.read().await;
+            sessions.get(session_id).cloned()
+        };
+        let Some(tx) = tx else {
+            return;
+        };
+
+        let update = &msg["params"]["update"];
+        for event in Self::parse_session_update(update).into_events() {
+            let _ = tx.send(event);
+        }
+    }
+
+    fn parse_session_update(update: &Value) -> SessionUpdateAction {
+        let update_type = update["sessionUpdate"].as_str().unwrap_or("");
+        match update_type {
+            "agent_thought_chunk" => {
+                if let Some(text) = Self::update_text(update) {
+                    SessionUpdateAction::MessageUpdate {
+                        thinking: text,
+                        text: "".to_string(),
+                        is_delta: true,
+                        id: None,
+                    }
+                } else {
+                    SessionUpdateAction::Ignore
+                }
+            }
+            "agent_message_chunk" => {
+                let content = &update["content"];
+                if content["type"].as_str() == Some("resource_link") {
+                    return match content["uri"].as_str() {
+                        Some(uri) if uri.starts_with("http://") || uri.starts_with("https://") => {
+                            SessionUpdateAction::Citation {
+                                url: uri.to_string(),
+                                title: content["title"]
+                                    .as_str()
+                                    .or_else(|| content["name"].as_str())
+                                    .map(str::to_string),
+                            }
+                        }
+                        _ => SessionUpdateAction::Ignore,
+                    };
+                }
+                if let Some(text) = Self::update_text(update) {
+                    SessionUpdateAction::MessageUpdate {
+                        thinking: "".to_string(),
+                        text,
+                        is_delta: true,
+                        id: None,
+                    }
+                } else {
+                    SessionUpdateAction::Ignore
+                }
+            }
+            "tool_call" => {
+                let id = update["toolCallId"].as_str().unwrap_or("tool").to_string();
+                let status = update["status"].as_str().unwrap_or("");
+                let title = update["title"]
+                    .as_str()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "Tool Call".to_string());
+                if status == "pending" || status == "running" {
+                    SessionUpdateAction::ToolStart { id, name: title }
+                } else {
+                    SessionUpdateAction::Ignore
+                }
+            }
+            "tool_call_update" => {
+                let id = update["toolCallId"].as_str().unwrap_or("tool").to_string();
+                let status = update["status"].as_str().unwrap_or("");
+                let output = if !update["rawOutput"].is_null() {
+                    Self::value_text(&update["rawOutput"])
+                } else {
+                    status.to_string()
+                };
+                if status == "completed" || status == "failed" {
+                    let name = update["title"].as_str().unwrap_or("tool").to_string();
+                    let output = if update["rawOutput"].is_null() {
+                        String::new()
+                    } else {
+                        output
+                    };
+                    SessionUpdateAction::ToolEnd { id, name, output }
+                } else if output.is_empty() {
+                    SessionUpdateAction::Ignore
+                } else {
+                    SessionUpdateAction::ToolUpdate { id, output }
+                }
+            }
+            _ => SessionUpdateAction::Ignore,
+        }
+    }
+
+    fn update_text(update: &Value) -> Option<String> {
+        update
+            .get("content")
+            .and_then(|c| c.get("text"))
+            .and_then(Value::as_str)
+            .map(|s| s.to_string())
+            .or_else(|| {
+                update
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .map(|s| s.to_string())
+            })
+    }
+
+    fn value_text(value: &Value) -> String {
+        if let Some(s) = value.as_str() {
+            s.to_string()
+        } else {
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+        }
+    }
+
+    fn error_text(err: &Value) -> String {
+        let message = err
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown error");
+        match err.get("data") {
+            Some(data) if !data.is_null() => format!("{}: {}", message, data),
+            _ => message.to_string(),
+        }
+    }
+
+    async fn send_raw(&self, payload: &Value) -> anyhow::Result<()> {
+        let line = serde_json::to_string(payload)?;
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(line.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn request(&self, method: &str, params: Value) -> anyhow::Result<Value> {
+        self.ensure_alive().await?;
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+        self.send_raw(&payload).await?;
+
+        let timeout = if method == "session/prompt" {
+            Duration::from_secs(3600)
+        } else {
+            Duration::from_secs(300)
+        };
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => anyhow::bail!("ACP response channel dropped: {}", method),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                anyhow::bail!("ACP request timeout: {}", method);
+            }
+        }
+    }
+
+    fn parse_session_bootstrap(
+        result: Value,
+        fallback_session_id: Option<&str>,
+        provider: &str,
+    ) -> anyhow::Result<SessionBootstrap> {
+        let session_id = result["sessionId"]
+            .as_str()
+            .map(|s| s.to_string())
+            .or_else(|| fallback_session_id.map(|s| s.to_string()))
+            .ok_or_else(|| anyhow::anyhow!("Missing sessionId in ACP response"))?;
+
+        let models = result["models"]["availableModels"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| {
+                        let id = m.get("modelId")?.as_str()?;
+                        let label = m
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or(id)
+                            .to_string();
+                        Some(ModelInfo {
+                            provider: provider.to_string(),
+                            id: id.to_string(),
+                            label,
+                            ..Default::default()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let current_model = result["models"]["currentModelId"]
+            .as_str()
+            .map(|s| s.to_string());
+
+        Ok(SessionBootstrap {
+            session_id,
+            info: SessionInfoCache {
+                models,
+                current_model,
+            },
+        })
+    }
+
+    async fn create_session(&self, cwd: &str) -> anyhow::Result<SessionBootstrap> {
+        let result = self
+            .request("session/new", json!({ "cwd": cwd, "mcpServers": [] }))
+            .await?;
+        let bootstrap = Self::parse_session_bootstrap(result, None, self.agent_type)?;
+        self.session_info
+            .write()
+            .await
+            .insert(bootstrap.session_id.clone(), bootstrap.info.clone());
+        Ok(bootstrap)
+    }
+
+    async fn load_session(&self, session_id: &str, cwd: &str) -> anyhow::Result<SessionBootstrap> {
+        let result = self
+            .request(
+                "session/load",
+                json!({ "sessionId": session_id, "cwd": cwd, "mcpServers": [] }),
+            )
+            .await?;
+        let bootstrap = Self::parse_session_bootstrap(result, Some(session_id), self.agent_type)?;
+        self.session_info
+            .write()
+            .await
+            .insert(bootstrap.session_id.clone(), bootstrap.info.clone());
+        Ok(bootstrap)
+    }
+
+    async fn cached_session_info(&self, session_id: &str) -> Option<SessionInfoCache> {
+        self.session_info.read().await.get(session_id).cloned()
+    }
+
+    /// 放棄 session：之後不再轉發其事件，也不再視為已載入
+    async fn release_session(&self, session_id: &str) {
+        self.session_senders.write().await.remove(session_id);
+        self.session_channels.write().await.remove(session_id);
+        self.session_info.write().await.remove(session_id);
+    }
+
+    async fn register_session_sender(
+        &self,
+        session_id: &str,
+        channel_id: u64,
+        tx: broadcast::Sender<AgentEvent>,
+    ) {
+        self.session_senders
+            .write()
+            .await
+            .insert(session_id.to_string(), tx);
+        self.session_channels
+            .write()
+            .await
+            .insert(session_id.to_string(), channel_id);
+    }
+
+    /// Sends a session/prompt request and returns a broadcast receiver that
+    /// was subscribed **inside** the prompt_lock, after the lock was acquired
+    /// but before the request was sent.
+    ///
+    /// Subscribing inside the lock is critical: any session/update events from
+    /// a previously cancelled prompt that Copilot emits while we are waiting
+    /// for the lock have no active receiver → they are naturally dropped.
+    /// Events from *this* prompt arrive only after we subscribed → received ✓
+    async fn prompt(
+        &self,
+        session_id: &str,
+        message: &str,
+    ) -> anyhow::Result<(broadcast::Receiver<AgentEvent>, Value)> {
+        let _prompt_guard = self.prompt_lock.lock().await;
+        self.ensure_alive().await?;
+
+        // Create the event receiver here, inside the lock, so we never see
+        // leftover events from a previously cancelled prompt.
+        let event_rx = {
+            let senders = self.session_senders.read().await;
+            senders
+                .get(session_id)
+                .map(|tx| tx.subscribe())
+                .ok_or_else(|| anyhow::anyhow!("No event sender for session {}", session_id))?
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+        *self.active_prompt_id.lock().await = Some(id);
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "session/prompt",
+            "params": {
+                "sessionId": session_id,
+                "prompt": [{ "type": "text", "text": message }]
+            }
+        });
+
+        if let Err(e) = self.send_raw(&payload).await {
+            self.pending.lock().await.remove(&id);
+            *self.active_prompt_id.lock().await = None;
+            return Err(e);
+        }
+
+        let result = match tokio::time::timeout(Duration::from_secs(3600), rx).await {
+            Ok(Ok(val)) => val,
+            Ok(Err(_)) => {
+                *self.active_prompt_id.lock().await = None;
+                anyhow::bail!("ACP response channel dropped: session/prompt");
+            }
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                *self.active_prompt_id.lock().await = None;
+                anyhow::bail!("ACP request timeout: session/prompt");
+            }
+        };
+
+        *self.active_prompt_id.lock().await = None;
+        let response = result?;
+        Ok((event_rx, response))
+    }
+
+    async fn cancel(&self, session_id: &str) -> anyhow::Result<()> {
+        // Force-resolve the in-flight session/prompt request from our side.
+        // This wakes up the rx.await in prompt(), which returns an error,
+        // clears active_prompt_id, and drops prompt_lock — all immediately,
+        // without waiting for Copilot to send a JSON-RPC response.
+        let maybe_id = *self.active_prompt_id.lock().await;
+        if let Some(id) = maybe_id {
+            let tx = self.pending.lock().await.remove(&id);
+            if let Some(tx) = tx {
+                let _ = tx.send(Err(anyhow::anyhow!("Cancelled by user")));
+            }
+        }
+
+        // Also tell Copilot to stop its internal work (best-effort).
+        if let Err(e) = self
+            .request("session/cancel", json!({ "sessionId": session_id }))
+            .await
+        {
+            warn!("session/cancel to ACP backend failed (may be benign): {e}");
+        }
+        Ok(())
+    }
+
+    async fn set_model(&self, session_id: &str, model_id: &str) -> anyhow::Result<()> {
+        self.request(
+            "session/set_model",
+            json!({
+                "sessionId": session_id,
+                "modelId": model_id
+            }),
+        )
+        .await?;
+
+        let mut info_map = self.session_info.write().await;
+        let entry = info_map.entry(session_id.to_string()).or_default();
+        entry.current_model = Some(model_id.to_string());
+        Ok(())
+    }
+}
+
+pub struct AcpAgent {
+    runtime: Arc<AcpRuntime>,
+    channel_id: u64,
+    session_id: StdRwLock<String>,
+    event_tx: broadcast::Sender<AgentEvent>,
+    message_count: AtomicU64,
+    prompt_generation: AtomicU64,
+    models: Arc<RwLock<Vec<ModelInfo>>>,
+    current_model: Arc<RwLock<Option<String>>>,
+}
+
+impl AcpAgent {
+    pub async fn new(
+        profile: &AcpProfile,
+        channel_id: u64,
+        existing_sid: Option<String>,
+        model_opt: Option<(String, String)>,
+        workdir: Option<String>,
+    ) -> anyhow::Result<Arc<Self>> {
+        let runtime = AcpRuntime::get(profile, channel_id).await?;
+        // 頻道綁定的專案目錄，未設定時沿用 bot 程序的目錄
+        let cwd = workdir.unwrap_or_else(|| {
+            std::env::current_dir()
+                .unwrap_or_else(|_| std::path::PathBuf::from("."))
+                .to_string_lossy()
+                .to_string()
+        });
+
+        let (bootstrap, loaded_existing) = if let Some(sid) = existing_sid {
+            match runtime.load_session(&sid, &cwd).await {
+                Ok(info) => (info, true),
+                Err(e) if e.to_string().contains("already loaded") => {
+                    let cached = runtime.cached_session_info(&sid).await.unwrap_or_default();
+                    (
+                        SessionBootstrap {
+                            session_id: sid,
+                            info: cached,
+                        },
+                        true,
+                    )
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to load {} session, creating new one: {}",
+                        profile.agent_type, e
+                    );
+                    (runtime.create_session(&cwd).await?, false)
+                }
+            }
+        } else {
+            (runtime.create_session(&cwd).await?, false)
+        };
+
+        let (event_tx, _) = broadcast::channel(1000);
+        runtime
+            .register_session_sender(&bootstrap.session_id, channel_id, event_tx.clone())
+            .await;
+
+        let agent = Arc::new(Self {
+            runtime,
+            channel_id,
+            session_id: StdRwLock::new(bootstrap.session_id.clone()),
+            event_tx,
+            message_count: AtomicU64::new(if loaded_existing { 1 } else { 0 }),
+            prompt_generation: AtomicU64::new(0),
+            models: Arc::new(RwLock::new(bootstrap.info.models.clone())),
+            current_model: Arc::new(RwLock::new(bootstrap.info.current_model.clone())),
+        });
+
+        if let Some((provider, model_id)) = model_opt {
+            if provider == profile.agent_type && !model_id.is_empty() {
+                if let Err(e) = agent.set_model(&provider, &model_id).await {
+                    warn!(
+                        "Failed to restore {} model preference: {}",
+                        profile.agent_type, e
+                    );
+                }
+            }
+        }
+
+        Ok(agent)
+    }
+
+    pub fn session_id(&self) -> String {
+        self.session_id
+            .read()
+            .expect("acp session_id lock poisoned")
+            .clone()
+    }
+
+    fn is_meaningful_stream_event(event: &AgentEvent) -> bool {
+        match event {
+            AgentEvent::MessageUpdate { thinking, text, .. } => {
+                !thinking.is_empty() || !text.is_empty()
+            }
+            AgentEvent::ContentSync { items } => !items.is_empty(),
+            AgentEvent::ToolExecutionStart { .. }
+            | AgentEvent::ToolExecutionUpdate { .. }
+            | AgentEvent::ToolExecutionEnd { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// session/prompt 回傳後的錯誤：請求失敗，或成功但沒有任何串流輸出
+    fn prompt_error(agent_type: &str, outcome: Result<bool, String>) -> Option<String> {
+        match outcome {
+            Ok(true) => None,
+            Ok(false) => Some(format!(
+                "{} produced no stream output; please retry.",
+                agent_type
+            )),
+            Err(e) => Some(e),
+        }
+    }
+
+    /// session/prompt 回應的 stopReason 表示模型達到輸出上限
+    fn stopped_by_max_tokens(response: &Value) -> bool {
+        response["stopReason"] == "max_tokens"
+    }
+
+    /// 回合結束事件；失敗時先送出錯誤訊息，輸出被截斷時先送出截斷通知
+    fn turn_end_events(error: Option<String>, truncated: bool) -> Vec<AgentEvent> {
+        match error {
+            None if truncated => vec![
+                AgentEvent::OutputTruncated,
+                AgentEvent::AgentEnd {
+                    success: true,
+                    error: None,
+                },
+            ],
+            None => vec![AgentEvent::AgentEnd {
+                success: true,
+                error: None,
+            }],
+            Some(err) => vec![
+                AgentEvent::Error {
+                    message: err.clone(),
+                },
+                AgentEvent::AgentEnd {
+                    success: false,
+                    error: Some(err),
+                },
+            ],
+        }
+    }
+
+    async fn wait_for_stream_output(
+        &self,
+        rx: &mut broadcast::Receiver<AgentEvent>,
+        generation: u64,
+    ) -> bool {
+        const FIRST_EVENT_TIMEOUT: Duration = Duration::from_secs(30);
+        const IDLE_AFTER_EVENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+        let mut timeout = FIRST_EVENT_TIMEOUT;
+        let mut saw_output = false;
+
+        loop {
+            if self.prompt_generation.load(Ordering::SeqCst) != generation {
+                return false;
+            }
+
+            match tokio::time::timeout(timeout, rx.recv()).await {
+                Ok(Ok(event)) => {
+                    if Self::is_meaningful_stream_event(&event) {
+                        saw_output = true;
+                        timeout = IDLE_AFTER_EVENT_TIMEOUT;
+                    }
+                }
+                Ok(Err(tokio::sync::broadcast::error::RecvError::Lagged(_))) => {
+                    saw_output = true;
+                    timeout = IDLE_AFTER_EVENT_TIMEOUT;
+                }
+                Ok(Err(tokio::sync::broadcast::error::RecvError::Closed)) => return saw_output,
+                Err(_) => return saw_output,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AiAgent for AcpAgent {
+    async fn prompt(&self, message: &str) -> anyhow::Result<()> {
+        let generation = self.prompt_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let session_id = self.session_id();
+
+        // runtime.prompt() acquires prompt_lock, subscribes to events inside
+        // the lock, sends the request, waits for Copilot to finish, and returns
+        // the event receiver.  Because the receiver was created inside the lock,
+        // any session/update events from a previously cancelled prompt (which
+        // had no subscriber) were dropped — so wait_for_stream_output below
+        // only sees events from THIS prompt.
+        let mut truncated = false;
+        let outcome = match self.runtime.prompt(&session_id, message).await {
+            Ok((mut stream_rx, response)) => {
+                truncated = Self::stopped_by_max_tokens(&response);
+                if self.prompt_generation.load(Ordering::SeqCst) != generation {
+                    return Ok(());
+                }
+                let saw_output = self
+                    .wait_for_stream_output(&mut stream_rx, generation)
+                    .await;
+                if self.prompt_generation.load(Ordering::SeqCst) != generation {
+                    return Ok(());
+                }
+                if saw_output {
+                    self.message_count.fetch_add(1, Ordering::SeqCst);
+                } else {
+                    warn!(
+                        "⚠️ {} empty response detected: channel={}, session={}",
+                        self.agent_type(),
+                        self.channel_id,
+                        session_id
+                    );
+                }
+                Ok(saw_output)
+            }
+            Err(e) => {
+                if self.prompt_generation.load(Ordering::SeqCst) != generation {
+                    return Ok(());
+                }
+                Err(e.to_string())
+            }
+        };
+
+        let error = Self::prompt_error(self.agent_type(), outcome);
+        for event in Self::turn_end_events(error.clone(), truncated) {
+            let _ = self.event_tx.send(event);
+        }
+        match error {
+            Some(err) => anyhow::bail!(err),
+            None => Ok(()),
+        }
+    }
+
+    async fn set_session_name(&self, _name: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get_state(&self) -> anyhow::Result<AgentState> {
+        let model = self.current_model.read().await.clone();
+        Ok(AgentState {
+            message_count: self.message_count.load(Ordering::SeqCst),
+            model,
+            thinking_level: None,
+        })
+    }
+
+    async fn compact(&self) -> anyhow::Result<()> {
+        let session_id = self.session_id();
+        self.runtime.prompt(&session_id, "/compact").await?;
+        self.message_count.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn abort(&self) -> anyhow::Result<()> {
+        // Invalidate in-flight prompt completions first so stale responses are
+        // silently dropped regardless of whether the cancel reaches Copilot.
+        self.prompt_generation.fetch_add(1, Ordering::SeqCst);
+
+        // Ask Copilot to stop processing the current prompt.  This causes the
+        // pending session/prompt ACP call to return early, releasing prompt_lock
+        // so the next prompt can start immediately instead of waiting for the
+        // old generation to fully finish.
+        let session_id = self.session_id();
+        if let Err(e) = self.runtime.cancel(&session_id).await {
+            // Non-fatal: if there's no active prompt, cancel may fail.
+            warn!("session/cancel failed (may be benign): {e}");
+        }
+        Ok(())
+    }
+
+    /// ACP 沒有刪除 session 的方法：中止進行中的回合並釋放舊 session，
+    /// 呼叫端清除持久化 ID 後，下次對話會以 session/new 開新 session 並重新套用模型
+    async fn clear(&self) -> anyhow::Result<()> {
+        self.abort().await?;
+        let session_id = self.session_id();
+        self.runtime.release_session(&session_id).await;
+        self.message_count.store(0, Ordering::SeqCst);
+        // 每頻道一個進程時，session 釋放後進程已無用途；下次對話會重新啟動
+        if self.runtime.dedicated {
+            self.runtime.shutdown().await;
+        }
+        info!(
+            "🧹 Released {} session {} for channel {}",
+            self.runtime.agent_type, session_id, self.channel_id
+        );
+        Ok(())
+    }
+
+    async fn set_model(&self, provider: &str, model_id: &str) -> anyhow::Result<()> {
+        let session_id = self.session_id();
+        self.runtime.set_model(&session_id, model_id).await?;
+        {
+            let mut current = self.current_model.write().await;
+            *current = Some(model_id.to_string());
+        }
+
+        let channel_id = self.channel_id.to_string();
+        let saved = crate::commands::agent::ChannelConfig::update(|config| {
+            if let Some(entry) = config.channels.get_mut(&channel_id) {
+                entry.model_provider = Some(provider.to_string());
+                entry.model_id = Some(model_id.to_string());
+            }
+        })
+        .await;
+        if let Err(e) = saved {
+            error!(
+                "❌ Failed to persist {} model selection: {}",
+                self.agent_type(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    async fn set_thinking_level(&self, _level: &str) -> anyhow::Result<ThinkingSupport> {
+        anyhow::bail!(
+            "{} backend does not support thinking level setting",
+            self.agent_type()
+        )
+    }
+
+    async fn get_available_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+        let mut models = self.models.read().await.clone();
+        if models.is_empty() {
+            let session_id = self.session_id();
+            if let Some(info) = self.runtime.cached_session_info(&session_id).await {
+                models = info.models;
+                let mut lock = self.models.write().await;
+                *lock = models.clone();
+            }
+        }
+        Ok(models)
+    }
+
+    async fn load_skill(&self, _name: &str) -> anyhow::Result<()> {
+        anyhow::bail!(
+            "{} backend does not support loading skills",
+            self.agent_type()
+        )
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<AgentEvent> {
+        self.event_tx.subscribe()
+    }
+
+    fn agent_type(&self) -> &'static str {
+        self.runtime.agent_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AcpAgent, AcpProcessMode, AcpProfile, AcpRuntime, AgentEvent, SessionUpdateAction,
+    };
+    use crate::agent::conformance::{
+        assert_conforms, Scenario, ANSWER, ERROR, PARTIAL, THINKING, TOOL_ID, TOOL_OUTPUT,
+    };
+    use crate::config::AcpConfig;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_process_mode_slot_is_stable_per_channel() {
+        assert_eq!(AcpProcessMode::Shared.slot(123, 4), 0);
+        assert_eq!(AcpProcessMode::PerChannel.slot(123, 4), 123);
+        let slot = AcpProcessMode::Pool.slot(123, 4);
+        assert!(slot < 4);
+        assert_eq!(AcpProcessMode::Pool.slot(123, 4), slot);
+        // pool_size 為 0 時視為單一進程
+        assert_eq!(AcpProcessMode::Pool.slot(123, 0), 0);
+    }
+
+    #[test]
+    fn test_pool_slot_spreads_snowflake_ids() {
+        // 真實的 snowflake：時間戳在高位元，同一毫秒內建立的頻道只差序號
+        let mut counts = [0usize; 4];
+        for ms in 0..400u64 {
+            let snowflake = ((1_700_000_000_000 + ms * 7919) << 22) | (1 << 17);
+            counts[AcpProcessMode::Pool.slot(snowflake, 4) as usize] += 1;
+        }
+        assert!(counts.iter().all(|n| *n > 50), "{:?}", counts);
+    }
+
+    /// 只回應 initialize 的假 ACP 代理；`then` 決定回應後結束或繼續執行
+    #[cfg(unix)]
+    fn fake_profile(agent_type: &'static str, then: &str) -> AcpProfile {
+        AcpProfile {
+            agent_type,
+            binary: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!(
+                    "read line; echo '{{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{{}}}}'; {}",
+                    then
+                ),
+            ],
+            env: Default::default(),
+            process_mode: AcpProcessMode::PerChannel,
+            pool_size: 1,
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exited_runtime_is_evicted_and_respawned() {
+        let profile = fake_profile("acp-test-exit", "exit 0");
+        let first = AcpRuntime::get(&profile, 1).await.expect("spawn");
+        let runtimes = super::RUNTIMES.get().expect("initialized");
+        for _ in 0..50 {
+            if !runtimes.lock().await.contains_key(&("acp-test-exit", 1)) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(!runtimes.lock().await.contains_key(&("acp-test-exit", 1)));
+
+        let second = AcpRuntime::get(&profile, 1).await.expect("respawn");
+        assert!(!std::sync::Arc::ptr_eq(&first, &second));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_kills_dedicated_runtime() {
+        let profile = fake_profile("acp-test-kill", "sleep 30");
+        let runtime = AcpRuntime::get(&profile, 2).await.expect("spawn");
+        assert!(runtime.dedicated);
+        runtime.shutdown().await;
+        let runtimes = super::RUNTIMES.get().expect("initialized");
+        assert!(!runtimes.lock().await.contains_key(&("acp-test-kill", 2)));
+        assert!(runtime.ensure_alive().await.is_err());
+    }
+
+    #[test]
+    fn test_profile_from_config_requires_binary() {
+        let err = AcpProfile::from_config(&AcpConfig::default()).expect_err("empty binary");
+        assert!(err.to_string().contains("[acp] binary"));
+
+        let profile = AcpProfile::from_config(&AcpConfig {
+            binary: "/opt/agents/gemini".to_string(),
+            args: vec!["--experimental-acp".to_string()],
+            env: [("GEMINI_API_KEY".to_string(), "k".to_string())].into(),
+        })
+        .expect("profile");
+        assert_eq!(profile.agent_type, "acp");
+        assert_eq!(profile.binary, "/opt/agents/gemini");
+        assert_eq!(profile.args, vec!["--experimental-acp".to_string()]);
+        assert_eq!(
+            profile.env.get("GEMINI_API_KEY").map(String::as_str),
+            Some("k")
+        );
+    }
+
+    #[test]
+    fn test_parse_session_bootstrap_uses_profile_provider() {
+        let result = json!({
+            "sessionId": "sid-1",
+            "models": {"availableModels": [{"modelId":"g1"}]}
+        });
+        let parsed = AcpRuntime::parse_session_bootstrap(result, None, "acp").expect("parse");
+        assert_eq!(parsed.info.models[0].provider, "acp");
+        assert_eq!(parsed.info.models[0].label, "g1");
+    }
+
+    #[test]
+    fn test_update_text_and_value_text_extract_text() {
+        let update = json!({
+            "content": {"text": "abc"}
+        });
+        assert_eq!(AcpRuntime::update_text(&update), Some("abc".to_string()));
+
+        let v = json!({"text":"hello"});
+        let out = AcpRuntime::value_text(&v);
+        assert!(out.contains("\"text\""));
+    }
+
+    #[test]
+    fn test_error_text_formats_object_and_string() {
+        let err_obj = json!({"message": "boom"});
+        assert_eq!(AcpRuntime::error_text(&err_obj), "boom");
+        let err_str = json!("oops");
+        assert_eq!(AcpRuntime::error_text(&err_str), "Unknown error");
+    }
+
+    #[test]
+    fn test_parse_session_bootstrap_parses_models_and_current_model() {
+        let result = json!({
+            "sessionId": "sid-1",
+            "models": {
+                "availableModels": [
+                    {"modelId":"m1","name":"M1"},
+                    {"modelId":"m2","name":"M2"}
+                ],
+                "currentModelId": "m2"
+            }
+        });
+        let parsed = AcpRuntime::parse_session_bootstrap(result, None, "copilot").expect("parse");
+        assert_eq!(parsed.session_id, "sid-1");
+        assert_eq!(parsed.info.models.len(), 2);
+        assert_eq!(parsed.info.current_model.as_deref(), Some("m2"));
+    }
+
+    #[test]
+    fn test_permission_option_id_prefers_allow_always() {
+        let msg = json!({
+            "params": {
+                "options": [
+                    {"optionId":"allow_once"},
+                    {"optionId":"allow_always_workspace"}
+                ]
+            }
+        });
+        assert_eq!(
+            AcpRuntime::permission_option_id(&msg).as_deref(),
+            Some("allow_always_workspace")
+        );
+    }
+
+    #[test]
+    fn test_asked_option_id_allows_once_or_rejects() {
+        let msg = json!({
+            "params": {
+                "options": [
+                    {"optionId":"allow_always_workspace","kind":"allow_always"},
+                    {"optionId":"yes","kind":"allow_once"},
+                    {"optionId":"no","kind":"reject_once"}
+                ]
+            }
+        });
+        assert_eq!(
+            AcpRuntime::asked_option_id(&msg, true).as_deref(),
+            Some("yes")
+        );
+        assert_eq!(
+            AcpRuntime::asked_option_id(&msg, false).as_deref(),
+            Some("no")
+        );
+
+        // 沒有 kind 時依 optionId 判斷；沒有拒絕選項時回傳 None (改以取消回覆)
+        let msg = json!({"params": {"options": [{"optionId":"allow_always"}]}});
+        assert_eq!(
+            AcpRuntime::asked_option_id(&msg, true).as_deref(),
+            Some("allow_always")
+        );
+        assert!(AcpRuntime::asked_option_id(&msg, false).is_none());
+    }
+
+    #[test]
+    fn test_parse_session_update_variants() {
+        let thought = json!({"sessionUpdate":"agent_thought_chunk","content":{"text":"hmm"}});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&thought),
+            SessionUpdateAction::MessageUpdate {
+                thinking: "hmm".to_string(),
+                text: "".to_string(),
+                is_delta: true,
+                id: None
+            }
+        );
+
+        let tool = json!({"sessionUpdate":"tool_call","toolCallId":"t1","status":"running","title":"Shell"});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&tool),
+            SessionUpdateAction::ToolStart {
+                id: "t1".to_string(),
+                name: "Shell".to_string()
+            }
+        );
+
+        let link = json!({"sessionUpdate":"agent_message_chunk","content":{"type":"resource_link","uri":"https://docs.rs/tokio","name":"tokio"}});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&link),
+            SessionUpdateAction::Citation {
+                url: "https://docs.rs/tokio".to_string(),
+                title: Some("tokio".to_string())
+            }
+        );
+        let file = json!({"sessionUpdate":"agent_message_chunk","content":{"type":"resource_link","uri":"file:///src/main.rs","name":"main.rs"}});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&file),
+            SessionUpdateAction::Ignore
+        );
+
+        let update = json!({"sessionUpdate":"tool_call_update","toolCallId":"t1","status":"done","rawOutput":{"ok":true}});
+        let parsed = AcpRuntime::parse_session_update(&update);
+        match parsed {
+            SessionUpdateAction::ToolUpdate { id, output } => {
+                assert_eq!(id, "t1");
+                assert!(output.contains("\"ok\""));
+            }
+            _ => panic!("expected tool update"),
+        }
+    }
+
+    #[test]
+    fn test_permission_option_id_fallback_and_none() {
+        let msg = json!({
+            "params": {
+                "options": [
+                    {"optionId":"allow_once"}
+                ]
+            }
+        });
+        assert_eq!(
+            AcpRuntime::permission_option_id(&msg).as_deref(),
+            Some("allow_once")
+        );
+
+        let empty = json!({"params":{"options":[]}});
+        assert!(AcpRuntime::permission_option_id(&empty).is_none());
+    }
+
+    #[test]
+    fn test_parse_session_update_ignore_paths() {
+        let non_running = json!({"sessionUpdate":"tool_call","toolCallId":"t1","status":"done"});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&non_running),
+            SessionUpdateAction::Ignore
+        );
+
+        let empty_update = json!({"sessionUpdate":"tool_call_update","toolCallId":"t1","status":"","rawOutput":null});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&empty_update),
+            SessionUpdateAction::Ignore
+        );
+
+        let unknown = json!({"sessionUpdate":"other"});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&unknown),
+            SessionUpdateAction::Ignore
+        );
+    }
+
+    #[test]
+    fn test_parse_session_update_message_chunk() {
+        let msg = json!({"sessionUpdate":"agent_message_chunk","text":"hello"});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&msg),
+            SessionUpdateAction::MessageUpdate {
+                thinking: "".to_string(),
+                text: "hello".to_string(),
+                is_delta: true,
+                id: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_session_bootstrap_missing_session_id_fails() {
+        let result = json!({
+            "models": {
+                "availableModels": [],
+                "currentModelId": null
+            }
+        });
+        let err =
+            AcpRuntime::parse_session_bootstrap(result, None, "copilot").expect_err("should fail");
+        assert!(err.to_string().contains("Missing sessionId"));
+    }
+
+    #[test]
+    fn test_parse_session_bootstrap_uses_fallback_session_id() {
+        let result = json!({
+            "models": {
+                "availableModels": [],
+                "currentModelId": null
+            }
+        });
+        let parsed = AcpRuntime::parse_session_bootstrap(result, Some("sid-fallback"), "copilot")
+            .expect("parse");
+        assert_eq!(parsed.session_id, "sid-fallback");
+    }
+
+    #[test]
+    fn test_parse_session_update_completed_tool_ends() {
+        let update = json!({
+            "sessionUpdate":"tool_call_update",
+            "toolCallId":"t3",
+            "status":"completed",
+            "rawOutput":"done"
+        });
+        assert_eq!(
+            AcpRuntime::parse_session_update(&update),
+            SessionUpdateAction::ToolEnd {
+                id: "t3".to_string(),
+                name: "tool".to_string(),
+                output: "done".to_string()
+            }
+        );
+
+        let failed =
+            json!({"sessionUpdate":"tool_call_update","toolCallId":"t4","status":"failed"});
+        assert_eq!(
+            AcpRuntime::parse_session_update(&failed),
+            SessionUpdateAction::ToolEnd {
+                id: "t4".to_string(),
+                name: "tool".to_string(),
+                output: String::new()
+            }
+        );
+    }
+
+    #[test]
+    fn test_value_text_string_passthrough_and_tool_update_status_fallback() {
+        assert_eq!(AcpRuntime::value_text(&json!("raw")), "raw");
+
+        let update = json!({
+            "sessionUpdate":"tool_call_update",
+            "toolCallId":"t2",
+            "status":"running",
+            "rawOutput":null
+        });
+        assert_eq!(
+            AcpRuntime::parse_session_update(&update),
+            SessionUpdateAction::ToolUpdate {
+                id: "t2".to_string(),
+                output: "running".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_permission_option_id_without_options_returns_none() {
+        let msg = json!({"params":{}});
+        assert!(AcpRuntime::permission_option_id(&msg).is_none());
+    }
+
+    /// 重現 ACP 的事件流程：session/update 逐筆轉換，最後的 session/prompt 回應決定回合結束事件
+    fn replay_acp(frames: Vec<Value>) -> Vec<AgentEvent> {
+        let mut events = Vec::new();
+        for frame in frames {
+            if frame.get("sessionUpdate").is_some() {
+                events.extend(AcpRuntime::parse_session_update(&frame).into_events());
+                continue;
+            }
+            let outcome = match frame.get("error") {
+                Some(err) => Err(AcpRuntime::error_text(err)),
+                None => Ok(events.iter().any(AcpAgent::is_meaningful_stream_event)),
+            };
+            events.extend(AcpAgent::turn_end_events(
+                AcpAgent::prompt_error("copilot", outcome),
+                AcpAgent::stopped_by_max_tokens(&frame["result"]),
+            ));
+        }
+        events
+    }
+
+    #[test]
+    fn test_max_tokens_stop_reports_truncated_output() {
+        let events = replay_acp(vec![
+            json!({"sessionUpdate": "agent_message_chunk", "content": {"text": "half"}}),
+            json!({"result": {"stopReason": "max_tokens"}}),
+        ]);
+        assert!(matches!(
+            events[events.len() - 2],
+            AgentEvent::OutputTruncated
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(AgentEvent::AgentEnd { success: true, .. })
+        ));
+    }
+
+    fn conformance_fixture(scenario: Scenario) -> Vec<Value> {
+        let chunk =
+            |kind: &str, text: &str| json!({"sessionUpdate": kind, "content": {"text": text}});
+        let done = json!({"result": {"stopReason": "end_turn"}});
+        match scenario {
+            Scenario::PlainAnswer => vec![
+                chunk("agent_message_chunk", "Hello "),
+                chunk("agent_message_chunk", "world"),
+                done,
+            ],
+            Scenario::ThinkingAnswer => vec![
+                chunk("agent_thought_chunk", THINKING),
+                chunk("agent_message_chunk", ANSWER),
+                done,
+            ],
+            Scenario::ToolRun => vec![
+                json!({"sessionUpdate": "tool_call", "toolCallId": TOOL_ID, "status": "pending", "title": "bash"}),
+                json!({"sessionUpdate": "tool_call_update", "toolCallId": TOOL_ID, "status": "in_progress", "rawOutput": "o"}),
+                json!({"sessionUpdate": "tool_call_update", "toolCallId": TOOL_ID, "status": "completed", "rawOutput": TOOL_OUTPUT}),
+                chunk("agent_message_chunk", ANSWER),
+                done,
+            ],
+            Scenario::Error => vec![json!({"error": {"message": ERROR}})],
+            // abort() 先作廢本輪，之後的 session/prompt 回應會被丟棄
+            Scenario::Abort => vec![chunk("agent_message_chunk", PARTIAL)],
+        }
+    }
+
+    #[test]
+    fn test_conformance_matrix() {
+        for scenario in Scenario::ALL {
+            assert_conforms("acp", scenario, &replay_acp(conformance_fixture(scenario)));
+        }
+    }
+}
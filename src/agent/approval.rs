@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use std::sync::{Arc, OnceLock};
+
+static APPROVER: OnceLock<Arc<dyn Approver>> = OnceLock::new();
+
+/// 工具執行的核准方式：自動同意，或在頻道中詢問管理員
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApprovalMode {
+    #[default]
+    Auto,
+    Ask,
+}
+
+/// 代理要求執行的工具
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolRequest {
+    pub channel_id: u64,
+    pub backend: &'static str,
+    pub tool: String,
+    /// 工具參數等補充說明，可為空
+    pub detail: String,
+}
+
+#[async_trait]
+pub trait Approver: Send + Sync {
+    /// 等待核准結果；逾時視為拒絕
+    async fn approve(&self, request: ToolRequest) -> bool;
+}
+
+/// `ask` 模式啟動時安裝一次；未安裝時工具一律自動核准
+pub fn install(approver: Arc<dyn Approver>) {
+    let _ = APPROVER.set(approver);
+}
+
+pub fn approver() -> Option<Arc<dyn Approver>> {
+    APPROVER.get().cloned()
+}
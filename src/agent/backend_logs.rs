@@ -0,0 +1,68 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+// 每個後端進程保留最近的輸出行數
+const MAX_LINES_PER_SOURCE: usize = 500;
+
+static LOGS: OnceLock<Mutex<HashMap<String, VecDeque<String>>>> = OnceLock::new();
+
+fn logs() -> &'static Mutex<HashMap<String, VecDeque<String>>> {
+    LOGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Pi 每個頻道一個進程
+pub fn pi_source(channel_id: u64) -> String {
+    format!("pi:{}", channel_id)
+}
+
+/// 記錄後端進程的一行輸出，供 `/debug backend` 查詢
+pub fn record(source: &str, line: &str) {
+    let mut map = logs().lock().unwrap_or_else(|e| e.into_inner());
+    let buf = map.entry(source.to_string()).or_default();
+    if buf.len() >= MAX_LINES_PER_SOURCE {
+        buf.pop_front();
+    }
+    buf.push_back(format!(
+        "{} {}",
+        chrono::Local::now().format("%H:%M:%S"),
+        line
+    ));
+}
+
+/// 取最後 `limit` 行；有 `filter` 時只保留含該字串的行
+pub fn tail(source: &str, limit: usize, filter: Option<&str>) -> Vec<String> {
+    let map = logs().lock().unwrap_or_else(|e| e.into_inner());
+    let Some(buf) = map.get(source) else {
+        return Vec::new();
+    };
+    let mut lines: Vec<String> = buf
+        .iter()
+        .rev()
+        .filter(|line| filter.is_none_or(|f| line.contains(f)))
+        .take(limit)
+        .cloned()
+        .collect();
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{record, tail, MAX_LINES_PER_SOURCE};
+
+    #[test]
+    fn test_record_keeps_bounded_tail_and_filters() {
+        let source = "test-source-tail";
+        for i in 0..MAX_LINES_PER_SOURCE + 10 {
+            record(source, &format!("line {} sid={}", i, i % 2));
+        }
+        let last = tail(source, 3, None);
+        assert_eq!(last.len(), 3);
+        assert!(last[2].ends_with(&format!("line {} sid=1", MAX_LINES_PER_SOURCE + 9)));
+
+        let filtered = tail(source, 1000, Some("sid=0"));
+        assert_eq!(filtered.len(), MAX_LINES_PER_SOURCE / 2);
+        assert!(filtered.iter().all(|l| l.contains("sid=0")));
+        assert!(tail("missing-source", 5, None).is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use super::acp::AcpProfile;
+use crate::agent::runtime;
+use crate::config::ClaudeConfig;
+use std::collections::HashMap;
+
+/// Claude Code 的 ACP 預設；`claude-code-acp` 以 ACP 包裝 `claude` CLI 的 stream-json 模式
+pub fn profile(config: &ClaudeConfig) -> AcpProfile {
+    AcpProfile {
+        agent_type: "claude",
+        binary: runtime::resolve_binary_with_env("CLAUDE_CODE_ACP_BINARY", "claude-code-acp"),
+        args: Vec::new(),
+        env: HashMap::new(),
+        process_mode: config.process_mode,
+        pool_size: config.pool_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::profile;
+    use crate::agent::acp::AcpProcessMode;
+    use crate::config::ClaudeConfig;
+
+    #[test]
+    fn test_claude_profile_uses_acp_adapter() {
+        let p = profile(&ClaudeConfig::default());
+        assert_eq!(p.agent_type, "claude");
+        assert!(p.binary.ends_with("claude-code-acp"));
+        assert!(p.args.is_empty());
+        assert_eq!(p.process_mode, AcpProcessMode::Shared);
+
+        let per_channel = profile(&ClaudeConfig {
+            process_mode: AcpProcessMode::PerChannel,
+            pool_size: 2,
+        });
+        assert_eq!(per_channel.process_mode, AcpProcessMode::PerChannel);
+        assert_eq!(per_channel.pool_size, 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! 後端事件對應的一致性測試矩陣：每個後端以自己的協定重現下列標準情境，
+//! 解析後的 AgentEvent 正規化後必須與預期步驟相同，新增後端時也要補上所有情境
+use super::{AgentEvent, ContentType};
+
+pub const THINKING: &str = "Let me think";
+pub const ANSWER: &str = "Hello world";
+/// 中止前已串流的部分回答
+pub const PARTIAL: &str = "Hel";
+pub const TOOL_ID: &str = "t1";
+pub const TOOL_OUTPUT: &str = "ok";
+pub const ERROR: &str = "boom";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scenario {
+    /// 只有回答文字
+    PlainAnswer,
+    /// 先思考再回答
+    ThinkingAnswer,
+    /// 執行一個工具 (TOOL_ID，輸出 TOOL_OUTPUT) 後回答
+    ToolRun,
+    /// 後端回報錯誤 ERROR
+    Error,
+    /// 串流 PARTIAL 後被使用者中止
+    Abort,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 5] = [
+        Scenario::PlainAnswer,
+        Scenario::ThinkingAnswer,
+        Scenario::ToolRun,
+        Scenario::Error,
+        Scenario::Abort,
+    ];
+}
+
+/// 與協定細節無關的事件步驟：連續的增量合併、工具輸出只留最新快照
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Thinking(String),
+    Text(String),
+    ToolStart(String),
+    ToolOutput(String, String),
+    ToolEnd(String),
+    Error(String),
+    End { success: bool },
+}
+
+fn push_text(steps: &mut Vec<Step>, thinking: bool, chunk: &str, is_delta: bool) {
+    if chunk.is_empty() {
+        return;
+    }
+    match (steps.last_mut(), thinking) {
+        (Some(Step::Thinking(s)), true) | (Some(Step::Text(s)), false) => {
+            if !is_delta {
+                s.clear();
+            }
+            s.push_str(chunk);
+        }
+        _ if thinking => steps.push(Step::Thinking(chunk.to_string())),
+        _ => steps.push(Step::Text(chunk.to_string())),
+    }
+}
+
+/// ContentSync 是全量快照，不列入步驟 (另由 `assert_conforms` 檢查內容一致)
+pub fn normalize(events: &[AgentEvent]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for event in events {
+        match event {
+            AgentEvent::MessageUpdate {
+                thinking,
+                text,
+                is_delta,
+                ..
+            } => {
+                push_text(&mut steps, true, thinking, *is_delta);
+                push_text(&mut steps, false, text, *is_delta);
+            }
+            AgentEvent::ToolExecutionStart { id, .. } => steps.push(Step::ToolStart(id.clone())),
+            AgentEvent::ToolExecutionUpdate { id, output } => match steps.last_mut() {
+                Some(Step::ToolOutput(last_id, last)) if last_id == id => *last = output.clone(),
+                _ => steps.push(Step::ToolOutput(id.clone(), output.clone())),
+            },
+            AgentEvent::ToolExecutionEnd { id, .. } => steps.push(Step::ToolEnd(id.clone())),
+            AgentEvent::Error { message } => steps.push(Step::Error(message.clone())),
+            AgentEvent::AgentEnd { success, error } => {
+                if let Some(err) = error {
+                    if steps.last() != Some(&Step::Error(err.clone())) {
+                        steps.push(Step::Error(err.clone()));
+                    }
+                }
+                steps.push(Step::End { success: *success });
+            }
+            _ => {}
+        }
+    }
+    steps
+}
+
+pub fn expected(scenario: Scenario) -> Vec<Step> {
+    let text = |s: &str| Step::Text(s.to_string());
+    match scenario {
+        Scenario::PlainAnswer => vec![text(ANSWER), Step::End { success: true }],
+        Scenario::ThinkingAnswer => vec![
+            Step::Thinking(THINKING.to_string()),
+            text(ANSWER),
+            Step::End { success: true },
+        ],
+        Scenario::ToolRun => vec![
+            Step::ToolStart(TOOL_ID.to_string()),
+            Step::ToolOutput(TOOL_ID.to_string(), TOOL_OUTPUT.to_string()),
+            Step::ToolEnd(TOOL_ID.to_string()),
+            text(ANSWER),
+            Step::End { success: true },
+        ],
+        Scenario::Error => vec![Step::Error(ERROR.to_string()), Step::End { success: false }],
+        Scenario::Abort => vec![text(PARTIAL)],
+    }
+}
+
+/// 檢查一個後端在某情境下的事件序列；中止時後端可以不再送出任何事件，
+/// 若送出結束事件則必須是失敗 (錯誤訊息內容各後端不同，不比對)
+pub fn assert_conforms(backend: &str, scenario: Scenario, events: &[AgentEvent]) {
+    let mut steps = normalize(events);
+    if scenario == Scenario::Abort && steps.last() == Some(&Step::End { success: false }) {
+        steps.pop();
+        if matches!(steps.last(), Some(Step::Error(_))) {
+            steps.pop();
+        }
+    }
+    assert_eq!(
+        steps,
+        expected(scenario),
+        "{} does not conform in {:?}: {:#?}",
+        backend,
+        scenario,
+        events
+    );
+
+    // 全量同步的回答必須與串流內容一致，避免最終畫面與串流過程不同
+    let streamed: String = steps
+        .iter()
+        .filter_map(|s| match s {
+            Step::Text(t) => Some(t.as_str()),
+            _ => None,
+        })
+        .collect();
+    for event in events {
+        if let AgentEvent::ContentSync { items } = event {
+            let synced: String = items
+                .iter()
+                .filter(|i| i.type_ == ContentType::Text)
+                .map(|i| i.content.as_str())
+                .collect();
+            if !synced.is_empty() {
+                assert_eq!(
+                    synced, streamed,
+                    "{} synced a different answer in {:?}",
+                    backend, scenario
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_merges_deltas_and_end_errors() {
+        let events = vec![
+            AgentEvent::MessageUpdate {
+                thinking: String::new(),
+                text: "Hel".to_string(),
+                is_delta: true,
+                id: None,
+            },
+            AgentEvent::MessageUpdate {
+                thinking: String::new(),
+                text: "lo".to_string(),
+                is_delta: true,
+                id: None,
+            },
+            AgentEvent::Error {
+                message: "x".to_string(),
+            },
+            AgentEvent::AgentEnd {
+                success: false,
+                error: Some("x".to_string()),
+            },
+        ];
+        assert_eq!(
+            normalize(&events),
+            vec![
+                Step::Text("Hello".to_string()),
+                Step::Error("x".to_string()),
+                Step::End { success: false },
+            ]
+        );
+    }
+}
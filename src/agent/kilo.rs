@@ -1,5 +1,7 @@
 use super::opencode::OpencodeAgent;
-use super::{AgentEvent, AgentState, AiAgent, ModelInfo, UserInput};
+use super::{
+    AgentEvent, AgentState, AiAgent, HistoryEntry, ModelInfo, ThinkingSupport, ToolInfo, UserInput,
+};
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::broadcast;
@@ -16,6 +18,7 @@ impl KiloAgent {
         base_url: String,
         existing_sid: Option<String>,
         model_opt: Option<(String, String)>,
+        workdir: Option<String>,
     ) -> anyhow::Result<Arc<Self>> {
         let inner = OpencodeAgent::new(
             channel_id,
@@ -23,6 +26,7 @@ impl KiloAgent {
             "".to_string(),
             existing_sid,
             model_opt,
+            workdir,
             "kilo",
         )
         .await?;
@@ -62,7 +66,7 @@ impl AiAgent for KiloAgent {
     async fn set_model(&self, provider: &str, model_id: &str) -> anyhow::Result<()> {
         self.inner.set_model(provider, model_id).await
     }
-    async fn set_thinking_level(&self, level: &str) -> anyhow::Result<()> {
+    async fn set_thinking_level(&self, level: &str) -> anyhow::Result<ThinkingSupport> {
         self.inner.set_thinking_level(level).await
     }
     async fn get_available_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
@@ -71,6 +75,12 @@ impl AiAgent for KiloAgent {
     async fn load_skill(&self, name: &str) -> anyhow::Result<()> {
         self.inner.load_skill(name).await
     }
+    async fn list_tools(&self) -> anyhow::Result<Vec<ToolInfo>> {
+        self.inner.list_tools().await
+    }
+    async fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        self.inner.history().await
+    }
     fn subscribe_events(&self) -> broadcast::Receiver<AgentEvent> {
         self.inner.subscribe_events()
     }
@@ -1,5 +1,5 @@
-use crate::agent::runtime;
 use crate::agent::AgentType;
+use crate::agent::{backend_logs, runtime};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -11,25 +11,189 @@ use tracing::{error, info, warn};
 pub struct BackendProcess {
     pub child: Mutex<Child>,
     pub port: u16,
+    /// port_range 指定的埠被佔用而改用空閒埠時，原本設定的埠
+    pub configured_port: Option<u16>,
+}
+
+/// `/health` 顯示的後端實例狀態
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceStatus {
+    pub key: String,
+    pub port: u16,
+    pub configured_port: Option<u16>,
+    pub running: bool,
+}
+
+/// 同類型後端有多個實例時，新頻道的分配策略
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStrategy {
+    #[default]
+    RoundRobin,
+    LeastLoaded,
+}
+
+#[derive(Default)]
+struct PoolState {
+    // (後端類型, 頻道) -> 實例編號；頻道一旦分配即固定，session 不會跨實例漂移
+    assignments: HashMap<(String, u64), usize>,
+    next_index: HashMap<String, usize>,
 }
 
 pub struct BackendManager {
     processes: Arc<Mutex<HashMap<String, Arc<BackendProcess>>>>,
+    pool: Mutex<PoolState>,
     config: Arc<crate::config::Config>,
 }
 
+fn pick_instance(strategy: PoolStrategy, loads: &[usize], next_index: usize) -> usize {
+    match strategy {
+        PoolStrategy::RoundRobin => next_index % loads.len().max(1),
+        PoolStrategy::LeastLoaded => loads
+            .iter()
+            .enumerate()
+            .min_by_key(|(idx, load)| (**load, *idx))
+            .map(|(idx, _)| idx)
+            .unwrap_or(0),
+    }
+}
+
 impl BackendManager {
     pub fn new(config: Arc<crate::config::Config>) -> Self {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
+            pool: Mutex::new(PoolState::default()),
             config,
         }
     }
 
-    fn spawn_stream_logger<R>(label: String, reader: R)
+    /// 為頻道挑選（或沿用）後端實例編號
+    async fn assign_instance(&self, agent_type: &AgentType, channel_id: u64) -> usize {
+        let instances = self.config.opencode.instances.max(1);
+        let key = agent_type.to_string();
+        let mut pool = self.pool.lock().await;
+        if let Some(idx) = pool.assignments.get(&(key.clone(), channel_id)) {
+            if *idx < instances {
+                return *idx;
+            }
+        }
+
+        let mut loads = vec![0usize; instances];
+        for ((t, _), idx) in pool.assignments.iter() {
+            if *t == key && *idx < instances {
+                loads[*idx] += 1;
+            }
+        }
+        let next = pool.next_index.get(&key).copied().unwrap_or(0);
+        let idx = pick_instance(self.config.opencode.strategy, &loads, next);
+        pool.next_index.insert(key.clone(), next.wrapping_add(1));
+        pool.assignments.insert((key, channel_id), idx);
+        info!(
+            "📦 Assigned channel {} to {} instance #{}",
+            channel_id, agent_type, idx
+        );
+        idx
+    }
+
+    /// 頻道目前使用的後端實例 (如 `kilo#0`)，尚未分配時為 None
+    pub async fn instance_key(&self, agent_type: &AgentType, channel_id: u64) -> Option<String> {
+        self.assigned_instance(agent_type, channel_id)
+            .await
+            .map(|idx| format!("{}#{}", agent_type, idx))
+    }
+
+    pub async fn assigned_instance(
+        &self,
+        agent_type: &AgentType,
+        channel_id: u64,
+    ) -> Option<usize> {
+        let pool = self.pool.lock().await;
+        pool.assignments
+            .get(&(agent_type.to_string(), channel_id))
+            .copied()
+    }
+
+    /// 把頻道固定在 session 所在的實例 (重啟後由 channel_config 還原)；實例已不在池中時回傳 false
+    pub async fn pin_channel(
+        &self,
+        agent_type: &AgentType,
+        channel_id: u64,
+        instance: usize,
+    ) -> bool {
+        if instance >= self.config.opencode.instances.max(1) {
+            return false;
+        }
+        let mut pool = self.pool.lock().await;
+        pool.assignments
+            .insert((agent_type.to_string(), channel_id), instance);
+        true
+    }
+
+    /// 頻道目前的實例無法使用時改分配到負載最輕的其他實例；池中只有一個實例時回傳 None
+    pub async fn reassign_channel(&self, agent_type: &AgentType, channel_id: u64) -> Option<usize> {
+        let instances = self.config.opencode.instances.max(1);
+        let key = agent_type.to_string();
+        let mut pool = self.pool.lock().await;
+        let current = pool.assignments.get(&(key.clone(), channel_id)).copied();
+        let mut loads = vec![0usize; instances];
+        for ((t, ch), idx) in pool.assignments.iter() {
+            if *t == key && *ch != channel_id && *idx < instances {
+                loads[*idx] += 1;
+            }
+        }
+        let idx = loads
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| Some(*idx) != current)
+            .min_by_key(|(idx, load)| (**load, *idx))
+            .map(|(idx, _)| idx)?;
+        pool.assignments.insert((key, channel_id), idx);
+        info!(
+            "📦 Reassigned channel {} to {} instance #{}",
+            channel_id, agent_type, idx
+        );
+        Some(idx)
+    }
+
+    /// 頻道切換後端時釋放其實例分配，讓 least_loaded 統計保持正確
+    pub async fn release_channel(&self, channel_id: u64) {
+        let mut pool = self.pool.lock().await;
+        pool.assignments.retain(|(_, ch), _| *ch != channel_id);
+    }
+
+    /// 結束所有由此管理器啟動的後端進程 (供一次性的 CLI 指令收尾)
+    pub async fn shutdown(&self) {
+        let mut procs = self.processes.lock().await;
+        for (key, process) in procs.drain() {
+            if let Err(e) = process.child.lock().await.kill().await {
+                warn!("⚠️ Failed to stop backend {}: {}", key, e);
+            }
+        }
+    }
+
+    fn instance_port(&self, idx: usize) -> anyhow::Result<u16> {
+        match self.config.opencode.port_range {
+            Some((start, end)) => {
+                let port = start as usize + idx;
+                if port > end as usize {
+                    anyhow::bail!(
+                        "Backend instance #{} does not fit in port range {}-{}",
+                        idx,
+                        start,
+                        end
+                    );
+                }
+                Ok(port as u16)
+            }
+            None => Ok(Self::get_free_port()),
+        }
+    }
+
+    fn spawn_stream_logger<R>(source: String, stream: &'static str, reader: R)
     where
         R: tokio::io::AsyncRead + Unpin + Send + 'static,
     {
+        let label = format!("{}({})", source, stream);
         tokio::spawn(async move {
             let mut r = BufReader::new(reader);
             let mut line = String::new();
@@ -40,12 +204,38 @@ impl BackendManager {
                 let msg = line.trim();
                 if !msg.is_empty() {
                     warn!("{}: {}", label, msg);
+                    backend_logs::record(&source, msg);
                 }
                 line.clear();
             }
         });
     }
 
+    /// 設定的埠已被其他程式佔用時改用空閒埠 (只影響執行期狀態，不改寫設定檔)
+    fn resolve_port(configured: u16) -> u16 {
+        if std::net::TcpListener::bind(("127.0.0.1", configured)).is_ok() {
+            configured
+        } else {
+            Self::get_free_port()
+        }
+    }
+
+    /// 目前由此管理器啟動的後端實例與實際使用的埠
+    pub async fn instances(&self) -> Vec<InstanceStatus> {
+        let procs = self.processes.lock().await;
+        let mut out = Vec::new();
+        for (key, process) in procs.iter() {
+            out.push(InstanceStatus {
+                key: key.clone(),
+                port: process.port,
+                configured_port: process.configured_port,
+                running: matches!(process.child.lock().await.try_wait(), Ok(None)),
+            });
+        }
+        out.sort_by(|a, b| a.key.cmp(&b.key));
+        out
+    }
+
     fn get_free_port() -> u16 {
         std::net::TcpListener::bind("127.0.0.1:0")
             .and_then(|listener| listener.local_addr())
@@ -53,8 +243,23 @@ impl BackendManager {
             .unwrap_or(40000)
     }
 
-    pub async fn ensure_backend(&self, agent_type: &AgentType) -> anyhow::Result<u16> {
-        let key = agent_type.to_string();
+    pub async fn ensure_backend(
+        &self,
+        agent_type: &AgentType,
+        channel_id: u64,
+    ) -> anyhow::Result<u16> {
+        if !matches!(agent_type, AgentType::Kilo | AgentType::Opencode) {
+            return Err(anyhow::anyhow!("Unsupported agent type"));
+        }
+        if !super::is_backend_enabled(agent_type) {
+            anyhow::bail!("Backend {} is disabled by enabled_backends", agent_type);
+        }
+        let idx = self.assign_instance(agent_type, channel_id).await;
+        self.ensure_instance(agent_type, idx).await
+    }
+
+    async fn ensure_instance(&self, agent_type: &AgentType, idx: usize) -> anyhow::Result<u16> {
+        let key = format!("{}#{}", agent_type, idx);
 
         // 1. 快速檢查是否已有運行的進程 (使用最小鎖定範圍)
         let mut dead_backend = false;
@@ -62,6 +267,9 @@ impl BackendManager {
             let procs = self.processes.lock().await;
             if let Some(p) = procs.get(&key) {
                 let mut child = p.child.lock().await;
+                if crate::chaos::should_kill_backend() {
+                    let _ = child.kill().await;
+                }
                 if let Ok(None) = child.try_wait() {
                     return Ok(p.port);
                 }
@@ -71,8 +279,9 @@ impl BackendManager {
 
         if dead_backend {
             let mut procs = self.processes.lock().await;
-            warn!("Backend {} died. Removing from map.", agent_type);
+            warn!("Backend {} died. Removing from map.", key);
             procs.remove(&key);
+            crate::prometheus::backend_restarted(&agent_type.to_string());
         }
 
         // 2. 啟動新進程 (重新加鎖)
@@ -82,7 +291,19 @@ impl BackendManager {
             return Ok(p.port);
         }
 
-        let port = Self::get_free_port();
+        let mut port = self.instance_port(idx)?;
+        let mut configured_port = None;
+        if self.config.opencode.port_range.is_some() {
+            let free = Self::resolve_port(port);
+            if free != port {
+                warn!(
+                    "⚠️ Port {} for {} is already in use; using free port {} instead",
+                    port, key, free
+                );
+                configured_port = Some(port);
+                port = free;
+            }
+        }
         let bin_name = match agent_type {
             AgentType::Kilo => "kilo",
             AgentType::Opencode => "opencode",
@@ -101,7 +322,7 @@ impl BackendManager {
         };
         info!(
             "🚀 Starting {} on port {} from {}",
-            agent_type, port, resolved_path
+            key, port, resolved_path
         );
 
         let mut cmd = Command::new(&resolved_path);
@@ -136,16 +357,17 @@ impl BackendManager {
             .spawn()
             .map_err(|e| anyhow::anyhow!("Spawn failed: {}", e))?;
         if let Some(stdout) = child.stdout.take() {
-            Self::spawn_stream_logger(format!("{}(stdout)", agent_type), stdout);
+            Self::spawn_stream_logger(key.clone(), "stdout", stdout);
         }
         if let Some(stderr) = child.stderr.take() {
-            Self::spawn_stream_logger(format!("{}(stderr)", agent_type), stderr);
+            Self::spawn_stream_logger(key.clone(), "stderr", stderr);
         }
         let process = Arc::new(BackendProcess {
             child: Mutex::new(child),
             port,
+            configured_port,
         });
-        procs.insert(key, process);
+        procs.insert(key.clone(), Arc::clone(&process));
 
         // 3. 等待健康檢查 (釋放鎖定，避免阻塞其他頻道)
         drop(procs);
@@ -165,13 +387,30 @@ impl BackendManager {
 
             match req.send().await {
                 Ok(resp) if resp.status().is_success() => {
-                    info!("✅ Backend {} is ready on port {}", agent_type, port);
+                    info!("✅ Backend {} is ready on port {}", key, port);
                     return Ok(port);
                 }
                 _ => {
+                    // 啟動中就結束的進程 (例如埠被佔用) 不必等到逾時
+                    if let Ok(Some(status)) = process.child.lock().await.try_wait() {
+                        self.processes.lock().await.remove(&key);
+                        let logs = backend_logs::tail(&key, 20, None);
+                        let port_taken = logs.iter().any(|line| {
+                            let lower = line.to_lowercase();
+                            lower.contains("address already in use") || lower.contains("eaddrinuse")
+                        });
+                        if port_taken {
+                            anyhow::bail!("Port {} is already in use", port);
+                        }
+                        anyhow::bail!(
+                            "Backend exited during startup ({}): {}",
+                            status,
+                            logs.last().map(String::as_str).unwrap_or("no output")
+                        );
+                    }
                     attempts += 1;
                     if attempts > 60 {
-                        error!("❌ Backend {} failed to start on port {}", agent_type, port);
+                        error!("❌ Backend {} failed to start on port {}", key, port);
                         return Err(anyhow::anyhow!("Backend timeout"));
                     }
                 }
@@ -180,9 +419,40 @@ impl BackendManager {
     }
 }
 
+#[async_trait::async_trait]
+impl crate::services::BackendService for BackendManager {
+    async fn ensure_backend(&self, agent_type: &AgentType, channel_id: u64) -> anyhow::Result<u16> {
+        BackendManager::ensure_backend(self, agent_type, channel_id).await
+    }
+
+    async fn instance_key(&self, agent_type: &AgentType, channel_id: u64) -> Option<String> {
+        BackendManager::instance_key(self, agent_type, channel_id).await
+    }
+
+    async fn assigned_instance(&self, agent_type: &AgentType, channel_id: u64) -> Option<usize> {
+        BackendManager::assigned_instance(self, agent_type, channel_id).await
+    }
+
+    async fn pin_channel(&self, agent_type: &AgentType, channel_id: u64, instance: usize) -> bool {
+        BackendManager::pin_channel(self, agent_type, channel_id, instance).await
+    }
+
+    async fn reassign_channel(&self, agent_type: &AgentType, channel_id: u64) -> Option<usize> {
+        BackendManager::reassign_channel(self, agent_type, channel_id).await
+    }
+
+    async fn release_channel(&self, channel_id: u64) {
+        BackendManager::release_channel(self, channel_id).await
+    }
+
+    async fn instances(&self) -> Vec<InstanceStatus> {
+        BackendManager::instances(self).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::BackendManager;
+    use super::{pick_instance, BackendManager, PoolStrategy};
     use crate::agent::AgentType;
     use crate::config::Config;
     use std::sync::Arc;
@@ -197,9 +467,86 @@ mod tests {
     async fn test_ensure_backend_rejects_unsupported_agent_type() {
         let manager = BackendManager::new(Arc::new(Config::default()));
         let err = manager
-            .ensure_backend(&AgentType::Pi)
+            .ensure_backend(&AgentType::Pi, 1)
             .await
             .expect_err("pi should be unsupported in backend manager");
         assert!(err.to_string().contains("Unsupported agent type"));
     }
+
+    #[test]
+    fn test_pick_instance_strategies() {
+        assert_eq!(pick_instance(PoolStrategy::RoundRobin, &[5, 0, 0], 4), 1);
+        assert_eq!(pick_instance(PoolStrategy::LeastLoaded, &[2, 1, 1], 0), 1);
+        assert_eq!(pick_instance(PoolStrategy::LeastLoaded, &[], 0), 0);
+    }
+
+    #[tokio::test]
+    async fn test_assign_instance_is_sticky_and_balanced() {
+        let mut config = Config::default();
+        config.opencode.instances = 2;
+        config.opencode.strategy = PoolStrategy::LeastLoaded;
+        let manager = BackendManager::new(Arc::new(config));
+
+        let a = manager.assign_instance(&AgentType::Kilo, 1).await;
+        let b = manager.assign_instance(&AgentType::Kilo, 2).await;
+        assert_ne!(a, b);
+        assert_eq!(manager.assign_instance(&AgentType::Kilo, 1).await, a);
+
+        manager.release_channel(1).await;
+        assert_eq!(manager.assign_instance(&AgentType::Kilo, 3).await, a);
+    }
+
+    #[tokio::test]
+    async fn test_pin_and_reassign_channel() {
+        let mut config = Config::default();
+        config.opencode.instances = 3;
+        let manager = BackendManager::new(Arc::new(config));
+
+        assert!(manager.pin_channel(&AgentType::Kilo, 1, 2).await);
+        assert_eq!(manager.assign_instance(&AgentType::Kilo, 1).await, 2);
+        assert_eq!(
+            manager.instance_key(&AgentType::Kilo, 1).await.as_deref(),
+            Some("kilo#2")
+        );
+        // 池縮小後不存在的實例不接受固定
+        assert!(!manager.pin_channel(&AgentType::Kilo, 2, 3).await);
+        assert_eq!(manager.assigned_instance(&AgentType::Kilo, 2).await, None);
+
+        manager.pin_channel(&AgentType::Kilo, 2, 0).await;
+        assert_eq!(manager.reassign_channel(&AgentType::Kilo, 1).await, Some(1));
+        assert_eq!(
+            manager.assigned_instance(&AgentType::Kilo, 1).await,
+            Some(1)
+        );
+
+        let single = BackendManager::new(Arc::new(Config::default()));
+        single.assign_instance(&AgentType::Opencode, 1).await;
+        assert_eq!(single.reassign_channel(&AgentType::Opencode, 1).await, None);
+    }
+
+    #[test]
+    fn test_resolve_port_skips_occupied_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let taken = listener.local_addr().expect("addr").port();
+        let port = BackendManager::resolve_port(taken);
+        assert_ne!(port, taken);
+        assert!(port > 0);
+        drop(listener);
+        assert_eq!(BackendManager::resolve_port(taken), taken);
+    }
+
+    #[tokio::test]
+    async fn test_instances_empty_before_any_backend_starts() {
+        let manager = BackendManager::new(Arc::new(Config::default()));
+        assert!(manager.instances().await.is_empty());
+    }
+
+    #[test]
+    fn test_instance_port_respects_range() {
+        let mut config = Config::default();
+        config.opencode.port_range = Some((41000, 41001));
+        let manager = BackendManager::new(Arc::new(config));
+        assert_eq!(manager.instance_port(1).expect("in range"), 41001);
+        assert!(manager.instance_port(2).is_err());
+    }
 }
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::OnceLock;
 use tokio::sync::broadcast;
 
 #[derive(Clone, Debug)]
@@ -8,13 +9,61 @@ use tokio::sync::broadcast;
 pub struct AgentState {
     pub message_count: u64,
     pub model: Option<String>,
+    /// 後端回報的推理等級，不支援查詢時為 None
+    pub thinking_level: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ModelInfo {
     pub provider: String,
     pub id: String,
     pub label: String,
+    /// 以下為後端提供的模型能力資訊，未提供時為 None
+    pub context_length: Option<u64>,
+    pub supports_vision: Option<bool>,
+    pub pricing: Option<ModelPricing>,
+}
+
+/// 每百萬 token 的美元價格
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPricing {
+    pub fn estimate_usd(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_mtok + output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+impl ModelInfo {
+    /// `AgentState.model` 依後端不同可能是 id、label 或 provider/id
+    pub fn matches(&self, name: &str) -> bool {
+        self.id == name || self.label == name || format!("{}/{}", self.provider, self.id) == name
+    }
+}
+
+/// 後端提供給代理使用的工具
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ToolInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// 匯出對話紀錄用的一則訊息
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    /// `user` 或 `assistant`
+    pub role: String,
+    pub text: String,
+    /// 這則訊息呼叫的工具名稱
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// RFC 3339 時間，後端未提供時為 None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -32,7 +81,7 @@ pub struct ContentItem {
     pub id: Option<String>, // 新增 ID 支持
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct UploadedFile {
     pub id: String,
     pub name: String,
@@ -40,9 +89,21 @@ pub struct UploadedFile {
     pub size: u64,
     pub local_path: String,
     pub source_url: String,
+    /// 來自內容定址快取時的 sha256；同內容的附件在各頻道共用檔案與擷取結果
+    pub content_hash: Option<String>,
 }
 
+/// 快取檔案旁存放 OCR 結果的副檔名
+pub const OCR_CACHE_SUFFIX: &str = ".ocr.txt";
+
 impl UploadedFile {
+    /// 只有內容定址快取的附件才共用 OCR 結果
+    pub fn ocr_cache_path(&self) -> Option<String> {
+        self.content_hash
+            .as_ref()
+            .map(|_| format!("{}{}", self.local_path, OCR_CACHE_SUFFIX))
+    }
+
     pub fn is_image(&self) -> bool {
         self.mime.starts_with("image/")
     }
@@ -60,10 +121,37 @@ impl UploadedFile {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+/// /thinking 的通用等級
+pub const THINKING_LEVELS: [&str; 6] = ["off", "minimal", "low", "medium", "high", "xhigh"];
+
+/// set_thinking_level 的結果：後端是否真的依此等級調整推理
+#[derive(Clone, Debug, PartialEq)]
+pub enum ThinkingSupport {
+    /// 已套用；附上實際送給後端的參數 (如 `variant=high`) 供顯示
+    Applied(Option<String>),
+    /// 等級已記下，但目前的模型不支援調整推理，不會生效
+    Unsupported,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct UserInput {
     pub text: String,
     pub files: Vec<UploadedFile>,
+    /// 觸發此輪的 Discord 使用者，排程等系統觸發時為 None
+    pub requester: Option<u64>,
+    /// /quick 簡答模式：停用工具 (後端支援時)，只顯示回答
+    pub quick: bool,
+    /// 排程以此使用者名義執行：套用其個人偏好，但仍視為系統觸發
+    pub on_behalf_of: Option<u64>,
+    /// 頻道工具政策停用的工具 (後端支援時)
+    pub disabled_tools: Vec<String>,
+    /// 「繼續」接續的先前回答；本輪內容在 composer 中接在它後面
+    pub continues: Option<String>,
+    /// 使用者送出提示的 Discord 訊息；狀態反應模式在上面標示進度
+    pub source_message: Option<u64>,
+    /// 發起者為伺服器管理員或 `[priority] users` 內的使用者：插隊，並可中斷一般使用者的回合
+    pub priority: bool,
 }
 
 impl UserInput {
@@ -71,6 +159,13 @@ impl UserInput {
         Self {
             text,
             files: Vec::new(),
+            requester: None,
+            quick: false,
+            on_behalf_of: None,
+            disabled_tools: Vec::new(),
+            continues: None,
+            source_message: None,
+            priority: false,
         }
     }
 
@@ -99,6 +194,17 @@ impl UserInput {
     }
 }
 
+/// 圖片附件實際交給模型的方式，顯示於 Embed 頁尾
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageInputMode {
+    /// 模型支援視覺，直接附上圖片
+    Vision,
+    /// 模型不支援視覺，改附 OCR 文字
+    OcrFallback,
+    /// 模型不支援視覺且 OCR 失敗，僅提供檔案路徑
+    Unavailable,
+}
+
 #[derive(Clone, Debug)]
 pub enum AgentEvent {
     MessageUpdate {
@@ -118,7 +224,6 @@ pub enum AgentEvent {
         id: String,
         output: String,
     },
-    #[allow(dead_code)]
     ToolExecutionEnd {
         id: String,
         name: String,
@@ -127,6 +232,8 @@ pub enum AgentEvent {
         success: bool,
         error: Option<String>,
     },
+    /// 回答因模型輸出長度上限而中斷 (在 AgentEnd 之前送出)
+    OutputTruncated,
     #[allow(dead_code)]
     AutoRetry {
         attempt: u64,
@@ -139,6 +246,27 @@ pub enum AgentEvent {
         id: String,
         data: serde_json::Value,
     },
+    ImageInput {
+        mode: ImageInputMode,
+    },
+    /// 使用者的追加指示已注入進行中的回合
+    SteeringAdded,
+    /// 回答引用的來源 (如網路搜尋工具的引用標註)
+    Citations {
+        items: Vec<Citation>,
+    },
+    /// 後端回報的本輪 token 用量 (在 AgentEnd 之前送出)
+    Usage {
+        input_tokens: u64,
+        output_tokens: u64,
+    },
+}
+
+/// 後端標註的引用來源
+#[derive(Clone, Debug, PartialEq)]
+pub struct Citation {
+    pub url: String,
+    pub title: Option<String>,
 }
 
 #[async_trait]
@@ -154,9 +282,30 @@ pub trait AiAgent: Send + Sync {
     async fn abort(&self) -> anyhow::Result<()>;
     async fn clear(&self) -> anyhow::Result<()>;
     async fn set_model(&self, provider: &str, model_id: &str) -> anyhow::Result<()>;
-    async fn set_thinking_level(&self, level: &str) -> anyhow::Result<()>;
+    async fn set_thinking_level(&self, level: &str) -> anyhow::Result<ThinkingSupport>;
     async fn get_available_models(&self) -> anyhow::Result<Vec<ModelInfo>>;
     async fn load_skill(&self, name: &str) -> anyhow::Result<()>;
+    /// 目前 session 可用的工具清單；不支援查詢的後端回傳錯誤
+    async fn list_tools(&self) -> anyhow::Result<Vec<ToolInfo>> {
+        anyhow::bail!(
+            "{} backend does not support listing tools",
+            self.agent_type()
+        )
+    }
+    /// 目前 session 的對話紀錄；不支援讀取歷史的後端回傳錯誤
+    async fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        anyhow::bail!(
+            "{} backend does not support reading history",
+            self.agent_type()
+        )
+    }
+    /// 是否能在回合進行中接受追加指示；不支援時新訊息改為排隊
+    fn supports_steering(&self) -> bool {
+        false
+    }
+    async fn steer(&self, _message: &str) -> anyhow::Result<()> {
+        anyhow::bail!("{} backend does not support steering", self.agent_type())
+    }
     fn subscribe_events(&self) -> broadcast::Receiver<AgentEvent>;
     fn agent_type(&self) -> &'static str;
 }
@@ -169,9 +318,18 @@ pub enum AgentType {
     Opencode,
     #[serde(rename = "copilot")]
     Copilot,
+    /// Claude Code，經 `claude-code-acp` 以 ACP 驅動
+    #[serde(rename = "claude")]
+    Claude,
     #[serde(rename = "kilo")]
     #[default]
     Kilo,
+    /// 依 config.toml `[acp]` 設定啟動的任意 ACP 相容代理
+    #[serde(rename = "acp")]
+    Acp,
+    /// 依 config.toml `[openai]` 設定連線的 OpenAI 相容 HTTP 伺服器
+    #[serde(rename = "openai")]
+    OpenAi,
 }
 
 impl std::fmt::Display for AgentType {
@@ -180,7 +338,10 @@ impl std::fmt::Display for AgentType {
             AgentType::Pi => write!(f, "pi"),
             AgentType::Opencode => write!(f, "opencode"),
             AgentType::Copilot => write!(f, "copilot"),
+            AgentType::Claude => write!(f, "claude"),
             AgentType::Kilo => write!(f, "kilo"),
+            AgentType::Acp => write!(f, "acp"),
+            AgentType::OpenAi => write!(f, "openai"),
         }
     }
 }
@@ -192,20 +353,97 @@ impl std::str::FromStr for AgentType {
             "pi" => Ok(AgentType::Pi),
             "opencode" => Ok(AgentType::Opencode),
             "copilot" => Ok(AgentType::Copilot),
+            "claude" => Ok(AgentType::Claude),
             "kilo" => Ok(AgentType::Kilo),
+            "acp" => Ok(AgentType::Acp),
+            "openai" => Ok(AgentType::OpenAi),
             _ => anyhow::bail!("Unknown agent type: {}", s),
         }
     }
 }
 
+impl AgentType {
+    /// 選單顯示順序
+    pub const ALL: [AgentType; 7] = [
+        AgentType::Kilo,
+        AgentType::Copilot,
+        AgentType::Claude,
+        AgentType::Pi,
+        AgentType::Opencode,
+        AgentType::Acp,
+        AgentType::OpenAi,
+    ];
+
+    /// 選單上顯示名稱的 i18n key
+    pub fn choice_key(&self) -> &'static str {
+        match self {
+            AgentType::Pi => "agent_choice_pi",
+            AgentType::Opencode => "agent_choice_opencode",
+            AgentType::Copilot => "agent_choice_copilot",
+            AgentType::Claude => "agent_choice_claude",
+            AgentType::Kilo => "agent_choice_kilo",
+            AgentType::Acp => "agent_choice_acp",
+            AgentType::OpenAi => "agent_choice_openai",
+        }
+    }
+}
+
+// config.toml `enabled_backends`；未設定時全部啟用
+static ENABLED_BACKENDS: OnceLock<Vec<AgentType>> = OnceLock::new();
+
+pub fn install_enabled_backends(enabled: Option<&[AgentType]>) {
+    if let Some(list) = enabled {
+        let _ = ENABLED_BACKENDS.set(list.to_vec());
+    }
+}
+
+fn filter_enabled(enabled: Option<&[AgentType]>) -> Vec<AgentType> {
+    AgentType::ALL
+        .into_iter()
+        .filter(|t| enabled.is_none_or(|list| list.contains(t)))
+        .collect()
+}
+
+/// 目前允許使用的後端 (依選單順序)
+pub fn enabled_backends() -> Vec<AgentType> {
+    filter_enabled(ENABLED_BACKENDS.get().map(Vec::as_slice))
+}
+
+pub fn is_backend_enabled(agent_type: &AgentType) -> bool {
+    ENABLED_BACKENDS
+        .get()
+        .is_none_or(|list| list.contains(agent_type))
+}
+
+fn pick_default_backend(enabled: &[AgentType]) -> AgentType {
+    if enabled.contains(&AgentType::default()) {
+        AgentType::default()
+    } else {
+        enabled.first().cloned().unwrap_or_default()
+    }
+}
+
+/// 未設定後端的頻道使用的預設值：預設後端被停用時改用第一個啟用的後端
+pub fn default_backend() -> AgentType {
+    pick_default_backend(&enabled_backends())
+}
+
+pub mod acp;
+pub mod approval;
+pub mod backend_logs;
+pub mod claude;
+#[cfg(test)]
+pub mod conformance;
 pub mod copilot;
 pub mod kilo;
 pub mod manager;
+pub mod openai;
 pub mod opencode;
 pub mod pi;
 pub mod runtime;
-pub use copilot::CopilotAgent;
+pub use acp::{AcpAgent, AcpProfile};
 pub use kilo::KiloAgent;
+pub use openai::OpenAiAgent;
 pub use opencode::OpencodeAgent;
 pub use pi::PiAgent;
 
@@ -248,6 +486,7 @@ impl AiAgent for MockAgent {
         Ok(AgentState {
             message_count: 1,
             model: Some("mock".into()),
+            thinking_level: None,
         })
     }
     async fn compact(&self) -> anyhow::Result<()> {
@@ -262,8 +501,8 @@ impl AiAgent for MockAgent {
     async fn set_model(&self, _p: &str, _m: &str) -> anyhow::Result<()> {
         Ok(())
     }
-    async fn set_thinking_level(&self, _l: &str) -> anyhow::Result<()> {
-        Ok(())
+    async fn set_thinking_level(&self, _l: &str) -> anyhow::Result<ThinkingSupport> {
+        Ok(ThinkingSupport::Applied(None))
     }
     async fn get_available_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
         Ok(vec![])
@@ -281,7 +520,68 @@ impl AiAgent for MockAgent {
 
 #[cfg(test)]
 mod tests {
-    use super::{UploadedFile, UserInput};
+    use super::{
+        filter_enabled, pick_default_backend, AgentType, ModelInfo, ModelPricing, UploadedFile,
+        UserInput,
+    };
+
+    #[test]
+    fn test_model_info_matches_and_pricing_estimate() {
+        let model = ModelInfo {
+            provider: "anthropic".to_string(),
+            id: "claude-sonnet".to_string(),
+            label: "Claude Sonnet".to_string(),
+            ..Default::default()
+        };
+        assert!(model.matches("claude-sonnet"));
+        assert!(model.matches("anthropic/claude-sonnet"));
+        assert!(model.matches("Claude Sonnet"));
+        assert!(!model.matches("openai/claude-sonnet"));
+
+        let pricing = ModelPricing {
+            input_per_mtok: 3.0,
+            output_per_mtok: 15.0,
+        };
+        assert!((pricing.estimate_usd(1_000_000, 100_000) - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_enabled_backends_filter_keeps_menu_order() {
+        assert_eq!(filter_enabled(None).len(), AgentType::ALL.len());
+        let only = [AgentType::Copilot, AgentType::Kilo];
+        assert_eq!(
+            filter_enabled(Some(&only)),
+            vec![AgentType::Kilo, AgentType::Copilot]
+        );
+        assert_eq!(pick_default_backend(&only), AgentType::Kilo);
+        assert_eq!(
+            pick_default_backend(&[AgentType::Pi, AgentType::Acp]),
+            AgentType::Pi
+        );
+    }
+
+    #[test]
+    fn test_agent_type_acp_roundtrip() {
+        let parsed: AgentType = "ACP".parse().expect("parse");
+        assert_eq!(parsed, AgentType::Acp);
+        assert_eq!(parsed.to_string(), "acp");
+        assert_eq!(
+            serde_json::to_string(&AgentType::Acp).expect("json"),
+            "\"acp\""
+        );
+        let parsed: AgentType = "claude".parse().expect("parse");
+        assert_eq!(parsed, AgentType::Claude);
+        assert_eq!(
+            serde_json::to_string(&AgentType::Claude).expect("json"),
+            "\"claude\""
+        );
+        let parsed: AgentType = "OpenAI".parse().expect("parse");
+        assert_eq!(parsed, AgentType::OpenAi);
+        assert_eq!(
+            serde_json::to_string(&AgentType::OpenAi).expect("json"),
+            "\"openai\""
+        );
+    }
 
     #[test]
     fn test_uploaded_file_display_name_fallback_to_path() {
@@ -292,6 +592,7 @@ mod tests {
             size: 10,
             local_path: "/tmp/demo/a.txt".to_string(),
             source_url: "https://example.com/a.txt".to_string(),
+            content_hash: None,
         };
         assert_eq!(file.display_name(), "a.txt");
     }
@@ -307,7 +608,15 @@ mod tests {
                 size: 1234,
                 local_path: "/tmp/uploads/image.png".to_string(),
                 source_url: "https://cdn.discordapp.com/x".to_string(),
+                content_hash: None,
             }],
+            requester: None,
+            quick: false,
+            on_behalf_of: None,
+            disabled_tools: Vec::new(),
+            continues: None,
+            source_message: None,
+            priority: false,
         };
 
         let rendered = input.to_fallback_prompt();
@@ -0,0 +1,701 @@
+use super::{
+    AgentEvent, AgentState, AiAgent, Citation, HistoryEntry, ModelInfo, ThinkingSupport, UserInput,
+};
+use crate::config::OpenAiConfig;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// 可對應到 `reasoning_effort` 的推理等級，其餘等級不送出
+const REASONING_EFFORTS: [&str; 3] = ["low", "medium", "high"];
+
+/// 對話紀錄中的一則訊息；送給伺服器時只帶 role 與 content
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+struct ChatMessage {
+    role: String,
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+            timestamp: Some(chrono::Utc::now().to_rfc3339()),
+        }
+    }
+}
+
+/// 把 `/chat/completions` 的 SSE 串流轉成 AgentEvent；
+/// 不送出工具定義，回應中的 tool_calls 一律忽略
+#[derive(Default)]
+struct StreamParser {
+    buffer: Vec<u8>,
+    answer: String,
+    truncated: bool,
+    error: Option<String>,
+    /// 最後一次看到的 usage (prompt, completion)，串流結束時送出
+    usage: Option<(u64, u64)>,
+}
+
+impl StreamParser {
+    /// 以位元組累積，避免多位元組字元被切在兩個 chunk 之間
+    fn feed(&mut self, chunk: &[u8]) -> Vec<AgentEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data.is_empty() || data == "[DONE]" {
+                continue;
+            }
+            match serde_json::from_str::<Value>(data) {
+                Ok(val) => events.extend(self.parse_chunk(&val)),
+                Err(e) => warn!("⚠️ Ignoring malformed completion chunk: {}", e),
+            }
+        }
+        events
+    }
+
+    fn parse_chunk(&mut self, val: &Value) -> Vec<AgentEvent> {
+        if let Some(err) = val.get("error") {
+            let message = err["message"]
+                .as_str()
+                .or_else(|| err.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| err.to_string());
+            self.error = Some(message.clone());
+            return vec![AgentEvent::Error { message }];
+        }
+        if let Some(usage) = val["usage"].as_object() {
+            let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+            self.usage = Some((count("prompt_tokens"), count("completion_tokens")));
+        }
+        let choice = &val["choices"][0];
+        if choice["finish_reason"].as_str() == Some("length") {
+            self.truncated = true;
+        }
+        let delta = &choice["delta"];
+        // llama.cpp / DeepSeek 用 reasoning_content，ollama 用 reasoning
+        let thinking = delta["reasoning_content"]
+            .as_str()
+            .or_else(|| delta["reasoning"].as_str())
+            .unwrap_or("");
+        let text = delta["content"].as_str().unwrap_or("");
+        let mut events = Vec::new();
+        if !thinking.is_empty() || !text.is_empty() {
+            self.answer.push_str(text);
+            events.push(AgentEvent::MessageUpdate {
+                thinking: thinking.to_string(),
+                text: text.to_string(),
+                is_delta: true,
+                id: None,
+            });
+        }
+        let citations = url_citations(&delta["annotations"]);
+        if !citations.is_empty() {
+            events.push(AgentEvent::Citations { items: citations });
+        }
+        events
+    }
+
+    /// 串流結束 (`[DONE]` 或連線關閉) 時的結束事件
+    fn finish(&mut self) -> Vec<AgentEvent> {
+        if let Some(error) = self.error.clone() {
+            return vec![AgentEvent::AgentEnd {
+                success: false,
+                error: Some(error),
+            }];
+        }
+        let mut events = Vec::new();
+        if let Some((input_tokens, output_tokens)) = self.usage {
+            events.push(AgentEvent::Usage {
+                input_tokens,
+                output_tokens,
+            });
+        }
+        if self.truncated {
+            events.push(AgentEvent::OutputTruncated);
+        }
+        events.push(AgentEvent::AgentEnd {
+            success: true,
+            error: None,
+        });
+        events
+    }
+}
+
+/// 串流中的 `annotations` (網路搜尋模型與 OpenRouter 的 `url_citation`)
+fn url_citations(annotations: &Value) -> Vec<Citation> {
+    annotations
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|a| a["type"].as_str() == Some("url_citation"))
+        .filter_map(|a| {
+            // 標準格式包在 url_citation 內，部分相容伺服器直接放在外層
+            let c = if a["url_citation"].is_object() {
+                &a["url_citation"]
+            } else {
+                a
+            };
+            Some(Citation {
+                url: c["url"].as_str()?.to_string(),
+                title: c["title"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// 任何 OpenAI 相容的 `/v1/chat/completions` 伺服器。對話紀錄由 bot 保存，
+/// 每輪送出完整紀錄；中止或失敗的回合不寫入紀錄
+pub struct OpenAiAgent {
+    channel_id: u64,
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: RwLock<Option<String>>,
+    reasoning_effort: RwLock<Option<String>>,
+    messages: Arc<Mutex<Vec<ChatMessage>>>,
+    history_file: PathBuf,
+    stream_task: Mutex<Option<JoinHandle<()>>>,
+    event_tx: broadcast::Sender<AgentEvent>,
+}
+
+impl OpenAiAgent {
+    /// 頻道的對話紀錄檔案路徑
+    pub fn history_file(session_dir: &Path, channel_id: u64) -> PathBuf {
+        session_dir.join(format!("discord-rs-{}.json", channel_id))
+    }
+
+    pub async fn new(
+        channel_id: u64,
+        session_dir: &Path,
+        config: &OpenAiConfig,
+        model_opt: Option<(String, String)>,
+    ) -> anyhow::Result<Arc<Self>> {
+        if config.base_url.trim().is_empty() {
+            anyhow::bail!("Set base_url under [openai] in config.toml");
+        }
+        tokio::fs::create_dir_all(session_dir).await?;
+        let history_file = Self::history_file(session_dir, channel_id);
+        let messages = match tokio::fs::read_to_string(&history_file).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!(
+                    "⚠️ OpenAI history of channel {} is corrupt, starting empty: {}",
+                    channel_id, e
+                );
+                Vec::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let model = model_opt
+            .map(|(_, id)| id)
+            .or_else(|| Some(config.model.clone()).filter(|m| !m.is_empty()));
+        let (event_tx, _) = broadcast::channel(1000);
+        info!(
+            "🔌 OpenAI-compatible backend at {} for channel {}",
+            config.base_url, channel_id
+        );
+        Ok(Arc::new(Self {
+            channel_id,
+            client: reqwest::Client::new(),
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            model: RwLock::new(model),
+            reasoning_effort: RwLock::new(None),
+            messages: Arc::new(Mutex::new(messages)),
+            history_file,
+            stream_task: Mutex::new(None),
+            event_tx,
+        }))
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let req = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+        if self.api_key.is_empty() {
+            req
+        } else {
+            req.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn save_messages(path: &Path, messages: &[ChatMessage]) -> anyhow::Result<()> {
+        tokio::fs::write(path, serde_json::to_string_pretty(messages)?).await?;
+        Ok(())
+    }
+
+    async fn request_body(&self, messages: &[ChatMessage]) -> anyhow::Result<Value> {
+        let Some(model) = self.model.read().await.clone() else {
+            anyhow::bail!(
+                "No model selected: set model under [openai] in config.toml or use /model"
+            );
+        };
+        let messages: Vec<Value> = messages
+            .iter()
+            .map(|m| json!({"role": m.role, "content": m.content}))
+            .collect();
+        let mut body = json!({
+            "model": model,
+            "messages": messages,
+            "stream": true,
+            "stream_options": {"include_usage": true},
+        });
+        if let Some(effort) = self.reasoning_effort.read().await.clone() {
+            body["reasoning_effort"] = json!(effort);
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl AiAgent for OpenAiAgent {
+    async fn prompt(&self, message: &str) -> anyhow::Result<()> {
+        self.prompt_with_input(&UserInput::new_text(message.to_string()))
+            .await
+    }
+
+    async fn prompt_with_input(&self, input: &UserInput) -> anyhow::Result<()> {
+        let user = ChatMessage::new("user", input.to_fallback_prompt());
+        let mut turn = self.messages.lock().await.clone();
+        turn.push(user.clone());
+        let body = self.request_body(&turn).await?;
+
+        let mut response = self
+            .request(reqwest::Method::POST, "/chat/completions")
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!("HTTP {}: {}", status, text);
+        }
+
+        let tx = self.event_tx.clone();
+        let messages = Arc::clone(&self.messages);
+        let history_file = self.history_file.clone();
+        let task = tokio::spawn(async move {
+            let mut parser = StreamParser::default();
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        for event in parser.feed(&chunk) {
+                            let _ = tx.send(event);
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        parser.error = Some(e.to_string());
+                        let _ = tx.send(AgentEvent::Error {
+                            message: e.to_string(),
+                        });
+                        break;
+                    }
+                }
+            }
+            // 先寫入紀錄再送出結束事件，下一輪才會帶到這次的回答
+            if parser.error.is_none() {
+                let mut messages = messages.lock().await;
+                messages.push(user);
+                messages.push(ChatMessage::new("assistant", parser.answer.clone()));
+                if let Err(e) = Self::save_messages(&history_file, &messages).await {
+                    warn!("⚠️ Failed to save OpenAI history: {}", e);
+                }
+            }
+            for event in parser.finish() {
+                let _ = tx.send(event);
+            }
+        });
+        if let Some(previous) = self.stream_task.lock().await.replace(task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    async fn set_session_name(&self, _name: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get_state(&self) -> anyhow::Result<AgentState> {
+        Ok(AgentState {
+            message_count: self.messages.lock().await.len() as u64,
+            model: self.model.read().await.clone(),
+            thinking_level: self.reasoning_effort.read().await.clone(),
+        })
+    }
+
+    async fn compact(&self) -> anyhow::Result<()> {
+        anyhow::bail!("{} backend does not support compacting", self.agent_type())
+    }
+
+    async fn abort(&self) -> anyhow::Result<()> {
+        // 中斷讀取即關閉連線，伺服器會停止產生；未完成的回合不寫入紀錄
+        if let Some(task) = self.stream_task.lock().await.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn clear(&self) -> anyhow::Result<()> {
+        self.abort().await?;
+        self.messages.lock().await.clear();
+        match tokio::fs::remove_file(&self.history_file).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        info!("🧹 Cleared OpenAI history for channel {}", self.channel_id);
+        Ok(())
+    }
+
+    async fn set_model(&self, _provider: &str, model_id: &str) -> anyhow::Result<()> {
+        *self.model.write().await = Some(model_id.to_string());
+        Ok(())
+    }
+
+    async fn set_thinking_level(&self, level: &str) -> anyhow::Result<ThinkingSupport> {
+        if REASONING_EFFORTS.contains(&level) {
+            *self.reasoning_effort.write().await = Some(level.to_string());
+            Ok(ThinkingSupport::Applied(Some(format!(
+                "reasoning_effort={}",
+                level
+            ))))
+        } else {
+            *self.reasoning_effort.write().await = None;
+            Ok(ThinkingSupport::Unsupported)
+        }
+    }
+
+    async fn get_available_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+        let resp: Value = self
+            .request(reqwest::Method::GET, "/models")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(resp["data"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m["id"].as_str())
+                    .map(|id| ModelInfo {
+                        provider: "openai".to_string(),
+                        id: id.to_string(),
+                        label: id.to_string(),
+                        ..Default::default()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn load_skill(&self, _name: &str) -> anyhow::Result<()> {
+        anyhow::bail!(
+            "{} backend does not support loading skills",
+            self.agent_type()
+        )
+    }
+
+    async fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        Ok(self
+            .messages
+            .lock()
+            .await
+            .iter()
+            .map(|m| HistoryEntry {
+                role: m.role.clone(),
+                text: m.content.clone(),
+                tools: Vec::new(),
+                timestamp: m.timestamp.clone(),
+            })
+            .collect())
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<AgentEvent> {
+        self.event_tx.subscribe()
+    }
+
+    fn agent_type(&self) -> &'static str {
+        "openai"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::conformance::{assert_conforms, Scenario, ANSWER, ERROR, PARTIAL, THINKING};
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn sse(frames: &[Value]) -> String {
+        frames
+            .iter()
+            .map(|f| format!("data: {}\n\n", f))
+            .collect::<String>()
+            + "data: [DONE]\n\n"
+    }
+
+    fn delta(delta: Value) -> Value {
+        json!({"choices": [{"index": 0, "delta": delta, "finish_reason": null}]})
+    }
+
+    fn config(base_url: &str) -> OpenAiConfig {
+        OpenAiConfig {
+            base_url: base_url.to_string(),
+            api_key: "sk-test".to_string(),
+            model: "qwen".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parser_reports_usage_before_agent_end() {
+        let mut parser = StreamParser::default();
+        let body = sse(&[
+            delta(json!({"content": "hi"})),
+            json!({"choices": [], "usage": {"prompt_tokens": 42, "completion_tokens": 7}}),
+        ]);
+        parser.feed(body.as_bytes());
+        let events = parser.finish();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                AgentEvent::Usage {
+                    input_tokens: 42,
+                    output_tokens: 7
+                },
+                AgentEvent::AgentEnd { success: true, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_parser_emits_url_citations() {
+        let mut parser = StreamParser::default();
+        let body = sse(&[
+            delta(json!({"content": "Rust 1.80 shipped."})),
+            delta(json!({"annotations": [
+                {"type": "url_citation", "url_citation": {
+                    "url": "https://blog.rust-lang.org/", "title": "Rust Blog",
+                    "start_index": 0, "end_index": 18
+                }},
+                {"type": "url_citation", "url": "https://example.com/"},
+                {"type": "file_citation", "file_id": "f1"}
+            ]})),
+        ]);
+        let events = parser.feed(body.as_bytes());
+        let citations: Vec<Citation> = events
+            .into_iter()
+            .filter_map(|e| match e {
+                AgentEvent::Citations { items } => Some(items),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(
+            citations,
+            vec![
+                Citation {
+                    url: "https://blog.rust-lang.org/".to_string(),
+                    title: Some("Rust Blog".to_string()),
+                },
+                Citation {
+                    url: "https://example.com/".to_string(),
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_handles_split_chunks_and_truncation() {
+        let mut parser = StreamParser::default();
+        let body = sse(&[
+            delta(json!({"content": "你好"})),
+            json!({"choices": [{"delta": {"content": "!"}, "finish_reason": "length"}]}),
+        ]);
+        let bytes = body.as_bytes();
+        // 在多位元組字元中間切開
+        let split = body.find("你").expect("char") + 1;
+        let mut events = parser.feed(&bytes[..split]);
+        events.extend(parser.feed(&bytes[split..]));
+        assert_eq!(events.len(), 2);
+        assert_eq!(parser.answer, "你好!");
+        let end = parser.finish();
+        assert!(matches!(end[0], AgentEvent::OutputTruncated));
+        assert!(matches!(end[1], AgentEvent::AgentEnd { success: true, .. }));
+    }
+
+    fn conformance_fixture(scenario: Scenario) -> (String, bool) {
+        match scenario {
+            Scenario::PlainAnswer => (
+                sse(&[
+                    delta(json!({"role": "assistant", "content": ""})),
+                    delta(json!({"content": "Hello "})),
+                    delta(json!({"content": "world"})),
+                ]),
+                true,
+            ),
+            Scenario::ThinkingAnswer => (
+                sse(&[
+                    delta(json!({"reasoning_content": THINKING})),
+                    delta(json!({"content": ANSWER})),
+                ]),
+                true,
+            ),
+            // 不轉送工具呼叫，伺服器即使回傳 tool_calls 也只留下回答
+            Scenario::ToolRun => unreachable!("tool calls are not passed through"),
+            Scenario::Error => (sse(&[json!({"error": {"message": ERROR}})]), true),
+            Scenario::Abort => (
+                format!("data: {}\n\n", delta(json!({"content": PARTIAL}))),
+                false,
+            ),
+        }
+    }
+
+    #[test]
+    fn test_conformance_matrix() {
+        for scenario in Scenario::ALL {
+            if scenario == Scenario::ToolRun {
+                continue;
+            }
+            let (body, finished) = conformance_fixture(scenario);
+            let mut parser = StreamParser::default();
+            let mut events = parser.feed(body.as_bytes());
+            if finished {
+                events.extend(parser.finish());
+            }
+            assert_conforms("openai", scenario, &events);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prompt_streams_and_keeps_history() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_partial_json(json!({"model": "qwen", "stream": true})))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(sse(&[
+                        delta(json!({"tool_calls": [{"index": 0, "id": "c1"}]})),
+                        delta(json!({"content": ANSWER})),
+                    ])),
+            )
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir()?;
+        let base_url = format!("{}/v1/", server.uri());
+        let agent = OpenAiAgent::new(7, dir.path(), &config(&base_url), None).await?;
+        let mut rx = agent.subscribe_events();
+        agent.prompt("hi").await?;
+        let mut text = String::new();
+        loop {
+            match rx.recv().await? {
+                AgentEvent::MessageUpdate { text: t, .. } => text.push_str(&t),
+                AgentEvent::AgentEnd { success, .. } => {
+                    assert!(success);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(text, ANSWER);
+
+        let history = agent.history().await?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].text, "hi");
+        assert_eq!(history[1].role, "assistant");
+        assert_eq!(history[1].text, ANSWER);
+
+        // 重新建立後沿用保存的紀錄，頻道選擇的模型優先於設定檔
+        let reopened = OpenAiAgent::new(
+            7,
+            dir.path(),
+            &config(&base_url),
+            Some(("openai".to_string(), "llama".to_string())),
+        )
+        .await?;
+        let state = reopened.get_state().await?;
+        assert_eq!(state.message_count, 2);
+        assert_eq!(state.model.as_deref(), Some("llama"));
+
+        reopened.clear().await?;
+        assert!(reopened.history().await?.is_empty());
+        assert!(!OpenAiAgent::history_file(dir.path(), 7).exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http_error_and_model_list() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [{"id": "qwen", "object": "model"}, {"id": "llama"}]
+            })))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir()?;
+        let agent = OpenAiAgent::new(
+            8,
+            dir.path(),
+            &config(&format!("{}/v1", server.uri())),
+            None,
+        )
+        .await?;
+        let err = agent.prompt("hi").await.expect_err("401");
+        assert!(err.to_string().contains("401"));
+        assert!(agent.history().await?.is_empty());
+
+        let models = agent.get_available_models().await?;
+        let ids: Vec<_> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["qwen", "llama"]);
+        assert!(models[0].matches("openai/qwen"));
+
+        assert_eq!(
+            agent.set_thinking_level("high").await?,
+            ThinkingSupport::Applied(Some("reasoning_effort=high".to_string()))
+        );
+        assert_eq!(
+            agent.set_thinking_level("xhigh").await?,
+            ThinkingSupport::Unsupported
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_new_requires_base_url() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let err = OpenAiAgent::new(1, dir.path(), &OpenAiConfig::default(), None)
+            .await
+            .err()
+            .expect("missing base_url");
+        assert!(err.to_string().contains("[openai]"));
+    }
+}
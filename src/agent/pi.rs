@@ -1,8 +1,11 @@
-use super::{AgentEvent, AgentState, AiAgent, ContentItem, ContentType, ModelInfo};
+use super::{
+    AgentEvent, AgentState, AiAgent, ContentItem, ContentType, HistoryEntry, ModelInfo,
+    ModelPricing, ThinkingSupport,
+};
 use crate::agent::runtime;
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -15,25 +18,45 @@ pub struct PiAgent {
     stdin: Arc<Mutex<ChildStdin>>,
     event_tx: broadcast::Sender<AgentEvent>,
     child_pid: u32,
+    session_file: PathBuf,
     _pending_trace: Arc<Mutex<String>>, // 修改為非 Option，方便狀態機追加
 }
 
 impl PiAgent {
-    pub async fn new(channel_id: u64, session_dir: &PathBuf) -> anyhow::Result<(Arc<Self>, u64)> {
+    /// 頻道的 Pi session 檔案路徑
+    pub fn session_file(session_dir: &Path, channel_id: u64) -> PathBuf {
+        session_dir.join(format!("discord-rs-{}.jsonl", channel_id))
+    }
+
+    pub async fn new(
+        channel_id: u64,
+        session_dir: &PathBuf,
+        workdir: Option<&str>,
+    ) -> anyhow::Result<(Arc<Self>, u64)> {
         std::fs::create_dir_all(session_dir)?;
         let pi_binary = runtime::resolve_binary_with_env("PI_BINARY", "pi");
         let current_path = std::env::var("PATH").unwrap_or_default();
         let augmented_path = runtime::build_augmented_path(&current_path);
 
         info!("🚀 Spawning Pi binary: {}", pi_binary);
-        let session_file = session_dir.join(format!("discord-rs-{}.jsonl", channel_id));
-        let mut child = Command::new(&pi_binary)
+        let session_file = Self::session_file(session_dir, channel_id);
+        let mut command = Command::new(&pi_binary);
+        command
             .arg("--mode")
             .arg("rpc")
             .arg("--session")
             .arg(&session_file)
             .arg("--session-dir")
-            .arg(session_dir)
+            .arg(session_dir);
+        // 透過 /skill install 安裝的技能包
+        for skill_dir in crate::skills::installed_skill_dirs(&crate::migrate::get_skills_dir()) {
+            command.arg("--skill").arg(skill_dir);
+        }
+        // 頻道綁定的專案目錄，未設定時沿用 bot 程序的目錄
+        if let Some(dir) = workdir {
+            command.current_dir(dir);
+        }
+        let mut child = command
             .env("PATH", augmented_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
@@ -64,6 +87,7 @@ impl PiAgent {
         });
 
         let stderr = child.stderr.take().unwrap();
+        let log_source = super::backend_logs::pi_source(channel_id);
         tokio::spawn(async move {
             let mut reader = BufReader::new(stderr);
             let mut line = String::new();
@@ -74,6 +98,7 @@ impl PiAgent {
                 let msg = line.trim();
                 if !msg.is_empty() {
                     warn!("pi(stderr): {}", msg);
+                    super::backend_logs::record(&log_source, msg);
                 }
                 line.clear();
             }
@@ -84,10 +109,15 @@ impl PiAgent {
             info!("Pi process (PID {}) exited with {:?}", child_pid, status);
         });
 
+        if let Some(approver) = super::approval::approver() {
+            Self::spawn_tool_gate(approver, channel_id, tx.subscribe(), stdin.clone());
+        }
+
         let agent = Arc::new(PiAgent {
             stdin,
             event_tx: tx,
             child_pid,
+            session_file: Self::session_file(session_dir, channel_id),
             _pending_trace: pending_trace,
         });
         agent
@@ -98,6 +128,42 @@ impl PiAgent {
         Ok((agent, 0))
     }
 
+    /// pi 無法暫停工具，只能在工具開始時詢問；被拒絕或逾時就中止這一輪
+    fn spawn_tool_gate(
+        approver: Arc<dyn super::approval::Approver>,
+        channel_id: u64,
+        mut rx: broadcast::Receiver<AgentEvent>,
+        stdin: Arc<Mutex<ChildStdin>>,
+    ) {
+        tokio::spawn(async move {
+            loop {
+                let name = match rx.recv().await {
+                    Ok(AgentEvent::ToolExecutionStart { name, .. }) => name,
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let approver = approver.clone();
+                let stdin = stdin.clone();
+                tokio::spawn(async move {
+                    let request = super::approval::ToolRequest {
+                        channel_id,
+                        backend: "pi",
+                        tool: name,
+                        detail: String::new(),
+                    };
+                    if approver.approve(request).await {
+                        return;
+                    }
+                    info!("⛔ Tool denied on channel {}; aborting pi turn", channel_id);
+                    let abort = json!({ "type": "abort", "id": uuid::Uuid::new_v4().to_string() });
+                    let mut stdin = stdin.lock().await;
+                    let _ = stdin.write_all((abort.to_string() + "\n").as_bytes()).await;
+                    let _ = stdin.flush().await;
+                });
+            }
+        });
+    }
+
     async fn parse_event(
         tx: &broadcast::Sender<AgentEvent>,
         val: Value,
@@ -325,8 +391,8 @@ impl PiAgent {
                                         continue;
                                     } else if !s.is_empty() && !is_trace_start(s) && !is_control(s)
                                     {
-                                        // 如果這是工具訊息的結果文字
-                                        if role == "tool" {
+                                        // 如果這是工具訊息的結果文字 (pi 的角色為 toolResult)
+                                        if role == "tool" || role == "toolResult" {
                                             items.push(ContentItem {
                                                 type_: ContentType::ToolOutput,
                                                 content: s.to_string(),
@@ -371,6 +437,20 @@ impl PiAgent {
                     if !items.is_empty() {
                         let _ = tx.send(AgentEvent::ContentSync { items });
                     }
+                    // 最後一則助理訊息因輸出長度上限停止
+                    let cut_off = current_turn
+                        .iter()
+                        .rfind(|m| m["role"] == "assistant")
+                        .is_some_and(|m| m["stopReason"] == "length");
+                    if cut_off {
+                        let _ = tx.send(AgentEvent::OutputTruncated);
+                    }
+                    if let Some((input_tokens, output_tokens)) = turn_usage(current_turn) {
+                        let _ = tx.send(AgentEvent::Usage {
+                            input_tokens,
+                            output_tokens,
+                        });
+                    }
                 }
                 let _ = tx.send(AgentEvent::AgentEnd {
                     success: final_err.is_none(),
@@ -411,6 +491,106 @@ impl PiAgent {
         Ok(id)
     }
 
+    /// 送出指令並等待對應的 response 資料
+    async fn call(&self, cmd: Value) -> anyhow::Result<Value> {
+        let mut rx = self.event_tx.subscribe();
+        let id = self.raw_call(cmd).await?;
+        tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
+            loop {
+                match rx.recv().await {
+                    Ok(AgentEvent::CommandResponse { id: rid, data }) if rid == id => {
+                        return Ok(data);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        anyhow::bail!("pi process exited")
+                    }
+                    _ => continue,
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err(anyhow::anyhow!("Timeout")))
+    }
+
+    /// `get_state` 回傳目前模型 (provider 與 id)、thinkingLevel 與 messageCount
+    fn parse_state(data: &Value) -> AgentState {
+        let model = match (
+            data["model"]["provider"].as_str(),
+            data["model"]["id"].as_str(),
+        ) {
+            (Some(provider), Some(id)) => Some(format!("{}/{}", provider, id)),
+            _ => None,
+        };
+        AgentState {
+            message_count: data["messageCount"].as_u64().unwrap_or(0),
+            model,
+            thinking_level: data["thinkingLevel"].as_str().map(str::to_string),
+        }
+    }
+
+    /// pi 的模型資料含 contextWindow、input (支援的輸入類型) 與每百萬 token 的 cost
+    fn parse_model(m: &Value) -> Option<ModelInfo> {
+        let provider = m["provider"].as_str()?;
+        let id = m["id"].as_str()?;
+        let pricing = match (m["cost"]["input"].as_f64(), m["cost"]["output"].as_f64()) {
+            (Some(input), Some(output)) => Some(ModelPricing {
+                input_per_mtok: input,
+                output_per_mtok: output,
+            }),
+            _ => None,
+        };
+        Some(ModelInfo {
+            provider: provider.to_string(),
+            id: id.to_string(),
+            label: format!("{}/{}", provider, id),
+            context_length: m["contextWindow"].as_u64(),
+            supports_vision: m["input"]
+                .as_array()
+                .map(|inputs| inputs.iter().any(|i| i.as_str() == Some("image"))),
+            pricing,
+        })
+    }
+
+    /// 解析 session 檔案中的 user / assistant 訊息；工具結果與壓縮紀錄不列入
+    fn parse_history(content: &str) -> Vec<HistoryEntry> {
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter(|v| v["type"] == "message")
+            .filter_map(|v| {
+                let msg = &v["message"];
+                let role = msg["role"].as_str()?;
+                if role != "user" && role != "assistant" {
+                    return None;
+                }
+                let mut entry = HistoryEntry {
+                    role: role.to_string(),
+                    timestamp: v["timestamp"].as_str().map(str::to_string),
+                    ..Default::default()
+                };
+                if let Some(text) = msg["content"].as_str() {
+                    entry.text = text.to_string();
+                    return Some(entry);
+                }
+                let mut texts = Vec::new();
+                for item in msg["content"].as_array().into_iter().flatten() {
+                    match item["type"].as_str() {
+                        Some("text") => texts.extend(item["text"].as_str().map(str::to_string)),
+                        Some("toolCall") => entry.tools.extend(
+                            item["name"]
+                                .as_str()
+                                .or(item["toolCall"]["name"].as_str())
+                                .map(str::to_string),
+                        ),
+                        _ => {}
+                    }
+                }
+                entry.text = texts.join("\n\n");
+                Some(entry)
+            })
+            .collect()
+    }
+
     fn kill_child(&self) {
         if self.child_pid > 0 {
             unsafe {
@@ -432,10 +612,8 @@ impl AiAgent for PiAgent {
         Ok(())
     }
     async fn get_state(&self) -> anyhow::Result<AgentState> {
-        Ok(AgentState {
-            message_count: 0,
-            model: None,
-        })
+        let data = self.call(json!({ "type": "get_state" })).await?;
+        Ok(Self::parse_state(&data))
     }
     async fn compact(&self) -> anyhow::Result<()> {
         self.raw_call(json!({ "type": "compact" })).await?;
@@ -453,50 +631,40 @@ impl AiAgent for PiAgent {
             .await?;
         Ok(())
     }
-    async fn set_thinking_level(&self, l: &str) -> anyhow::Result<()> {
+    async fn set_thinking_level(&self, l: &str) -> anyhow::Result<ThinkingSupport> {
         self.raw_call(json!({ "type": "set_thinking_level", "level": l }))
             .await?;
-        Ok(())
+        Ok(ThinkingSupport::Applied(None))
     }
     async fn get_available_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
-        let id = self
-            .raw_call(json!({ "type": "get_available_models" }))
-            .await?;
-        let mut rx = self.event_tx.subscribe();
-        let result = tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
-            loop {
-                match rx.recv().await {
-                    Ok(AgentEvent::CommandResponse { id: rid, data }) if rid == id => {
-                        let models = data["models"]
-                            .as_array()
-                            .ok_or_else(|| anyhow::anyhow!("Missing models array"))?;
-                        return Ok(models
-                            .iter()
-                            .filter_map(|m| {
-                                Some(ModelInfo {
-                                    provider: m["provider"].as_str()?.to_string(),
-                                    id: m["id"].as_str()?.to_string(),
-                                    label: format!(
-                                        "{}/{}",
-                                        m["provider"].as_str()?,
-                                        m["id"].as_str()?
-                                    ),
-                                })
-                            })
-                            .collect());
-                    }
-                    _ => continue,
-                }
-            }
-        })
-        .await;
-        result.unwrap_or(Err(anyhow::anyhow!("Timeout")))
+        let data = self.call(json!({ "type": "get_available_models" })).await?;
+        let models = data["models"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Missing models array"))?;
+        Ok(models.iter().filter_map(Self::parse_model).collect())
     }
     async fn load_skill(&self, n: &str) -> anyhow::Result<()> {
         self.raw_call(json!({ "type": "load_skill", "name": n }))
             .await?;
         Ok(())
     }
+    async fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        match tokio::fs::read_to_string(&self.session_file).await {
+            Ok(content) => Ok(Self::parse_history(&content)),
+            // 尚未有任何對話時 pi 不會建立 session 檔案
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    fn supports_steering(&self) -> bool {
+        true
+    }
+    async fn steer(&self, message: &str) -> anyhow::Result<()> {
+        self.raw_call(json!({ "type": "steer", "message": message }))
+            .await?;
+        let _ = self.event_tx.send(AgentEvent::SteeringAdded);
+        Ok(())
+    }
     fn subscribe_events(&self) -> broadcast::Receiver<AgentEvent> {
         self.event_tx.subscribe()
     }
@@ -505,6 +673,20 @@ impl AiAgent for PiAgent {
     }
 }
 
+/// 本輪各助理訊息回報的 token 用量加總；都沒有回報時為 None
+fn turn_usage(messages: &[Value]) -> Option<(u64, u64)> {
+    messages
+        .iter()
+        .filter(|m| m["role"] == "assistant" && m["usage"].is_object())
+        .map(|m| {
+            (
+                m["usage"]["input"].as_u64().unwrap_or(0),
+                m["usage"]["output"].as_u64().unwrap_or(0),
+            )
+        })
+        .reduce(|(a, b), (c, d)| (a + c, b + d))
+}
+
 impl Drop for PiAgent {
     fn drop(&mut self) {
         self.kill_child();
@@ -514,6 +696,9 @@ impl Drop for PiAgent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::conformance::{
+        assert_conforms, Scenario, ANSWER, ERROR, PARTIAL, THINKING, TOOL_ID, TOOL_OUTPUT,
+    };
 
     fn setup_parser_test() -> (
         broadcast::Sender<AgentEvent>,
@@ -525,6 +710,81 @@ mod tests {
         (tx, rx, pending)
     }
 
+    #[test]
+    fn test_parse_state_reads_model_and_thinking_level() {
+        let state = PiAgent::parse_state(&json!({
+            "model": {"provider": "anthropic", "id": "claude-sonnet"},
+            "thinkingLevel": "high",
+            "messageCount": 4
+        }));
+        assert_eq!(state.model.as_deref(), Some("anthropic/claude-sonnet"));
+        assert_eq!(state.thinking_level.as_deref(), Some("high"));
+        assert_eq!(state.message_count, 4);
+
+        let empty = PiAgent::parse_state(&Value::Null);
+        assert!(empty.model.is_none() && empty.thinking_level.is_none());
+        assert_eq!(empty.message_count, 0);
+    }
+
+    #[test]
+    fn test_parse_model_reads_capabilities() {
+        let model = PiAgent::parse_model(&json!({
+            "provider": "anthropic",
+            "id": "claude-sonnet",
+            "contextWindow": 200000,
+            "input": ["text", "image"],
+            "cost": {"input": 3, "output": 15, "cacheRead": 0.3}
+        }))
+        .expect("model");
+        assert_eq!(model.label, "anthropic/claude-sonnet");
+        assert_eq!(model.context_length, Some(200000));
+        assert_eq!(model.supports_vision, Some(true));
+        assert_eq!(
+            model.pricing,
+            Some(ModelPricing {
+                input_per_mtok: 3.0,
+                output_per_mtok: 15.0
+            })
+        );
+
+        let bare = PiAgent::parse_model(&json!({"provider": "p", "id": "m"})).expect("model");
+        assert!(bare.context_length.is_none());
+        assert!(bare.supports_vision.is_none());
+        assert!(bare.pricing.is_none());
+        assert!(PiAgent::parse_model(&json!({"id": "m"})).is_none());
+    }
+
+    #[test]
+    fn test_parse_history_reads_messages_only() {
+        let lines = [
+            json!({"type": "session", "id": "s1"}),
+            json!({"type": "message", "timestamp": "2026-01-01T00:00:00Z",
+                   "message": {"role": "user", "content": "fix the build"}}),
+            json!({"type": "message", "message": {"role": "assistant", "content": [
+                {"type": "thinking", "thinking": "hmm"},
+                {"type": "toolCall", "id": "t1", "name": "bash"},
+                {"type": "text", "text": "done"}
+            ]}}),
+            json!({"type": "message", "message": {"role": "toolResult", "content": "ok"}}),
+        ];
+        let content = lines
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\nnot json";
+        let history = PiAgent::parse_history(&content);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].text, "fix the build");
+        assert_eq!(
+            history[0].timestamp.as_deref(),
+            Some("2026-01-01T00:00:00Z")
+        );
+        assert_eq!(history[1].role, "assistant");
+        assert_eq!(history[1].text, "done");
+        assert_eq!(history[1].tools, vec!["bash".to_string()]);
+    }
+
     #[tokio::test]
     async fn test_parse_event_text_delta() {
         let (tx, mut rx, pending) = setup_parser_test();
@@ -753,4 +1013,108 @@ mod tests {
             _ => panic!("expected agent end"),
         }
     }
+
+    #[tokio::test]
+    async fn test_parse_event_agent_end_reports_length_stop() {
+        let (tx, mut rx, pending) = setup_parser_test();
+        let val = json!({
+            "type":"agent_end",
+            "messages":[
+                {"role":"user","content":[{"type":"text","text":"question"}]},
+                {"role":"assistant","content":[{"type":"text","text":"half an ans"}],"stopReason":"length",
+                 "usage":{"input":900,"output":4096,"cacheRead":0}}
+            ]
+        });
+        PiAgent::parse_event(&tx, val, &pending).await;
+
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            AgentEvent::ContentSync { .. }
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            AgentEvent::OutputTruncated
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            AgentEvent::Usage {
+                input_tokens: 900,
+                output_tokens: 4096
+            }
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            AgentEvent::AgentEnd { success: true, .. }
+        ));
+    }
+
+    fn conformance_fixture(scenario: Scenario) -> Vec<Value> {
+        let delta = |kind: &str, d: &str| json!({"type": "message_update", "assistantMessageEvent": {"type": kind, "delta": d}});
+        let user = json!({"role": "user", "content": [{"type": "text", "text": "hi"}]});
+        let end = |messages: Vec<Value>| {
+            let mut all = vec![user.clone()];
+            all.extend(messages);
+            json!({"type": "agent_end", "messages": all})
+        };
+        let answer = json!({"role": "assistant", "content": [{"type": "text", "text": ANSWER}]});
+        match scenario {
+            Scenario::PlainAnswer => vec![
+                delta("text_delta", "Hello "),
+                delta("text_delta", "world"),
+                end(vec![answer]),
+            ],
+            Scenario::ThinkingAnswer => vec![
+                delta("thinking_delta", THINKING),
+                delta("text_delta", ANSWER),
+                end(vec![json!({"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": THINKING},
+                    {"type": "text", "text": ANSWER}
+                ]})]),
+            ],
+            Scenario::ToolRun => vec![
+                json!({"type": "tool_execution_start", "toolCallId": TOOL_ID, "toolName": "bash"}),
+                json!({"type": "tool_execution_update", "toolCallId": TOOL_ID,
+                    "partialResult": {"content": [{"type": "text", "text": "o"}]}}),
+                json!({"type": "tool_execution_end", "toolCallId": TOOL_ID, "toolName": "bash",
+                    "result": {"content": [{"type": "text", "text": TOOL_OUTPUT}]}}),
+                delta("text_delta", ANSWER),
+                end(vec![
+                    json!({"role": "assistant", "content": [
+                        {"type": "toolCall", "toolCall": {"id": TOOL_ID, "name": "bash"}}
+                    ]}),
+                    json!({"role": "toolResult", "toolCallId": TOOL_ID,
+                        "content": [{"type": "text", "text": TOOL_OUTPUT}]}),
+                    answer,
+                ]),
+            ],
+            Scenario::Error => vec![end(vec![json!({
+                "role": "assistant", "content": [], "stopReason": "error", "errorMessage": ERROR
+            })])],
+            Scenario::Abort => vec![
+                delta("text_delta", PARTIAL),
+                end(vec![json!({
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": PARTIAL}],
+                    "stopReason": "aborted",
+                    "errorMessage": "Request was aborted"
+                })]),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conformance_matrix() {
+        for scenario in Scenario::ALL {
+            let (tx, mut rx) = broadcast::channel(64);
+            let pending = Arc::new(Mutex::new(String::new()));
+            let mut events = Vec::new();
+            for frame in conformance_fixture(scenario) {
+                PiAgent::parse_event(&tx, frame, &pending).await;
+                while let Ok(event) = rx.try_recv() {
+                    events.push(event);
+                }
+            }
+            assert_conforms("pi", scenario, &events);
+        }
+    }
 }
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+const FILE_NAME: &str = "turns.ndjson";
+
+/// 單輪完成後寫入的一筆分析紀錄
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TurnRecord {
+    pub ts: String,
+    pub channel_id: u64,
+    pub backend: String,
+    pub model: Option<String>,
+    pub duration_ms: u64,
+    /// 後端未回報用量時為 None
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    /// 依模型價格與估計 token 數粗估的花費 (美元)；模型沒有價格資訊時為 None
+    pub estimated_cost_usd: Option<f64>,
+    pub tool_count: usize,
+    pub status: String,
+    pub error_class: Option<String>,
+}
+
+/// 將錯誤訊息歸類為粗略類別，方便離線統計
+pub fn classify_error(message: &str) -> &'static str {
+    let lower = message.to_lowercase();
+    if lower.contains("timeout") || lower.contains("timed out") {
+        "timeout"
+    } else if lower.contains("429") || lower.contains("rate limit") {
+        "rate_limit"
+    } else if lower.contains("401") || lower.contains("403") || lower.contains("unauthorized") {
+        "auth"
+    } else if lower.contains("connect") || lower.contains("broken pipe") {
+        "connection"
+    } else {
+        "backend"
+    }
+}
+
+/// 回合最終狀態對應的 (status, error_class)
+pub fn status_fields(status: &crate::ExecStatus) -> (&'static str, Option<&'static str>) {
+    match status {
+        crate::ExecStatus::Success => ("success", None),
+        crate::ExecStatus::TimedOut => ("timed_out", Some("timeout")),
+        crate::ExecStatus::Interrupted => ("interrupted", Some("shutdown")),
+        crate::ExecStatus::Error(message) => ("error", Some(classify_error(message))),
+        crate::ExecStatus::Running => ("running", None),
+    }
+}
+
+/// 留下 `cutoff` 之後的紀錄，回傳 (保留的內容, 移除的筆數)；時間無法解析的行一律保留
+fn retain_since(content: &str, cutoff: DateTime<Utc>) -> (String, usize) {
+    let mut kept = String::new();
+    let mut removed = 0;
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let ts = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|v| v["ts"].as_str().map(str::to_string))
+            .and_then(|ts| DateTime::parse_from_rfc3339(&ts).ok());
+        if ts.is_some_and(|ts| ts < cutoff) {
+            removed += 1;
+            continue;
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    (kept, removed)
+}
+
+/// NDJSON 分析檔輸出，超過大小上限時輪替為 turns.ndjson.1 ~ .N
+pub struct AnalyticsSink {
+    dir: PathBuf,
+    enabled: bool,
+    max_file_bytes: u64,
+    max_files: usize,
+    write_lock: Mutex<()>,
+}
+
+impl AnalyticsSink {
+    pub fn new(dir: PathBuf, config: &crate::config::AnalyticsConfig) -> Self {
+        Self {
+            dir,
+            enabled: config.enabled,
+            max_file_bytes: config.max_file_bytes,
+            max_files: config.max_files.max(1),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    fn current_path(&self) -> PathBuf {
+        self.dir.join(FILE_NAME)
+    }
+
+    fn rotated_path(dir: &Path, idx: usize) -> PathBuf {
+        dir.join(format!("{}.{}", FILE_NAME, idx))
+    }
+
+    async fn rotate_if_needed(&self) -> anyhow::Result<()> {
+        let current = self.current_path();
+        let size = match tokio::fs::metadata(&current).await {
+            Ok(m) => m.len(),
+            Err(_) => return Ok(()),
+        };
+        if size < self.max_file_bytes {
+            return Ok(());
+        }
+        let _ = tokio::fs::remove_file(Self::rotated_path(&self.dir, self.max_files)).await;
+        for idx in (1..self.max_files).rev() {
+            let from = Self::rotated_path(&self.dir, idx);
+            if from.exists() {
+                tokio::fs::rename(&from, Self::rotated_path(&self.dir, idx + 1)).await?;
+            }
+        }
+        tokio::fs::rename(&current, Self::rotated_path(&self.dir, 1)).await?;
+        Ok(())
+    }
+
+    async fn append(&self, record: &TurnRecord) -> anyhow::Result<()> {
+        let _guard = self.write_lock.lock().await;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        self.rotate_if_needed().await?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.current_path())
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// 移除 `cutoff` 之前的紀錄 (含輪替檔)，回傳移除的筆數；整個檔案都過期時直接刪除
+    pub async fn purge_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<usize> {
+        let _guard = self.write_lock.lock().await;
+        let paths = std::iter::once(self.current_path())
+            .chain((1..=self.max_files).map(|idx| Self::rotated_path(&self.dir, idx)));
+        let mut removed = 0;
+        for path in paths {
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            let (kept, count) = retain_since(&content, cutoff);
+            if count == 0 {
+                continue;
+            }
+            if kept.is_empty() {
+                tokio::fs::remove_file(&path).await?;
+            } else {
+                tokio::fs::write(&path, kept).await?;
+            }
+            removed += count;
+        }
+        Ok(removed)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 寫入失敗只記錄警告，不影響對話流程
+    pub async fn record(&self, record: TurnRecord) {
+        if !self.enabled {
+            return;
+        }
+        if let Err(e) = self.append(&record).await {
+            warn!("⚠️ Failed to write analytics record: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_error, status_fields, AnalyticsSink, TurnRecord, FILE_NAME};
+    use crate::config::AnalyticsConfig;
+    use chrono::{DateTime, Utc};
+    use tempfile::tempdir;
+
+    fn record(channel_id: u64) -> TurnRecord {
+        TurnRecord {
+            ts: "2026-01-01T00:00:00Z".to_string(),
+            channel_id,
+            backend: "kilo".to_string(),
+            model: Some("m".to_string()),
+            duration_ms: 1200,
+            input_tokens: None,
+            output_tokens: None,
+            estimated_cost_usd: None,
+            tool_count: 2,
+            status: "success".to_string(),
+            error_class: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(classify_error("Request timed out"), "timeout");
+        assert_eq!(classify_error("HTTP 429 Too Many Requests"), "rate_limit");
+        assert_eq!(classify_error("401 Unauthorized"), "auth");
+        assert_eq!(classify_error("error trying to connect"), "connection");
+        assert_eq!(classify_error("boom"), "backend");
+    }
+
+    #[test]
+    fn test_status_fields() {
+        assert_eq!(
+            status_fields(&crate::ExecStatus::Success),
+            ("success", None)
+        );
+        assert_eq!(
+            status_fields(&crate::ExecStatus::TimedOut),
+            ("timed_out", Some("timeout"))
+        );
+        assert_eq!(
+            status_fields(&crate::ExecStatus::Error("429".to_string())),
+            ("error", Some("rate_limit"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_appends_ndjson_and_rotates() {
+        let dir = tempdir().expect("tempdir");
+        let sink = AnalyticsSink::new(
+            dir.path().to_path_buf(),
+            &AnalyticsConfig {
+                enabled: true,
+                max_file_bytes: 200,
+                max_files: 2,
+            },
+        );
+        for i in 0..6 {
+            sink.record(record(i)).await;
+        }
+
+        let current = tokio::fs::read_to_string(dir.path().join(FILE_NAME))
+            .await
+            .expect("read current");
+        let last: serde_json::Value =
+            serde_json::from_str(current.lines().last().expect("line")).expect("json");
+        assert_eq!(last["channel_id"], 5);
+        assert_eq!(last["tool_count"], 2);
+        assert!(dir.path().join(format!("{}.1", FILE_NAME)).exists());
+        assert!(!dir.path().join(format!("{}.3", FILE_NAME)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_purge_before_drops_old_records() {
+        let dir = tempdir().expect("tempdir");
+        let sink = AnalyticsSink::new(
+            dir.path().to_path_buf(),
+            &AnalyticsConfig {
+                enabled: true,
+                ..AnalyticsConfig::default()
+            },
+        );
+        sink.record(record(1)).await;
+        sink.record(TurnRecord {
+            ts: "2026-03-01T00:00:00Z".to_string(),
+            ..record(2)
+        })
+        .await;
+        let cutoff: DateTime<Utc> = "2026-02-01T00:00:00Z".parse().expect("cutoff");
+
+        assert_eq!(sink.purge_before(cutoff).await.expect("purge"), 1);
+        let current = tokio::fs::read_to_string(dir.path().join(FILE_NAME))
+            .await
+            .expect("read current");
+        assert_eq!(current.lines().count(), 1);
+        assert!(current.contains("\"channel_id\":2"));
+
+        let later: DateTime<Utc> = "2026-04-01T00:00:00Z".parse().expect("cutoff");
+        assert_eq!(sink.purge_before(later).await.expect("purge"), 1);
+        assert!(!dir.path().join(FILE_NAME).exists());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_sink_writes_nothing() {
+        let dir = tempdir().expect("tempdir");
+        let sink = AnalyticsSink::new(dir.path().to_path_buf(), &AnalyticsConfig::default());
+        sink.record(record(1)).await;
+        assert!(!dir.path().join(FILE_NAME).exists());
+    }
+}
@@ -23,12 +23,15 @@ pub struct Registry {
     pub users: HashMap<String, AuthEntry>, // user_id -> entry
     #[serde(default)]
     pub channels: HashMap<String, AuthEntry>, // channel_id -> entry
+    /// 整個伺服器授權；頻道層級的授權優先
+    #[serde(default)]
+    pub guilds: HashMap<String, AuthEntry>, // guild_id -> entry
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PendingToken {
     pub token: String,
-    pub type_: String, // "user" or "channel"
+    pub type_: String, // "user", "channel" or "guild"
     pub id: String,
     pub expires_at: DateTime<Utc>,
 }
@@ -125,11 +128,19 @@ impl AuthManager {
         None
     }
 
+    /// 伺服器已授權時回傳其 mention_only 設定
+    pub fn get_guild_mention_only(&self, guild_id: &str) -> Option<bool> {
+        let content = fs::read_to_string(&self.auth_path).ok()?;
+        let reg = serde_json::from_str::<Registry>(&content).ok()?;
+        reg.guilds.get(guild_id).map(|entry| entry.mention_only)
+    }
+
     pub async fn is_authorized_with_thread(
         &self,
         ctx: &serenity::all::Context,
         user_id: &str,
         channel_id: serenity::model::id::ChannelId,
+        guild_id: Option<serenity::model::id::GuildId>,
     ) -> (bool, bool) {
         let id_str = channel_id.to_string();
         let (auth, mention) = self.is_authorized(user_id, &id_str);
@@ -141,11 +152,19 @@ impl AuthManager {
         if let Ok(channel) = channel_id.to_channel(&ctx.http).await {
             if let Some(guild_channel) = channel.guild() {
                 if let Some(parent_id) = guild_channel.parent_id {
-                    return self.is_authorized(user_id, &parent_id.to_string());
+                    let (auth, mention) = self.is_authorized(user_id, &parent_id.to_string());
+                    if auth {
+                        return (auth, mention);
+                    }
                 }
             }
         }
 
+        // 最後才看整個伺服器的授權
+        if let Some(mention) = guild_id.and_then(|g| self.get_guild_mention_only(&g.to_string())) {
+            return (true, mention);
+        }
+
         (false, false)
     }
 
@@ -204,7 +223,7 @@ impl AuthManager {
         self.with_lock(self.auth_path.clone(), Registry::default(), |reg| {
             let auth_entry = AuthEntry {
                 authorized_at: Utc::now(),
-                mention_only: entry.type_ == "channel", // Default true for channels
+                mention_only: entry.type_ != "user", // Default true for channels and guilds
             };
             match entry.type_.as_str() {
                 "user" => {
@@ -213,6 +232,9 @@ impl AuthManager {
                 "channel" => {
                     reg.channels.insert(entry.id.clone(), auth_entry);
                 }
+                "guild" => {
+                    reg.guilds.insert(entry.id.clone(), auth_entry);
+                }
                 _ => {}
             }
             Ok(())
@@ -221,6 +243,60 @@ impl AuthManager {
         Ok((entry.type_, entry.id))
     }
 
+    /// 頻道重建後將授權轉移到新 ID；舊頻道未授權時回傳 false
+    pub fn migrate_channel(&self, old_id: &str, new_id: &str) -> Result<bool> {
+        let mut moved = false;
+        self.with_lock(self.auth_path.clone(), Registry::default(), |reg| {
+            if let Some(entry) = reg.channels.remove(old_id) {
+                reg.channels.insert(new_id.to_string(), entry);
+                moved = true;
+            }
+            Ok(())
+        })?;
+        Ok(moved)
+    }
+
+    /// 讀取完整授權表；與 is_authorized 不同，檔案損毀時回傳錯誤而非視為空白
+    pub fn registry(&self) -> Result<Registry> {
+        match fs::read_to_string(&self.auth_path) {
+            Ok(content) if content.trim().is_empty() => Ok(Registry::default()),
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Registry::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 撤銷頻道授權；頻道原本未授權時回傳 false
+    pub fn remove_channel(&self, channel_id: &str) -> Result<bool> {
+        let mut removed = false;
+        self.with_lock(self.auth_path.clone(), Registry::default(), |reg| {
+            removed = reg.channels.remove(channel_id).is_some();
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
+    /// 撤銷伺服器授權；個別授權的頻道不受影響。伺服器原本未授權時回傳 false
+    pub fn remove_guild(&self, guild_id: &str) -> Result<bool> {
+        let mut removed = false;
+        self.with_lock(self.auth_path.clone(), Registry::default(), |reg| {
+            removed = reg.guilds.remove(guild_id).is_some();
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
+    pub fn set_guild_mention_only(&self, guild_id: &str, enable: bool) -> Result<()> {
+        self.with_lock(self.auth_path.clone(), Registry::default(), |reg| {
+            let Some(entry) = reg.guilds.get_mut(guild_id) else {
+                anyhow::bail!("Guild not authorized yet.");
+            };
+            entry.mention_only = enable;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
     // New method: Toggle mention_only
     pub fn set_mention_only(&self, channel_id: &str, enable: bool) -> Result<()> {
         self.with_lock(self.auth_path.clone(), Registry::default(), |reg| {
@@ -236,6 +312,47 @@ impl AuthManager {
     }
 }
 
+#[async_trait::async_trait]
+impl crate::services::AuthService for AuthManager {
+    async fn is_authorized_with_thread(
+        &self,
+        ctx: &serenity::all::Context,
+        user_id: &str,
+        channel_id: serenity::model::id::ChannelId,
+        guild_id: Option<serenity::model::id::GuildId>,
+    ) -> (bool, bool) {
+        AuthManager::is_authorized_with_thread(self, ctx, user_id, channel_id, guild_id).await
+    }
+
+    fn get_channel_mention_only(&self, channel_id: &str) -> Option<bool> {
+        AuthManager::get_channel_mention_only(self, channel_id)
+    }
+
+    fn get_guild_mention_only(&self, guild_id: &str) -> Option<bool> {
+        AuthManager::get_guild_mention_only(self, guild_id)
+    }
+
+    fn create_token(&self, type_: &str, id: &str) -> Result<String> {
+        AuthManager::create_token(self, type_, id)
+    }
+
+    fn migrate_channel(&self, old_id: &str, new_id: &str) -> Result<bool> {
+        AuthManager::migrate_channel(self, old_id, new_id)
+    }
+
+    fn remove_guild(&self, guild_id: &str) -> Result<bool> {
+        AuthManager::remove_guild(self, guild_id)
+    }
+
+    fn set_guild_mention_only(&self, guild_id: &str, enable: bool) -> Result<()> {
+        AuthManager::set_guild_mention_only(self, guild_id, enable)
+    }
+
+    fn set_mention_only(&self, channel_id: &str, enable: bool) -> Result<()> {
+        AuthManager::set_mention_only(self, channel_id, enable)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -290,4 +407,61 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_migrate_channel_moves_entry() -> anyhow::Result<()> {
+        let (_dir, manager) = create_test_manager()?;
+        let token = manager.create_token("channel", "old")?;
+        manager.redeem_token(&token)?;
+        manager.set_mention_only("old", false)?;
+
+        assert!(manager.migrate_channel("old", "new")?);
+        assert_eq!(manager.is_authorized("u", "old"), (false, false));
+        assert_eq!(manager.is_authorized("u", "new"), (true, false));
+        assert!(!manager.migrate_channel("missing", "new")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_guild_authorization_and_channel_precedence() -> anyhow::Result<()> {
+        let (_dir, manager) = create_test_manager()?;
+        assert_eq!(manager.get_guild_mention_only("g1"), None);
+
+        let token = manager.create_token("guild", "g1")?;
+        assert_eq!(manager.redeem_token(&token)?, ("guild".into(), "g1".into()));
+        assert_eq!(manager.get_guild_mention_only("g1"), Some(true));
+        manager.set_guild_mention_only("g1", false)?;
+        assert_eq!(manager.get_guild_mention_only("g1"), Some(false));
+        assert!(manager.set_guild_mention_only("g2", false).is_err());
+
+        // 伺服器授權不會寫入個別頻道，頻道自己的設定仍優先
+        let token = manager.create_token("channel", "chan_1")?;
+        manager.redeem_token(&token)?;
+        assert_eq!(manager.is_authorized("u", "chan_1"), (true, true));
+        assert!(manager.registry()?.channels.len() == 1);
+
+        assert!(manager.remove_guild("g1")?);
+        assert!(!manager.remove_guild("g1")?);
+        assert_eq!(manager.get_guild_mention_only("g1"), None);
+        assert_eq!(manager.is_authorized("u", "chan_1"), (true, true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_registry_and_remove_channel() -> anyhow::Result<()> {
+        let (dir, manager) = create_test_manager()?;
+        assert!(manager.registry()?.channels.is_empty());
+
+        let token = manager.create_token("channel", "chan_1")?;
+        manager.redeem_token(&token)?;
+        assert!(manager.registry()?.channels.contains_key("chan_1"));
+
+        assert!(manager.remove_channel("chan_1")?);
+        assert!(!manager.remove_channel("chan_1")?);
+        assert_eq!(manager.is_authorized("u", "chan_1"), (false, false));
+
+        std::fs::write(dir.path().join("auth.json"), "{ broken")?;
+        assert!(manager.registry().is_err());
+        Ok(())
+    }
 }
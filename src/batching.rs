@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+use crate::agent::UserInput;
+
+/// 粗估 token 數：ASCII 約 4 字元一個 token，其他字元 (如中文) 各算一個
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), c| {
+        if c.is_ascii() {
+            (a + 1, o)
+        } else {
+            (a, o + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// 合併多則訊息為單一輸入，附件依序保留，觸發者與來源訊息取最後一則
+pub fn merge_inputs(inputs: Vec<UserInput>) -> UserInput {
+    let mut merged = UserInput {
+        quick: !inputs.is_empty(),
+        ..UserInput::default()
+    };
+    let mut texts = Vec::new();
+    for input in inputs {
+        // 只有全部都是簡答時才維持簡答模式
+        merged.quick &= input.quick;
+        if !input.text.trim().is_empty() {
+            texts.push(input.text);
+        }
+        merged.files.extend(input.files);
+        merged.requester = input.requester.or(merged.requester);
+        merged.source_message = input.source_message.or(merged.source_message);
+        merged.priority |= input.priority;
+    }
+    merged.text = texts.join("\n\n");
+    merged
+}
+
+/// 依估計 token 上限將排隊訊息切成依序處理的批次；單則超過上限時獨立成一批。
+/// `max_tokens` 為 0 時全部合併為一批。
+pub fn plan_batches(inputs: Vec<UserInput>, max_tokens: usize) -> Vec<UserInput> {
+    let mut batches = Vec::new();
+    let mut current: Vec<UserInput> = Vec::new();
+    let mut current_tokens = 0;
+    for input in inputs {
+        let tokens = estimate_tokens(&input.text);
+        if max_tokens > 0 && !current.is_empty() && current_tokens + tokens > max_tokens {
+            batches.push(merge_inputs(std::mem::take(&mut current)));
+            current_tokens = 0;
+        }
+        current_tokens += tokens;
+        current.push(input);
+    }
+    if !current.is_empty() {
+        batches.push(merge_inputs(current));
+    }
+    batches
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    pub index: usize,
+    pub total: usize,
+}
+
+/// 頻道執行中時累積的訊息；派送時才規劃批次，批次全部處理完才納入新訊息
+#[derive(Debug, Default)]
+pub struct PendingQueue {
+    /// 優先使用者的訊息，各自獨立成一輪且排在所有批次之前
+    urgent: VecDeque<UserInput>,
+    /// 被優先訊息中斷的回合，於優先訊息處理完後接著重跑
+    preempted: VecDeque<UserInput>,
+    waiting: Vec<UserInput>,
+    planned: VecDeque<UserInput>,
+    total: usize,
+    dispatched: usize,
+    /// 已派送但尚未由新回合取用的進度
+    progress: Option<BatchProgress>,
+}
+
+impl PendingQueue {
+    pub fn push(&mut self, input: UserInput) {
+        self.waiting.push(input);
+    }
+
+    pub fn push_urgent(&mut self, input: UserInput) {
+        self.urgent.push_back(input);
+    }
+
+    pub fn push_preempted(&mut self, input: UserInput) {
+        self.preempted.push_back(input);
+    }
+
+    /// 自動復原時把剛失敗的批次放回最前面重試
+    pub fn retry(&mut self, input: UserInput) {
+        self.planned.push_front(input);
+        self.dispatched = self.dispatched.saturating_sub(1);
+    }
+
+    pub fn next_batch(&mut self, max_tokens: usize) -> Option<UserInput> {
+        // 插隊的訊息不屬於任何批次，不影響批次進度
+        if let Some(input) = self
+            .urgent
+            .pop_front()
+            .or_else(|| self.preempted.pop_front())
+        {
+            self.progress = None;
+            return Some(input);
+        }
+        if self.planned.is_empty() {
+            if self.waiting.is_empty() {
+                return None;
+            }
+            self.planned = plan_batches(std::mem::take(&mut self.waiting), max_tokens).into();
+            self.total = self.planned.len();
+            self.dispatched = 0;
+        }
+        let batch = self.planned.pop_front()?;
+        self.dispatched += 1;
+        self.progress = (self.total > 1).then_some(BatchProgress {
+            index: self.dispatched,
+            total: self.total,
+        });
+        Some(batch)
+    }
+
+    pub fn take_progress(&mut self) -> Option<BatchProgress> {
+        self.progress.take()
+    }
+
+    /// 尚未處理的訊息數 (不含已派送的回合)
+    pub fn len(&self) -> usize {
+        self.urgent.len() + self.preempted.len() + self.waiting.len() + self.planned.len()
+    }
+
+    /// 尚未處理的訊息，依派送順序排列
+    pub fn inputs(&self) -> Vec<UserInput> {
+        self.urgent
+            .iter()
+            .chain(&self.preempted)
+            .chain(&self.planned)
+            .chain(&self.waiting)
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urgent.is_empty()
+            && self.preempted.is_empty()
+            && self.waiting.is_empty()
+            && self.planned.is_empty()
+            && self.progress.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_tokens, merge_inputs, plan_batches, BatchProgress, PendingQueue};
+    use crate::agent::UserInput;
+
+    fn msg(text: &str, requester: u64) -> UserInput {
+        UserInput {
+            text: text.to_string(),
+            files: Vec::new(),
+            requester: Some(requester),
+            quick: false,
+            on_behalf_of: None,
+            disabled_tools: Vec::new(),
+            continues: None,
+            source_message: None,
+            priority: false,
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("你好"), 2);
+    }
+
+    #[test]
+    fn test_merge_inputs_joins_text_and_keeps_last_requester() {
+        let merged = merge_inputs(vec![msg("a", 1), msg(" ", 2), msg("b", 3)]);
+        assert_eq!(merged.text, "a\n\nb");
+        assert_eq!(merged.requester, Some(3));
+        assert!(!merged.quick);
+    }
+
+    #[test]
+    fn test_merge_inputs_keeps_quick_only_when_all_quick() {
+        let quick = |text: &str| UserInput {
+            quick: true,
+            ..msg(text, 1)
+        };
+        assert!(merge_inputs(vec![quick("a"), quick("b")]).quick);
+        assert!(!merge_inputs(vec![quick("a"), msg("b", 1)]).quick);
+    }
+
+    #[test]
+    fn test_plan_batches_splits_by_token_limit() {
+        let inputs = vec![
+            msg(&"a".repeat(40), 1),
+            msg(&"b".repeat(40), 1),
+            msg(&"c".repeat(40), 1),
+        ];
+        let batches = plan_batches(inputs.clone(), 20);
+        assert_eq!(batches.len(), 2);
+        assert!(batches[0].text.starts_with('a') && batches[0].text.contains('b'));
+        assert!(batches[1].text.starts_with('c'));
+
+        assert_eq!(plan_batches(inputs.clone(), 0).len(), 1);
+        // 單則超過上限仍獨立送出
+        assert_eq!(plan_batches(inputs, 5).len(), 3);
+    }
+
+    #[test]
+    fn test_pending_queue_reports_progress_and_retries() {
+        let mut queue = PendingQueue::default();
+        queue.push(msg(&"a".repeat(40), 1));
+        queue.push(msg(&"b".repeat(40), 1));
+
+        let first = queue.next_batch(10).expect("first");
+        assert!(first.text.starts_with('a'));
+        assert_eq!(
+            queue.take_progress(),
+            Some(BatchProgress { index: 1, total: 2 })
+        );
+
+        // 執行中新到的訊息要等目前批次處理完
+        queue.push(msg("late", 2));
+        queue.retry(first);
+        assert!(queue.next_batch(10).expect("retry").text.starts_with('a'));
+        assert!(queue.next_batch(10).expect("second").text.starts_with('b'));
+        assert_eq!(
+            queue.take_progress(),
+            Some(BatchProgress { index: 2, total: 2 })
+        );
+
+        assert_eq!(queue.next_batch(10).expect("late").text, "late");
+        assert_eq!(queue.take_progress(), None);
+        assert!(queue.is_empty());
+        assert!(queue.next_batch(10).is_none());
+    }
+
+    #[test]
+    fn test_pending_queue_urgent_inputs_jump_ahead_of_batches() {
+        let mut queue = PendingQueue::default();
+        queue.push(msg(&"a".repeat(40), 1));
+        queue.push(msg(&"b".repeat(40), 1));
+        assert!(queue.next_batch(10).expect("first").text.starts_with('a'));
+        assert!(queue.take_progress().is_some());
+
+        queue.push_preempted(msg("interrupted", 2));
+        queue.push_urgent(msg("admin 1", 9));
+        queue.push_urgent(msg("admin 2", 9));
+        assert_eq!(queue.next_batch(10).expect("urgent").text, "admin 1");
+        assert_eq!(queue.take_progress(), None);
+        assert_eq!(queue.next_batch(10).expect("urgent").text, "admin 2");
+        assert_eq!(queue.next_batch(10).expect("resumed").text, "interrupted");
+        // 插隊不打亂原本的批次進度
+        assert!(queue.next_batch(10).expect("second").text.starts_with('b'));
+        assert_eq!(
+            queue.take_progress(),
+            Some(BatchProgress { index: 2, total: 2 })
+        );
+        assert!(queue.is_empty());
+    }
+}
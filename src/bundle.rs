@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SCHEMA_FILE: &str = "SCHEMA.md";
+pub const BUNDLE_FORMAT: &str = "agent-discord-rs-bundle";
+/// 套件格式版本；套件內的路徑與目錄結構無關，只有套件本身的格式改變時才遞增
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// 套件內的一個區段：邏輯路徑 (`bundle`) 對應到目前目錄結構中的位置 (`layout`)
+///
+/// 以 `/` 結尾的是整個資料夾。之後目錄結構改變時只需調整這張表，
+/// 舊的套件即可匯入新的結構
+struct Section {
+    name: &'static str,
+    bundle: &'static str,
+    layout: &'static str,
+    description: &'static str,
+}
+
+const SECTIONS: &[Section] = &[
+    Section {
+        name: "config",
+        bundle: "config/config.toml",
+        layout: "config.toml",
+        description: "Bot configuration, including the Discord token",
+    },
+    Section {
+        name: "config",
+        bundle: "config/welcome.md",
+        layout: "welcome.md",
+        description: "Custom welcome message template",
+    },
+    Section {
+        name: "auth",
+        bundle: "auth/auth.json",
+        layout: "auth.json",
+        description: "Authorized users and channels",
+    },
+    Section {
+        name: "state",
+        bundle: "state/channel_config.json",
+        layout: "channel_config.json",
+        description: "Per-channel backend, model, session and feature settings",
+    },
+    Section {
+        name: "state",
+        bundle: "state/user_prefs.json",
+        layout: "user_prefs.json",
+        description: "Personal preferences set with /prefs",
+    },
+    Section {
+        name: "state",
+        bundle: "state/usage_caps.json",
+        layout: "usage_caps.json",
+        description: "Server usage caps and this month's usage",
+    },
+    Section {
+        name: "state",
+        bundle: "state/cron_jobs.json",
+        layout: "cron_jobs.json",
+        description: "Scheduled prompts",
+    },
+    Section {
+        name: "transcripts",
+        bundle: "transcripts/pi/",
+        layout: "sessions/pi/",
+        description: "pi conversation transcripts, one JSONL file per channel",
+    },
+    Section {
+        name: "sessions",
+        bundle: "sessions/",
+        layout: "sessions/",
+        description: "Session files of the other backends, one folder per backend",
+    },
+    Section {
+        name: "prompts",
+        bundle: "prompts/",
+        layout: "prompts/",
+        description: "System prompts",
+    },
+    Section {
+        name: "prompts",
+        bundle: "skills/",
+        layout: "skills/",
+        description: "Installed skills, one folder per skill",
+    },
+];
+
+/// 以最長的前綴比對區段，把一邊的相對路徑換成另一邊的路徑
+fn map_path(
+    path: &str,
+    from: impl Fn(&Section) -> &'static str,
+    to: impl Fn(&Section) -> &'static str,
+) -> Option<(&'static Section, String)> {
+    SECTIONS
+        .iter()
+        .filter_map(|section| {
+            let prefix = from(section);
+            let rest = if prefix.ends_with('/') {
+                path.strip_prefix(prefix).filter(|rest| !rest.is_empty())?
+            } else if path == prefix {
+                ""
+            } else {
+                return None;
+            };
+            Some((prefix.len(), section, format!("{}{}", to(section), rest)))
+        })
+        .max_by_key(|(len, _, _)| *len)
+        .map(|(_, section, mapped)| (section, mapped))
+}
+
+fn to_bundle_path(layout_path: &str) -> Option<(&'static Section, String)> {
+    map_path(layout_path, |s| s.layout, |s| s.bundle)
+}
+
+fn to_layout_path(bundle_path: &str) -> Option<(&'static Section, String)> {
+    map_path(bundle_path, |s| s.bundle, |s| s.layout)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BundleEntry {
+    pub path: String,
+    pub section: String,
+    pub bytes: u64,
+}
+
+/// 套件根目錄的 manifest.json
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BundleManifest {
+    pub format: String,
+    pub bundle_version: u32,
+    /// 匯出時的資料目錄結構版本 (`.version`)，僅供參考
+    pub layout_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub entries: Vec<BundleEntry>,
+}
+
+/// 套件內附的格式說明
+pub fn schema_doc() -> String {
+    let mut doc = format!(
+        "# {} v{}\n\n\
+         `{}` lists every file with its section and size. Paths are logical and do not \
+         depend on the data directory layout; `import-bundle` maps them to the layout of \
+         the running version. Folders end with `/`.\n\n\
+         | Section | Path | Contents |\n|---|---|---|\n",
+        BUNDLE_FORMAT, BUNDLE_VERSION, MANIFEST_FILE
+    );
+    for section in SECTIONS {
+        doc.push_str(&format!(
+            "| {} | `{}` | {} |\n",
+            section.name, section.bundle, section.description
+        ));
+    }
+    doc
+}
+
+/// 資料目錄中會匯出的檔案 (相對路徑，以 `/` 分隔，依路徑排序)
+fn layout_files(base_dir: &Path) -> Vec<String> {
+    fn walk(dir: &Path, rel: &str, out: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let path = format!("{}{}", rel, name);
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => walk(&entry.path(), &format!("{}/", path), out),
+                Ok(t) if t.is_file() => out.push(path),
+                _ => {}
+            }
+        }
+    }
+    let mut files = Vec::new();
+    for section in SECTIONS {
+        if section.layout.ends_with('/') {
+            walk(&base_dir.join(section.layout), section.layout, &mut files);
+        } else if base_dir.join(section.layout).is_file() {
+            files.push(section.layout.to_string());
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// 將資料目錄匯出成 zip 套件
+pub fn export(base_dir: &Path, output: &Path) -> anyhow::Result<BundleManifest> {
+    let version_file = base_dir.join(".version");
+    let layout_version = std::fs::read_to_string(version_file)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0);
+    let mut manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        bundle_version: BUNDLE_VERSION,
+        layout_version,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        entries: Vec::new(),
+    };
+
+    let file = std::fs::File::create(output)?;
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for layout_path in layout_files(base_dir) {
+        let Some((section, bundle_path)) = to_bundle_path(&layout_path) else {
+            continue;
+        };
+        let bytes = std::fs::read(base_dir.join(&layout_path))?;
+        writer.start_file(bundle_path.as_str(), options)?;
+        writer.write_all(&bytes)?;
+        manifest.entries.push(BundleEntry {
+            path: bundle_path,
+            section: section.name.to_string(),
+            bytes: bytes.len() as u64,
+        });
+    }
+    writer.start_file(SCHEMA_FILE, options)?;
+    writer.write_all(schema_doc().as_bytes())?;
+    writer.start_file(MANIFEST_FILE, options)?;
+    writer.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    writer.finish()?;
+    Ok(manifest)
+}
+
+fn read_manifest<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> anyhow::Result<BundleManifest> {
+    let mut content = String::new();
+    archive
+        .by_name(MANIFEST_FILE)
+        .map_err(|_| anyhow::anyhow!("{} not found in bundle", MANIFEST_FILE))?
+        .read_to_string(&mut content)?;
+    let manifest: BundleManifest = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("invalid {}: {}", MANIFEST_FILE, e))?;
+    if manifest.format != BUNDLE_FORMAT {
+        anyhow::bail!("not an {} file", BUNDLE_FORMAT);
+    }
+    if manifest.bundle_version > BUNDLE_VERSION {
+        anyhow::bail!(
+            "bundle version {} was created by a newer release (v{}); this release reads up to version {}",
+            manifest.bundle_version,
+            manifest.app_version,
+            BUNDLE_VERSION
+        );
+    }
+    Ok(manifest)
+}
+
+/// 將套件還原到資料目錄；已有檔案時需 `force` 才覆蓋，套件以外的檔案不受影響
+pub fn import(bundle: &Path, base_dir: &Path, force: bool) -> anyhow::Result<BundleManifest> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(bundle)?)?;
+    let manifest = read_manifest(&mut archive)?;
+
+    // 先確認所有路徑都安全且可對應，再開始寫入
+    let mut targets = Vec::new();
+    for entry in &manifest.entries {
+        let safe = Path::new(&entry.path)
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)));
+        let Some((_, layout_path)) = to_layout_path(&entry.path).filter(|_| safe) else {
+            anyhow::bail!("unexpected path in bundle: {}", entry.path);
+        };
+        targets.push((entry.path.as_str(), base_dir.join(layout_path)));
+    }
+    let existing: Vec<&PathBuf> = targets
+        .iter()
+        .map(|(_, target)| target)
+        .filter(|target| target.exists())
+        .collect();
+    if !force && !existing.is_empty() {
+        anyhow::bail!(
+            "{} file(s) already exist in {} (e.g. {}); re-run with --force to overwrite them",
+            existing.len(),
+            base_dir.display(),
+            existing[0].display()
+        );
+    }
+
+    for (bundle_path, target) in &targets {
+        let mut file = archive
+            .by_name(bundle_path)
+            .map_err(|_| anyhow::anyhow!("{} is listed but missing", bundle_path))?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = std::fs::File::create(target)?;
+        std::io::copy(&mut file, &mut writer)?;
+    }
+    // 套件已是目前的目錄結構，不需要再執行舊版遷移
+    std::fs::write(
+        base_dir.join(".version"),
+        crate::migrate::CURRENT_VERSION.to_string(),
+    )?;
+    Ok(manifest)
+}
+
+/// `export-bundle` 預設的輸出檔名
+pub fn default_output() -> PathBuf {
+    PathBuf::from(format!(
+        "agent-discord-bundle-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(base: &Path, rel: &str, content: &str) {
+        let path = base.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_paths_map_by_longest_section_prefix() {
+        assert_eq!(
+            to_bundle_path("sessions/pi/1.jsonl").map(|(s, p)| (s.name, p)),
+            Some(("transcripts", "transcripts/pi/1.jsonl".to_string()))
+        );
+        assert_eq!(
+            to_bundle_path("sessions/copilot/1.json").map(|(_, p)| p),
+            Some("sessions/copilot/1.json".to_string())
+        );
+        assert_eq!(
+            to_layout_path("config/config.toml").map(|(_, p)| p),
+            Some("config.toml".to_string())
+        );
+        assert!(to_layout_path("uploads/x.png").is_none());
+        assert!(to_layout_path("prompts/").is_none());
+    }
+
+    #[test]
+    fn test_export_then_import_roundtrip() -> anyhow::Result<()> {
+        let src = tempdir()?;
+        write(src.path(), "config.toml", "discord_token = \"t\"");
+        write(src.path(), "auth.json", "{}");
+        write(
+            src.path(),
+            "sessions/pi/42.jsonl",
+            "{\"type\":\"message\"}\n",
+        );
+        write(src.path(), "prompts/system.md", "be nice");
+        // 暫存的上傳檔不匯出
+        write(src.path(), "uploads/42/a.png", "png");
+        std::fs::write(src.path().join(".version"), "1")?;
+
+        let out = tempdir()?;
+        let bundle = out.path().join("bundle.zip");
+        let manifest = export(src.path(), &bundle)?;
+        assert_eq!(manifest.layout_version, 1);
+        let paths: Vec<&str> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "auth/auth.json",
+                "config/config.toml",
+                "prompts/system.md",
+                "transcripts/pi/42.jsonl"
+            ]
+        );
+
+        let dest = tempdir()?;
+        import(&bundle, dest.path(), false)?;
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("sessions/pi/42.jsonl"))?,
+            "{\"type\":\"message\"}\n"
+        );
+        assert!(!dest.path().join("uploads").exists());
+        assert_eq!(std::fs::read_to_string(dest.path().join(".version"))?, "1");
+
+        // 已有資料時需要 --force
+        assert!(import(&bundle, dest.path(), false).is_err());
+        write(dest.path(), "auth.json", "changed");
+        import(&bundle, dest.path(), true)?;
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("auth.json"))?,
+            "{}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_rejects_foreign_or_newer_bundles() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let bundle = dir.path().join("b.zip");
+        let build = |manifest: &BundleManifest| -> anyhow::Result<()> {
+            let mut writer = zip::ZipWriter::new(std::fs::File::create(&bundle)?);
+            writer.start_file(MANIFEST_FILE, zip::write::SimpleFileOptions::default())?;
+            writer.write_all(serde_json::to_string(manifest)?.as_bytes())?;
+            writer.finish()?;
+            Ok(())
+        };
+        let mut manifest = BundleManifest {
+            format: BUNDLE_FORMAT.to_string(),
+            bundle_version: BUNDLE_VERSION + 1,
+            layout_version: 1,
+            app_version: "9.9.9".to_string(),
+            created_at: String::new(),
+            entries: Vec::new(),
+        };
+        build(&manifest)?;
+        let dest = tempdir()?;
+        assert!(import(&bundle, dest.path(), false)
+            .unwrap_err()
+            .to_string()
+            .contains("newer release"));
+
+        manifest.bundle_version = BUNDLE_VERSION;
+        manifest.entries.push(BundleEntry {
+            path: "prompts/../../etc/passwd".to_string(),
+            section: "prompts".to_string(),
+            bytes: 0,
+        });
+        build(&manifest)?;
+        assert!(import(&bundle, dest.path(), false).is_err());
+        assert!(!dest.path().join(".version").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use rand::Rng;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::ChaosConfig;
+
+static INJECTOR: OnceLock<FailureInjector> = OnceLock::new();
+
+/// 依設定機率注入故障，用來驗證重試與復原流程
+pub struct FailureInjector {
+    config: ChaosConfig,
+}
+
+impl FailureInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        Self { config }
+    }
+
+    fn roll(&self, probability: f64) -> bool {
+        self.config.enabled
+            && probability > 0.0
+            && rand::rng().random_bool(probability.clamp(0.0, 1.0))
+    }
+
+    pub fn sse_delay(&self) -> Option<Duration> {
+        if self.config.sse_delay_max_ms == 0 || !self.roll(self.config.sse_delay_probability) {
+            return None;
+        }
+        Some(Duration::from_millis(
+            rand::rng().random_range(0..=self.config.sse_delay_max_ms),
+        ))
+    }
+
+    pub fn drop_event(&self) -> bool {
+        self.roll(self.config.drop_event_probability)
+    }
+
+    pub fn kill_backend(&self) -> bool {
+        self.roll(self.config.kill_backend_probability)
+    }
+
+    pub fn fail_http(&self) -> bool {
+        self.roll(self.config.http_500_probability)
+    }
+}
+
+/// 啟動時安裝一次；未安裝時所有注入點都不動作
+pub fn install(config: &ChaosConfig) {
+    if config.enabled {
+        warn!("🧪 Failure injection is ENABLED: {:?}", config);
+    }
+    let _ = INJECTOR.set(FailureInjector::new(config.clone()));
+}
+
+fn injector() -> Option<&'static FailureInjector> {
+    INJECTOR.get().filter(|i| i.config.enabled)
+}
+
+pub async fn delay_sse_event() {
+    if let Some(delay) = injector().and_then(|i| i.sse_delay()) {
+        warn!("🧪 Injected SSE delay: {}ms", delay.as_millis());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+pub fn should_drop_event() -> bool {
+    let drop = injector().is_some_and(|i| i.drop_event());
+    if drop {
+        warn!("🧪 Injected dropped agent event");
+    }
+    drop
+}
+
+pub fn should_kill_backend() -> bool {
+    let kill = injector().is_some_and(|i| i.kill_backend());
+    if kill {
+        warn!("🧪 Injected backend process kill");
+    }
+    kill
+}
+
+pub fn should_fail_http() -> bool {
+    let fail = injector().is_some_and(|i| i.fail_http());
+    if fail {
+        warn!("🧪 Injected HTTP 500 from backend");
+    }
+    fail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FailureInjector;
+    use crate::config::ChaosConfig;
+
+    fn always() -> ChaosConfig {
+        ChaosConfig {
+            enabled: true,
+            sse_delay_probability: 1.0,
+            sse_delay_max_ms: 50,
+            drop_event_probability: 1.0,
+            kill_backend_probability: 1.0,
+            http_500_probability: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_injector_fires_at_full_probability() {
+        let injector = FailureInjector::new(always());
+        assert!(injector.drop_event());
+        assert!(injector.kill_backend());
+        assert!(injector.fail_http());
+        let delay = injector.sse_delay().expect("delay");
+        assert!(delay.as_millis() <= 50);
+    }
+
+    #[test]
+    fn test_injector_inert_when_disabled_or_zero() {
+        let disabled = FailureInjector::new(ChaosConfig {
+            enabled: false,
+            ..always()
+        });
+        assert!(!disabled.drop_event());
+        assert!(disabled.sse_delay().is_none());
+
+        let zero = FailureInjector::new(ChaosConfig {
+            enabled: true,
+            ..ChaosConfig::default()
+        });
+        assert!(!zero.fail_http());
+        assert!(!zero.kill_backend());
+        assert!(zero.sse_delay().is_none());
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::CircuitBreakerConfig;
+
+/// 斷路器開啟時回傳的錯誤，呼叫端可 downcast 後顯示在地化訊息
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendUnavailable {
+    pub backend: String,
+    /// 下次背景探測的 Unix 時間 (秒)
+    pub retry_at: i64,
+}
+
+impl std::fmt::Display for BackendUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Backend {} unavailable, retrying at {}",
+            self.backend, self.retry_at
+        )
+    }
+}
+
+impl std::error::Error for BackendUnavailable {}
+
+#[derive(Default)]
+struct Circuit {
+    failures: u32,
+    /// 開啟中時為下次探測時間
+    open_until: Option<Instant>,
+    backoff: Duration,
+}
+
+/// 每個 (頻道, 後端) 連續失敗 `failure_threshold` 次後開啟，之後訊息直接回覆不可用，
+/// 由背景探測以指數退避 (上限 `max_backoff_secs`) 重試，成功才關閉
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    circuits: Mutex<HashMap<(u64, String), Circuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.failure_threshold > 0
+    }
+
+    fn base_backoff(&self) -> Duration {
+        Duration::from_secs(self.config.base_backoff_secs.max(1))
+    }
+
+    fn max_backoff(&self) -> Duration {
+        Duration::from_secs(self.config.max_backoff_secs).max(self.base_backoff())
+    }
+
+    /// 開啟中回傳下次探測時間；探測逾期未回報時以現在為準
+    pub async fn check(&self, channel_id: u64, backend: &str, now: Instant) -> Option<Instant> {
+        if !self.enabled() {
+            return None;
+        }
+        let circuits = self.circuits.lock().await;
+        let until = circuits
+            .get(&(channel_id, backend.to_string()))?
+            .open_until?;
+        Some(until.max(now))
+    }
+
+    pub async fn record_success(&self, channel_id: u64, backend: &str) {
+        self.circuits
+            .lock()
+            .await
+            .remove(&(channel_id, backend.to_string()));
+    }
+
+    /// 回傳 true 表示這次失敗使斷路器開啟，呼叫端應啟動背景探測
+    pub async fn record_failure(&self, channel_id: u64, backend: &str, now: Instant) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+        let base = self.base_backoff();
+        let mut circuits = self.circuits.lock().await;
+        let circuit = circuits
+            .entry((channel_id, backend.to_string()))
+            .or_default();
+        circuit.failures += 1;
+        if circuit.open_until.is_some() || circuit.failures < self.config.failure_threshold {
+            return false;
+        }
+        circuit.backoff = base;
+        circuit.open_until = Some(now + base);
+        true
+    }
+
+    /// 探測失敗：退避時間加倍 (有上限)，回傳下次探測時間
+    pub async fn probe_failed(&self, channel_id: u64, backend: &str, now: Instant) -> Instant {
+        let (base, max) = (self.base_backoff(), self.max_backoff());
+        let mut circuits = self.circuits.lock().await;
+        let circuit = circuits
+            .entry((channel_id, backend.to_string()))
+            .or_default();
+        circuit.backoff = if circuit.backoff.is_zero() {
+            base
+        } else {
+            (circuit.backoff * 2).min(max)
+        };
+        let next = now + circuit.backoff;
+        circuit.open_until = Some(next);
+        next
+    }
+
+    pub async fn next_probe(&self, channel_id: u64, backend: &str) -> Option<Instant> {
+        let circuits = self.circuits.lock().await;
+        circuits.get(&(channel_id, backend.to_string()))?.open_until
+    }
+}
+
+/// 將 Instant 換算為 Unix 時間，供 Discord 時間戳顯示
+pub fn unix_time(at: Instant, now: Instant) -> i64 {
+    chrono::Utc::now().timestamp() + at.saturating_duration_since(now).as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_breaker(failure_threshold: u32) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold,
+            base_backoff_secs: 10,
+            max_backoff_secs: 30,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_after_consecutive_failures() {
+        let now = Instant::now();
+        let breaker = new_breaker(3);
+
+        assert!(!breaker.record_failure(1, "kilo", now).await);
+        assert!(!breaker.record_failure(1, "kilo", now).await);
+        breaker.record_success(1, "kilo").await;
+        assert!(!breaker.record_failure(1, "kilo", now).await);
+        assert!(!breaker.record_failure(1, "kilo", now).await);
+        assert_eq!(breaker.check(1, "kilo", now).await, None);
+
+        assert!(breaker.record_failure(1, "kilo", now).await);
+        // 已開啟時不重複觸發探測
+        assert!(!breaker.record_failure(1, "kilo", now).await);
+        assert_eq!(
+            breaker.check(1, "kilo", now).await,
+            Some(now + Duration::from_secs(10))
+        );
+        // 其他頻道與後端不受影響
+        assert_eq!(breaker.check(2, "kilo", now).await, None);
+        assert_eq!(breaker.check(1, "pi", now).await, None);
+
+        breaker.record_success(1, "kilo").await;
+        assert_eq!(breaker.check(1, "kilo", now).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_probe_backoff_doubles_up_to_max() {
+        let now = Instant::now();
+        let breaker = new_breaker(1);
+        assert!(breaker.record_failure(1, "opencode", now).await);
+
+        let secs = |at: Instant| at.duration_since(now).as_secs();
+        assert_eq!(secs(breaker.probe_failed(1, "opencode", now).await), 20);
+        assert_eq!(secs(breaker.probe_failed(1, "opencode", now).await), 30);
+        assert_eq!(secs(breaker.probe_failed(1, "opencode", now).await), 30);
+        // 探測逾期時顯示現在
+        let later = now + Duration::from_secs(60);
+        assert_eq!(breaker.check(1, "opencode", later).await, Some(later));
+    }
+
+    #[tokio::test]
+    async fn test_breaker_disabled_with_zero_threshold() {
+        let breaker = new_breaker(0);
+        let now = Instant::now();
+        for _ in 0..5 {
+            assert!(!breaker.record_failure(1, "kilo", now).await);
+        }
+        assert_eq!(breaker.check(1, "kilo", now).await, None);
+    }
+}
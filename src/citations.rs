@@ -0,0 +1,226 @@
+use crate::agent::Citation;
+
+/// Discord Embed 內文的字數上限
+pub const EMBED_DESCRIPTION_MAX: usize = 4096;
+// 來源清單最多列出的項目數與標題長度
+const MAX_SOURCES: usize = 10;
+const MAX_TITLE_CHARS: usize = 80;
+
+/// 回答內的裸網址：(位元組起點, 網址)；程式碼區塊、既有的 Markdown 連結與 `<網址>` 不算
+fn bare_urls(text: &str) -> Vec<(usize, &str)> {
+    let mut found = Vec::new();
+    let mut in_code = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let mut in_inline_code = false;
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            if rest.starts_with('`') {
+                in_inline_code = !in_inline_code;
+                i += 1;
+                continue;
+            }
+            if in_inline_code || !(rest.starts_with("https://") || rest.starts_with("http://")) {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+                continue;
+            }
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+                .unwrap_or(rest.len());
+            let mut url = &rest[..end];
+            // 句尾標點與未配對的右括號不屬於網址
+            loop {
+                let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '*', '_']);
+                let trimmed = if trimmed.ends_with(')')
+                    && trimmed.matches(')').count() > trimmed.matches('(').count()
+                {
+                    &trimmed[..trimmed.len() - 1]
+                } else {
+                    trimmed
+                };
+                if trimmed.len() == url.len() {
+                    break;
+                }
+                url = trimmed;
+            }
+            let before = &line[..i];
+            let linked = before.ends_with("](") || before.ends_with('<');
+            if !linked && url.len() > "https://".len() {
+                found.push((line_start + i, url));
+            }
+            i += url.len().max(1);
+        }
+    }
+    found
+}
+
+fn same_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+/// 連結目標中的空白與括號會打斷 Markdown 連結
+fn link_target(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// 清單中的連結文字：標題去掉會破壞連結的符號，沒有標題時用網域名稱
+fn link_label(citation: &Citation) -> String {
+    let title = citation
+        .title
+        .as_deref()
+        .map(|t| {
+            t.chars()
+                .filter(|c| !matches!(c, '[' | ']') && !c.is_control())
+                .collect::<String>()
+        })
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty());
+    let label = title.unwrap_or_else(|| {
+        let host = citation
+            .url
+            .split("://")
+            .nth(1)
+            .unwrap_or(&citation.url)
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or_default();
+        host.trim_start_matches("www.").to_string()
+    });
+    let mut short: String = label.chars().take(MAX_TITLE_CHARS).collect();
+    if short.len() < label.len() {
+        short.push('…');
+    }
+    short
+}
+
+/// 依在回答中出現的順序編號；未出現在正文的引用排在最後
+fn numbered_sources(answer: &str, citations: &[Citation]) -> Vec<Citation> {
+    let mut sources: Vec<Citation> = Vec::new();
+    let mut add = |citation: Citation| {
+        if !sources.iter().any(|s| same_url(&s.url, &citation.url)) {
+            sources.push(citation);
+        }
+    };
+    for (_, url) in bare_urls(answer) {
+        let cited = citations.iter().find(|c| same_url(&c.url, url));
+        add(cited.cloned().unwrap_or_else(|| Citation {
+            url: url.to_string(),
+            title: None,
+        }));
+    }
+    for citation in citations {
+        add(citation.clone());
+    }
+    sources
+}
+
+fn render(answer: &str, sources: &[Citation], header: &str) -> String {
+    let mut out = String::with_capacity(answer.len());
+    let mut last = 0;
+    for (start, url) in bare_urls(answer) {
+        let Some(n) = sources.iter().position(|s| same_url(&s.url, url)) else {
+            continue;
+        };
+        out.push_str(&answer[last..start]);
+        out.push_str(&format!("[\\[{}\\]]({})", n + 1, link_target(url)));
+        last = start + url.len();
+    }
+    out.push_str(&answer[last..]);
+    let list = sources
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{}. [{}]({})", i + 1, link_label(s), link_target(&s.url)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\n**{}**\n{}", out.trim_end(), header, list)
+}
+
+/// 最終回答：正文的裸網址改成編號連結，結尾附上以遮罩連結列出的來源清單。
+/// 超過 `max_chars` 時從清單尾端捨去來源 (對應的網址維持原樣)，一個都放不下就不變。
+pub fn with_sources(
+    answer: &str,
+    citations: &[Citation],
+    header: &str,
+    max_chars: usize,
+) -> String {
+    if citations.is_empty() {
+        return answer.to_string();
+    }
+    let mut sources = numbered_sources(answer, citations);
+    sources.truncate(MAX_SOURCES);
+    while !sources.is_empty() {
+        let rendered = render(answer, &sources, header);
+        if rendered.chars().count() <= max_chars {
+            return rendered;
+        }
+        sources.pop();
+    }
+    answer.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cite(url: &str, title: Option<&str>) -> Citation {
+        Citation {
+            url: url.to_string(),
+            title: title.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_bare_urls_become_numbered_masked_links() {
+        let answer = "Rust 1.80 shipped (https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html). \
+                      See also [the docs](https://doc.rust-lang.org/) and https://example.com/a_(b).\n\
+                      ```\ncurl https://example.com/skip\n```";
+        let citations = vec![
+            cite(
+                "https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html",
+                Some("Announcing [Rust] 1.80.0"),
+            ),
+            cite("https://doc.rust-lang.org/", None),
+        ];
+        let out = with_sources(answer, &citations, "Sources", EMBED_DESCRIPTION_MAX);
+        assert!(out.starts_with(
+            "Rust 1.80 shipped ([\\[1\\]](https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html))."
+        ));
+        assert!(out.contains("[the docs](https://doc.rust-lang.org/)"));
+        assert!(out.contains("and [\\[2\\]](https://example.com/a_%28b%29)."));
+        assert!(out.contains("curl https://example.com/skip"));
+        assert!(out.ends_with(
+            "**Sources**\n\
+             1. [Announcing Rust 1.80.0](https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html)\n\
+             2. [example.com](https://example.com/a_%28b%29)\n\
+             3. [doc.rust-lang.org](https://doc.rust-lang.org/)"
+        ));
+    }
+
+    #[test]
+    fn test_sources_are_dropped_to_fit_and_skipped_without_citations() {
+        let answer = "See https://a.example/x and https://b.example/y";
+        assert_eq!(with_sources(answer, &[], "Sources", 4096), answer);
+
+        let citations = vec![cite("https://b.example/y", Some("B"))];
+        let full = with_sources(answer, &citations, "Sources", 4096);
+        assert!(full.contains("[\\[2\\]](https://b.example/y)"));
+
+        let limit = full.chars().count() - 1;
+        let trimmed = with_sources(answer, &citations, "Sources", limit);
+        assert!(trimmed.starts_with("See [\\[1\\]](https://a.example/x) and https://b.example/y"));
+        assert!(trimmed.chars().count() <= limit);
+        assert_eq!(with_sources(answer, &citations, "Sources", 10), answer);
+    }
+}
@@ -1,6 +1,6 @@
 use super::SlashCommand;
 use async_trait::async_trait;
-use serenity::all::{CommandInteraction, Context, EditInteractionResponse};
+use serenity::all::{CommandInteraction, Context, EditInteractionResponse, EditMessage};
 
 pub struct AbortCommand;
 
@@ -22,35 +22,14 @@ impl SlashCommand for AbortCommand {
     ) -> anyhow::Result<()> {
         command.defer_ephemeral(&ctx.http).await?;
 
-        let active = {
-            let mut active = state.active_renders.lock().await;
-            active.remove(&command.channel_id.get())
-        };
-        if let Some((_msg_id, handles)) = active {
-            for handle in handles {
-                handle.abort();
-            }
-            // Do not delete the message: the user may want to keep the partial
-            // output as a record.  The render task stopped updating it; whatever
-            // content was last rendered stays visible in Discord.
+        let aborted = crate::turn_controls::abort_turn(state, command.channel_id).await?;
+        // render 已停止，拿掉訊息上的「中止」按鈕 (狀態反應模式下是使用者的訊息，編輯失敗可忽略)
+        if let Some(msg_id) = aborted {
+            let _ = command
+                .channel_id
+                .edit_message(&ctx.http, msg_id, EditMessage::new().components(vec![]))
+                .await;
         }
-        {
-            let mut pending = state.pending_inputs.lock().await;
-            pending.remove(&command.channel_id.get());
-        }
-
-        let channel_id_str = command.channel_id.to_string();
-        let channel_config = crate::commands::agent::ChannelConfig::load()
-            .await
-            .unwrap_or_default();
-        let agent_type = channel_config.get_agent_type(&channel_id_str);
-
-        let (agent, _) = state
-            .session_manager
-            .get_or_create_session(command.channel_id.get(), agent_type, &state.backend_manager)
-            .await?;
-
-        agent.abort().await?;
 
         let i18n = state.i18n.read().await;
         let msg = i18n.get("abort_success");
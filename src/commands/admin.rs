@@ -0,0 +1,123 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommandOption,
+};
+use tracing::info;
+
+use crate::i18n::I18n;
+use crate::retention::{self, Category};
+
+pub struct AdminCommand;
+
+fn string_option<'a>(opts: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    opts.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
+
+#[async_trait]
+impl SlashCommand for AdminCommand {
+    fn name(&self) -> &'static str {
+        "admin"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_admin_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        let mut category = CreateCommandOption::new(
+            CommandOptionType::String,
+            "category",
+            i18n.get("cmd_admin_purge_opt_category"),
+        )
+        .required(true);
+        for c in Category::ALL {
+            category = category.add_string_choice(c.name(), c.name());
+        }
+        vec![CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "purge",
+            i18n.get("cmd_admin_purge_desc"),
+        )
+        .add_sub_option(category)
+        .add_sub_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "before",
+            i18n.get("cmd_admin_purge_opt_before"),
+        ))]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let i18n = state.channel_i18n(command.channel_id.get()).await;
+        // 清除的是整個 bot 的資料，不屬於任何單一伺服器，只接受擁有者私訊操作
+        if command.guild_id.is_some() {
+            let msg = i18n.read().await.get("admin_dm_only");
+            return reply(ctx, command, msg).await;
+        }
+
+        let opts = match command.data.options.first() {
+            Some(sub) if sub.name == "purge" => match &sub.value {
+                CommandDataOptionValue::SubCommand(opts) => opts.as_slice(),
+                _ => &[],
+            },
+            _ => &[],
+        };
+        let Some(category) = string_option(opts, "category").and_then(Category::parse) else {
+            return Ok(());
+        };
+        let cutoff = match string_option(opts, "before") {
+            Some(date) => match retention::parse_before(date) {
+                Some(cutoff) => cutoff,
+                None => {
+                    let msg = i18n
+                        .read()
+                        .await
+                        .get_args("admin_purge_bad_date", &[date.to_string()]);
+                    return reply(ctx, command, msg).await;
+                }
+            },
+            None => match retention::retention_cutoff(
+                &state.config.retention,
+                category,
+                chrono::Utc::now(),
+            ) {
+                Some(cutoff) => cutoff,
+                None => {
+                    let msg = i18n
+                        .read()
+                        .await
+                        .get_args("admin_purge_no_retention", &[category.name().to_string()]);
+                    return reply(ctx, command, msg).await;
+                }
+            },
+        };
+
+        let removed = retention::purge(state, category, cutoff).await?;
+        info!(
+            "🧹 {} purged {} {} item(s) before {}",
+            command.user.name,
+            removed,
+            category.name(),
+            cutoff.to_rfc3339()
+        );
+        let msg = i18n.read().await.get_args(
+            "admin_purge_done",
+            &[
+                removed.to_string(),
+                category.name().to_string(),
+                cutoff.format("%Y-%m-%d %H:%M UTC").to_string(),
+            ],
+        );
+        reply(ctx, command, msg).await
+    }
+}
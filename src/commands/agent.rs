@@ -11,13 +11,6 @@ use crate::agent::AgentType;
 
 pub struct AgentCommand;
 
-fn is_binary_not_found(error_text: &str) -> bool {
-    let lower = error_text.to_lowercase();
-    lower.contains("no such file or directory")
-        || lower.contains("not found")
-        || lower.contains("enoent")
-}
-
 pub fn build_backend_error_message(
     i18n: &crate::i18n::I18n,
     agent_type: AgentType,
@@ -25,26 +18,22 @@ pub fn build_backend_error_message(
     port: u16,
 ) -> String {
     let backend = agent_type.to_string();
+    if !crate::agent::is_backend_enabled(&agent_type) {
+        return i18n.get_args("backend_disabled", &[backend]);
+    }
+    // 已知錯誤直接給出處理步驟，取代原始錯誤訊息
+    if let Some(explained) = crate::error_catalog::explain(i18n, error_text, &agent_type, port) {
+        return format!(
+            "{}\n\n{}",
+            i18n.get_args("backend_start_failed_known", &[backend]),
+            explained
+        );
+    }
     let base = i18n.get_args(
         "backend_start_failed",
         &[backend.clone(), error_text.to_string()],
     );
 
-    if is_binary_not_found(error_text) {
-        let install_cmd = match agent_type {
-            AgentType::Pi => "npm i -g @mariozechner/pi-coding-agent",
-            AgentType::Opencode => "npm install -g @opencode-ai/cli",
-            AgentType::Kilo => "npm i -g @kilocode/cli",
-            AgentType::Copilot => "npm i -g @github/copilot",
-        };
-        return format!(
-            "{}\n\n{}:\n```bash\n{}\n```",
-            base,
-            i18n.get("backend_install_hint"),
-            install_cmd
-        );
-    }
-
     match agent_type {
         AgentType::Opencode => format!(
             "{}\n\n{}:\n```bash\nopencode serve --port {}\n```",
@@ -76,7 +65,10 @@ pub fn build_backend_error_message(
                 auth_hint
             )
         }
+        AgentType::Claude => format!("{}\n\n{}", base, i18n.get("claude_runtime_hint")),
         AgentType::Pi => format!("{}\n\n{}", base, i18n.get("pi_runtime_hint")),
+        AgentType::Acp => format!("{}\n\n{}", base, i18n.get("acp_runtime_hint")),
+        AgentType::OpenAi => format!("{}\n\n{}", base, i18n.get("openai_runtime_hint")),
     }
 }
 
@@ -84,9 +76,19 @@ pub fn build_backend_error_message(
 pub struct ChannelConfig {
     #[serde(default)]
     pub channels: HashMap<String, ChannelEntry>,
+    /// 伺服器層級的預設值；頻道第一次使用時套用，之後以頻道自己的設定為準
+    #[serde(default)]
+    pub guilds: HashMap<String, GuildEntry>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GuildEntry {
+    /// 伺服器內新頻道的預設後端，None 表示沿用全域預設
+    #[serde(default)]
+    pub agent_type: Option<AgentType>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
 pub struct ChannelEntry {
     #[serde(default)]
     pub agent_type: AgentType,
@@ -97,9 +99,71 @@ pub struct ChannelEntry {
     // 通用 Session ID，不再區分 kilo 或 opencode
     #[serde(default, alias = "kilo_session_id")]
     pub session_id: Option<String>,
+    /// kilo/opencode session 所在的後端實例編號；後續請求固定送往此實例
+    #[serde(default)]
+    pub backend_instance: Option<usize>,
     pub model_provider: Option<String>,
     pub model_id: Option<String>,
     pub assistant_name: Option<String>,
+    // 單輪最長執行秒數，None 表示沿用全域設定，0 表示不限制
+    #[serde(default)]
+    pub max_turn_secs: Option<u64>,
+    /// 最終回應另外轉送到 webhook 或封存頻道
+    #[serde(default)]
+    pub mirror: crate::mirror::MirrorConfig,
+    /// 私人 session：只有擁有者與名單內的使用者可以送出提示
+    #[serde(default)]
+    pub private: Option<crate::commands::session::PrivateSession>,
+    /// 安靜時段內完成的排程回合延後到時段結束才送出
+    #[serde(default)]
+    pub quiet_hours: Option<crate::quiet::QuietHours>,
+    /// 保存完整工具輸出的回合數，None 表示沿用全域設定，0 表示不保存
+    #[serde(default)]
+    pub tool_output_retention: Option<usize>,
+    /// 相似問題近期回答過時直接提供快取答案 (需設定 [faq_cache])
+    #[serde(default)]
+    pub faq_cache: bool,
+    /// 偵測每則提示的語言，要求模型以相同語言回覆
+    #[serde(default)]
+    pub auto_reply_language: bool,
+    /// 代理回覆中允許實際通知的使用者與身分組，其餘提及一律不通知
+    #[serde(default)]
+    pub mentions: crate::mentions::MentionPolicy,
+    /// 無障礙模式：此頻道的回應一律以不含裝飾表情符號的純文字訊息呈現
+    #[serde(default)]
+    pub accessible: bool,
+    /// 狀態反應模式：以提示上的 ⏳/✅/❌ 反應標示進度，只送出最終回答
+    #[serde(default)]
+    pub status_reactions: bool,
+    /// 頻道的工具政策：列出的工具在此頻道停用
+    #[serde(default)]
+    pub disabled_tools: Vec<String>,
+    /// 代理的工作目錄 (專案目錄)，None 表示沿用 bot 程序的目錄
+    #[serde(default)]
+    pub workdir: Option<String>,
+    /// /thinking 最後設定的推理等級，建立 session 時重新套用
+    #[serde(default)]
+    pub thinking_level: Option<String>,
+    /// 以前綴開頭的訊息 (如 `!model`) 視為文字指令，供無法使用斜線指令的客戶端
+    #[serde(default)]
+    pub text_commands: bool,
+}
+
+impl ChannelEntry {
+    pub fn new(agent_type: AgentType) -> Self {
+        Self {
+            agent_type,
+            authorized_at: chrono::Utc::now().to_rfc3339(),
+            mention_only: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// channel_config.json 的寫入鎖；所有寫入都在鎖內完成
+fn write_lock() -> &'static tokio::sync::Mutex<()> {
+    static LOCK: std::sync::OnceLock<tokio::sync::Mutex<()>> = std::sync::OnceLock::new();
+    LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
 }
 
 impl ChannelConfig {
@@ -113,33 +177,135 @@ impl ChannelConfig {
         Ok(config)
     }
 
+    /// 整份覆寫設定檔；只供離線工具 (fsck) 使用，執行中的 bot 一律透過 `update`
     pub async fn save(&self) -> anyhow::Result<()> {
+        let _guard = write_lock().lock().await;
+        self.write().await
+    }
+
+    /// 先寫入暫存檔再改名，寫到一半中斷時不會留下損毀的設定檔
+    async fn write(&self) -> anyhow::Result<()> {
         let path = super::super::migrate::get_channel_config_path();
         let content = serde_json::to_string_pretty(self)?;
-        tokio::fs::write(&path, content).await?;
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, content).await?;
+        tokio::fs::rename(&tmp, &path).await?;
         Ok(())
     }
 
+    /// 在單一寫入鎖內讀取、修改並寫回設定，並行的更新不會互相覆蓋；
+    /// 內容沒有變更時不寫檔
+    pub async fn update<T>(f: impl FnOnce(&mut Self) -> T) -> anyhow::Result<T> {
+        let _guard = write_lock().lock().await;
+        let mut config = Self::load().await?;
+        let before = serde_json::to_value(&config)?;
+        let out = f(&mut config);
+        if serde_json::to_value(&config)? != before {
+            config.write().await?;
+        }
+        Ok(out)
+    }
+
     pub fn get_agent_type(&self, channel_id: &str) -> AgentType {
         self.channels
             .get(channel_id)
             .map(|e| e.agent_type.clone())
-            .unwrap_or_default()
+            .unwrap_or_else(crate::agent::default_backend)
+    }
+
+    pub fn private_session(
+        &self,
+        channel_id: &str,
+    ) -> Option<&crate::commands::session::PrivateSession> {
+        self.channels.get(channel_id)?.private.as_ref()
+    }
+
+    /// 將舊頻道的設定 (含 session ID) 搬到新頻道，覆蓋新頻道既有設定
+    pub fn migrate_channel(&mut self, old_id: &str, new_id: &str) -> bool {
+        match self.channels.remove(old_id) {
+            Some(entry) => {
+                self.channels.insert(new_id.to_string(), entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 頻道尚無設定時依伺服器預設建立；有建立時回傳 true，呼叫端需寫回
+    pub fn apply_guild_defaults(&mut self, channel_id: &str, guild_id: &str) -> bool {
+        if self.channels.contains_key(channel_id) {
+            return false;
+        }
+        let Some(agent_type) = self.guilds.get(guild_id).and_then(|g| g.agent_type.clone()) else {
+            return false;
+        };
+        self.channels
+            .insert(channel_id.to_string(), ChannelEntry::new(agent_type));
+        true
+    }
+
+    /// 頻道尚無設定，且有父頻道設定或伺服器預設可套用
+    fn needs_defaults(&self, channel_id: &str, guild_id: &str, parent_id: Option<&str>) -> bool {
+        !self.channels.contains_key(channel_id)
+            && (parent_id.is_some_and(|p| self.channels.contains_key(p))
+                || self
+                    .guilds
+                    .get(guild_id)
+                    .is_some_and(|g| g.agent_type.is_some()))
+    }
+
+    /// 套用討論串繼承與伺服器預設後的設定；每則訊息都會呼叫，因此先唯讀載入，
+    /// 只在確實有設定要補上時才取寫入鎖
+    pub async fn load_resolved(
+        channel_id: &str,
+        guild_id: &str,
+        parent_id: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let config = Self::load().await?;
+        if !config.needs_defaults(channel_id, guild_id, parent_id) {
+            return Ok(config);
+        }
+        Self::update(|cfg| {
+            if let Some(parent_id) = parent_id {
+                cfg.inherit_parent(channel_id, parent_id);
+            }
+            cfg.apply_guild_defaults(channel_id, guild_id);
+            cfg.clone()
+        })
+        .await
+    }
+
+    /// 新的討論串沿用父頻道的設定，但不共用 session；討論串已有設定或父頻道沒有設定時不動
+    pub fn inherit_parent(&mut self, thread_id: &str, parent_id: &str) -> bool {
+        if self.channels.contains_key(thread_id) {
+            return false;
+        }
+        let Some(parent) = self.channels.get(parent_id) else {
+            return false;
+        };
+        let entry = ChannelEntry {
+            session_id: None,
+            backend_instance: None,
+            authorized_at: chrono::Utc::now().to_rfc3339(),
+            ..parent.clone()
+        };
+        self.channels.insert(thread_id.to_string(), entry);
+        true
+    }
+
+    /// 頻道的設定；尚無設定時以目前的後端建立
+    pub fn entry_mut(&mut self, channel_id: &str) -> &mut ChannelEntry {
+        let agent_type = self.get_agent_type(channel_id);
+        self.channels
+            .entry(channel_id.to_string())
+            .or_insert_with(|| ChannelEntry::new(agent_type))
     }
 
     pub fn set_agent_type(&mut self, channel_id: &str, agent_type: AgentType) {
         let entry = self
             .channels
             .entry(channel_id.to_string())
-            .or_insert_with(|| ChannelEntry {
-                agent_type: agent_type.clone(),
-                authorized_at: chrono::Utc::now().to_rfc3339(),
-                mention_only: true,
-                session_id: None,
-                model_provider: None,
-                model_id: None,
-                assistant_name: None,
-            });
+            .or_insert_with(|| ChannelEntry::new(agent_type.clone()));
         entry.agent_type = agent_type;
     }
 }
@@ -155,16 +321,17 @@ impl SlashCommand for AgentCommand {
     }
 
     fn options(&self, i18n: &crate::i18n::I18n) -> Vec<CreateCommandOption> {
-        vec![CreateCommandOption::new(
+        let option = CreateCommandOption::new(
             CommandOptionType::String,
             "backend",
             i18n.get("cmd_agent_opt_backend"),
         )
-        .required(true)
-        .add_string_choice(i18n.get("agent_choice_kilo"), "kilo")
-        .add_string_choice(i18n.get("agent_choice_copilot"), "copilot")
-        .add_string_choice(i18n.get("agent_choice_pi"), "pi")
-        .add_string_choice(i18n.get("agent_choice_opencode"), "opencode")]
+        .required(true);
+        vec![crate::agent::enabled_backends()
+            .into_iter()
+            .fold(option, |opt, t| {
+                opt.add_string_choice(i18n.get(t.choice_key()), t.to_string())
+            })]
     }
 
     async fn execute(
@@ -187,6 +354,19 @@ impl SlashCommand for AgentCommand {
         let new_agent_type: AgentType = new_agent_type_str.parse()?;
         let channel_id = command.channel_id.to_string();
 
+        // 舊版指令選單可能仍列出已停用的後端
+        if !crate::agent::is_backend_enabled(&new_agent_type) {
+            let msg = state
+                .i18n
+                .read()
+                .await
+                .get_args("backend_disabled", &[new_agent_type.to_string()]);
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        }
+
         // 檢查當前 agent 類型
         let config = ChannelConfig::load().await?;
         let current_agent = config.get_agent_type(&channel_id);
@@ -251,22 +431,20 @@ pub async fn handle_button(
         let channel_id = interaction.channel_id.to_string();
         let channel_id_u64 = interaction.channel_id.get();
 
-        // 先更新配置
-        let mut channel_config = ChannelConfig::load().await?;
-        channel_config.set_agent_type(&channel_id, agent_type.clone());
-
         // 移除舊 session
-        state.session_manager.remove_session(channel_id_u64).await;
+        state.sessions().remove_session(channel_id_u64).await;
+        state.backends().release_channel(channel_id_u64).await;
 
         // 測試並創建新 session
         match state
-            .session_manager
-            .get_or_create_session(channel_id_u64, agent_type.clone(), &state.backend_manager)
+            .sessions()
+            .get_or_create_session(channel_id_u64, agent_type.clone())
             .await
         {
             Ok(_) => {
-                // 連接成功，保存配置
-                channel_config.save().await?;
+                // 連接成功，保存配置 (在建立 session 時寫入的 session ID 之上更新)
+                ChannelConfig::update(|cfg| cfg.set_agent_type(&channel_id, agent_type.clone()))
+                    .await?;
                 info!("Channel {} switched to {} backend", channel_id, agent_type);
 
                 interaction
@@ -304,10 +482,52 @@ pub async fn handle_button(
 }
 
 #[cfg(test)]
+#[allow(clippy::await_holding_lock)]
 mod tests {
-    use super::{build_backend_error_message, is_binary_not_found, ChannelConfig, ChannelEntry};
+    use super::{build_backend_error_message, ChannelConfig, ChannelEntry, GuildEntry};
     use crate::agent::AgentType;
+    use crate::error_catalog::is_binary_not_found;
     use crate::i18n::I18n;
+    use crate::migrate::BASE_DIR_ENV;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_concurrent_updates_are_not_lost() {
+        let _guard = crate::migrate::env_lock();
+        let dir = tempdir().expect("tempdir");
+        // SAFETY: serialized by env lock
+        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
+
+        let tasks: Vec<_> = (0..20)
+            .map(|i| {
+                tokio::spawn(ChannelConfig::update(move |cfg| {
+                    cfg.entry_mut(&i.to_string()).faq_cache = true
+                }))
+            })
+            .collect();
+        for task in tasks {
+            task.await.expect("join").expect("update");
+        }
+        let config = ChannelConfig::load().await.expect("load");
+        assert_eq!(config.channels.len(), 20);
+        assert!(config.channels.values().all(|e| e.faq_cache));
+        assert!(!dir.path().join("channel_config.json.tmp").exists());
+
+        // 沒有變更時不改寫檔案
+        let path = dir.path().join("channel_config.json");
+        std::fs::write(&path, "{\"channels\":{}}").expect("write");
+        let unchanged = ChannelConfig::update(|cfg| cfg.channels.len())
+            .await
+            .expect("update");
+        assert_eq!(unchanged, 0);
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read"),
+            "{\"channels\":{}}"
+        );
+
+        // SAFETY: serialized by env lock
+        unsafe { std::env::remove_var(BASE_DIR_ENV) };
+    }
 
     #[test]
     fn test_binary_not_found_detection() {
@@ -378,6 +598,10 @@ mod tests {
     fn test_channel_config_set_agent_type_creates_entry_with_defaults() {
         let mut cfg = ChannelConfig::default();
         cfg.set_agent_type("123", AgentType::Opencode);
+        assert!(cfg.migrate_channel("123", "456"));
+        assert!(!cfg.migrate_channel("123", "456"));
+        assert_eq!(cfg.get_agent_type("456"), AgentType::Opencode);
+        assert!(cfg.migrate_channel("456", "123"));
 
         let entry = cfg.channels.get("123").expect("entry");
         assert_eq!(entry.agent_type, AgentType::Opencode);
@@ -387,6 +611,91 @@ mod tests {
         assert!(!entry.authorized_at.is_empty());
     }
 
+    #[test]
+    fn test_guild_default_backend_only_fills_new_channels() {
+        let mut cfg = ChannelConfig::default();
+        assert!(!cfg.apply_guild_defaults("1", "g"));
+        cfg.guilds.insert(
+            "g".to_string(),
+            GuildEntry {
+                agent_type: Some(AgentType::Kilo),
+            },
+        );
+        cfg.set_agent_type("2", AgentType::Opencode);
+
+        assert!(cfg.apply_guild_defaults("1", "g"));
+        assert_eq!(cfg.get_agent_type("1"), AgentType::Kilo);
+        assert!(!cfg.apply_guild_defaults("1", "g"));
+        // 頻道已有自己的設定時不覆蓋
+        assert!(!cfg.apply_guild_defaults("2", "g"));
+        assert_eq!(cfg.get_agent_type("2"), AgentType::Opencode);
+        assert!(!cfg.apply_guild_defaults("3", "other"));
+    }
+
+    #[test]
+    fn test_thread_inherits_parent_settings_without_session() {
+        let mut cfg = ChannelConfig::default();
+        assert!(!cfg.inherit_parent("t", "p"));
+        cfg.set_agent_type("p", AgentType::Kilo);
+        {
+            let parent = cfg.entry_mut("p");
+            parent.session_id = Some("ses_parent".to_string());
+            parent.faq_cache = true;
+        }
+
+        assert!(cfg.inherit_parent("t", "p"));
+        let thread = &cfg.channels["t"];
+        assert_eq!(thread.agent_type, AgentType::Kilo);
+        assert!(thread.faq_cache);
+        assert_eq!(thread.session_id, None);
+        // 討論串之後的設定不會被父頻道覆蓋
+        cfg.set_agent_type("t", AgentType::Opencode);
+        assert!(!cfg.inherit_parent("t", "p"));
+        assert_eq!(cfg.get_agent_type("t"), AgentType::Opencode);
+    }
+
+    #[tokio::test]
+    async fn test_load_resolved_writes_only_when_defaults_apply() {
+        let _guard = crate::migrate::env_lock();
+        let dir = tempdir().expect("tempdir");
+        // SAFETY: serialized by env lock
+        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
+        let path = dir.path().join("channel_config.json");
+
+        // 沒有可套用的設定時不建立設定檔
+        let cfg = ChannelConfig::load_resolved("t", "g", Some("p"))
+            .await
+            .expect("resolve");
+        assert!(cfg.channels.is_empty());
+        assert!(!path.exists());
+
+        ChannelConfig::update(|cfg| cfg.set_agent_type("p", AgentType::Kilo))
+            .await
+            .expect("update");
+        let cfg = ChannelConfig::load_resolved("t", "g", Some("p"))
+            .await
+            .expect("resolve");
+        assert_eq!(cfg.get_agent_type("t"), AgentType::Kilo);
+        assert_eq!(
+            ChannelConfig::load()
+                .await
+                .expect("load")
+                .get_agent_type("t"),
+            AgentType::Kilo
+        );
+        // 已有設定後只讀不寫
+        let before = std::fs::metadata(&path).expect("stat").modified().ok();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        ChannelConfig::load_resolved("t", "g", Some("p"))
+            .await
+            .expect("resolve");
+        assert_eq!(
+            std::fs::metadata(&path).expect("stat").modified().ok(),
+            before
+        );
+        unsafe { std::env::remove_var(BASE_DIR_ENV) };
+    }
+
     #[test]
     fn test_backend_error_message_for_kilo_has_start_command() {
         let i18n = I18n::new("en");
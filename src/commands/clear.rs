@@ -4,6 +4,7 @@ use serenity::all::{CommandInteraction, Context, EditInteractionResponse};
 
 use super::agent::ChannelConfig;
 use crate::migrate;
+use tracing::warn;
 
 pub struct ClearCommand;
 
@@ -25,40 +26,7 @@ impl SlashCommand for ClearCommand {
     ) -> anyhow::Result<()> {
         command.defer_ephemeral(&ctx.http).await?;
 
-        let channel_id_u64 = command.channel_id.get();
-        let channel_id_str = channel_id_u64.to_string();
-        let channel_config = crate::commands::agent::ChannelConfig::load()
-            .await
-            .unwrap_or_default();
-        let agent_type = channel_config.get_agent_type(&channel_id_str);
-
-        let (agent, _) = state
-            .session_manager
-            .get_or_create_session(channel_id_u64, agent_type, &state.backend_manager)
-            .await?;
-
-        // 1. 清除後端 session
-        agent.clear().await?;
-
-        // 2. 移除記憶體快取
-        state.session_manager.remove_session(channel_id_u64).await;
-
-        // 3. 刪除本地 session 檔案
-        let agent_type = agent.agent_type();
-        let session_file = migrate::get_sessions_dir(agent_type)
-            .join(format!("discord-rs-{}.jsonl", channel_id_u64));
-
-        if session_file.exists() {
-            tokio::fs::remove_file(&session_file).await.ok();
-        }
-
-        // 4. 清除持久化配置中的 ID
-        if let Ok(mut config) = ChannelConfig::load().await {
-            if let Some(entry) = config.channels.get_mut(&channel_id_str) {
-                entry.session_id = None;
-                let _ = config.save().await;
-            }
-        }
+        clear_channel(state, command.channel_id.get()).await?;
 
         let i18n = state.i18n.read().await;
         let msg = i18n.get("clear_success");
@@ -71,3 +39,50 @@ impl SlashCommand for ClearCommand {
         Ok(())
     }
 }
+
+/// 清除頻道的對話：後端 session、快取、本地檔案與記憶摘要；`/clear` 與文字指令共用
+pub async fn clear_channel(state: &crate::AppState, channel_id_u64: u64) -> anyhow::Result<()> {
+    let channel_id_str = channel_id_u64.to_string();
+    let channel_config = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default();
+    let agent_type = channel_config.get_agent_type(&channel_id_str);
+
+    let (agent, _) = state
+        .sessions()
+        .get_or_create_session(channel_id_u64, agent_type)
+        .await?;
+
+    // 1. 清除後端 session (opencode/kilo 刪除伺服器端 session，ACP 釋放舊 session)；
+    //    失敗時仍解除綁定，下次對話一樣會開新 session 並重新套用模型與提示詞
+    if let Err(e) = agent.clear().await {
+        warn!(
+            "⚠️ Backend clear failed on channel {}: {}",
+            channel_id_u64, e
+        );
+    }
+
+    // 2. 移除記憶體快取
+    state.sessions().remove_session(channel_id_u64).await;
+
+    // 3. 刪除本地 session 檔案
+    let agent_type = agent.agent_type();
+    let session_file =
+        migrate::get_sessions_dir(agent_type).join(format!("discord-rs-{}.jsonl", channel_id_u64));
+
+    if session_file.exists() {
+        tokio::fs::remove_file(&session_file).await.ok();
+    }
+
+    // 4. 壓縮摘要屬於舊對話，一併移除
+    crate::memory::remove(channel_id_u64).await;
+
+    // 5. 清除持久化配置中的 ID
+    let _ = ChannelConfig::update(|config| {
+        if let Some(entry) = config.channels.get_mut(&channel_id_str) {
+            entry.session_id = None;
+        }
+    })
+    .await;
+    Ok(())
+}
@@ -1,6 +1,8 @@
+use super::long_reply::LongReply;
 use super::SlashCommand;
 use async_trait::async_trait;
 use serenity::all::{CommandInteraction, Context, EditInteractionResponse};
+use tracing::warn;
 
 pub struct CompactCommand;
 
@@ -30,20 +32,77 @@ impl SlashCommand for CompactCommand {
         let agent_type = channel_config.get_agent_type(&channel_id_str);
 
         let (agent, _) = state
-            .session_manager
-            .get_or_create_session(channel_id_u64, agent_type, &state.backend_manager)
+            .sessions()
+            .get_or_create_session(channel_id_u64, agent_type)
             .await?;
 
-        agent.compact().await?;
-
-        let i18n = state.i18n.read().await;
-        let msg = i18n.get("compact_success");
-        drop(i18n);
+        // 摘要需要跑一輪對話，執行中的回合會把摘要顯示在使用者的訊息裡
+        if state
+            .active_renders
+            .lock()
+            .await
+            .contains_key(&channel_id_u64)
+        {
+            let msg = state.i18n.read().await.get("compact_busy");
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        }
 
-        command
-            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        let mut reply = LongReply::new(ctx, command);
+        let progress_i18n = state.i18n.read().await.get("long_op_progress");
+        let summarize_and_compact = async {
+            // 先產生滾動摘要，失敗時沿用舊摘要，不阻擋壓縮
+            let previous = crate::memory::load(channel_id_u64).await;
+            let summary = match crate::memory::summarize(
+                &*agent,
+                previous
+                    .as_ref()
+                    .map(|m| m.summary.as_str())
+                    .filter(|s| !s.is_empty()),
+            )
+            .await
+            {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!(
+                        "⚠️ Failed to summarize channel {} before compact: {}",
+                        channel_id_u64, e
+                    );
+                    previous
+                        .as_ref()
+                        .map(|m| m.summary.clone())
+                        .filter(|s| !s.is_empty())
+                }
+            };
+            agent.compact().await?;
+            if let Some(summary) = summary {
+                // 交接筆記與壓縮無關，保留下來
+                let memory = crate::memory::ChannelMemory {
+                    summary,
+                    updated_at: chrono::Utc::now().to_rfc3339(),
+                    pending_inject: true,
+                    handoff: previous.and_then(|m| m.handoff),
+                };
+                if let Err(e) = crate::memory::save(channel_id_u64, &memory).await {
+                    warn!(
+                        "⚠️ Failed to save memory for channel {}: {}",
+                        channel_id_u64, e
+                    );
+                }
+            }
+            anyhow::Ok(())
+        };
+        reply
+            .run(summarize_and_compact, |secs| {
+                progress_i18n.replace("{0}", &secs.to_string())
+            })
             .await?;
 
+        let msg = state.i18n.read().await.get("compact_success");
+        reply.update(msg).await?;
+
         Ok(())
     }
 }
@@ -1,14 +1,17 @@
 use super::SlashCommand;
 use async_trait::async_trait;
 use serenity::all::{
-    ActionRowComponent, CommandInteraction, Context, CreateActionRow, CreateInputText,
-    CreateInteractionResponse, CreateModal, CreateSelectMenu, CreateSelectMenuKind,
-    CreateSelectMenuOption, EditInteractionResponse, InputTextStyle, ModalInteraction,
+    ActionRowComponent, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateActionRow, CreateCommandOption, CreateInputText, CreateInteractionResponse, CreateModal,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditInteractionResponse,
+    InputTextStyle, ModalInteraction,
 };
 
 use crate::agent::AgentType;
 
 const ASSISTANT_NAME_MAX_CHARS: usize = 48;
+const TURN_LIMIT_CHOICES: [u64; 5] = [300, 900, 1800, 3600, 0];
+const TOOL_OUTPUT_RETENTION_CHOICES: [usize; 4] = [5, 20, 100, 0];
 
 #[derive(Debug, Clone, PartialEq)]
 enum ConfigSelectAction {
@@ -16,6 +19,8 @@ enum ConfigSelectAction {
     Mention(bool),
     AssistantDefault,
     AssistantCustom,
+    TurnLimit(Option<u64>),
+    ToolOutputRetention(Option<usize>),
     Ignore,
 }
 
@@ -31,6 +36,36 @@ impl SlashCommand for ConfigCommand {
         i18n.get("cmd_config_desc")
     }
 
+    fn options(&self, i18n: &crate::i18n::I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "mentions",
+                i18n.get("cmd_config_opt_mentions"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "accessible",
+                i18n.get("cmd_config_opt_accessible"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "reactions",
+                i18n.get("cmd_config_opt_reactions"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "workdir",
+                i18n.get("cmd_config_opt_workdir"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "text_commands",
+                i18n.get("cmd_config_opt_text_commands"),
+            ),
+        ]
+    }
+
     async fn execute(
         &self,
         ctx: &Context,
@@ -39,10 +74,85 @@ impl SlashCommand for ConfigCommand {
     ) -> anyhow::Result<()> {
         command.defer_ephemeral(&ctx.http).await?;
 
+        let mentions_input = command
+            .data
+            .options
+            .iter()
+            .find(|opt| opt.name == "mentions")
+            .and_then(|opt| match &opt.value {
+                CommandDataOptionValue::String(s) => Some(s.clone()),
+                _ => None,
+            });
+        let accessible_input = command
+            .data
+            .options
+            .iter()
+            .find(|opt| opt.name == "accessible")
+            .and_then(|opt| opt.value.as_bool());
+        let reactions_input = command
+            .data
+            .options
+            .iter()
+            .find(|opt| opt.name == "reactions")
+            .and_then(|opt| opt.value.as_bool());
+        let workdir_input = command
+            .data
+            .options
+            .iter()
+            .find(|opt| opt.name == "workdir")
+            .and_then(|opt| opt.value.as_str().map(str::to_string));
+        let text_commands_input = command
+            .data
+            .options
+            .iter()
+            .find(|opt| opt.name == "text_commands")
+            .and_then(|opt| opt.value.as_bool());
+        if mentions_input.is_some()
+            || accessible_input.is_some()
+            || reactions_input.is_some()
+            || workdir_input.is_some()
+            || text_commands_input.is_some()
+        {
+            let mut replies = Vec::new();
+            if let Some(input) = mentions_input {
+                replies.push(set_mentions(command, state, &input).await?);
+            }
+            if let Some(enable) = accessible_input {
+                replies.push(set_accessible(command, state, enable).await?);
+            }
+            if let Some(enable) = reactions_input {
+                replies.push(set_status_reactions(command, state, enable).await?);
+            }
+            if let Some(input) = workdir_input {
+                replies.push(set_workdir(command, state, &input).await?);
+            }
+            if let Some(enable) = text_commands_input {
+                replies.push(set_text_commands(command, state, enable).await?);
+            }
+            let msg = replies.join("\n");
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        }
+
         let channel_id_str = command.channel_id.to_string();
         let channel_config = crate::commands::agent::ChannelConfig::load()
             .await
             .unwrap_or_default();
+        let (mentions, accessible, status_reactions, workdir, text_commands) = channel_config
+            .channels
+            .get(&channel_id_str)
+            .map(|e| {
+                (
+                    e.mentions.clone(),
+                    e.accessible,
+                    e.status_reactions,
+                    e.workdir.clone(),
+                    e.text_commands,
+                )
+            })
+            .unwrap_or_default();
         let backend = channel_config.get_agent_type(&channel_id_str);
         let assistant_name = channel_config
             .channels
@@ -51,11 +161,22 @@ impl SlashCommand for ConfigCommand {
             .filter(|s| !s.trim().is_empty())
             .unwrap_or_else(|| state.config.assistant_name.clone());
         let mention_only = state
-            .auth
+            .auth()
             .get_channel_mention_only(&channel_id_str)
             .unwrap_or(true);
+        let max_turn = crate::flow::resolve_channel_max_turn(
+            &channel_config,
+            &channel_id_str,
+            state.config.max_turn_secs,
+        );
+        let tool_output_retention = crate::flow::resolve_channel_tool_output_retention(
+            &channel_config,
+            &channel_id_str,
+            state.config.composer.tool_output_retention,
+        );
 
-        let i18n = state.i18n.read().await;
+        let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+        let i18n = channel_i18n.read().await;
         let status = i18n.get_args(
             "config_current",
             &[
@@ -66,18 +187,23 @@ impl SlashCommand for ConfigCommand {
                     i18n.get("config_mention_off")
                 },
                 assistant_name,
+                format_turn_limit(&i18n, max_turn.map(|d| d.as_secs())),
+                format_tool_output_retention(&i18n, tool_output_retention),
+                format_mentions(&i18n, &mentions),
+                accessible.to_string(),
+                workdir.unwrap_or_else(|| i18n.get("config_workdir_default")),
+                status_reactions.to_string(),
+                text_commands.to_string(),
             ],
         );
 
         let backend_menu = CreateSelectMenu::new(
             "config_backend_select",
             CreateSelectMenuKind::String {
-                options: vec![
-                    CreateSelectMenuOption::new(i18n.get("agent_choice_kilo"), "kilo"),
-                    CreateSelectMenuOption::new(i18n.get("agent_choice_copilot"), "copilot"),
-                    CreateSelectMenuOption::new(i18n.get("agent_choice_pi"), "pi"),
-                    CreateSelectMenuOption::new(i18n.get("agent_choice_opencode"), "opencode"),
-                ],
+                options: crate::agent::enabled_backends()
+                    .into_iter()
+                    .map(|t| CreateSelectMenuOption::new(i18n.get(t.choice_key()), t.to_string()))
+                    .collect(),
             },
         )
         .placeholder(i18n.get("config_backend_placeholder"))
@@ -110,6 +236,50 @@ impl SlashCommand for ConfigCommand {
         .min_values(1)
         .max_values(1);
 
+        let turn_limit_menu = CreateSelectMenu::new(
+            "config_turn_limit_select",
+            CreateSelectMenuKind::String {
+                options: TURN_LIMIT_CHOICES
+                    .iter()
+                    .map(|secs| {
+                        CreateSelectMenuOption::new(
+                            format_turn_limit(&i18n, Some(*secs)),
+                            secs.to_string(),
+                        )
+                    })
+                    .chain(std::iter::once(CreateSelectMenuOption::new(
+                        i18n.get("config_turn_limit_default"),
+                        "default",
+                    )))
+                    .collect(),
+            },
+        )
+        .placeholder(i18n.get("config_turn_limit_placeholder"))
+        .min_values(1)
+        .max_values(1);
+
+        let tool_output_menu = CreateSelectMenu::new(
+            "config_tool_output_select",
+            CreateSelectMenuKind::String {
+                options: TOOL_OUTPUT_RETENTION_CHOICES
+                    .iter()
+                    .map(|turns| {
+                        CreateSelectMenuOption::new(
+                            format_tool_output_retention(&i18n, *turns),
+                            turns.to_string(),
+                        )
+                    })
+                    .chain(std::iter::once(CreateSelectMenuOption::new(
+                        i18n.get("config_turn_limit_default"),
+                        "default",
+                    )))
+                    .collect(),
+            },
+        )
+        .placeholder(i18n.get("config_tool_output_placeholder"))
+        .min_values(1)
+        .max_values(1);
+
         command
             .edit_response(
                 &ctx.http,
@@ -119,6 +289,8 @@ impl SlashCommand for ConfigCommand {
                         CreateActionRow::SelectMenu(backend_menu),
                         CreateActionRow::SelectMenu(mention_menu),
                         CreateActionRow::SelectMenu(assistant_menu),
+                        CreateActionRow::SelectMenu(turn_limit_menu),
+                        CreateActionRow::SelectMenu(tool_output_menu),
                     ]),
             )
             .await?;
@@ -127,7 +299,183 @@ impl SlashCommand for ConfigCommand {
     }
 }
 
-fn sanitize_assistant_name(raw: &str) -> Option<String> {
+fn format_turn_limit(i18n: &crate::i18n::I18n, secs: Option<u64>) -> String {
+    match secs {
+        None | Some(0) => i18n.get("config_turn_limit_off"),
+        Some(secs) => i18n.format_duration(std::time::Duration::from_secs(secs)),
+    }
+}
+
+fn format_tool_output_retention(i18n: &crate::i18n::I18n, turns: usize) -> String {
+    match turns {
+        0 => i18n.get("config_tool_output_off"),
+        turns => i18n.get_args(
+            "config_tool_output_turns",
+            &[i18n.format_number(turns as u64)],
+        ),
+    }
+}
+
+fn format_mentions(i18n: &crate::i18n::I18n, policy: &crate::mentions::MentionPolicy) -> String {
+    if policy.is_empty() {
+        i18n.get("config_mentions_none")
+    } else {
+        policy.describe()
+    }
+}
+
+/// `/config mentions`：設定代理可實際通知的對象，限管理員
+async fn set_mentions(
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    input: &str,
+) -> anyhow::Result<String> {
+    let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+    if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+        return Ok(channel_i18n.read().await.get("config_mentions_admin_only"));
+    }
+    let policy = crate::mentions::MentionPolicy::parse(input);
+    let channel_id = command.channel_id.to_string();
+    crate::commands::agent::ChannelConfig::update(|cfg| {
+        cfg.entry_mut(&channel_id).mentions = policy.clone()
+    })
+    .await?;
+
+    let i18n = channel_i18n.read().await;
+    Ok(if policy.is_empty() {
+        i18n.get("config_mentions_cleared")
+    } else {
+        i18n.get_args("config_mentions_set", &[policy.describe()])
+    })
+}
+
+/// `/config accessible`：此頻道的回應改以純文字呈現，不影響其他設定所以不限管理員
+async fn set_accessible(
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    enable: bool,
+) -> anyhow::Result<String> {
+    let channel_id = command.channel_id.to_string();
+    crate::commands::agent::ChannelConfig::update(|cfg| {
+        cfg.entry_mut(&channel_id).accessible = enable
+    })
+    .await?;
+
+    let i18n = state.channel_i18n(command.channel_id.get()).await;
+    let msg = i18n.read().await.get(if enable {
+        "config_accessible_on"
+    } else {
+        "config_accessible_off"
+    });
+    Ok(msg)
+}
+
+/// `/config reactions`：以提示上的反應標示進度，只送出最終回答，大幅減少訊息編輯次數
+async fn set_status_reactions(
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    enable: bool,
+) -> anyhow::Result<String> {
+    let channel_id = command.channel_id.to_string();
+    crate::commands::agent::ChannelConfig::update(|cfg| {
+        cfg.entry_mut(&channel_id).status_reactions = enable
+    })
+    .await?;
+
+    let i18n = state.channel_i18n(command.channel_id.get()).await;
+    let msg = i18n.read().await.get(if enable {
+        "config_reactions_on"
+    } else {
+        "config_reactions_off"
+    });
+    Ok(msg)
+}
+
+/// `/config text_commands`：以前綴開頭的訊息視為文字指令，不影響其他設定所以不限管理員
+async fn set_text_commands(
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    enable: bool,
+) -> anyhow::Result<String> {
+    let channel_id = command.channel_id.to_string();
+    crate::commands::agent::ChannelConfig::update(|cfg| {
+        cfg.entry_mut(&channel_id).text_commands = enable
+    })
+    .await?;
+
+    let i18n = state.channel_i18n(command.channel_id.get()).await;
+    let prefix = state.config.text_command_prefix.clone();
+    let msg = if enable {
+        i18n.read()
+            .await
+            .get_args("config_text_commands_on", &[prefix])
+    } else {
+        i18n.read().await.get("config_text_commands_off")
+    };
+    Ok(msg)
+}
+
+/// `/config workdir` 的輸入：`off` 或空白表示清除，否則須為已存在目錄的絕對路徑
+pub fn parse_workdir(input: &str) -> Result<Option<String>, &'static str> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+    let path = std::path::Path::new(input);
+    if !path.is_absolute() {
+        return Err("config_workdir_not_absolute");
+    }
+    if !path.is_dir() {
+        return Err("config_workdir_missing");
+    }
+    Ok(Some(input.to_string()))
+}
+
+/// `/config workdir`：將頻道綁定到專案目錄，限管理員。
+/// 既有 session 屬於舊目錄，所以改設定後下一則訊息會開新的 session
+async fn set_workdir(
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    input: &str,
+) -> anyhow::Result<String> {
+    let channel_id_u64 = command.channel_id.get();
+    let channel_i18n = state.channel_i18n(channel_id_u64).await;
+    if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+        return Ok(channel_i18n.read().await.get("config_workdir_admin_only"));
+    }
+    let workdir = match parse_workdir(input) {
+        Ok(workdir) => workdir,
+        Err(key) => {
+            return Ok(channel_i18n
+                .read()
+                .await
+                .get_args(key, &[input.trim().to_string()]))
+        }
+    };
+    let channel_id = command.channel_id.to_string();
+    let changed = crate::commands::agent::ChannelConfig::update(|cfg| {
+        let entry = cfg.entry_mut(&channel_id);
+        if entry.workdir == workdir {
+            return false;
+        }
+        entry.workdir = workdir.clone();
+        entry.session_id = None;
+        true
+    })
+    .await?;
+    if changed {
+        state.sessions().remove_session(channel_id_u64).await;
+        state.backends().release_channel(channel_id_u64).await;
+    }
+
+    let i18n = channel_i18n.read().await;
+    Ok(match workdir {
+        Some(dir) => i18n.get_args("config_workdir_set", &[dir]),
+        None => i18n.get("config_workdir_cleared"),
+    })
+}
+
+pub fn sanitize_assistant_name(raw: &str) -> Option<String> {
     let trimmed = raw.trim();
     if trimmed.is_empty() {
         return None;
@@ -174,6 +522,18 @@ fn parse_config_select_action(custom_id: &str, value: &str) -> ConfigSelectActio
         "config_mention_select" => ConfigSelectAction::Mention(value == "on"),
         "config_assistant_select" if value == "default" => ConfigSelectAction::AssistantDefault,
         "config_assistant_select" if value == "custom" => ConfigSelectAction::AssistantCustom,
+        "config_turn_limit_select" if value == "default" => ConfigSelectAction::TurnLimit(None),
+        "config_turn_limit_select" => value
+            .parse::<u64>()
+            .map(|secs| ConfigSelectAction::TurnLimit(Some(secs)))
+            .unwrap_or(ConfigSelectAction::Ignore),
+        "config_tool_output_select" if value == "default" => {
+            ConfigSelectAction::ToolOutputRetention(None)
+        }
+        "config_tool_output_select" => value
+            .parse::<usize>()
+            .map(|turns| ConfigSelectAction::ToolOutputRetention(Some(turns)))
+            .unwrap_or(ConfigSelectAction::Ignore),
         _ => ConfigSelectAction::Ignore,
     }
 }
@@ -229,25 +589,28 @@ pub async fn handle_config_select(
 
     match parse_config_select_action(custom_id, &value) {
         ConfigSelectAction::Backend(selected) => {
-            let mut channel_config = crate::commands::agent::ChannelConfig::load()
+            let current = crate::commands::agent::ChannelConfig::load()
                 .await
-                .unwrap_or_default();
-            let current = channel_config.get_agent_type(&channel_id_str);
+                .unwrap_or_default()
+                .get_agent_type(&channel_id_str);
 
             let msg = if current == selected {
                 let i18n = state.i18n.read().await;
                 i18n.get_args("agent_already", &[selected.to_string()])
             } else {
-                channel_config.set_agent_type(&channel_id_str, selected.clone());
-                state.session_manager.remove_session(channel_id_u64).await;
+                state.sessions().remove_session(channel_id_u64).await;
+                state.backends().release_channel(channel_id_u64).await;
 
                 match state
-                    .session_manager
-                    .get_or_create_session(channel_id_u64, selected.clone(), &state.backend_manager)
+                    .sessions()
+                    .get_or_create_session(channel_id_u64, selected.clone())
                     .await
                 {
                     Ok(_) => {
-                        channel_config.save().await?;
+                        crate::commands::agent::ChannelConfig::update(|cfg| {
+                            cfg.set_agent_type(&channel_id_str, selected.clone())
+                        })
+                        .await?;
                         let i18n = state.i18n.read().await;
                         i18n.get_args("config_backend_set", &[selected.to_string()])
                     }
@@ -270,7 +633,7 @@ pub async fn handle_config_select(
         ConfigSelectAction::Mention(enable) => {
             let msg = {
                 let i18n = state.i18n.read().await;
-                match state.auth.set_mention_only(&channel_id_str, enable) {
+                match state.auth().set_mention_only(&channel_id_str, enable) {
                     Ok(_) => i18n.get(if enable { "mention_on" } else { "mention_off" }),
                     Err(_) => i18n.get("mention_not_auth"),
                 }
@@ -281,23 +644,60 @@ pub async fn handle_config_select(
                 .await?;
         }
         ConfigSelectAction::AssistantDefault => {
-            let mut channel_config = crate::commands::agent::ChannelConfig::load()
-                .await
-                .unwrap_or_default();
-            channel_config.set_agent_type(
-                &channel_id_str,
-                channel_config.get_agent_type(&channel_id_str),
-            );
-            if let Some(entry) = channel_config.channels.get_mut(&channel_id_str) {
-                entry.assistant_name = None;
-            }
-            channel_config.save().await?;
+            crate::commands::agent::ChannelConfig::update(|cfg| {
+                cfg.entry_mut(&channel_id_str).assistant_name = None
+            })
+            .await?;
 
             let msg = {
                 let i18n = state.i18n.read().await;
                 i18n.get_args(
                     "config_assistant_set",
-                    &[state.config.assistant_name.clone()],
+                    std::slice::from_ref(&state.config.assistant_name),
+                )
+            };
+
+            interaction
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+        }
+        ConfigSelectAction::TurnLimit(secs) => {
+            let effective = crate::commands::agent::ChannelConfig::update(|cfg| {
+                cfg.entry_mut(&channel_id_str).max_turn_secs = secs;
+                crate::flow::resolve_channel_max_turn(
+                    cfg,
+                    &channel_id_str,
+                    state.config.max_turn_secs,
+                )
+            })
+            .await?;
+            let msg = {
+                let i18n = state.i18n.read().await;
+                i18n.get_args(
+                    "config_turn_limit_set",
+                    &[format_turn_limit(&i18n, effective.map(|d| d.as_secs()))],
+                )
+            };
+
+            interaction
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+        }
+        ConfigSelectAction::ToolOutputRetention(turns) => {
+            let effective = crate::commands::agent::ChannelConfig::update(|cfg| {
+                cfg.entry_mut(&channel_id_str).tool_output_retention = turns;
+                crate::flow::resolve_channel_tool_output_retention(
+                    cfg,
+                    &channel_id_str,
+                    state.config.composer.tool_output_retention,
+                )
+            })
+            .await?;
+            let msg = {
+                let i18n = state.i18n.read().await;
+                i18n.get_args(
+                    "config_tool_output_set",
+                    &[format_tool_output_retention(&i18n, effective)],
                 )
             };
 
@@ -341,14 +741,10 @@ pub async fn handle_assistant_modal_submit(
     };
 
     let channel_id = interaction.channel_id.to_string();
-    let mut channel_config = crate::commands::agent::ChannelConfig::load()
-        .await
-        .unwrap_or_default();
-    channel_config.set_agent_type(&channel_id, channel_config.get_agent_type(&channel_id));
-    if let Some(entry) = channel_config.channels.get_mut(&channel_id) {
-        entry.assistant_name = Some(safe_name.clone());
-    }
-    channel_config.save().await?;
+    crate::commands::agent::ChannelConfig::update(|cfg| {
+        cfg.entry_mut(&channel_id).assistant_name = Some(safe_name.clone())
+    })
+    .await?;
 
     let msg = {
         let i18n = state.i18n.read().await;
@@ -365,8 +761,8 @@ pub async fn handle_assistant_modal_submit(
 #[cfg(test)]
 mod tests {
     use super::{
-        extract_selected_value, parse_config_select_action, sanitize_assistant_name,
-        ConfigSelectAction,
+        extract_selected_value, format_turn_limit, parse_config_select_action, parse_workdir,
+        sanitize_assistant_name, ConfigSelectAction,
     };
     use crate::agent::AgentType;
     use serenity::all::ComponentInteractionDataKind;
@@ -430,5 +826,55 @@ mod tests {
             parse_config_select_action("config_backend_select", "invalid-backend"),
             ConfigSelectAction::Ignore
         );
+        assert_eq!(
+            parse_config_select_action("config_turn_limit_select", "900"),
+            ConfigSelectAction::TurnLimit(Some(900))
+        );
+        assert_eq!(
+            parse_config_select_action("config_turn_limit_select", "default"),
+            ConfigSelectAction::TurnLimit(None)
+        );
+        assert_eq!(
+            parse_config_select_action("config_tool_output_select", "0"),
+            ConfigSelectAction::ToolOutputRetention(Some(0))
+        );
+        assert_eq!(
+            parse_config_select_action("config_tool_output_select", "default"),
+            ConfigSelectAction::ToolOutputRetention(None)
+        );
+    }
+
+    #[test]
+    fn test_parse_workdir_requires_existing_absolute_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().to_string_lossy().to_string();
+        assert_eq!(
+            parse_workdir(&format!(" {} ", path)),
+            Ok(Some(path.clone()))
+        );
+        assert_eq!(parse_workdir("off"), Ok(None));
+        assert_eq!(parse_workdir("  "), Ok(None));
+        assert_eq!(
+            parse_workdir("relative/project"),
+            Err("config_workdir_not_absolute")
+        );
+        assert_eq!(
+            parse_workdir(&format!("{}/missing", path)),
+            Err("config_workdir_missing")
+        );
+    }
+
+    #[test]
+    fn test_format_turn_limit_minutes_and_off() {
+        let i18n = crate::i18n::I18n::new("en");
+        assert_eq!(format_turn_limit(&i18n, Some(900)), "15m");
+        assert_eq!(
+            format_turn_limit(&i18n, Some(0)),
+            i18n.get("config_turn_limit_off")
+        );
+        assert_eq!(
+            format_turn_limit(&i18n, None),
+            i18n.get("config_turn_limit_off")
+        );
     }
 }
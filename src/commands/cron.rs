@@ -1,17 +1,63 @@
 use async_trait::async_trait;
 use serenity::all::{
-    ActionRowComponent, CommandInteraction, Context, CreateActionRow, CreateInputText,
+    ActionRowComponent, CommandDataOption, CommandDataOptionValue, CommandInteraction,
+    CommandOptionType, Context, CreateActionRow, CreateCommandOption, CreateInputText,
     CreateInteractionResponse, CreateModal, CreateSelectMenu, CreateSelectMenuKind,
     CreateSelectMenuOption, EditInteractionResponse, InputTextStyle, ModalInteraction,
 };
 use uuid::Uuid;
 
-use crate::commands::SlashCommand;
+use crate::commands::{reply, SlashCommand};
 use crate::cron::manager::CronJobInfo;
 use crate::i18n::I18n;
 
 pub struct CronCommand;
 
+pub const MODAL_ID: &str = "cron_setup";
+
+/// `/cron add` 的選項透過 modal ID 帶到送出時：`cron_setup:<run_as>:<attach_files>`
+fn modal_id(run_as: Option<u64>, attach_files: bool) -> String {
+    if run_as.is_none() && !attach_files {
+        return MODAL_ID.to_string();
+    }
+    format!(
+        "{}:{}:{}",
+        MODAL_ID,
+        run_as.unwrap_or(0),
+        u8::from(attach_files)
+    )
+}
+
+fn parse_modal_id(custom_id: &str) -> (Option<u64>, bool) {
+    let mut parts = custom_id
+        .strip_prefix(MODAL_ID)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .unwrap_or("")
+        .split(':');
+    let run_as = parts
+        .next()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|id| *id != 0);
+    let attach_files = parts.next() == Some("1");
+    (run_as, attach_files)
+}
+
+/// 清單中顯示的短 ID (UUID 前 8 碼)
+fn short_id(id: &Uuid) -> String {
+    id.to_string()[..8].to_string()
+}
+
+/// 以完整 ID 或唯一前綴找出排程
+fn find_job<'a>(jobs: &'a [CronJobInfo], id: &str) -> Option<&'a CronJobInfo> {
+    let id = id.trim().to_lowercase();
+    if id.is_empty() {
+        return None;
+    }
+    let mut matches = jobs.iter().filter(|j| j.id.to_string().starts_with(&id));
+    let job = matches.next()?;
+    matches.next().is_none().then_some(job)
+}
+
 fn normalize_freq(freq: &str) -> String {
     let freq_parts: Vec<&str> = freq.split_whitespace().collect();
     match freq_parts.len() {
@@ -47,6 +93,7 @@ pub async fn handle_modal_submit(
 ) -> anyhow::Result<()> {
     interaction.defer_ephemeral(&ctx.http).await?;
 
+    let (run_as, attach_files) = parse_modal_id(&interaction.data.custom_id);
     let mut minute = String::from("*");
     let mut hour = String::from("*");
     let mut freq = String::from("* * *");
@@ -89,6 +136,11 @@ pub async fn handle_modal_submit(
         }
     };
 
+    // 記下目前的後端，頻道之後換後端時排程不會在另一個後端上執行
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&interaction.channel_id.to_string());
     let job_id = Uuid::new_v4();
     let info = CronJobInfo {
         id: job_id,
@@ -98,9 +150,16 @@ pub async fn handle_modal_submit(
         prompt: prompt.to_string(),
         creator_id: interaction.user.id.get(),
         description: description.clone(),
+        guild_id: interaction.guild_id.map(|g| g.get()),
+        event_id: None,
+        last_result: None,
+        run_as,
+        attach_files,
+        agent_type: Some(agent_type),
+        paused: false,
     };
 
-    state.cron_manager.add_job(info).await?;
+    state.scheduler().add_job(info).await?;
 
     interaction
         .edit_response(
@@ -126,7 +185,7 @@ pub async fn handle_delete_select(
     {
         if let Some(uuid_str) = values.first() {
             if let Ok(id) = Uuid::parse_str(uuid_str) {
-                state.cron_manager.remove_job(id).await?;
+                state.scheduler().remove_job(id).await?;
 
                 // 核心修復：刪除完後，傳入空 components 陣列以移除下拉選單
                 interaction
@@ -153,15 +212,89 @@ impl SlashCommand for CronCommand {
         i18n.get("cmd_cron_desc")
     }
 
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                i18n.get("cmd_cron_add_desc"),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::User,
+                "run_as",
+                i18n.get("cmd_cron_opt_run_as"),
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "attach_files",
+                i18n.get("cmd_cron_opt_attach_files"),
+            )),
+            id_subcommand("run", i18n.get("cmd_cron_run_desc"), i18n),
+            id_subcommand("remove", i18n.get("cmd_cron_remove_desc"), i18n),
+            id_subcommand("pause", i18n.get("cmd_cron_pause_desc"), i18n),
+        ]
+    }
+
     async fn execute(
         &self,
         ctx: &Context,
         command: &CommandInteraction,
         state: &crate::AppState,
     ) -> anyhow::Result<()> {
-        let i18n = state.i18n.read().await;
+        let Some(sub) = command.data.options.first() else {
+            return Ok(());
+        };
+        let CommandDataOptionValue::SubCommand(opts) = &sub.value else {
+            return Ok(());
+        };
+        match sub.name.as_str() {
+            "add" => open_add_modal(ctx, command, state, opts).await,
+            "run" => run_job(ctx, command, state, opts).await,
+            "remove" => remove_job(ctx, command, state, opts).await,
+            "pause" => toggle_pause(ctx, command, state, opts).await,
+            _ => Ok(()),
+        }
+    }
+}
 
-        let modal = CreateModal::new("cron_setup", i18n.get("cron_modal_title")).components(vec![
+async fn open_add_modal(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    let run_as = opts
+        .iter()
+        .find(|o| o.name == "run_as")
+        .and_then(|o| o.value.as_user_id())
+        .map(|u| u.get());
+    let attach_files = opts
+        .iter()
+        .find(|o| o.name == "attach_files")
+        .and_then(|o| o.value.as_bool())
+        .unwrap_or(false);
+
+    let i18n = state.i18n.read().await;
+
+    // 只有管理員可以指定其他使用者
+    if run_as.is_some_and(|u| u != command.user.id.get())
+        && !super::is_admin(command.member.as_deref(), command.guild_id.is_some())
+    {
+        command
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    serenity::all::CreateInteractionResponseMessage::new()
+                        .content(i18n.get("cron_run_as_admin_only"))
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let modal = CreateModal::new(modal_id(run_as, attach_files), i18n.get("cron_modal_title"))
+        .components(vec![
             CreateActionRow::InputText(
                 CreateInputText::new(
                     InputTextStyle::Short,
@@ -203,12 +336,110 @@ impl SlashCommand for CronCommand {
             ),
         ]);
 
-        command
-            .create_response(&ctx.http, CreateInteractionResponse::Modal(modal))
-            .await?;
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Modal(modal))
+        .await?;
 
-        Ok(())
+    Ok(())
+}
+
+/// 以排程 ID 為參數的子指令 (`run`、`remove`、`pause`)
+fn id_subcommand(name: &str, description: String, i18n: &I18n) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::SubCommand, name, description).add_sub_option(
+        CreateCommandOption::new(CommandOptionType::String, "id", i18n.get("cmd_cron_opt_id"))
+            .required(true),
+    )
+}
+
+fn id_option(opts: &[CommandDataOption]) -> &str {
+    opts.iter()
+        .find(|o| o.name == "id")
+        .and_then(|o| o.value.as_str())
+        .unwrap_or("")
+}
+
+/// 本頻道中符合 ID (或唯一前綴) 的排程
+async fn find_channel_job(
+    state: &crate::AppState,
+    command: &CommandInteraction,
+    id: &str,
+) -> Option<CronJobInfo> {
+    let jobs = state
+        .scheduler()
+        .get_jobs_for_channel(command.channel_id.get())
+        .await;
+    find_job(&jobs, id).cloned()
+}
+
+/// `/cron remove <id>`：刪除本頻道的排程
+async fn remove_job(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    command.defer_ephemeral(&ctx.http).await?;
+    let id = id_option(opts);
+    let job = find_channel_job(state, command, id).await;
+    if let Some(job) = &job {
+        state.scheduler().remove_job(job.id).await?;
     }
+    let msg = {
+        let i18n = state.i18n.read().await;
+        match job {
+            Some(job) => i18n.get_args("cron_deleted", &[short_id(&job.id)]),
+            None => i18n.get_args("cron_run_not_found", &[id.to_string()]),
+        }
+    };
+    reply(ctx, command, msg).await
+}
+
+/// `/cron pause <id>`：暫停排程，已暫停時恢復
+async fn toggle_pause(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    command.defer_ephemeral(&ctx.http).await?;
+    let id = id_option(opts);
+    let job = find_channel_job(state, command, id).await;
+    if let Some(job) = &job {
+        state.scheduler().set_paused(job.id, !job.paused).await?;
+    }
+    let msg = {
+        let i18n = state.i18n.read().await;
+        match job {
+            Some(job) if job.paused => i18n.get_args("cron_resumed", &[short_id(&job.id)]),
+            Some(job) => i18n.get_args("cron_paused", &[short_id(&job.id)]),
+            None => i18n.get_args("cron_run_not_found", &[id.to_string()]),
+        }
+    };
+    reply(ctx, command, msg).await
+}
+
+/// `/cron run <id>`：立即執行本頻道的排程，方便測試
+async fn run_job(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    command.defer_ephemeral(&ctx.http).await?;
+    let id = id_option(opts);
+    let started = match find_channel_job(state, command, id).await {
+        Some(job) => state.scheduler().run_now(job.id).await,
+        None => false,
+    };
+    let msg = {
+        let i18n = state.i18n.read().await;
+        if started {
+            i18n.get("cron_run_started")
+        } else {
+            i18n.get_args("cron_run_not_found", &[id.to_string()])
+        }
+    };
+    reply(ctx, command, msg).await
 }
 
 pub struct CronListCommand;
@@ -232,9 +463,10 @@ impl SlashCommand for CronListCommand {
         command.defer_ephemeral(&ctx.http).await?;
 
         let channel_id = command.channel_id.get();
-        let jobs = state.cron_manager.get_jobs_for_channel(channel_id).await;
+        let jobs = state.scheduler().get_jobs_for_channel(channel_id).await;
 
-        let i18n = state.i18n.read().await;
+        let channel_i18n = state.channel_i18n(channel_id).await;
+        let i18n = channel_i18n.read().await;
 
         if jobs.is_empty() {
             command
@@ -251,9 +483,29 @@ impl SlashCommand for CronListCommand {
 
         for job in jobs {
             content.push_str(&format!(
-                "- **{}**: `{}`\n  > {}\n",
-                job.cron_expr, job.description, job.prompt
+                "- `{}` **{}**: `{}`",
+                short_id(&job.id),
+                job.cron_expr,
+                job.description,
             ));
+            if let Some(agent_type) = &job.agent_type {
+                content.push_str(&format!(" · `{}`", agent_type));
+            }
+            content.push_str(&format!("\n  > {}\n", job.prompt));
+            if job.paused {
+                content.push_str(&format!("  {}\n", i18n.get("cron_list_paused")));
+            } else if let Some(next) = state.scheduler().next_run(&job).await {
+                content.push_str(&format!(
+                    "  {}\n",
+                    i18n.get_args(
+                        "cron_scheduled",
+                        &[
+                            i18n.format_datetime(&next.with_timezone(&chrono::Local)),
+                            format!("<t:{}:R>", next.timestamp()),
+                        ],
+                    )
+                ));
+            }
 
             options.push(
                 CreateSelectMenuOption::new(
@@ -287,7 +539,12 @@ impl SlashCommand for CronListCommand {
 
 #[cfg(test)]
 mod tests {
-    use super::{build_cron_expr, normalize_freq, prompt_preview};
+    use super::{
+        build_cron_expr, find_job, modal_id, normalize_freq, parse_modal_id, prompt_preview,
+        short_id,
+    };
+    use crate::cron::manager::CronJobInfo;
+    use uuid::Uuid;
 
     #[test]
     fn test_normalize_freq_supports_1_2_3_parts() {
@@ -315,4 +572,42 @@ mod tests {
     fn test_prompt_preview_short_string_unchanged() {
         assert_eq!(prompt_preview("hello", 50), "hello");
     }
+
+    #[test]
+    fn test_modal_id_round_trips_add_options() {
+        assert_eq!(modal_id(None, false), "cron_setup");
+        assert_eq!(parse_modal_id("cron_setup"), (None, false));
+        assert_eq!(parse_modal_id(&modal_id(Some(42), true)), (Some(42), true));
+        assert_eq!(parse_modal_id(&modal_id(None, true)), (None, true));
+    }
+
+    #[test]
+    fn test_find_job_by_unique_prefix() {
+        let job = |id: &str| CronJobInfo {
+            id: Uuid::parse_str(id).unwrap(),
+            scheduler_id: None,
+            channel_id: 1,
+            cron_expr: "0 0 8 * * *".into(),
+            prompt: "p".into(),
+            creator_id: 1,
+            description: "d".into(),
+            guild_id: None,
+            event_id: None,
+            last_result: None,
+            run_as: None,
+            attach_files: false,
+            agent_type: None,
+            paused: false,
+        };
+        let jobs = vec![
+            job("abcd1234-0000-0000-0000-000000000000"),
+            job("abcd9999-0000-0000-0000-000000000000"),
+        ];
+        assert_eq!(short_id(&jobs[0].id), "abcd1234");
+        assert_eq!(find_job(&jobs, "ABCD1234").map(|j| j.id), Some(jobs[0].id));
+        // 前綴不唯一或不存在時不執行
+        assert!(find_job(&jobs, "abcd").is_none());
+        assert!(find_job(&jobs, "ffff").is_none());
+        assert!(find_job(&jobs, " ").is_none());
+    }
 }
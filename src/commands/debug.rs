@@ -0,0 +1,174 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommand,
+    CreateCommandOption, EditInteractionResponse, Permissions,
+};
+
+use crate::agent::{backend_logs, AgentType};
+use crate::i18n::I18n;
+
+const DEFAULT_LINES: usize = 50;
+const MAX_LINES: usize = 200;
+// 保留標頭空間，整則訊息需在 Discord 2000 字內
+const LOG_BLOCK_MAX_CHARS: usize = 1800;
+
+pub struct DebugCommand;
+
+/// 由新到舊保留能放進區塊的行，並避免內容提前結束程式碼區塊
+fn format_log_block(lines: &[String], max_chars: usize) -> String {
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for line in lines.iter().rev() {
+        let line = line.replace("```", "'''");
+        let len = line.chars().count() + 1;
+        if used + len > max_chars {
+            break;
+        }
+        used += len;
+        kept.push(line);
+    }
+    kept.reverse();
+    format!("```\n{}\n```", kept.join("\n"))
+}
+
+fn requested_lines(command: &CommandInteraction) -> usize {
+    command
+        .data
+        .options
+        .first()
+        .and_then(|sub| match &sub.value {
+            CommandDataOptionValue::SubCommand(opts) => opts
+                .iter()
+                .find(|o| o.name == "lines")
+                .and_then(|o| o.value.as_i64()),
+            _ => None,
+        })
+        .map(|n| (n.max(1) as usize).min(MAX_LINES))
+        .unwrap_or(DEFAULT_LINES)
+}
+
+#[async_trait]
+impl SlashCommand for DebugCommand {
+    fn name(&self) -> &'static str {
+        "debug"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_debug_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "backend",
+            i18n.get("cmd_debug_backend_desc"),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "lines",
+                i18n.get("cmd_debug_opt_lines"),
+            )
+            .min_int_value(1)
+            .max_int_value(MAX_LINES as u64),
+        )]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+            let msg = state.i18n.read().await.get("debug_admin_only");
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        }
+
+        let channel_id = command.channel_id.get();
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        let channel_id_str = channel_id.to_string();
+        let agent_type = channel_config.get_agent_type(&channel_id_str);
+        let session_id = channel_config
+            .channels
+            .get(&channel_id_str)
+            .and_then(|e| e.session_id.clone());
+
+        // Pi 每頻道獨立進程；其餘後端可能多頻道共用，依 session ID 過濾
+        let (source, filter) = match agent_type {
+            AgentType::Pi => (Some(backend_logs::pi_source(channel_id)), None),
+            AgentType::Copilot | AgentType::Claude | AgentType::Acp => {
+                (Some(agent_type.to_string()), session_id.clone())
+            }
+            // HTTP 後端沒有由 bot 啟動的進程，也就沒有日誌
+            AgentType::OpenAi => (None, None),
+            AgentType::Kilo | AgentType::Opencode => (
+                state.backends().instance_key(&agent_type, channel_id).await,
+                session_id.clone(),
+            ),
+        };
+
+        let limit = requested_lines(command);
+        let i18n = state.i18n.read().await;
+        let msg = match source {
+            None => i18n.get_args("debug_backend_not_started", &[agent_type.to_string()]),
+            Some(source) => {
+                let mut lines = backend_logs::tail(&source, limit, filter.as_deref());
+                let mut note = String::new();
+                if lines.is_empty() && filter.is_some() {
+                    lines = backend_logs::tail(&source, limit, None);
+                    note = i18n.get("debug_backend_unfiltered");
+                }
+                if lines.is_empty() {
+                    i18n.get_args("debug_backend_empty", &[source])
+                } else {
+                    format!(
+                        "{}{}\n{}",
+                        i18n.get_args("debug_backend_header", &[source, lines.len().to_string()]),
+                        note,
+                        format_log_block(&lines, LOG_BLOCK_MAX_CHARS)
+                    )
+                }
+            }
+        };
+        drop(i18n);
+
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_log_block;
+
+    #[test]
+    fn test_format_log_block_keeps_newest_lines_within_limit() {
+        let lines: Vec<String> = (0..10).map(|i| format!("line-{}", i)).collect();
+        let block = format_log_block(&lines, 21);
+        assert_eq!(block, "```\nline-7\nline-8\nline-9\n```");
+
+        let escaped = format_log_block(&["a ``` b".to_string()], 100);
+        assert_eq!(escaped, "```\na ''' b\n```");
+    }
+}
@@ -0,0 +1,109 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommandOption, EditInteractionResponse,
+};
+use tracing::{info, warn};
+
+use crate::i18n::I18n;
+use crate::transcript::{self, Format};
+
+pub struct ExportCommand;
+
+#[async_trait]
+impl SlashCommand for ExportCommand {
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_export_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::String,
+            "format",
+            i18n.get("cmd_export_opt_format"),
+        )
+        .add_string_choice("Markdown", "markdown")
+        .add_string_choice("JSON", "json")]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let channel_id_u64 = command.channel_id.get();
+        let i18n = state.channel_i18n(channel_id_u64).await;
+        let format = Format::parse(
+            command
+                .data
+                .options
+                .iter()
+                .find(|o| o.name == "format")
+                .and_then(|o| o.value.as_str())
+                .unwrap_or("markdown"),
+        );
+
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        let agent_type = channel_config.get_agent_type(&channel_id_u64.to_string());
+        let (agent, _) = state
+            .sessions()
+            .get_or_create_session(channel_id_u64, agent_type)
+            .await?;
+
+        let entries = match agent.history().await {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    "⚠️ Failed to read history of channel {}: {}",
+                    channel_id_u64, e
+                );
+                let msg = i18n
+                    .read()
+                    .await
+                    .get_args("export_failed", &[e.to_string()]);
+                command
+                    .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                    .await?;
+                return Ok(());
+            }
+        };
+        if entries.is_empty() {
+            let msg = i18n.read().await.get("export_empty");
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        }
+
+        let (name, body) =
+            transcript::render(format, channel_id_u64, agent.agent_type(), &entries)?;
+        info!(
+            "📤 Exported {} message(s) of channel {} as {}",
+            entries.len(),
+            channel_id_u64,
+            name
+        );
+        let msg = i18n
+            .read()
+            .await
+            .get_args("export_done", &[entries.len().to_string()]);
+        crate::delivery::send_interaction_file_within_limit(
+            &ctx.http,
+            command.channel_id,
+            &command.token,
+            EditInteractionResponse::new().content(msg),
+            &name,
+            body.into_bytes(),
+        )
+        .await
+    }
+}
@@ -0,0 +1,77 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, Permissions,
+};
+
+use crate::i18n::I18n;
+
+pub struct FaqCacheCommand;
+
+#[async_trait]
+impl SlashCommand for FaqCacheCommand {
+    fn name(&self) -> &'static str {
+        "faq_cache"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_faq_cache_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "enable",
+            i18n.get("cmd_faq_cache_opt_enable"),
+        )
+        .required(true)]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let enable = command
+            .data
+            .options
+            .iter()
+            .find(|o| o.name == "enable")
+            .and_then(|o| o.value.as_bool())
+            .unwrap_or(false);
+
+        let msg = if enable && !state.faq_cache.is_enabled() {
+            state.i18n.read().await.get("faq_cache_unconfigured")
+        } else {
+            let channel_id = command.channel_id.to_string();
+            crate::commands::agent::ChannelConfig::update(|cfg| {
+                cfg.entry_mut(&channel_id).faq_cache = enable
+            })
+            .await?;
+            state.i18n.read().await.get(if enable {
+                "faq_cache_on"
+            } else {
+                "faq_cache_off"
+            })
+        };
+
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        Ok(())
+    }
+}
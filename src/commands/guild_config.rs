@@ -0,0 +1,210 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommand, CreateCommandOption, Permissions,
+};
+use tracing::info;
+
+use crate::agent::AgentType;
+use crate::commands::agent::ChannelConfig;
+use crate::i18n::I18n;
+
+pub struct GuildConfigCommand;
+
+/// `backend` 選項中代表「沿用全域預設」的值
+const GLOBAL_DEFAULT: &str = "default";
+
+fn sub_option<'a>(opts: &'a [CommandDataOption], name: &str) -> Option<&'a CommandDataOptionValue> {
+    opts.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+#[async_trait]
+impl SlashCommand for GuildConfigCommand {
+    fn name(&self) -> &'static str {
+        "guild_config"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_guild_config_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        let backend = CreateCommandOption::new(
+            CommandOptionType::String,
+            "backend",
+            i18n.get("cmd_guild_config_opt_backend"),
+        )
+        .required(true)
+        .add_string_choice(i18n.get("guild_config_backend_global"), GLOBAL_DEFAULT);
+        let backend = crate::agent::enabled_backends()
+            .into_iter()
+            .fold(backend, |opt, t| {
+                opt.add_string_choice(i18n.get(t.choice_key()), t.to_string())
+            });
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "show",
+                i18n.get("cmd_guild_config_show_desc"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "authorize",
+                i18n.get("cmd_guild_config_authorize_desc"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "revoke",
+                i18n.get("cmd_guild_config_revoke_desc"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "backend",
+                i18n.get("cmd_guild_config_backend_desc"),
+            )
+            .add_sub_option(backend),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "mention_only",
+                i18n.get("cmd_guild_config_mention_only_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "enable",
+                    i18n.get("cmd_guild_config_opt_enable"),
+                )
+                .required(true),
+            ),
+        ]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+        let Some(guild_id) = command.guild_id else {
+            let msg = channel_i18n.read().await.get("guild_config_guild_only");
+            return reply(ctx, command, msg).await;
+        };
+        if !super::is_admin(command.member.as_deref(), true) {
+            let msg = channel_i18n.read().await.get("guild_config_admin_only");
+            return reply(ctx, command, msg).await;
+        }
+        let Some(sub) = command.data.options.first() else {
+            return Ok(());
+        };
+        let opts = match &sub.value {
+            CommandDataOptionValue::SubCommand(opts) => opts.as_slice(),
+            _ => &[],
+        };
+        let guild = guild_id.to_string();
+        let authorized = state.auth().get_guild_mention_only(&guild);
+
+        let msg = match sub.name.as_str() {
+            "authorize" => {
+                if authorized.is_some() {
+                    channel_i18n
+                        .read()
+                        .await
+                        .get("guild_config_already_authorized")
+                } else {
+                    // 與頻道授權相同，由 bot 營運者在主機上以 CLI 兌換權杖
+                    let token = state.auth().create_token("guild", &guild)?;
+                    channel_i18n
+                        .read()
+                        .await
+                        .get_args("guild_config_authorize", &[token])
+                }
+            }
+            "revoke" => {
+                let key = if state.auth().remove_guild(&guild)? {
+                    info!("🏰 Guild {} authorization revoked", guild);
+                    "guild_config_revoked"
+                } else {
+                    "guild_config_not_authorized"
+                };
+                channel_i18n.read().await.get(key)
+            }
+            "mention_only" => {
+                let enable = sub_option(opts, "enable")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+                if authorized.is_none() {
+                    channel_i18n.read().await.get("guild_config_not_authorized")
+                } else {
+                    state.auth().set_guild_mention_only(&guild, enable)?;
+                    channel_i18n
+                        .read()
+                        .await
+                        .get_args("guild_config_mention_only_set", &[enable.to_string()])
+                }
+            }
+            "backend" => {
+                let selected = sub_option(opts, "backend")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(GLOBAL_DEFAULT);
+                let agent_type = match selected {
+                    GLOBAL_DEFAULT => None,
+                    name => Some(name.parse::<AgentType>()?),
+                };
+                if let Some(t) = agent_type
+                    .as_ref()
+                    .filter(|t| !crate::agent::is_backend_enabled(t))
+                {
+                    let msg = channel_i18n
+                        .read()
+                        .await
+                        .get_args("backend_disabled", &[t.to_string()]);
+                    return reply(ctx, command, msg).await;
+                }
+                ChannelConfig::update(|cfg| {
+                    cfg.guilds.entry(guild.clone()).or_default().agent_type = agent_type.clone()
+                })
+                .await?;
+                let i18n = channel_i18n.read().await;
+                let backend = agent_type
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| i18n.get("guild_config_backend_global"));
+                i18n.get_args("guild_config_backend_set", &[backend])
+            }
+            _ => {
+                let channel_config = ChannelConfig::load().await.unwrap_or_default();
+                let i18n = channel_i18n.read().await;
+                let backend = channel_config
+                    .guilds
+                    .get(&guild)
+                    .and_then(|g| g.agent_type.as_ref())
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| i18n.get("guild_config_backend_global"));
+                i18n.get_args(
+                    "guild_config_status",
+                    &[
+                        authorized.is_some().to_string(),
+                        authorized
+                            .map(|m| m.to_string())
+                            .unwrap_or_else(|| "-".to_string()),
+                        backend,
+                    ],
+                )
+            }
+        };
+        reply(ctx, command, msg).await
+    }
+}
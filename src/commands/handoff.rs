@@ -0,0 +1,217 @@
+use super::long_reply::LongReply;
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelId, ChannelType, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateAttachment, CreateCommandOption, CreateEmbed, CreateMessage, GetMessages, Message,
+};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::i18n::I18n;
+use crate::memory::HandoffNote;
+
+pub struct HandoffCommand;
+
+/// 參與者清單最多列出的人數
+const MAX_PARTICIPANTS: usize = 10;
+/// 掃描頻道歷史的訊息數
+const HISTORY_LIMIT: u8 = 100;
+/// Embed 描述的長度上限 (字元)，超過時改附完整檔案
+const EMBED_DESCRIPTION_LIMIT: usize = 4000;
+
+/// 依發言數排序非機器人的發言者，同數時依先出現者
+fn rank_participants(messages: &[Message]) -> Vec<String> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let mut index: HashMap<u64, usize> = HashMap::new();
+    for msg in messages.iter().filter(|m| !m.author.bot) {
+        let i = *index.entry(msg.author.id.get()).or_insert_with(|| {
+            counts.push((msg.author.display_name().to_string(), 0));
+            counts.len() - 1
+        });
+        counts[i].1 += 1;
+    }
+    counts.sort_by_key(|c| std::cmp::Reverse(c.1));
+    counts
+        .into_iter()
+        .take(MAX_PARTICIPANTS)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// 過長的筆記在 embed 中截斷，完整內容另附檔案
+fn embed_description(note: &str) -> (String, bool) {
+    if note.chars().count() <= EMBED_DESCRIPTION_LIMIT {
+        return (note.to_string(), false);
+    }
+    let head: String = note.chars().take(EMBED_DESCRIPTION_LIMIT - 1).collect();
+    (head + "…", true)
+}
+
+#[async_trait]
+impl SlashCommand for HandoffCommand {
+    fn name(&self) -> &'static str {
+        "handoff"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_handoff_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::Channel,
+            "to",
+            i18n.get("cmd_handoff_opt_to"),
+        )
+        .channel_types(vec![
+            ChannelType::Text,
+            ChannelType::PublicThread,
+            ChannelType::PrivateThread,
+        ])]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let source = command.channel_id;
+        let channel_id_u64 = source.get();
+        let user_id = command.user.id.get();
+        let i18n = state.channel_i18n(channel_id_u64).await;
+        let target: ChannelId = command
+            .data
+            .options
+            .iter()
+            .find_map(|o| match (o.name.as_str(), &o.value) {
+                ("to", CommandDataOptionValue::Channel(id)) => Some(*id),
+                _ => None,
+            })
+            .unwrap_or(source);
+
+        // 張貼到其他頻道時比照 /pipe 檢查授權
+        if target != source {
+            let (authorized, _) = state
+                .auth()
+                .is_authorized_with_thread(ctx, &user_id.to_string(), target, command.guild_id)
+                .await;
+            if !authorized {
+                let msg = i18n
+                    .read()
+                    .await
+                    .get_args("pipe_target_not_authorized", &[target.to_string()]);
+                return reply(ctx, command, msg).await;
+            }
+        }
+
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        let agent_type = channel_config.get_agent_type(&channel_id_u64.to_string());
+        let (agent, _) = state
+            .sessions()
+            .get_or_create_session(channel_id_u64, agent_type)
+            .await?;
+
+        // 與 /compact 相同：執行中的回合會把筆記顯示在使用者的訊息裡
+        if state
+            .active_renders
+            .lock()
+            .await
+            .contains_key(&channel_id_u64)
+        {
+            let msg = i18n.read().await.get("handoff_busy");
+            return reply(ctx, command, msg).await;
+        }
+
+        let mut reply_msg = LongReply::new(ctx, command);
+        let progress_i18n = i18n.read().await.get("long_op_progress");
+        let note = reply_msg
+            .run(crate::memory::handoff(&*agent), |secs| {
+                progress_i18n.replace("{0}", &secs.to_string())
+            })
+            .await?;
+
+        let participants = match source
+            .messages(&ctx.http, GetMessages::new().limit(HISTORY_LIMIT))
+            .await
+        {
+            Ok(messages) => rank_participants(&messages),
+            Err(e) => {
+                warn!("⚠️ Failed to read history of channel {}: {}", source, e);
+                Vec::new()
+            }
+        };
+
+        let handoff = HandoffNote {
+            note: note.clone(),
+            participants: participants.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let mut memory = crate::memory::load(channel_id_u64)
+            .await
+            .unwrap_or_default();
+        memory.handoff = Some(handoff);
+        if let Err(e) = crate::memory::save(channel_id_u64, &memory).await {
+            warn!(
+                "⚠️ Failed to save hand-off note for channel {}: {}",
+                channel_id_u64, e
+            );
+        }
+
+        let (title, participants_label) = {
+            let i18n = i18n.read().await;
+            (
+                i18n.get_args("handoff_title", &[format!("<#{}>", source)]),
+                i18n.get("handoff_participants"),
+            )
+        };
+        let (description, truncated) = embed_description(&note);
+        let mut embed = CreateEmbed::new()
+            .title(title)
+            .description(description)
+            .color(0x5865F2);
+        if !participants.is_empty() {
+            embed = embed.field(participants_label, participants.join(", "), false);
+        }
+        let mut message = CreateMessage::new()
+            .embed(embed)
+            .allowed_mentions(crate::mentions::suppressed());
+        if truncated {
+            message = message.add_file(CreateAttachment::bytes(note.into_bytes(), "handoff.md"));
+        }
+        let posted = target.send_message(&ctx.http, message).await?;
+        info!(
+            "🤝 Posted hand-off note of channel {} to {} for {}",
+            source, target, command.user.name
+        );
+
+        let msg = i18n
+            .read()
+            .await
+            .get_args("handoff_posted", &[posted.link()]);
+        reply_msg.update(msg).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_description_truncates_long_notes() {
+        let (short, truncated) = embed_description("## Current goal\nship");
+        assert_eq!(short, "## Current goal\nship");
+        assert!(!truncated);
+
+        let (long, truncated) = embed_description(&"筆".repeat(5000));
+        assert!(truncated);
+        assert_eq!(long.chars().count(), EMBED_DESCRIPTION_LIMIT);
+        assert!(long.ends_with('…'));
+    }
+}
@@ -0,0 +1,104 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, Permissions,
+};
+
+use crate::agent::manager::InstanceStatus;
+use crate::i18n::I18n;
+
+pub struct HealthCommand;
+
+/// 每個後端實例一行：狀態、名稱與實際使用的埠；自動改埠時附上原本設定的埠
+fn format_instances(i18n: &I18n, instances: &[InstanceStatus]) -> String {
+    if instances.is_empty() {
+        return i18n.get("health_no_backends");
+    }
+    let mut out = i18n.get("health_header");
+    for instance in instances {
+        let mark = if instance.running { "✅" } else { "❌" };
+        out.push_str(&format!(
+            "\n{} {}",
+            mark,
+            i18n.get_args(
+                "health_instance",
+                &[instance.key.clone(), instance.port.to_string()]
+            )
+        ));
+        if let Some(configured) = instance.configured_port {
+            out.push_str(&i18n.get_args("health_port_reassigned", &[configured.to_string()]));
+        }
+    }
+    out
+}
+
+#[async_trait]
+impl SlashCommand for HealthCommand {
+    fn name(&self) -> &'static str {
+        "health"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_health_desc")
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let i18n = state.i18n.read().await;
+        let msg = if super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+            format_instances(&i18n, &state.backends().instances().await)
+        } else {
+            i18n.get("health_admin_only")
+        };
+        drop(i18n);
+
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_instances_reports_reassigned_port() {
+        let i18n = I18n::new("en");
+        assert_eq!(format_instances(&i18n, &[]), i18n.get("health_no_backends"));
+
+        let text = format_instances(
+            &i18n,
+            &[
+                InstanceStatus {
+                    key: "kilo#0".to_string(),
+                    port: 41000,
+                    configured_port: None,
+                    running: true,
+                },
+                InstanceStatus {
+                    key: "opencode#0".to_string(),
+                    port: 45123,
+                    configured_port: Some(41000),
+                    running: false,
+                },
+            ],
+        );
+        assert!(text.contains("✅ `kilo#0` — port 41000\n"));
+        assert!(text.contains("❌ `opencode#0` — port 45123"));
+        assert!(text.ends_with("41000 was in use)"));
+    }
+}
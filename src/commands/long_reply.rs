@@ -0,0 +1,122 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateMessage, EditInteractionResponse, EditMessage, Message,
+};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// Interaction token 15 分鐘後失效，提早一分鐘改用一般訊息
+const TOKEN_SAFE_AGE: Duration = Duration::from_secs(14 * 60);
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(60);
+
+fn should_migrate(age: Duration) -> bool {
+    age >= TOKEN_SAFE_AGE
+}
+
+/// 50027 Invalid Webhook Token、10015 Unknown Webhook：token 已過期
+fn is_expired_token_code(code: isize) -> bool {
+    matches!(code, 50027 | 10015)
+}
+
+/// 長時間指令的回覆：token 快過期時把狀態搬到頻道訊息，之後持續更新該訊息
+pub struct LongReply<'a> {
+    ctx: &'a Context,
+    command: &'a CommandInteraction,
+    started: Instant,
+    migrated: Option<Message>,
+}
+
+impl<'a> LongReply<'a> {
+    /// 應在 defer 之後立即建立，以 defer 時間作為 token 起算點
+    pub fn new(ctx: &'a Context, command: &'a CommandInteraction) -> Self {
+        Self {
+            ctx,
+            command,
+            started: Instant::now(),
+            migrated: None,
+        }
+    }
+
+    async fn migrate(&mut self, content: &str) -> anyhow::Result<()> {
+        let msg = self
+            .command
+            .channel_id
+            .send_message(&self.ctx.http, CreateMessage::new().content(content))
+            .await?;
+        info!(
+            "🔁 Moved /{} status to channel message {} before token expiry",
+            self.command.data.name, msg.id
+        );
+        self.migrated = Some(msg);
+        Ok(())
+    }
+
+    pub async fn update(&mut self, content: String) -> anyhow::Result<()> {
+        if let Some(msg) = self.migrated.as_mut() {
+            msg.edit(&self.ctx.http, EditMessage::new().content(content))
+                .await?;
+            return Ok(());
+        }
+        if should_migrate(self.started.elapsed()) {
+            return self.migrate(&content).await;
+        }
+        match self
+            .command
+            .edit_response(
+                &self.ctx.http,
+                EditInteractionResponse::new().content(content.clone()),
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e)
+                if crate::delivery::discord_error_code(&e).is_some_and(is_expired_token_code) =>
+            {
+                warn!("⚠️ Interaction token expired early: {}", e);
+                self.migrate(&content).await
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 執行耗時操作，期間定期以 `progress(經過秒數)` 更新狀態
+    pub async fn run<F, T>(&mut self, fut: F, progress: impl Fn(u64) -> String) -> T
+    where
+        F: Future<Output = T>,
+    {
+        tokio::pin!(fut);
+        let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                out = &mut fut => return out,
+                _ = ticker.tick() => {
+                    let text = progress(self.started.elapsed().as_secs());
+                    if let Err(e) = self.update(text).await {
+                        warn!("⚠️ Failed to update long-running status: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_expired_token_code, should_migrate, TOKEN_SAFE_AGE};
+    use std::time::Duration;
+
+    #[test]
+    fn test_should_migrate_before_token_expiry() {
+        assert!(!should_migrate(Duration::from_secs(60)));
+        assert!(should_migrate(TOKEN_SAFE_AGE));
+        assert!(TOKEN_SAFE_AGE < Duration::from_secs(15 * 60));
+    }
+
+    #[test]
+    fn test_is_expired_token_code() {
+        assert!(is_expired_token_code(50027));
+        assert!(is_expired_token_code(10015));
+        assert!(!is_expired_token_code(50035));
+    }
+}
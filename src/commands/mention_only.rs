@@ -42,7 +42,7 @@ impl SlashCommand for MentionOnlyCommand {
             .unwrap_or(true);
 
         let ch_id = command.channel_id.to_string();
-        let auth = state.auth.clone();
+        let auth = state.auth().clone();
 
         let i18n = state.i18n.read().await;
         let msg = match auth.set_mention_only(&ch_id, enable) {
@@ -0,0 +1,215 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelType, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context, CreateCommand, CreateCommandOption, Permissions,
+};
+
+use crate::i18n::I18n;
+use crate::mirror::{is_valid_webhook_url, MirrorConfig};
+
+pub struct MirrorCommand;
+
+/// `/mirror set` 的輸入；未提供的欄位沿用既有設定
+#[derive(Debug, Default, PartialEq)]
+struct MirrorUpdate {
+    channel_id: Option<u64>,
+    webhook_url: Option<String>,
+    template: Option<String>,
+}
+
+fn parse_update(opts: &[CommandDataOption]) -> MirrorUpdate {
+    let mut update = MirrorUpdate::default();
+    for opt in opts {
+        match (opt.name.as_str(), &opt.value) {
+            ("channel", CommandDataOptionValue::Channel(id)) => update.channel_id = Some(id.get()),
+            ("webhook", CommandDataOptionValue::String(s)) => {
+                update.webhook_url = Some(s.trim().to_string())
+            }
+            ("template", CommandDataOptionValue::String(s)) => {
+                // Discord 指令參數無法輸入換行，允許以 \n 表示
+                update.template = Some(s.replace("\\n", "\n"))
+            }
+            _ => {}
+        }
+    }
+    update
+}
+
+fn apply_update(current: &MirrorConfig, update: MirrorUpdate) -> MirrorConfig {
+    MirrorConfig {
+        enabled: true,
+        channel_id: update.channel_id.or(current.channel_id),
+        webhook_url: update.webhook_url.or_else(|| current.webhook_url.clone()),
+        template: update.template.or_else(|| current.template.clone()),
+    }
+}
+
+fn describe(i18n: &I18n, cfg: &MirrorConfig) -> String {
+    let none = i18n.get("mirror_none");
+    i18n.get_args(
+        "mirror_status",
+        &[
+            i18n.get(if cfg.enabled {
+                "mirror_state_on"
+            } else {
+                "mirror_state_off"
+            }),
+            cfg.channel_id
+                .map(|id| format!("<#{}>", id))
+                .unwrap_or_else(|| none.clone()),
+            // webhook URL 內含權杖，只顯示是否已設定
+            if cfg.webhook_url.is_some() {
+                "✅".to_string()
+            } else {
+                none.clone()
+            },
+            cfg.template.clone().unwrap_or(none),
+        ],
+    )
+}
+
+#[async_trait]
+impl SlashCommand for MirrorCommand {
+    fn name(&self) -> &'static str {
+        "mirror"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_mirror_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                i18n.get("cmd_mirror_set_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "channel",
+                    i18n.get("cmd_mirror_opt_channel"),
+                )
+                .channel_types(vec![ChannelType::Text, ChannelType::News]),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "webhook",
+                i18n.get("cmd_mirror_opt_webhook"),
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "template",
+                i18n.get("cmd_mirror_opt_template"),
+            )),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "off",
+                i18n.get("cmd_mirror_off_desc"),
+            ),
+        ]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+            let msg = state.i18n.read().await.get("mirror_admin_only");
+            return reply(ctx, command, msg).await;
+        }
+
+        let Some(sub) = command.data.options.first() else {
+            return Ok(());
+        };
+        let channel_id = command.channel_id.to_string();
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        let current = channel_config
+            .channels
+            .get(&channel_id)
+            .map(|e| e.mirror.clone())
+            .unwrap_or_default();
+
+        let updated = match (sub.name.as_str(), &sub.value) {
+            ("set", CommandDataOptionValue::SubCommand(opts)) => {
+                let update = parse_update(opts);
+                if update
+                    .webhook_url
+                    .as_deref()
+                    .is_some_and(|url| !is_valid_webhook_url(url))
+                {
+                    let msg = state.i18n.read().await.get("mirror_invalid_webhook");
+                    return reply(ctx, command, msg).await;
+                }
+                if update.channel_id == Some(command.channel_id.get()) {
+                    let msg = state.i18n.read().await.get("mirror_same_channel");
+                    return reply(ctx, command, msg).await;
+                }
+                let updated = apply_update(&current, update);
+                if !updated.is_active() {
+                    let msg = state.i18n.read().await.get("mirror_no_target");
+                    return reply(ctx, command, msg).await;
+                }
+                updated
+            }
+            ("off", _) => MirrorConfig {
+                enabled: false,
+                ..current
+            },
+            _ => return Ok(()),
+        };
+
+        crate::commands::agent::ChannelConfig::update(|cfg| {
+            cfg.entry_mut(&channel_id).mirror = updated.clone()
+        })
+        .await?;
+
+        let msg = describe(&*state.i18n.read().await, &updated);
+        reply(ctx, command, msg).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_update, MirrorUpdate};
+    use crate::mirror::MirrorConfig;
+
+    #[test]
+    fn test_apply_update_keeps_existing_targets_and_enables() {
+        let current = MirrorConfig {
+            enabled: false,
+            webhook_url: Some("https://example.com/hook".to_string()),
+            channel_id: None,
+            template: Some("{answer}".to_string()),
+        };
+        let updated = apply_update(
+            &current,
+            MirrorUpdate {
+                channel_id: Some(9),
+                ..Default::default()
+            },
+        );
+        assert!(updated.enabled);
+        assert_eq!(updated.channel_id, Some(9));
+        assert_eq!(updated.webhook_url, current.webhook_url);
+        assert_eq!(updated.template, current.template);
+    }
+}
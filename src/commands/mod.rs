@@ -1,19 +1,62 @@
 use async_trait::async_trait;
-use serenity::all::{CommandInteraction, Context, CreateCommand, CreateCommandOption};
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, CreateCommandOption, EditInteractionResponse,
+    Member,
+};
 
 use crate::i18n::I18n;
 
 pub mod abort;
+pub mod admin;
 pub mod agent;
 pub mod clear;
 pub mod compact;
 pub mod config;
 pub mod cron;
+pub mod debug;
+pub mod export;
+pub mod faq;
+pub mod guild_config;
+pub mod handoff;
+pub mod health;
 pub mod language;
+pub mod long_reply;
 pub mod mention_only;
+pub mod mirror;
 pub mod model;
+pub mod pipe;
+pub mod prefs;
+pub mod provider;
+pub mod quick;
+pub mod quiet;
+pub mod reply_language;
+pub mod session;
 pub mod skill;
+pub mod stats;
+pub mod status;
 pub mod thinking;
+pub mod thread;
+pub mod tools;
+pub mod usage;
+pub mod voice;
+
+/// 私訊沒有伺服器權限可查，視為已授權的擁有者
+pub fn is_admin(member: Option<&Member>, in_guild: bool) -> bool {
+    if !in_guild {
+        return true;
+    }
+    member
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.administrator())
+}
+
+/// 以文字取代延遲回應的內容
+pub async fn reply(ctx: &Context, command: &CommandInteraction, msg: String) -> anyhow::Result<()> {
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        .await?;
+    Ok(())
+}
 
 #[async_trait]
 pub trait SlashCommand: Send + Sync {
@@ -45,14 +88,35 @@ pub fn get_all_commands() -> Vec<Box<dyn SlashCommand>> {
         Box::new(model::ModelCommand),
         Box::new(thinking::ThinkingCommand),
         Box::new(compact::CompactCommand),
+        Box::new(handoff::HandoffCommand),
+        Box::new(export::ExportCommand),
         Box::new(config::ConfigCommand),
+        Box::new(guild_config::GuildConfigCommand),
         Box::new(clear::ClearCommand),
         Box::new(abort::AbortCommand),
         Box::new(skill::SkillCommand),
         Box::new(mention_only::MentionOnlyCommand),
         Box::new(language::LanguageCommand),
+        Box::new(prefs::PrefsCommand),
+        Box::new(provider::ProviderCommand),
+        Box::new(quick::QuickCommand),
+        Box::new(pipe::PipeCommand),
+        Box::new(tools::ToolsCommand),
+        Box::new(reply_language::ReplyLanguageCommand),
+        Box::new(debug::DebugCommand),
+        Box::new(health::HealthCommand),
+        Box::new(status::StatusCommand),
+        Box::new(stats::StatsCommand),
+        Box::new(session::SessionCommand),
+        Box::new(thread::ThreadCommand),
+        Box::new(mirror::MirrorCommand),
+        Box::new(quiet::QuietCommand),
+        Box::new(faq::FaqCacheCommand),
+        Box::new(voice::VoiceCommand),
+        Box::new(usage::UsageCommand),
         Box::new(cron::CronCommand),
         Box::new(cron::CronListCommand),
+        Box::new(admin::AdminCommand),
     ]
 }
 
@@ -72,4 +136,10 @@ mod tests {
             let _create = cmd.create_command(&i18n);
         }
     }
+
+    #[test]
+    fn test_is_admin_requires_guild_permissions() {
+        assert!(is_admin(None, false));
+        assert!(!is_admin(None, true));
+    }
 }
@@ -1,17 +1,60 @@
 use super::SlashCommand;
 use async_trait::async_trait;
 use serenity::all::{
-    CommandInteraction, Context, CreateActionRow, CreateSelectMenu, CreateSelectMenuKind,
-    CreateSelectMenuOption, EditInteractionResponse,
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, Context,
+    CreateActionRow, CreateAutocompleteResponse, CreateButton, CreateCommandOption,
+    CreateInteractionResponse, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+    EditInteractionResponse,
 };
 use std::sync::Arc;
 
-use crate::agent::AiAgent;
+use crate::agent::{AiAgent, ModelInfo};
 use tracing::{error, info};
 
 pub struct ModelCommand;
 const MAX_SELECT_OPTIONS: usize = 125;
 const SELECT_CHUNK_SIZE: usize = 25;
+// Discord 按鈕標籤上限 80 字
+const BUTTON_LABEL_MAX_CHARS: usize = 80;
+// Discord 選單選項說明上限 100 字
+const OPTION_DESCRIPTION_MAX_CHARS: usize = 100;
+// Discord 自動完成最多 25 個選項，名稱與值各限 100 字
+const AUTOCOMPLETE_MAX_CHOICES: usize = 25;
+const AUTOCOMPLETE_MAX_CHARS: usize = 100;
+
+fn format_context_length(tokens: u64) -> String {
+    if tokens >= 1_000_000 && tokens.is_multiple_of(1_000_000) {
+        format!("{}M", tokens / 1_000_000)
+    } else if tokens >= 1_000 {
+        format!("{}K", tokens / 1_000)
+    } else {
+        tokens.to_string()
+    }
+}
+
+/// 選單選項說明：provider 加上後端有提供的 context 長度、圖片支援與價格
+fn build_model_description(i18n: &crate::i18n::I18n, model: &ModelInfo) -> String {
+    let mut parts =
+        vec![i18n.get_args("model_provider_desc", std::slice::from_ref(&model.provider))];
+    if let Some(context) = model.context_length {
+        parts.push(format!("{} ctx", format_context_length(context)));
+    }
+    if model.supports_vision == Some(true) {
+        parts.push("🖼️".to_string());
+    }
+    if let Some(pricing) = model.pricing {
+        parts.push(format!(
+            "${}/${} per 1M",
+            i18n.format_decimal(pricing.input_per_mtok, 2),
+            i18n.format_decimal(pricing.output_per_mtok, 2)
+        ));
+    }
+    parts
+        .join(" · ")
+        .chars()
+        .take(OPTION_DESCRIPTION_MAX_CHARS)
+        .collect()
+}
 
 fn capped_model_count(models_len: usize) -> usize {
     models_len.min(MAX_SELECT_OPTIONS)
@@ -25,6 +68,42 @@ fn parse_model_value(composite: &str) -> Option<(&str, &str)> {
     composite.split_once('|')
 }
 
+/// 自動完成的候選：以空白分隔的每個關鍵字都要出現在 label、id 或 provider 中 (不分大小寫)
+fn matching_models<'a>(models: &'a [ModelInfo], query: &str) -> Vec<&'a ModelInfo> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    models
+        .iter()
+        .filter(|m| build_model_value(&m.provider, &m.id).chars().count() <= AUTOCOMPLETE_MAX_CHARS)
+        .filter(|m| {
+            let haystack = format!("{} {} {}", m.label, m.id, m.provider).to_lowercase();
+            terms.iter().all(|t| haystack.contains(t.as_str()))
+        })
+        .take(AUTOCOMPLETE_MAX_CHOICES)
+        .collect()
+}
+
+/// `model` 參數的值：自動完成送來的 `provider|id`，或手動輸入、唯一對應到 id 或 label 的名稱
+fn resolve_model_input(models: &[ModelInfo], input: &str) -> Option<String> {
+    let input = input.trim();
+    if parse_model_value(input).is_some() {
+        return Some(input.to_string());
+    }
+    let mut found = models
+        .iter()
+        .filter(|m| m.id.eq_ignore_ascii_case(input) || m.label.eq_ignore_ascii_case(input));
+    match (found.next(), found.next()) {
+        (Some(m), None) => Some(build_model_value(&m.provider, &m.id)),
+        _ => None,
+    }
+}
+
+fn build_replay_label(i18n: &crate::i18n::I18n, model: &str) -> String {
+    i18n.get_args("model_replay_button", &[model.to_string()])
+        .chars()
+        .take(BUTTON_LABEL_MAX_CHARS)
+        .collect()
+}
+
 #[async_trait]
 impl SlashCommand for ModelCommand {
     fn name(&self) -> &'static str {
@@ -35,9 +114,15 @@ impl SlashCommand for ModelCommand {
         i18n.get("cmd_model_desc")
     }
 
-    // 不使用 options，改用 Select Menu
-    fn options(&self, _i18n: &crate::i18n::I18n) -> Vec<serenity::all::CreateCommandOption> {
-        vec![]
+    // 未指定 model 時顯示 Select Menu；指定時以自動完成搜尋全部模型
+    fn options(&self, i18n: &crate::i18n::I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::String,
+            "model",
+            i18n.get("cmd_model_option_desc"),
+        )
+        .required(false)
+        .set_autocomplete(true)]
     }
 
     async fn execute(
@@ -56,8 +141,8 @@ impl SlashCommand for ModelCommand {
         let agent_type = channel_config.get_agent_type(&channel_id_str);
 
         let (agent, _) = state
-            .session_manager
-            .get_or_create_session(command.channel_id.get(), agent_type, &state.backend_manager)
+            .sessions()
+            .get_or_create_session(command.channel_id.get(), agent_type)
             .await?;
 
         let i18n = state.i18n.read().await;
@@ -81,6 +166,30 @@ impl SlashCommand for ModelCommand {
             }
         };
 
+        let requested = command
+            .data
+            .options
+            .iter()
+            .find(|o| o.name == "model")
+            .and_then(|o| o.value.as_str());
+        if let Some(requested) = requested {
+            let response = match resolve_model_input(&models, requested) {
+                Some(composite) => {
+                    switch_model(
+                        agent.as_ref(),
+                        state,
+                        command.channel_id.get(),
+                        &composite,
+                        &i18n,
+                    )
+                    .await
+                }
+                None => EditInteractionResponse::new().content(i18n.get("model_invalid")),
+            };
+            command.edit_response(&ctx.http, response).await?;
+            return Ok(());
+        }
+
         if models.is_empty() {
             command
                 .edit_response(
@@ -105,7 +214,7 @@ impl SlashCommand for ModelCommand {
                     // 使用 | 作為定界符，避免與 ID 內部的 / 衝突
                     let value = build_model_value(&m.provider, &m.id);
                     CreateSelectMenuOption::new(&m.label, value)
-                        .description(i18n.get_args("model_provider_desc", &[m.provider.clone()]))
+                        .description(build_model_description(&i18n, m))
                 })
                 .collect();
 
@@ -156,53 +265,247 @@ pub async fn handle_model_select(
         &interaction.data.kind
     {
         if let Some(composite_id) = values.first() {
-            // 使用 | 分解
-            if let Some((provider, model)) = parse_model_value(composite_id) {
-                match agent.set_model(provider, model).await {
-                    Ok(_) => {
-                        interaction
-                            .edit_response(
-                                &ctx.http,
-                                EditInteractionResponse::new()
-                                    .content(
-                                        i18n.get_args(
-                                            "model_switched",
-                                            &[composite_id.to_string()],
-                                        ),
-                                    )
-                                    .components(vec![]), // 移除 Select Menu
-                            )
-                            .await?;
-                    }
-                    Err(e) => {
-                        interaction
-                            .edit_response(
-                                &ctx.http,
-                                EditInteractionResponse::new()
-                                    .content(i18n.get_args("model_failed", &[e.to_string()]))
-                                    .components(vec![]),
-                            )
-                            .await?;
-                    }
-                }
+            let response = switch_model(
+                agent.as_ref(),
+                state,
+                interaction.channel_id.get(),
+                composite_id,
+                &i18n,
+            )
+            .await;
+            interaction.edit_response(&ctx.http, response).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 後端回報的模型是否為 provider/id 指定的模型
+fn reports_model(reported: &str, provider: &str, model_id: &str) -> bool {
+    reported == model_id || reported == format!("{}/{}", provider, model_id)
+}
+
+/// 切換前後的模型確認訊息；後端回報的模型不是指定的模型時提示切換未生效
+fn model_change_message(
+    i18n: &crate::i18n::I18n,
+    before: Option<String>,
+    after: Option<String>,
+    provider: &str,
+    model_id: &str,
+) -> String {
+    let requested = format!("{}/{}", provider, model_id);
+    match after {
+        Some(after) if !reports_model(&after, provider, model_id) => {
+            i18n.get_args("model_change_ignored", &[requested, after])
+        }
+        after => i18n.get_args(
+            "model_changed",
+            &[
+                before.unwrap_or_else(|| i18n.get("state_unknown")),
+                after.unwrap_or(requested),
+            ],
+        ),
+    }
+}
+
+/// 切換模型並回傳「前 → 後」的確認訊息；`/model` 與文字指令共用
+pub async fn change_model(
+    agent: &dyn AiAgent,
+    i18n: &crate::i18n::I18n,
+    provider: &str,
+    model_id: &str,
+) -> anyhow::Result<String> {
+    let before = agent.get_state().await.ok().and_then(|s| s.model);
+    agent.set_model(provider, model_id).await?;
+    let after = agent.get_state().await.ok().and_then(|s| s.model);
+    Ok(model_change_message(
+        i18n, before, after, provider, model_id,
+    ))
+}
+
+/// 切換到 `provider|id` 指定的模型，回傳要顯示的結果與重新回答按鈕
+async fn switch_model(
+    agent: &dyn AiAgent,
+    state: &crate::AppState,
+    channel_id: u64,
+    composite_id: &str,
+    i18n: &crate::i18n::I18n,
+) -> EditInteractionResponse {
+    // 使用 | 分解
+    let Some((provider, model)) = parse_model_value(composite_id) else {
+        return EditInteractionResponse::new()
+            .content(i18n.get("model_invalid"))
+            .components(vec![]);
+    };
+    match change_model(agent, i18n, provider, model).await {
+        Ok(confirmation) => {
+            // 若此頻道有上一輪提問，提供以新模型重新回答的按鈕
+            let has_last_turn = state.last_turns.lock().await.contains_key(&channel_id);
+            let components = if has_last_turn {
+                vec![CreateActionRow::Buttons(vec![CreateButton::new(
+                    "model_replay",
+                )
+                .label(build_replay_label(i18n, model))
+                .style(ButtonStyle::Primary)])]
             } else {
-                interaction
-                    .edit_response(
-                        &ctx.http,
-                        EditInteractionResponse::new()
-                            .content(i18n.get("model_invalid"))
-                            .components(vec![]),
-                    )
-                    .await?;
+                vec![] // 移除 Select Menu
+            };
+            EditInteractionResponse::new()
+                .content(confirmation)
+                .components(components)
+        }
+        Err(e) => EditInteractionResponse::new()
+            .content(i18n.get_args("model_failed", &[e.to_string()]))
+            .components(vec![]),
+    }
+}
+
+/// `model` 參數的自動完成：依輸入過濾目前頻道代理的模型列表
+pub async fn handle_autocomplete(
+    ctx: &Context,
+    interaction: &CommandInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    let query = interaction
+        .data
+        .autocomplete()
+        .map(|o| o.value.to_string())
+        .unwrap_or_default();
+    let (is_auth, _) = state
+        .auth()
+        .is_authorized_with_thread(
+            ctx,
+            &interaction.user.id.to_string(),
+            interaction.channel_id,
+            interaction.guild_id,
+        )
+        .await;
+    let mut response = CreateAutocompleteResponse::new();
+    if is_auth {
+        let agent_type = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default()
+            .get_agent_type(&interaction.channel_id.to_string());
+        let models = match state
+            .sessions()
+            .get_or_create_session(interaction.channel_id.get(), agent_type)
+            .await
+        {
+            Ok((agent, _)) => agent.get_available_models().await.unwrap_or_else(|e| {
+                error!("Failed to fetch models for autocomplete: {}", e);
+                Vec::new()
+            }),
+            Err(e) => {
+                error!("Failed to open session for autocomplete: {}", e);
+                Vec::new()
             }
+        };
+        for m in matching_models(&models, &query) {
+            let name: String = m.label.chars().take(AUTOCOMPLETE_MAX_CHARS).collect();
+            response = response.add_string_choice(name, build_model_value(&m.provider, &m.id));
         }
     }
+    interaction
+        .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+        .await?;
+    Ok(())
+}
+
+// 以目前模型重播此頻道最近一次的提問，新回應會標記為上一則回應的修訂版
+pub async fn handle_model_replay(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !crate::turn_controls::authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction.defer_ephemeral(&ctx.http).await?;
+
+    let channel_id = interaction.channel_id;
+    let last_turn = state
+        .last_turns
+        .lock()
+        .await
+        .get(&channel_id.get())
+        .cloned();
+    let Some((previous_msg_id, input)) = last_turn else {
+        let msg = state.i18n.read().await.get("model_replay_missing");
+        interaction
+            .edit_response(
+                &ctx.http,
+                EditInteractionResponse::new()
+                    .content(msg)
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, is_new) = state
+        .sessions()
+        .get_or_create_session(channel_id.get(), agent_type)
+        .await?;
+
+    let msg = state.i18n.read().await.get("model_replay_started");
+    interaction
+        .edit_response(
+            &ctx.http,
+            EditInteractionResponse::new()
+                .content(msg)
+                .components(vec![]),
+        )
+        .await?;
+
+    info!("🔁 Replaying last prompt on channel {}", channel_id);
+    crate::Handler::start_agent_loop(
+        agent,
+        ctx.http.clone(),
+        channel_id,
+        state.clone(),
+        Some(input),
+        is_new,
+        Some(previous_msg_id),
+    )
+    .await;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{build_model_value, capped_model_count, parse_model_value};
+    use super::{
+        build_model_description, build_model_value, build_replay_label, capped_model_count,
+        matching_models, model_change_message, parse_model_value, resolve_model_input,
+    };
+    use crate::agent::{ModelInfo, ModelPricing};
+
+    #[test]
+    fn test_build_model_description_lists_capabilities() {
+        let i18n = crate::i18n::I18n::new("en");
+        let mut model = ModelInfo {
+            provider: "openai".to_string(),
+            id: "gpt-4.1".to_string(),
+            label: "openai/gpt-4.1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(build_model_description(&i18n, &model), "Provider: openai");
+
+        model.context_length = Some(128_000);
+        model.supports_vision = Some(true);
+        model.pricing = Some(ModelPricing {
+            input_per_mtok: 2.5,
+            output_per_mtok: 10.0,
+        });
+        assert_eq!(
+            build_model_description(&i18n, &model),
+            "Provider: openai · 128K ctx · 🖼️ · $2.5/$10 per 1M"
+        );
+        model.context_length = Some(1_000_000);
+        assert!(build_model_description(&i18n, &model).contains("1M ctx"));
+    }
 
     #[test]
     fn test_capped_model_count_limited_to_125() {
@@ -220,6 +523,100 @@ mod tests {
         assert_eq!(model, "gpt-4.1");
     }
 
+    #[test]
+    fn test_build_replay_label_respects_discord_limit() {
+        let i18n = crate::i18n::I18n::new("en");
+        assert!(build_replay_label(&i18n, "gpt-4.1").contains("gpt-4.1"));
+        assert_eq!(
+            build_replay_label(&i18n, &"m".repeat(200)).chars().count(),
+            80
+        );
+    }
+
+    fn model(provider: &str, id: &str) -> ModelInfo {
+        ModelInfo {
+            provider: provider.to_string(),
+            id: id.to_string(),
+            label: format!("{}/{}", provider, id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matching_models_filters_by_all_terms_and_caps_at_25() {
+        let mut models = vec![
+            model("openai", "gpt-4.1"),
+            model("openai", "gpt-4.1-mini"),
+            model("anthropic", "claude-sonnet-4"),
+        ];
+        let ids = |found: Vec<&ModelInfo>| found.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            ids(matching_models(&models, "GPT mini")),
+            vec!["gpt-4.1-mini"]
+        );
+        assert_eq!(
+            ids(matching_models(&models, "anthropic")),
+            vec!["claude-sonnet-4"]
+        );
+        assert_eq!(matching_models(&models, "").len(), 3);
+
+        models.extend((0..100).map(|i| model("openrouter", &format!("m-{}", i))));
+        assert_eq!(matching_models(&models, "openrouter").len(), 25);
+        // 超過 Discord 值長度上限的模型無法作為選項
+        models.push(model("x", &"y".repeat(120)));
+        assert!(matching_models(&models, "yyy").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_model_input_accepts_choice_or_unique_name() {
+        let models = vec![
+            model("openai", "gpt-4.1"),
+            model("azure", "gpt-4.1"),
+            model("anthropic", "claude-sonnet-4"),
+        ];
+        assert_eq!(
+            resolve_model_input(&models, "openai|gpt-4.1").as_deref(),
+            Some("openai|gpt-4.1")
+        );
+        assert_eq!(
+            resolve_model_input(&models, "Claude-Sonnet-4").as_deref(),
+            Some("anthropic|claude-sonnet-4")
+        );
+        assert_eq!(
+            resolve_model_input(&models, "azure/gpt-4.1").as_deref(),
+            Some("azure|gpt-4.1")
+        );
+        assert!(resolve_model_input(&models, "gpt-4.1").is_none());
+        assert!(resolve_model_input(&models, "unknown").is_none());
+    }
+
+    #[test]
+    fn test_model_change_message_shows_before_after_and_ignored_switch() {
+        let i18n = crate::i18n::I18n::new("en");
+        assert_eq!(
+            model_change_message(
+                &i18n,
+                Some("openai/gpt-4o".to_string()),
+                Some("claude-3.7".to_string()),
+                "anthropic",
+                "claude-3.7"
+            ),
+            "✅ Model: openai/gpt-4o → claude-3.7"
+        );
+        assert_eq!(
+            model_change_message(&i18n, None, None, "anthropic", "claude-3.7"),
+            "✅ Model: unknown → anthropic/claude-3.7"
+        );
+        assert!(model_change_message(
+            &i18n,
+            Some("openai/gpt-4o".to_string()),
+            Some("openai/gpt-4o".to_string()),
+            "anthropic",
+            "claude-3.7"
+        )
+        .starts_with("⚠️"));
+    }
+
     #[test]
     fn test_parse_model_value_rejects_invalid() {
         assert!(parse_model_value("no-delimiter").is_none());
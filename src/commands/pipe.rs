@@ -0,0 +1,171 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelType, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommandOption, CreateMessage,
+};
+use tracing::info;
+
+use crate::agent::UserInput;
+use crate::i18n::I18n;
+use crate::pipe::{build_source_note, build_target_prompt, message_link};
+
+pub struct PipeCommand;
+
+#[async_trait]
+impl SlashCommand for PipeCommand {
+    fn name(&self) -> &'static str {
+        "pipe"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_pipe_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "to",
+                i18n.get("cmd_pipe_opt_to"),
+            )
+            .channel_types(vec![
+                ChannelType::Text,
+                ChannelType::PublicThread,
+                ChannelType::PrivateThread,
+            ])
+            .required(true),
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "note",
+                i18n.get("cmd_pipe_opt_note"),
+            ),
+        ]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let mut target = None;
+        let mut note = None;
+        for opt in &command.data.options {
+            match (opt.name.as_str(), &opt.value) {
+                ("to", CommandDataOptionValue::Channel(id)) => target = Some(*id),
+                ("note", CommandDataOptionValue::String(s)) => note = Some(s.clone()),
+                _ => {}
+            }
+        }
+        let Some(target) = target else {
+            return Ok(());
+        };
+
+        let source = command.channel_id;
+        let user_id = command.user.id.get();
+        let channel_i18n = state.channel_i18n(source.get()).await;
+        if target == source {
+            let msg = channel_i18n.read().await.get("pipe_same_channel");
+            return reply(ctx, command, msg).await;
+        }
+        // 目標頻道需已授權，私人 session 也只接受名單內的使用者
+        let (authorized, _) = state
+            .auth()
+            .is_authorized_with_thread(ctx, &user_id.to_string(), target, command.guild_id)
+            .await;
+        if !authorized {
+            let msg = channel_i18n
+                .read()
+                .await
+                .get_args("pipe_target_not_authorized", &[target.to_string()]);
+            return reply(ctx, command, msg).await;
+        }
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        if let Some(private) = channel_config.private_session(&target.to_string()) {
+            if !private.allows(user_id) {
+                let msg = channel_i18n
+                    .read()
+                    .await
+                    .get_args("session_private_ignored", &[private.mentions()]);
+                return reply(ctx, command, msg).await;
+            }
+        }
+        let Some((source_msg, answer)) = state.pipes.latest(source.get()).await else {
+            let msg = channel_i18n.read().await.get("pipe_no_response");
+            return reply(ctx, command, msg).await;
+        };
+
+        let agent_type = channel_config.get_agent_type(&target.to_string());
+        let (agent, is_new) = crate::Handler::open_session(state, target.get(), agent_type).await?;
+
+        let link = message_link(command.guild_id.map(|g| g.get()), source.get(), source_msg);
+        // 兩邊頻道都留下可見的串接紀錄
+        let target_note = state
+            .channel_i18n(target.get())
+            .await
+            .read()
+            .await
+            .get_args(
+                "pipe_received",
+                &[source.to_string(), user_id.to_string(), link.clone()],
+            );
+        let _ = target
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(target_note)
+                    .allowed_mentions(crate::mentions::suppressed()),
+            )
+            .await;
+        let source_note = channel_i18n
+            .read()
+            .await
+            .get_args("pipe_sent", &[target.to_string(), user_id.to_string()]);
+        let _ = source
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(source_note)
+                    .allowed_mentions(crate::mentions::suppressed())
+                    .reference_message((source, source_msg)),
+            )
+            .await;
+        state
+            .pipes
+            .add_note(source.get(), build_source_note(target.get(), user_id))
+            .await;
+
+        let msg = channel_i18n
+            .read()
+            .await
+            .get_args("pipe_started", &[target.to_string()]);
+        reply(ctx, command, msg).await?;
+
+        info!("🔗 Piped response of channel {} into {}", source, target);
+        let input = UserInput {
+            text: build_target_prompt(source.get(), &link, user_id, note.as_deref(), &answer),
+            requester: Some(user_id),
+            priority: state.config.priority.is_priority(
+                Some(user_id),
+                super::is_admin(command.member.as_deref(), command.guild_id.is_some()),
+            ),
+            ..UserInput::default()
+        };
+        crate::Handler::start_agent_loop(
+            agent,
+            ctx.http.clone(),
+            target,
+            state.clone(),
+            Some(input),
+            is_new,
+            None,
+        )
+        .await;
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandInteraction, CommandOptionType, Context, CreateCommandOption,
+    EditInteractionResponse,
+};
+
+use crate::i18n::I18n;
+use crate::prefs::{UserPrefs, UserPrefsStore};
+
+pub struct PrefsCommand;
+
+/// 套用指令選項；回傳是否有任何變更
+fn apply_pref_options(prefs: &mut UserPrefs, options: &[CommandDataOption]) -> bool {
+    let mut changed = false;
+    for opt in options {
+        match opt.name.as_str() {
+            "language" => {
+                if let Some(lang) = opt.value.as_str() {
+                    prefs.language = (lang != "default").then(|| lang.to_string());
+                    changed = true;
+                }
+            }
+            "dm_long_replies" => {
+                if let Some(v) = opt.value.as_bool() {
+                    prefs.dm_long_replies = v;
+                    changed = true;
+                }
+            }
+            "compact_embeds" => {
+                if let Some(v) = opt.value.as_bool() {
+                    prefs.compact_embeds = v;
+                    changed = true;
+                }
+            }
+            "ping_on_complete" => {
+                if let Some(v) = opt.value.as_bool() {
+                    prefs.ping_on_complete = v;
+                    changed = true;
+                }
+            }
+            "accessible" => {
+                if let Some(v) = opt.value.as_bool() {
+                    prefs.accessible = v;
+                    changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+fn format_prefs(i18n: &I18n, prefs: &UserPrefs) -> String {
+    i18n.get_args(
+        "prefs_current",
+        &[
+            prefs
+                .language
+                .clone()
+                .unwrap_or_else(|| i18n.get("prefs_language_default")),
+            prefs.dm_long_replies.to_string(),
+            prefs.compact_embeds.to_string(),
+            prefs.ping_on_complete.to_string(),
+            prefs.accessible.to_string(),
+        ],
+    )
+}
+
+#[async_trait]
+impl SlashCommand for PrefsCommand {
+    fn name(&self) -> &'static str {
+        "prefs"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_prefs_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "language",
+                i18n.get("cmd_prefs_opt_language"),
+            )
+            .add_string_choice(i18n.get("prefs_language_default"), "default")
+            .add_string_choice(i18n.get("lang_choice_zh_tw"), "zh-TW")
+            .add_string_choice(i18n.get("lang_choice_en"), "en"),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "dm_long_replies",
+                i18n.get("cmd_prefs_opt_dm_long_replies"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "compact_embeds",
+                i18n.get("cmd_prefs_opt_compact_embeds"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "ping_on_complete",
+                i18n.get("cmd_prefs_opt_ping_on_complete"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "accessible",
+                i18n.get("cmd_prefs_opt_accessible"),
+            ),
+        ]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let user_id = command.user.id.get();
+        let mut store = UserPrefsStore::load().await.unwrap_or_default();
+        let changed = apply_pref_options(store.get_mut(user_id), &command.data.options);
+        if changed {
+            store.save().await?;
+        }
+
+        let prefs = store.get(user_id);
+        let msg = {
+            let i18n = state.i18n.read().await;
+            let current = format_prefs(&i18n, &prefs);
+            if changed {
+                format!("{}\n{}", i18n.get("prefs_updated"), current)
+            } else {
+                current
+            }
+        };
+
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_pref_options, format_prefs};
+    use crate::prefs::UserPrefs;
+    use serenity::all::CommandDataOption;
+
+    fn opts(json: &str) -> Vec<CommandDataOption> {
+        serde_json::from_str(json).expect("options")
+    }
+
+    #[test]
+    fn test_apply_pref_options_updates_only_given_fields() {
+        let mut prefs = UserPrefs {
+            compact_embeds: true,
+            ..Default::default()
+        };
+        let changed = apply_pref_options(
+            &mut prefs,
+            &opts(
+                r#"[{"name":"language","type":3,"value":"en"},{"name":"ping_on_complete","type":5,"value":true}]"#,
+            ),
+        );
+        assert!(changed);
+        assert_eq!(prefs.language.as_deref(), Some("en"));
+        assert!(prefs.ping_on_complete);
+        assert!(prefs.compact_embeds);
+
+        apply_pref_options(
+            &mut prefs,
+            &opts(r#"[{"name":"language","type":3,"value":"default"}]"#),
+        );
+        assert_eq!(prefs.language, None);
+        assert!(!apply_pref_options(&mut prefs, &[]));
+    }
+
+    #[test]
+    fn test_format_prefs_lists_all_fields() {
+        let i18n = crate::i18n::I18n::new("en");
+        let text = format_prefs(&i18n, &UserPrefs::default());
+        assert!(text.contains(&i18n.get("prefs_language_default")));
+        assert!(text.contains("false"));
+        assert!(text.contains("accessible"));
+    }
+}
@@ -0,0 +1,271 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    ActionRowComponent, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateActionRow, CreateCommand, CreateCommandOption, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateModal,
+    EditInteractionResponse, InputTextStyle, ModalInteraction, Permissions,
+};
+use tracing::{info, warn};
+
+use super::is_admin;
+use crate::agent::{AgentType, OpencodeAgent};
+use crate::i18n::I18n;
+
+pub const PROVIDER_MODAL_PREFIX: &str = "provider_login_modal:";
+const PROVIDER_ID_MAX_CHARS: usize = 64;
+// Discord modal 標題上限 45 字
+const MODAL_TITLE_MAX_CHARS: usize = 45;
+
+pub struct ProviderCommand;
+
+/// 供應商 ID 會放進 URL 路徑與 modal custom_id，只允許安全字元
+fn sanitize_provider_id(raw: &str) -> Option<String> {
+    let id = raw.trim().to_lowercase();
+    let valid = !id.is_empty()
+        && id.len() <= PROVIDER_ID_MAX_CHARS
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !id.starts_with('.');
+    valid.then_some(id)
+}
+
+fn supports_provider_login(agent_type: &AgentType) -> bool {
+    matches!(agent_type, AgentType::Kilo | AgentType::Opencode)
+}
+
+fn build_login_modal(i18n: &I18n, provider: &str) -> CreateModal {
+    CreateModal::new(
+        format!("{}{}", PROVIDER_MODAL_PREFIX, provider),
+        i18n.get_args("provider_modal_title", &[provider.to_string()])
+            .chars()
+            .take(MODAL_TITLE_MAX_CHARS)
+            .collect::<String>(),
+    )
+    .components(vec![CreateActionRow::InputText(
+        CreateInputText::new(
+            InputTextStyle::Short,
+            i18n.get("provider_modal_label"),
+            "api_key",
+        )
+        .required(true),
+    )])
+}
+
+#[async_trait]
+impl SlashCommand for ProviderCommand {
+    fn name(&self) -> &'static str {
+        "provider"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_provider_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "login",
+            i18n.get("cmd_provider_login_desc"),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "provider",
+                i18n.get("cmd_provider_opt_provider"),
+            )
+            .required(true),
+        )]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        let raw_provider = command
+            .data
+            .options
+            .first()
+            .and_then(|sub| match &sub.value {
+                CommandDataOptionValue::SubCommand(opts) => opts
+                    .iter()
+                    .find(|o| o.name == "provider")
+                    .and_then(|o| o.value.as_str().map(str::to_string)),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        let agent_type = channel_config.get_agent_type(&command.channel_id.to_string());
+
+        let i18n = state.i18n.read().await;
+        let rejection = if !is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+            Some(i18n.get("provider_admin_only"))
+        } else if !supports_provider_login(&agent_type) {
+            Some(i18n.get_args("provider_unsupported_backend", &[agent_type.to_string()]))
+        } else if sanitize_provider_id(&raw_provider).is_none() {
+            Some(i18n.get("provider_invalid_id"))
+        } else {
+            None
+        };
+
+        let response = match (rejection, sanitize_provider_id(&raw_provider)) {
+            (None, Some(provider)) => {
+                CreateInteractionResponse::Modal(build_login_modal(&i18n, &provider))
+            }
+            (msg, _) => CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(msg.unwrap_or_default())
+                    .ephemeral(true),
+            ),
+        };
+        drop(i18n);
+
+        command.create_response(&ctx.http, response).await?;
+        Ok(())
+    }
+}
+
+async fn reply(ctx: &Context, interaction: &ModalInteraction, msg: String) -> anyhow::Result<()> {
+    interaction
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        .await?;
+    Ok(())
+}
+
+/// 將 modal 收到的金鑰轉交後端憑證儲存；金鑰本身絕不寫入日誌
+pub async fn handle_provider_modal_submit(
+    ctx: &Context,
+    interaction: &ModalInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    interaction.defer_ephemeral(&ctx.http).await?;
+
+    if !is_admin(interaction.member.as_ref(), interaction.guild_id.is_some()) {
+        let msg = state.i18n.read().await.get("provider_admin_only");
+        return reply(ctx, interaction, msg).await;
+    }
+
+    let Some(provider) = interaction
+        .data
+        .custom_id
+        .strip_prefix(PROVIDER_MODAL_PREFIX)
+        .and_then(sanitize_provider_id)
+    else {
+        let msg = state.i18n.read().await.get("provider_invalid_id");
+        return reply(ctx, interaction, msg).await;
+    };
+
+    let mut api_key = String::new();
+    for row in &interaction.data.components {
+        for component in &row.components {
+            if let ActionRowComponent::InputText(text) = component {
+                if text.custom_id == "api_key" {
+                    api_key = text.value.clone().unwrap_or_default().trim().to_string();
+                }
+            }
+        }
+    }
+    if api_key.is_empty() {
+        let msg = state.i18n.read().await.get("provider_empty_key");
+        return reply(ctx, interaction, msg).await;
+    }
+
+    let channel_id = interaction.channel_id.get();
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    if !supports_provider_login(&agent_type) {
+        let msg = state
+            .i18n
+            .read()
+            .await
+            .get_args("provider_unsupported_backend", &[agent_type.to_string()]);
+        return reply(ctx, interaction, msg).await;
+    }
+
+    let password = state.config.opencode.password.clone().unwrap_or_default();
+    let result = match state
+        .backends()
+        .ensure_backend(&agent_type, channel_id)
+        .await
+    {
+        Ok(port) => {
+            OpencodeAgent::set_provider_api_key(
+                &format!("http://127.0.0.1:{}", port),
+                &password,
+                &provider,
+                &api_key,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+
+    let msg = {
+        let i18n = state.i18n.read().await;
+        match result {
+            Ok(()) => {
+                info!(
+                    "🔑 Stored {} credentials for provider '{}' (by user {})",
+                    agent_type, provider, interaction.user.id
+                );
+                i18n.get_args("provider_login_ok", &[provider, agent_type.to_string()])
+            }
+            Err(e) => {
+                warn!(
+                    "❌ Failed to store provider '{}' credentials: {}",
+                    provider, e
+                );
+                i18n.get_args("provider_login_failed", &[e.to_string()])
+            }
+        }
+    };
+    reply(ctx, interaction, msg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize_provider_id, supports_provider_login};
+    use crate::agent::AgentType;
+
+    #[test]
+    fn test_sanitize_provider_id() {
+        assert_eq!(
+            sanitize_provider_id(" Anthropic "),
+            Some("anthropic".to_string())
+        );
+        assert_eq!(
+            sanitize_provider_id("openai-compatible_2"),
+            Some("openai-compatible_2".to_string())
+        );
+        assert_eq!(sanitize_provider_id(""), None);
+        assert_eq!(sanitize_provider_id("../etc"), None);
+        assert_eq!(sanitize_provider_id("a/b"), None);
+        assert_eq!(sanitize_provider_id(&"x".repeat(65)), None);
+    }
+
+    #[test]
+    fn test_provider_login_gates() {
+        assert!(supports_provider_login(&AgentType::Kilo));
+        assert!(supports_provider_login(&AgentType::Opencode));
+        assert!(!supports_provider_login(&AgentType::Pi));
+        assert!(!supports_provider_login(&AgentType::Copilot));
+    }
+}
@@ -0,0 +1,133 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommandOption,
+    EditInteractionResponse,
+};
+
+use crate::agent::UserInput;
+use crate::i18n::I18n;
+use tracing::info;
+
+pub struct QuickCommand;
+
+/// 附加在問題前的簡答指示
+const QUICK_INSTRUCTION: &str = "Answer the following question directly and concisely in a few sentences. Do not use any tools, do not run commands, and do not read or modify files.";
+
+fn build_quick_prompt(question: &str) -> String {
+    format!("{}\n\n{}", QUICK_INSTRUCTION, question.trim())
+}
+
+#[async_trait]
+impl SlashCommand for QuickCommand {
+    fn name(&self) -> &'static str {
+        "quick"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_quick_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::String,
+            "question",
+            i18n.get("cmd_quick_opt_question"),
+        )
+        .required(true)]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let question = command
+            .data
+            .options
+            .iter()
+            .find(|o| o.name == "question")
+            .and_then(|o| match &o.value {
+                CommandDataOptionValue::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        if question.trim().is_empty() {
+            let msg = state.i18n.read().await.get("quick_empty");
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        }
+
+        let channel_id = command.channel_id;
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        if let Some(private) = channel_config.private_session(&channel_id.to_string()) {
+            if !private.allows(command.user.id.get()) {
+                let msg = state
+                    .i18n
+                    .read()
+                    .await
+                    .get_args("session_private_ignored", &[private.mentions()]);
+                command
+                    .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                    .await?;
+                return Ok(());
+            }
+        }
+        let agent_type = channel_config.get_agent_type(&channel_id.to_string());
+        let (agent, is_new) = state
+            .sessions()
+            .get_or_create_session(channel_id.get(), agent_type)
+            .await?;
+
+        let msg = state.i18n.read().await.get("quick_started");
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+
+        info!("⚡ Quick question on channel {}", channel_id);
+        let input = UserInput {
+            text: build_quick_prompt(&question),
+            files: vec![],
+            requester: Some(command.user.id.get()),
+            quick: true,
+            on_behalf_of: None,
+            disabled_tools: Vec::new(),
+            continues: None,
+            source_message: None,
+            priority: state.config.priority.is_priority(
+                Some(command.user.id.get()),
+                crate::commands::is_admin(command.member.as_deref(), command.guild_id.is_some()),
+            ),
+        };
+        crate::Handler::start_agent_loop(
+            agent,
+            ctx.http.clone(),
+            channel_id,
+            state.clone(),
+            Some(input),
+            is_new,
+            None,
+        )
+        .await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_quick_prompt, QUICK_INSTRUCTION};
+
+    #[test]
+    fn test_build_quick_prompt_prefixes_instruction() {
+        let prompt = build_quick_prompt("  what is rust?  ");
+        assert!(prompt.starts_with(QUICK_INSTRUCTION));
+        assert!(prompt.ends_with("\n\nwhat is rust?"));
+    }
+}
@@ -0,0 +1,125 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommand, CreateCommandOption, Permissions,
+};
+
+use crate::i18n::I18n;
+use crate::quiet::{parse_time, QuietHours};
+
+pub struct QuietCommand;
+
+/// `/quiet set` 的起訖時間；格式錯誤或兩者相同時為 None
+fn parse_hours(opts: &[CommandDataOption]) -> Option<QuietHours> {
+    let time = |name: &str| {
+        opts.iter()
+            .find(|o| o.name == name)
+            .and_then(|o| o.value.as_str())
+            .and_then(parse_time)
+    };
+    let hours = QuietHours {
+        start: time("start")?,
+        end: time("end")?,
+    };
+    (hours.start != hours.end).then_some(hours)
+}
+
+#[async_trait]
+impl SlashCommand for QuietCommand {
+    fn name(&self) -> &'static str {
+        "quiet"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_quiet_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                i18n.get("cmd_quiet_set_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "start",
+                    i18n.get("cmd_quiet_opt_start"),
+                )
+                .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "end",
+                    i18n.get("cmd_quiet_opt_end"),
+                )
+                .required(true),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "off",
+                i18n.get("cmd_quiet_off_desc"),
+            ),
+        ]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+            let msg = state.i18n.read().await.get("quiet_admin_only");
+            return reply(ctx, command, msg).await;
+        }
+
+        let Some(sub) = command.data.options.first() else {
+            return Ok(());
+        };
+        let updated = match (sub.name.as_str(), &sub.value) {
+            ("set", CommandDataOptionValue::SubCommand(opts)) => match parse_hours(opts) {
+                Some(hours) => Some(hours),
+                None => {
+                    let msg = state.i18n.read().await.get("quiet_invalid_time");
+                    return reply(ctx, command, msg).await;
+                }
+            },
+            ("off", _) => None,
+            _ => return Ok(()),
+        };
+
+        let channel_id = command.channel_id.to_string();
+        crate::commands::agent::ChannelConfig::update(|cfg| {
+            cfg.entry_mut(&channel_id).quiet_hours = updated
+        })
+        .await?;
+
+        let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+        let i18n = channel_i18n.read().await;
+        let msg = match updated {
+            Some(hours) => i18n.get_args(
+                "quiet_set",
+                &[i18n.format_time(hours.start), i18n.format_time(hours.end)],
+            ),
+            None => i18n.get("quiet_off"),
+        };
+        drop(i18n);
+        reply(ctx, command, msg).await
+    }
+}
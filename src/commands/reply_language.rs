@@ -0,0 +1,73 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, Permissions,
+};
+
+use crate::i18n::I18n;
+
+pub struct ReplyLanguageCommand;
+
+#[async_trait]
+impl SlashCommand for ReplyLanguageCommand {
+    fn name(&self) -> &'static str {
+        "reply_language"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_reply_language_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "auto",
+            i18n.get("cmd_reply_language_opt_auto"),
+        )
+        .required(true)]
+    }
+
+    fn create_command(&self, i18n: &I18n) -> CreateCommand {
+        let mut cmd = CreateCommand::new(self.name())
+            .description(self.description(i18n))
+            .default_member_permissions(Permissions::ADMINISTRATOR);
+        for opt in self.options(i18n) {
+            cmd = cmd.add_option(opt);
+        }
+        cmd
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let auto = command
+            .data
+            .options
+            .iter()
+            .find(|o| o.name == "auto")
+            .and_then(|o| o.value.as_bool())
+            .unwrap_or(false);
+
+        let channel_id = command.channel_id.to_string();
+        crate::commands::agent::ChannelConfig::update(|cfg| {
+            cfg.entry_mut(&channel_id).auto_reply_language = auto
+        })
+        .await?;
+
+        let msg = state.i18n.read().await.get(if auto {
+            "reply_language_auto_on"
+        } else {
+            "reply_language_auto_off"
+        });
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,469 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommandOption,
+};
+
+use crate::i18n::I18n;
+use crate::recipe::SessionRecipe;
+use tracing::info;
+
+pub struct SessionCommand;
+
+/// 私人 session：只有擁有者 (開啟的人) 與名單內的使用者可以在頻道送出提示
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PrivateSession {
+    pub owner: u64,
+    #[serde(default)]
+    pub members: Vec<u64>,
+}
+
+impl PrivateSession {
+    pub fn allows(&self, user_id: u64) -> bool {
+        self.owner == user_id || self.members.contains(&user_id)
+    }
+
+    /// 以 Discord 提及格式列出可發言的使用者
+    pub fn mentions(&self) -> String {
+        std::iter::once(&self.owner)
+            .chain(&self.members)
+            .map(|id| format!("<@{}>", id))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 解析 `<@id>`、`<@!id>` 或純數字的使用者清單 (空白或逗號分隔)
+fn parse_user_ids(raw: &str) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for token in raw.split(|c: char| c.is_whitespace() || c == ',') {
+        let id = token
+            .trim_start_matches("<@")
+            .trim_start_matches('!')
+            .trim_end_matches('>')
+            .parse::<u64>()
+            .ok()
+            .filter(|id| *id > 0);
+        if let Some(id) = id {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+fn parse_channel_id(raw: &str) -> Option<u64> {
+    // 允許直接貼上 <#id> 頻道提及
+    raw.trim()
+        .trim_start_matches("<#")
+        .trim_end_matches('>')
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id > 0)
+}
+
+fn adopt_option(opts: &[CommandDataOption]) -> Option<String> {
+    opts.iter()
+        .find(|o| o.name == "old_channel_id")
+        .and_then(|o| o.value.as_str())
+        .map(str::to_string)
+}
+
+/// `/session private on|off` 的子指令名稱與參數
+fn private_options(group: &[CommandDataOption]) -> Option<(&str, &[CommandDataOption])> {
+    let sub = group.first()?;
+    match &sub.value {
+        CommandDataOptionValue::SubCommand(opts) => Some((sub.name.as_str(), opts.as_slice())),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl SlashCommand for SessionCommand {
+    fn name(&self) -> &'static str {
+        "session"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_session_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "adopt",
+                i18n.get("cmd_session_adopt_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "old_channel_id",
+                    i18n.get("cmd_session_opt_old_channel"),
+                )
+                .required(true),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommandGroup,
+                "private",
+                i18n.get("cmd_session_private_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "on",
+                    i18n.get("cmd_session_private_on_desc"),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "users",
+                    i18n.get("cmd_session_opt_users"),
+                )),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "off",
+                i18n.get("cmd_session_private_off_desc"),
+            )),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "recipe",
+                i18n.get("cmd_session_recipe_desc"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "apply-recipe",
+                i18n.get("cmd_session_apply_recipe_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "recipe",
+                    i18n.get("cmd_session_opt_recipe"),
+                )
+                .required(true),
+            ),
+        ]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let Some(sub) = command.data.options.first() else {
+            return Ok(());
+        };
+        match (sub.name.as_str(), &sub.value) {
+            ("adopt", CommandDataOptionValue::SubCommand(opts)) => {
+                adopt(ctx, command, state, opts).await
+            }
+            ("private", CommandDataOptionValue::SubCommandGroup(group)) => {
+                match private_options(group) {
+                    Some(("on", opts)) => private_on(ctx, command, state, opts).await,
+                    Some(("off", _)) => private_off(ctx, command, state).await,
+                    _ => Ok(()),
+                }
+            }
+            ("recipe", _) => recipe(ctx, command, state).await,
+            ("apply-recipe", CommandDataOptionValue::SubCommand(opts)) => {
+                apply_recipe(ctx, command, state, opts).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+async fn private_on(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    let channel_id = command.channel_id.to_string();
+    let user_id = command.user.id.get();
+    let is_admin = super::is_admin(command.member.as_deref(), command.guild_id.is_some());
+    let members = opts
+        .iter()
+        .find(|o| o.name == "users")
+        .and_then(|o| o.value.as_str())
+        .map(parse_user_ids)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|id| *id != user_id)
+        .collect();
+    let private = PrivateSession {
+        owner: user_id,
+        members,
+    };
+    // 已是別人的私人 session 時，只有擁有者或管理員能改
+    let denied_owner = crate::commands::agent::ChannelConfig::update(|cfg| {
+        if let Some(existing) = cfg.private_session(&channel_id) {
+            if existing.owner != user_id && !is_admin {
+                return Some(existing.owner);
+            }
+        }
+        cfg.entry_mut(&channel_id).private = Some(private.clone());
+        None
+    })
+    .await?;
+    if let Some(owner) = denied_owner {
+        let msg = state
+            .i18n
+            .read()
+            .await
+            .get_args("session_private_not_owner", &[owner.to_string()]);
+        return reply(ctx, command, msg).await;
+    }
+    // 重新開啟後，被擋下的使用者會再收到一次提示
+    state
+        .private_notices
+        .lock()
+        .await
+        .retain(|(channel, _)| *channel != command.channel_id.get());
+
+    info!(
+        "🔒 Channel {} session made private by {} ({} extra member(s))",
+        channel_id,
+        user_id,
+        private.members.len()
+    );
+    let msg = state
+        .i18n
+        .read()
+        .await
+        .get_args("session_private_on", &[private.mentions()]);
+    reply(ctx, command, msg).await
+}
+
+async fn private_off(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    let channel_id = command.channel_id.to_string();
+    let user_id = command.user.id.get();
+    let is_admin = super::is_admin(command.member.as_deref(), command.guild_id.is_some());
+    let refused = crate::commands::agent::ChannelConfig::update(|cfg| {
+        let entry = cfg.channels.get_mut(&channel_id);
+        let Some(existing) = entry.as_ref().and_then(|e| e.private.as_ref()) else {
+            return Some(("session_private_already_off", Vec::new()));
+        };
+        if existing.owner != user_id && !is_admin {
+            return Some((
+                "session_private_not_owner",
+                vec![existing.owner.to_string()],
+            ));
+        }
+        if let Some(entry) = entry {
+            entry.private = None;
+        }
+        None
+    })
+    .await?;
+    if let Some((key, args)) = refused {
+        let msg = state.i18n.read().await.get_args(key, &args);
+        return reply(ctx, command, msg).await;
+    }
+    info!(
+        "🔓 Channel {} session is no longer private ({})",
+        channel_id, command.user.id
+    );
+    let msg = state.i18n.read().await.get("session_private_off");
+    reply(ctx, command, msg).await
+}
+
+/// `/session recipe`：匯出目前頻道設定的配方
+async fn recipe(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    let channel_id = command.channel_id.to_string();
+    let channel_config = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default();
+    let recipe = SessionRecipe::from_entry(
+        channel_config.get_agent_type(&channel_id),
+        channel_config.channels.get(&channel_id),
+    );
+    let msg = state
+        .channel_i18n(command.channel_id.get())
+        .await
+        .read()
+        .await
+        .get_args("session_recipe", &[recipe.to_snippet()]);
+    reply(ctx, command, msg).await
+}
+
+/// `/session apply-recipe`：以配方取代此頻道的對應設定，限管理員
+async fn apply_recipe(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    let channel_id_u64 = command.channel_id.get();
+    let channel_i18n = state.channel_i18n(channel_id_u64).await;
+    if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+        let msg = channel_i18n.read().await.get("session_recipe_admin_only");
+        return reply(ctx, command, msg).await;
+    }
+    let input = opts
+        .iter()
+        .find(|o| o.name == "recipe")
+        .and_then(|o| o.value.as_str())
+        .unwrap_or_default();
+    let mut recipe = match SessionRecipe::parse(input) {
+        Ok(recipe) => recipe,
+        Err(e) => {
+            let msg = channel_i18n
+                .read()
+                .await
+                .get_args("session_recipe_invalid", &[e.to_string()]);
+            return reply(ctx, command, msg).await;
+        }
+    };
+    if !crate::agent::is_backend_enabled(&recipe.backend) {
+        let msg = channel_i18n.read().await.get_args(
+            "session_recipe_backend_disabled",
+            &[recipe.backend.to_string()],
+        );
+        return reply(ctx, command, msg).await;
+    }
+    // 工作目錄與助理名稱沿用 /config 的檢查
+    if let Some(dir) = recipe.workdir.clone() {
+        match super::config::parse_workdir(&dir) {
+            Ok(workdir) => recipe.workdir = workdir,
+            Err(key) => {
+                let msg = channel_i18n.read().await.get_args(key, &[dir]);
+                return reply(ctx, command, msg).await;
+            }
+        }
+    }
+    recipe.assistant_name = recipe
+        .assistant_name
+        .as_deref()
+        .and_then(super::config::sanitize_assistant_name);
+
+    let channel_id = command.channel_id.to_string();
+    let changes = crate::commands::agent::ChannelConfig::update(|cfg| {
+        recipe.apply_to(cfg.entry_mut(&channel_id))
+    })
+    .await?;
+    // 模型與推理等級在建立 session 時套用，所以一律丟棄目前的連線
+    state.sessions().remove_session(channel_id_u64).await;
+    if changes.new_session {
+        state.backends().release_channel(channel_id_u64).await;
+    }
+
+    info!(
+        "📋 Applied session recipe to channel {} (backend={}, new_session={})",
+        channel_id, recipe.backend, changes.new_session
+    );
+    let i18n = channel_i18n.read().await;
+    let mut msg = i18n.get_args("session_recipe_applied", &[recipe.backend.to_string()]);
+    if changes.new_session {
+        msg.push('\n');
+        msg.push_str(&i18n.get("session_recipe_new_session"));
+    }
+    drop(i18n);
+    reply(ctx, command, msg).await
+}
+
+async fn adopt(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+        let msg = state.i18n.read().await.get("session_admin_only");
+        return reply(ctx, command, msg).await;
+    }
+
+    let new_id = command.channel_id.get();
+    let Some(old_id) = adopt_option(opts).as_deref().and_then(parse_channel_id) else {
+        let msg = state.i18n.read().await.get("session_adopt_invalid");
+        return reply(ctx, command, msg).await;
+    };
+    if old_id == new_id {
+        let msg = state.i18n.read().await.get("session_adopt_same");
+        return reply(ctx, command, msg).await;
+    }
+
+    // 兩邊的執行中 session 都要丟棄，避免沿用舊頻道的連線狀態
+    for id in [old_id, new_id] {
+        state.sessions().remove_session(id).await;
+        state.backends().release_channel(id).await;
+    }
+
+    let (old_str, new_str) = (old_id.to_string(), new_id.to_string());
+    let auth_moved = state.auth().migrate_channel(&old_str, &new_str)?;
+    let config_moved = crate::commands::agent::ChannelConfig::update(|cfg| {
+        cfg.migrate_channel(&old_str, &new_str)
+    })
+    .await?;
+    let files_moved = crate::migrate::migrate_channel_files(old_id, new_id).await?;
+
+    let i18n = state.i18n.read().await;
+    let msg = if !auth_moved && !config_moved && files_moved == 0 {
+        i18n.get_args("session_adopt_nothing", &[old_str])
+    } else {
+        info!(
+            "📦 Channel {} adopted {} (auth={}, config={}, files={})",
+            new_id, old_id, auth_moved, config_moved, files_moved
+        );
+        let mark = |done: bool| if done { "✅" } else { "➖" };
+        i18n.get_args(
+            "session_adopt_done",
+            &[
+                old_str,
+                mark(auth_moved).to_string(),
+                mark(config_moved).to_string(),
+                files_moved.to_string(),
+            ],
+        )
+    };
+    drop(i18n);
+    reply(ctx, command, msg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_channel_id, parse_user_ids, PrivateSession};
+
+    #[test]
+    fn test_parse_user_ids_accepts_mentions_and_dedups() {
+        assert_eq!(
+            parse_user_ids("<@12> <@!34>, 56 nobody <@12>"),
+            vec![12, 34, 56]
+        );
+        assert!(parse_user_ids("").is_empty());
+    }
+
+    #[test]
+    fn test_private_session_allows_owner_and_members() {
+        let private = PrivateSession {
+            owner: 1,
+            members: vec![2],
+        };
+        assert!(private.allows(1));
+        assert!(private.allows(2));
+        assert!(!private.allows(3));
+        assert_eq!(private.mentions(), "<@1> <@2>");
+    }
+
+    #[test]
+    fn test_parse_channel_id_accepts_raw_and_mention() {
+        assert_eq!(parse_channel_id(" 123456 "), Some(123456));
+        assert_eq!(parse_channel_id("<#987>"), Some(987));
+        assert_eq!(parse_channel_id("0"), None);
+        assert_eq!(parse_channel_id("general"), None);
+    }
+}
@@ -1,11 +1,115 @@
-use super::SlashCommand;
+use super::long_reply::LongReply;
+use super::{reply, SlashCommand};
 use async_trait::async_trait;
 use serenity::all::{
-    CommandInteraction, CommandOptionType, Context, CreateCommandOption, EditInteractionResponse,
+    Attachment, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context, CreateCommandOption,
 };
+use tracing::info;
+
+use crate::skills::{self, SkillManifest, MAX_BUNDLE_BYTES};
 
 pub struct SkillCommand;
 
+fn sub_options(command: &CommandInteraction) -> Option<(&str, &[CommandDataOption])> {
+    let sub = command.data.options.first()?;
+    match &sub.value {
+        CommandDataOptionValue::SubCommand(opts) => Some((sub.name.as_str(), opts.as_slice())),
+        _ => None,
+    }
+}
+
+async fn load(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    name: &str,
+) -> anyhow::Result<()> {
+    let channel_id_u64 = command.channel_id.get();
+    let channel_id_str = channel_id_u64.to_string();
+    let channel_config = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default();
+    let agent_type = channel_config.get_agent_type(&channel_id_str);
+
+    let (agent, _) = state
+        .sessions()
+        .get_or_create_session(channel_id_u64, agent_type)
+        .await?;
+
+    let mut reply = LongReply::new(ctx, command);
+    let progress_i18n = state.i18n.read().await.get("long_op_progress");
+    let result = reply
+        .run(agent.load_skill(name), |secs| {
+            progress_i18n.replace("{0}", &secs.to_string())
+        })
+        .await;
+
+    let msg = {
+        let i18n = state.i18n.read().await;
+        match result {
+            Ok(_) => i18n.get_args("skill_loading", &[name.to_string()]),
+            Err(e) => i18n.get_args("skill_failed", &[e.to_string()]),
+        }
+    };
+    reply.update(msg).await?;
+
+    Ok(())
+}
+
+async fn install_attachment(attachment: &Attachment) -> anyhow::Result<SkillManifest> {
+    if u64::from(attachment.size) > MAX_BUNDLE_BYTES {
+        anyhow::bail!("bundle exceeds {} bytes", MAX_BUNDLE_BYTES);
+    }
+    let bytes = attachment.download().await?;
+    let skills_dir = crate::migrate::get_skills_dir();
+    tokio::task::spawn_blocking(move || skills::install_bundle(&skills_dir, &bytes)).await?
+}
+
+async fn install(
+    ctx: &Context,
+    command: &CommandInteraction,
+    state: &crate::AppState,
+    opts: &[CommandDataOption],
+) -> anyhow::Result<()> {
+    if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+        let msg = state.i18n.read().await.get("skill_install_admin_only");
+        return reply(ctx, command, msg).await;
+    }
+    let attachment = opts.iter().find_map(|o| match (o.name.as_str(), &o.value) {
+        ("bundle", CommandDataOptionValue::Attachment(id)) => {
+            command.data.resolved.attachments.get(id)
+        }
+        _ => None,
+    });
+    let Some(attachment) = attachment else {
+        return Ok(());
+    };
+
+    let result = install_attachment(attachment).await;
+    let msg = {
+        let i18n = state.i18n.read().await;
+        match result {
+            Ok(manifest) => {
+                info!(
+                    "🧩 Installed skill bundle {} ({}) from {}",
+                    manifest.name, attachment.filename, command.user.id
+                );
+                i18n.get_args(
+                    "skill_installed",
+                    &[
+                        manifest.name,
+                        manifest.version.unwrap_or_else(|| "-".to_string()),
+                        manifest.description,
+                    ],
+                )
+            }
+            Err(e) => i18n.get_args("skill_install_failed", &[e.to_string()]),
+        }
+    };
+    reply(ctx, command, msg).await
+}
+
 #[async_trait]
 impl SlashCommand for SkillCommand {
     fn name(&self) -> &'static str {
@@ -17,12 +121,34 @@ impl SlashCommand for SkillCommand {
     }
 
     fn options(&self, i18n: &crate::i18n::I18n) -> Vec<CreateCommandOption> {
-        vec![CreateCommandOption::new(
-            CommandOptionType::String,
-            "name",
-            i18n.get("cmd_skill_opt_name"),
-        )
-        .required(true)]
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "load",
+                i18n.get("cmd_skill_load_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    i18n.get("cmd_skill_opt_name"),
+                )
+                .required(true),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "install",
+                i18n.get("cmd_skill_install_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Attachment,
+                    "bundle",
+                    i18n.get("cmd_skill_opt_bundle"),
+                )
+                .required(true),
+            ),
+        ]
     }
 
     async fn execute(
@@ -33,43 +159,17 @@ impl SlashCommand for SkillCommand {
     ) -> anyhow::Result<()> {
         command.defer_ephemeral(&ctx.http).await?;
 
-        let name = command
-            .data
-            .options
-            .iter()
-            .find(|o| o.name == "name")
-            .and_then(|o| o.value.as_str())
-            .unwrap_or("");
-
-        let channel_id_u64 = command.channel_id.get();
-        let channel_id_str = channel_id_u64.to_string();
-        let channel_config = crate::commands::agent::ChannelConfig::load()
-            .await
-            .unwrap_or_default();
-        let agent_type = channel_config.get_agent_type(&channel_id_str);
-
-        let (agent, _) = state
-            .session_manager
-            .get_or_create_session(channel_id_u64, agent_type, &state.backend_manager)
-            .await?;
-
-        let i18n = state.i18n.read().await;
-        match agent.load_skill(name).await {
-            Ok(_) => {
-                let msg = i18n.get_args("skill_loading", &[name.to_string()]);
-                command
-                    .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
-                    .await?;
-            }
-            Err(e) => {
-                let msg = i18n.get_args("skill_failed", &[e.to_string()]);
-                command
-                    .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
-                    .await?;
+        match sub_options(command) {
+            Some(("load", opts)) => {
+                let name = opts
+                    .iter()
+                    .find(|o| o.name == "name")
+                    .and_then(|o| o.value.as_str())
+                    .unwrap_or("");
+                load(ctx, command, state, name).await
             }
+            Some(("install", opts)) => install(ctx, command, state, opts).await,
+            _ => Ok(()),
         }
-        drop(i18n);
-
-        Ok(())
     }
 }
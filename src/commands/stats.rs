@@ -0,0 +1,89 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, EditInteractionResponse, UserId,
+};
+
+use crate::i18n::I18n;
+use crate::metrics::{format_counters, Counters, DayStats};
+
+pub struct StatsCommand;
+
+fn stats_embed(
+    i18n: &I18n,
+    channel: &Counters,
+    user_id: UserId,
+    user: &Counters,
+    today: &DayStats,
+) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(i18n.get("stats_title"))
+        .color(0x5865F2)
+        .field(
+            i18n.get("stats_channel"),
+            format_counters(i18n, channel),
+            false,
+        )
+        .field(
+            i18n.get("stats_user"),
+            format!("<@{}>\n{}", user_id, format_counters(i18n, user)),
+            false,
+        )
+        .field(
+            i18n.get("stats_today"),
+            format_counters(i18n, &today.total),
+            false,
+        )
+        .footer(CreateEmbedFooter::new(i18n.get("stats_tokens_note")))
+}
+
+#[async_trait]
+impl SlashCommand for StatsCommand {
+    fn name(&self) -> &'static str {
+        "stats"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_stats_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::User,
+            "user",
+            i18n.get("cmd_stats_opt_user"),
+        )]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let user_id = command
+            .data
+            .options
+            .iter()
+            .find_map(|opt| match (opt.name.as_str(), &opt.value) {
+                ("user", CommandDataOptionValue::User(id)) => Some(*id),
+                _ => None,
+            })
+            .unwrap_or(command.user.id);
+        let channel = state.metrics.channel(command.channel_id.get()).await;
+        let user = state.metrics.user(user_id.get()).await;
+        let today = state.metrics.day(chrono::Local::now().date_naive()).await;
+
+        let i18n = state.i18n.read().await;
+        let embed = stats_embed(&i18n, &channel, user_id, &user, &today);
+        drop(i18n);
+
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().embed(embed))
+            .await?;
+        Ok(())
+    }
+}
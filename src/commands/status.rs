@@ -0,0 +1,155 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{CommandInteraction, Context, CreateEmbed, EditInteractionResponse};
+use std::time::Duration;
+
+use crate::i18n::I18n;
+use crate::session::SessionManager;
+
+pub struct StatusCommand;
+
+/// /status 顯示的頻道狀態；尚未建立 session 時模型與訊息數為 None
+#[derive(Debug)]
+struct StatusReport {
+    backend: String,
+    model: Option<String>,
+    message_count: Option<u64>,
+    processing: bool,
+    pending: usize,
+    session_file_bytes: Option<u64>,
+    uptime: Duration,
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b >= KIB * KIB {
+        format!("{:.1} MiB", b / KIB / KIB)
+    } else if b >= KIB {
+        format!("{:.1} KiB", b / KIB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// 超過一天的運行時間另外顯示天數，其餘交給語系的時間格式
+fn format_uptime(i18n: &I18n, uptime: Duration) -> String {
+    let days = uptime.as_secs() / 86_400;
+    let rest = i18n.format_duration(Duration::from_secs(uptime.as_secs() % 86_400));
+    if days > 0 {
+        i18n.get_args("status_uptime_days", &[days.to_string(), rest])
+    } else {
+        rest
+    }
+}
+
+fn status_embed(i18n: &I18n, report: &StatusReport) -> CreateEmbed {
+    let unknown = || i18n.get("status_unknown");
+    let processing = if report.processing {
+        i18n.get("status_processing_yes")
+    } else {
+        i18n.get("status_processing_no")
+    };
+    CreateEmbed::new()
+        .title(i18n.get("status_title"))
+        .color(0x5865F2)
+        .field(i18n.get("status_backend"), &report.backend, true)
+        .field(
+            i18n.get("status_model"),
+            report.model.clone().unwrap_or_else(unknown),
+            true,
+        )
+        .field(
+            i18n.get("status_messages"),
+            report
+                .message_count
+                .map(|n| i18n.format_number(n))
+                .unwrap_or_else(unknown),
+            true,
+        )
+        .field(i18n.get("status_processing"), processing, true)
+        .field(
+            i18n.get("status_pending"),
+            i18n.format_number(report.pending as u64),
+            true,
+        )
+        .field(
+            i18n.get("status_session_file"),
+            report
+                .session_file_bytes
+                .map(format_bytes)
+                .unwrap_or_else(unknown),
+            true,
+        )
+        .field(
+            i18n.get("status_uptime"),
+            format_uptime(i18n, report.uptime),
+            true,
+        )
+}
+
+#[async_trait]
+impl SlashCommand for StatusCommand {
+    fn name(&self) -> &'static str {
+        "status"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_status_desc")
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let channel_id = command.channel_id.get();
+        let agent_type = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default()
+            .get_agent_type(&channel_id.to_string());
+        // 只查詢已開啟的 session，/status 本身不應啟動後端
+        let agent_state = match state.sessions().get_session(channel_id).await {
+            Some(agent) => agent.get_state().await.ok(),
+            None => None,
+        };
+        let report = StatusReport {
+            backend: agent_type.to_string(),
+            model: agent_state.as_ref().and_then(|s| s.model.clone()),
+            message_count: agent_state.map(|s| s.message_count),
+            processing: state.is_processing(channel_id).await,
+            pending: state.pending_count(channel_id).await,
+            session_file_bytes: SessionManager::session_file_size(&agent_type, channel_id),
+            uptime: state.started_at.elapsed(),
+        };
+
+        let channel_i18n = state.channel_i18n(channel_id).await;
+        let embed = status_embed(&*channel_i18n.read().await, &report);
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().embed(embed))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes_and_uptime() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+
+        let i18n = I18n::new("en");
+        assert_eq!(format_uptime(&i18n, Duration::from_secs(3900)), "1h 5m");
+        assert_eq!(
+            format_uptime(&i18n, Duration::from_secs(2 * 86_400 + 60)),
+            "2d 1m"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use super::SlashCommand;
+use crate::agent::ThinkingSupport;
 use async_trait::async_trait;
 use serenity::all::{
     CommandInteraction, CommandOptionType, Context, CreateCommandOption, EditInteractionResponse,
@@ -47,35 +48,72 @@ impl SlashCommand for ThinkingCommand {
             .and_then(|o| o.value.as_str())
             .unwrap_or("medium");
 
-        let channel_id_u64 = command.channel_id.get();
-        let channel_id_str = channel_id_u64.to_string();
-        let channel_config = crate::commands::agent::ChannelConfig::load()
-            .await
-            .unwrap_or_default();
-        let agent_type = channel_config.get_agent_type(&channel_id_str);
-
-        let (agent, _) = state
-            .session_manager
-            .get_or_create_session(channel_id_u64, agent_type, &state.backend_manager)
+        let msg = apply_level(state, command.channel_id.get(), level).await?;
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
             .await?;
 
-        let i18n = state.i18n.read().await;
-        match agent.set_thinking_level(level).await {
-            Ok(_) => {
-                let msg = i18n.get_args("thinking_set", &[level.to_string()]);
-                command
-                    .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
-                    .await?;
-            }
-            Err(e) => {
-                let msg = i18n.get_args("thinking_failed", &[e.to_string()]);
-                command
-                    .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
-                    .await?;
-            }
-        }
-        drop(i18n);
-
         Ok(())
     }
 }
+
+/// 套用推理等級並回傳要顯示的結果訊息；`/thinking` 與文字指令共用
+pub async fn apply_level(
+    state: &crate::AppState,
+    channel_id_u64: u64,
+    level: &str,
+) -> anyhow::Result<String> {
+    let channel_id_str = channel_id_u64.to_string();
+    let channel_config = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default();
+    let agent_type = channel_config.get_agent_type(&channel_id_str);
+
+    let (agent, _) = state
+        .sessions()
+        .get_or_create_session(channel_id_u64, agent_type)
+        .await?;
+
+    // 後端不回報等級時，以此頻道上次設定的等級作為原本的值
+    let saved = channel_config
+        .channels
+        .get(&channel_id_str)
+        .and_then(|e| e.thinking_level.clone());
+    let before = agent
+        .get_state()
+        .await
+        .ok()
+        .and_then(|s| s.thinking_level)
+        .or(saved);
+
+    let i18n = state.i18n.read().await;
+    let before = before.unwrap_or_else(|| i18n.get("state_unknown"));
+    let msg = match agent.set_thinking_level(level).await {
+        Ok(support) => {
+            // 記住等級，之後重建 session 或匯出配方時沿用
+            crate::commands::agent::ChannelConfig::update(|cfg| {
+                if let Some(entry) = cfg.channels.get_mut(&channel_id_str) {
+                    entry.thinking_level = Some(level.to_string());
+                }
+            })
+            .await?;
+            let after = agent.get_state().await.ok().and_then(|s| s.thinking_level);
+            match (support, after) {
+                (ThinkingSupport::Applied(_), Some(after)) if after != level => {
+                    i18n.get_args("thinking_change_ignored", &[level.to_string(), after])
+                }
+                (ThinkingSupport::Applied(None), _) => {
+                    i18n.get_args("thinking_set", &[before, level.to_string()])
+                }
+                (ThinkingSupport::Applied(Some(detail)), _) => {
+                    i18n.get_args("thinking_set_detail", &[before, level.to_string(), detail])
+                }
+                (ThinkingSupport::Unsupported, _) => {
+                    i18n.get_args("thinking_unsupported", &[level.to_string()])
+                }
+            }
+        }
+        Err(e) => i18n.get_args("thinking_failed", &[e.to_string()]),
+    };
+    Ok(msg)
+}
@@ -0,0 +1,126 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelType, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context, CreateCommandOption, CreateMessage, CreateThread, EditInteractionResponse,
+};
+use tracing::{info, warn};
+
+use super::agent::ChannelConfig;
+use crate::i18n::I18n;
+use crate::threads;
+
+pub struct ThreadCommand;
+
+fn topic_option(opts: &[CommandDataOption]) -> Option<&str> {
+    opts.iter()
+        .find(|o| o.name == "topic")
+        .and_then(|o| o.value.as_str())
+        .filter(|t| !t.trim().is_empty())
+}
+
+#[async_trait]
+impl SlashCommand for ThreadCommand {
+    fn name(&self) -> &'static str {
+        "thread"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_thread_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "new",
+            i18n.get("cmd_thread_new_desc"),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "topic",
+                i18n.get("cmd_thread_topic_desc"),
+            )
+            .required(true),
+        )]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let i18n = state.channel_i18n(command.channel_id.get()).await;
+        let topic = match command.data.options.first() {
+            Some(sub) if sub.name == "new" => match &sub.value {
+                CommandDataOptionValue::SubCommand(opts) => topic_option(opts),
+                _ => None,
+            },
+            _ => None,
+        };
+        let (Some(guild_id), Some(topic)) = (command.guild_id, topic) else {
+            let msg = i18n.read().await.get("thread_guild_only");
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+                .await?;
+            return Ok(());
+        };
+
+        // 討論串內不能再開討論串，改在其父頻道建立
+        let parent = threads::parent_channel(ctx, guild_id, command.channel_id)
+            .await
+            .unwrap_or(command.channel_id);
+        let thread = parent
+            .create_thread(
+                &ctx.http,
+                CreateThread::new(threads::thread_name(topic)).kind(ChannelType::PublicThread),
+            )
+            .await?;
+        let thread_id = thread.id.get();
+        let parent_key = parent.to_string();
+        let thread_key = thread.id.to_string();
+        let config = ChannelConfig::update(|cfg| {
+            cfg.inherit_parent(&thread_key, &parent_key);
+            cfg.apply_guild_defaults(&thread_key, &guild_id.to_string());
+            cfg.clone()
+        })
+        .await
+        .unwrap_or_default();
+        info!(
+            "🧵 Created thread {} under channel {} for {}",
+            thread_id, parent, command.user.name
+        );
+
+        // 先開好 session，討論串的第一則訊息不必等後端啟動
+        if let Err(e) =
+            crate::Handler::open_session(state, thread_id, config.get_agent_type(&thread_key)).await
+        {
+            warn!("⚠️ Failed to open session for thread {}: {}", thread_id, e);
+        }
+
+        let (intro, created) = {
+            let i18n = i18n.read().await;
+            (
+                i18n.get_args(
+                    "thread_started",
+                    &[format!("<@{}>", command.user.id), topic.trim().to_string()],
+                ),
+                i18n.get_args("thread_created", &[format!("<#{}>", thread_id)]),
+            )
+        };
+        if let Err(e) = thread
+            .id
+            .send_message(&ctx.http, CreateMessage::new().content(intro))
+            .await
+        {
+            warn!("⚠️ Failed to post intro in thread {}: {}", thread_id, e);
+        }
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(created))
+            .await?;
+        Ok(())
+    }
+}
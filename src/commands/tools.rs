@@ -0,0 +1,185 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommandOption,
+};
+
+use crate::agent::ToolInfo;
+use crate::delivery::MESSAGE_MAX_CHARS;
+use crate::i18n::I18n;
+
+pub struct ToolsCommand;
+
+/// 工具說明只取第一行，過長時截斷
+const DESCRIPTION_MAX_CHARS: usize = 100;
+
+fn short_description(description: &str) -> String {
+    let line = description.lines().next().unwrap_or("").trim();
+    if line.chars().count() > DESCRIPTION_MAX_CHARS {
+        let cut: String = line.chars().take(DESCRIPTION_MAX_CHARS - 1).collect();
+        format!("{}…", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+/// 每個工具一行 (啟用狀態、名稱、說明)；超過單則訊息上限時以「還有 N 個」結尾
+fn format_tool_list(i18n: &I18n, backend: &str, tools: &[ToolInfo], disabled: &[String]) -> String {
+    let disabled_count = tools.iter().filter(|t| disabled.contains(&t.name)).count();
+    let mut out = i18n.get_args(
+        "tools_header",
+        &[
+            backend.to_string(),
+            (tools.len() - disabled_count).to_string(),
+            disabled_count.to_string(),
+        ],
+    );
+    for (i, tool) in tools.iter().enumerate() {
+        let mark = if disabled.contains(&tool.name) {
+            "🚫"
+        } else {
+            "✅"
+        };
+        let description = short_description(&tool.description);
+        let line = if description.is_empty() {
+            format!("\n{} `{}`", mark, tool.name)
+        } else {
+            format!("\n{} `{}` — {}", mark, tool.name, description)
+        };
+        let more = format!(
+            "\n{}",
+            i18n.get_args("tools_more", &[(tools.len() - i).to_string()])
+        );
+        if out.chars().count() + line.chars().count() + more.chars().count() > MESSAGE_MAX_CHARS {
+            out.push_str(&more);
+            break;
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+#[async_trait]
+impl SlashCommand for ToolsCommand {
+    fn name(&self) -> &'static str {
+        "tools"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_tools_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "disable",
+                i18n.get("cmd_tools_opt_disable"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "enable",
+                i18n.get("cmd_tools_opt_enable"),
+            ),
+        ]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let channel_id = command.channel_id.to_string();
+        let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+        let channel_config = crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default();
+        let agent_type = channel_config.get_agent_type(&channel_id);
+
+        // 有 enable / disable 參數時修改頻道的工具政策，只限管理員
+        let mut change = None;
+        for opt in &command.data.options {
+            if let CommandDataOptionValue::String(name) = &opt.value {
+                let name = name.trim().to_string();
+                if !name.is_empty() {
+                    change = Some((opt.name == "disable", name));
+                }
+            }
+        }
+        if let Some((disable, name)) = change {
+            if !super::is_admin(command.member.as_deref(), command.guild_id.is_some()) {
+                let msg = channel_i18n.read().await.get("tools_admin_only");
+                return reply(ctx, command, msg).await;
+            }
+            crate::commands::agent::ChannelConfig::update(|cfg| {
+                let entry = cfg.entry_mut(&channel_id);
+                entry.disabled_tools.retain(|t| t != &name);
+                if disable {
+                    entry.disabled_tools.push(name.clone());
+                }
+            })
+            .await?;
+            let key = if disable {
+                "tools_disabled"
+            } else {
+                "tools_enabled"
+            };
+            let msg = channel_i18n.read().await.get_args(key, &[name]);
+            return reply(ctx, command, msg).await;
+        }
+
+        let (agent, _) = state
+            .sessions()
+            .get_or_create_session(command.channel_id.get(), agent_type)
+            .await?;
+        let backend = agent.agent_type().to_string();
+        let disabled = channel_config
+            .channels
+            .get(&channel_id)
+            .map(|e| e.disabled_tools.clone())
+            .unwrap_or_default();
+        let i18n = channel_i18n.read().await;
+        let msg = match agent.list_tools().await {
+            Ok(tools) if tools.is_empty() => i18n.get_args("tools_none", &[backend]),
+            Ok(tools) => format_tool_list(&i18n, &backend, &tools, &disabled),
+            Err(e) => i18n.get_args("tools_unsupported", &[backend, e.to_string()]),
+        };
+        drop(i18n);
+        reply(ctx, command, msg).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, description: &str) -> ToolInfo {
+        ToolInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_tool_list_marks_disabled_and_fits_message() {
+        let i18n = I18n::new("en");
+        let tools = vec![
+            tool("bash", "Run a shell command\nMore details here"),
+            tool("webfetch", ""),
+        ];
+        let text = format_tool_list(&i18n, "opencode", &tools, &["bash".to_string()]);
+        assert!(text.contains("🚫 `bash` — Run a shell command"));
+        assert!(!text.contains("More details"));
+        assert!(text.contains("✅ `webfetch`"));
+
+        let many: Vec<_> = (0..200)
+            .map(|i| tool(&format!("tool_{}", i), &"x".repeat(200)))
+            .collect();
+        let text = format_tool_list(&i18n, "opencode", &many, &[]);
+        assert!(text.chars().count() <= MESSAGE_MAX_CHARS);
+        assert!(text.ends_with("more"));
+    }
+}
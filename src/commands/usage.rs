@@ -0,0 +1,224 @@
+use super::{reply, SlashCommand};
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelType, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context, CreateCommandOption,
+};
+
+use crate::i18n::I18n;
+use crate::usage_caps::{next_reset, GuildCap, GuildEntry};
+
+pub struct UsageCommand;
+
+/// `/usage set` 的輸入轉成上限設定；錯誤時回傳對應的語系 key
+fn parse_cap(
+    opts: &[CommandDataOption],
+    default_alert_channel: u64,
+) -> Result<GuildCap, &'static str> {
+    let mut cap = GuildCap {
+        max_turns: None,
+        max_tokens: None,
+        fallback_model: None,
+        alert_channel: default_alert_channel,
+    };
+    for opt in opts {
+        match (opt.name.as_str(), &opt.value) {
+            ("turns", CommandDataOptionValue::Integer(n)) if *n > 0 => {
+                cap.max_turns = Some(*n as u64)
+            }
+            ("tokens", CommandDataOptionValue::Integer(n)) if *n > 0 => {
+                cap.max_tokens = Some(*n as u64)
+            }
+            ("fallback_model", CommandDataOptionValue::String(s)) => {
+                cap.fallback_model = Some(s.trim().to_string())
+            }
+            ("alert_channel", CommandDataOptionValue::Channel(id)) => cap.alert_channel = id.get(),
+            _ => {}
+        }
+    }
+    if cap.max_turns.is_none() && cap.max_tokens.is_none() {
+        return Err("usage_cap_missing_limit");
+    }
+    if cap.fallback_model.is_some() && cap.fallback().is_none() {
+        return Err("usage_cap_invalid_model");
+    }
+    Ok(cap)
+}
+
+fn describe(i18n: &I18n, entry: &GuildEntry) -> String {
+    let limit = |value: Option<u64>| {
+        value
+            .map(|v| i18n.format_number(v))
+            .unwrap_or_else(|| i18n.get("usage_unlimited"))
+    };
+    let action = match &entry.cap.fallback_model {
+        Some(model) => i18n.get_args("usage_action_fallback", std::slice::from_ref(model)),
+        None => i18n.get("usage_action_pause"),
+    };
+    let mut msg = i18n.get_args(
+        "usage_guild_status",
+        &[
+            entry.usage.period.clone(),
+            i18n.format_number(entry.usage.turns),
+            limit(entry.cap.max_turns),
+            i18n.format_number(entry.usage.tokens),
+            limit(entry.cap.max_tokens),
+            i18n.format_decimal(entry.cap.ratio(&entry.usage) * 100.0, 0),
+            action,
+            entry.cap.alert_channel.to_string(),
+            next_reset(&chrono::Local::now()).timestamp().to_string(),
+        ],
+    );
+    if entry.usage.exceeded {
+        msg.push('\n');
+        msg.push_str(&i18n.get("usage_state_exceeded"));
+    }
+    msg
+}
+
+#[async_trait]
+impl SlashCommand for UsageCommand {
+    fn name(&self) -> &'static str {
+        "usage"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_usage_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "guild",
+                i18n.get("cmd_usage_guild_desc"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                i18n.get("cmd_usage_set_desc"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "turns",
+                    i18n.get("cmd_usage_opt_turns"),
+                )
+                .min_int_value(1),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "tokens",
+                    i18n.get("cmd_usage_opt_tokens"),
+                )
+                .min_int_value(1),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "fallback_model",
+                i18n.get("cmd_usage_opt_fallback_model"),
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "alert_channel",
+                    i18n.get("cmd_usage_opt_alert_channel"),
+                )
+                .channel_types(vec![ChannelType::Text, ChannelType::News]),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "off",
+                i18n.get("cmd_usage_off_desc"),
+            ),
+        ]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+        let Some(guild_id) = command.guild_id else {
+            let msg = channel_i18n.read().await.get("usage_guild_only");
+            return reply(ctx, command, msg).await;
+        };
+        let Some(sub) = command.data.options.first() else {
+            return Ok(());
+        };
+        // 查看用量開放給所有人，設定與移除上限限管理員
+        if sub.name != "guild" && !super::is_admin(command.member.as_deref(), true) {
+            let msg = channel_i18n.read().await.get("usage_admin_only");
+            return reply(ctx, command, msg).await;
+        }
+
+        let msg = match (sub.name.as_str(), &sub.value) {
+            ("guild", _) => {
+                let i18n = channel_i18n.read().await;
+                match state.usage().get(guild_id.get()).await {
+                    Some(entry) => describe(&i18n, &entry),
+                    None => i18n.get("usage_no_cap"),
+                }
+            }
+            ("set", CommandDataOptionValue::SubCommand(opts)) => {
+                match parse_cap(opts, command.channel_id.get()) {
+                    Ok(cap) => {
+                        state.usage().set(guild_id.get(), cap).await?;
+                        let entry = state.usage().get(guild_id.get()).await;
+                        let i18n = channel_i18n.read().await;
+                        entry
+                            .map(|entry| describe(&i18n, &entry))
+                            .unwrap_or_default()
+                    }
+                    Err(key) => channel_i18n.read().await.get(key),
+                }
+            }
+            ("off", _) => {
+                let removed = state.usage().remove(guild_id.get()).await?;
+                channel_i18n.read().await.get(if removed {
+                    "usage_cap_removed"
+                } else {
+                    "usage_no_cap"
+                })
+            }
+            _ => return Ok(()),
+        };
+        reply(ctx, command, msg).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage_caps::GuildUsage;
+
+    #[test]
+    fn test_describe_shows_usage_against_limits() {
+        let i18n = I18n::new("en");
+        let entry = GuildEntry {
+            cap: GuildCap {
+                max_turns: None,
+                max_tokens: Some(2_000_000),
+                fallback_model: Some("openai/gpt-4o-mini".to_string()),
+                alert_channel: 42,
+            },
+            usage: GuildUsage {
+                period: "2026-03".to_string(),
+                turns: 120,
+                tokens: 1_700_000,
+                warned: true,
+                ..GuildUsage::default()
+            },
+        };
+        let msg = describe(&i18n, &entry);
+        assert!(msg.contains("1,700,000 / 2,000,000"));
+        assert!(msg.contains("85%"));
+        assert!(msg.contains("openai/gpt-4o-mini"));
+        assert!(msg.contains("<#42>"));
+    }
+}
@@ -0,0 +1,100 @@
+use super::SlashCommand;
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelId, CommandInteraction, CommandOptionType, Context, CreateCommandOption,
+    EditInteractionResponse, GuildId, UserId,
+};
+
+use crate::i18n::I18n;
+
+pub struct VoiceCommand;
+
+/// 呼叫者目前所在的語音頻道 (來自 gateway 快取)
+fn caller_voice_channel(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Option<ChannelId> {
+    ctx.cache
+        .guild(guild_id)
+        .and_then(|g| g.voice_states.get(&user_id).and_then(|v| v.channel_id))
+}
+
+#[async_trait]
+impl SlashCommand for VoiceCommand {
+    fn name(&self) -> &'static str {
+        "voice"
+    }
+
+    fn description(&self, i18n: &I18n) -> String {
+        i18n.get("cmd_voice_desc")
+    }
+
+    fn options(&self, i18n: &I18n) -> Vec<CreateCommandOption> {
+        vec![
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "join",
+                i18n.get("cmd_voice_join_desc"),
+            ),
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "leave",
+                i18n.get("cmd_voice_leave_desc"),
+            ),
+        ]
+    }
+
+    async fn execute(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        state: &crate::AppState,
+    ) -> anyhow::Result<()> {
+        command.defer_ephemeral(&ctx.http).await?;
+
+        let channel_i18n = state.channel_i18n(command.channel_id.get()).await;
+        let sub = command.data.options.first().map(|o| o.name.as_str());
+        let msg = match (command.guild_id, sub) {
+            (None, _) => channel_i18n.read().await.get("voice_guild_only"),
+            (Some(_), _) if !state.voice.is_enabled() => {
+                channel_i18n.read().await.get("voice_not_configured")
+            }
+            (Some(guild_id), Some("join")) => {
+                match caller_voice_channel(ctx, guild_id, command.user.id) {
+                    None => channel_i18n.read().await.get("voice_join_needs_channel"),
+                    Some(voice_channel) => match state
+                        .voice
+                        .join(
+                            guild_id.get(),
+                            voice_channel.get(),
+                            command.channel_id.get(),
+                        )
+                        .await
+                    {
+                        Ok(()) => channel_i18n.read().await.get_args(
+                            "voice_joined",
+                            &[
+                                format!("<#{}>", voice_channel),
+                                format!("<#{}>", command.channel_id),
+                            ],
+                        ),
+                        Err(e) => channel_i18n
+                            .read()
+                            .await
+                            .get_args("voice_join_failed", &[e.to_string()]),
+                    },
+                }
+            }
+            (Some(guild_id), Some("leave")) => {
+                let key = if state.voice.leave(guild_id.get()).await {
+                    "voice_left"
+                } else {
+                    "voice_not_joined"
+                };
+                channel_i18n.read().await.get(key)
+            }
+            _ => return Ok(()),
+        };
+        command
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlockType {
@@ -8,12 +9,38 @@ pub enum BlockType {
     ToolOutput,
 }
 
+/// 工具執行計時：開始時間、完成後的耗時與慢速警示門檻
+#[derive(Debug, Clone, Copy)]
+pub struct ToolTiming {
+    pub started: Instant,
+    pub elapsed: Option<Duration>,
+    pub warn_after: Option<Duration>,
+}
+
+impl ToolTiming {
+    fn decorate(&self, label: &str, now: Instant) -> String {
+        let elapsed = self
+            .elapsed
+            .unwrap_or_else(|| now.saturating_duration_since(self.started));
+        let slow = self.warn_after.is_some_and(|limit| elapsed >= limit);
+        match (self.elapsed.is_some(), slow) {
+            (true, false) => format!("{} ({:.1}s)", label, elapsed.as_secs_f64()),
+            (true, true) => format!("⚠️ {} ({:.1}s)", label, elapsed.as_secs_f64()),
+            (false, true) => format!("⚠️ {} (⏳ {}s)", label, elapsed.as_secs()),
+            (false, false) => label.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Block {
     pub id: Option<String>,
     pub block_type: BlockType,
     pub content: String,
     pub label: Option<String>,
+    pub timing: Option<ToolTiming>,
+    /// 分頁模式下已移到先前訊息的字數；None 表示尚未送出任何部分
+    pub flushed: Option<usize>,
 }
 
 impl Block {
@@ -23,6 +50,8 @@ impl Block {
             block_type,
             content,
             label: None,
+            timing: None,
+            flushed: None,
         }
     }
     pub fn with_id(block_type: BlockType, content: String, id: String) -> Self {
@@ -31,6 +60,8 @@ impl Block {
             block_type,
             content,
             label: None,
+            timing: None,
+            flushed: None,
         }
     }
     pub fn with_label(block_type: BlockType, label: String, id: Option<String>) -> Self {
@@ -39,33 +70,76 @@ impl Block {
             block_type,
             content: String::new(),
             label: Some(label),
+            timing: None,
+            flushed: None,
         }
     }
 
+    /// 尚未送出的部分；整塊都已在先前頁面時回傳 None
+    fn unflushed(&self) -> Option<Block> {
+        let Some(done) = self.flushed else {
+            return Some(self.clone());
+        };
+        if self.block_type == BlockType::ToolCall {
+            return None;
+        }
+        let rest: String = self.content.chars().skip(done).collect();
+        if rest.is_empty() {
+            return None;
+        }
+        Some(Block {
+            content: rest,
+            flushed: None,
+            ..self.clone()
+        })
+    }
+
     /// 純渲染邏輯，不修改 content 原始數據
     pub fn render(&self) -> String {
+        self.render_limited(TOOL_OUTPUT_DEFAULT_CHARS, usize::MAX)
+    }
+
+    /// 依預算渲染：工具輸出保留開頭 `tool_limit` 字，思考保留最後 `thinking_limit` 字
+    fn render_limited(&self, tool_limit: usize, thinking_limit: usize) -> String {
         match &self.block_type {
             BlockType::Thinking => {
-                if self.content.trim().is_empty() {
+                if self.content.trim().is_empty() || thinking_limit == 0 {
                     return String::new();
                 }
-                self.content
+                // 思考過程越新越有參考價值，截斷時保留尾端
+                let char_count = self.content.chars().count();
+                let content = if char_count > thinking_limit {
+                    let skip = char_count - thinking_limit;
+                    match self.content.char_indices().nth(skip) {
+                        Some((byte_pos, _)) => format!("…{}", &self.content[byte_pos..]),
+                        None => self.content.clone(),
+                    }
+                } else {
+                    self.content.clone()
+                };
+                content
                     .lines()
                     .map(|l| format!("> {}", l))
                     .collect::<Vec<_>>()
                     .join("\n")
             }
             BlockType::Text => self.content.clone(),
-            BlockType::ToolCall => self.label.as_deref().unwrap_or("🛠️ **Tool:**").to_string(),
+            BlockType::ToolCall => {
+                let label = self.label.as_deref().unwrap_or("🛠️ **Tool:**");
+                match &self.timing {
+                    Some(timing) => timing.decorate(label, Instant::now()),
+                    None => label.to_string(),
+                }
+            }
             BlockType::ToolOutput => {
-                if self.content.trim().is_empty() {
+                if self.content.trim().is_empty() || tool_limit == 0 {
                     return String::new();
                 }
 
-                // 強化截斷：單個工具輸出限制在 500 字元，且保留開頭（通常開頭更有用）
+                // 強化截斷：單個工具輸出預設限制在 500 字元，且保留開頭（通常開頭更有用）
                 let char_count = self.content.chars().count();
-                let display_content = if char_count > 500 {
-                    if let Some((byte_pos, _)) = self.content.char_indices().nth(500) {
+                let display_content = if char_count > tool_limit {
+                    if let Some((byte_pos, _)) = self.content.char_indices().nth(tool_limit) {
                         format!("{}... (truncated)", &self.content[..byte_pos])
                     } else {
                         self.content.clone()
@@ -82,10 +156,90 @@ impl Block {
     }
 }
 
+const TOOL_OUTPUT_DEFAULT_CHARS: usize = 500;
+
+/// 內容逼近上限時，思考與工具輸出最少保留的字數
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockMinimums {
+    pub thinking: usize,
+    pub tool_output: usize,
+}
+
+impl Default for BlockMinimums {
+    fn default() -> Self {
+        Self {
+            thinking: 200,
+            tool_output: 120,
+        }
+    }
+}
+
+/// 多 Embed 模式下的內容分區
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Section {
+    Thinking,
+    Tools,
+    Answer,
+}
+
+impl Section {
+    fn of(block_type: &BlockType) -> Self {
+        match block_type {
+            BlockType::Thinking => Section::Thinking,
+            BlockType::ToolCall | BlockType::ToolOutput => Section::Tools,
+            BlockType::Text => Section::Answer,
+        }
+    }
+}
+
+// 分區預算過小時直接省略該分區，避免只剩截斷提示
+const MIN_SECTION_BUDGET: usize = 100;
+
+/// 分頁在程式碼區塊中間切開時，前一頁補上的結尾與下一頁補上的開頭
+const FENCE_CLOSE: &str = "\n```";
+const FENCE_OPEN: &str = "```\n";
+
+/// 長回答的切點：優先在換行，其次在空白，都太前面時直接在 `max_chars` 處切開
+fn split_point(text: &str, max_chars: usize) -> usize {
+    let head: Vec<char> = text.chars().take(max_chars).collect();
+    if head.len() < max_chars {
+        return head.len();
+    }
+    let min = max_chars / 2;
+    let last = |c: char| head.iter().rposition(|&h| h == c).filter(|&i| i >= min);
+    last('\n')
+        .or_else(|| last(' '))
+        .map(|i| i + 1)
+        .unwrap_or(max_chars)
+}
+
 pub struct EmbedComposer {
     pub blocks: VecDeque<Block>,
     max_len: usize,
+    minimums: BlockMinimums,
     pub has_truncated: bool,
+    /// 本輪圖片附件的處理方式，顯示於頁尾
+    pub image_mode: Option<crate::agent::ImageInputMode>,
+    /// 回合進行中注入的追加指示數，顯示於頁尾
+    pub steered: usize,
+    /// 工具執行超過此時間即加上警示
+    slow_tool_after: Option<Duration>,
+    /// 最後一次收到後端串流事件的時間，用來決定是否顯示輸入中
+    pub last_activity: Instant,
+    /// 工具的完整輸出 (不受區塊數與字數限制)，回合結束後保存供「完整輸出」按鈕查看
+    pub tool_log: crate::tool_outputs::ToolOutputLog,
+    /// 分頁模式：超過上限的內容接續到後續訊息，不再截斷
+    paginate: bool,
+    /// 上一頁在程式碼區塊中間切開，目前這頁開頭需重新開啟程式碼區塊
+    fence_open: bool,
+    /// 後端回報本輪回答因輸出長度上限而中斷
+    pub output_truncated: bool,
+    /// 「繼續」接續的先前回答；完整回答為它加上本輪內容
+    continues: Option<String>,
+    /// 後端回報的引用來源，回合結束時整理成來源清單
+    pub citations: Vec<crate::agent::Citation>,
+    /// 後端回報的本輪 (輸入, 輸出) token 數；未回報時為 None
+    pub token_usage: Option<(u64, u64)>,
 }
 
 impl EmbedComposer {
@@ -93,12 +247,57 @@ impl EmbedComposer {
         Self {
             blocks: VecDeque::new(),
             max_len,
+            minimums: BlockMinimums::default(),
             has_truncated: false,
+            image_mode: None,
+            steered: 0,
+            slow_tool_after: None,
+            last_activity: Instant::now(),
+            tool_log: Default::default(),
+            paginate: false,
+            fence_open: false,
+            output_truncated: false,
+            continues: None,
+            citations: Vec::new(),
+            token_usage: None,
         }
     }
 
+    pub fn with_minimums(mut self, minimums: BlockMinimums) -> Self {
+        self.minimums = minimums;
+        self
+    }
+
+    /// 0 表示不顯示慢速警示
+    pub fn with_slow_tool_warning(mut self, secs: u64) -> Self {
+        self.slow_tool_after = (secs > 0).then(|| Duration::from_secs(secs));
+        self
+    }
+
+    pub fn with_pagination(mut self, enabled: bool) -> Self {
+        self.paginate = enabled;
+        self
+    }
+
+    /// 接續先前被截斷的回答：本輪內容接在它後面，停在程式碼區塊中間時本輪開頭重新開啟
+    pub fn with_continuation(mut self, previous: Option<String>) -> Self {
+        self.fence_open = previous
+            .as_deref()
+            .is_some_and(|p| p.matches("```").count() % 2 == 1);
+        self.continues = previous;
+        self
+    }
+
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
     /// 主動物理截斷：保持記憶體中的數據量在合理範圍
     fn prune(&mut self) {
+        // 分頁模式不丟棄區塊：已送出的區塊若被移除，後端同步內容時會被當成新內容再送一次
+        if self.paginate {
+            return;
+        }
         // 硬性限制：只保留最後 10 個 Block
         while self.blocks.len() > 10 {
             self.blocks.pop_front();
@@ -175,11 +374,41 @@ impl EmbedComposer {
                 return;
             }
         }
-        self.blocks
-            .push_back(Block::with_label(BlockType::ToolCall, label, Some(id)));
+        let mut block = Block::with_label(BlockType::ToolCall, label, Some(id));
+        block.timing = Some(ToolTiming {
+            started: Instant::now(),
+            elapsed: None,
+            warn_after: self.slow_tool_after,
+        });
+        self.blocks.push_back(block);
         self.prune();
     }
 
+    /// 記錄工具耗時；重複的結束事件不覆蓋第一次的結果
+    pub fn finish_tool_call(&mut self, id: &str) {
+        let now = Instant::now();
+        if let Some(timing) = self
+            .blocks
+            .iter_mut()
+            .filter(|b| b.id.as_deref() == Some(id) && b.block_type == BlockType::ToolCall)
+            .find_map(|b| b.timing.as_mut())
+        {
+            if timing.elapsed.is_none() {
+                timing.elapsed = Some(now.saturating_duration_since(timing.started));
+            }
+        }
+    }
+
+    /// 回合結束時仍未收到結束事件的工具一律以當下時間收尾
+    pub fn finish_all_tool_calls(&mut self) {
+        let now = Instant::now();
+        for timing in self.blocks.iter_mut().filter_map(|b| b.timing.as_mut()) {
+            if timing.elapsed.is_none() {
+                timing.elapsed = Some(now.saturating_duration_since(timing.started));
+            }
+        }
+    }
+
     pub fn sync_content(&mut self, items: Vec<Block>) {
         if items.is_empty() {
             return;
@@ -197,6 +426,12 @@ impl EmbedComposer {
                 if merged.id.is_none() {
                     merged.id = local.id.clone();
                 }
+                if merged.timing.is_none() {
+                    merged.timing = local.timing;
+                }
+                if merged.flushed.is_none() {
+                    merged.flushed = local.flushed;
+                }
             }
             new_list.push_back(merged);
         }
@@ -210,18 +445,15 @@ impl EmbedComposer {
     }
 
     pub fn render(&self) -> String {
+        if self.paginate || self.fence_open {
+            return self.pending_page().render();
+        }
         if self.blocks.is_empty() {
             return String::new();
         }
 
-        // 1. 合併塊渲染
-        let renderings: Vec<String> = self
-            .blocks
-            .iter()
-            .map(|b| b.render())
-            .filter(|r| !r.is_empty())
-            .collect();
-        let mut res = renderings.join("\n\n");
+        // 1. 合併塊渲染（超出預算時先犧牲思考與工具輸出，保住回答正文）
+        let mut res = self.render_within_budget();
 
         // 2. 物理截斷顯示與 4096 硬性保險
         let char_count = res.chars().count();
@@ -246,6 +478,256 @@ impl EmbedComposer {
 
         res.trim().to_string()
     }
+
+    /// 依分區拆開渲染，回答優先取得預算，其餘由工具與思考分攤 `total_budget`
+    /// (Discord 單則訊息所有 Embed 合計上限 6000 字)。空分區不回傳。
+    pub fn render_sections(&self, total_budget: usize) -> Vec<(Section, String)> {
+        let section_view = |section: Section, max_len: usize| -> String {
+            if max_len < MIN_SECTION_BUDGET {
+                return String::new();
+            }
+            EmbedComposer {
+                blocks: self
+                    .blocks
+                    .iter()
+                    .filter(|b| Section::of(&b.block_type) == section)
+                    .cloned()
+                    .collect(),
+                max_len,
+                minimums: self.minimums,
+                has_truncated: self.has_truncated && section == Section::Answer,
+                image_mode: None,
+                steered: 0,
+                slow_tool_after: self.slow_tool_after,
+                last_activity: self.last_activity,
+                tool_log: Default::default(),
+                paginate: false,
+                fence_open: false,
+                output_truncated: false,
+                continues: None,
+                citations: Vec::new(),
+                token_usage: None,
+            }
+            .render()
+        };
+        let has = |section: Section| {
+            self.blocks
+                .iter()
+                .any(|b| Section::of(&b.block_type) == section)
+        };
+
+        let answer = section_view(Section::Answer, self.max_len.min(total_budget));
+        let mut remaining = total_budget.saturating_sub(answer.chars().count());
+        let tools_budget = if has(Section::Thinking) {
+            remaining / 2
+        } else {
+            remaining
+        };
+        let tools = section_view(Section::Tools, self.max_len.min(tools_budget));
+        remaining = remaining.saturating_sub(tools.chars().count());
+        let thinking = section_view(Section::Thinking, self.max_len.min(remaining));
+
+        [
+            (Section::Thinking, thinking),
+            (Section::Tools, tools),
+            (Section::Answer, answer),
+        ]
+        .into_iter()
+        .filter(|(_, body)| !body.is_empty())
+        .collect()
+    }
+
+    /// 分頁模式下目前這則訊息的內容：只含尚未移到先前頁面的部分
+    fn pending_page(&self) -> EmbedComposer {
+        let mut blocks: VecDeque<Block> = self.blocks.iter().filter_map(Block::unflushed).collect();
+        if self.fence_open {
+            if let Some(first) = blocks
+                .front_mut()
+                .filter(|b| b.block_type == BlockType::Text)
+            {
+                first.content = format!("{}{}", FENCE_OPEN, first.content);
+            }
+        }
+        EmbedComposer {
+            blocks,
+            max_len: self.max_len,
+            minimums: self.minimums,
+            has_truncated: self.has_truncated,
+            image_mode: None,
+            steered: 0,
+            slow_tool_after: self.slow_tool_after,
+            last_activity: self.last_activity,
+            tool_log: Default::default(),
+            paginate: false,
+            fence_open: false,
+            output_truncated: false,
+            continues: None,
+            citations: Vec::new(),
+            token_usage: None,
+        }
+    }
+
+    /// 分頁模式下，未送出的內容超過上限時依序切出已滿的頁面並標記為已送出；
+    /// 呼叫端把每頁定稿在目前訊息後改在新訊息繼續串流
+    pub fn take_pages(&mut self) -> Vec<String> {
+        let mut pages = Vec::new();
+        while let Some(page) = self.take_page() {
+            pages.push(page);
+        }
+        pages
+    }
+
+    fn take_page(&mut self) -> Option<String> {
+        if !self.paginate {
+            return None;
+        }
+        let pending = self.pending_page();
+        let full = pending
+            .blocks
+            .iter()
+            .map(Block::render)
+            .filter(|r| !r.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if full.chars().count() <= self.max_len {
+            return None;
+        }
+
+        let limit = self.max_len - FENCE_CLOSE.len();
+        let prefix = if self.fence_open { FENCE_OPEN } else { "" };
+        let mut page = prefix.to_string();
+        let mut split_text = false;
+        for block in self.blocks.iter_mut() {
+            let Some(view) = block.unflushed() else {
+                continue;
+            };
+            let rendered = view.render();
+            if rendered.is_empty() {
+                continue;
+            }
+            let sep = if page.len() > prefix.len() {
+                "\n\n"
+            } else {
+                ""
+            };
+            let used = page.chars().count() + sep.len();
+            let total_chars = block.content.chars().count();
+            if used + rendered.chars().count() <= limit {
+                page.push_str(sep);
+                page.push_str(&rendered);
+                block.flushed = Some(total_chars);
+                continue;
+            }
+
+            let room = limit.saturating_sub(used);
+            if block.block_type == BlockType::Text && room >= MIN_SECTION_BUDGET {
+                // 回答正文在換行或空白處切開，其餘留到下一頁
+                let cut = split_point(&view.content, room);
+                let head: String = view.content.chars().take(cut).collect();
+                page.push_str(sep);
+                page.push_str(head.trim_end());
+                block.flushed = Some(block.flushed.unwrap_or(0) + cut);
+                split_text = true;
+            } else if page.len() == prefix.len() {
+                // 單一區塊就超過一頁 (如很長的思考)：縮減後整塊送出
+                let shrunk: String = view
+                    .render_limited(self.minimums.tool_output, limit / 2)
+                    .chars()
+                    .take(limit)
+                    .collect();
+                page.push_str(&shrunk);
+                block.flushed = Some(total_chars);
+            }
+            break;
+        }
+        if page.len() == prefix.len() {
+            return None;
+        }
+
+        self.fence_open = split_text && !page.matches("```").count().is_multiple_of(2);
+        if self.fence_open {
+            page.push_str(FENCE_CLOSE);
+        }
+        Some(page.trim().to_string())
+    }
+
+    /// 不受長度預算限制的完整回答正文；接續的回答會併入先前的內容
+    pub fn render_answer_text(&self) -> String {
+        // 接續的內容可能從行中或縮排開始，不能去掉開頭的空白
+        let trim: fn(&str) -> &str = if self.continues.is_some() {
+            str::trim_end
+        } else {
+            str::trim
+        };
+        let own = self
+            .blocks
+            .iter()
+            .filter(|b| b.block_type == BlockType::Text)
+            .map(|b| trim(&b.content))
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        match &self.continues {
+            Some(previous) => format!("{}{}", previous, own),
+            None => own,
+        }
+    }
+
+    fn render_within_budget(&self) -> String {
+        let join = |limits: &[(usize, usize)]| -> String {
+            self.blocks
+                .iter()
+                .zip(limits)
+                .map(|(b, (tool, thinking))| b.render_limited(*tool, *thinking))
+                .filter(|r| !r.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+
+        let mut res = self
+            .blocks
+            .iter()
+            .map(Block::render)
+            .filter(|r| !r.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if res.chars().count() <= self.max_len {
+            return res;
+        }
+
+        // 第一階段：由舊到新把思考與工具輸出縮到最低保留量
+        let mut limits: Vec<(usize, usize)> = self
+            .blocks
+            .iter()
+            .map(|_| (TOOL_OUTPUT_DEFAULT_CHARS, usize::MAX))
+            .collect();
+        for (i, block) in self.blocks.iter().enumerate() {
+            match block.block_type {
+                BlockType::Thinking => limits[i].1 = self.minimums.thinking,
+                BlockType::ToolOutput => limits[i].0 = self.minimums.tool_output,
+                _ => continue,
+            }
+            res = join(&limits);
+            if res.chars().count() <= self.max_len {
+                return res;
+            }
+        }
+
+        // 第二階段：由舊到新整塊丟棄思考與工具輸出
+        for (i, block) in self.blocks.iter().enumerate() {
+            if matches!(
+                block.block_type,
+                BlockType::Thinking | BlockType::ToolOutput
+            ) {
+                limits[i] = (0, 0);
+                res = join(&limits);
+                if res.chars().count() <= self.max_len {
+                    return res;
+                }
+            }
+        }
+        res
+    }
 }
 
 #[cfg(test)]
@@ -262,6 +744,40 @@ mod tests {
         assert!(rendered.len() < 600); // 500 chars + Markdown wrappers
     }
 
+    #[test]
+    fn test_tool_timing_decorates_label() {
+        let now = Instant::now();
+        let started = now.checked_sub(Duration::from_secs(45)).unwrap_or(now);
+        let mut timing = ToolTiming {
+            started,
+            elapsed: None,
+            warn_after: Some(Duration::from_secs(30)),
+        };
+        assert!(timing.decorate("bash", now).starts_with("⚠️ bash (⏳ "));
+
+        timing.elapsed = Some(Duration::from_millis(3200));
+        assert_eq!(timing.decorate("bash", now), "bash (3.2s)");
+
+        timing.elapsed = Some(Duration::from_secs(31));
+        assert_eq!(timing.decorate("bash", now), "⚠️ bash (31.0s)");
+
+        timing.warn_after = None;
+        timing.elapsed = None;
+        assert_eq!(timing.decorate("bash", now), "bash");
+    }
+
+    #[test]
+    fn test_finish_tool_call_keeps_first_elapsed() {
+        let mut composer = EmbedComposer::new(2000).with_slow_tool_warning(0);
+        composer.set_tool_call("c1".into(), "🛠️ `ls`".into());
+        composer.finish_tool_call("c1");
+        let first = composer.blocks[0].timing.and_then(|t| t.elapsed);
+        assert!(first.is_some());
+        composer.finish_tool_call("c1");
+        assert_eq!(composer.blocks[0].timing.and_then(|t| t.elapsed), first);
+        assert!(composer.render().starts_with("🛠️ `ls` ("));
+    }
+
     #[test]
     fn test_markdown_guard() {
         let mut composer = EmbedComposer::new(100);
@@ -278,6 +794,80 @@ mod tests {
         assert_eq!(rendered.matches("```").count() % 2, 0);
     }
 
+    #[test]
+    fn test_budget_shrinks_thinking_before_answer() {
+        let mut composer = EmbedComposer::new(600).with_minimums(BlockMinimums {
+            thinking: 50,
+            tool_output: 40,
+        });
+        composer.push_delta(Some("t".into()), BlockType::Thinking, &"T".repeat(400));
+        composer.push_delta(Some("a".into()), BlockType::Text, &"A".repeat(450));
+
+        let rendered = composer.render();
+        assert!(
+            rendered.ends_with(&"A".repeat(450)),
+            "answer must stay intact"
+        );
+        assert!(rendered.contains(&format!("> …{}", "T".repeat(50))));
+        assert!(!rendered.contains("部分歷史內容已截斷"));
+    }
+
+    #[test]
+    fn test_budget_drops_tool_output_when_minimum_does_not_fit() {
+        let mut composer = EmbedComposer::new(300).with_minimums(BlockMinimums {
+            thinking: 50,
+            tool_output: 100,
+        });
+        composer.set_tool_call("c1".into(), "🛠️ **Tool:** `ls`".into());
+        composer.blocks.push_back(Block::with_id(
+            BlockType::ToolOutput,
+            "O".repeat(300),
+            "c1".into(),
+        ));
+        composer.push_delta(Some("a".into()), BlockType::Text, &"A".repeat(250));
+
+        let rendered = composer.render();
+        assert!(rendered.ends_with(&"A".repeat(250)));
+        assert!(!rendered.contains('O'));
+        assert!(rendered.contains("`ls`"));
+    }
+
+    #[test]
+    fn test_render_sections_splits_and_shares_total_budget() {
+        let mut composer = EmbedComposer::new(3900);
+        composer.push_delta(Some("t".into()), BlockType::Thinking, &"T".repeat(3000));
+        composer.set_tool_call("c1".into(), "🛠️ **Tool:** `ls`".into());
+        composer.push_delta(Some("a".into()), BlockType::Text, &"A".repeat(3000));
+
+        let sections = composer.render_sections(5800);
+        let kinds: Vec<Section> = sections.iter().map(|(s, _)| *s).collect();
+        assert_eq!(
+            kinds,
+            vec![Section::Thinking, Section::Tools, Section::Answer]
+        );
+        assert_eq!(sections[2].1, "A".repeat(3000));
+        assert!(sections[1].1.contains("`ls`"));
+        let total: usize = sections.iter().map(|(_, b)| b.chars().count()).sum();
+        assert!(total <= 5800);
+    }
+
+    #[test]
+    fn test_render_answer_text_ignores_budget_and_other_blocks() {
+        let mut composer = EmbedComposer::new(100);
+        composer.push_delta(Some("t".into()), BlockType::Thinking, "hmm");
+        composer.push_delta(Some("a".into()), BlockType::Text, &"A".repeat(500));
+        assert_eq!(composer.render_answer_text(), "A".repeat(500));
+    }
+
+    #[test]
+    fn test_render_sections_skips_empty_sections() {
+        let mut composer = EmbedComposer::new(3900);
+        composer.push_delta(None, BlockType::Text, "hello");
+        let sections = composer.render_sections(5800);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0], (Section::Answer, "hello".to_string()));
+    }
+
     #[test]
     fn test_thinking_block_rendering() {
         let block = Block::new(BlockType::Thinking, "Line 1\nLine 2".into());
@@ -313,4 +903,96 @@ mod tests {
         // 如果 sync 的內容較短，應保留本地較長的內容（防止網路延遲導致抖動）
         assert_eq!(composer.blocks[0].content, "longer_old_data");
     }
+
+    #[test]
+    fn test_split_point_prefers_line_breaks() {
+        assert_eq!(split_point("short", 10), 5);
+        assert_eq!(split_point("aaaaaa\nbbbbbbbbbb", 10), 7);
+        assert_eq!(split_point("aaaaaa bbbbbbbbbb", 10), 7);
+        assert_eq!(split_point(&"x".repeat(20), 10), 10);
+    }
+
+    #[test]
+    fn test_take_pages_continues_answer_without_truncation() {
+        let mut composer = EmbedComposer::new(300).with_pagination(true);
+        composer.push_delta(Some("t".into()), BlockType::Thinking, "plan");
+        let paragraph = format!("{}\n", "word ".repeat(20).trim_end());
+        composer.push_delta(Some("a".into()), BlockType::Text, &paragraph.repeat(8));
+        assert!(composer.render().chars().count() <= 300);
+
+        let pages = composer.take_pages();
+        assert!(pages.len() >= 2);
+        assert!(pages[0].starts_with("> plan"));
+        assert!(pages.iter().all(|p| p.chars().count() <= 300));
+        assert!(composer.take_pages().is_empty());
+
+        // 後續串流只出現在目前這頁，已送出的內容不再重複
+        composer.push_delta(Some("a".into()), BlockType::Text, "tail");
+        let current = composer.render();
+        assert!(current.ends_with("tail"));
+        assert!(!current.contains("plan"));
+        let rejoined: String = pages
+            .iter()
+            .skip(1)
+            .map(String::as_str)
+            .chain([current.as_str()])
+            .collect::<Vec<_>>()
+            .join("\n")
+            .split_whitespace()
+            .collect();
+        let original: String = format!("{}tail", paragraph.repeat(8))
+            .split_whitespace()
+            .collect();
+        assert!(original.ends_with(&rejoined));
+        assert!(!composer.has_truncated);
+    }
+
+    #[test]
+    fn test_take_pages_reopens_split_code_block() {
+        let mut composer = EmbedComposer::new(200).with_pagination(true);
+        let code = format!("```rust\n{}```", "let x = 1;\n".repeat(30));
+        composer.push_delta(None, BlockType::Text, &code);
+
+        let pages = composer.take_pages();
+        assert!(!pages.is_empty());
+        assert!(pages[0].starts_with("```rust"));
+        assert!(pages[0].ends_with("```"));
+        assert!(composer.render().starts_with("```\nlet x"));
+    }
+
+    #[test]
+    fn test_pagination_keeps_flushed_blocks_across_sync() {
+        let mut composer = EmbedComposer::new(150).with_pagination(true);
+        for i in 0..15 {
+            composer.set_tool_call(i.to_string(), format!("🛠️ tool {}", i));
+        }
+        assert_eq!(composer.blocks.len(), 15);
+        assert!(!composer.take_pages().is_empty());
+
+        // 後端同步完整內容時，已送出的工具呼叫不會再出現在目前這頁
+        let items = (0..15)
+            .map(|i| {
+                Block::with_label(
+                    BlockType::ToolCall,
+                    format!("🛠️ tool {}", i),
+                    Some(i.to_string()),
+                )
+            })
+            .collect();
+        composer.sync_content(items);
+        assert!(!composer.render().contains("tool 0"));
+        assert!(composer.render().contains("tool 14"));
+    }
+
+    #[test]
+    fn test_continuation_merges_answer_and_reopens_code_block() {
+        let previous = "Setup:\n```rust\nfn main() {".to_string();
+        let mut composer = EmbedComposer::new(2000).with_continuation(Some(previous.clone()));
+        composer.push_delta(None, BlockType::Text, "    run();\n}\n```\nDone.");
+        assert!(composer.render().starts_with("```\n    run();"));
+        assert_eq!(
+            composer.render_answer_text(),
+            format!("{}    run();\n}}\n```\nDone.", previous)
+        );
+    }
 }
@@ -10,6 +10,523 @@ pub struct Config {
     pub assistant_name: String,
     #[serde(default)]
     pub opencode: OpencodeConfig,
+    /// 單輪對話最長秒數，超過會自動 abort；0 表示不限制
+    #[serde(default = "default_max_turn_secs")]
+    pub max_turn_secs: u64,
+    /// 超過此秒數沒有新的串流事件 (例如工具長時間執行) 就停止顯示「輸入中」
+    #[serde(default = "default_typing_idle_secs")]
+    pub typing_idle_secs: u64,
+    /// 收到 SIGTERM/SIGINT 後等待進行中回合完成的秒數，逾時的回合標示為因重啟中斷
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// 排隊訊息合併時每批的估計 token 上限，超過則拆成多輪依序處理；0 表示不拆分
+    #[serde(default = "default_max_batch_tokens")]
+    pub max_batch_tokens: usize,
+    #[serde(default)]
+    pub composer: ComposerConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    /// 授權成功後發送導覽訊息 (可用 welcome.md 自訂內容)
+    #[serde(default = "default_welcome_message")]
+    pub welcome_message: bool,
+    /// 提及機器人並詢問 help 等問題時直接回覆功能卡片，不啟動對話
+    #[serde(default = "default_help_on_mention")]
+    pub help_on_mention: bool,
+    /// 文字指令的前綴 (如 `!abort`)，頻道以 `/config text_commands` 啟用後才解析
+    #[serde(default = "default_text_command_prefix")]
+    pub text_command_prefix: String,
+    /// 伺服器頻道預設使用該伺服器在 Discord 設定的偏好語言 (支援的語系才生效)
+    #[serde(default = "default_guild_locale")]
+    pub guild_locale: bool,
+    /// 韌性測試用的故障注入，正式環境請保持關閉
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+    #[serde(default)]
+    pub acp: AcpConfig,
+    #[serde(default)]
+    pub openai: OpenAiConfig,
+    #[serde(default)]
+    pub copilot: CopilotConfig,
+    #[serde(default)]
+    pub claude: ClaudeConfig,
+    /// 允許使用的後端；未設定時全部啟用
+    #[serde(default)]
+    pub enabled_backends: Option<Vec<crate::agent::AgentType>>,
+    /// 伺服器內的排程同步為 Discord 排程活動，顯示下次執行時間與上次結果 (需要「管理活動」權限)
+    #[serde(default)]
+    pub cron_scheduled_events: bool,
+    /// 以內容雜湊共用各頻道的附件下載與 OCR 結果，以引用計數決定何時刪除
+    #[serde(default)]
+    pub attachment_cache: bool,
+    /// 被回覆訊息的引用另外移除不可見字元與看似聊天角色標記的行
+    #[serde(default)]
+    pub strict_quote_sanitization: bool,
+    /// 附件下載的大小、類型與頻道配額限制
+    #[serde(default)]
+    pub uploads: UploadsConfig,
+    /// 每種後端同時執行的回合數上限 (如 copilot = 3)，超過的回合排隊並顯示位置；未設定表示不限制
+    #[serde(default)]
+    pub max_concurrent_turns: std::collections::HashMap<String, usize>,
+    /// 機器人在頻道內回應過多時暫停一段時間，把對話留給使用者
+    #[serde(default)]
+    pub flood: FloodConfig,
+    /// 指定使用者的訊息在共用頻道可插隊，必要時中斷一般使用者進行中的回合
+    #[serde(default)]
+    pub priority: PriorityConfig,
+    /// 後端連續失敗時暫停送出並在背景探測，避免每則訊息都慢慢失敗
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// 以 embedding 比對近期問過的問題，直接提供快取答案
+    #[serde(default)]
+    pub faq_cache: FaqCacheConfig,
+    /// 每輪前後執行的外部腳本 (政策檢查、紀錄、通知)
+    #[serde(default)]
+    pub hooks: crate::hooks::HooksConfig,
+    /// 各類資料的保留天數，逾期由背景工作定期清除
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// 每位使用者的提示頻率與全域同時執行的回合數上限
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// 定期比對各後端的模型清單，有新增或移除時通知管理頻道
+    #[serde(default)]
+    pub model_watch: ModelWatchConfig,
+    /// 主要 token 被 Discord 拒絕時改用的備用 token
+    #[serde(default)]
+    pub token_failover: TokenFailoverConfig,
+    /// /voice join 後以語音朗讀最終回答的 TTS 設定
+    #[serde(default)]
+    pub voice: VoiceConfig,
+    /// 代理執行工具前是否在頻道中詢問管理員
+    #[serde(default)]
+    pub tool_approval: ToolApprovalConfig,
+    /// 使用統計的每日摘要
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// 附件放不進 Discord 時改貼的下載連結
+    #[serde(default)]
+    pub downloads: crate::downloads::DownloadsConfig,
+}
+
+/// summary_channel 為 0 表示不發送每日摘要，port 為 0 表示不開啟 Prometheus 端點
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub summary_channel: u64,
+    /// 發送前一天摘要的時間 (本地時間的整點，0–23)
+    #[serde(default = "default_metrics_summary_hour")]
+    pub summary_hour: u32,
+    /// Prometheus 抓取端點 (`/metrics`) 的埠
+    #[serde(default)]
+    pub port: u16,
+    #[serde(default = "default_metrics_bind_address")]
+    pub bind_address: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            summary_channel: 0,
+            summary_hour: default_metrics_summary_hour(),
+            port: 0,
+            bind_address: default_metrics_bind_address(),
+        }
+    }
+}
+
+fn default_metrics_summary_hour() -> u32 {
+    9
+}
+
+fn default_metrics_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+/// mode 為 ask 時，ACP 後端的權限請求與 pi 的工具呼叫需由管理員按鈕核准
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ToolApprovalConfig {
+    #[serde(default)]
+    pub mode: crate::agent::approval::ToolApprovalMode,
+    /// 等待核准的秒數，逾時視為拒絕
+    #[serde(default = "default_tool_approval_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for ToolApprovalConfig {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            timeout_secs: default_tool_approval_timeout_secs(),
+        }
+    }
+}
+
+/// piper_model 與 http_endpoint 都未設定表示停用；兩者皆有時使用 piper
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct VoiceConfig {
+    #[serde(default = "default_piper_binary")]
+    pub piper_binary: String,
+    /// piper 的語音模型 (.onnx)
+    #[serde(default)]
+    pub piper_model: String,
+    /// OpenAI 相容的 `/v1/audio/speech` 端點
+    #[serde(default)]
+    pub http_endpoint: String,
+    #[serde(default)]
+    pub http_api_key: String,
+    #[serde(default = "default_tts_model")]
+    pub http_model: String,
+    #[serde(default = "default_tts_voice")]
+    pub http_voice: String,
+    /// 朗讀的字數上限，較長的回答只念開頭
+    #[serde(default = "default_voice_max_chars")]
+    pub max_chars: usize,
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            piper_binary: default_piper_binary(),
+            piper_model: String::new(),
+            http_endpoint: String::new(),
+            http_api_key: String::new(),
+            http_model: default_tts_model(),
+            http_voice: default_tts_voice(),
+            max_chars: default_voice_max_chars(),
+        }
+    }
+}
+
+impl VoiceConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.piper_model.trim().is_empty() || !self.http_endpoint.trim().is_empty()
+    }
+}
+
+/// backup_token 為空表示停用；admin_channel 為 0 表示切換時不通知
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct TokenFailoverConfig {
+    #[serde(default)]
+    pub backup_token: String,
+    #[serde(default)]
+    pub admin_channel: u64,
+}
+
+/// admin_channel 為 0 表示停用
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ModelWatchConfig {
+    #[serde(default)]
+    pub admin_channel: u64,
+    #[serde(default = "default_model_watch_interval_mins")]
+    pub interval_mins: u64,
+}
+
+impl Default for ModelWatchConfig {
+    fn default() -> Self {
+        Self {
+            admin_channel: 0,
+            interval_mins: default_model_watch_interval_mins(),
+        }
+    }
+}
+
+fn default_model_watch_interval_mins() -> u64 {
+    60
+}
+
+/// 超過上限的訊息直接回覆稍後重試，不進入頻道佇列；0 表示不限制
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct LimitsConfig {
+    /// 每位使用者每分鐘可送出的提示數
+    #[serde(default)]
+    pub user_prompts_per_minute: usize,
+    /// 所有頻道合計同時執行的回合數；已有回合的頻道仍可把訊息併入下一批
+    #[serde(default)]
+    pub global_concurrent_turns: usize,
+}
+
+/// 各類資料的保留天數；0 表示永久保留
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct RetentionConfig {
+    /// pi 的對話紀錄 (sessions/pi/*.jsonl)，依最後修改時間計算
+    #[serde(default)]
+    pub transcripts_days: u64,
+    /// 附件下載與 OCR 結果
+    #[serde(default)]
+    pub uploads_days: u64,
+    /// analytics 的回合紀錄與 /stats 的個人統計
+    #[serde(default)]
+    pub usage_days: u64,
+}
+
+/// 訊息附件下載限制；超出限制的附件不會傳給代理，並在頻道內說明原因
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct UploadsConfig {
+    #[serde(default = "default_upload_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// 允許的 MIME 類型，可用 `image/*` 這類萬用字元；空白表示不限制
+    #[serde(default)]
+    pub allowed_mime: Vec<String>,
+    /// 每個頻道保留中的上傳檔總量上限；0 表示不限制
+    #[serde(default)]
+    pub channel_quota_bytes: u64,
+    /// 上傳檔保留秒數，逾時後自動清除
+    #[serde(default = "default_upload_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: default_upload_max_file_bytes(),
+            allowed_mime: Vec::new(),
+            channel_quota_bytes: 0,
+            ttl_secs: default_upload_ttl_secs(),
+        }
+    }
+}
+
+/// 通用 ACP 後端：任何支援 Agent Client Protocol 的程式 (如 Gemini CLI、Zed 代理)
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct AcpConfig {
+    /// 執行檔路徑或 PATH 中的名稱
+    #[serde(default)]
+    pub binary: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: std::collections::HashMap<String, String>,
+}
+
+/// OpenAI 相容的 HTTP 後端 (`/v1/chat/completions`)，如 llama.cpp、ollama 的本機伺服器
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct OpenAiConfig {
+    /// 含 `/v1` 的 API 位址，如 `http://127.0.0.1:11434/v1`
+    #[serde(default)]
+    pub base_url: String,
+    /// 以 Bearer 送出；本機伺服器通常不需要
+    #[serde(default)]
+    pub api_key: String,
+    /// 預設模型；頻道以 /model 選擇的模型優先
+    #[serde(default)]
+    pub model: String,
+}
+
+/// Copilot 的 ACP 進程配置；預設所有頻道共用一個進程，同時只能處理一個回合
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CopilotConfig {
+    #[serde(default)]
+    pub process_mode: crate::agent::acp::AcpProcessMode,
+    /// process_mode = "pool" 時的進程數，頻道依 ID 的雜湊固定分配到其中一個
+    #[serde(default = "default_copilot_pool_size")]
+    pub pool_size: usize,
+}
+
+impl Default for CopilotConfig {
+    fn default() -> Self {
+        Self {
+            process_mode: Default::default(),
+            pool_size: default_copilot_pool_size(),
+        }
+    }
+}
+
+/// Claude Code 的 ACP 進程配置，選項同 `[copilot]`
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ClaudeConfig {
+    #[serde(default)]
+    pub process_mode: crate::agent::acp::AcpProcessMode,
+    #[serde(default = "default_claude_pool_size")]
+    pub pool_size: usize,
+}
+
+impl Default for ClaudeConfig {
+    fn default() -> Self {
+        Self {
+            process_mode: Default::default(),
+            pool_size: default_claude_pool_size(),
+        }
+    }
+}
+
+/// 故障注入機率 (0.0 ~ 1.0)，僅在 enabled 時生效
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ChaosConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 延遲 SSE 事件的機率與最長延遲毫秒數
+    #[serde(default)]
+    pub sse_delay_probability: f64,
+    #[serde(default)]
+    pub sse_delay_max_ms: u64,
+    /// 丟棄廣播給 Writer 的事件
+    #[serde(default)]
+    pub drop_event_probability: f64,
+    /// 取用後端時直接終止其進程
+    #[serde(default)]
+    pub kill_backend_probability: f64,
+    /// 後端請求改回傳 500
+    #[serde(default)]
+    pub http_500_probability: f64,
+}
+
+/// `window_secs` 內回應超過 `max_messages` 則後冷卻 `cooldown_secs`；max_messages 為 0 表示停用
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct FloodConfig {
+    #[serde(default)]
+    pub max_messages: usize,
+    #[serde(default = "default_flood_window_secs")]
+    pub window_secs: u64,
+    #[serde(default = "default_flood_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+impl Default for FloodConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: 0,
+            window_secs: default_flood_window_secs(),
+            cooldown_secs: default_flood_cooldown_secs(),
+        }
+    }
+}
+
+/// 伺服器管理員與 `users` 的訊息排在佇列最前面；`preempt_in_flight` 開啟時另會中斷非優先使用者
+/// 進行中的回合，被中斷的回合在優先回合完成後自動重新排入
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct PriorityConfig {
+    #[serde(default)]
+    pub users: Vec<u64>,
+    #[serde(default)]
+    pub preempt_in_flight: bool,
+}
+
+impl PriorityConfig {
+    /// `is_admin` 為 `commands::is_admin` 的結果；`users` 只是額外加入的非管理員
+    pub fn is_priority(&self, user_id: Option<u64>, is_admin: bool) -> bool {
+        user_id.is_some_and(|id| is_admin || self.users.contains(&id))
+    }
+}
+
+/// 同一頻道的後端連續失敗 `failure_threshold` 次後開啟斷路器；0 表示停用
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CircuitBreakerConfig {
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub failure_threshold: u32,
+    #[serde(default = "default_circuit_base_backoff_secs")]
+    pub base_backoff_secs: u64,
+    #[serde(default = "default_circuit_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_circuit_failure_threshold(),
+            base_backoff_secs: default_circuit_base_backoff_secs(),
+            max_backoff_secs: default_circuit_max_backoff_secs(),
+        }
+    }
+}
+
+/// 語意 FAQ 快取；endpoint 為 OpenAI 相容的 embeddings API，留空表示停用。各頻道另需以 /faq_cache 開啟
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct FaqCacheConfig {
+    #[serde(default)]
+    pub endpoint: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default = "default_faq_model")]
+    pub model: String,
+    /// 餘弦相似度門檻
+    #[serde(default = "default_faq_similarity")]
+    pub similarity: f32,
+    #[serde(default = "default_faq_ttl_secs")]
+    pub ttl_secs: u64,
+    /// 每個頻道保留的問題數
+    #[serde(default = "default_faq_max_entries")]
+    pub max_entries: usize,
+}
+
+impl Default for FaqCacheConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            api_key: None,
+            model: default_faq_model(),
+            similarity: default_faq_similarity(),
+            ttl_secs: default_faq_ttl_secs(),
+            max_entries: default_faq_max_entries(),
+        }
+    }
+}
+
+/// 每輪一筆 NDJSON 分析紀錄 (analytics/turns.ndjson)
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AnalyticsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_analytics_max_file_bytes")]
+    pub max_file_bytes: u64,
+    #[serde(default = "default_analytics_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_file_bytes: default_analytics_max_file_bytes(),
+            max_files: default_analytics_max_files(),
+        }
+    }
+}
+
+/// Embed 內容逼近上限時，思考與工具輸出最少保留的字數
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ComposerConfig {
+    #[serde(default = "default_thinking_min_chars")]
+    pub thinking_min_chars: usize,
+    #[serde(default = "default_tool_output_min_chars")]
+    pub tool_output_min_chars: usize,
+    /// 思考、工具活動與回答分別使用獨立 Embed 顯示
+    #[serde(default)]
+    pub multi_embed: bool,
+    /// 回答超過單一 Embed 上限時接續到新訊息，而非折疊截斷
+    #[serde(default)]
+    pub paginate: bool,
+    /// 工具執行超過此秒數時加上 ⚠️ 標示；0 表示不標示
+    #[serde(default = "default_slow_tool_warn_secs")]
+    pub slow_tool_warn_secs: u64,
+    /// 每個頻道保存完整工具輸出的回合數 (供「完整輸出」按鈕查看)；0 表示不保存
+    #[serde(default = "default_tool_output_retention")]
+    pub tool_output_retention: usize,
+    /// 所有頻道同時進行的 Discord 訊息編輯上限；0 表示不限制
+    #[serde(default = "default_max_concurrent_edits")]
+    pub max_concurrent_edits: usize,
+}
+
+impl Default for ComposerConfig {
+    fn default() -> Self {
+        Self {
+            thinking_min_chars: default_thinking_min_chars(),
+            tool_output_min_chars: default_tool_output_min_chars(),
+            multi_embed: false,
+            paginate: false,
+            slow_tool_warn_secs: default_slow_tool_warn_secs(),
+            tool_output_retention: default_tool_output_retention(),
+            max_concurrent_edits: default_max_concurrent_edits(),
+        }
+    }
+}
+
+impl ComposerConfig {
+    pub fn minimums(&self) -> crate::composer::BlockMinimums {
+        crate::composer::BlockMinimums {
+            thinking: self.thinking_min_chars,
+            tool_output: self.tool_output_min_chars,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -19,6 +536,14 @@ pub struct OpencodeConfig {
     #[serde(default = "default_port")]
     pub port: u16,
     pub password: Option<String>,
+    /// 每種後端 (opencode/kilo) 啟動的 server 實例數
+    #[serde(default = "default_instances")]
+    pub instances: usize,
+    /// 實例使用的連接埠範圍 [start, end]；未設定時自動挑選空閒埠
+    #[serde(default)]
+    pub port_range: Option<(u16, u16)>,
+    #[serde(default)]
+    pub strategy: crate::agent::manager::PoolStrategy,
 }
 
 impl Default for OpencodeConfig {
@@ -27,6 +552,9 @@ impl Default for OpencodeConfig {
             host: "127.0.0.1".to_string(),
             port: 4096,
             password: None,
+            instances: default_instances(),
+            port_range: None,
+            strategy: Default::default(),
         }
     }
 }
@@ -39,6 +567,142 @@ fn default_assistant_name() -> String {
     "Agent".to_string()
 }
 
+fn default_welcome_message() -> bool {
+    true
+}
+
+fn default_typing_idle_secs() -> u64 {
+    10
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_max_batch_tokens() -> usize {
+    8000
+}
+
+fn default_help_on_mention() -> bool {
+    true
+}
+
+fn default_text_command_prefix() -> String {
+    "!".to_string()
+}
+
+fn default_guild_locale() -> bool {
+    true
+}
+
+fn default_max_turn_secs() -> u64 {
+    15 * 60
+}
+
+fn default_copilot_pool_size() -> usize {
+    4
+}
+
+fn default_piper_binary() -> String {
+    "piper".to_string()
+}
+
+fn default_tts_model() -> String {
+    "tts-1".to_string()
+}
+
+fn default_tts_voice() -> String {
+    "alloy".to_string()
+}
+
+fn default_voice_max_chars() -> usize {
+    1000
+}
+
+fn default_tool_approval_timeout_secs() -> u64 {
+    120
+}
+
+fn default_claude_pool_size() -> usize {
+    4
+}
+
+fn default_flood_window_secs() -> u64 {
+    5 * 60
+}
+
+fn default_flood_cooldown_secs() -> u64 {
+    2 * 60
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    3
+}
+
+fn default_circuit_base_backoff_secs() -> u64 {
+    10
+}
+
+fn default_circuit_max_backoff_secs() -> u64 {
+    5 * 60
+}
+
+fn default_faq_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+fn default_faq_similarity() -> f32 {
+    0.92
+}
+
+fn default_faq_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_faq_max_entries() -> usize {
+    200
+}
+
+fn default_analytics_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_analytics_max_files() -> usize {
+    5
+}
+
+fn default_instances() -> usize {
+    1
+}
+
+fn default_thinking_min_chars() -> usize {
+    200
+}
+
+fn default_tool_output_min_chars() -> usize {
+    120
+}
+
+fn default_slow_tool_warn_secs() -> u64 {
+    30
+}
+
+fn default_tool_output_retention() -> usize {
+    20
+}
+
+fn default_upload_max_file_bytes() -> u64 {
+    20 * 1024 * 1024
+}
+
+fn default_upload_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_max_concurrent_edits() -> usize {
+    8
+}
+
 fn default_host() -> String {
     "127.0.0.1".to_string()
 }
@@ -57,11 +721,153 @@ impl Config {
 debug_level = "INFO"
 language = "zh-TW"
 assistant_name = "Agent"
+max_turn_secs = 900
+max_batch_tokens = 8000
+typing_idle_secs = 10
+shutdown_grace_secs = 30  # 停止時等待進行中回合的秒數
+welcome_message = true
+help_on_mention = true
+text_command_prefix = "!"  # 頻道以 /config text_commands 啟用後，"!abort" 等訊息視為指令
+guild_locale = true
+cron_scheduled_events = false
+attachment_cache = false  # 相同內容的附件在各頻道共用一份下載
+strict_quote_sanitization = false  # 引用內容移除不可見字元與 "system:" 等角色標記行
+# enabled_backends = ["kilo", "copilot"]  # 未設定時全部啟用
 
 [opencode]
 host = "127.0.0.1"
 port = 4096
 # password = "your-password"  # Uncomment if using OPENCODE_SERVER_PASSWORD
+instances = 1
+# port_range = [41000, 41009]  # 埠被佔用時自動改用空閒埠
+strategy = "round_robin"  # or "least_loaded"
+
+# 通用 ACP 後端 (/agent acp)，例如 Gemini CLI
+# [acp]
+# binary = "gemini"
+# args = ["--experimental-acp"]
+
+# OpenAI 相容的 HTTP 後端 (/agent openai)，例如 llama.cpp 或 ollama
+# [openai]
+# base_url = "http://127.0.0.1:11434/v1"
+# api_key = ""
+# model = "qwen2.5-coder"
+
+# Copilot 進程配置："shared" 全部頻道共用、"per_channel" 每頻道一個、"pool" 固定 pool_size 個
+[copilot]
+process_mode = "shared"
+pool_size = 4
+
+# Claude Code (/agent claude) 透過 claude-code-acp 啟動，進程配置同 [copilot]
+# [claude]
+# process_mode = "shared"
+# pool_size = 4
+
+[composer]
+thinking_min_chars = 200
+tool_output_min_chars = 120
+multi_embed = false
+paginate = false
+slow_tool_warn_secs = 30
+tool_output_retention = 20
+max_concurrent_edits = 8
+
+[uploads]
+max_file_bytes = 20971520
+# allowed_mime = ["image/*", "application/pdf", "text/*"]  # 未設定時不限制
+channel_quota_bytes = 0  # 每個頻道保留中的上傳檔總量，0 表示不限制
+ttl_secs = 86400
+
+[analytics]
+enabled = false
+max_file_bytes = 10485760
+max_files = 5
+
+# 每種後端同時執行的回合數上限，超過的回合排隊並顯示位置
+# [max_concurrent_turns]
+# copilot = 3
+
+# 防洗版：window_secs 內回應超過 max_messages 則後暫停 cooldown_secs (0 表示停用)
+[flood]
+max_messages = 0
+window_secs = 300
+cooldown_secs = 120
+
+# 伺服器管理員的訊息插隊，users 可額外加入其他使用者 (Discord 使用者 ID)；preempt_in_flight 另會中斷一般使用者進行中的回合並於之後重新排入
+# [priority]
+# users = [123456789012345678]
+# preempt_in_flight = false
+
+# 後端連續失敗 failure_threshold 次後暫停送出，背景以指數退避探測直到恢復 (0 表示停用)
+[circuit_breaker]
+failure_threshold = 3
+base_backoff_secs = 10
+max_backoff_secs = 300
+
+# 語意 FAQ 快取：相似問題近期回答過時直接提供快取答案 (各頻道以 /faq_cache 開啟)
+# [faq_cache]
+# endpoint = "https://api.openai.com/v1/embeddings"
+# api_key = "sk-..."
+# model = "text-embedding-3-small"
+# similarity = 0.92
+# ttl_secs = 86400
+# max_entries = 200
+
+# 每輪前後執行的腳本，從 stdin 讀取 JSON
+# [hooks]
+# pre_turn = "/path/to/pre_turn.sh"
+# post_turn = "/path/to/post_turn.sh"
+# timeout_secs = 10
+# pre_turn 無法執行或逾時時預設擋下該則訊息；設為 true 改為照常放行
+# fail_open = false
+
+# 資料保留天數，逾期由背景工作每 6 小時清除一次 (0 表示永久保留)；也可用 /admin purge 手動清除
+[retention]
+transcripts_days = 0
+uploads_days = 0
+usage_days = 0
+
+# 速率限制 (0 表示不限制)：超過時回覆「請於 N 秒後重試」，訊息不會排入佇列
+[limits]
+user_prompts_per_minute = 0
+global_concurrent_turns = 0
+
+# 定期比對各後端的模型清單，有新增或移除時通知此頻道 (0 表示停用)
+[model_watch]
+admin_channel = 0
+interval_mins = 60
+
+# 主要 token 失效 (gateway 驗證失敗) 時改用備用 token 連線，並通知此頻道 (0 表示不通知)
+# [token_failover]
+# backup_token = "SECONDARY_DISCORD_TOKEN"
+# admin_channel = 0
+
+# /voice join 後朗讀最終回答 (需以 --features voice 編譯)；設定 piper_model 或 http_endpoint 其一
+# [voice]
+# piper_model = "/opt/piper/en_US-lessac-medium.onnx"
+# http_endpoint = "http://127.0.0.1:8880/v1/audio/speech"
+# http_api_key = ""
+# http_voice = "alloy"
+# max_chars = 1000
+
+# 工具核准：auto 自動允許；ask 在頻道中以按鈕詢問管理員 (ACP 後端與 pi)，逾時視為拒絕
+[tool_approval]
+mode = "auto"
+timeout_secs = 120
+
+# 每天在 summary_hour (本地時間) 把前一天的使用統計送到此頻道 (0 表示停用)；/stats 隨時可查
+[metrics]
+summary_channel = 0
+summary_hour = 9
+# Prometheus 抓取端點 http://<bind_address>:<port>/metrics (0 表示停用)
+port = 0
+bind_address = "127.0.0.1"
+
+# 附件壓縮分割後仍超過上限時，改貼會過期的下載連結；檔案由上面 [metrics] 的 HTTP 端點提供，
+# public_url 填外部可連到該端點的網址 (通常經由反向代理)，留空表示停用
+# [downloads]
+# public_url = "https://bot.example.com"
+# ttl_secs = 86400
 "#;
             tokio::fs::write(&config_path, default_config).await?;
             anyhow::bail!(
@@ -77,20 +883,15 @@ port = 4096
 }
 
 #[cfg(test)]
+#[allow(clippy::await_holding_lock)]
 mod tests {
     use super::Config;
     use crate::migrate::BASE_DIR_ENV;
-    use std::sync::{Mutex, OnceLock};
     use tempfile::tempdir;
 
-    fn env_lock() -> &'static Mutex<()> {
-        static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-        LOCK.get_or_init(|| Mutex::new(()))
-    }
-
     #[tokio::test]
     async fn test_load_creates_default_config_when_missing() {
-        let _guard = env_lock().lock().expect("lock");
+        let _guard = crate::migrate::env_lock();
         let dir = tempdir().expect("tempdir");
         // SAFETY: serialized by env lock
         unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
@@ -105,7 +906,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_load_reads_existing_config() {
-        let _guard = env_lock().lock().expect("lock");
+        let _guard = crate::migrate::env_lock();
         let dir = tempdir().expect("tempdir");
         // SAFETY: serialized by env lock
         unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
@@ -127,6 +928,75 @@ port = 4096
         assert_eq!(cfg.discord_token, "abc");
         assert_eq!(cfg.language, "en");
         assert_eq!(cfg.assistant_name, "AgentX");
+        assert_eq!(cfg.max_turn_secs, 900);
+        assert_eq!(cfg.max_batch_tokens, 8000);
+        assert_eq!(cfg.typing_idle_secs, 10);
+        assert_eq!(cfg.shutdown_grace_secs, 30);
+        assert!(cfg.welcome_message);
+        assert!(cfg.help_on_mention);
+        assert_eq!(cfg.text_command_prefix, "!");
+        assert!(cfg.guild_locale);
+        assert!(!cfg.chaos.enabled);
+        assert!(cfg.acp.binary.is_empty());
+        assert!(cfg.openai.base_url.is_empty());
+        assert_eq!(
+            cfg.copilot.process_mode,
+            crate::agent::acp::AcpProcessMode::Shared
+        );
+        assert_eq!(cfg.copilot.pool_size, 4);
+        assert_eq!(cfg.claude, super::ClaudeConfig::default());
+        assert!(cfg.enabled_backends.is_none());
+        assert!(!cfg.cron_scheduled_events);
+        assert!(!cfg.attachment_cache);
+        assert!(!cfg.strict_quote_sanitization);
+        assert_eq!(cfg.uploads.max_file_bytes, 20 * 1024 * 1024);
+        assert!(cfg.uploads.allowed_mime.is_empty());
+        assert_eq!(cfg.uploads.channel_quota_bytes, 0);
+        assert_eq!(cfg.uploads.ttl_secs, 86400);
+        assert!(cfg.max_concurrent_turns.is_empty());
+        assert_eq!(cfg.flood.max_messages, 0);
+        assert_eq!(cfg.flood.window_secs, 300);
+        assert!(cfg.priority.users.is_empty());
+        assert!(!cfg.priority.is_priority(Some(1), false));
+        assert!(cfg.priority.is_priority(Some(1), true));
+        assert!(!cfg.priority.is_priority(None, true));
+        assert_eq!(cfg.circuit_breaker.failure_threshold, 3);
+        assert_eq!(cfg.circuit_breaker.max_backoff_secs, 300);
+        assert!(cfg.faq_cache.endpoint.is_empty());
+        assert_eq!(cfg.faq_cache.ttl_secs, 86400);
+        assert!(cfg.hooks.pre_turn.is_none());
+        assert_eq!(cfg.hooks.timeout_secs, 10);
+        assert!(!cfg.hooks.fail_open);
+        assert_eq!(cfg.retention, super::RetentionConfig::default());
+        assert_eq!(cfg.limits, super::LimitsConfig::default());
+        assert_eq!(cfg.model_watch.admin_channel, 0);
+        assert_eq!(cfg.model_watch.interval_mins, 60);
+        assert!(cfg.token_failover.backup_token.is_empty());
+        assert_eq!(cfg.voice, super::VoiceConfig::default());
+        assert!(!cfg.voice.is_enabled());
+        assert_eq!(
+            cfg.tool_approval.mode,
+            crate::agent::approval::ToolApprovalMode::Auto
+        );
+        assert_eq!(cfg.tool_approval.timeout_secs, 120);
+        assert_eq!(cfg.metrics.summary_channel, 0);
+        assert_eq!(cfg.metrics.summary_hour, 9);
+        assert_eq!(cfg.metrics.port, 0);
+        assert_eq!(cfg.metrics.bind_address, "127.0.0.1");
+        assert!(cfg.downloads.public_url.is_empty());
+        assert_eq!(cfg.downloads.ttl_secs, 86400);
+        assert_eq!(cfg.composer.thinking_min_chars, 200);
+        assert_eq!(cfg.composer.tool_output_min_chars, 120);
+        assert!(!cfg.composer.multi_embed);
+        assert!(!cfg.composer.paginate);
+        assert_eq!(cfg.composer.slow_tool_warn_secs, 30);
+        assert_eq!(cfg.opencode.instances, 1);
+        assert!(!cfg.analytics.enabled);
+        assert_eq!(cfg.analytics.max_files, 5);
+        assert_eq!(
+            cfg.opencode.strategy,
+            crate::agent::manager::PoolStrategy::RoundRobin
+        );
         // SAFETY: serialized by env lock
         unsafe { std::env::remove_var(BASE_DIR_ENV) };
     }
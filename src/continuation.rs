@@ -0,0 +1,169 @@
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    EditInteractionResponse, MessageId,
+};
+use tracing::info;
+
+use crate::agent::UserInput;
+
+pub const BUTTON_PREFIX: &str = "turn_continue_";
+
+/// 接續被截斷回答的指示；只附上結尾一段讓模型知道停在哪裡
+const CONTINUE_INSTRUCTION: &str = "Your previous reply was cut off by the output length limit. Continue exactly where it stopped, without repeating anything or adding an introduction.";
+
+/// 附給模型的回答結尾長度 (字元)
+const TAIL_CHARS: usize = 1500;
+
+/// 沒有後端訊號時，回答至少要這麼長才以結尾判斷是否被截斷
+const HEURISTIC_MIN_CHARS: usize = 1000;
+
+/// 句子明顯還沒結束的結尾字元
+const DANGLING_ENDINGS: &[char] = &[',', ':', ';', '(', '、', '，', '：', '；', '（'];
+
+pub fn continue_button(label: String, message_id: MessageId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "{}{}",
+        BUTTON_PREFIX, message_id
+    ))
+    .label(label)
+    .style(ButtonStyle::Secondary)])
+}
+
+pub fn parse_button(custom_id: &str) -> Option<u64> {
+    custom_id.strip_prefix(BUTTON_PREFIX)?.parse().ok()
+}
+
+/// 後端沒有回報截斷時的推測：程式碼區塊沒關閉，或長回答停在句子中間
+pub fn looks_truncated(answer: &str) -> bool {
+    let answer = answer.trim_end();
+    if answer.matches("```").count() % 2 == 1 {
+        return true;
+    }
+    answer.chars().count() >= HEURISTIC_MIN_CHARS && answer.ends_with(DANGLING_ENDINGS)
+}
+
+/// 「繼續」用的輸入：要求模型從回答結尾接著寫，並記住先前的回答供 composer 合併
+pub fn continue_input(original: &UserInput, answer: &str) -> UserInput {
+    let chars: Vec<char> = answer.chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(TAIL_CHARS)..]
+        .iter()
+        .collect();
+    UserInput {
+        text: format!(
+            "{}\n\nYour reply ended with:\n\n{}",
+            CONTINUE_INSTRUCTION, tail
+        ),
+        // 附件已隨原本的提問送出
+        files: Vec::new(),
+        continues: Some(answer.to_string()),
+        ..original.clone()
+    }
+}
+
+/// 「繼續」按鈕：讓模型接著寫被截斷的回答，新回應標記為原訊息的修訂版並顯示合併後的全文
+pub async fn handle_continue(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !crate::turn_controls::authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction.defer_ephemeral(&ctx.http).await?;
+
+    let channel_id = interaction.channel_id;
+    let cut_msg = parse_button(&interaction.data.custom_id).map(MessageId::new);
+    // 只能接續頻道中最近一次的回合，之後已有新回合時按鈕失效
+    let input = state
+        .last_turns
+        .lock()
+        .await
+        .get(&channel_id.get())
+        .filter(|(msg_id, _)| Some(*msg_id) == cut_msg)
+        .map(|(_, input)| input.clone());
+    let answer = state
+        .partial_answers
+        .lock()
+        .await
+        .remove(&channel_id.get())
+        .filter(|(msg_id, _)| Some(*msg_id) == cut_msg)
+        .map(|(_, answer)| answer);
+    let (Some(previous_msg_id), Some(input), Some(answer)) = (cut_msg, input, answer) else {
+        let msg = state.i18n.read().await.get("turn_continue_expired");
+        interaction
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        return Ok(());
+    };
+
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, is_new) = crate::Handler::open_session(state, channel_id.get(), agent_type).await?;
+
+    let msg = state.i18n.read().await.get("turn_continue_started");
+    interaction
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        .await?;
+
+    info!("⏩ Continuing truncated answer on channel {}", channel_id);
+    crate::Handler::start_agent_loop(
+        agent,
+        ctx.http.clone(),
+        channel_id,
+        state.clone(),
+        Some(continue_input(&input, &answer)),
+        is_new,
+        Some(previous_msg_id),
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_button_roundtrip() {
+        let id = format!("{}{}", BUTTON_PREFIX, 42);
+        assert_eq!(parse_button(&id), Some(42));
+        assert_eq!(parse_button("turn_retry_42"), None);
+        assert_eq!(parse_button("turn_continue_x"), None);
+    }
+
+    #[test]
+    fn test_looks_truncated_heuristics() {
+        assert!(looks_truncated("Example:\n```rust\nfn main() {"));
+        assert!(!looks_truncated("```rust\nfn main() {}\n```"));
+        // 短回答停在冒號多半是刻意的
+        assert!(!looks_truncated("Here is the list:"));
+        let long = format!("{} and then,", "word ".repeat(300));
+        assert!(looks_truncated(&long));
+        assert!(!looks_truncated(&format!("{}.", "word ".repeat(300))));
+    }
+
+    #[test]
+    fn test_continue_input_keeps_tail_and_previous_answer() {
+        let original = UserInput {
+            text: "write a long guide".to_string(),
+            files: Vec::new(),
+            requester: Some(7),
+            quick: false,
+            on_behalf_of: None,
+            disabled_tools: vec!["bash".to_string()],
+            continues: None,
+            source_message: None,
+            priority: false,
+        };
+        let answer = format!("{}the end of part one,", "前".repeat(TAIL_CHARS));
+        let next = continue_input(&original, &answer);
+        assert!(next.text.starts_with(CONTINUE_INSTRUCTION));
+        assert!(next.text.ends_with("the end of part one,"));
+        assert!(!next.text.contains(&"前".repeat(TAIL_CHARS)));
+        assert_eq!(next.continues.as_deref(), Some(answer.as_str()));
+        assert_eq!(next.requester, Some(7));
+        assert_eq!(next.disabled_tools, vec!["bash".to_string()]);
+    }
+}
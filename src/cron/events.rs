@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serenity::all::{
+    CreateScheduledEvent, EditScheduledEvent, GuildId, Http, ScheduledEventId, ScheduledEventType,
+    Timestamp,
+};
+use tracing::{info, warn};
+
+// Discord 排程活動名稱與說明的長度上限
+const EVENT_NAME_MAX_CHARS: usize = 100;
+const EVENT_DESCRIPTION_MAX_CHARS: usize = 1000;
+// 外部活動必須有結束時間；排程本身沒有長度，固定顯示 30 分鐘
+const EVENT_DURATION_MINS: i64 = 30;
+const RESULT_PREVIEW_CHARS: usize = 300;
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// 活動名稱取提示的第一行
+pub fn event_name(prompt: &str) -> String {
+    let first_line = prompt.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    truncate_chars(&format!("⏰ {}", first_line.trim()), EVENT_NAME_MAX_CHARS)
+}
+
+/// 上次執行結果的簡短摘要
+pub fn result_summary(success: bool, answer: &str, at: DateTime<Utc>) -> String {
+    let icon = if success { "✅" } else { "❌" };
+    let preview = truncate_chars(answer.trim(), RESULT_PREVIEW_CHARS);
+    format!("{} <t:{}:f>\n{}", icon, at.timestamp(), preview)
+        .trim_end()
+        .to_string()
+}
+
+pub fn event_description(body: &str) -> String {
+    truncate_chars(body, EVENT_DESCRIPTION_MAX_CHARS)
+}
+
+pub fn channel_link(guild_id: u64, channel_id: u64) -> String {
+    format!("https://discord.com/channels/{}/{}", guild_id, channel_id)
+}
+
+pub struct EventContent {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub name: String,
+    pub description: String,
+    pub next_run: DateTime<Utc>,
+}
+
+/// 建立或更新排程對應的 Discord 活動；原活動已被刪除或結束時改為建立新的
+pub async fn sync_event(
+    http: &Http,
+    existing: Option<u64>,
+    content: &EventContent,
+) -> anyhow::Result<u64> {
+    let guild_id = GuildId::new(content.guild_id);
+    let start = Timestamp::from_unix_timestamp(content.next_run.timestamp())?;
+    let end = Timestamp::from_unix_timestamp(
+        (content.next_run + chrono::Duration::minutes(EVENT_DURATION_MINS)).timestamp(),
+    )?;
+    let location = channel_link(content.guild_id, content.channel_id);
+
+    if let Some(event_id) = existing {
+        let edit = EditScheduledEvent::new()
+            .name(content.name.clone())
+            .description(content.description.clone())
+            .start_time(start)
+            .end_time(end)
+            .location(location.clone());
+        match guild_id
+            .edit_scheduled_event(http, ScheduledEventId::new(event_id), edit)
+            .await
+        {
+            Ok(_) => return Ok(event_id),
+            Err(e) => warn!(
+                "⚠️ Failed to update scheduled event {}; creating a new one: {}",
+                event_id, e
+            ),
+        }
+    }
+
+    let create = CreateScheduledEvent::new(ScheduledEventType::External, &content.name, start)
+        .description(content.description.clone())
+        .end_time(end)
+        .location(location);
+    let event = guild_id.create_scheduled_event(http, create).await?;
+    info!(
+        "📆 Created scheduled event {} for cron in channel {}",
+        event.id, content.channel_id
+    );
+    Ok(event.id.get())
+}
+
+pub async fn delete_event(http: &Http, guild_id: u64, event_id: u64) {
+    if let Err(e) = GuildId::new(guild_id)
+        .delete_scheduled_event(http, ScheduledEventId::new(event_id))
+        .await
+    {
+        warn!("⚠️ Failed to delete scheduled event {}: {}", event_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_name_uses_first_line_and_truncates() {
+        assert_eq!(event_name("\n  Daily report  \nmore"), "⏰ Daily report");
+        let long = "字".repeat(200);
+        let name = event_name(&long);
+        assert_eq!(name.chars().count(), EVENT_NAME_MAX_CHARS);
+        assert!(name.ends_with('…'));
+    }
+
+    #[test]
+    fn test_result_summary_formats_status_and_preview() {
+        let at = DateTime::from_timestamp(1_700_000_000, 0).expect("timestamp");
+        assert_eq!(
+            result_summary(true, " all good ", at),
+            "✅ <t:1700000000:f>\nall good"
+        );
+        assert_eq!(result_summary(false, "", at), "❌ <t:1700000000:f>");
+    }
+}
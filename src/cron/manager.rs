@@ -1,5 +1,6 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serenity::all::{CreateEmbed, CreateEmbedFooter, CreateMessage};
+use serenity::all::{CreateAttachment, CreateEmbed, CreateEmbedFooter, CreateMessage};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -8,6 +9,7 @@ use tokio_cron_scheduler::{Job, JobScheduler};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use super::events;
 use crate::AppState;
 use std::sync::Weak;
 
@@ -21,6 +23,74 @@ pub struct CronJobInfo {
     pub prompt: String,
     pub creator_id: u64,
     pub description: String,
+    /// 建立排程的伺服器；私訊排程為 None，不建立 Discord 活動
+    #[serde(default)]
+    pub guild_id: Option<u64>,
+    /// 對應的 Discord 排程活動
+    #[serde(default)]
+    pub event_id: Option<u64>,
+    /// 上次執行結果摘要，顯示在活動說明中
+    #[serde(default)]
+    pub last_result: Option<String>,
+    /// 以此使用者名義執行：套用其個人偏好並在觸發訊息中標示
+    #[serde(default)]
+    pub run_as: Option<u64>,
+    /// 回合結束後把代理存放在產出資料夾的檔案作為附件送出
+    #[serde(default)]
+    pub attach_files: bool,
+    /// 建立時頻道使用的後端；頻道之後換了後端時跳過執行，避免覆蓋頻道的 session。
+    /// 舊排程為 None，沿用頻道目前的後端
+    #[serde(default)]
+    pub agent_type: Option<crate::agent::AgentType>,
+    /// 暫停中的排程不會自動執行，`/cron run` 仍可手動執行
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// 每次執行最多附上的產出檔案數 (Discord 單則訊息附件上限)
+const MAX_ARTIFACTS: usize = 10;
+
+/// 排程產出檔案的資料夾，每次執行前清空
+fn artifact_dir(config_dir: &std::path::Path, id: Uuid) -> PathBuf {
+    config_dir.join("cron_artifacts").join(id.to_string())
+}
+
+/// 排程送給代理的提示；需要附件時告知產出資料夾
+fn build_job_prompt(info: &CronJobInfo, artifact_dir: Option<&std::path::Path>) -> String {
+    match artifact_dir {
+        Some(dir) => format!(
+            "{}\n\n[Save any files you create for this run in `{}`; they will be attached to the result.]",
+            info.prompt,
+            dir.display()
+        ),
+        None => info.prompt.clone(),
+    }
+}
+
+/// 產出資料夾內的一般檔案，依檔名排序並限制數量
+async fn collect_artifacts(dir: &std::path::Path) -> Vec<(String, Vec<u8>)> {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+    let mut paths = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.file_type().await.is_ok_and(|t| t.is_file()) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    let mut files = Vec::new();
+    for path in paths.into_iter().take(MAX_ARTIFACTS) {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => files.push((name, bytes)),
+            Err(e) => warn!("⚠️ Failed to read cron artifact {}: {}", path.display(), e),
+        }
+    }
+    files
 }
 
 pub struct CronManager {
@@ -29,6 +99,8 @@ pub struct CronManager {
     config_dir: PathBuf,
     http: Arc<Mutex<Option<Arc<serenity::all::Http>>>>,
     state: Arc<Mutex<Option<Weak<AppState>>>>,
+    /// 已觸發、等待回合完成的排程 (頻道 -> 排程 ID)
+    running: Arc<Mutex<HashMap<u64, Uuid>>>,
 }
 
 impl CronManager {
@@ -49,9 +121,15 @@ impl CronManager {
             config_dir,
             http: Arc::new(Mutex::new(None)),
             state: Arc::new(Mutex::new(None)),
+            running: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// 備用 token 接手連線後，之後的排程改用新的 Http
+    pub async fn set_http(&self, http: Arc<serenity::all::Http>) {
+        *self.http.lock().await = Some(http);
+    }
+
     pub async fn init(&self, http: Arc<serenity::all::Http>, state: Weak<AppState>) {
         {
             let mut h = self.http.lock().await;
@@ -96,9 +174,220 @@ impl CronManager {
         // 3. 存入磁碟
         self.save_to_disk().await?;
 
+        self.sync_scheduled_event(id).await;
+
         Ok(id)
     }
 
+    async fn scheduled_events_enabled(&self) -> bool {
+        let state = self.state.lock().await;
+        state
+            .as_ref()
+            .and_then(|w| w.upgrade())
+            .is_some_and(|s| s.config.cron_scheduled_events)
+    }
+
+    /// 依下次執行時間與上次結果建立或更新 Discord 排程活動；失敗只記錄警告
+    async fn sync_scheduled_event(&self, id: Uuid) {
+        if !self.scheduled_events_enabled().await {
+            return;
+        }
+        let Some(http) = self.http.lock().await.clone() else {
+            return;
+        };
+        let Some(info) = self.jobs.lock().await.get(&id).cloned() else {
+            return;
+        };
+        let (Some(guild_id), Some(scheduler_id), false) =
+            (info.guild_id, info.scheduler_id, info.paused)
+        else {
+            return;
+        };
+        let next_run = match self.scheduler.clone().next_tick_for_job(scheduler_id).await {
+            Ok(Some(next)) => next,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("⚠️ Failed to get next run of cron job {}: {}", id, e);
+                return;
+            }
+        };
+
+        let body = {
+            let state = self.state.lock().await.as_ref().and_then(|w| w.upgrade());
+            let Some(state) = state else {
+                return;
+            };
+            let i18n = state.i18n.read().await;
+            let mut body = i18n.get_args(
+                "cron_event_description",
+                &[info.description.clone(), info.prompt.clone()],
+            );
+            if let Some(result) = info.last_result.clone() {
+                body = format!(
+                    "{}\n\n{}",
+                    i18n.get_args("cron_event_last_result", &[result]),
+                    body
+                );
+            }
+            body
+        };
+        let content = events::EventContent {
+            guild_id,
+            channel_id: info.channel_id,
+            name: events::event_name(&info.prompt),
+            description: events::event_description(&body),
+            next_run,
+        };
+        match events::sync_event(&http, info.event_id, &content).await {
+            Ok(event_id) if Some(event_id) != info.event_id => {
+                if let Some(job) = self.jobs.lock().await.get_mut(&id) {
+                    job.event_id = Some(event_id);
+                }
+                if let Err(e) = self.save_to_disk().await {
+                    warn!("⚠️ Failed to save cron jobs: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "⚠️ Failed to sync scheduled event for cron job {}: {}",
+                id, e
+            ),
+        }
+    }
+
+    /// 排程觸發的回合結束後記錄結果、送出產出檔案並更新活動
+    pub async fn record_result(&self, channel_id: u64, success: bool, answer: &str) {
+        let Some(id) = self.running.lock().await.remove(&channel_id) else {
+            return;
+        };
+        let info = {
+            let mut jobs = self.jobs.lock().await;
+            let Some(job) = jobs.get_mut(&id) else {
+                return;
+            };
+            job.last_result = Some(events::result_summary(success, answer, chrono::Utc::now()));
+            job.clone()
+        };
+        if let Err(e) = self.save_to_disk().await {
+            warn!("⚠️ Failed to save cron jobs: {}", e);
+        }
+        if info.attach_files {
+            self.deliver_artifacts(&info).await;
+        }
+        self.sync_scheduled_event(id).await;
+    }
+
+    async fn deliver_artifacts(&self, info: &CronJobInfo) {
+        let dir = artifact_dir(&self.config_dir, info.id);
+        let files = collect_artifacts(&dir).await;
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        if files.is_empty() {
+            return;
+        }
+        let Some(http) = self.http.lock().await.clone() else {
+            return;
+        };
+        let note = {
+            let state = self.state.lock().await.as_ref().and_then(|w| w.upgrade());
+            let Some(state) = state else {
+                return;
+            };
+            let i18n = state.i18n.read().await;
+            i18n.get_args("cron_artifacts", std::slice::from_ref(&info.description))
+        };
+        let channel_id = serenity::model::id::ChannelId::from(info.channel_id);
+        let limit = crate::delivery::upload_limit_for_channel(&http, channel_id).await;
+        let mut first = true;
+        for (name, bytes) in files {
+            let messages = match crate::delivery::prepare_upload(&name, bytes.clone(), limit) {
+                Ok(parts) => parts
+                    .into_iter()
+                    .map(|(part_name, part)| {
+                        CreateMessage::new().add_file(CreateAttachment::bytes(part, part_name))
+                    })
+                    .collect(),
+                // 壓縮分割後仍過大時改貼下載連結
+                Err(e) => match crate::downloads::publish(&name, &bytes).await {
+                    Ok(Some(link)) => {
+                        let content = if std::mem::take(&mut first) {
+                            format!("{}\n{}", note, link)
+                        } else {
+                            link
+                        };
+                        vec![CreateMessage::new().content(content)]
+                    }
+                    result => {
+                        if let Err(link_err) = result {
+                            warn!("⚠️ Failed to publish cron artifact {}: {}", name, link_err);
+                        }
+                        warn!("⚠️ Cron artifact {} is too large to attach: {}", name, e);
+                        continue;
+                    }
+                },
+            };
+            for mut message in messages {
+                if first {
+                    message = message.content(note.clone());
+                    first = false;
+                }
+                if let Err(e) = channel_id.send_message(&http, message).await {
+                    warn!("⚠️ Failed to upload cron artifact {}: {}", name, e);
+                }
+            }
+        }
+    }
+
+    /// 立即執行排程 (`/cron run`)；找不到排程時回傳 false
+    pub async fn run_now(&self, id: Uuid) -> bool {
+        let Some(info) = self.jobs.lock().await.get(&id).cloned() else {
+            return false;
+        };
+        tokio::spawn(execute_job(
+            self.http.clone(),
+            self.state.clone(),
+            self.running.clone(),
+            self.config_dir.clone(),
+            info,
+        ));
+        true
+    }
+
+    /// 暫停或恢復排程；暫停時移除 Discord 活動，恢復時重新建立。找不到排程時回傳 false
+    pub async fn set_paused(&self, id: Uuid, paused: bool) -> anyhow::Result<bool> {
+        let event = {
+            let mut jobs = self.jobs.lock().await;
+            let Some(job) = jobs.get_mut(&id) else {
+                return Ok(false);
+            };
+            job.paused = paused;
+            if paused {
+                job.guild_id.zip(job.event_id.take())
+            } else {
+                None
+            }
+        };
+        self.save_to_disk().await?;
+        info!(
+            "{} cron job {}",
+            if paused {
+                "⏸️ Paused"
+            } else {
+                "▶️ Resumed"
+            },
+            id
+        );
+
+        if let Some((guild_id, event_id)) = event {
+            if let Some(http) = self.http.lock().await.clone() {
+                events::delete_event(&http, guild_id, event_id).await;
+            }
+        }
+        if !paused {
+            self.sync_scheduled_event(id).await;
+        }
+        Ok(true)
+    }
+
     async fn re_register_job(&self, id: Uuid) -> anyhow::Result<()> {
         let mut jobs = self.jobs.lock().await;
         if let Some(info) = jobs.get_mut(&id) {
@@ -110,92 +399,30 @@ impl CronManager {
 
     async fn register_job_to_scheduler(&self, info: &CronJobInfo) -> anyhow::Result<Uuid> {
         let cron_expr = info.cron_expr.clone();
-        let prompt = info.prompt.clone();
-        let channel_id_u64 = info.channel_id;
+        let job_id = info.id;
 
         let http_ptr = self.http.clone();
         let state_ptr = self.state.clone();
+        let running_ptr = self.running.clone();
+        let jobs_ptr = self.jobs.clone();
+        let config_dir = self.config_dir.clone();
 
         let job = Job::new_async_tz(cron_expr.as_str(), chrono::Local, move |_uuid, _l| {
-            let prompt = prompt.clone();
             let http_ptr = http_ptr.clone();
             let state_ptr = state_ptr.clone();
+            let running_ptr = running_ptr.clone();
+            let jobs_ptr = jobs_ptr.clone();
+            let config_dir = config_dir.clone();
             Box::pin(async move {
-                info!("⏰ Cron job triggered for channel {}", channel_id_u64);
-                let http_opt = http_ptr.lock().await;
-                let state_weak_opt = state_ptr.lock().await;
-
-                if let (Some(http), Some(state_weak)) = (http_opt.as_ref(), state_weak_opt.as_ref())
-                {
-                    if let Some(state) = state_weak.upgrade() {
-                        let channel_id = serenity::model::id::ChannelId::from(channel_id_u64);
-                        let cron_footer = {
-                            let i18n = state.i18n.read().await;
-                            i18n.get("cron_triggered_footer")
-                        };
-                        if let Err(e) = channel_id
-                            .send_message(
-                                http,
-                                CreateMessage::new().embed(
-                                    CreateEmbed::new()
-                                        .description(prompt.clone())
-                                        .footer(CreateEmbedFooter::new(cron_footer)),
-                                ),
-                            )
-                            .await
-                        {
-                            warn!("⚠️ Failed to send cron trigger embed: {}", e);
-                        }
-
-                        let has_active_render = {
-                            let active = state.active_renders.lock().await;
-                            active.contains_key(&channel_id_u64)
-                        };
-                        if has_active_render {
-                            info!(
-                                "⏭️ Cron job skipped for channel {} because an active render is running",
-                                channel_id_u64
-                            );
-                            return;
-                        }
-
-                        let channel_id_str = channel_id.to_string();
-
-                        let channel_config = crate::commands::agent::ChannelConfig::load()
-                            .await
-                            .unwrap_or_default();
-                        let agent_type = channel_config.get_agent_type(&channel_id_str);
-
-                        match state
-                            .session_manager
-                            .get_or_create_session(
-                                channel_id_u64,
-                                agent_type,
-                                &state.backend_manager,
-                            )
-                            .await
-                        {
-                            Ok((agent, is_new)) => {
-                                crate::Handler::start_agent_loop(
-                                    agent,
-                                    http.clone(),
-                                    channel_id,
-                                    (*state).clone(),
-                                    Some(crate::agent::UserInput::new_text(prompt)),
-                                    is_new,
-                                )
-                                .await;
-                            }
-                            Err(e) => {
-                                error!("❌ Cron job execution failed to create session: {}", e)
-                            }
-                        }
-                    } else {
-                        error!("❌ Cron job triggered but AppState was dropped");
-                    }
-                } else {
-                    error!("❌ Cron job triggered but Http/State not initialized. Did you call init()?");
+                // 觸發時才讀取排程內容，讓執行身分等設定的變更立即生效
+                let Some(info) = jobs_ptr.lock().await.get(&job_id).cloned() else {
+                    return;
+                };
+                if info.paused {
+                    info!("⏸️ Cron job {} is paused; skipping", job_id);
+                    return;
                 }
+                execute_job(http_ptr, state_ptr, running_ptr, config_dir, info).await;
             })
         })?;
 
@@ -235,22 +462,196 @@ impl CronManager {
             .collect()
     }
 
+    /// 排程下次執行的時間；尚未註冊到排程器或已無下次執行時為 None
+    pub async fn next_run(&self, info: &CronJobInfo) -> Option<DateTime<Utc>> {
+        let scheduler_id = info.scheduler_id?;
+        self.scheduler
+            .clone()
+            .next_tick_for_job(scheduler_id)
+            .await
+            .ok()
+            .flatten()
+    }
+
     pub async fn remove_job(&self, id: Uuid) -> anyhow::Result<()> {
-        let removed_scheduler_id = {
+        let removed = {
             let mut jobs = self.jobs.lock().await;
-            jobs.remove(&id).and_then(|info| info.scheduler_id)
+            jobs.remove(&id)
         };
 
-        if let Some(s_id) = removed_scheduler_id {
+        if let Some(s_id) = removed.as_ref().and_then(|info| info.scheduler_id) {
             self.scheduler.remove(&s_id).await?;
             info!("🗑️ Removed cron job {} (scheduler id: {})", id, s_id);
         }
 
+        if let Some((guild_id, event_id)) =
+            removed.and_then(|info| info.guild_id.zip(info.event_id))
+        {
+            if let Some(http) = self.http.lock().await.clone() {
+                events::delete_event(&http, guild_id, event_id).await;
+            }
+        }
+
         self.save_to_disk().await?;
         Ok(())
     }
 }
 
+/// 執行一次排程：送出觸發訊息並開始回合 (排程器與 `/cron run` 共用)
+async fn execute_job(
+    http_ptr: Arc<Mutex<Option<Arc<serenity::all::Http>>>>,
+    state_ptr: Arc<Mutex<Option<Weak<AppState>>>>,
+    running_ptr: Arc<Mutex<HashMap<u64, Uuid>>>,
+    config_dir: PathBuf,
+    info: CronJobInfo,
+) {
+    let channel_id_u64 = info.channel_id;
+    info!("⏰ Cron job triggered for channel {}", channel_id_u64);
+    let http_opt = http_ptr.lock().await.clone();
+    let state_weak_opt = state_ptr.lock().await.clone();
+
+    let (Some(http), Some(state_weak)) = (http_opt, state_weak_opt) else {
+        error!("❌ Cron job triggered but Http/State not initialized. Did you call init()?");
+        return;
+    };
+    let Some(state) = state_weak.upgrade() else {
+        error!("❌ Cron job triggered but AppState was dropped");
+        return;
+    };
+    let channel_id = serenity::model::id::ChannelId::from(channel_id_u64);
+    let channel_id_str = channel_id.to_string();
+    let channel_config = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default();
+    let agent_type = channel_config.get_agent_type(&channel_id_str);
+    if let Some(expected) = info.agent_type.as_ref().filter(|t| **t != agent_type) {
+        warn!(
+            "⏭️ Cron job {} skipped: created for {} but channel {} now uses {}",
+            info.id, expected, channel_id_u64, agent_type
+        );
+        let note = state.i18n.read().await.get_args(
+            "cron_backend_mismatch",
+            &[
+                info.description.clone(),
+                expected.to_string(),
+                agent_type.to_string(),
+            ],
+        );
+        let _ = channel_id.say(&http, note).await;
+        return;
+    }
+
+    let embed = {
+        let i18n = state.i18n.read().await;
+        let mut embed = CreateEmbed::new()
+            .description(info.prompt.clone())
+            .footer(CreateEmbedFooter::new(i18n.get("cron_triggered_footer")));
+        // 提及放在 embed 內不會通知對方
+        if let Some(user_id) = info.run_as {
+            embed = embed.field(
+                i18n.get("cron_run_as_label"),
+                format!("<@{}>", user_id),
+                true,
+            );
+        }
+        embed
+    };
+    if let Err(e) = channel_id
+        .send_message(&http, CreateMessage::new().embed(embed))
+        .await
+    {
+        warn!("⚠️ Failed to send cron trigger embed: {}", e);
+    }
+
+    let has_active_render = {
+        let active = state.active_renders.lock().await;
+        active.contains_key(&channel_id_u64)
+    };
+    if has_active_render {
+        info!(
+            "⏭️ Cron job skipped for channel {} because an active render is running",
+            channel_id_u64
+        );
+        return;
+    }
+
+    let artifacts = if info.attach_files {
+        let dir = artifact_dir(&config_dir, info.id);
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        match tokio::fs::create_dir_all(&dir).await {
+            Ok(()) => Some(dir),
+            Err(e) => {
+                warn!("⚠️ Failed to create cron artifact folder: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let input = crate::agent::UserInput {
+        on_behalf_of: info.run_as,
+        ..crate::agent::UserInput::new_text(build_job_prompt(&info, artifacts.as_deref()))
+    };
+
+    match crate::Handler::open_session(&state, channel_id_u64, agent_type).await {
+        Ok((agent, is_new)) => {
+            running_ptr.lock().await.insert(channel_id_u64, info.id);
+            crate::Handler::start_agent_loop(
+                agent,
+                http.clone(),
+                channel_id,
+                (*state).clone(),
+                Some(input),
+                is_new,
+                None,
+            )
+            .await;
+        }
+        Err(e) => {
+            error!("❌ Cron job execution failed to create session: {}", e)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::services::SchedulerService for CronManager {
+    async fn init(&self, http: Arc<serenity::all::Http>, state: Weak<AppState>) {
+        CronManager::init(self, http, state).await
+    }
+
+    async fn set_http(&self, http: Arc<serenity::all::Http>) {
+        CronManager::set_http(self, http).await
+    }
+
+    async fn add_job(&self, info: CronJobInfo) -> anyhow::Result<Uuid> {
+        CronManager::add_job(self, info).await
+    }
+
+    async fn remove_job(&self, id: Uuid) -> anyhow::Result<()> {
+        CronManager::remove_job(self, id).await
+    }
+
+    async fn set_paused(&self, id: Uuid, paused: bool) -> anyhow::Result<bool> {
+        CronManager::set_paused(self, id, paused).await
+    }
+
+    async fn run_now(&self, id: Uuid) -> bool {
+        CronManager::run_now(self, id).await
+    }
+
+    async fn get_jobs_for_channel(&self, channel_id: u64) -> Vec<CronJobInfo> {
+        CronManager::get_jobs_for_channel(self, channel_id).await
+    }
+
+    async fn next_run(&self, info: &CronJobInfo) -> Option<DateTime<Utc>> {
+        CronManager::next_run(self, info).await
+    }
+
+    async fn record_result(&self, channel_id: u64, success: bool, answer: &str) {
+        CronManager::record_result(self, channel_id, success, answer).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -269,6 +670,13 @@ mod tests {
             prompt: prompt.to_string(),
             creator_id: 1,
             description: "test".to_string(),
+            guild_id: None,
+            event_id: None,
+            last_result: None,
+            run_as: None,
+            attach_files: false,
+            agent_type: None,
+            paused: false,
         }
     }
 
@@ -327,6 +735,51 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_record_result_only_applies_to_running_cron_turn() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let manager = new_test_manager(&dir).await?;
+        let job_id = Uuid::new_v4();
+        manager.add_job(build_job(job_id, 555, "Report")).await?;
+
+        // 非排程觸發的回合不記錄
+        manager.record_result(555, true, "ignored").await;
+        assert!(manager.get_jobs_for_channel(555).await[0]
+            .last_result
+            .is_none());
+
+        manager.running.lock().await.insert(555, job_id);
+        manager.record_result(555, true, "done").await;
+        let result = manager.get_jobs_for_channel(555).await[0]
+            .last_result
+            .clone()
+            .expect("result recorded");
+        assert!(result.starts_with("✅"));
+        assert!(result.ends_with("done"));
+        assert!(manager.running.lock().await.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pause_is_persisted() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let manager = new_test_manager(&dir).await?;
+        let job_id = Uuid::new_v4();
+        manager.add_job(build_job(job_id, 777, "Digest")).await?;
+
+        assert!(manager.set_paused(job_id, true).await?);
+        assert!(!manager.set_paused(Uuid::new_v4(), true).await?);
+
+        let manager2 = new_test_manager(&dir).await?;
+        manager2.load_from_disk().await?;
+        assert!(manager2.get_jobs_for_channel(777).await[0].paused);
+
+        manager.set_paused(job_id, false).await?;
+        assert!(!manager.get_jobs_for_channel(777).await[0].paused);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_jobs_for_channel_filters_correctly() -> anyhow::Result<()> {
         let dir = tempdir()?;
@@ -350,4 +803,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_artifacts_are_collected_from_job_folder() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let mut job = build_job(Uuid::new_v4(), 1, "Report");
+        assert_eq!(build_job_prompt(&job, None), "Report");
+
+        job.attach_files = true;
+        let folder = artifact_dir(dir.path(), job.id);
+        let prompt = build_job_prompt(&job, Some(&folder));
+        assert!(prompt.starts_with("Report\n\n"));
+        assert!(prompt.contains(&folder.display().to_string()));
+
+        assert!(collect_artifacts(&folder).await.is_empty());
+        tokio::fs::create_dir_all(folder.join("nested")).await?;
+        tokio::fs::write(folder.join("b.csv"), "1,2").await?;
+        tokio::fs::write(folder.join("a.txt"), "hi").await?;
+        let files = collect_artifacts(&folder).await;
+        let names: Vec<_> = files.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["a.txt", "b.csv"]);
+        assert_eq!(files[0].1, b"hi");
+        Ok(())
+    }
 }
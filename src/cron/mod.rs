@@ -1,3 +1,4 @@
+pub mod events;
 pub mod manager;
 
 pub use manager::CronManager;
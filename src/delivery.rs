@@ -0,0 +1,441 @@
+use serenity::all::{
+    Channel, ChannelId, CreateAttachment, CreateInteractionResponseFollowup, CreateMessage,
+    EditInteractionResponse, EditMessage, Http, Message, PremiumTier, UserId,
+};
+use serenity::builder::Builder;
+use std::io::Write;
+use tracing::warn;
+
+// Discord 純文字訊息上限
+pub const MESSAGE_MAX_CHARS: usize = 2000;
+/// 未加成伺服器與私訊的附件上限
+pub const DEFAULT_UPLOAD_LIMIT_BYTES: u64 = 10 * 1024 * 1024;
+// 分割上傳最多幾個分段，超過則放棄上傳
+const MAX_UPLOAD_PARTS: usize = 10;
+// 保留給 multipart 表單欄位與訊息內容的空間
+const UPLOAD_OVERHEAD_BYTES: u64 = 64 * 1024;
+
+/// 編輯被 Discord 拒絕時的降級方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditFallback {
+    /// 移除 Embed，改以純文字分段送出
+    StripEmbeds,
+    /// 內容改為附件檔案
+    AttachFile,
+}
+
+pub fn discord_error_code(err: &serenity::Error) -> Option<isize> {
+    match err {
+        serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(resp)) => {
+            Some(resp.error.code)
+        }
+        _ => None,
+    }
+}
+
+pub fn fallback_for_code(code: isize) -> Option<EditFallback> {
+    match code {
+        // Invalid Form Body：Embed 過大或 Markdown/欄位不合法
+        50035 => Some(EditFallback::StripEmbeds),
+        // 40005 Request entity too large、200000/200001 被 AutoMod 擋下
+        40005 | 200000 | 200001 => Some(EditFallback::AttachFile),
+        _ => None,
+    }
+}
+
+/// 伺服器加成等級對應的附件上限
+pub fn upload_limit_for_tier(tier: PremiumTier) -> u64 {
+    match tier {
+        PremiumTier::Tier2 => 50 * 1024 * 1024,
+        PremiumTier::Tier3 => 100 * 1024 * 1024,
+        _ => DEFAULT_UPLOAD_LIMIT_BYTES,
+    }
+}
+
+/// 查詢頻道所屬伺服器的附件上限；私訊或查詢失敗時採用預設值
+pub async fn upload_limit_for_channel(http: &Http, channel_id: ChannelId) -> u64 {
+    let guild_id = match channel_id.to_channel(http).await {
+        Ok(Channel::Guild(channel)) => channel.guild_id,
+        _ => return DEFAULT_UPLOAD_LIMIT_BYTES,
+    };
+    match http.get_guild(guild_id).await {
+        Ok(guild) => upload_limit_for_tier(guild.premium_tier),
+        Err(e) => {
+            warn!("⚠️ Failed to look up upload limit for {}: {}", guild_id, e);
+            DEFAULT_UPLOAD_LIMIT_BYTES
+        }
+    }
+}
+
+fn zip_file(name: &str, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut buf = std::io::Cursor::new(Vec::new());
+    let mut writer = zip::ZipWriter::new(&mut buf);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    writer.start_file(name, options)?;
+    writer.write_all(bytes)?;
+    writer.finish()?;
+    Ok(buf.into_inner())
+}
+
+/// 讓檔案符合附件上限：原檔過大時先壓縮成 zip，仍過大則切成 `.001`、`.002`… 分段
+/// (可用 `cat name.zip.* > name.zip` 合併)；分段數超過上限時回傳錯誤
+pub fn prepare_upload(
+    name: &str,
+    bytes: Vec<u8>,
+    limit: u64,
+) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let part_size = limit.saturating_sub(UPLOAD_OVERHEAD_BYTES).max(1) as usize;
+    if bytes.len() <= part_size {
+        return Ok(vec![(name.to_string(), bytes)]);
+    }
+    let zip_name = format!("{}.zip", name);
+    let zipped = zip_file(name, &bytes)?;
+    if zipped.len() <= part_size {
+        return Ok(vec![(zip_name, zipped)]);
+    }
+    let parts = zipped.len().div_ceil(part_size);
+    if parts > MAX_UPLOAD_PARTS {
+        anyhow::bail!(
+            "{} is {} bytes after compression, more than {} parts of {} bytes",
+            name,
+            zipped.len(),
+            MAX_UPLOAD_PARTS,
+            part_size
+        );
+    }
+    Ok(zipped
+        .chunks(part_size)
+        .enumerate()
+        .map(|(i, chunk)| (format!("{}.{:03}", zip_name, i + 1), chunk.to_vec()))
+        .collect())
+}
+
+/// 依伺服器上限上傳檔案到頻道：第一段附在 `first` 編輯中，其餘分段各自成一則訊息
+/// 上傳仍被拒絕 (40005) 時改用預設上限重試一次。超過 `MAX_UPLOAD_PARTS` 段或以預設上限
+/// 仍被拒絕時改貼下載連結 (`[downloads]`)；未設定連結時回傳錯誤
+pub async fn send_file_within_limit(
+    http: &Http,
+    msg: &mut Message,
+    first: EditMessage,
+    name: &str,
+    bytes: Vec<u8>,
+) -> anyhow::Result<()> {
+    let mut limit = upload_limit_for_channel(http, msg.channel_id).await;
+    let too_large = loop {
+        let parts = match prepare_upload(name, bytes.clone(), limit) {
+            Ok(parts) => parts,
+            Err(e) => break e,
+        };
+        match upload_parts(http, msg, first.clone(), parts).await {
+            Err(e) if discord_error_code(&e) == Some(40005) => {
+                if limit <= DEFAULT_UPLOAD_LIMIT_BYTES {
+                    break e.into();
+                }
+                warn!(
+                    "⚠️ Upload of {} rejected at {} bytes; retrying with the default limit",
+                    name, limit
+                );
+                limit = DEFAULT_UPLOAD_LIMIT_BYTES;
+            }
+            result => return Ok(result?),
+        }
+    };
+    let Some(link) = crate::downloads::publish(name, &bytes).await? else {
+        return Err(too_large);
+    };
+    msg.edit(http, first).await?;
+    msg.channel_id
+        .send_message(http, CreateMessage::new().content(link))
+        .await?;
+    Ok(())
+}
+
+async fn upload_parts(
+    http: &Http,
+    msg: &mut Message,
+    first: EditMessage,
+    parts: Vec<(String, Vec<u8>)>,
+) -> serenity::Result<()> {
+    let mut parts = parts.into_iter();
+    if let Some((name, bytes)) = parts.next() {
+        msg.edit(
+            http,
+            first.new_attachment(CreateAttachment::bytes(bytes, name)),
+        )
+        .await?;
+    }
+    for (name, bytes) in parts {
+        msg.channel_id
+            .send_message(
+                http,
+                CreateMessage::new().add_file(CreateAttachment::bytes(bytes, name)),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// 與 `send_file_within_limit` 相同，但回覆在延遲的僅自己可見互動上：第一段附在 `first`
+/// 對原回應的編輯中，其餘分段或下載連結各自以僅自己可見的 follow-up 送出
+pub async fn send_interaction_file_within_limit(
+    http: &Http,
+    channel_id: ChannelId,
+    token: &str,
+    first: EditInteractionResponse,
+    name: &str,
+    bytes: Vec<u8>,
+) -> anyhow::Result<()> {
+    let mut limit = upload_limit_for_channel(http, channel_id).await;
+    let too_large = loop {
+        let parts = match prepare_upload(name, bytes.clone(), limit) {
+            Ok(parts) => parts,
+            Err(e) => break e,
+        };
+        match upload_interaction_parts(http, token, first.clone(), parts).await {
+            Err(e) if discord_error_code(&e) == Some(40005) => {
+                if limit <= DEFAULT_UPLOAD_LIMIT_BYTES {
+                    break e.into();
+                }
+                warn!(
+                    "⚠️ Upload of {} rejected at {} bytes; retrying with the default limit",
+                    name, limit
+                );
+                limit = DEFAULT_UPLOAD_LIMIT_BYTES;
+            }
+            result => return Ok(result?),
+        }
+    };
+    let Some(link) = crate::downloads::publish(name, &bytes).await? else {
+        return Err(too_large);
+    };
+    first.execute(http, token).await?;
+    CreateInteractionResponseFollowup::new()
+        .content(link)
+        .ephemeral(true)
+        .execute(http, (None, token))
+        .await?;
+    Ok(())
+}
+
+async fn upload_interaction_parts(
+    http: &Http,
+    token: &str,
+    first: EditInteractionResponse,
+    parts: Vec<(String, Vec<u8>)>,
+) -> serenity::Result<()> {
+    let mut parts = parts.into_iter();
+    if let Some((name, bytes)) = parts.next() {
+        first
+            .new_attachment(CreateAttachment::bytes(bytes, name))
+            .execute(http, token)
+            .await?;
+    }
+    for (name, bytes) in parts {
+        CreateInteractionResponseFollowup::new()
+            .add_file(CreateAttachment::bytes(bytes, name))
+            .ephemeral(true)
+            .execute(http, (None, token))
+            .await?;
+    }
+    Ok(())
+}
+
+/// 依字元數切段，盡量在換行處斷開
+pub fn split_content(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if rest.chars().count() <= max_chars {
+            chunks.push(rest.to_string());
+            break;
+        }
+        let hard_end = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let cut = rest[..hard_end]
+            .rfind('\n')
+            .filter(|pos| *pos > 0)
+            .unwrap_or(hard_end);
+        chunks.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    chunks
+}
+
+pub fn views_to_plain_text(views: &[(String, u32, String)]) -> String {
+    views
+        .iter()
+        .map(|(title, _, body)| format!("**{}**\n{}", title, body))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 私訊使用者，超過單則上限時分段送出
+pub async fn send_dm_chunks(http: &Http, user_id: u64, text: &str) -> serenity::Result<()> {
+    let dm = UserId::new(user_id).create_dm_channel(http).await?;
+    for chunk in split_content(text, MESSAGE_MAX_CHARS) {
+        dm.send_message(http, CreateMessage::new().content(chunk))
+            .await?;
+    }
+    Ok(())
+}
+
+/// 以降級方式重送內容；僅在最終狀態送出額外分段或附件，避免串流中洗版
+pub async fn apply_edit_fallback(
+    http: &Http,
+    msg: &mut Message,
+    views: &[(String, u32, String)],
+    fallback: EditFallback,
+    is_final: bool,
+    attached_note: &str,
+    too_large_note: &str,
+) -> serenity::Result<()> {
+    let plain = views_to_plain_text(views);
+    match fallback {
+        EditFallback::StripEmbeds => {
+            let chunks = split_content(&plain, MESSAGE_MAX_CHARS);
+            let first = chunks.first().cloned().unwrap_or_default();
+            // 回答改以一般訊息呈現，其中的提及不應通知任何人
+            msg.edit(
+                http,
+                EditMessage::new()
+                    .content(first)
+                    .embeds(vec![])
+                    .allowed_mentions(crate::mentions::suppressed()),
+            )
+            .await?;
+            if is_final {
+                for chunk in chunks.into_iter().skip(1) {
+                    msg.channel_id
+                        .send_message(
+                            http,
+                            CreateMessage::new()
+                                .content(chunk)
+                                .allowed_mentions(crate::mentions::suppressed()),
+                        )
+                        .await?;
+                }
+            }
+        }
+        EditFallback::AttachFile => {
+            let edit = EditMessage::new().content(attached_note).embeds(vec![]);
+            if !is_final {
+                msg.edit(http, edit).await?;
+                return Ok(());
+            }
+            if let Err(e) =
+                send_file_within_limit(http, msg, edit, "response.md", plain.into_bytes()).await
+            {
+                // 壓縮與分割後仍無法上傳時，至少告知使用者而非靜默失敗
+                warn!("⚠️ Failed to attach response: {}", e);
+                msg.edit(
+                    http,
+                    EditMessage::new().content(too_large_note).embeds(vec![]),
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fallback_for_code, prepare_upload, split_content, upload_limit_for_tier,
+        views_to_plain_text, EditFallback, DEFAULT_UPLOAD_LIMIT_BYTES, UPLOAD_OVERHEAD_BYTES,
+    };
+    use serenity::all::PremiumTier;
+
+    #[test]
+    fn test_upload_limit_follows_boost_tier() {
+        assert_eq!(
+            upload_limit_for_tier(PremiumTier::Tier0),
+            DEFAULT_UPLOAD_LIMIT_BYTES
+        );
+        assert_eq!(
+            upload_limit_for_tier(PremiumTier::Tier1),
+            DEFAULT_UPLOAD_LIMIT_BYTES
+        );
+        assert_eq!(upload_limit_for_tier(PremiumTier::Tier2), 50 * 1024 * 1024);
+        assert_eq!(upload_limit_for_tier(PremiumTier::Tier3), 100 * 1024 * 1024);
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prepare_upload_compresses_then_splits() -> anyhow::Result<()> {
+        let limit = UPLOAD_OVERHEAD_BYTES + 1000;
+        let small = prepare_upload("a.md", vec![b'x'; 10], limit)?;
+        assert_eq!(small, vec![("a.md".to_string(), vec![b'x'; 10])]);
+
+        // 重複內容壓縮後可放進單一附件
+        let compressible = prepare_upload("a.md", vec![b'x'; 50_000], limit)?;
+        assert_eq!(compressible.len(), 1);
+        assert_eq!(compressible[0].0, "a.md.zip");
+
+        // 無法壓縮的內容切成分段
+        let parts = prepare_upload("a.md", noise(3000), limit)?;
+        assert!(parts.len() > 1);
+        assert_eq!(parts[0].0, "a.md.zip.001");
+        assert!(parts.iter().all(|(_, bytes)| bytes.len() <= 1000));
+
+        assert!(prepare_upload("a.md", noise(20_000), limit).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_for_known_error_codes() {
+        assert_eq!(fallback_for_code(50035), Some(EditFallback::StripEmbeds));
+        assert_eq!(fallback_for_code(200000), Some(EditFallback::AttachFile));
+        assert_eq!(fallback_for_code(200001), Some(EditFallback::AttachFile));
+        assert_eq!(fallback_for_code(40005), Some(EditFallback::AttachFile));
+        assert_eq!(fallback_for_code(10008), None);
+    }
+
+    #[test]
+    fn test_discord_json_error_code_roundtrip() {
+        let err: serenity::all::DiscordJsonError = serde_json::from_str(
+            r#"{"code": 200000, "message": "Message was blocked by automatic moderation"}"#,
+        )
+        .expect("parse");
+        assert_eq!(fallback_for_code(err.code), Some(EditFallback::AttachFile));
+    }
+
+    #[test]
+    fn test_split_content_prefers_newlines_and_respects_limit() {
+        let text = format!("{}\n{}", "a".repeat(15), "b".repeat(15));
+        let chunks = split_content(&text, 20);
+        assert_eq!(chunks, vec!["a".repeat(15), "b".repeat(15)]);
+
+        let long = "中".repeat(45);
+        let chunks = split_content(&long, 20);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 20));
+    }
+
+    #[test]
+    fn test_views_to_plain_text() {
+        let views = vec![
+            ("💭".to_string(), 0, "think".to_string()),
+            ("✅".to_string(), 0, "answer".to_string()),
+        ];
+        assert_eq!(
+            views_to_plain_text(&views),
+            "**💭**\nthink\n\n**✅**\nanswer"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::storage::Storage;
+
+/// HTTP 端點上提供下載的路徑前綴
+pub const ROUTE: &str = "/downloads/";
+// 儲存空間中放置待下載檔案的 prefix，每個檔案位於 `downloads/<token>/<name>`
+const PREFIX: &str = "downloads";
+// 簽署連結用的密鑰，存在 prefix 之外以免被過期清除刪掉
+const SECRET_KEY: &str = "download_secret";
+
+static CONFIG: OnceLock<DownloadsConfig> = OnceLock::new();
+static SECRET: tokio::sync::OnceCell<Vec<u8>> = tokio::sync::OnceCell::const_new();
+
+/// 附件壓縮分割後仍無法上傳時改貼的下載連結；檔案由 `[metrics] port` 的 HTTP 端點提供
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DownloadsConfig {
+    /// 外部可連到該 HTTP 端點的網址 (如 `https://bot.example.com`)；空字串表示停用
+    #[serde(default)]
+    pub public_url: String,
+    /// 連結有效秒數，過期的檔案在下次發布連結時刪除
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for DownloadsConfig {
+    fn default() -> Self {
+        Self {
+            public_url: String::new(),
+            ttl_secs: default_ttl_secs(),
+        }
+    }
+}
+
+fn default_ttl_secs() -> u64 {
+    86400
+}
+
+pub fn install(config: DownloadsConfig) {
+    let _ = CONFIG.set(config);
+}
+
+/// 設定了 `public_url` 時的下載連結設定
+fn config() -> Option<&'static DownloadsConfig> {
+    CONFIG.get().filter(|c| !c.public_url.trim().is_empty())
+}
+
+async fn secret(storage: &dyn Storage) -> anyhow::Result<&'static [u8]> {
+    let secret = SECRET
+        .get_or_try_init(|| async {
+            if let Some(secret) = storage.get(SECRET_KEY).await? {
+                return anyhow::Ok(secret);
+            }
+            let secret = rand::random::<[u8; 32]>().to_vec();
+            storage.put(SECRET_KEY, &secret).await?;
+            Ok(secret)
+        })
+        .await?;
+    Ok(secret)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn sign(secret: &[u8], token: &str, expires: u64) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(secret);
+    hasher.update(format!(":{}:{}", token, expires));
+    format!("{:x}", hasher.finalize())
+}
+
+/// 刪除過期的下載檔案
+async fn sweep(storage: &dyn Storage, ttl: Duration, now: SystemTime) -> anyhow::Result<()> {
+    for object in storage.list(PREFIX).await? {
+        if object.modified + ttl < now {
+            storage.delete(&object.key).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 存下檔案並回傳簽署過的連結與到期時間 (Unix 秒)
+async fn publish_to(
+    storage: &dyn Storage,
+    config: &DownloadsConfig,
+    secret: &[u8],
+    name: &str,
+    bytes: &[u8],
+    now: SystemTime,
+) -> anyhow::Result<(String, u64)> {
+    let ttl = Duration::from_secs(config.ttl_secs);
+    if let Err(e) = sweep(storage, ttl, now).await {
+        warn!("⚠️ Failed to remove expired downloads: {}", e);
+    }
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    // 檔名只用於 Content-Disposition，不能含路徑分隔字元
+    let file_name = name.replace(['/', '\\'], "_");
+    storage
+        .put(&format!("{}/{}/{}", PREFIX, token, file_name), bytes)
+        .await?;
+    let expires = unix_secs(now + ttl);
+    let url = format!(
+        "{}{}{}?expires={}&sig={}",
+        config.public_url.trim_end_matches('/'),
+        ROUTE,
+        token,
+        expires,
+        sign(secret, &token, expires)
+    );
+    Ok((url, expires))
+}
+
+/// 附件放不進 Discord 時改用的下載連結訊息；未設定 `[downloads] public_url` 時回傳 None
+pub async fn publish(name: &str, bytes: &[u8]) -> anyhow::Result<Option<String>> {
+    let Some(config) = config() else {
+        return Ok(None);
+    };
+    let storage = crate::storage::get();
+    let secret = secret(storage.as_ref()).await?;
+    let (url, expires) = publish_to(
+        storage.as_ref(),
+        config,
+        secret,
+        name,
+        bytes,
+        SystemTime::now(),
+    )
+    .await?;
+    info!(
+        "🔗 Published {} ({} bytes) as a download link",
+        name,
+        bytes.len()
+    );
+    // Discord 時間戳記依閱讀者的語系顯示剩餘時間
+    Ok(Some(format!(
+        "📦 {}: <{}> (⏳ <t:{}:R>)",
+        name, url, expires
+    )))
+}
+
+/// 驗證 `/downloads/<token>?expires=..&sig=..` 並讀出檔案；連結無效或過期時回傳 None
+async fn open_from(
+    storage: &dyn Storage,
+    secret: &[u8],
+    target: &str,
+    now: SystemTime,
+) -> Option<(String, Vec<u8>)> {
+    let (path, query) = target.strip_prefix(ROUTE)?.split_once('?')?;
+    if path.is_empty() || !path.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let mut expires = None;
+    let mut sig = None;
+    for pair in query.split('&') {
+        match pair.split_once('=') {
+            Some(("expires", v)) => expires = v.parse::<u64>().ok(),
+            Some(("sig", v)) => sig = Some(v),
+            _ => {}
+        }
+    }
+    let expires = expires?;
+    if sig? != sign(secret, path, expires) || expires < unix_secs(now) {
+        return None;
+    }
+    let object = storage
+        .list(&format!("{}/{}", PREFIX, path))
+        .await
+        .ok()?
+        .into_iter()
+        .next()?;
+    let bytes = storage.get(&object.key).await.ok()??;
+    let name = object
+        .key
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    Some((name, bytes))
+}
+
+/// HTTP 端點收到下載請求時呼叫
+pub async fn open(target: &str) -> Option<(String, Vec<u8>)> {
+    config()?;
+    let storage = crate::storage::get();
+    let secret = match secret(storage.as_ref()).await {
+        Ok(secret) => secret,
+        Err(e) => {
+            warn!("⚠️ Failed to load download secret: {}", e);
+            return None;
+        }
+    };
+    open_from(storage.as_ref(), secret, target, SystemTime::now()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_signed_link_opens_until_it_expires() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let storage = LocalStorage::new(dir.path());
+        let config = DownloadsConfig {
+            public_url: "https://bot.example.com/".to_string(),
+            ttl_secs: 60,
+        };
+        let secret = b"secret";
+        let now = SystemTime::now();
+        let (url, expires) =
+            publish_to(&storage, &config, secret, "big.zip", b"payload", now).await?;
+        assert_eq!(expires, unix_secs(now) + 60);
+        let target = url
+            .strip_prefix("https://bot.example.com")
+            .expect("public url prefix");
+        assert!(target.starts_with(ROUTE));
+
+        assert_eq!(
+            open_from(&storage, secret, target, now).await,
+            Some(("big.zip".to_string(), b"payload".to_vec()))
+        );
+        // 竄改到期時間、換密鑰或過期都無法下載
+        let tampered = target.replace(
+            &format!("expires={}", expires),
+            &format!("expires={}", expires + 3600),
+        );
+        assert_eq!(open_from(&storage, secret, &tampered, now).await, None);
+        assert_eq!(open_from(&storage, b"other", target, now).await, None);
+        let later = now + Duration::from_secs(120);
+        assert_eq!(open_from(&storage, secret, target, later).await, None);
+
+        // 下次發布時清除過期的檔案
+        publish_to(&storage, &config, secret, "next.zip", b"x", later).await?;
+        assert_eq!(storage.list(PREFIX).await?.len(), 1);
+        Ok(())
+    }
+}
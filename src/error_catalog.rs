@@ -0,0 +1,152 @@
+use crate::agent::AgentType;
+use crate::i18n::I18n;
+
+/// 有固定處理方式的常見錯誤，對應語系檔中的 `error_*` 說明
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KnownError {
+    /// 模型供應商拒絕憑證 (401 / API key 無效)
+    ProviderAuth,
+    /// 供應商帳號額度或餘額不足
+    Quota,
+    RateLimit,
+    /// 找不到後端執行檔
+    BinaryMissing,
+    /// 後端要監聽的埠已被其他程式佔用
+    PortInUse,
+    /// 後端已沒有這個頻道的 session
+    SessionNotFound,
+}
+
+pub fn is_binary_not_found(error_text: &str) -> bool {
+    let lower = error_text.to_lowercase();
+    lower.contains("no such file or directory")
+        || lower.contains("enoent")
+        || lower.contains("command not found")
+}
+
+/// 後端的安裝指令
+pub fn install_command(agent_type: &AgentType) -> &'static str {
+    match agent_type {
+        AgentType::Pi => "npm i -g @mariozechner/pi-coding-agent",
+        AgentType::Opencode => "npm install -g @opencode-ai/cli",
+        AgentType::Kilo => "npm i -g @kilocode/cli",
+        AgentType::Copilot => "npm i -g @github/copilot",
+        AgentType::Claude => "npm i -g @zed-industries/claude-code-acp",
+        AgentType::Acp => "# install your ACP agent and set [acp] binary in config.toml",
+        AgentType::OpenAi => {
+            "# start an OpenAI-compatible server and set [openai] base_url in config.toml"
+        }
+    }
+}
+
+impl KnownError {
+    /// 依錯誤訊息判斷類別；較明確的類別先比對 (例如 session 404 不算找不到執行檔)
+    pub fn classify(error_text: &str) -> Option<Self> {
+        let lower = error_text.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+        if has(&["address already in use", "eaddrinuse", "already in use"]) {
+            Some(Self::PortInUse)
+        } else if lower.contains("session")
+            && has(&["404", "not found", "expired", "does not exist"])
+        {
+            Some(Self::SessionNotFound)
+        } else if has(&[
+            "insufficient_quota",
+            "quota",
+            "credit balance",
+            "billing",
+            "402",
+            "payment required",
+        ]) {
+            Some(Self::Quota)
+        } else if has(&["429", "rate limit", "rate_limit", "too many requests"]) {
+            Some(Self::RateLimit)
+        } else if has(&[
+            "401",
+            "unauthorized",
+            "invalid api key",
+            "invalid_api_key",
+            "invalid x-api-key",
+            "authentication",
+            "not authenticated",
+        ]) {
+            Some(Self::ProviderAuth)
+        } else if is_binary_not_found(error_text) {
+            Some(Self::BinaryMissing)
+        } else {
+            None
+        }
+    }
+}
+
+/// 已知錯誤的說明與處理步驟；不在目錄中的錯誤回傳 None，由呼叫端顯示原始訊息
+pub fn explain(i18n: &I18n, error_text: &str, agent_type: &AgentType, port: u16) -> Option<String> {
+    let backend = agent_type.to_string();
+    let message = match KnownError::classify(error_text)? {
+        KnownError::ProviderAuth => match agent_type {
+            AgentType::Copilot => i18n.get("error_provider_auth_copilot"),
+            AgentType::Claude => i18n.get("error_provider_auth_claude"),
+            // /provider login 只支援 kilo 與 opencode 的憑證儲存
+            AgentType::Kilo | AgentType::Opencode => i18n.get("error_provider_auth"),
+            AgentType::Pi | AgentType::Acp | AgentType::OpenAi => {
+                i18n.get_args("error_provider_auth_env", std::slice::from_ref(&backend))
+            }
+        },
+        KnownError::Quota => i18n.get("error_quota"),
+        KnownError::RateLimit => i18n.get("error_rate_limit"),
+        KnownError::BinaryMissing => i18n.get_args(
+            "error_binary_missing",
+            &[backend, install_command(agent_type).to_string()],
+        ),
+        KnownError::PortInUse => i18n.get_args("error_port_in_use", &[backend, port.to_string()]),
+        KnownError::SessionNotFound => i18n.get("error_session_not_found"),
+    };
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_common_backend_errors() {
+        let cases = [
+            (
+                "HTTP 401 Unauthorized: invalid x-api-key",
+                KnownError::ProviderAuth,
+            ),
+            (
+                "You exceeded your current quota (insufficient_quota)",
+                KnownError::Quota,
+            ),
+            ("429 Too Many Requests", KnownError::RateLimit),
+            (
+                "Spawn failed: No such file or directory (os error 2)",
+                KnownError::BinaryMissing,
+            ),
+            ("Port 4096 is already in use", KnownError::PortInUse),
+            ("Session expired (404)", KnownError::SessionNotFound),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(KnownError::classify(text), Some(expected), "{}", text);
+        }
+        assert_eq!(KnownError::classify("model not found"), None);
+        assert_eq!(KnownError::classify("connection refused"), None);
+    }
+
+    #[test]
+    fn test_explain_points_to_the_right_command() {
+        let i18n = I18n::new("en");
+        let auth = explain(&i18n, "401 Unauthorized", &AgentType::Opencode, 0).unwrap();
+        assert!(auth.contains("/provider login"));
+        let copilot = explain(&i18n, "401 Unauthorized", &AgentType::Copilot, 0).unwrap();
+        assert!(copilot.contains("copilot login"));
+        let claude = explain(&i18n, "invalid x-api-key", &AgentType::Claude, 0).unwrap();
+        assert!(claude.contains("claude login"));
+        let port = explain(&i18n, "EADDRINUSE", &AgentType::Kilo, 4096).unwrap();
+        assert!(port.contains("4096"));
+        let session = explain(&i18n, "Session expired (404)", &AgentType::Kilo, 0).unwrap();
+        assert!(session.contains("/clear"));
+        assert!(explain(&i18n, "boom", &AgentType::Pi, 0).is_none());
+    }
+}
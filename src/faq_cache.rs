@@ -0,0 +1,383 @@
+use crate::config::FaqCacheConfig;
+use crate::storage::Storage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, MessageId,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+pub const BUTTON_PREFIX: &str = "faq_regenerate_";
+/// Embed 描述上限 4096，保留截斷提示的空間
+const ANSWER_MAX_CHARS: usize = 4000;
+const EMBED_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 一筆已回答的問題與其 embedding
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaqEntry {
+    pub prompt: String,
+    pub embedding: Vec<f32>,
+    pub answer: String,
+    /// Unix 時間 (秒)
+    pub created_at: i64,
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 {
+        0.0
+    } else {
+        dot / denom
+    }
+}
+
+fn is_fresh(entry: &FaqEntry, config: &FaqCacheConfig, now: i64) -> bool {
+    now - entry.created_at < config.ttl_secs as i64
+}
+
+/// 未過期且相似度達門檻的最接近問題
+fn find_match<'a>(
+    entries: &'a [FaqEntry],
+    embedding: &[f32],
+    config: &FaqCacheConfig,
+    now: i64,
+) -> Option<&'a FaqEntry> {
+    entries
+        .iter()
+        .filter(|e| is_fresh(e, config, now))
+        .map(|e| (e, cosine_similarity(&e.embedding, embedding)))
+        .filter(|(_, score)| *score >= config.similarity)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(e, _)| e)
+}
+
+/// 加入新回答：移除過期與相同問題的舊紀錄，超過上限時丟掉最舊的
+fn insert(entries: &mut Vec<FaqEntry>, entry: FaqEntry, config: &FaqCacheConfig, now: i64) {
+    entries.retain(|e| {
+        is_fresh(e, config, now)
+            && cosine_similarity(&e.embedding, &entry.embedding) < config.similarity
+    });
+    entries.push(entry);
+    let overflow = entries.len().saturating_sub(config.max_entries.max(1));
+    entries.drain(..overflow);
+}
+
+fn cache_key(channel_id: u64) -> String {
+    format!("faq_cache/{}.json", channel_id)
+}
+
+async fn load_from(storage: &dyn Storage, channel_id: u64) -> Vec<FaqEntry> {
+    match storage.get(&cache_key(channel_id)).await {
+        Ok(Some(content)) => serde_json::from_slice(&content).unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+async fn save_to(
+    storage: &dyn Storage,
+    channel_id: u64,
+    entries: &[FaqEntry],
+) -> anyhow::Result<()> {
+    storage
+        .put(
+            &cache_key(channel_id),
+            serde_json::to_string(entries)?.as_bytes(),
+        )
+        .await
+}
+
+/// OpenAI 相容 embeddings 回應：`{"data": [{"embedding": [...]}]}`
+fn parse_embedding(body: &Value) -> Option<Vec<f32>> {
+    body["data"][0]["embedding"]
+        .as_array()?
+        .iter()
+        .map(|v| v.as_f64().map(|f| f as f32))
+        .collect()
+}
+
+/// 語意快取：相似問題近期已回答過時直接提供舊答案，省下後端 token
+pub struct FaqCache {
+    config: FaqCacheConfig,
+    client: reqwest::Client,
+    /// 未命中的問題與其 embedding，回合成功後才存入
+    pending: Mutex<HashMap<u64, (String, Vec<f32>)>>,
+}
+
+impl FaqCache {
+    pub fn new(config: FaqCacheConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.endpoint.trim().is_empty()
+    }
+
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        let mut req = self
+            .client
+            .post(&self.config.endpoint)
+            .timeout(EMBED_TIMEOUT)
+            .json(&json!({ "model": self.config.model, "input": text }));
+        if let Some(key) = self.config.api_key.as_deref().filter(|k| !k.is_empty()) {
+            req = req.bearer_auth(key);
+        }
+        let resp = req.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Embedding request failed: {}", resp.status());
+        }
+        parse_embedding(&resp.json().await?)
+            .ok_or_else(|| anyhow::anyhow!("Embedding response has no vector"))
+    }
+
+    /// 命中時回傳快取答案；未命中時記下 embedding，待 `complete` 存入
+    pub async fn lookup(&self, channel_id: u64, prompt: &str) -> Option<FaqEntry> {
+        let embedding = match self.embed(prompt).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("⚠️ FAQ cache embedding failed: {}", e);
+                return None;
+            }
+        };
+        let entries = load_from(crate::storage::get().as_ref(), channel_id).await;
+        let now = chrono::Utc::now().timestamp();
+        if let Some(hit) = find_match(&entries, &embedding, &self.config, now) {
+            info!("💾 FAQ cache hit in channel {}", channel_id);
+            return Some(hit.clone());
+        }
+        self.pending
+            .lock()
+            .await
+            .insert(channel_id, (prompt.to_string(), embedding));
+        None
+    }
+
+    /// 重新產生時略過查詢，只記下問題供回合結束後覆蓋舊答案
+    pub async fn remember(&self, channel_id: u64, prompt: &str) {
+        match self.embed(prompt).await {
+            Ok(embedding) => {
+                self.pending
+                    .lock()
+                    .await
+                    .insert(channel_id, (prompt.to_string(), embedding));
+            }
+            Err(e) => warn!("⚠️ FAQ cache embedding failed: {}", e),
+        }
+    }
+
+    /// 回合成功後呼叫；只有與記下的問題相同時才存入，避免排隊中的其他回合配錯答案
+    pub async fn complete(&self, channel_id: u64, prompt: &str, answer: &str) {
+        let embedding = {
+            let mut pending = self.pending.lock().await;
+            match pending.get(&channel_id) {
+                Some((p, _)) if p == prompt => pending.remove(&channel_id).map(|(_, e)| e),
+                _ => None,
+            }
+        };
+        let Some(embedding) = embedding else {
+            return;
+        };
+        if answer.trim().is_empty() {
+            return;
+        }
+        let storage = crate::storage::get();
+        let now = chrono::Utc::now().timestamp();
+        let mut entries = load_from(storage.as_ref(), channel_id).await;
+        insert(
+            &mut entries,
+            FaqEntry {
+                prompt: prompt.to_string(),
+                embedding,
+                answer: answer.to_string(),
+                created_at: now,
+            },
+            &self.config,
+            now,
+        );
+        if let Err(e) = save_to(storage.as_ref(), channel_id, &entries).await {
+            warn!("⚠️ Failed to save FAQ cache for {}: {}", channel_id, e);
+        }
+    }
+}
+
+pub fn regenerate_button(label: String, question_id: MessageId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "{}{}",
+        BUTTON_PREFIX, question_id
+    ))
+    .label(label)
+    .style(ButtonStyle::Secondary)])
+}
+
+pub fn parse_button(custom_id: &str) -> Option<u64> {
+    custom_id.strip_prefix(BUTTON_PREFIX)?.parse().ok()
+}
+
+/// 快取命中的回覆：附上原答案與「重新產生」按鈕
+pub fn hit_message(
+    i18n: &crate::i18n::I18n,
+    entry: &FaqEntry,
+    question_id: MessageId,
+) -> CreateMessage {
+    let mut answer: String = entry.answer.chars().take(ANSWER_MAX_CHARS).collect();
+    if answer.len() < entry.answer.len() {
+        answer.push('…');
+    }
+    CreateMessage::new()
+        .content(i18n.get_args("faq_cache_hit", &[entry.created_at.to_string()]))
+        .embed(CreateEmbed::new().description(answer).color(0x95a5a6))
+        .components(vec![regenerate_button(
+            i18n.get("faq_cache_regenerate"),
+            question_id,
+        )])
+}
+
+/// 「重新產生」按鈕：移除按鈕後把原問題交給後端，新答案覆蓋快取
+pub async fn handle_regenerate(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !crate::turn_controls::authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().components(vec![]),
+            ),
+        )
+        .await?;
+    let Some(question_id) = parse_button(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+    let channel_id = interaction.channel_id;
+    let question = channel_id
+        .message(&ctx.http, MessageId::new(question_id))
+        .await?;
+    let input = crate::agent::UserInput {
+        text: question.content.clone(),
+        files: Vec::new(),
+        requester: Some(interaction.user.id.get()),
+        quick: false,
+        on_behalf_of: None,
+        disabled_tools: Vec::new(),
+        continues: None,
+        source_message: None,
+        priority: state.config.priority.is_priority(
+            Some(interaction.user.id.get()),
+            crate::commands::is_admin(interaction.member.as_ref(), interaction.guild_id.is_some()),
+        ),
+    };
+    state
+        .faq_cache
+        .remember(channel_id.get(), &input.text)
+        .await;
+
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, is_new) = crate::Handler::open_session(state, channel_id.get(), agent_type).await?;
+    info!("🔁 Regenerating cached answer on channel {}", channel_id);
+    crate::Handler::start_agent_loop(
+        agent,
+        ctx.http.clone(),
+        channel_id,
+        state.clone(),
+        Some(input),
+        is_new,
+        None,
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+    use tempfile::tempdir;
+
+    fn config() -> FaqCacheConfig {
+        FaqCacheConfig {
+            endpoint: "http://localhost/v1/embeddings".into(),
+            similarity: 0.9,
+            ttl_secs: 100,
+            max_entries: 2,
+            ..Default::default()
+        }
+    }
+
+    fn entry(prompt: &str, embedding: Vec<f32>, created_at: i64) -> FaqEntry {
+        FaqEntry {
+            prompt: prompt.into(),
+            embedding,
+            answer: format!("answer to {}", prompt),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_find_match_respects_similarity_and_ttl() {
+        let cfg = config();
+        let entries = vec![
+            entry("old", vec![1.0, 0.0], 0),
+            entry("near", vec![0.99, 0.1], 50),
+            entry("far", vec![0.0, 1.0], 50),
+        ];
+        let hit = find_match(&entries, &[1.0, 0.0], &cfg, 120);
+        assert_eq!(hit.map(|e| e.prompt.as_str()), Some("near"));
+        assert!(find_match(&entries, &[-1.0, 0.0], &cfg, 120).is_none());
+        assert!(find_match(&entries, &[1.0, 0.0], &cfg, 200).is_none());
+    }
+
+    #[test]
+    fn test_insert_replaces_similar_and_caps_entries() {
+        let cfg = config();
+        let mut entries = vec![
+            entry("a", vec![1.0, 0.0], 10),
+            entry("b", vec![0.0, 1.0], 20),
+        ];
+        insert(&mut entries, entry("a2", vec![0.99, 0.05], 30), &cfg, 30);
+        let prompts: Vec<_> = entries.iter().map(|e| e.prompt.as_str()).collect();
+        assert_eq!(prompts, ["b", "a2"]);
+
+        insert(&mut entries, entry("c", vec![-1.0, 0.0], 40), &cfg, 40);
+        let prompts: Vec<_> = entries.iter().map(|e| e.prompt.as_str()).collect();
+        assert_eq!(prompts, ["a2", "c"]);
+    }
+
+    #[test]
+    fn test_parse_embedding_and_button() {
+        let body = json!({"data": [{"embedding": [0.5, -1.0]}]});
+        assert_eq!(parse_embedding(&body), Some(vec![0.5, -1.0]));
+        assert_eq!(parse_embedding(&json!({"data": []})), None);
+        assert_eq!(parse_button("faq_regenerate_42"), Some(42));
+        assert_eq!(parse_button("tool_output_42"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_round_trips_through_storage() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let storage = LocalStorage::new(dir.path());
+        assert!(load_from(&storage, 7).await.is_empty());
+        let entries = vec![entry("q", vec![1.0], 5)];
+        save_to(&storage, 7, &entries).await?;
+        assert_eq!(load_from(&storage, 7).await, entries);
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::FloodConfig;
+
+/// 冷卻中收到訊息時的處理方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloodCheck {
+    Allowed,
+    /// `notify` 只在本次冷卻的第一則訊息為 true，避免重複提示
+    CoolingDown {
+        remaining: Duration,
+        notify: bool,
+    },
+}
+
+#[derive(Default)]
+struct ChannelActivity {
+    posts: VecDeque<Instant>,
+    cooldown_until: Option<Instant>,
+    notified: bool,
+}
+
+/// 防洗版：機器人在 `window_secs` 內於同一頻道發出超過 `max_messages` 則回應後暫停 `cooldown_secs`
+pub struct FloodGuard {
+    config: FloodConfig,
+    channels: Mutex<HashMap<u64, ChannelActivity>>,
+}
+
+impl FloodGuard {
+    pub fn new(config: FloodConfig) -> Self {
+        Self {
+            config,
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.max_messages > 0 && self.config.cooldown_secs > 0
+    }
+
+    /// 機器人在頻道送出一則回應後呼叫
+    pub async fn record_post(&self, channel_id: u64, now: Instant) {
+        if !self.enabled() {
+            return;
+        }
+        let window = Duration::from_secs(self.config.window_secs);
+        let mut channels = self.channels.lock().await;
+        let activity = channels.entry(channel_id).or_default();
+        activity.posts.push_back(now);
+        while activity
+            .posts
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) > window)
+        {
+            activity.posts.pop_front();
+        }
+        if activity.posts.len() > self.config.max_messages {
+            activity.posts.clear();
+            activity.cooldown_until = Some(now + Duration::from_secs(self.config.cooldown_secs));
+            activity.notified = false;
+        }
+    }
+
+    pub async fn check(&self, channel_id: u64, now: Instant) -> FloodCheck {
+        if !self.enabled() {
+            return FloodCheck::Allowed;
+        }
+        let mut channels = self.channels.lock().await;
+        let Some(activity) = channels.get_mut(&channel_id) else {
+            return FloodCheck::Allowed;
+        };
+        match activity.cooldown_until {
+            Some(until) if until > now => {
+                let notify = !activity.notified;
+                activity.notified = true;
+                FloodCheck::CoolingDown {
+                    remaining: until - now,
+                    notify,
+                }
+            }
+            Some(_) => {
+                activity.cooldown_until = None;
+                FloodCheck::Allowed
+            }
+            None => FloodCheck::Allowed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_guard(max_messages: usize) -> FloodGuard {
+        FloodGuard::new(FloodConfig {
+            max_messages,
+            window_secs: 60,
+            cooldown_secs: 30,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_flood_guard_cools_down_after_burst_and_notes_once() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let guard = new_guard(2);
+
+        guard.record_post(1, at(0)).await;
+        guard.record_post(1, at(10)).await;
+        assert_eq!(guard.check(1, at(11)).await, FloodCheck::Allowed);
+
+        guard.record_post(1, at(20)).await;
+        assert_eq!(
+            guard.check(1, at(25)).await,
+            FloodCheck::CoolingDown {
+                remaining: Duration::from_secs(25),
+                notify: true
+            }
+        );
+        assert_eq!(
+            guard.check(1, at(30)).await,
+            FloodCheck::CoolingDown {
+                remaining: Duration::from_secs(20),
+                notify: false
+            }
+        );
+        // 其他頻道不受影響
+        assert_eq!(guard.check(2, at(30)).await, FloodCheck::Allowed);
+        assert_eq!(guard.check(1, at(50)).await, FloodCheck::Allowed);
+    }
+
+    #[tokio::test]
+    async fn test_flood_guard_ignores_posts_outside_window_and_when_disabled() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let guard = new_guard(2);
+        guard.record_post(1, at(0)).await;
+        guard.record_post(1, at(50)).await;
+        guard.record_post(1, at(100)).await;
+        assert_eq!(guard.check(1, at(101)).await, FloodCheck::Allowed);
+
+        let disabled = new_guard(0);
+        for secs in 0..10 {
+            disabled.record_post(1, at(secs)).await;
+        }
+        assert_eq!(disabled.check(1, at(10)).await, FloodCheck::Allowed);
+    }
+}
@@ -1,13 +1,16 @@
 use crate::commands::agent::ChannelConfig;
+use crate::composer::Section;
 use crate::i18n::I18n;
 use crate::ExecStatus;
 use serenity::all::MessageType;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ModalRoute {
     CronSetup,
     ConfigAssistant,
+    ProviderLogin,
     Ignore,
 }
 
@@ -17,6 +20,16 @@ pub enum ComponentRoute {
     Agent,
     CronDelete,
     ModelSelect,
+    ModelReplay,
+    Welcome,
+    ToolOutput,
+    FaqRegenerate,
+    TurnRetry,
+    TurnContinue,
+    TurnAbort,
+    TurnRegenerate,
+    QueueJournal,
+    ToolApproval,
     Ignore,
 }
 
@@ -33,6 +46,36 @@ pub fn resolve_channel_assistant_name(
         .unwrap_or_else(|| default_name.to_string())
 }
 
+pub fn resolve_channel_max_turn(
+    channel_cfg: &ChannelConfig,
+    channel_id: &str,
+    default_secs: u64,
+) -> Option<Duration> {
+    let secs = channel_cfg
+        .channels
+        .get(channel_id)
+        .and_then(|e| e.max_turn_secs)
+        .unwrap_or(default_secs);
+    if secs == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(secs))
+    }
+}
+
+/// 頻道保存完整工具輸出的回合數；頻道未設定時沿用全域設定
+pub fn resolve_channel_tool_output_retention(
+    channel_cfg: &ChannelConfig,
+    channel_id: &str,
+    default_turns: usize,
+) -> usize {
+    channel_cfg
+        .channels
+        .get(channel_id)
+        .and_then(|e| e.tool_output_retention)
+        .unwrap_or(default_turns)
+}
+
 pub fn is_supported_message_kind(kind: MessageType) -> bool {
     kind == MessageType::Regular || kind == MessageType::InlineReply
 }
@@ -54,8 +97,11 @@ pub fn should_process_message(
 
 pub fn route_modal(custom_id: &str) -> ModalRoute {
     match custom_id {
-        "cron_setup" => ModalRoute::CronSetup,
         "config_assistant_modal" => ModalRoute::ConfigAssistant,
+        id if id.starts_with(crate::commands::cron::MODAL_ID) => ModalRoute::CronSetup,
+        id if id.starts_with(crate::commands::provider::PROVIDER_MODAL_PREFIX) => {
+            ModalRoute::ProviderLogin
+        }
         _ => ModalRoute::Ignore,
     }
 }
@@ -69,11 +115,51 @@ pub fn route_component(custom_id: &str) -> ComponentRoute {
         ComponentRoute::CronDelete
     } else if custom_id.starts_with("model_select") {
         ComponentRoute::ModelSelect
+    } else if custom_id == "model_replay" {
+        ComponentRoute::ModelReplay
+    } else if custom_id.starts_with("welcome_") {
+        ComponentRoute::Welcome
+    } else if custom_id.starts_with(crate::tool_outputs::BUTTON_PREFIX) {
+        ComponentRoute::ToolOutput
+    } else if custom_id.starts_with(crate::faq_cache::BUTTON_PREFIX) {
+        ComponentRoute::FaqRegenerate
+    } else if custom_id.starts_with(crate::retry::BUTTON_PREFIX) {
+        ComponentRoute::TurnRetry
+    } else if custom_id.starts_with(crate::continuation::BUTTON_PREFIX) {
+        ComponentRoute::TurnContinue
+    } else if custom_id.starts_with(crate::turn_controls::ABORT_PREFIX) {
+        ComponentRoute::TurnAbort
+    } else if custom_id.starts_with(crate::turn_controls::REGENERATE_PREFIX) {
+        ComponentRoute::TurnRegenerate
+    } else if custom_id.starts_with(crate::queue_journal::BUTTON_PREFIX) {
+        ComponentRoute::QueueJournal
+    } else if custom_id.starts_with(crate::tool_approval::BUTTON_PREFIX) {
+        ComponentRoute::ToolApproval
     } else {
         ComponentRoute::Ignore
     }
 }
 
+/// 已知的錯誤改以說明與處理步驟顯示，原始訊息以小字附在最後方便回報
+pub fn explain_status(
+    i18n: &I18n,
+    status: &ExecStatus,
+    backend: &crate::agent::AgentType,
+    port: u16,
+) -> ExecStatus {
+    match status {
+        ExecStatus::Error(raw) => match crate::error_catalog::explain(i18n, raw, backend, port) {
+            Some(explained) => ExecStatus::Error(format!(
+                "{}\n-# {}",
+                explained,
+                i18n.get_args("error_details", std::slice::from_ref(raw))
+            )),
+            None => status.clone(),
+        },
+        _ => status.clone(),
+    }
+}
+
 pub fn build_render_view(
     i18n: &I18n,
     status: &ExecStatus,
@@ -86,6 +172,16 @@ pub fn build_render_view(
             0xff0000,
             format!("{}\n\n{} {}", desc, i18n.get("runtime_error_prefix"), e),
         ),
+        ExecStatus::TimedOut => (
+            i18n.get("turn_timed_out"),
+            0xff0000,
+            format!("{}\n\n{}", desc, i18n.get("turn_timed_out_desc")),
+        ),
+        ExecStatus::Interrupted => (
+            i18n.get("turn_interrupted"),
+            0x99aab5,
+            format!("{}\n\n{}", desc, i18n.get("turn_interrupted_desc")),
+        ),
         ExecStatus::Success => (
             i18n.get_args("agent_response", &[assistant_name.to_string()]),
             0x00ff00,
@@ -107,6 +203,56 @@ pub fn build_render_view(
     }
 }
 
+/// 圖片附件處理方式的頁尾說明
+pub fn image_mode_label(i18n: &I18n, mode: crate::agent::ImageInputMode) -> String {
+    use crate::agent::ImageInputMode;
+    i18n.get(match mode {
+        ImageInputMode::Vision => "image_mode_vision",
+        ImageInputMode::OcrFallback => "image_mode_ocr",
+        ImageInputMode::Unavailable => "image_mode_unavailable",
+    })
+}
+
+/// 回答 Embed 的頁尾：圖片處理方式與回合中追加的指示數
+pub fn embed_footer(
+    i18n: &I18n,
+    image_mode: Option<crate::agent::ImageInputMode>,
+    steered: usize,
+) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(mode) = image_mode {
+        parts.push(image_mode_label(i18n, mode));
+    }
+    if steered > 0 {
+        parts.push(i18n.get_args("steering_added", &[steered.to_string()]));
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// 將各分區轉為 Embed 視圖 (標題、顏色、內文)；回答 Embed 永遠放在最後並帶執行狀態
+pub fn build_section_embeds(
+    i18n: &I18n,
+    status: &ExecStatus,
+    sections: &[(Section, String)],
+    assistant_name: &str,
+) -> Vec<(String, u32, String)> {
+    let mut views: Vec<(String, u32, String)> = sections
+        .iter()
+        .filter_map(|(section, body)| match section {
+            Section::Thinking => Some((i18n.get("embed_thinking"), 0x95a5a6, body.clone())),
+            Section::Tools => Some((i18n.get("embed_tools"), 0x3498db, body.clone())),
+            Section::Answer => None,
+        })
+        .collect();
+    let answer = sections
+        .iter()
+        .find(|(section, _)| *section == Section::Answer)
+        .map(|(_, body)| body.as_str())
+        .unwrap_or("");
+    views.push(build_render_view(i18n, status, answer, assistant_name));
+    views
+}
+
 pub fn get_systemd_service_path() -> anyhow::Result<PathBuf> {
     Ok(dirs::config_dir()
         .or_else(dirs::home_dir)
@@ -156,6 +302,7 @@ mod tests {
     fn test_resolve_channel_assistant_name_prefers_channel_value() {
         let mut cfg = ChannelConfig {
             channels: HashMap::new(),
+            guilds: HashMap::new(),
         };
         cfg.channels.insert(
             "1".to_string(),
@@ -164,9 +311,24 @@ mod tests {
                 authorized_at: Utc::now().to_rfc3339(),
                 mention_only: true,
                 session_id: None,
+                backend_instance: None,
                 model_provider: None,
                 model_id: None,
                 assistant_name: Some("MyAgent".to_string()),
+                max_turn_secs: None,
+                mirror: Default::default(),
+                private: None,
+                quiet_hours: None,
+                tool_output_retention: None,
+                faq_cache: false,
+                auto_reply_language: false,
+                mentions: Default::default(),
+                accessible: false,
+                status_reactions: false,
+                disabled_tools: Vec::new(),
+                workdir: None,
+                thinking_level: None,
+                text_commands: false,
             },
         );
 
@@ -176,6 +338,38 @@ mod tests {
         assert_eq!(fallback, "Agent");
     }
 
+    #[test]
+    fn test_resolve_channel_max_turn_prefers_channel_and_zero_disables() {
+        let mut cfg = ChannelConfig::default();
+        let mut entry = ChannelEntry::new(crate::agent::AgentType::Pi);
+        entry.max_turn_secs = Some(60);
+        cfg.channels.insert("1".to_string(), entry);
+        let mut off = ChannelEntry::new(crate::agent::AgentType::Pi);
+        off.max_turn_secs = Some(0);
+        cfg.channels.insert("2".to_string(), off);
+
+        assert_eq!(
+            resolve_channel_max_turn(&cfg, "1", 900),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(resolve_channel_max_turn(&cfg, "2", 900), None);
+        assert_eq!(
+            resolve_channel_max_turn(&cfg, "3", 900),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(resolve_channel_max_turn(&cfg, "3", 0), None);
+    }
+
+    #[test]
+    fn test_resolve_channel_tool_output_retention_falls_back_to_default() {
+        let mut cfg = ChannelConfig::default();
+        let mut entry = ChannelEntry::new(crate::agent::AgentType::Pi);
+        entry.tool_output_retention = Some(0);
+        cfg.channels.insert("1".to_string(), entry);
+        assert_eq!(resolve_channel_tool_output_retention(&cfg, "1", 20), 0);
+        assert_eq!(resolve_channel_tool_output_retention(&cfg, "2", 20), 20);
+    }
+
     #[test]
     fn test_should_process_message_rules() {
         assert!(!should_process_message(
@@ -207,10 +401,15 @@ mod tests {
     #[test]
     fn test_modal_and_component_routing() {
         assert_eq!(route_modal("cron_setup"), ModalRoute::CronSetup);
+        assert_eq!(route_modal("cron_setup:42:1"), ModalRoute::CronSetup);
         assert_eq!(
             route_modal("config_assistant_modal"),
             ModalRoute::ConfigAssistant
         );
+        assert_eq!(
+            route_modal("provider_login_modal:anthropic"),
+            ModalRoute::ProviderLogin
+        );
         assert_eq!(route_modal("other"), ModalRoute::Ignore);
 
         assert_eq!(
@@ -226,6 +425,34 @@ mod tests {
             route_component("model_select_0"),
             ComponentRoute::ModelSelect
         );
+        assert_eq!(route_component("model_replay"), ComponentRoute::ModelReplay);
+        assert_eq!(route_component("welcome_config"), ComponentRoute::Welcome);
+        assert_eq!(
+            route_component("tool_output_123"),
+            ComponentRoute::ToolOutput
+        );
+        assert_eq!(
+            route_component("faq_regenerate_123"),
+            ComponentRoute::FaqRegenerate
+        );
+        assert_eq!(route_component("turn_retry_123"), ComponentRoute::TurnRetry);
+        assert_eq!(
+            route_component("turn_continue_123"),
+            ComponentRoute::TurnContinue
+        );
+        assert_eq!(route_component("turn_abort_123"), ComponentRoute::TurnAbort);
+        assert_eq!(
+            route_component("turn_regenerate_123"),
+            ComponentRoute::TurnRegenerate
+        );
+        assert_eq!(
+            route_component("queue_journal_replay"),
+            ComponentRoute::QueueJournal
+        );
+        assert_eq!(
+            route_component("tool_approval_approve_abc"),
+            ComponentRoute::ToolApproval
+        );
         assert_eq!(route_component("x"), ComponentRoute::Ignore);
     }
 
@@ -241,6 +468,69 @@ mod tests {
             build_render_view(&i18n, &ExecStatus::Error("boom".to_string()), "x", "AgentX");
         assert_eq!(err_color, 0xff0000);
         assert!(err_desc.contains("boom"));
+
+        let (timeout_title, _, timeout_desc) =
+            build_render_view(&i18n, &ExecStatus::TimedOut, "partial", "AgentX");
+        assert_eq!(timeout_title, i18n.get("turn_timed_out"));
+        assert!(timeout_desc.starts_with("partial"));
+
+        let (interrupted_title, _, interrupted_desc) =
+            build_render_view(&i18n, &ExecStatus::Interrupted, "partial", "AgentX");
+        assert_eq!(interrupted_title, i18n.get("turn_interrupted"));
+        assert!(interrupted_desc.ends_with(&i18n.get("turn_interrupted_desc")));
+    }
+
+    #[test]
+    fn test_explain_status_replaces_known_errors_only() {
+        let i18n = I18n::new("en");
+        let backend = &crate::agent::AgentType::Kilo;
+        let explained = explain_status(
+            &i18n,
+            &ExecStatus::Error("Session expired (404)".to_string()),
+            backend,
+            4096,
+        );
+        let ExecStatus::Error(text) = explained else {
+            panic!("expected error status");
+        };
+        assert!(text.contains("/clear"));
+        assert!(text.ends_with("Details: Session expired (404)"));
+
+        let unknown = ExecStatus::Error("boom".to_string());
+        assert_eq!(explain_status(&i18n, &unknown, backend, 4096), unknown);
+        assert_eq!(
+            explain_status(&i18n, &ExecStatus::Success, backend, 4096),
+            ExecStatus::Success
+        );
+    }
+
+    #[test]
+    fn test_build_section_embeds_puts_answer_last_with_status() {
+        let i18n = I18n::new("en");
+        let sections = vec![
+            (Section::Thinking, "> hmm".to_string()),
+            (Section::Tools, "🛠️ ls".to_string()),
+        ];
+        let views = build_section_embeds(&i18n, &ExecStatus::Running, &sections, "AgentX");
+        assert_eq!(views.len(), 3);
+        assert_eq!(views[0].0, i18n.get("embed_thinking"));
+        assert_eq!(views[1].0, i18n.get("embed_tools"));
+        assert!(views[2].0.contains("AgentX"));
+        assert_eq!(views[2].2, i18n.get("wait"));
+    }
+
+    #[test]
+    fn test_embed_footer_combines_image_mode_and_steering() {
+        let i18n = I18n::new("en");
+        assert_eq!(embed_footer(&i18n, None, 0), None);
+        assert_eq!(
+            embed_footer(&i18n, None, 2),
+            Some("↪️ Steering added (2)".to_string())
+        );
+        let both =
+            embed_footer(&i18n, Some(crate::agent::ImageInputMode::Vision), 1).expect("footer");
+        assert!(both.starts_with("🖼️"));
+        assert!(both.ends_with("↪️ Steering added (1)"));
     }
 
     #[test]
@@ -0,0 +1,548 @@
+use serenity::all::{ChannelId, Http};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::agent::manager::BackendManager;
+use crate::agent::AgentType;
+use crate::auth::{AuthManager, Registry};
+use crate::commands::agent::ChannelConfig;
+use crate::config::Config;
+use crate::migrate;
+
+/// `fsck` 發現的不一致
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// 狀態檔無法解析，Bot 會把它當成空白檔而默默遺失設定
+    Corrupt { path: PathBuf, error: String },
+    /// Discord 上已看不到的頻道 (已刪除或 Bot 失去權限)，仍留有授權、設定或 session 檔
+    UnreachableChannel {
+        channel: String,
+        reason: String,
+        authorized: bool,
+        configured: bool,
+        session_file: Option<PathBuf>,
+    },
+    /// channel_config 記錄的 session ID 在後端已不存在，或該後端根本不使用 session ID
+    DanglingSession {
+        channel: String,
+        backend: AgentType,
+        session_id: String,
+    },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::Corrupt { path, error } => {
+                write!(f, "{} cannot be parsed: {}", path.display(), error)
+            }
+            Issue::UnreachableChannel {
+                channel,
+                reason,
+                authorized,
+                configured,
+                session_file,
+            } => {
+                let mut leftovers = Vec::new();
+                if *authorized {
+                    leftovers.push("auth.json");
+                }
+                if *configured {
+                    leftovers.push("channel_config.json");
+                }
+                if session_file.is_some() {
+                    leftovers.push("pi session file");
+                }
+                write!(
+                    f,
+                    "channel {} is no longer visible ({}); leftovers: {}",
+                    channel,
+                    reason,
+                    leftovers.join(", ")
+                )
+            }
+            Issue::DanglingSession {
+                channel,
+                backend,
+                session_id,
+            } => write!(
+                f,
+                "channel {} points at {} session {} which does not exist",
+                channel, backend, session_id
+            ),
+        }
+    }
+}
+
+impl Issue {
+    fn repair_hint(&self) -> &'static str {
+        match self {
+            Issue::Corrupt { .. } => "move the file aside so the bot starts with a clean one",
+            Issue::UnreachableChannel { .. } => {
+                "revoke the channel and delete its settings and session file"
+            }
+            Issue::DanglingSession { .. } => {
+                "clear the session ID so a new session is created on the next message"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Options {
+    /// 逐項詢問是否修復
+    pub repair: bool,
+    /// 修復時不詢問，全部套用
+    pub assume_yes: bool,
+    /// 不連線 Discord 與後端，只做本機檢查
+    pub offline: bool,
+}
+
+/// `sessions/pi/discord-rs-<channel>.jsonl`
+pub fn pi_session_files(dir: &Path) -> HashMap<String, PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let channel = name.strip_prefix("discord-rs-")?.strip_suffix(".jsonl")?;
+            channel.parse::<u64>().ok()?;
+            Some((channel.to_string(), entry.path()))
+        })
+        .collect()
+}
+
+/// 依線上檢查結果交叉比對各狀態檔
+///
+/// `unreachable` 為 Discord 上看不到的頻道與原因，`missing_sessions` 為後端回報不存在的 session
+pub fn diagnose(
+    registry: &Registry,
+    config: &ChannelConfig,
+    pi_sessions: &HashMap<String, PathBuf>,
+    unreachable: &HashMap<String, String>,
+    missing_sessions: &HashSet<String>,
+) -> Vec<Issue> {
+    let channels: BTreeSet<&String> = registry
+        .channels
+        .keys()
+        .chain(config.channels.keys())
+        .chain(pi_sessions.keys())
+        .collect();
+
+    let mut issues = Vec::new();
+    for channel in channels {
+        if let Some(reason) = unreachable.get(channel) {
+            issues.push(Issue::UnreachableChannel {
+                channel: channel.clone(),
+                reason: reason.clone(),
+                authorized: registry.channels.contains_key(channel),
+                configured: config.channels.contains_key(channel),
+                session_file: pi_sessions.get(channel).cloned(),
+            });
+            continue;
+        }
+        let Some(entry) = config.channels.get(channel) else {
+            continue;
+        };
+        let Some(session_id) = &entry.session_id else {
+            continue;
+        };
+        // pi 以頻道 ID 命名 session 檔，留下的 session ID 是切換後端前的殘留
+        if entry.agent_type == AgentType::Pi || missing_sessions.contains(channel) {
+            issues.push(Issue::DanglingSession {
+                channel: channel.clone(),
+                backend: entry.agent_type.clone(),
+                session_id: session_id.clone(),
+            });
+        }
+    }
+    issues
+}
+
+/// 套用單一修復；channel_config 的變更由呼叫端最後統一寫回
+pub async fn repair(
+    issue: &Issue,
+    auth: &AuthManager,
+    config: &mut ChannelConfig,
+) -> anyhow::Result<()> {
+    match issue {
+        Issue::Corrupt { path, .. } => {
+            let mut aside = path.clone().into_os_string();
+            aside.push(format!(
+                ".corrupt-{}",
+                chrono::Local::now().format("%Y%m%d%H%M%S")
+            ));
+            tokio::fs::rename(path, &aside).await?;
+        }
+        Issue::UnreachableChannel {
+            channel,
+            session_file,
+            ..
+        } => {
+            auth.remove_channel(channel)?;
+            config.channels.remove(channel);
+            if let Some(path) = session_file {
+                match tokio::fs::remove_file(path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Issue::DanglingSession { channel, .. } => {
+            if let Some(entry) = config.channels.get_mut(channel) {
+                entry.session_id = None;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 向 Discord 查詢每個頻道；只有 403/404 視為看不到，其他錯誤 (限流、網路) 略過不判定
+async fn probe_discord(token: &str, channels: &BTreeSet<String>) -> HashMap<String, String> {
+    let http = Http::new(token);
+    let mut unreachable = HashMap::new();
+    for channel in channels {
+        let Some(id) = channel.parse::<u64>().ok().filter(|id| *id != 0) else {
+            unreachable.insert(channel.clone(), "invalid channel ID".to_string());
+            continue;
+        };
+        if let Err(e) = http.get_channel(ChannelId::new(id)).await {
+            let status = match &e {
+                serenity::Error::Http(http_err) => http_err.status_code().map(|s| s.as_u16()),
+                _ => None,
+            };
+            match status {
+                Some(404) => {
+                    unreachable.insert(channel.clone(), "deleted".to_string());
+                }
+                Some(403) => {
+                    unreachable.insert(channel.clone(), "missing access".to_string());
+                }
+                _ => eprintln!("⚠️ Could not check channel {}: {}", channel, e),
+            }
+        }
+    }
+    unreachable
+}
+
+/// 啟動 kilo/opencode 後端，確認 channel_config 記錄的 session 仍存在
+async fn probe_backends(config: &Arc<Config>, channels: &ChannelConfig) -> HashSet<String> {
+    let manager = BackendManager::new(config.clone());
+    let client = reqwest::Client::new();
+    let mut missing = HashSet::new();
+    let mut entries: Vec<_> = channels.channels.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    for (channel, entry) in entries {
+        let (Some(session_id), Ok(channel_id)) = (&entry.session_id, channel.parse::<u64>()) else {
+            continue;
+        };
+        if !matches!(entry.agent_type, AgentType::Kilo | AgentType::Opencode) {
+            continue;
+        }
+        // session 只存在於它所在的實例上
+        if let Some(instance) = entry.backend_instance {
+            manager
+                .pin_channel(&entry.agent_type, channel_id, instance)
+                .await;
+        }
+        let port = match manager.ensure_backend(&entry.agent_type, channel_id).await {
+            Ok(port) => port,
+            Err(e) => {
+                eprintln!(
+                    "⚠️ Could not start {} to check channel {}: {}",
+                    entry.agent_type, channel, e
+                );
+                continue;
+            }
+        };
+        let mut req = client.get(format!("http://127.0.0.1:{}/session/{}", port, session_id));
+        if let Some(password) = config
+            .opencode
+            .password
+            .as_deref()
+            .filter(|p| !p.is_empty())
+        {
+            req = req.header("Authorization", format!("Bearer {}", password));
+        }
+        match req.send().await {
+            Ok(resp) if resp.status() == reqwest::StatusCode::NOT_FOUND => {
+                missing.insert(channel.clone());
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("⚠️ Could not check session of channel {}: {}", channel, e),
+        }
+    }
+    manager.shutdown().await;
+    missing
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// `agent-discord fsck` 進入點；回傳發現的問題數
+pub async fn run(opts: Options) -> anyhow::Result<usize> {
+    let base_dir = migrate::get_base_dir();
+    let auth = AuthManager::new();
+    let mut issues = Vec::new();
+
+    let registry = match auth.registry() {
+        Ok(registry) => registry,
+        Err(e) => {
+            issues.push(Issue::Corrupt {
+                path: base_dir.join("auth.json"),
+                error: e.to_string(),
+            });
+            Registry::default()
+        }
+    };
+    // 設定檔損毀時不寫回，避免修復其他項目時覆蓋掉
+    let (mut channel_config, config_ok) = match ChannelConfig::load().await {
+        Ok(config) => (config, true),
+        Err(e) => {
+            issues.push(Issue::Corrupt {
+                path: migrate::get_channel_config_path(),
+                error: e.to_string(),
+            });
+            (ChannelConfig::default(), false)
+        }
+    };
+    let pi_sessions = pi_session_files(&migrate::get_sessions_dir("pi"));
+
+    let (unreachable, missing_sessions) = if opts.offline {
+        (HashMap::new(), HashSet::new())
+    } else {
+        let config = Arc::new(Config::load().await?);
+        crate::agent::install_enabled_backends(config.enabled_backends.as_deref());
+        let channels: BTreeSet<String> = registry
+            .channels
+            .keys()
+            .chain(channel_config.channels.keys())
+            .chain(pi_sessions.keys())
+            .cloned()
+            .collect();
+        println!("🔍 Checking {} channel(s) on Discord...", channels.len());
+        let unreachable = probe_discord(&config.discord_token, &channels).await;
+        println!("🔍 Checking backend sessions...");
+        let missing = probe_backends(&config, &channel_config).await;
+        (unreachable, missing)
+    };
+
+    issues.extend(diagnose(
+        &registry,
+        &channel_config,
+        &pi_sessions,
+        &unreachable,
+        &missing_sessions,
+    ));
+
+    if issues.is_empty() {
+        println!("✅ No inconsistencies found.");
+        return Ok(0);
+    }
+    println!("Found {} issue(s):", issues.len());
+    for (i, issue) in issues.iter().enumerate() {
+        println!("  {}. {}", i + 1, issue);
+    }
+    if !opts.repair {
+        println!("Run again with --repair to fix them (stop the bot first).");
+        return Ok(issues.len());
+    }
+
+    let mut config_changed = false;
+    for issue in &issues {
+        let question = format!("{} → {}?", issue, issue.repair_hint());
+        if !opts.assume_yes && !confirm(&question) {
+            continue;
+        }
+        match repair(issue, &auth, &mut channel_config).await {
+            Ok(()) => {
+                config_changed |= !matches!(issue, Issue::Corrupt { .. });
+                println!("🔧 Repaired: {}", issue);
+            }
+            Err(e) => eprintln!("❌ Failed to repair ({}): {}", issue, e),
+        }
+    }
+    if config_changed && config_ok {
+        channel_config.save().await?;
+    }
+    Ok(issues.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agent::ChannelEntry;
+    use tempfile::tempdir;
+
+    fn entry(agent_type: AgentType, session_id: Option<&str>) -> ChannelEntry {
+        ChannelEntry {
+            session_id: session_id.map(str::to_string),
+            ..ChannelEntry::new(agent_type)
+        }
+    }
+
+    #[test]
+    fn test_pi_session_files_only_matches_channel_files() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("discord-rs-123.jsonl"), "")?;
+        std::fs::write(dir.path().join("discord-rs-abc.jsonl"), "")?;
+        std::fs::write(dir.path().join("other.jsonl"), "")?;
+        let files = pi_session_files(dir.path());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files["123"], dir.path().join("discord-rs-123.jsonl"));
+        assert!(pi_session_files(&dir.path().join("missing")).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnose_reports_unreachable_and_dangling() {
+        let mut registry = Registry::default();
+        registry.channels.insert(
+            "1".into(),
+            crate::auth::AuthEntry {
+                authorized_at: chrono::Utc::now(),
+                mention_only: true,
+            },
+        );
+        let mut config = ChannelConfig::default();
+        config
+            .channels
+            .insert("2".into(), entry(AgentType::Kilo, Some("ses_live")));
+        config
+            .channels
+            .insert("3".into(), entry(AgentType::Opencode, Some("ses_gone")));
+        config
+            .channels
+            .insert("4".into(), entry(AgentType::Pi, Some("ses_old")));
+        let pi_sessions = HashMap::from([("5".to_string(), PathBuf::from("discord-rs-5.jsonl"))]);
+        let unreachable = HashMap::from([
+            ("1".to_string(), "deleted".to_string()),
+            ("5".to_string(), "missing access".to_string()),
+        ]);
+        let missing = HashSet::from(["3".to_string()]);
+
+        let issues = diagnose(&registry, &config, &pi_sessions, &unreachable, &missing);
+        assert_eq!(
+            issues,
+            vec![
+                Issue::UnreachableChannel {
+                    channel: "1".into(),
+                    reason: "deleted".into(),
+                    authorized: true,
+                    configured: false,
+                    session_file: None,
+                },
+                Issue::DanglingSession {
+                    channel: "3".into(),
+                    backend: AgentType::Opencode,
+                    session_id: "ses_gone".into(),
+                },
+                Issue::DanglingSession {
+                    channel: "4".into(),
+                    backend: AgentType::Pi,
+                    session_id: "ses_old".into(),
+                },
+                Issue::UnreachableChannel {
+                    channel: "5".into(),
+                    reason: "missing access".into(),
+                    authorized: false,
+                    configured: false,
+                    session_file: Some(PathBuf::from("discord-rs-5.jsonl")),
+                },
+            ]
+        );
+
+        // 離線模式只剩本機可判斷的項目
+        let offline = diagnose(
+            &registry,
+            &config,
+            &pi_sessions,
+            &HashMap::new(),
+            &HashSet::new(),
+        );
+        assert_eq!(offline.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_repair_cleans_channel_state() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let auth = AuthManager::with_paths(
+            dir.path().join("auth.json"),
+            dir.path().join("pending_tokens.json"),
+        );
+        let token = auth.create_token("channel", "1")?;
+        auth.redeem_token(&token)?;
+        let session_file = dir.path().join("discord-rs-1.jsonl");
+        std::fs::write(&session_file, "{}")?;
+        let mut config = ChannelConfig::default();
+        config
+            .channels
+            .insert("1".into(), entry(AgentType::Pi, None));
+        config
+            .channels
+            .insert("2".into(), entry(AgentType::Kilo, Some("ses_gone")));
+
+        repair(
+            &Issue::UnreachableChannel {
+                channel: "1".into(),
+                reason: "deleted".into(),
+                authorized: true,
+                configured: true,
+                session_file: Some(session_file.clone()),
+            },
+            &auth,
+            &mut config,
+        )
+        .await?;
+        assert!(!auth.registry()?.channels.contains_key("1"));
+        assert!(!config.channels.contains_key("1"));
+        assert!(!session_file.exists());
+
+        repair(
+            &Issue::DanglingSession {
+                channel: "2".into(),
+                backend: AgentType::Kilo,
+                session_id: "ses_gone".into(),
+            },
+            &auth,
+            &mut config,
+        )
+        .await?;
+        assert_eq!(config.channels["2"].session_id, None);
+        assert_eq!(config.channels["2"].agent_type, AgentType::Kilo);
+
+        let corrupt = dir.path().join("channel_config.json");
+        std::fs::write(&corrupt, "{ broken")?;
+        repair(
+            &Issue::Corrupt {
+                path: corrupt.clone(),
+                error: "EOF".into(),
+            },
+            &auth,
+            &mut config,
+        )
+        .await?;
+        assert!(!corrupt.exists());
+        let moved = std::fs::read_dir(dir.path())?
+            .filter_map(|e| e.ok())
+            .any(|e| {
+                e.file_name()
+                    .to_string_lossy()
+                    .starts_with("channel_config.json.corrupt-")
+            });
+        assert!(moved);
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// 每輪前後執行的外部腳本；腳本從 stdin 讀取 JSON
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct HooksConfig {
+    /// 收到提示 JSON，可改寫 (stdout 輸出 `{"text": ...}`) 或拒絕 (`{"reject": ...}` 或非 0 結束碼)
+    #[serde(default)]
+    pub pre_turn: Option<String>,
+    /// 收到結果 JSON，輸出被忽略；不會延誤回合完成
+    #[serde(default)]
+    pub post_turn: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// pre_turn 無法執行 (啟動失敗、逾時) 時是否照常放行；預設拒絕，避免政策檢查無聲失效
+    #[serde(default)]
+    pub fail_open: bool,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_turn: None,
+            post_turn: None,
+            timeout_secs: default_timeout_secs(),
+            fail_open: false,
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+/// 傳給 pre_turn 的內容
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PreTurnPayload<'a> {
+    pub channel_id: u64,
+    pub requester: Option<u64>,
+    pub backend: &'a str,
+    pub text: &'a str,
+    pub files: Vec<&'a str>,
+}
+
+/// 傳給 post_turn 的內容
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PostTurnPayload {
+    pub channel_id: u64,
+    pub requester: Option<u64>,
+    pub backend: String,
+    pub model: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub answer: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreTurnDecision {
+    /// 照原樣或以改寫後的文字繼續
+    Continue(Option<String>),
+    Reject(String),
+    /// 腳本無法執行且未設定 `fail_open`，本輪被擋下
+    Unavailable,
+}
+
+#[derive(Deserialize, Default)]
+struct PreTurnReply {
+    text: Option<String>,
+    reject: Option<String>,
+}
+
+struct HookOutput {
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+async fn run_script(path: &str, input: &[u8], timeout: Duration) -> anyhow::Result<HookOutput> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // 腳本不讀 stdin 就結束時寫入會失敗，不影響結果
+        let _ = stdin.write_all(input).await;
+    }
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out after {}s", timeout.as_secs()))??;
+    Ok(HookOutput {
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+fn parse_pre_turn(output: HookOutput) -> PreTurnDecision {
+    if !output.success {
+        let reason = if output.stderr.is_empty() {
+            output.stdout
+        } else {
+            output.stderr
+        };
+        return PreTurnDecision::Reject(reason);
+    }
+    if output.stdout.is_empty() {
+        return PreTurnDecision::Continue(None);
+    }
+    // 非 JSON 的輸出視為改寫後的純文字提示
+    let reply = match serde_json::from_str::<PreTurnReply>(&output.stdout) {
+        Ok(reply) => reply,
+        Err(_) => return PreTurnDecision::Continue(Some(output.stdout)),
+    };
+    match reply.reject {
+        Some(reason) => PreTurnDecision::Reject(reason),
+        None => PreTurnDecision::Continue(reply.text),
+    }
+}
+
+/// 執行 pre_turn；腳本無法啟動或逾時時記錄警告，依 `fail_open` 放行或擋下
+pub async fn run_pre_turn(config: &HooksConfig, payload: &PreTurnPayload<'_>) -> PreTurnDecision {
+    let Some(path) = config.pre_turn.as_deref() else {
+        return PreTurnDecision::Continue(None);
+    };
+    let on_failure = if config.fail_open {
+        PreTurnDecision::Continue(None)
+    } else {
+        PreTurnDecision::Unavailable
+    };
+    let input = match serde_json::to_vec(payload) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("⚠️ Failed to encode pre_turn payload: {}", e);
+            return on_failure;
+        }
+    };
+    match run_script(path, &input, Duration::from_secs(config.timeout_secs)).await {
+        Ok(output) => {
+            let decision = parse_pre_turn(output);
+            if let PreTurnDecision::Reject(reason) = &decision {
+                info!(
+                    "🪝 pre_turn rejected prompt on channel {}: {}",
+                    payload.channel_id, reason
+                );
+            }
+            decision
+        }
+        Err(e) => {
+            warn!(
+                "⚠️ pre_turn hook {} failed on channel {}: {}",
+                path, payload.channel_id, e
+            );
+            on_failure
+        }
+    }
+}
+
+/// 執行 post_turn；失敗只記錄警告
+pub async fn run_post_turn(config: &HooksConfig, payload: &PostTurnPayload) {
+    let Some(path) = config.post_turn.as_deref() else {
+        return;
+    };
+    let input = match serde_json::to_vec(payload) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("⚠️ Failed to encode post_turn payload: {}", e);
+            return;
+        }
+    };
+    match run_script(path, &input, Duration::from_secs(config.timeout_secs)).await {
+        Ok(output) if !output.success => warn!(
+            "⚠️ post_turn hook {} exited with failure on channel {}: {}",
+            path, payload.channel_id, output.stderr
+        ),
+        Ok(_) => {}
+        Err(e) => warn!(
+            "⚠️ post_turn hook {} failed on channel {}: {}",
+            path, payload.channel_id, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(success: bool, stdout: &str, stderr: &str) -> HookOutput {
+        HookOutput {
+            success,
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_pre_turn_decisions() {
+        assert_eq!(
+            parse_pre_turn(output(true, "", "")),
+            PreTurnDecision::Continue(None)
+        );
+        assert_eq!(
+            parse_pre_turn(output(true, r#"{"text":"rewritten"}"#, "")),
+            PreTurnDecision::Continue(Some("rewritten".to_string()))
+        );
+        assert_eq!(
+            parse_pre_turn(output(true, "plain rewrite", "")),
+            PreTurnDecision::Continue(Some("plain rewrite".to_string()))
+        );
+        assert_eq!(
+            parse_pre_turn(output(true, r#"{"reject":"no secrets"}"#, "")),
+            PreTurnDecision::Reject("no secrets".to_string())
+        );
+        assert_eq!(
+            parse_pre_turn(output(false, "", "blocked")),
+            PreTurnDecision::Reject("blocked".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pre_turn_script_reads_stdin_and_fails_closed() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().expect("tempdir");
+        let script = dir.path().join("hook.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nif grep -q secret; then echo '{\"reject\":\"leak\"}'; fi\n",
+        )
+        .expect("write script");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        let slow = dir.path().join("slow.sh");
+        std::fs::write(&slow, "#!/bin/sh\nsleep 5\n").expect("write script");
+        std::fs::set_permissions(&slow, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let mut config = HooksConfig {
+            pre_turn: Some(script.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let payload = |text| PreTurnPayload {
+            channel_id: 1,
+            requester: Some(2),
+            backend: "kilo",
+            text,
+            files: Vec::new(),
+        };
+        assert_eq!(
+            run_pre_turn(&config, &payload("my secret")).await,
+            PreTurnDecision::Reject("leak".to_string())
+        );
+        assert_eq!(
+            run_pre_turn(&config, &payload("hello")).await,
+            PreTurnDecision::Continue(None)
+        );
+
+        // 逾時與無法啟動的腳本預設擋下，`fail_open` 時放行
+        config.pre_turn = Some(slow.to_string_lossy().to_string());
+        config.timeout_secs = 1;
+        assert_eq!(
+            run_pre_turn(&config, &payload("hello")).await,
+            PreTurnDecision::Unavailable
+        );
+        config.pre_turn = Some(dir.path().join("missing.sh").to_string_lossy().to_string());
+        assert_eq!(
+            run_pre_turn(&config, &payload("hello")).await,
+            PreTurnDecision::Unavailable
+        );
+        config.pre_turn = Some(slow.to_string_lossy().to_string());
+        config.fail_open = true;
+        assert_eq!(
+            run_pre_turn(&config, &payload("hello")).await,
+            PreTurnDecision::Continue(None)
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use chrono::{DateTime, NaiveTime, TimeZone};
 use rust_embed::RustEmbed;
 use serde_json::Value;
+use std::time::Duration;
+use tracing::warn;
 
 #[derive(RustEmbed)]
 #[folder = "locales/"]
@@ -10,22 +13,88 @@ pub struct I18n {
     pub current_lang: String,
 }
 
+/// 所有語系的基準，其他語系缺少的字串逐一以英文補上
+const BASE_LANG: &str = "en";
+
+fn load_texts(lang: &str) -> Option<Value> {
+    let file = Asset::get(&format!("{}.json", lang))?;
+    let content = std::str::from_utf8(file.data.as_ref()).expect("UTF-8");
+    Some(serde_json::from_str(content).expect("JSON"))
+}
+
+/// 以 `overlay` 的字串覆蓋 `base`，`overlay` 缺少的鍵保留 `base` 的內容
+fn merge_texts(mut base: Value, overlay: Value) -> Value {
+    if let (Some(base_map), Value::Object(overlay_map)) = (base.as_object_mut(), overlay) {
+        base_map.extend(overlay_map);
+    }
+    base
+}
+
+/// `overlay` 相對於 `base` 缺少的鍵，依字母排序
+fn missing_from(base: &Value, overlay: &Value) -> Vec<String> {
+    let mut missing: Vec<String> = base
+        .as_object()
+        .map(|map| {
+            map.keys()
+                .filter(|key| overlay.get(key.as_str()).is_none())
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    missing.sort();
+    missing
+}
+
 impl I18n {
     pub fn new(lang: &str) -> Self {
-        let path = format!("{}.json", lang);
-        let content = if let Some(file) = Asset::get(&path) {
-            std::str::from_utf8(file.data.as_ref())
-                .expect("UTF-8")
-                .to_string()
-        } else {
-            r#"{"processing": "...", "wait": "..."}"#.to_string()
+        let base = load_texts(BASE_LANG)
+            .unwrap_or_else(|| serde_json::json!({"processing": "...", "wait": "..."}));
+        let texts = match load_texts(lang) {
+            Some(overlay) if lang != BASE_LANG => merge_texts(base, overlay),
+            _ => base,
         };
         I18n {
-            texts: serde_json::from_str(&content).expect("JSON"),
+            texts,
             current_lang: lang.to_string(),
         }
     }
 
+    /// 啟動時檢查各語系相對於英文缺少的字串；缺少的部分會顯示英文
+    pub fn warn_missing_keys() {
+        let Some(base) = load_texts(BASE_LANG) else {
+            return;
+        };
+        for file in Asset::iter() {
+            let Some(lang) = file.strip_suffix(".json") else {
+                continue;
+            };
+            if lang == BASE_LANG {
+                continue;
+            }
+            let Some(overlay) = load_texts(lang) else {
+                continue;
+            };
+            let missing = missing_from(&base, &overlay);
+            if !missing.is_empty() {
+                warn!(
+                    "🌐 Locale {} is missing {} key(s), English is shown instead: {}",
+                    lang,
+                    missing.len(),
+                    missing.join(", ")
+                );
+            }
+        }
+    }
+
+    /// 將 Discord 的語系代碼 (如 `zh-TW`、`en-US`) 對應到支援的介面語言
+    pub fn lang_for_discord_locale(locale: &str) -> Option<&'static str> {
+        match locale {
+            "zh-TW" => Some("zh-TW"),
+            l if l == "en" || l.starts_with("en-") => Some("en"),
+            _ => None,
+        }
+    }
+
     pub fn get(&self, key: &str) -> String {
         self.texts
             .get(key)
@@ -42,6 +111,79 @@ impl I18n {
         }
         s
     }
+
+    /// 語系檔中的格式設定 (`fmt_*`)；缺少時使用英文慣例
+    fn pattern(&self, key: &str, default: &'static str) -> String {
+        self.texts
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or(default)
+            .to_string()
+    }
+
+    /// 整數加上千分位 (如 `1,234,567`)
+    pub fn format_number(&self, n: u64) -> String {
+        let sep = self.pattern("fmt_thousands_sep", ",");
+        let digits = n.to_string();
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(&sep);
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    /// 小數最多保留 `max_fraction` 位並去掉尾端的 0，整數部分加千分位
+    pub fn format_decimal(&self, value: f64, max_fraction: usize) -> String {
+        let fixed = format!("{:.*}", max_fraction, value.abs());
+        let (int_part, frac_part) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let frac_part = frac_part.trim_end_matches('0');
+        let mut out = if value < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            "-".to_string()
+        } else {
+            String::new()
+        };
+        out.push_str(&self.format_number(int_part.parse().unwrap_or(0)));
+        if !frac_part.is_empty() {
+            out.push_str(&self.pattern("fmt_decimal_sep", "."));
+            out.push_str(frac_part);
+        }
+        out
+    }
+
+    /// 時間長度只顯示最大的兩個單位，較小單位為 0 時省略 (如 `1h 5m`、`15m`)
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        let (key, default, args) = match (secs / 3600, secs % 3600 / 60, secs % 60) {
+            (0, 0, s) => ("fmt_duration_s", "{0}s", [s, 0]),
+            (0, m, 0) => ("fmt_duration_m", "{0}m", [m, 0]),
+            (0, m, s) => ("fmt_duration_ms", "{0}m {1}s", [m, s]),
+            (h, 0, _) => ("fmt_duration_h", "{0}h", [h, 0]),
+            (h, m, _) => ("fmt_duration_hm", "{0}h {1}m", [h, m]),
+        };
+        let mut s = self.pattern(key, default);
+        for (i, arg) in args.iter().enumerate() {
+            s = s.replace(&format!("{{{}}}", i), &arg.to_string());
+        }
+        s
+    }
+
+    /// 日期時間，依語系採 12 或 24 小時制
+    pub fn format_datetime<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        at.format(&self.pattern("fmt_datetime", "%b %-d, %Y %-I:%M %p"))
+            .to_string()
+    }
+
+    /// 一天中的時間，依語系採 12 或 24 小時制
+    pub fn format_time(&self, time: NaiveTime) -> String {
+        time.format(&self.pattern("fmt_time", "%-I:%M %p"))
+            .to_string()
+    }
 }
 
 #[cfg(test)]
@@ -64,6 +206,85 @@ mod tests {
         assert_eq!(result, "Value: A, B");
     }
 
+    #[test]
+    fn test_lang_for_discord_locale() {
+        assert_eq!(I18n::lang_for_discord_locale("zh-TW"), Some("zh-TW"));
+        assert_eq!(I18n::lang_for_discord_locale("en-US"), Some("en"));
+        assert_eq!(I18n::lang_for_discord_locale("en-GB"), Some("en"));
+        assert_eq!(I18n::lang_for_discord_locale("zh-CN"), None);
+        assert_eq!(I18n::lang_for_discord_locale("ja"), None);
+    }
+
+    #[test]
+    fn test_format_numbers_per_locale() {
+        let en = I18n::new("en");
+        assert_eq!(en.format_number(0), "0");
+        assert_eq!(en.format_number(999), "999");
+        assert_eq!(en.format_number(1_234_567), "1,234,567");
+        assert_eq!(en.format_decimal(2.5, 2), "2.5");
+        assert_eq!(en.format_decimal(10.0, 2), "10");
+        assert_eq!(en.format_decimal(12345.678, 2), "12,345.68");
+        assert_eq!(en.format_decimal(-0.001, 2), "0");
+
+        let mut custom = I18n::new("en");
+        custom.texts["fmt_thousands_sep"] = Value::String(".".into());
+        custom.texts["fmt_decimal_sep"] = Value::String(",".into());
+        assert_eq!(custom.format_decimal(1234.5, 2), "1.234,5");
+    }
+
+    #[test]
+    fn test_format_duration_and_time_per_locale() {
+        let en = I18n::new("en");
+        let zh = I18n::new("zh-TW");
+        assert_eq!(en.format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(en.format_duration(Duration::from_secs(150)), "2m 30s");
+        assert_eq!(en.format_duration(Duration::from_secs(3900)), "1h 5m");
+        assert_eq!(en.format_duration(Duration::from_secs(900)), "15m");
+        assert_eq!(en.format_duration(Duration::from_secs(7200)), "2h");
+        assert_eq!(zh.format_duration(Duration::from_secs(150)), "2 分 30 秒");
+
+        let time = NaiveTime::from_hms_opt(22, 5, 0).expect("time");
+        assert_eq!(en.format_time(time), "10:05 PM");
+        assert_eq!(zh.format_time(time), "22:05");
+
+        let at = chrono::Utc
+            .with_ymd_and_hms(2026, 3, 9, 14, 30, 0)
+            .single()
+            .expect("datetime");
+        assert_eq!(en.format_datetime(&at), "Mar 9, 2026 2:30 PM");
+        assert_eq!(zh.format_datetime(&at), "2026/03/09 14:30");
+    }
+
+    #[test]
+    fn test_missing_keys_fall_back_to_english_per_key() {
+        let base = serde_json::json!({"a": "A", "b": "B {0}", "c": "C"});
+        let overlay = serde_json::json!({"a": "甲", "extra": "only here"});
+        assert_eq!(missing_from(&base, &overlay), vec!["b", "c"]);
+        let merged = merge_texts(base, overlay);
+        assert_eq!(merged["a"], "甲");
+        assert_eq!(merged["b"], "B {0}");
+        assert_eq!(merged["extra"], "only here");
+
+        // 不存在的語系整份使用英文
+        let unknown = I18n::new("xx");
+        assert_eq!(unknown.get("processing"), I18n::new("en").get("processing"));
+    }
+
+    #[test]
+    fn test_shipped_locales_are_complete() {
+        let base = load_texts(BASE_LANG).expect("en locale");
+        for file in Asset::iter() {
+            let lang = file.trim_end_matches(".json");
+            let overlay = load_texts(lang).expect("locale");
+            assert_eq!(
+                missing_from(&base, &overlay),
+                Vec::<String>::new(),
+                "{}",
+                lang
+            );
+        }
+    }
+
     #[test]
     fn test_i18n_fallback_to_key() {
         let i18n = I18n::new("en");
@@ -0,0 +1,217 @@
+/// 太短的訊息 (例如 "ok"、"👍") 判斷不可靠，直接略過
+const MIN_LETTERS: usize = 8;
+
+/// 拉丁字母語言以常見虛詞比對，每個語言取出現頻率最高的一小組
+const STOPWORDS: [(&str, &[&str]); 7] = [
+    (
+        "English",
+        &[
+            "the", "and", "is", "are", "to", "of", "in", "it", "you", "what", "how", "this",
+            "that", "with", "for", "can", "do", "does", "why", "my",
+        ],
+    ),
+    (
+        "Spanish",
+        &[
+            "el", "la", "los", "las", "de", "que", "y", "es", "en", "por", "para", "con", "una",
+            "cómo", "qué", "pero", "está", "mi", "se", "del",
+        ],
+    ),
+    (
+        "French",
+        &[
+            "le", "la", "les", "de", "et", "est", "un", "une", "des", "que", "pour", "dans",
+            "avec", "je", "vous", "pas", "comment", "ce", "du", "qui",
+        ],
+    ),
+    (
+        "German",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ich", "ein", "eine", "zu", "mit", "wie",
+            "was", "auf", "für", "den", "sie", "es", "wir", "warum",
+        ],
+    ),
+    (
+        "Portuguese",
+        &[
+            "o", "a", "os", "de", "que", "e", "é", "em", "um", "uma", "para", "com", "não", "como",
+            "por", "do", "da", "meu", "isso", "você",
+        ],
+    ),
+    (
+        "Italian",
+        &[
+            "il", "la", "di", "che", "e", "è", "un", "una", "per", "non", "con", "come", "del",
+            "della", "sono", "questo", "perché", "mi", "gli", "ho",
+        ],
+    ),
+    (
+        "Dutch",
+        &[
+            "de", "het", "een", "en", "is", "van", "niet", "ik", "je", "dat", "met", "voor", "hoe",
+            "wat", "zijn", "op", "waarom", "mijn", "ook", "maar",
+        ],
+    ),
+];
+
+/// 判斷提示的語言，回傳給模型看的英文語言名稱；無法可靠判斷時為 None
+///
+/// 非拉丁文字依 Unicode 區段判斷，拉丁文字則比對常見虛詞，
+/// 程式碼區塊與網址不列入計算
+pub fn detect(text: &str) -> Option<&'static str> {
+    let prose = strip_code_and_urls(text);
+
+    let mut han = 0;
+    let mut kana = 0;
+    let mut hangul = 0;
+    let mut cyrillic = 0;
+    let mut arabic = 0;
+    let mut thai = 0;
+    let mut hebrew = 0;
+    let mut greek = 0;
+    let mut devanagari = 0;
+    let mut latin = 0;
+    for c in prose.chars() {
+        match c as u32 {
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => han += 1,
+            0x3040..=0x30FF => kana += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => hangul += 1,
+            0x0400..=0x04FF => cyrillic += 1,
+            0x0600..=0x06FF => arabic += 1,
+            0x0E00..=0x0E7F => thai += 1,
+            0x0590..=0x05FF => hebrew += 1,
+            0x0370..=0x03FF => greek += 1,
+            0x0900..=0x097F => devanagari += 1,
+            _ if c.is_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+
+    // CJK 每個字資訊量較高，門檻放寬
+    let cjk = han + kana + hangul;
+    if cjk * 4 >= MIN_LETTERS && cjk * 3 >= latin {
+        return Some(if kana > 0 {
+            "Japanese"
+        } else if hangul >= han {
+            "Korean"
+        } else {
+            "Chinese"
+        });
+    }
+
+    let scripts = [
+        (cyrillic, "Russian"),
+        (arabic, "Arabic"),
+        (thai, "Thai"),
+        (hebrew, "Hebrew"),
+        (greek, "Greek"),
+        (devanagari, "Hindi"),
+    ];
+    if let Some((count, name)) = scripts.iter().max_by_key(|(count, _)| *count) {
+        if *count >= MIN_LETTERS && *count >= latin {
+            return Some(name);
+        }
+    }
+
+    if latin < MIN_LETTERS {
+        return None;
+    }
+    detect_latin(&prose)
+}
+
+fn detect_latin(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    let mut scores: Vec<(usize, &'static str)> = STOPWORDS
+        .iter()
+        .map(|(name, list)| {
+            let hits = words.iter().filter(|w| list.contains(&w.as_str())).count();
+            (hits, *name)
+        })
+        .collect();
+    scores.sort_by_key(|(hits, _)| std::cmp::Reverse(*hits));
+    let (best, name) = scores[0];
+    let runner_up = scores[1].0;
+    // 至少兩個虛詞且明顯領先才採用，避免混雜語句誤判
+    if best >= 2 && best > runner_up {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn strip_code_and_urls(text: &str) -> String {
+    let mut prose = String::new();
+    for (i, part) in text.split("```").enumerate() {
+        if i % 2 == 0 {
+            prose.push_str(part);
+            prose.push(' ');
+        }
+    }
+    prose
+        .split_whitespace()
+        .filter(|w| !w.contains("://") && !w.starts_with('`'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 附加在提示前的回覆語言指示
+pub fn reply_instruction(language: &str) -> String {
+    format!(
+        "Reply in {}, the language the user wrote in, unless they explicitly ask for another language.",
+        language
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_non_latin_scripts() {
+        assert_eq!(detect("請問這個錯誤訊息是什麼意思？"), Some("Chinese"));
+        assert_eq!(detect("このエラーはどういう意味ですか？"), Some("Japanese"));
+        assert_eq!(detect("이 오류 메시지는 무슨 뜻인가요?"), Some("Korean"));
+        assert_eq!(detect("Что означает эта ошибка?"), Some("Russian"));
+        // 中文句子夾雜英文術語仍判為中文
+        assert_eq!(
+            detect("幫我看看這個 Rust borrow checker 的錯誤"),
+            Some("Chinese")
+        );
+    }
+
+    #[test]
+    fn test_detects_latin_languages_by_stopwords() {
+        assert_eq!(
+            detect("How do I fix the build error in this project?"),
+            Some("English")
+        );
+        assert_eq!(
+            detect("¿Cómo puedo arreglar el error de compilación en el proyecto?"),
+            Some("Spanish")
+        );
+        assert_eq!(
+            detect("Comment est-ce que je peux corriger les erreurs dans le projet ?"),
+            Some("French")
+        );
+        assert_eq!(
+            detect("Warum ist der Build kaputt und wie kann ich das beheben?"),
+            Some("German")
+        );
+    }
+
+    #[test]
+    fn test_skips_short_or_ambiguous_text() {
+        assert_eq!(detect("ok"), None);
+        assert_eq!(detect("👍👍"), None);
+        assert_eq!(detect("cargo build --release"), None);
+        // 程式碼區塊不列入判斷
+        assert_eq!(
+            detect("```\nfn main() { println!(\"the and is are\"); }\n```\nfix it"),
+            None
+        );
+    }
+}
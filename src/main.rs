@@ -1,13 +1,16 @@
-use agent::{AiAgent, UserInput};
+use agent::{AgentEvent, AiAgent, UserInput};
+use analytics::{status_fields, AnalyticsSink, TurnRecord};
 use clap::{Parser, Subcommand};
 use rust_embed::RustEmbed;
 use serenity::all::{
-    Context, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
-    CreateMessage, EditMessage, EventHandler, GatewayIntents, Interaction, Message, Ready,
+    Context, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, EditMessage, EventHandler, GatewayIntents,
+    Interaction, Message, MessageFlags, Ready,
 };
 use serenity::async_trait;
 use serenity::Client;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::task::JoinHandle;
@@ -16,32 +19,92 @@ use tracing::{debug, error, info, warn, Level};
 mod cron;
 mod i18n;
 
+mod accessibility;
 mod agent;
+mod analytics;
 mod auth;
+mod batching;
+mod bundle;
+mod chaos;
+mod circuit;
+mod citations;
 mod commands;
 mod composer;
 mod config;
+mod continuation;
+mod delivery;
+mod downloads;
+mod error_catalog;
+mod faq_cache;
+mod flood;
 mod flow;
+mod fsck;
+mod hooks;
+mod lang_detect;
+mod memory;
+mod mentions;
+mod meta;
+mod metrics;
 mod migrate;
+mod mirror;
+mod model_watch;
+mod outbox;
+mod pipe;
+mod prefs;
+mod prometheus;
+mod queue_journal;
+mod quiet;
+mod rate_limit;
+mod recipe;
+mod render_scheduler;
+mod reply_context;
+mod retention;
+mod retry;
+mod service_gen;
+mod services;
 mod session;
+mod shutdown;
+mod skills;
+mod status_reactions;
+mod storage;
+mod text_commands;
+mod threads;
+mod token_failover;
+mod tool_approval;
+mod tool_outputs;
+mod transcript;
+mod turn_controls;
+mod turn_limit;
+mod typing;
 mod uploads;
+mod usage_caps;
+mod voice;
+mod welcome;
 mod writer_logic;
 
 use auth::AuthManager;
 use commands::agent::{handle_button, ChannelConfig};
-use composer::EmbedComposer;
+use composer::{EmbedComposer, Section};
 use config::Config;
-use cron::CronManager;
+use delivery::{apply_edit_fallback, discord_error_code, fallback_for_code, send_dm_chunks};
 use flow::{
-    build_render_view, build_systemd_service_content, detect_timezone, get_systemd_service_path,
-    resolve_channel_assistant_name, route_component, route_modal, should_process_message,
+    build_section_embeds, build_systemd_service_content, detect_timezone, embed_footer,
+    get_systemd_service_path, resolve_channel_assistant_name, resolve_channel_max_turn,
+    resolve_channel_tool_output_retention, route_component, route_modal, should_process_message,
     ComponentRoute, ModalRoute,
 };
 use i18n::I18n;
-use session::SessionManager;
+use outbox::{Outbox, PendingDelivery};
 use uploads::UploadManager;
 use writer_logic::apply_agent_event;
 
+// Discord 單則訊息所有 Embed 合計 6000 字，扣除標題與狀態附註後的內文預算
+const MULTI_EMBED_BUDGET: usize = 5400;
+// 開啟 dm_long_replies 時，超過此字數的回答改以私訊送出
+const DM_LONG_REPLY_CHARS: usize = 2000;
+// 串流中的回應每隔這段時間重新渲染一次
+const RENDER_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1500);
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -60,6 +123,40 @@ enum Commands {
     Auth {
         token: String,
     },
+    /// 交叉檢查 auth.json、channel_config.json、session 檔與後端 session
+    Fsck {
+        /// 逐項詢問並修復發現的問題
+        #[arg(long)]
+        repair: bool,
+        /// 搭配 --repair 時不詢問，全部修復
+        #[arg(long)]
+        yes: bool,
+        /// 不連線 Discord 與後端，只做本機檢查
+        #[arg(long)]
+        offline: bool,
+    },
+    /// 將設定、授權、session、對話紀錄與提示匯出成可攜式套件
+    ExportBundle {
+        /// 輸出檔案 (預設為目前目錄下依時間命名的 .zip)
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// 將 export-bundle 產生的套件還原到目前版本的資料目錄
+    ImportBundle {
+        path: std::path::PathBuf,
+        /// 覆蓋資料目錄中已存在的檔案
+        #[arg(long)]
+        force: bool,
+    },
+    /// 執行舊版資料目錄的遷移 (啟動時也會自動執行)
+    Migrate {
+        /// 只列出會複製或改寫的檔案與設定鍵，不做任何變更
+        #[arg(long)]
+        dry_run: bool,
+        /// 遷移前先把現有的資料目錄複製成帶時間戳記的備份
+        #[arg(long)]
+        backup: bool,
+    },
     Version,
 }
 
@@ -67,6 +164,17 @@ enum Commands {
 enum DaemonAction {
     Enable,
     Disable,
+    /// 產生 systemd 以外的啟動檔 (Dockerfile/compose、podman、runit、launchd)
+    Generate {
+        #[arg(long, value_enum)]
+        target: service_gen::Target,
+        /// 輸出目錄 (預設為目前目錄下的 agent-discord-<target>)
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+        /// 覆蓋已存在的檔案
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[derive(RustEmbed)]
@@ -74,21 +182,120 @@ enum DaemonAction {
 struct DefaultPrompts;
 
 type ActiveRenderMap = HashMap<u64, (serenity::model::id::MessageId, Vec<JoinHandle<()>>)>;
-type PendingInputMap = HashMap<u64, UserInput>;
+type PendingInputMap = HashMap<u64, batching::PendingQueue>;
 type QueuedLoopRequest = (u64, UserInput);
+type LastTurnMap = HashMap<u64, (serenity::model::id::MessageId, UserInput)>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<Config>,
-    pub session_manager: Arc<SessionManager>,
-    pub auth: Arc<AuthManager>,
     pub i18n: Arc<RwLock<I18n>>,
-    pub backend_manager: Arc<agent::manager::BackendManager>,
-    pub cron_manager: Arc<CronManager>,
     pub active_renders: Arc<Mutex<ActiveRenderMap>>,
     pub pending_inputs: Arc<Mutex<PendingInputMap>>,
     pub queued_loop_tx: mpsc::UnboundedSender<QueuedLoopRequest>,
     pub upload_manager: Arc<UploadManager>,
+    /// 每個頻道最近一次提問與其回應訊息，用於切換模型後重新回答
+    pub last_turns: Arc<Mutex<LastTurnMap>>,
+    pub outbox: Arc<Outbox>,
+    /// 頻道所屬伺服器的偏好語言 (已對應到支援的介面語言)
+    pub channel_locales: Arc<Mutex<HashMap<u64, String>>>,
+    /// 頻道所屬的伺服器，用於套用伺服器層級的用量上限
+    pub channel_guilds: Arc<Mutex<HashMap<u64, u64>>>,
+    pub analytics: Arc<AnalyticsSink>,
+    /// 各頻道與使用者的使用次數，供 /stats 與每日摘要
+    pub metrics: Arc<metrics::Metrics>,
+    pub flood: Arc<flood::FloodGuard>,
+    pub rate_limiter: Arc<rate_limit::RateLimiter>,
+    pub turn_limiter: Arc<turn_limit::TurnLimiter>,
+    /// 所有頻道共用的訊息編輯名額，最終結果優先
+    pub render_scheduler: Arc<render_scheduler::RenderScheduler>,
+    /// 已提示過私人 session 的 (頻道, 使用者)，避免重複提示
+    pub private_notices: Arc<Mutex<std::collections::HashSet<(u64, u64)>>>,
+    pub quiet_queue: Arc<quiet::QuietQueue>,
+    pub circuit: Arc<circuit::CircuitBreaker>,
+    pub faq_cache: Arc<faq_cache::FaqCache>,
+    /// 每個頻道最近一次出錯或被截斷回合的訊息與已輸出的回答，供「重試」與「繼續」接續
+    pub partial_answers: Arc<Mutex<HashMap<u64, (serenity::model::id::MessageId, String)>>>,
+    /// 各頻道最近完成的回答與待補的轉送紀錄，供 /pipe 串接到其他頻道
+    pub pipes: Arc<pipe::Pipes>,
+    /// bot 啟動時間，供 /status 顯示運行時間
+    pub started_at: std::time::Instant,
+    /// 排隊中與執行中的提示紀錄，程序重啟後可確認重送
+    pub queue_journal: Arc<queue_journal::QueueJournal>,
+    /// /voice join 的語音頻道與 TTS 朗讀
+    pub voice: Arc<voice::Voice>,
+    /// 授權、session、後端、用量與排程等可替換的服務
+    pub services: Arc<services::Services>,
+    /// 等待管理員以按鈕核准的工具呼叫
+    pub tool_approval: Arc<tool_approval::ToolApproval>,
+    /// SIGTERM/SIGINT 後的停止流程
+    pub shutdown: Arc<shutdown::Shutdown>,
+}
+
+impl AppState {
+    pub fn auth(&self) -> &Arc<dyn services::AuthService> {
+        &self.services.auth
+    }
+
+    pub fn sessions(&self) -> &Arc<dyn services::SessionService> {
+        &self.services.sessions
+    }
+
+    pub fn backends(&self) -> &Arc<dyn services::BackendService> {
+        &self.services.backends
+    }
+
+    pub fn usage(&self) -> &Arc<dyn services::UsageService> {
+        &self.services.usage
+    }
+
+    pub fn scheduler(&self) -> &Arc<dyn services::SchedulerService> {
+        &self.services.scheduler
+    }
+
+    /// 頻道所屬伺服器的偏好語言；未開啟 guild_locale 或尚未得知時為 None
+    pub async fn channel_locale(&self, channel_id: u64) -> Option<String> {
+        if !self.config.guild_locale {
+            return None;
+        }
+        self.channel_locales.lock().await.get(&channel_id).cloned()
+    }
+
+    /// 頻道使用的介面語言 (含數字與日期格式)；與全域語言相同時共用同一份
+    pub async fn channel_i18n(&self, channel_id: u64) -> Arc<RwLock<I18n>> {
+        match self.channel_locale(channel_id).await {
+            Some(lang) if lang != self.i18n.read().await.current_lang => {
+                Arc::new(RwLock::new(I18n::new(&lang)))
+            }
+            _ => Arc::clone(&self.i18n),
+        }
+    }
+
+    /// 頻道是否有正在執行的回合
+    pub async fn is_processing(&self, channel_id: u64) -> bool {
+        self.active_renders.lock().await.contains_key(&channel_id)
+    }
+
+    /// 頻道排隊等待下一輪的訊息數
+    pub async fn pending_count(&self, channel_id: u64) -> usize {
+        self.pending_inputs
+            .lock()
+            .await
+            .get(&channel_id)
+            .map_or(0, |q| q.len())
+    }
+
+    /// 把頻道佇列目前的內容寫入佇列紀錄；每次變更佇列後呼叫
+    pub async fn sync_queue_journal(&self, channel_id: u64) {
+        let queued = self
+            .pending_inputs
+            .lock()
+            .await
+            .get(&channel_id)
+            .map(|q| q.inputs())
+            .unwrap_or_default();
+        self.queue_journal.record_queue(channel_id, queued).await;
+    }
 }
 
 fn load_all_prompts() -> String {
@@ -120,12 +327,42 @@ fn load_all_prompts() -> String {
         .join("\n\n")
 }
 
+/// 本輪各區塊的 Embed 視圖 (標題、顏色、內文)，標題附上批次/修訂等後綴
+fn build_turn_views(
+    i18n: &I18n,
+    status: &ExecStatus,
+    sections: &[(Section, String)],
+    assistant_name: &str,
+    title_suffix: &str,
+    backend: &str,
+    port: u16,
+) -> Vec<(String, u32, String)> {
+    let status = match backend.parse() {
+        Ok(backend) => flow::explain_status(i18n, status, &backend, port),
+        Err(_) => status.clone(),
+    };
+    build_section_embeds(i18n, &status, sections, assistant_name)
+        .into_iter()
+        .map(|(title, color, body)| {
+            if title_suffix.is_empty() {
+                (title, color, body)
+            } else {
+                (format!("{} {}", title, title_suffix), color, body)
+            }
+        })
+        .collect()
+}
+
 fn should_auto_recover_request_error(agent_type: &str, error_text: &str) -> bool {
+    let lower = error_text.to_lowercase();
+    // ACP 進程已結束：丟掉快取的 session，重送時會重新啟動進程
+    if lower.contains("acp exited") {
+        return true;
+    }
     if agent_type != "kilo" && agent_type != "opencode" {
         return false;
     }
 
-    let lower = error_text.to_lowercase();
     lower.contains("error sending request for url")
         || lower.contains("connection refused")
         || lower.contains("tcp connect error")
@@ -133,6 +370,21 @@ fn should_auto_recover_request_error(agent_type: &str, error_text: &str) -> bool
         || lower.contains("broken pipe")
 }
 
+/// 訊息作者是否為伺服器管理員；訊息不帶權限，以快取中的身分組計算後交給 `commands::is_admin`
+async fn author_is_admin(ctx: &Context, msg: &Message) -> bool {
+    let Some(guild_id) = msg.guild_id else {
+        return commands::is_admin(None, false);
+    };
+    let Ok(mut member) = msg.member(ctx).await else {
+        return false;
+    };
+    member.permissions = ctx
+        .cache
+        .guild(guild_id)
+        .map(|guild| guild.member_permissions(&member));
+    commands::is_admin(Some(&member), true)
+}
+
 pub struct Handler {
     state: AppState,
 }
@@ -142,9 +394,172 @@ pub enum ExecStatus {
     Running,
     Success,
     Error(String),
+    TimedOut,
+    /// 程序停止時超過寬限期仍在執行，被中斷的回合
+    Interrupted,
 }
 
 impl Handler {
+    /// 經由斷路器取得 session：開啟中直接回報 BackendUnavailable，不再嘗試啟動後端
+    pub async fn open_session(
+        state: &AppState,
+        channel_id: u64,
+        agent_type: agent::AgentType,
+    ) -> anyhow::Result<(Arc<dyn AiAgent>, bool)> {
+        let backend = agent_type.to_string();
+        let now = std::time::Instant::now();
+        if let Some(retry_at) = state.circuit.check(channel_id, &backend, now).await {
+            return Err(circuit::BackendUnavailable {
+                backend,
+                retry_at: circuit::unix_time(retry_at, now),
+            }
+            .into());
+        }
+        let result = state
+            .sessions()
+            .get_or_create_session(channel_id, agent_type.clone())
+            .await;
+        match &result {
+            Ok(_) => state.circuit.record_success(channel_id, &backend).await,
+            // 被 enabled_backends 停用不算後端故障
+            Err(_) if agent::is_backend_enabled(&agent_type) => {
+                Self::record_backend_failure(state, channel_id, agent_type).await
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    async fn record_backend_failure(
+        state: &AppState,
+        channel_id: u64,
+        agent_type: agent::AgentType,
+    ) {
+        let backend = agent_type.to_string();
+        if state
+            .circuit
+            .record_failure(channel_id, &backend, std::time::Instant::now())
+            .await
+        {
+            warn!(
+                "🔌 Circuit opened for {} on channel {}; probing in background",
+                backend, channel_id
+            );
+            tokio::spawn(Self::probe_backend(state.clone(), channel_id, agent_type));
+        }
+    }
+
+    /// 斷路器開啟後依退避時間重試建立 session，成功即關閉
+    async fn probe_backend(state: AppState, channel_id: u64, agent_type: agent::AgentType) {
+        let backend = agent_type.to_string();
+        while let Some(at) = state.circuit.next_probe(channel_id, &backend).await {
+            tokio::time::sleep_until(at.into()).await;
+            let healthy = match state
+                .sessions()
+                .get_or_create_session(channel_id, agent_type.clone())
+                .await
+            {
+                Ok((agent, _)) => agent.get_state().await.is_ok(),
+                Err(_) => false,
+            };
+            if healthy {
+                state.circuit.record_success(channel_id, &backend).await;
+                info!(
+                    "🔌 Circuit closed for {} on channel {}; backend is healthy again",
+                    backend, channel_id
+                );
+                return;
+            }
+            let next = state
+                .circuit
+                .probe_failed(channel_id, &backend, std::time::Instant::now())
+                .await;
+            warn!(
+                "🔌 Probe failed for {} on channel {}; next probe in {}s",
+                backend,
+                channel_id,
+                next.saturating_duration_since(std::time::Instant::now())
+                    .as_secs()
+            );
+        }
+    }
+
+    /// 不啟動 Agent，直接回覆功能卡片
+    async fn reply_meta_query(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        query: meta::MetaQuery,
+        mention_only: bool,
+        auth_hint: Option<String>,
+    ) {
+        let channel_config = ChannelConfig::load().await.unwrap_or_default();
+        let channel_id_str = msg.channel_id.to_string();
+        let backend = channel_config.get_agent_type(&channel_id_str);
+        let assistant_name = resolve_channel_assistant_name(
+            &channel_config,
+            &channel_id_str,
+            &self.state.config.assistant_name,
+        );
+        let card = {
+            let i18n = self.state.i18n.read().await;
+            meta::build_capability_card(
+                &i18n,
+                query,
+                &assistant_name,
+                &backend,
+                mention_only,
+                auth_hint,
+            )
+        };
+        info!("💡 Answered meta query {:?} in {}", query, msg.channel_id);
+        let _ = msg
+            .channel_id
+            .send_message(&ctx.http, card.reference_message(msg))
+            .await;
+    }
+
+    async fn flush_outbox(&self, ctx: &Context) {
+        self.state.outbox.set_connected(true);
+        let note = self.state.i18n.read().await.get("delayed_response_note");
+        self.state.outbox.flush(&ctx.http, &note).await;
+    }
+
+    /// 中斷一般使用者進行中的回合，回傳要重新排入的原始輸入；
+    /// 回合仍在排隊、由優先使用者發起或找不到原始輸入時不中斷
+    async fn preempt_in_flight(
+        agent: &Arc<dyn AiAgent>,
+        http: &Arc<serenity::http::Http>,
+        channel_id: serenity::model::id::ChannelId,
+        state: &AppState,
+    ) -> Option<UserInput> {
+        let (last_msg_id, previous) = state
+            .last_turns
+            .lock()
+            .await
+            .get(&channel_id.get())
+            .cloned()?;
+        let msg_id = turn_controls::stop_running_turn(state, channel_id, agent, |active_msg_id| {
+            active_msg_id == last_msg_id && !previous.priority
+        })
+        .await?;
+        let note = state
+            .channel_i18n(channel_id.get())
+            .await
+            .read()
+            .await
+            .get("priority_preempted_note");
+        let _ = channel_id
+            .send_message(
+                http,
+                CreateMessage::new()
+                    .content(note)
+                    .reference_message((channel_id, msg_id)),
+            )
+            .await;
+        Some(previous)
+    }
+
     pub async fn start_agent_loop(
         agent: Arc<dyn AiAgent>,
         http: Arc<serenity::http::Http>,
@@ -152,11 +567,37 @@ impl Handler {
         state: AppState,
         initial_input: Option<UserInput>,
         is_brand_new: bool,
+        revision_of: Option<serenity::model::id::MessageId>,
     ) {
         let channel_id_u64 = channel_id.get();
         let mut initial_input = initial_input;
 
-        // 1. 若該頻道已有執行中任務，將新輸入排隊（覆蓋舊排隊）而不是硬中止。
+        // 停止中不再開始新回合：提示留在佇列紀錄，重啟後在頻道中確認重送
+        if state.shutdown.is_draining() {
+            if let Some(input) = initial_input {
+                let notify = input.requester.is_some();
+                state
+                    .pending_inputs
+                    .lock()
+                    .await
+                    .entry(channel_id_u64)
+                    .or_default()
+                    .push(input);
+                state.sync_queue_journal(channel_id_u64).await;
+                if notify {
+                    let note = state
+                        .channel_i18n(channel_id_u64)
+                        .await
+                        .read()
+                        .await
+                        .get("shutdown_prompt_deferred");
+                    let _ = channel_id.say(&http, note).await;
+                }
+            }
+            return;
+        }
+
+        // 1. 若該頻道已有執行中任務，將新輸入排隊（完成後依 token 上限合併成批次）而不是硬中止。
         {
             let has_active = {
                 let active = state.active_renders.lock().await;
@@ -164,52 +605,498 @@ impl Handler {
             };
             if has_active {
                 if let Some(input) = initial_input.take() {
-                    let mut pending = state.pending_inputs.lock().await;
-                    pending.insert(channel_id_u64, input);
+                    let priority = input.priority;
+                    // 支援 steer 的後端直接把使用者的新訊息注入進行中的回合；
+                    // 優先使用者的訊息是另一個請求，不併入別人的回合
+                    if input.requester.is_some()
+                        && !priority
+                        && !input.quick
+                        && input.files.is_empty()
+                        && agent.supports_steering()
+                    {
+                        match agent.steer(&input.text).await {
+                            Ok(()) => {
+                                info!("↪️ Steered in-flight turn on channel {}", channel_id_u64);
+                                return;
+                            }
+                            Err(e) => warn!(
+                                "⚠️ Steering failed on channel {}; queueing instead: {}",
+                                channel_id_u64, e
+                            ),
+                        }
+                    }
+                    if priority {
+                        let preempted = if state.config.priority.preempt_in_flight {
+                            Self::preempt_in_flight(&agent, &http, channel_id, &state).await
+                        } else {
+                            None
+                        };
+                        let mut pending = state.pending_inputs.lock().await;
+                        let queue = pending.entry(channel_id_u64).or_default();
+                        match preempted {
+                            Some(previous) => {
+                                queue.push_preempted(previous);
+                                info!(
+                                    "⏭️ Pre-empted in-flight turn on channel {} for a priority request",
+                                    channel_id_u64
+                                );
+                                initial_input = Some(input);
+                            }
+                            None => {
+                                queue.push_urgent(input);
+                                info!(
+                                    "⏫ Queued priority input ahead of channel {}",
+                                    channel_id_u64
+                                );
+                            }
+                        }
+                    } else {
+                        let mut pending = state.pending_inputs.lock().await;
+                        pending.entry(channel_id_u64).or_default().push(input);
+                        info!(
+                            "⏳ Queued input for channel {} while render is running",
+                            channel_id_u64
+                        );
+                    }
+                    state.sync_queue_journal(channel_id_u64).await;
+                }
+                if initial_input.is_none() {
+                    return;
+                }
+            }
+        }
+
+        // 由排隊批次派送而來時取出進度 (第 N 批 / 共 M 批)
+        let batch_progress = {
+            let mut pending = state.pending_inputs.lock().await;
+            let progress = pending
+                .get_mut(&channel_id_u64)
+                .and_then(|queue| queue.take_progress());
+            if pending.get(&channel_id_u64).is_some_and(|q| q.is_empty()) {
+                pending.remove(&channel_id_u64);
+            }
+            progress
+        };
+
+        // 本輪的頻道設定只讀一次，各項檢查都以同一份為準
+        let channel_id_str = channel_id.to_string();
+        let channel_cfg = ChannelConfig::load().await.unwrap_or_default();
+        let channel_entry = channel_cfg.channels.get(&channel_id_str);
+
+        let requester = initial_input.as_ref().and_then(|input| input.requester);
+        // /quick 簡答只顯示回答區塊
+        let quick = initial_input.as_ref().is_some_and(|input| input.quick);
+        // 「繼續」的回合把先前的回答併入顯示
+        let continues = initial_input
+            .as_ref()
+            .and_then(|input| input.continues.clone());
+        // FAQ 快取以使用者的原始問題比對，需在 pre_turn 改寫前取得
+        let faq_prompt = initial_input
+            .as_ref()
+            .filter(|input| input.requester.is_some() && !input.quick && input.continues.is_none())
+            .map(|input| input.text.clone());
+        // 頻道開啟自動語言時，以使用者原文 (pre_turn 改寫前) 判斷語言；
+        // /quick 的提示已帶英文指示，不列入判斷
+        let reply_language = match initial_input
+            .as_ref()
+            .filter(|i| i.requester.is_some() && !i.quick)
+        {
+            Some(input) if channel_entry.is_some_and(|entry| entry.auto_reply_language) => {
+                lang_detect::detect(&input.text)
+            }
+            _ => None,
+        };
+        if let Some(language) = reply_language {
+            debug!(
+                "🌐 Detected {} prompt on channel {}",
+                language, channel_id_u64
+            );
+        }
+        // 以使用者名義執行的排程也套用該使用者的個人偏好
+        let pref_user = requester.or(initial_input.as_ref().and_then(|input| input.on_behalf_of));
+        let user_prefs = prefs::load_for(pref_user).await;
+        // 觸發者有個人語言偏好時，本輪介面改用該語言
+        // 其次採用伺服器偏好語言，最後才是 config.toml 的全域語言
+        let guild_lang = state.channel_locale(channel_id_u64).await;
+        let turn_i18n = match user_prefs.language.as_ref().or(guild_lang.as_ref()) {
+            Some(lang) if *lang != state.i18n.read().await.current_lang => {
+                Arc::new(RwLock::new(I18n::new(lang)))
+            }
+            _ => Arc::clone(&state.i18n),
+        };
+
+        // 伺服器用量上限：超過後改用備用模型，未設定備用模型時暫停到下個月
+        let usage_guild = match initial_input {
+            Some(_) => state
+                .channel_guilds
+                .lock()
+                .await
+                .get(&channel_id_u64)
+                .copied(),
+            None => None,
+        };
+        let mut on_fallback_model = false;
+        if let Some(guild_id) = usage_guild {
+            let current_model =
+                channel_entry.and_then(|e| e.model_provider.clone().zip(e.model_id.clone()));
+            match state
+                .usage()
+                .gate(guild_id, channel_id_u64, current_model)
+                .await
+            {
+                usage_caps::Gate::Open => {}
+                usage_caps::Gate::Restore(Some((provider, model_id))) => {
+                    info!(
+                        "💰 Restoring model {}/{} on channel {}",
+                        provider, model_id, channel_id_u64
+                    );
+                    if let Err(e) = agent.set_model(&provider, &model_id).await {
+                        warn!("⚠️ Failed to restore model after usage cap: {}", e);
+                    }
+                }
+                usage_caps::Gate::Restore(None) => {
+                    // 切換前未指定模型：清除備用模型的偏好，下次建立 session 時回到預設
+                    let cleared = ChannelConfig::update(|cfg| {
+                        if let Some(entry) = cfg.channels.get_mut(&channel_id_str) {
+                            entry.model_provider = None;
+                            entry.model_id = None;
+                        }
+                    })
+                    .await;
+                    if let Err(e) = cleared {
+                        warn!("⚠️ Failed to clear fallback model preference: {}", e);
+                    }
+                }
+                usage_caps::Gate::Fallback {
+                    provider,
+                    model_id,
+                    switch,
+                } => {
+                    on_fallback_model = true;
+                    if switch {
+                        info!(
+                            "💰 Usage cap exceeded; switching channel {} to {}/{}",
+                            channel_id_u64, provider, model_id
+                        );
+                        if let Err(e) = agent.set_model(&provider, &model_id).await {
+                            warn!("⚠️ Failed to switch to fallback model: {}", e);
+                        }
+                    }
+                }
+                usage_caps::Gate::Paused { resets_at } => {
                     info!(
-                        "⏳ Queued input for channel {} while render is running",
-                        channel_id_u64
+                        "💰 Usage cap exceeded; pausing channel {} until {}",
+                        channel_id_u64, resets_at
                     );
+                    let text = turn_i18n
+                        .read()
+                        .await
+                        .get_args("usage_paused", &[resets_at.to_string()]);
+                    if let Err(e) = channel_id
+                        .send_message(&http, CreateMessage::new().content(text))
+                        .await
+                    {
+                        warn!("⚠️ Failed to send usage cap notice: {}", e);
+                    }
+                    // 暫停期間排隊中的輸入也不再派送
+                    state.pending_inputs.lock().await.remove(&channel_id_u64);
+                    state.queue_journal.finish(channel_id_u64).await;
+                    state.sync_queue_journal(channel_id_u64).await;
+                    return;
+                }
+            }
+        }
+
+        // pre_turn 腳本可改寫或拒絕本輪提示
+        if let Some(input) = initial_input.as_mut() {
+            let decision = hooks::run_pre_turn(
+                &state.config.hooks,
+                &hooks::PreTurnPayload {
+                    channel_id: channel_id_u64,
+                    requester,
+                    backend: agent.agent_type(),
+                    text: &input.text,
+                    files: input.files.iter().map(|f| f.name.as_str()).collect(),
+                },
+            )
+            .await;
+            let notice = match decision {
+                hooks::PreTurnDecision::Continue(Some(text)) => {
+                    input.text = text;
+                    None
+                }
+                hooks::PreTurnDecision::Continue(None) => None,
+                hooks::PreTurnDecision::Reject(reason) => {
+                    Some(turn_i18n.read().await.get_args("hook_rejected", &[reason]))
+                }
+                hooks::PreTurnDecision::Unavailable => {
+                    Some(turn_i18n.read().await.get("hook_unavailable"))
+                }
+            };
+            if let Some(text) = notice {
+                if let Err(e) = channel_id
+                    .send_message(&http, CreateMessage::new().content(text))
+                    .await
+                {
+                    warn!("⚠️ Failed to send hook rejection: {}", e);
+                }
+                // 被拒絕的可能是排隊批次，繼續派送剩下的
+                let next_input = {
+                    let mut pending = state.pending_inputs.lock().await;
+                    pending
+                        .get_mut(&channel_id_u64)
+                        .and_then(|queue| queue.next_batch(state.config.max_batch_tokens))
+                };
+                state.queue_journal.finish(channel_id_u64).await;
+                state.sync_queue_journal(channel_id_u64).await;
+                if let Some(next_input) = next_input {
+                    let _ = state.queued_loop_tx.send((channel_id_u64, next_input));
                 }
                 return;
             }
         }
 
-        let i18n = state.i18n.read().await;
-        let processing_msg = i18n.get("processing");
+        // 本輪要求模型以相同語言回覆時，在 pre_turn 改寫後附加指示
+        if let Some((input, language)) = initial_input.as_mut().zip(reply_language) {
+            input.text = format!(
+                "{}\n\n{}",
+                lang_detect::reply_instruction(language),
+                input.text
+            );
+        }
+
+        let i18n = turn_i18n.read().await;
+        let mut title_suffixes = Vec::new();
+        if revision_of.is_some() {
+            title_suffixes.push(i18n.get("response_revision"));
+        }
+        if on_fallback_model {
+            title_suffixes.push(i18n.get("usage_fallback_suffix"));
+        }
+        if let Some(progress) = batch_progress {
+            title_suffixes.push(i18n.get_args(
+                "queue_batch_progress",
+                &[progress.index.to_string(), progress.total.to_string()],
+            ));
+        }
+        let title_suffix = title_suffixes.join(" ");
+        let processing_msg = format!("{} {}", i18n.get("processing"), title_suffix)
+            .trim_end()
+            .to_string();
         drop(i18n);
 
-        let discord_msg = match channel_id
-            .send_message(
-                &http,
-                CreateMessage::new()
-                    .embed(CreateEmbed::new().title(&processing_msg).color(0xFFA500)),
+        // 排程等無人觸發的回合在安靜時段內以靜音佔位，結果留到時段結束再送出
+        let quiet_until = if requester.is_none() && initial_input.is_some() {
+            quiet::quiet_until(
+                channel_entry.and_then(|entry| entry.quiet_hours.as_ref()),
+                chrono::Local::now(),
             )
-            .await
+        } else {
+            None
+        };
+
+        // 無障礙模式：頻道或觸發者開啟時以不含表情符號的純文字訊息取代 Embed
+        let accessible =
+            user_prefs.accessible || channel_entry.is_some_and(|entry| entry.accessible);
+        // 狀態反應模式：不發佔位訊息也不即時更新，只在使用者的提示上以反應標示進度，完成時才送出回答
+        let status_prompt = match initial_input
+            .as_ref()
+            .and_then(|input| input.source_message)
         {
-            Ok(m) => m,
-            Err(e) => {
-                error!("Failed to send: {}", e);
-                return;
+            Some(prompt)
+                if quiet_until.is_none()
+                    && channel_entry.is_some_and(|entry| entry.status_reactions) =>
+            {
+                Some(serenity::model::id::MessageId::new(prompt))
+            }
+            _ => None,
+        };
+
+        let quiet_note = match quiet_until {
+            Some(until) => Some(
+                turn_i18n
+                    .read()
+                    .await
+                    .get_args("quiet_holding", &[until.to_string()]),
+            ),
+            None => None,
+        };
+        let mut create_msg = if accessible {
+            let text = std::iter::once(processing_msg.clone())
+                .chain(quiet_note)
+                .collect::<Vec<_>>()
+                .join("\n");
+            CreateMessage::new().content(accessibility::strip_decorative_emoji(&text))
+        } else {
+            let mut placeholder = CreateEmbed::new().title(&processing_msg).color(0xFFA500);
+            if let Some(note) = quiet_note {
+                placeholder = placeholder.description(note);
+            }
+            CreateMessage::new().embed(placeholder)
+        };
+        if quiet_until.is_some() {
+            create_msg = create_msg.flags(MessageFlags::SUPPRESS_NOTIFICATIONS);
+        }
+        if let Some(previous_msg_id) = revision_of {
+            create_msg = create_msg.reference_message((channel_id, previous_msg_id));
+        }
+        let (placeholder, turn_msg_id) = match status_prompt {
+            Some(prompt) => {
+                status_reactions::mark_working(&http, channel_id, prompt).await;
+                (None, prompt)
             }
+            None => match channel_id.send_message(&http, create_msg).await {
+                Ok(m) => {
+                    let id = m.id;
+                    (Some(m), id)
+                }
+                Err(e) => {
+                    error!("Failed to send: {}", e);
+                    return;
+                }
+            },
         };
+        state
+            .flood
+            .record_post(channel_id_u64, std::time::Instant::now())
+            .await;
 
-        let composer: Arc<Mutex<EmbedComposer>> = Arc::new(Mutex::new(EmbedComposer::new(3900)));
+        // 後端設有同時回合上限時先排隊，於佔位訊息顯示排隊位置
+        let turn_permit = match initial_input
+            .as_ref()
+            .and_then(|_| state.turn_limiter.ticket(agent.agent_type()))
+        {
+            Some(mut ticket) => {
+                // 排隊期間先登記佔位訊息，讓同頻道的新訊息照常排入 pending
+                state
+                    .active_renders
+                    .lock()
+                    .await
+                    .insert(channel_id_u64, (turn_msg_id, Vec::new()));
+                let mut wait_msg = placeholder.clone();
+                loop {
+                    let waited =
+                        tokio::time::timeout(std::time::Duration::from_secs(5), ticket.wait())
+                            .await;
+                    // /abort 會移除佔位登記，此時放棄排隊
+                    let still_active = state
+                        .active_renders
+                        .lock()
+                        .await
+                        .get(&channel_id_u64)
+                        .is_some_and(|(msg_id, _)| *msg_id == turn_msg_id);
+                    if !still_active {
+                        info!(
+                            "🛑 Abandoned {} turn queue on channel {}",
+                            agent.agent_type(),
+                            channel_id_u64
+                        );
+                        return;
+                    }
+                    match waited {
+                        Ok(Ok(permit)) => break Some(permit),
+                        Ok(Err(position)) => {
+                            let Some(wait_msg) = wait_msg.as_mut() else {
+                                continue;
+                            };
+                            let title = turn_i18n.read().await.get_args(
+                                "backend_queue_position",
+                                &[agent.agent_type().to_string(), position.to_string()],
+                            );
+                            let title = format!("{} {}", title, title_suffix);
+                            let edit = if accessible {
+                                EditMessage::new().content(accessibility::strip_decorative_emoji(
+                                    title.trim_end(),
+                                ))
+                            } else {
+                                EditMessage::new().embed(
+                                    CreateEmbed::new().title(title.trim_end()).color(0xFFA500),
+                                )
+                            };
+                            let _ = wait_msg.edit(&http, edit).await;
+                        }
+                        Err(_) => {}
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // 分頁只用於單一 Embed 的回覆；無障礙模式另以純文字分段，安靜時段與私訊長回答有各自的送出方式
+        let paginate = state.config.composer.paginate
+            && !state.config.composer.multi_embed
+            && !accessible
+            && !user_prefs.compact_embeds
+            && !user_prefs.dm_long_replies
+            && !quick
+            && quiet_until.is_none()
+            && status_prompt.is_none();
+        let composer: Arc<Mutex<EmbedComposer>> = Arc::new(Mutex::new(
+            EmbedComposer::new(3900)
+                .with_minimums(state.config.composer.minimums())
+                .with_slow_tool_warning(state.config.composer.slow_tool_warn_secs)
+                .with_pagination(paginate)
+                .with_continuation(continues),
+        ));
         let status: Arc<Mutex<ExecStatus>> = Arc::new(Mutex::new(ExecStatus::Running));
-        let assistant_name = {
-            let channel_cfg = ChannelConfig::load().await.unwrap_or_default();
-            resolve_channel_assistant_name(
-                &channel_cfg,
-                &channel_id.to_string(),
-                &state.config.assistant_name,
+        let (
+            assistant_name,
+            max_turn,
+            mirror_cfg,
+            mention_policy,
+            tool_output_retention,
+            disabled_tools,
+        ) = {
+            let (mirror_cfg, mention_policy, disabled_tools) = channel_entry
+                .map(|entry| {
+                    (
+                        entry.mirror.clone(),
+                        entry.mentions.clone(),
+                        entry.disabled_tools.clone(),
+                    )
+                })
+                .unwrap_or_default();
+            (
+                resolve_channel_assistant_name(
+                    &channel_cfg,
+                    &channel_id_str,
+                    &state.config.assistant_name,
+                ),
+                resolve_channel_max_turn(&channel_cfg, &channel_id_str, state.config.max_turn_secs),
+                mirror_cfg,
+                mention_policy,
+                resolve_channel_tool_output_retention(
+                    &channel_cfg,
+                    &channel_id_str,
+                    state.config.composer.tool_output_retention,
+                ),
+                disabled_tools,
             )
         };
 
         // --- 任務啟動：收集所有 Handles ---
         let mut handles = Vec::new();
 
+        if let Some(input) = &initial_input {
+            state
+                .last_turns
+                .lock()
+                .await
+                .insert(channel_id_u64, (turn_msg_id, input.clone()));
+        }
+        // 因重啟中斷時寫回佇列紀錄的原始提示
+        let interrupted_input = initial_input.clone();
+
         let prompt_input = if let Some(mut input) = initial_input {
             let mut final_msg = input.text;
+            // 壓縮後第一輪補回壓縮前的摘要
+            if let Some(summary) = memory::take_injection(channel_id_u64).await {
+                final_msg = format!("{}\n\n{}", summary, final_msg);
+            }
+            // 上一輪回答被 /pipe 轉送到其他頻道時，補上紀錄讓對話保有去向
+            if let Some(notes) = state.pipes.take_notes(channel_id_u64).await {
+                final_msg = format!("{}\n\n{}", notes, final_msg);
+            }
             if is_brand_new {
                 let prompts = load_all_prompts();
                 if !prompts.is_empty() {
@@ -217,76 +1104,722 @@ impl Handler {
                 }
             }
             input.text = final_msg;
+            input.disabled_tools = disabled_tools;
             Some(input)
         } else {
             None
         };
 
-        let typing_http = http.clone();
-        let typing_status = Arc::clone(&status);
-        handles.push(tokio::spawn(async move {
-            loop {
-                {
-                    let s = typing_status.lock().await;
+        // --- 看門狗：超過單輪時間上限時自動 abort，保留已輸出的部分內容 ---
+        if let Some(limit) = max_turn {
+            let watchdog_status = Arc::clone(&status);
+            let watchdog_agent = Arc::clone(&agent);
+            handles.push(tokio::spawn(async move {
+                let started = std::time::Instant::now();
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    let mut s = watchdog_status.lock().await;
                     if *s != ExecStatus::Running {
+                        return;
+                    }
+                    if started.elapsed() >= limit {
+                        *s = ExecStatus::TimedOut;
                         break;
                     }
                 }
-                let _ = channel_id.broadcast_typing(&typing_http).await;
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            }
-        }));
+                warn!(
+                    "⏱️ Turn exceeded {}s on channel {}; aborting backend",
+                    limit.as_secs(),
+                    channel_id_u64
+                );
+                if let Err(e) = watchdog_agent.abort().await {
+                    warn!(
+                        "⚠️ Failed to abort timed out turn on channel {}: {}",
+                        channel_id_u64, e
+                    );
+                }
+            }));
+        }
+
+        // --- 停止看守：寬限期結束仍在執行時中斷回合，提示留待重啟後重送 ---
+        {
+            let shutdown_status = Arc::clone(&status);
+            let shutdown_agent = Arc::clone(&agent);
+            let shutdown_state = state.clone();
+            let interrupt = state.shutdown.subscribe();
+            handles.push(tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    let mut s = shutdown_status.lock().await;
+                    if *s != ExecStatus::Running {
+                        return;
+                    }
+                    if *interrupt.borrow() {
+                        *s = ExecStatus::Interrupted;
+                        break;
+                    }
+                }
+                warn!(
+                    "🛑 Interrupting turn on channel {} for shutdown",
+                    channel_id_u64
+                );
+                if let Some(input) = &interrupted_input {
+                    shutdown_state
+                        .queue_journal
+                        .start(channel_id_u64, input)
+                        .await;
+                }
+                if let Err(e) = shutdown_agent.abort().await {
+                    warn!(
+                        "⚠️ Failed to abort interrupted turn on channel {}: {}",
+                        channel_id_u64, e
+                    );
+                }
+            }));
+        }
 
         // --- 任務 A: Render 循環 ---
         let render_status = Arc::clone(&status);
         let render_composer = Arc::clone(&composer);
         let render_http = http.clone();
-        let mut render_msg = discord_msg.clone();
-        let render_i18n = Arc::clone(&state.i18n);
+        let mut render_msg = placeholder;
+        let render_i18n = Arc::clone(&turn_i18n);
         let render_state = state.clone();
         let render_assistant_name = assistant_name.clone();
         let render_channel_id = channel_id;
-        let render_msg_id = discord_msg.id;
+        let render_msg_id = turn_msg_id;
+
+        let render_multi_embed = state.config.composer.multi_embed;
+        let turn_started = std::time::Instant::now();
+        let tool_count = Arc::new(AtomicUsize::new(0));
+        let render_tool_count = Arc::clone(&tool_count);
+        let render_agent = Arc::clone(&agent);
+        let render_prefs = user_prefs.clone();
+        let render_title_suffix = title_suffix.clone();
+        let render_quiet = quiet_until.is_some();
+        let render_prompt_tokens = prompt_input
+            .as_ref()
+            .map_or(0, |input| batching::estimate_tokens(&input.text) as u64);
+        let mut typing = typing::TypingGate::new(std::time::Duration::from_secs(
+            state.config.typing_idle_secs,
+        ));
 
         let render_task = tokio::spawn(async move {
-            let mut last_content = String::new();
+            // 回合結束 (或被 /abort 中止) 時釋放後端名額
+            let _turn_permit = turn_permit;
+            let _in_flight = prometheus::turn_started();
+            let mut last_sections: Vec<(Section, String)> = Vec::new();
             let mut last_status = ExecStatus::Running;
+            let mut last_footer_state = (None, 0);
+            let mut dm_note: Option<String> = None;
+            let mut page_count = 0usize;
+            // 回合結束時保存完整工具輸出，成功才附上「完整輸出」按鈕
+            let mut tool_outputs_stored: Option<bool> = None;
             loop {
-                tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
-
-                let (current_status, desc) = {
-                    let c = render_composer.lock().await;
+                tokio::time::sleep(RENDER_INTERVAL).await;
+
+                let (
+                    current_status,
+                    pages,
+                    mut sections,
+                    full_answer,
+                    footer_state,
+                    last_activity,
+                    output_truncated,
+                    citations,
+                ) = {
+                    let mut c = render_composer.lock().await;
                     let s = render_status.lock().await;
-                    (s.clone(), c.render())
+                    let pages = c.take_pages();
+                    let sections = if render_prefs.compact_embeds || quick {
+                        c.render_sections(MULTI_EMBED_BUDGET)
+                            .into_iter()
+                            .filter(|(section, _)| *section == Section::Answer)
+                            .collect()
+                    } else if render_multi_embed {
+                        c.render_sections(MULTI_EMBED_BUDGET)
+                    } else {
+                        vec![(Section::Answer, c.render())]
+                    };
+                    (
+                        s.clone(),
+                        pages,
+                        sections,
+                        c.render_answer_text(),
+                        (c.image_mode, c.steered),
+                        c.last_activity,
+                        c.output_truncated,
+                        c.citations.clone(),
+                    )
                 };
 
-                if desc != last_content || current_status != last_status {
+                // 輸入中狀態只在後端仍在串流時送出
+                if current_status == ExecStatus::Running
+                    && !render_quiet
+                    && typing.tick(std::time::Instant::now(), last_activity)
+                {
+                    let _ = render_channel_id.broadcast_typing(&render_http).await;
+                }
+
+                // 分頁模式：已滿的頁面定稿在目前訊息，之後的串流改在新訊息進行
+                for page in pages {
+                    page_count += 1;
                     let i18n = render_i18n.read().await;
-                    let (title, color, body) =
-                        build_render_view(&i18n, &current_status, &desc, &render_assistant_name);
-                    let embed = CreateEmbed::new()
-                        .title(title)
-                        .color(color)
-                        .description(body);
-
-                    if let Err(e) = render_msg
-                        .edit(&render_http, EditMessage::new().embed(embed))
+                    let page_embeds = build_turn_views(
+                        &i18n,
+                        &ExecStatus::Success,
+                        &[(Section::Answer, page)],
+                        &render_assistant_name,
+                        &render_title_suffix,
+                        render_agent.agent_type(),
+                        render_state.config.opencode.port,
+                    )
+                    .into_iter()
+                    .map(|(title, color, body)| {
+                        CreateEmbed::new()
+                            .title(title)
+                            .color(color)
+                            .description(body)
+                            .footer(CreateEmbedFooter::new(
+                                i18n.get_args("embed_page_continued", &[page_count.to_string()]),
+                            ))
+                    })
+                    .collect::<Vec<_>>();
+                    let _edit_permit = render_state
+                        .render_scheduler
+                        .acquire(true, RENDER_INTERVAL)
+                        .await;
+                    if let Some(Err(e)) = match render_msg.as_mut() {
+                        Some(msg) => Some(
+                            msg.edit(
+                                &render_http,
+                                EditMessage::new().embeds(page_embeds).components(vec![]),
+                            )
+                            .await,
+                        ),
+                        None => None,
+                    } {
+                        prometheus::edit_failed();
+                        warn!("⚠️ Failed to finalize response page {}: {}", page_count, e);
+                    }
+                    let placeholder = build_turn_views(
+                        &i18n,
+                        &ExecStatus::Running,
+                        &[],
+                        &render_assistant_name,
+                        &render_title_suffix,
+                        render_agent.agent_type(),
+                        render_state.config.opencode.port,
+                    )
+                    .into_iter()
+                    .map(|(title, color, body)| {
+                        CreateEmbed::new()
+                            .title(title)
+                            .color(color)
+                            .description(body)
+                    })
+                    .collect::<Vec<_>>();
+                    match render_channel_id
+                        .send_message(&render_http, CreateMessage::new().embeds(placeholder))
                         .await
                     {
+                        Ok(next) => {
+                            render_msg = Some(next);
+                            last_sections.clear();
+                        }
+                        Err(e) => warn!(
+                            "⚠️ Failed to start response page {} on channel {}: {}",
+                            page_count + 1,
+                            render_channel_id,
+                            e
+                        ),
+                    }
+                }
+
+                // 回合結束後把引用來源整理成編號清單，附在回答結尾
+                if current_status != ExecStatus::Running && !citations.is_empty() {
+                    let others: usize = sections
+                        .iter()
+                        .filter(|(section, _)| *section != Section::Answer)
+                        .map(|(_, body)| body.chars().count())
+                        .sum();
+                    let limit = citations::EMBED_DESCRIPTION_MAX
+                        .min(MULTI_EMBED_BUDGET.saturating_sub(others));
+                    let header = render_i18n.read().await.get("citations_sources");
+                    for (section, body) in sections.iter_mut() {
+                        if *section == Section::Answer {
+                            *body = citations::with_sources(body, &citations, &header, limit);
+                        }
+                    }
+                }
+
+                // 長回答改以私訊送出完整內容，頻道內僅留提示
+                if current_status == ExecStatus::Success
+                    && render_prefs.dm_long_replies
+                    && dm_note.is_none()
+                    && full_answer.chars().count() > DM_LONG_REPLY_CHARS
+                {
+                    if let Some(user_id) = pref_user {
+                        let i18n = render_i18n.read().await;
+                        match send_dm_chunks(&render_http, user_id, &full_answer).await {
+                            Ok(_) => dm_note = Some(i18n.get("prefs_dm_reply_sent")),
+                            Err(e) => warn!("⚠️ Failed to DM long reply to {}: {}", user_id, e),
+                        }
+                    }
+                }
+                if let Some(note) = &dm_note {
+                    sections.retain(|(section, _)| *section != Section::Answer);
+                    sections.push((Section::Answer, note.clone()));
+                }
+
+                if render_quiet {
+                    // 安靜時段不更新佔位訊息，完成後交給 quiet_queue 於時段結束時送出
+                    if current_status != ExecStatus::Running {
+                        let views = build_turn_views(
+                            &*render_i18n.read().await,
+                            &current_status,
+                            &sections,
+                            &render_assistant_name,
+                            &render_title_suffix,
+                            render_agent.agent_type(),
+                            render_state.config.opencode.port,
+                        );
+                        render_state
+                            .quiet_queue
+                            .hold(quiet::HeldDelivery {
+                                channel_id: render_channel_id,
+                                placeholder: render_msg_id,
+                                views,
+                                completed_at: chrono::Utc::now().timestamp(),
+                            })
+                            .await;
+                        info!(
+                            "🌙 Holding response for channel {} until quiet hours end",
+                            render_channel_id
+                        );
+                    }
+                } else if render_msg.is_none() && current_status == ExecStatus::Running {
+                    // 狀態反應模式：回合結束前不發訊息
+                } else if sections != last_sections
+                    || current_status != last_status
+                    || footer_state != last_footer_state
+                {
+                    let i18n = render_i18n.read().await;
+                    let views = build_turn_views(
+                        &i18n,
+                        &current_status,
+                        &sections,
+                        &render_assistant_name,
+                        &render_title_suffix,
+                        render_agent.agent_type(),
+                        render_state.config.opencode.port,
+                    );
+                    let (image_mode, steered) = footer_state;
+                    let footer = embed_footer(&i18n, image_mode, steered);
+                    let mut extra_chunks = Vec::new();
+                    let mut edit = if accessible {
+                        let mut chunks =
+                            accessibility::plain_chunks(&views, footer.as_deref()).into_iter();
+                        extra_chunks = chunks.by_ref().skip(1).collect();
+                        EditMessage::new()
+                            .content(chunks.next().unwrap_or_default())
+                            .embeds(vec![])
+                            .allowed_mentions(mentions::suppressed())
+                    } else {
+                        let mut embeds: Vec<CreateEmbed> = views
+                            .iter()
+                            .map(|(title, color, body)| {
+                                CreateEmbed::new()
+                                    .title(title)
+                                    .color(*color)
+                                    .description(body)
+                            })
+                            .collect();
+                        if let Some(footer) = footer {
+                            if let Some(last) = embeds.pop() {
+                                embeds.push(last.footer(CreateEmbedFooter::new(footer)));
+                            }
+                        }
+                        EditMessage::new().embeds(embeds)
+                    };
+
+                    let is_final = current_status != ExecStatus::Running;
+                    if is_final && tool_outputs_stored.is_none() {
+                        let log = render_composer.lock().await.tool_log.clone();
+                        tool_outputs_stored = Some(
+                            tool_outputs::store(
+                                channel_id_u64,
+                                render_msg_id.get(),
+                                &log,
+                                tool_output_retention,
+                            )
+                            .await,
+                        );
+                    }
+                    let mut components = Vec::new();
+                    if tool_outputs_stored == Some(true) {
+                        components.push(tool_outputs::show_button(
+                            i18n.get("tool_output_show"),
+                            render_msg_id,
+                        ));
+                    }
+                    // 出錯時附上已輸出的部分回答，並提供接續重試的按鈕
+                    if matches!(current_status, ExecStatus::Error(_)) && requester.is_some() {
+                        for attachment in retry::partial_attachments(render_msg_id, &full_answer) {
+                            edit = edit.new_attachment(attachment);
+                        }
+                        components.push(retry::retry_button(
+                            i18n.get("turn_retry_button"),
+                            render_msg_id,
+                        ));
+                        render_state
+                            .partial_answers
+                            .lock()
+                            .await
+                            .insert(channel_id_u64, (render_msg_id, full_answer.clone()));
+                    }
+                    // 回答被模型輸出上限截斷 (或看起來停在中間) 時提供「繼續」
+                    if current_status == ExecStatus::Success
+                        && requester.is_some()
+                        && (output_truncated || continuation::looks_truncated(&full_answer))
+                    {
+                        components.push(continuation::continue_button(
+                            i18n.get("turn_continue_button"),
+                            render_msg_id,
+                        ));
+                        render_state
+                            .partial_answers
+                            .lock()
+                            .await
+                            .insert(channel_id_u64, (render_msg_id, full_answer.clone()));
+                    }
+                    // 進行中提供「中止」，正常完成後提供以同一提問「重新產生」
+                    if current_status == ExecStatus::Running {
+                        components.push(turn_controls::abort_button(
+                            i18n.get("turn_abort_button"),
+                            render_msg_id,
+                        ));
+                    } else if matches!(current_status, ExecStatus::Success | ExecStatus::TimedOut)
+                        && requester.is_some()
+                    {
+                        components.push(turn_controls::regenerate_button(
+                            i18n.get("turn_regenerate_button"),
+                            render_msg_id,
+                        ));
+                    }
+                    // 一律覆寫，回合結束時才會拿掉「中止」按鈕
+                    edit = edit.components(components);
+                    // 編輯名額不足時放棄這次中間進度，下一輪以較新的內容重試
+                    let Some(_edit_permit) = render_state
+                        .render_scheduler
+                        .acquire(is_final, RENDER_INTERVAL)
+                        .await
+                    else {
+                        continue;
+                    };
+                    // 狀態反應模式在完成時才建立回答訊息 (回覆使用者的提示)，接著照常填入內容
+                    if render_msg.is_none() {
+                        let (title, color, _) = views.first().cloned().unwrap_or_default();
+                        let first = if accessible {
+                            CreateMessage::new()
+                                .content(accessibility::strip_decorative_emoji(&title))
+                        } else {
+                            CreateMessage::new().embed(CreateEmbed::new().title(title).color(color))
+                        };
+                        match render_channel_id
+                            .send_message(
+                                &render_http,
+                                first.reference_message((render_channel_id, render_msg_id)),
+                            )
+                            .await
+                        {
+                            Ok(m) => render_msg = Some(m),
+                            Err(e) => error!("❌ Failed to send response message: {}", e),
+                        }
+                    }
+                    let mut result = match render_msg.as_mut() {
+                        Some(msg) => msg.edit(&render_http, edit).await,
+                        None => Err(serenity::Error::Other("response message was not sent")),
+                    };
+                    // 被 Discord 拒絕（AutoMod、Embed 過大、格式不合法）時改用降級方式送出
+                    if let (Some(fallback), Some(msg)) = (
+                        result
+                            .as_ref()
+                            .err()
+                            .and_then(discord_error_code)
+                            .and_then(fallback_for_code),
+                        render_msg.as_mut(),
+                    ) {
+                        warn!(
+                            "⚠️ Edit rejected on channel {}; falling back to {:?}",
+                            render_channel_id, fallback
+                        );
+                        result = apply_edit_fallback(
+                            &render_http,
+                            msg,
+                            &views,
+                            fallback,
+                            is_final,
+                            &i18n.get("fallback_attached_note"),
+                            &i18n.get("fallback_too_large_note"),
+                        )
+                        .await;
+                    }
+
+                    if let Err(e) = result {
+                        prometheus::edit_failed();
                         error!("❌ Render failed to edit message: {}", e);
+                        // 最終結果送不出去（例如斷線中）時暫存，重新連線後補送
+                        if let (true, Some(msg)) = (is_final, render_msg.as_ref()) {
+                            render_state
+                                .outbox
+                                .push(PendingDelivery {
+                                    channel_id: render_channel_id,
+                                    message_id: msg.id,
+                                    views,
+                                })
+                                .await;
+                            warn!(
+                                "📪 Buffered final response for channel {} (gateway connected: {})",
+                                render_channel_id,
+                                render_state.outbox.is_connected()
+                            );
+                        }
                     } else {
+                        // 無障礙模式的長回答在完成時補上其餘分段
+                        if is_final {
+                            for chunk in extra_chunks {
+                                if let Err(e) = render_channel_id
+                                    .send_message(
+                                        &render_http,
+                                        CreateMessage::new()
+                                            .content(chunk)
+                                            .allowed_mentions(mentions::suppressed()),
+                                    )
+                                    .await
+                                {
+                                    warn!("⚠️ Failed to send remaining reply chunk: {}", e);
+                                    break;
+                                }
+                            }
+                        }
                         info!(
-                            "📢 [EMBED-UPDATE-{}]: status={:?}, len={}",
+                            "📢 [EMBED-UPDATE-{}]: status={:?}, embeds={}, len={}",
                             render_channel_id,
                             current_status,
-                            desc.len()
+                            sections.len(),
+                            sections.iter().map(|(_, b)| b.len()).sum::<usize>()
                         );
-                        last_content = desc;
+                        last_sections = sections;
                         last_status = current_status.clone();
+                        last_footer_state = footer_state;
                     }
                 }
 
                 if current_status != ExecStatus::Running {
+                    if let Some(prompt) = status_prompt {
+                        status_reactions::mark_finished(
+                            &render_http,
+                            render_channel_id,
+                            prompt,
+                            &current_status,
+                        )
+                        .await;
+                    }
+                    let token_usage = render_composer.lock().await.token_usage;
+                    render_state
+                        .metrics
+                        .record(metrics::TurnOutcome {
+                            channel_id: channel_id_u64,
+                            user_id: requester,
+                            success: current_status == ExecStatus::Success,
+                            tool_calls: render_tool_count.load(Ordering::SeqCst) as u64,
+                            tokens: token_usage,
+                        })
+                        .await;
+                    if !matches!(
+                        current_status,
+                        ExecStatus::Success | ExecStatus::Interrupted
+                    ) {
+                        prometheus::agent_error(render_agent.agent_type());
+                    }
+                    let (status_label, error_class) = status_fields(&current_status);
+                    let model = render_agent.get_state().await.ok().and_then(|s| s.model);
+                    let duration_ms = turn_started.elapsed().as_millis() as u64;
+                    let estimated_cost_usd = if render_state.analytics.is_enabled() {
+                        let models = render_agent
+                            .get_available_models()
+                            .await
+                            .unwrap_or_default();
+                        model
+                            .as_deref()
+                            .and_then(|name| models.iter().find(|m| m.matches(name)))
+                            .and_then(|m| m.pricing)
+                            .map(|pricing| {
+                                pricing.estimate_usd(
+                                    render_prompt_tokens,
+                                    batching::estimate_tokens(&full_answer) as u64,
+                                )
+                            })
+                    } else {
+                        None
+                    };
+                    render_state
+                        .analytics
+                        .record(TurnRecord {
+                            ts: chrono::Utc::now().to_rfc3339(),
+                            channel_id: channel_id_u64,
+                            backend: render_agent.agent_type().to_string(),
+                            model: model.clone(),
+                            duration_ms,
+                            input_tokens: token_usage.map(|(input, _)| input),
+                            output_tokens: token_usage.map(|(_, output)| output),
+                            estimated_cost_usd,
+                            tool_count: render_tool_count.load(Ordering::SeqCst),
+                            status: status_label.to_string(),
+                            error_class: error_class.map(str::to_string),
+                        })
+                        .await;
+
+                    if let Some(guild_id) = usage_guild {
+                        let tokens =
+                            render_prompt_tokens + batching::estimate_tokens(&full_answer) as u64;
+                        if let Some((alert, entry)) =
+                            render_state.usage().record(guild_id, tokens).await
+                        {
+                            let alert_i18n =
+                                render_state.channel_i18n(entry.cap.alert_channel).await;
+                            let text =
+                                usage_caps::alert_message(&*alert_i18n.read().await, alert, &entry);
+                            let alert_http = http.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) =
+                                    serenity::model::id::ChannelId::new(entry.cap.alert_channel)
+                                        .say(&alert_http, text)
+                                        .await
+                                {
+                                    warn!("⚠️ Failed to post usage cap alert: {}", e);
+                                }
+                            });
+                        }
+                    }
+
+                    // 系統觸發 (排程) 的回合結果回報給排程管理，更新 Discord 活動
+                    if requester.is_none() {
+                        let scheduler = Arc::clone(render_state.scheduler());
+                        let success = current_status == ExecStatus::Success;
+                        let answer = full_answer.clone();
+                        tokio::spawn(async move {
+                            scheduler
+                                .record_result(channel_id_u64, success, &answer)
+                                .await;
+                        });
+                    }
+
+                    if render_state.config.hooks.post_turn.is_some() {
+                        let hooks_cfg = render_state.config.hooks.clone();
+                        let payload = hooks::PostTurnPayload {
+                            channel_id: channel_id_u64,
+                            requester,
+                            backend: render_agent.agent_type().to_string(),
+                            model,
+                            status: status_label.to_string(),
+                            error: match &current_status {
+                                ExecStatus::Error(message) => Some(message.clone()),
+                                _ => None,
+                            },
+                            answer: full_answer.clone(),
+                            duration_ms,
+                        };
+                        tokio::spawn(async move {
+                            hooks::run_post_turn(&hooks_cfg, &payload).await;
+                        });
+                    }
+
+                    if current_status == ExecStatus::Success {
+                        render_state
+                            .pipes
+                            .complete(channel_id_u64, render_msg_id, &full_answer)
+                            .await;
+                        if let Some(prompt) = &faq_prompt {
+                            render_state
+                                .faq_cache
+                                .complete(channel_id_u64, prompt, &full_answer)
+                                .await;
+                        }
+                    }
+
+                    if current_status == ExecStatus::Success && mirror_cfg.is_active() {
+                        mirror::mirror_response(
+                            &render_http,
+                            &mirror_cfg,
+                            mirror::MirrorPayload {
+                                answer: &full_answer,
+                                channel_id: channel_id_u64,
+                                assistant: &render_assistant_name,
+                                backend: render_agent.agent_type(),
+                            },
+                        )
+                        .await;
+                    }
+
+                    if current_status == ExecStatus::Success {
+                        let voice = Arc::clone(&render_state.voice);
+                        let answer = full_answer.clone();
+                        tokio::spawn(async move {
+                            voice.speak(channel_id_u64, &answer).await;
+                        });
+                    }
+
+                    // 回答以 embed 呈現不會通知任何人；頻道允許的提及另以回覆訊息實際通知
+                    let pings = mention_policy.allowed_in(&full_answer);
+                    if current_status == ExecStatus::Success && !pings.is_empty() {
+                        let targets = pings
+                            .iter()
+                            .map(|m| m.markup())
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        let text = render_i18n
+                            .read()
+                            .await
+                            .get_args("mentions_ping", &[targets]);
+                        if let Err(e) = render_channel_id
+                            .send_message(
+                                &render_http,
+                                CreateMessage::new()
+                                    .content(text)
+                                    .allowed_mentions(mentions::allow_only(&pings))
+                                    .reference_message((render_channel_id, render_msg_id)),
+                            )
+                            .await
+                        {
+                            warn!(
+                                "⚠️ Failed to send allowed mentions on channel {}: {}",
+                                channel_id_u64, e
+                            );
+                        }
+                    }
+
+                    if render_prefs.ping_on_complete {
+                        if let Some(user_id) = pref_user {
+                            let text = render_i18n
+                                .read()
+                                .await
+                                .get_args("prefs_ping_complete", &[user_id.to_string()]);
+                            if let Err(e) = render_channel_id
+                                .send_message(
+                                    &render_http,
+                                    CreateMessage::new()
+                                        .content(text)
+                                        .reference_message((render_channel_id, render_msg_id)),
+                                )
+                                .await
+                            {
+                                warn!("⚠️ Failed to ping user {} on completion: {}", user_id, e);
+                            }
+                        }
+                    }
+
                     let mut should_start_queued = false;
                     // 完工：從活躍任務中移除自己
                     let mut active = render_state.active_renders.lock().await;
@@ -302,11 +1835,16 @@ impl Handler {
                     }
                     drop(active);
 
-                    if should_start_queued {
+                    // 被中斷的回合保留佇列紀錄，重啟後確認重送
+                    if should_start_queued && current_status != ExecStatus::Interrupted {
                         let next_input = {
                             let mut pending = render_state.pending_inputs.lock().await;
-                            pending.remove(&channel_id_u64)
+                            pending.get_mut(&channel_id_u64).and_then(|queue| {
+                                queue.next_batch(render_state.config.max_batch_tokens)
+                            })
                         };
+                        render_state.queue_journal.finish(channel_id_u64).await;
+                        render_state.sync_queue_journal(channel_id_u64).await;
                         if let Some(next_input) = next_input {
                             if let Err(e) = render_state
                                 .queued_loop_tx
@@ -329,12 +1867,23 @@ impl Handler {
         let writer_status = Arc::clone(&status);
         let writer_composer = Arc::clone(&composer);
         let writer_agent_type = agent.agent_type().to_string();
+        let writer_tool_count = Arc::clone(&tool_count);
         let writer_task = tokio::spawn(async move {
             loop {
                 match tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv()).await {
                     Ok(Ok(event)) => {
+                        if chaos::should_drop_event() {
+                            continue;
+                        }
                         let mut comp = writer_composer.lock().await;
                         let mut s = writer_status.lock().await;
+                        // 已被看門狗等外部終止時，不再讓遲到的事件覆蓋最終狀態
+                        if *s != ExecStatus::Running {
+                            break;
+                        }
+                        if matches!(event, AgentEvent::ToolExecutionStart { .. }) {
+                            writer_tool_count.fetch_add(1, Ordering::SeqCst);
+                        }
                         let finished = apply_agent_event(&mut comp, &mut s, event);
                         if finished && *s == ExecStatus::Success && comp.blocks.is_empty() {
                             warn!(
@@ -375,9 +1924,16 @@ impl Handler {
             // tasks).  The prompt task continues in the background so the
             // underlying backend (especially Copilot, which has no abort API)
             // finishes naturally before the next prompt is dispatched.
-            // For Copilot the prompt_lock in CopilotRuntime serialises this.
+            // For Copilot the prompt_lock in AcpRuntime serialises this.
             tokio::spawn(async move {
-                if let Err(e) = agent_for_prompt.prompt_with_input(&input).await {
+                let prompt_result = agent_for_prompt.prompt_with_input(&input).await;
+                if prompt_result.is_ok() {
+                    state_for_prompt
+                        .circuit
+                        .record_success(channel_id_u64, &prompt_agent_type)
+                        .await;
+                }
+                if let Err(e) = prompt_result {
                     let err_text = e.to_string();
                     let recoverable_request_error =
                         should_auto_recover_request_error(&prompt_agent_type, &err_text);
@@ -400,6 +1956,18 @@ impl Handler {
                         }
                     }
 
+                    // 沒有任何輸出的失敗才視為後端故障
+                    if has_no_stream_output {
+                        if let Ok(agent_type) = prompt_agent_type.parse::<agent::AgentType>() {
+                            Handler::record_backend_failure(
+                                &state_for_prompt,
+                                channel_id_u64,
+                                agent_type,
+                            )
+                            .await;
+                        }
+                    }
+
                     let mut queued_recovery = false;
                     if has_no_stream_output && recoverable_request_error {
                         let is_still_running = {
@@ -410,13 +1978,17 @@ impl Handler {
                             return;
                         }
                         state_for_prompt
-                            .session_manager
+                            .sessions()
                             .remove_session(channel_id_u64)
                             .await;
-                        let mut pending = state_for_prompt.pending_inputs.lock().await;
-                        pending
+                        state_for_prompt
+                            .pending_inputs
+                            .lock()
+                            .await
                             .entry(channel_id_u64)
-                            .or_insert_with(|| input.clone());
+                            .or_default()
+                            .retry(input.clone());
+                        state_for_prompt.sync_queue_journal(channel_id_u64).await;
                         queued_recovery = true;
                         warn!(
                             "♻️ Auto-recovery queued for channel {} ({}) due to backend request failure: {}",
@@ -447,7 +2019,7 @@ impl Handler {
         handles.push(writer_task);
         {
             let mut active = state.active_renders.lock().await;
-            active.insert(channel_id_u64, (discord_msg.id, handles));
+            active.insert(channel_id_u64, (turn_msg_id, handles));
         }
     }
 }
@@ -480,6 +2052,24 @@ impl EventHandler for Handler {
             Ok(_) => info!("✅ Registered global commands"),
             Err(e) => error!("❌ Failed to register commands: {}", e),
         }
+
+        self.flush_outbox(&ctx).await;
+    }
+
+    async fn resume(&self, ctx: Context, _event: serenity::all::ResumedEvent) {
+        info!("🔌 Gateway session resumed");
+        self.flush_outbox(&ctx).await;
+    }
+
+    async fn shard_stage_update(&self, _ctx: Context, event: serenity::all::ShardStageUpdateEvent) {
+        let connected = event.new == serenity::all::ConnectionStage::Connected;
+        if !connected && self.state.outbox.is_connected() {
+            warn!(
+                "🔌 Gateway shard {} left connected state: {:?}",
+                event.shard_id, event.new
+            );
+        }
+        self.state.outbox.set_connected(connected);
     }
 
     async fn guild_create(
@@ -495,6 +2085,50 @@ impl EventHandler for Handler {
         for (id, channel) in &guild.channels {
             debug!("📺 Channel: name={}, id={}", channel.name, id);
         }
+        {
+            let mut guilds = self.state.channel_guilds.lock().await;
+            for id in guild
+                .channels
+                .keys()
+                .chain(guild.threads.iter().map(|t| &t.id))
+            {
+                guilds.insert(id.get(), guild.id.get());
+            }
+        }
+        if let Some(lang) = I18n::lang_for_discord_locale(&guild.preferred_locale) {
+            let mut locales = self.state.channel_locales.lock().await;
+            for id in guild
+                .channels
+                .keys()
+                .chain(guild.threads.iter().map(|t| &t.id))
+            {
+                locales.insert(id.get(), lang.to_string());
+            }
+        }
+    }
+
+    /// 討論串封存後結束它的 session；重新開啟時會以父頻道的設定開新 session
+    async fn thread_update(
+        &self,
+        _ctx: Context,
+        old: Option<serenity::all::GuildChannel>,
+        new: serenity::all::GuildChannel,
+    ) {
+        let archived = |c: &serenity::all::GuildChannel| {
+            c.thread_metadata.as_ref().is_some_and(|m| m.archived)
+        };
+        if archived(&new) && !old.as_ref().is_some_and(archived) {
+            threads::close_session(&self.state, new.id.get()).await;
+        }
+    }
+
+    async fn thread_delete(
+        &self,
+        _ctx: Context,
+        thread: serenity::all::PartialGuildChannel,
+        _full: Option<serenity::all::GuildChannel>,
+    ) {
+        threads::close_session(&self.state, thread.id.get()).await;
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
@@ -503,53 +2137,261 @@ impl EventHandler for Handler {
             return;
         }
 
-        info!("📩 Message from {}: {}", msg.author.name, msg.content);
-
+        info!("📩 Message from {}: {}", msg.author.name, msg.content);
+
+        // guild_create 之後才建立的頻道或討論串，從快取補上伺服器偏好語言
+        if let Some(guild_id) = msg.guild_id {
+            self.state
+                .channel_guilds
+                .lock()
+                .await
+                .insert(msg.channel_id.get(), guild_id.get());
+            let mut locales = self.state.channel_locales.lock().await;
+            if let std::collections::hash_map::Entry::Vacant(slot) =
+                locales.entry(msg.channel_id.get())
+            {
+                let lang = ctx
+                    .cache
+                    .guild(guild_id)
+                    .and_then(|g| I18n::lang_for_discord_locale(&g.preferred_locale));
+                if let Some(lang) = lang {
+                    slot.insert(lang.to_string());
+                }
+            }
+        }
+
         let user_id = msg.author.id.to_string();
         let (is_auth, mention_only) = self
             .state
-            .auth
-            .is_authorized_with_thread(&ctx, &user_id, msg.channel_id)
+            .auth()
+            .is_authorized_with_thread(&ctx, &user_id, msg.channel_id, msg.guild_id)
             .await;
 
         let channel_id_str = msg.channel_id.to_string();
+        let meta_query = if mentioned && self.state.config.help_on_mention {
+            meta::detect_meta_query(&msg.content)
+        } else {
+            None
+        };
 
         if !is_auth {
             if mentioned {
-                if let Ok(token) = self.state.auth.create_token("channel", &channel_id_str) {
+                if let Ok(token) = self.state.auth().create_token("channel", &channel_id_str) {
                     let auth_msg = {
                         let i18n = self.state.i18n.read().await;
                         i18n.get_args("auth_required_cmd", &[token])
                     };
-                    let _ = msg.reply(&ctx.http, auth_msg).await;
+                    if let Some(query) = meta_query {
+                        self.reply_meta_query(&ctx, &msg, query, mention_only, Some(auth_msg))
+                            .await;
+                    } else {
+                        let _ = msg.reply(&ctx.http, auth_msg).await;
+                    }
                 }
             }
             return;
         }
 
+        // 討論串第一次使用時沿用父頻道的設定 (但開自己的 session)，新頻道套用伺服器預設；
+        // 文字指令與私人 session 都以套用後的設定判斷
+        let channel_config = match msg.guild_id {
+            Some(guild_id) => {
+                let parent = threads::parent_channel(&ctx, guild_id, msg.channel_id)
+                    .await
+                    .map(|p| p.to_string());
+                ChannelConfig::load_resolved(
+                    &channel_id_str,
+                    &guild_id.to_string(),
+                    parent.as_deref(),
+                )
+                .await
+                .unwrap_or_default()
+            }
+            None => ChannelConfig::load().await.unwrap_or_default(),
+        };
+
+        // 文字指令：頻道啟用後不需提及機器人，直接操作頻道而不送給代理
+        if let Some(command) =
+            text_commands::parse(&self.state.config.text_command_prefix, &msg.content)
+        {
+            if text_commands::allowed(&channel_config, &channel_id_str, msg.author.id.get()) {
+                let state = self.state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = text_commands::handle(&ctx, &msg, command, &state).await {
+                        error!("❌ Failed to run text command: {}", e);
+                    }
+                });
+                return;
+            }
+        }
+
         if !should_process_message(false, msg.kind, mention_only, mentioned) {
             return;
         }
 
-        let channel_config = ChannelConfig::load().await.unwrap_or_default();
+        // 私人 session：其他人的訊息不送給代理，每人只提示一次
+        if let Some(private) = channel_config.private_session(&channel_id_str) {
+            if !private.allows(msg.author.id.get()) {
+                let first = self
+                    .state
+                    .private_notices
+                    .lock()
+                    .await
+                    .insert((msg.channel_id.get(), msg.author.id.get()));
+                if first {
+                    let note = self
+                        .state
+                        .i18n
+                        .read()
+                        .await
+                        .get_args("session_private_ignored", &[private.mentions()]);
+                    let _ = msg.reply(&ctx.http, note).await;
+                }
+                return;
+            }
+        }
+
+        // 防洗版冷卻中：不回應，只在冷卻開始後第一則訊息提示一次
+        if let flood::FloodCheck::CoolingDown { remaining, notify } = self
+            .state
+            .flood
+            .check(msg.channel_id.get(), std::time::Instant::now())
+            .await
+        {
+            info!(
+                "🧊 Channel {} is cooling down for {}s; ignoring message",
+                msg.channel_id,
+                remaining.as_secs()
+            );
+            if notify {
+                let until = chrono::Utc::now().timestamp() + remaining.as_secs() as i64;
+                let note = self
+                    .state
+                    .i18n
+                    .read()
+                    .await
+                    .get_args("flood_cooling_down", &[until.to_string()]);
+                let _ = msg.channel_id.say(&ctx.http, note).await;
+            }
+            return;
+        }
+
+        if let Some(query) = meta_query {
+            self.reply_meta_query(&ctx, &msg, query, mention_only, None)
+                .await;
+            return;
+        }
+
+        // 速率限制：超過時回覆重試時間，訊息不進入佇列
+        let (active_turns, channel_busy) = {
+            let renders = self.state.active_renders.lock().await;
+            (renders.len(), renders.contains_key(&msg.channel_id.get()))
+        };
+        let rate = self
+            .state
+            .rate_limiter
+            .check(
+                msg.author.id.get(),
+                active_turns,
+                channel_busy,
+                std::time::Instant::now(),
+            )
+            .await;
+        let limited = {
+            let i18n = self.state.channel_i18n(msg.channel_id.get()).await;
+            let i18n = i18n.read().await;
+            rate_limit::limited_embed(&i18n, rate)
+        };
+        if let Some(embed) = limited {
+            info!(
+                "🚦 Rate limited {} in channel {}: {:?}",
+                msg.author.name, msg.channel_id, rate
+            );
+            let _ = msg
+                .channel_id
+                .send_message(
+                    &ctx.http,
+                    CreateMessage::new().embed(embed).reference_message(&msg),
+                )
+                .await;
+            return;
+        }
+
         let agent_type = channel_config.get_agent_type(&channel_id_str);
-        let files = self
+        // 回覆先前訊息時，被回覆訊息的附件也一併提供給代理
+        let mut attachments = msg.attachments.clone();
+        if let Some(referenced) = &msg.referenced_message {
+            attachments.extend(referenced.attachments.iter().cloned());
+        }
+        let staged = self
             .state
             .upload_manager
-            .stage_attachments(msg.channel_id.get(), &msg.attachments)
+            .stage_attachments(msg.channel_id.get(), &attachments)
             .await;
+        if !staged.rejected.is_empty() {
+            let notice = {
+                let i18n = self.state.channel_i18n(msg.channel_id.get()).await;
+                let i18n = i18n.read().await;
+                self.state
+                    .upload_manager
+                    .rejection_notice(&i18n, &staged.rejected)
+            };
+            if let Err(e) = msg.reply(&ctx.http, notice).await {
+                warn!("⚠️ Failed to report skipped attachments: {}", e);
+            }
+        }
+        let bot_id = ctx.cache.current_user().id;
         let input = UserInput {
-            text: msg.content.clone(),
-            files,
+            text: reply_context::prompt_with_reply(
+                &msg,
+                bot_id,
+                self.state.config.strict_quote_sanitization,
+            ),
+            files: staged.files,
+            requester: Some(msg.author.id.get()),
+            quick: false,
+            on_behalf_of: None,
+            disabled_tools: Vec::new(),
+            continues: None,
+            source_message: Some(msg.id.get()),
+            priority: self
+                .state
+                .config
+                .priority
+                .is_priority(Some(msg.author.id.get()), author_is_admin(&ctx, &msg).await),
         };
+        let faq_lookup = self.state.faq_cache.is_enabled()
+            && input.files.is_empty()
+            && msg.referenced_message.is_none()
+            && !input.text.trim().is_empty()
+            && channel_config
+                .channels
+                .get(&channel_id_str)
+                .is_some_and(|e| e.faq_cache);
 
         let state = self.state.clone();
         tokio::spawn(async move {
-            match state
-                .session_manager
-                .get_or_create_session(msg.channel_id.get(), agent_type, &state.backend_manager)
-                .await
-            {
+            if faq_lookup {
+                if let Some(hit) = state
+                    .faq_cache
+                    .lookup(msg.channel_id.get(), &input.text)
+                    .await
+                {
+                    let reply = {
+                        let i18n = state.i18n.read().await;
+                        faq_cache::hit_message(&i18n, &hit, msg.id)
+                    };
+                    if let Err(e) = msg
+                        .channel_id
+                        .send_message(&ctx.http, reply.reference_message(&msg))
+                        .await
+                    {
+                        warn!("⚠️ Failed to send cached answer: {}", e);
+                    }
+                    return;
+                }
+            }
+            match Handler::open_session(&state, msg.channel_id.get(), agent_type).await {
                 Ok((agent, is_new)) => {
                     Handler::start_agent_loop(
                         agent,
@@ -558,10 +2400,23 @@ impl EventHandler for Handler {
                         state,
                         Some(input),
                         is_new,
+                        None,
                     )
                     .await;
                 }
                 Err(e) => {
+                    if let Some(unavailable) = e.downcast_ref::<circuit::BackendUnavailable>() {
+                        info!("🔌 {} on channel {}", unavailable, msg.channel_id);
+                        let note = state.i18n.read().await.get_args(
+                            "backend_unavailable",
+                            &[
+                                unavailable.backend.clone(),
+                                unavailable.retry_at.to_string(),
+                            ],
+                        );
+                        let _ = msg.reply(&ctx.http, note).await;
+                        return;
+                    }
                     error!("❌ Session error: {}", e);
                     let err_text = e.to_string();
                     let channel_config = ChannelConfig::load().await.unwrap_or_default();
@@ -588,11 +2443,15 @@ impl EventHandler for Handler {
             let user_id = command.user.id.to_string();
             let (is_auth, _) = self
                 .state
-                .auth
-                .is_authorized_with_thread(&ctx, &user_id, command.channel_id)
+                .auth()
+                .is_authorized_with_thread(&ctx, &user_id, command.channel_id, command.guild_id)
                 .await;
 
-            if !is_auth {
+            // 管理員可在尚未授權的新頻道執行 /session adopt 搬回舊頻道授權，
+            // 或以 /guild_config 申請整個伺服器的授權
+            let admin_bootstrap = matches!(command.data.name.as_str(), "session" | "guild_config")
+                && commands::is_admin(command.member.as_deref(), command.guild_id.is_some());
+            if !is_auth && !admin_bootstrap {
                 let not_auth_msg = {
                     let i18n = self.state.i18n.read().await;
                     i18n.get("mention_not_auth")
@@ -609,6 +2468,17 @@ impl EventHandler for Handler {
                     .await;
                 return;
             }
+            if let (true, Some(guild_id)) = (is_auth, command.guild_id) {
+                let parent = threads::parent_channel(&ctx, guild_id, command.channel_id)
+                    .await
+                    .map(|p| p.to_string());
+                let _ = ChannelConfig::load_resolved(
+                    &command.channel_id.to_string(),
+                    &guild_id.to_string(),
+                    parent.as_deref(),
+                )
+                .await;
+            }
 
             let cmd_name = command.data.name.clone();
             let state = self.state.clone();
@@ -621,6 +2491,17 @@ impl EventHandler for Handler {
                     }
                 }
             });
+        } else if let Interaction::Autocomplete(autocomplete) = interaction {
+            if autocomplete.data.name == "model" {
+                let state = self.state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        commands::model::handle_autocomplete(&ctx, &autocomplete, &state).await
+                    {
+                        error!("❌ Model autocomplete failed: {}", e);
+                    }
+                });
+            }
         } else if let Interaction::Modal(modal) = interaction {
             let custom_id = modal.data.custom_id.as_str();
             match route_modal(custom_id) {
@@ -638,6 +2519,14 @@ impl EventHandler for Handler {
                                 .await;
                     });
                 }
+                ModalRoute::ProviderLogin => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        let _ =
+                            commands::provider::handle_provider_modal_submit(&ctx, &modal, &state)
+                                .await;
+                    });
+                }
                 ModalRoute::Ignore => {}
             }
         } else if let Interaction::Component(component) = interaction {
@@ -665,12 +2554,8 @@ impl EventHandler for Handler {
                         let agent_type = channel_config.get_agent_type(&channel_id_str);
 
                         if let Ok((agent, _)) = state
-                            .session_manager
-                            .get_or_create_session(
-                                component.channel_id.get(),
-                                agent_type,
-                                &state.backend_manager,
-                            )
+                            .sessions()
+                            .get_or_create_session(component.channel_id.get(), agent_type)
                             .await
                         {
                             let _ = commands::model::handle_model_select(
@@ -680,6 +2565,97 @@ impl EventHandler for Handler {
                         }
                     });
                 }
+                ComponentRoute::ModelReplay => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) =
+                            commands::model::handle_model_replay(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to replay last prompt: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::Welcome => {
+                    if let Err(e) =
+                        welcome::handle_welcome_button(&ctx, &component, &self.state).await
+                    {
+                        error!("❌ Failed to answer welcome button: {}", e);
+                    }
+                }
+                ComponentRoute::ToolOutput => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) =
+                            tool_outputs::handle_show_button(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to show full tool output: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::FaqRegenerate => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = faq_cache::handle_regenerate(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to regenerate cached answer: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::TurnRetry => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = retry::handle_retry(&ctx, &component, &state).await {
+                            error!("❌ Failed to retry turn: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::TurnContinue => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) =
+                            continuation::handle_continue(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to continue truncated answer: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::TurnAbort => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = turn_controls::handle_abort(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to abort turn: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::TurnRegenerate => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) =
+                            turn_controls::handle_regenerate(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to regenerate turn: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::ToolApproval => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tool_approval::handle_button(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to handle tool approval: {}", e);
+                        }
+                    });
+                }
+                ComponentRoute::QueueJournal => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = queue_journal::handle_button(&ctx, &component, &state).await
+                        {
+                            error!("❌ Failed to handle recovered prompts: {}", e);
+                        }
+                    });
+                }
                 ComponentRoute::Ignore => {}
             }
         }
@@ -689,52 +2665,105 @@ impl EventHandler for Handler {
 async fn run_bot() -> anyhow::Result<()> {
     migrate::run_migrations().await?;
     let config = Arc::new(Config::load().await?);
-    let cron_manager = Arc::new(CronManager::new().await?);
+    I18n::warn_missing_keys();
+    chaos::install(&config.chaos);
+    storage::install(Arc::new(
+        storage::LocalStorage::new(migrate::get_base_dir()),
+    ));
+    agent::install_enabled_backends(config.enabled_backends.as_deref());
+    let services = Arc::new(services::Services::wire(config.clone()).await?);
     let (queued_loop_tx, mut queued_loop_rx) = mpsc::unbounded_channel::<QueuedLoopRequest>();
-    if let Err(e) = cron_manager.load_from_disk().await {
-        error!("❌ Failed to load cron jobs from disk: {}", e);
+    let i18n = Arc::new(RwLock::new(I18n::new(&config.language)));
+    let tool_approval = Arc::new(tool_approval::ToolApproval::new(
+        std::time::Duration::from_secs(config.tool_approval.timeout_secs),
+        i18n.clone(),
+    ));
+    if config.tool_approval.mode == agent::approval::ToolApprovalMode::Ask {
+        info!("🔐 Tool calls require approval in the channel");
+        agent::approval::install(tool_approval.clone());
     }
     let state = Arc::new(AppState {
+        started_at: std::time::Instant::now(),
+        queue_journal: Arc::new(queue_journal::QueueJournal::load().await),
+        voice: Arc::new(voice::Voice::new(config.voice.clone())),
+        services,
+        tool_approval,
+        shutdown: Arc::new(shutdown::Shutdown::default()),
         config: config.clone(),
-        session_manager: Arc::new(SessionManager::new(config.clone())),
-        auth: Arc::new(AuthManager::new()),
-        i18n: Arc::new(RwLock::new(I18n::new(&config.language))),
-        backend_manager: Arc::new(agent::manager::BackendManager::new(config.clone())),
-        cron_manager,
+        i18n,
         active_renders: Arc::new(Mutex::new(HashMap::new())),
         pending_inputs: Arc::new(Mutex::new(HashMap::new())),
         queued_loop_tx,
-        upload_manager: Arc::new(UploadManager::new(
-            20 * 1024 * 1024,
-            std::time::Duration::from_secs(24 * 60 * 60),
-            std::time::Duration::from_secs(10 * 60),
-        )?),
+        upload_manager: Arc::new(
+            UploadManager::new(
+                storage::get(),
+                config.uploads.max_file_bytes,
+                std::time::Duration::from_secs(config.uploads.ttl_secs),
+                std::time::Duration::from_secs(10 * 60),
+                config.attachment_cache,
+            )
+            .with_mime_allowlist(config.uploads.allowed_mime.clone())
+            .with_channel_quota(config.uploads.channel_quota_bytes),
+        ),
+        last_turns: Arc::new(Mutex::new(HashMap::new())),
+        outbox: Arc::new(Outbox::new()),
+        channel_locales: Arc::new(Mutex::new(HashMap::new())),
+        channel_guilds: Arc::new(Mutex::new(HashMap::new())),
+        analytics: Arc::new(AnalyticsSink::new(
+            migrate::get_analytics_dir(),
+            &config.analytics,
+        )),
+        metrics: Arc::new(metrics::Metrics::load().await),
+        flood: Arc::new(flood::FloodGuard::new(config.flood.clone())),
+        rate_limiter: Arc::new(rate_limit::RateLimiter::new(config.limits.clone())),
+        private_notices: Arc::new(Mutex::new(std::collections::HashSet::new())),
+        quiet_queue: Arc::new(quiet::QuietQueue::new()),
+        circuit: Arc::new(circuit::CircuitBreaker::new(config.circuit_breaker.clone())),
+        faq_cache: Arc::new(faq_cache::FaqCache::new(config.faq_cache.clone())),
+        partial_answers: Arc::new(Mutex::new(HashMap::new())),
+        pipes: Arc::new(pipe::Pipes::default()),
+        turn_limiter: Arc::new(turn_limit::TurnLimiter::new(
+            config.max_concurrent_turns.clone(),
+        )),
+        render_scheduler: Arc::new(render_scheduler::RenderScheduler::new(
+            config.composer.max_concurrent_edits,
+        )),
     });
-    let mut client = Client::builder(
-        &state.config.discord_token,
-        GatewayIntents::GUILD_MESSAGES
-            | GatewayIntents::MESSAGE_CONTENT
-            | GatewayIntents::GUILDS
-            | GatewayIntents::DIRECT_MESSAGES,
-    )
-    .event_handler(Handler {
-        state: (*state).clone(),
-    })
-    .await?;
+    let mut tokens = token_failover::candidates(&config).into_iter();
+    let primary = tokens.next().unwrap_or_default();
+    let mut client = build_client(&primary, &state).await?;
+    // 背景工作透過 watch 取得目前連線的 Http，備用 token 接手後自動改用新的
+    let (http_tx, http_rx) = tokio::sync::watch::channel(client.http.clone());
+    state.tool_approval.attach(http_rx.clone());
 
     let queue_state = state.clone();
-    let queue_http = client.http.clone();
+    let queue_http_rx = http_rx.clone();
     tokio::spawn(async move {
+        let http = queue_http_rx.borrow().clone();
+        queue_journal::announce_recovered(&queue_state, &http).await;
         while let Some((channel_id_u64, input)) = queued_loop_rx.recv().await {
+            let queue_http = queue_http_rx.borrow().clone();
+            if queue_state.shutdown.is_draining() {
+                // 停止中：放回佇列紀錄，重啟後確認重送
+                queue_state
+                    .pending_inputs
+                    .lock()
+                    .await
+                    .entry(channel_id_u64)
+                    .or_default()
+                    .retry(input);
+                queue_state.sync_queue_journal(channel_id_u64).await;
+                continue;
+            }
+            queue_state
+                .queue_journal
+                .start(channel_id_u64, &input)
+                .await;
             let channel_id = serenity::model::id::ChannelId::from(channel_id_u64);
             let channel_id_str = channel_id.to_string();
             let channel_config = ChannelConfig::load().await.unwrap_or_default();
             let agent_type = channel_config.get_agent_type(&channel_id_str);
-            match queue_state
-                .session_manager
-                .get_or_create_session(channel_id_u64, agent_type, &queue_state.backend_manager)
-                .await
-            {
+            match Handler::open_session(&queue_state, channel_id_u64, agent_type).await {
                 Ok((agent, is_new)) => {
                     Handler::start_agent_loop(
                         agent,
@@ -743,69 +2772,86 @@ async fn run_bot() -> anyhow::Result<()> {
                         (*queue_state).clone(),
                         Some(input),
                         is_new,
+                        None,
                     )
                     .await;
                 }
-                Err(e) => error!("❌ Failed to run queued input: {}", e),
+                Err(e) => {
+                    error!("❌ Failed to run queued input: {}", e);
+                    queue_state.queue_journal.finish(channel_id_u64).await;
+                    if let Some(unavailable) = e.downcast_ref::<circuit::BackendUnavailable>() {
+                        let note = queue_state.i18n.read().await.get_args(
+                            "backend_unavailable",
+                            &[
+                                unavailable.backend.clone(),
+                                unavailable.retry_at.to_string(),
+                            ],
+                        );
+                        let _ = channel_id.say(&queue_http, note).await;
+                    }
+                }
             }
         }
     });
 
-    // 初始化 CronManager 的執行環境
+    Arc::clone(&state.quiet_queue).spawn_release_loop(http_rx.clone(), state.i18n.clone());
+    retention::spawn_purge_loop(state.clone());
+    model_watch::spawn_watch_loop(state.clone(), http_rx.clone());
+    metrics::spawn_summary_loop(state.clone(), http_rx);
+    downloads::install(state.config.downloads.clone());
+    prometheus::spawn_server(state.clone());
+
+    // 初始化排程的執行環境
     state
-        .cron_manager
+        .scheduler()
         .init(client.http.clone(), Arc::downgrade(&state))
         .await;
 
-    client.start().await?;
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::load_all_prompts;
-    use crate::migrate::{get_prompts_dir, BASE_DIR_ENV};
-    use std::sync::{Mutex, OnceLock};
-    use tempfile::tempdir;
-
-    fn env_lock() -> &'static Mutex<()> {
-        static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-        LOCK.get_or_init(|| Mutex::new(()))
-    }
-
-    #[test]
-    fn test_load_all_prompts_creates_defaults_when_empty() {
-        let _guard = env_lock().lock().expect("lock");
-        let dir = tempdir().expect("tempdir");
-        // SAFETY: serialized by env lock
-        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
-
-        let out = load_all_prompts();
-        assert!(!out.trim().is_empty());
-        assert!(dir.path().join("prompts").exists());
-
-        // SAFETY: serialized by env lock
-        unsafe { std::env::remove_var(BASE_DIR_ENV) };
+    loop {
+        let result = tokio::select! {
+            result = client.start() => result,
+            _ = shutdown::signal() => {
+                shutdown::drain(&state).await;
+                client.shard_manager.shutdown_all().await;
+                info!("👋 Shutdown complete");
+                return Ok(());
+            }
+        };
+        let Err(e) = result else {
+            return Ok(());
+        };
+        // 主要 token 被撤銷時改用備用 token，讓服務在更換 token 期間繼續運作
+        let backup = match tokens.next() {
+            Some(backup) if token_failover::is_invalid_token(&e) => backup,
+            _ => return Err(e.into()),
+        };
+        error!("❌ Discord rejected the bot token; failing over to the backup token");
+        client.shard_manager.shutdown_all().await;
+        client = build_client(&backup, &state).await?;
+        http_tx.send_replace(client.http.clone());
+        state.scheduler().set_http(client.http.clone()).await;
+        token_failover::alert_admins(&state, &client.http).await;
     }
+}
 
-    #[test]
-    fn test_load_all_prompts_reads_existing_files_sorted() {
-        let _guard = env_lock().lock().expect("lock");
-        let dir = tempdir().expect("tempdir");
-        // SAFETY: serialized by env lock
-        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
-
-        let prompts_dir = get_prompts_dir();
-        std::fs::create_dir_all(&prompts_dir).expect("create prompts dir");
-        std::fs::write(prompts_dir.join("b.md"), "B").expect("write b");
-        std::fs::write(prompts_dir.join("a.md"), "A").expect("write a");
-
-        let out = load_all_prompts();
-        assert_eq!(out, "A\n\nB");
-
-        // SAFETY: serialized by env lock
-        unsafe { std::env::remove_var(BASE_DIR_ENV) };
-    }
+async fn build_client(token: &str, state: &AppState) -> anyhow::Result<Client> {
+    let builder = Client::builder(
+        token,
+        GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::MESSAGE_CONTENT
+            | GatewayIntents::GUILDS
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::GUILD_VOICE_STATES,
+    )
+    .event_handler(Handler {
+        state: state.clone(),
+    });
+    #[cfg(feature = "voice")]
+    let builder = {
+        use songbird::SerenityInit;
+        builder.register_songbird_with(state.voice.songbird())
+    };
+    Ok(builder.await?)
 }
 
 #[tokio::main]
@@ -815,6 +2861,53 @@ async fn main() -> anyhow::Result<()> {
     match cli.command {
         Some(Commands::Run) => run_bot().await?,
         Some(Commands::Version) => println!("v{}", env!("CARGO_PKG_VERSION")),
+        Some(Commands::Auth { token }) => {
+            let auth = AuthManager::new();
+            let (type_, id) = auth.redeem_token(&token)?;
+            println!("✅ Authorized {} {}", type_, id);
+            if let Err(e) = welcome::post_after_auth(&auth, &type_, &id).await {
+                eprintln!("⚠️ Failed to post welcome message: {}", e);
+            }
+        }
+        Some(Commands::Fsck {
+            repair,
+            yes,
+            offline,
+        }) => {
+            let found = fsck::run(fsck::Options {
+                repair,
+                assume_yes: yes,
+                offline,
+            })
+            .await?;
+            // 只檢查不修復時以結束碼回報，方便排程監控
+            if found > 0 && !repair {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::ExportBundle { output }) => {
+            let output = output.unwrap_or_else(bundle::default_output);
+            let manifest = bundle::export(&migrate::get_base_dir(), &output)?;
+            println!(
+                "✅ Exported {} file(s) to {}",
+                manifest.entries.len(),
+                output.display()
+            );
+            println!(
+                "⚠️ The bundle contains your Discord token and authorizations; keep it private."
+            );
+        }
+        Some(Commands::ImportBundle { path, force }) => {
+            let base_dir = migrate::get_base_dir();
+            let manifest = bundle::import(&path, &base_dir, force)?;
+            println!(
+                "✅ Imported {} file(s) from v{} into {}",
+                manifest.entries.len(),
+                manifest.app_version,
+                base_dir.display()
+            );
+        }
+        Some(Commands::Migrate { dry_run, backup }) => migrate::run_cli(dry_run, backup).await?,
         Some(Commands::Daemon { action }) => {
             let service_path = get_systemd_service_path()?;
 
@@ -860,9 +2953,81 @@ async fn main() -> anyhow::Result<()> {
                     }
                     println!("🛑 Daemon disabled and service file removed.");
                 }
+                DaemonAction::Generate {
+                    target,
+                    output,
+                    force,
+                } => {
+                    let dir = output.unwrap_or_else(|| {
+                        let name = format!("{:?}", target).to_lowercase();
+                        std::path::PathBuf::from(format!("agent-discord-{}", name))
+                    });
+                    let files = service_gen::render(target, &service_gen::HostInfo::detect()?);
+                    for path in service_gen::write(&dir, &files, force)? {
+                        println!("✅ Wrote {}", path.display());
+                    }
+                    println!("   Next: {}", service_gen::next_steps(target, &dir));
+                }
             }
         }
         _ => run_bot().await?,
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{load_all_prompts, should_auto_recover_request_error};
+    use crate::migrate::{get_prompts_dir, BASE_DIR_ENV};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_acp_exit_is_recoverable_for_any_backend() {
+        assert!(should_auto_recover_request_error(
+            "copilot",
+            "copilot ACP exited: exit status: 1"
+        ));
+        assert!(should_auto_recover_request_error(
+            "kilo",
+            "error sending request for url"
+        ));
+        assert!(!should_auto_recover_request_error(
+            "copilot",
+            "connection refused"
+        ));
+    }
+
+    #[test]
+    fn test_load_all_prompts_creates_defaults_when_empty() {
+        let _guard = crate::migrate::env_lock();
+        let dir = tempdir().expect("tempdir");
+        // SAFETY: serialized by env lock
+        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
+
+        let out = load_all_prompts();
+        assert!(!out.trim().is_empty());
+        assert!(dir.path().join("prompts").exists());
+
+        // SAFETY: serialized by env lock
+        unsafe { std::env::remove_var(BASE_DIR_ENV) };
+    }
+
+    #[test]
+    fn test_load_all_prompts_reads_existing_files_sorted() {
+        let _guard = crate::migrate::env_lock();
+        let dir = tempdir().expect("tempdir");
+        // SAFETY: serialized by env lock
+        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
+
+        let prompts_dir = get_prompts_dir();
+        std::fs::create_dir_all(&prompts_dir).expect("create prompts dir");
+        std::fs::write(prompts_dir.join("b.md"), "B").expect("write b");
+        std::fs::write(prompts_dir.join("a.md"), "A").expect("write a");
+
+        let out = load_all_prompts();
+        assert_eq!(out, "A\n\nB");
+
+        // SAFETY: serialized by env lock
+        unsafe { std::env::remove_var(BASE_DIR_ENV) };
+    }
+}
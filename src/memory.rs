@@ -0,0 +1,203 @@
+use crate::agent::AiAgent;
+use crate::composer::EmbedComposer;
+use crate::storage::Storage;
+use crate::writer_logic::apply_agent_event;
+use crate::ExecStatus;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
+
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// 頻道記憶檔：壓縮前由代理產生的滾動摘要，壓縮後於下一輪重新注入
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ChannelMemory {
+    pub summary: String,
+    pub updated_at: String,
+    /// 尚未注入到壓縮後的 session
+    #[serde(default)]
+    pub pending_inject: bool,
+    /// 最近一次 /handoff 產生的交接筆記
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffNote>,
+}
+
+/// 交給其他人或其他頻道接手用的筆記
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HandoffNote {
+    pub note: String,
+    /// 最近在頻道發言的使用者 (顯示名稱)，依發言數排序
+    #[serde(default)]
+    pub participants: Vec<String>,
+    pub created_at: String,
+}
+
+fn memory_key(channel_id: u64) -> String {
+    format!("memory/{}.json", channel_id)
+}
+
+async fn load_from(storage: &dyn Storage, channel_id: u64) -> Option<ChannelMemory> {
+    let content = storage.get(&memory_key(channel_id)).await.ok()??;
+    serde_json::from_slice(&content).ok()
+}
+
+async fn save_to(
+    storage: &dyn Storage,
+    channel_id: u64,
+    memory: &ChannelMemory,
+) -> anyhow::Result<()> {
+    storage
+        .put(
+            &memory_key(channel_id),
+            serde_json::to_string_pretty(memory)?.as_bytes(),
+        )
+        .await
+}
+
+pub async fn load(channel_id: u64) -> Option<ChannelMemory> {
+    load_from(crate::storage::get().as_ref(), channel_id).await
+}
+
+pub async fn save(channel_id: u64, memory: &ChannelMemory) -> anyhow::Result<()> {
+    save_to(crate::storage::get().as_ref(), channel_id, memory).await
+}
+
+/// /clear 重新開始時一併忘記摘要
+pub async fn remove(channel_id: u64) {
+    let _ = crate::storage::get().delete(&memory_key(channel_id)).await;
+}
+
+pub fn build_summary_prompt(previous: Option<&str>) -> String {
+    let mut prompt = String::from(
+        "The conversation is about to be compacted. Write a structured summary that lets you \
+         continue this work afterwards. Use these sections:\n\
+         ## Goal\n## Key decisions\n## Open tasks\n## Important facts (files, names, constraints)\n\
+         Be concise. Reply with the summary only and do not use any tools.",
+    );
+    if let Some(prev) = previous.filter(|p| !p.trim().is_empty()) {
+        prompt.push_str("\n\nUpdate and extend this earlier summary instead of dropping it:\n");
+        prompt.push_str(prev.trim());
+    }
+    prompt
+}
+
+pub const HANDOFF_PROMPT: &str = "Write a hand-off note for someone who will take over this work \
+     (another person, or an agent in another channel) without having seen this conversation. \
+     Use these sections:\n\
+     ## Current goal\n## Decisions made\n## Open items\n## Relevant files\n\
+     List files with their paths and why they matter. Be concise. Reply with the note only and \
+     do not use any tools.";
+
+pub fn format_injection(summary: &str) -> String {
+    format!(
+        "[Summary of the conversation before context compaction]\n{}\n[End of summary]",
+        summary.trim()
+    )
+}
+
+/// 請代理產生摘要並收集回答正文
+pub async fn summarize(agent: &dyn AiAgent, previous: Option<&str>) -> anyhow::Result<String> {
+    collect_answer(agent, &build_summary_prompt(previous)).await
+}
+
+/// 請代理產生交接筆記
+pub async fn handoff(agent: &dyn AiAgent) -> anyhow::Result<String> {
+    collect_answer(agent, HANDOFF_PROMPT).await
+}
+
+/// 送出一輪不顯示在頻道的提示並收集回答正文
+async fn collect_answer(agent: &dyn AiAgent, prompt: &str) -> anyhow::Result<String> {
+    let mut rx = agent.subscribe_events();
+    agent.prompt(prompt).await?;
+
+    let mut composer = EmbedComposer::new(usize::MAX);
+    let mut status = ExecStatus::Running;
+    tokio::time::timeout(SUMMARY_TIMEOUT, async {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if apply_agent_event(&mut composer, &mut status, event) {
+                        break;
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("⚠️ Summary collector lagged by {} events", n);
+                }
+                Err(_) => break,
+            }
+        }
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Summary timed out"))?;
+
+    if let ExecStatus::Error(e) = status {
+        anyhow::bail!("Summary failed: {}", e);
+    }
+    let summary = composer.render_answer_text();
+    if summary.trim().is_empty() {
+        anyhow::bail!("Summary was empty");
+    }
+    Ok(summary)
+}
+
+/// 若有待注入的摘要，回傳要加在使用者訊息前的區塊並標記為已注入
+pub async fn take_injection(channel_id: u64) -> Option<String> {
+    let mut memory = load(channel_id).await?;
+    if !memory.pending_inject || memory.summary.trim().is_empty() {
+        return None;
+    }
+    memory.pending_inject = false;
+    if let Err(e) = save(channel_id, &memory).await {
+        warn!(
+            "⚠️ Failed to update memory for channel {}: {}",
+            channel_id, e
+        );
+    }
+    Some(format_injection(&memory.summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockAgent;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_build_summary_prompt_rolls_previous_summary() {
+        let first = build_summary_prompt(None);
+        assert!(first.contains("## Open tasks"));
+        assert!(!first.contains("earlier summary"));
+        let next = build_summary_prompt(Some("## Goal\nship v2"));
+        assert!(next.ends_with("## Goal\nship v2"));
+        assert!(format_injection(" s ").contains("\ns\n"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_roundtrip() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let storage = crate::storage::LocalStorage::new(dir.path());
+        assert!(load_from(&storage, 1).await.is_none());
+        let memory = ChannelMemory {
+            summary: "s".to_string(),
+            updated_at: "now".to_string(),
+            pending_inject: true,
+            handoff: Some(HandoffNote {
+                note: "## Current goal\nship".to_string(),
+                participants: vec!["Alice".to_string()],
+                created_at: "now".to_string(),
+            }),
+        };
+        save_to(&storage, 1, &memory).await?;
+        assert!(dir.path().join("memory").join("1.json").exists());
+        assert_eq!(load_from(&storage, 1).await, Some(memory));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_summarize_collects_answer_text() -> anyhow::Result<()> {
+        let agent = MockAgent::new();
+        let summary = summarize(&agent, None).await?;
+        assert_eq!(summary, "Mock Response");
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{CreateAllowedMentions, RoleId, UserId};
+
+/// 頻道允許代理回覆中提及 (ping) 的使用者與身分組；未列入的提及一律不通知
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MentionPolicy {
+    #[serde(default)]
+    pub users: Vec<u64>,
+    #[serde(default)]
+    pub roles: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mention {
+    User(u64),
+    Role(u64),
+}
+
+impl Mention {
+    pub fn markup(&self) -> String {
+        match self {
+            Self::User(id) => format!("<@{}>", id),
+            Self::Role(id) => format!("<@&{}>", id),
+        }
+    }
+}
+
+/// 依出現順序取出文字中的 `<@id>`、`<@!id>`、`<@&id>` 提及，重複的只保留一次
+pub fn extract(text: &str) -> Vec<Mention> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        rest = &rest[start + 2..];
+        let (role, body) = match rest.strip_prefix('&') {
+            Some(body) => (true, body),
+            None => (false, rest.strip_prefix('!').unwrap_or(rest)),
+        };
+        let Some(end) = body.find('>') else {
+            break;
+        };
+        if let Ok(id) = body[..end].parse::<u64>() {
+            let mention = if role {
+                Mention::Role(id)
+            } else {
+                Mention::User(id)
+            };
+            if !found.contains(&mention) {
+                found.push(mention);
+            }
+        }
+    }
+    found
+}
+
+/// 不通知任何人；代理輸出以一般訊息送出時使用
+pub fn suppressed() -> CreateAllowedMentions {
+    CreateAllowedMentions::new().replied_user(false)
+}
+
+/// 只通知指定的對象；@everyone 與其他提及仍不通知
+pub fn allow_only(mentions: &[Mention]) -> CreateAllowedMentions {
+    let users = mentions.iter().filter_map(|m| match m {
+        Mention::User(id) => Some(UserId::new(*id)),
+        Mention::Role(_) => None,
+    });
+    let roles = mentions.iter().filter_map(|m| match m {
+        Mention::Role(id) => Some(RoleId::new(*id)),
+        Mention::User(_) => None,
+    });
+    suppressed().users(users).roles(roles)
+}
+
+impl MentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.roles.is_empty()
+    }
+
+    /// 回答中提及且在允許名單內的對象
+    pub fn allowed_in(&self, text: &str) -> Vec<Mention> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        extract(text)
+            .into_iter()
+            .filter(|mention| match mention {
+                Mention::User(id) => self.users.contains(id),
+                Mention::Role(id) => self.roles.contains(id),
+            })
+            .collect()
+    }
+
+    /// `/config mentions` 的輸入：貼上要允許的提及，`off` 或空白表示清除
+    pub fn parse(input: &str) -> Self {
+        let mut policy = Self::default();
+        for mention in extract(input) {
+            match mention {
+                Mention::User(id) => policy.users.push(id),
+                Mention::Role(id) => policy.roles.push(id),
+            }
+        }
+        policy
+    }
+
+    pub fn describe(&self) -> String {
+        self.roles
+            .iter()
+            .map(|id| Mention::Role(*id))
+            .chain(self.users.iter().map(|id| Mention::User(*id)))
+            .map(|m| m.markup())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_users_roles_and_nicknames() {
+        let text = "Paging <@&42> and <@!7>, cc <@7> — not <@abc> or <#9> @everyone";
+        assert_eq!(extract(text), vec![Mention::Role(42), Mention::User(7)]);
+        assert!(extract("<@12").is_empty());
+    }
+
+    #[test]
+    fn test_policy_only_allows_listed_mentions() {
+        let policy = MentionPolicy::parse("<@&42> <@100>");
+        assert_eq!(policy.roles, vec![42]);
+        assert_eq!(policy.users, vec![100]);
+        assert_eq!(policy.describe(), "<@&42> <@100>");
+
+        let answer = "Incident summary for <@&42>; escalated by <@200>, fyi <@100> <@&43>";
+        assert_eq!(
+            policy.allowed_in(answer),
+            vec![Mention::Role(42), Mention::User(100)]
+        );
+        assert!(MentionPolicy::default().allowed_in(answer).is_empty());
+        assert!(MentionPolicy::parse("off").is_empty());
+    }
+}
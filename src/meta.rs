@@ -0,0 +1,114 @@
+use serenity::all::{CreateEmbed, CreateMessage};
+
+use crate::agent::AgentType;
+use crate::i18n::I18n;
+
+/// 不需經過 LLM 即可回答的詢問
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaQuery {
+    Help,
+    Backend,
+    Commands,
+}
+
+/// 去除提及標記與結尾標點後比對固定詞組；一般對話一律回傳 None
+pub fn detect_meta_query(content: &str) -> Option<MetaQuery> {
+    let text = content
+        .split_whitespace()
+        .filter(|word| !(word.starts_with("<@") && word.ends_with('>')))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['?', '？', '!', '！', '.', '。'])
+        .trim()
+        .to_lowercase();
+
+    match text.as_str() {
+        "help" | "/help" | "what can you do" | "how do i use you" | "幫助" | "說明"
+        | "你能做什麼" | "你會做什麼" => Some(MetaQuery::Help),
+        "backend" | "which backend" | "what backend" | "後端" | "目前後端" => {
+            Some(MetaQuery::Backend)
+        }
+        "commands" | "list commands" | "指令" | "指令列表" => Some(MetaQuery::Commands),
+        _ => None,
+    }
+}
+
+/// 功能卡片；未授權時附上授權指引
+pub fn build_capability_card(
+    i18n: &I18n,
+    query: MetaQuery,
+    assistant_name: &str,
+    backend: &AgentType,
+    mention_only: bool,
+    auth_hint: Option<String>,
+) -> CreateMessage {
+    let mut embed = CreateEmbed::new().color(0x5865F2);
+    embed = match query {
+        MetaQuery::Help => embed
+            .title(i18n.get_args("meta_help_title", &[assistant_name.to_string()]))
+            .description(i18n.get("meta_help_intro"))
+            .field(
+                i18n.get("meta_field_backend"),
+                format!("`{}`", backend),
+                true,
+            )
+            .field(
+                i18n.get("meta_field_mention_only"),
+                format!("`{}`", mention_only),
+                true,
+            )
+            .field(
+                i18n.get("meta_field_commands"),
+                crate::welcome::command_list(i18n),
+                false,
+            )
+            .field(
+                i18n.get("meta_field_examples"),
+                i18n.get("meta_examples"),
+                false,
+            ),
+        MetaQuery::Backend => embed
+            .title(i18n.get("meta_field_backend"))
+            .description(i18n.get_args("meta_backend_current", &[backend.to_string()])),
+        MetaQuery::Commands => embed
+            .title(i18n.get("meta_field_commands"))
+            .description(crate::welcome::command_list(i18n)),
+    };
+    if let Some(hint) = auth_hint {
+        embed = embed.field(i18n.get("meta_field_auth"), hint, false);
+    }
+    CreateMessage::new().embed(embed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_meta_query, MetaQuery};
+
+    #[test]
+    fn test_detect_meta_query_strips_mentions_and_punctuation() {
+        assert_eq!(detect_meta_query("<@123> help"), Some(MetaQuery::Help));
+        assert_eq!(detect_meta_query("<@!123>  Help?"), Some(MetaQuery::Help));
+        assert_eq!(
+            detect_meta_query("<@123> 你能做什麼？"),
+            Some(MetaQuery::Help)
+        );
+        assert_eq!(
+            detect_meta_query("which backend <@123>"),
+            Some(MetaQuery::Backend)
+        );
+        assert_eq!(
+            detect_meta_query("<@1> commands"),
+            Some(MetaQuery::Commands)
+        );
+    }
+
+    #[test]
+    fn test_detect_meta_query_ignores_normal_prompts() {
+        assert_eq!(detect_meta_query("<@123> help me fix this bug"), None);
+        assert_eq!(
+            detect_meta_query("what backend should I use for rust?"),
+            None
+        );
+        assert_eq!(detect_meta_query("<@123>"), None);
+    }
+}
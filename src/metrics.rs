@@ -0,0 +1,420 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, Http};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tracing::{info, warn};
+
+use crate::config::MetricsConfig;
+use crate::i18n::I18n;
+
+/// 每日統計保留的天數
+const DAYS_KEPT: i64 = 31;
+/// 每日摘要列出的頻道數
+const SUMMARY_TOP_CHANNELS: usize = 5;
+
+/// 累計的使用次數；token 只計入後端有回報用量的回合
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counters {
+    #[serde(default)]
+    pub prompts: u64,
+    #[serde(default)]
+    pub completions: u64,
+    #[serde(default)]
+    pub errors: u64,
+    #[serde(default)]
+    pub tool_calls: u64,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+}
+
+/// 一個結束的回合
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurnOutcome {
+    pub channel_id: u64,
+    /// 排程等系統觸發的回合沒有使用者
+    pub user_id: Option<u64>,
+    pub success: bool,
+    pub tool_calls: u64,
+    /// 後端回報的 (輸入, 輸出) token 數
+    pub tokens: Option<(u64, u64)>,
+}
+
+impl Counters {
+    fn add(&mut self, turn: &TurnOutcome) {
+        self.prompts += 1;
+        if turn.success {
+            self.completions += 1;
+        } else {
+            self.errors += 1;
+        }
+        self.tool_calls += turn.tool_calls;
+        if let Some((input, output)) = turn.tokens {
+            self.input_tokens += input;
+            self.output_tokens += output;
+        }
+    }
+}
+
+/// 單日 (本地時間) 的總計與各頻道統計
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DayStats {
+    #[serde(default)]
+    pub total: Counters,
+    #[serde(default)]
+    pub channels: HashMap<String, Counters>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct MetricsStore {
+    #[serde(default)]
+    channels: HashMap<String, Counters>,
+    #[serde(default)]
+    users: HashMap<String, Counters>,
+    /// 使用者最後一次有回合的日期 (`YYYY-MM-DD`)；`[retention] usage_days` 依此清除 `users`
+    #[serde(default)]
+    users_seen: HashMap<String, String>,
+    /// `YYYY-MM-DD` → 當日統計，只保留最近 DAYS_KEPT 天
+    #[serde(default)]
+    days: BTreeMap<String, DayStats>,
+}
+
+fn day_key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// 各頻道與使用者的累計次數，另保留每日統計供 /stats 與每日摘要使用
+pub struct Metrics {
+    path: PathBuf,
+    store: Mutex<MetricsStore>,
+}
+
+impl Metrics {
+    pub async fn load() -> Self {
+        Self::load_from(crate::migrate::get_metrics_path()).await
+    }
+
+    pub async fn load_from(path: PathBuf) -> Self {
+        let store = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("⚠️ Metrics file is corrupt, starting empty: {}", e);
+                MetricsStore::default()
+            }),
+            Err(_) => MetricsStore::default(),
+        };
+        Self {
+            path,
+            store: Mutex::new(store),
+        }
+    }
+
+    /// 先寫入暫存檔再改名，寫到一半中斷時不會留下損毀的統計檔
+    async fn write(&self, store: &MetricsStore) -> anyhow::Result<()> {
+        let content = serde_json::to_string_pretty(store)?;
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, content).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+
+    async fn persist(&self, store: &MetricsStore) {
+        if let Err(e) = self.write(store).await {
+            warn!("⚠️ Failed to save metrics: {}", e);
+        }
+    }
+
+    /// 寫入失敗只記錄警告，不影響對話流程
+    pub async fn record(&self, turn: TurnOutcome) {
+        self.record_on(turn, Local::now().date_naive()).await;
+    }
+
+    async fn record_on(&self, turn: TurnOutcome, today: NaiveDate) {
+        let mut store = self.store.lock().await;
+        let channel = turn.channel_id.to_string();
+        store
+            .channels
+            .entry(channel.clone())
+            .or_default()
+            .add(&turn);
+        if let Some(user_id) = turn.user_id {
+            store
+                .users
+                .entry(user_id.to_string())
+                .or_default()
+                .add(&turn);
+            store.users_seen.insert(user_id.to_string(), day_key(today));
+        }
+        let day = store.days.entry(day_key(today)).or_default();
+        day.total.add(&turn);
+        day.channels.entry(channel).or_default().add(&turn);
+        let oldest = day_key(today - Duration::days(DAYS_KEPT - 1));
+        store.days.retain(|key, _| *key >= oldest);
+        self.persist(&store).await;
+    }
+
+    pub async fn channel(&self, channel_id: u64) -> Counters {
+        let store = self.store.lock().await;
+        store
+            .channels
+            .get(&channel_id.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub async fn user(&self, user_id: u64) -> Counters {
+        let store = self.store.lock().await;
+        store
+            .users
+            .get(&user_id.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 刪除 `cutoff` 當日之前就沒有再使用的使用者統計，回傳刪除的人數；
+    /// 頻道與每日統計不含個人資料，不在此清除
+    pub async fn purge_users_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<usize> {
+        let cutoff = day_key(cutoff.with_timezone(&Local).date_naive());
+        let mut store = self.store.lock().await;
+        let before = store.users.len();
+        let MetricsStore {
+            users, users_seen, ..
+        } = &mut *store;
+        users.retain(|id, _| users_seen.get(id).is_some_and(|day| *day >= cutoff));
+        users_seen.retain(|id, _| users.contains_key(id));
+        let removed = before - store.users.len();
+        if removed > 0 {
+            self.write(&store).await?;
+        }
+        Ok(removed)
+    }
+
+    pub async fn day(&self, date: NaiveDate) -> DayStats {
+        let store = self.store.lock().await;
+        store.days.get(&day_key(date)).cloned().unwrap_or_default()
+    }
+}
+
+/// 一組統計的兩行文字：次數與 token 數
+pub fn format_counters(i18n: &I18n, counters: &Counters) -> String {
+    i18n.get_args(
+        "stats_counters",
+        &[
+            i18n.format_number(counters.prompts),
+            i18n.format_number(counters.completions),
+            i18n.format_number(counters.errors),
+            i18n.format_number(counters.tool_calls),
+            i18n.format_number(counters.input_tokens),
+            i18n.format_number(counters.output_tokens),
+        ],
+    )
+}
+
+/// 每日摘要：當日總計與提示數最多的幾個頻道
+pub fn summary_embed(i18n: &I18n, date: NaiveDate, day: &DayStats) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(i18n.get_args("stats_summary_title", &[day_key(date)]))
+        .color(0x5865F2)
+        .field(
+            i18n.get("stats_total"),
+            format_counters(i18n, &day.total),
+            false,
+        );
+    let mut channels: Vec<(&String, &Counters)> = day.channels.iter().collect();
+    channels.sort_by(|a, b| b.1.prompts.cmp(&a.1.prompts).then(a.0.cmp(b.0)));
+    let top = channels
+        .iter()
+        .take(SUMMARY_TOP_CHANNELS)
+        .map(|(id, c)| {
+            i18n.get_args(
+                "stats_summary_channel",
+                &[
+                    format!("<#{}>", id),
+                    i18n.format_number(c.prompts),
+                    i18n.format_number(c.errors),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    if !top.is_empty() {
+        embed = embed.field(i18n.get("stats_summary_top"), top.join("\n"), false);
+    }
+    embed
+}
+
+/// `now` 之後下一個本地時間 `hour` 點整
+pub fn next_summary_at<Tz: TimeZone>(now: &DateTime<Tz>, hour: u32) -> DateTime<Tz> {
+    let hour = hour.min(23);
+    let mut date = now.date_naive();
+    loop {
+        let at = date
+            .and_hms_opt(hour, 0, 0)
+            .and_then(|t| now.timezone().from_local_datetime(&t).earliest());
+        match at {
+            Some(at) if at > *now => return at,
+            _ => date += Duration::days(1),
+        }
+    }
+}
+
+/// 每天在 `summary_hour` 把前一天的統計送到摘要頻道；summary_channel 為 0 時不啟用
+pub fn spawn_summary_loop(state: Arc<crate::AppState>, http: watch::Receiver<Arc<Http>>) {
+    let MetricsConfig {
+        summary_channel,
+        summary_hour,
+        ..
+    } = state.config.metrics.clone();
+    if summary_channel == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            let now = Local::now();
+            let at = next_summary_at(&now, summary_hour);
+            tokio::time::sleep((at - now).to_std().unwrap_or_default()).await;
+            let yesterday = at.date_naive() - Duration::days(1);
+            let day = state.metrics.day(yesterday).await;
+            let embed = summary_embed(&*state.i18n.read().await, yesterday, &day);
+            let http = http.borrow().clone();
+            match ChannelId::new(summary_channel)
+                .send_message(&http, CreateMessage::new().embed(embed))
+                .await
+            {
+                Ok(_) => info!("📊 Posted daily usage summary for {}", yesterday),
+                Err(e) => warn!("⚠️ Failed to post daily usage summary: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn turn(channel_id: u64, user_id: Option<u64>, success: bool) -> TurnOutcome {
+        TurnOutcome {
+            channel_id,
+            user_id,
+            success,
+            tool_calls: 2,
+            tokens: success.then_some((100, 40)),
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().expect("date")
+    }
+
+    #[tokio::test]
+    async fn test_record_counts_per_channel_user_and_day_and_persists() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("metrics.json");
+        let metrics = Metrics::load_from(path.clone()).await;
+        metrics
+            .record_on(turn(1, Some(7), true), date("2026-10-01"))
+            .await;
+        metrics
+            .record_on(turn(1, Some(8), false), date("2026-10-01"))
+            .await;
+        metrics
+            .record_on(turn(2, None, true), date("2026-10-02"))
+            .await;
+
+        let reloaded = Metrics::load_from(path).await;
+        assert_eq!(
+            reloaded.channel(1).await,
+            Counters {
+                prompts: 2,
+                completions: 1,
+                errors: 1,
+                tool_calls: 4,
+                input_tokens: 100,
+                output_tokens: 40,
+            }
+        );
+        assert_eq!(reloaded.user(7).await.completions, 1);
+        assert_eq!(reloaded.user(8).await.errors, 1);
+        assert_eq!(reloaded.user(9).await, Counters::default());
+        let day = reloaded.day(date("2026-10-01")).await;
+        assert_eq!(day.total.prompts, 2);
+        assert!(!day.channels.contains_key("2"));
+    }
+
+    #[tokio::test]
+    async fn test_old_days_are_dropped() {
+        let dir = tempdir().expect("tempdir");
+        let metrics = Metrics::load_from(dir.path().join("metrics.json")).await;
+        metrics
+            .record_on(turn(1, None, true), date("2026-09-01"))
+            .await;
+        metrics
+            .record_on(turn(1, None, true), date("2026-10-16"))
+            .await;
+        assert_eq!(metrics.day(date("2026-09-01")).await, DayStats::default());
+        assert_eq!(metrics.day(date("2026-10-16")).await.total.prompts, 1);
+        assert_eq!(metrics.channel(1).await.prompts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_purge_drops_users_inactive_since_cutoff() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("metrics.json");
+        let metrics = Metrics::load_from(path.clone()).await;
+        metrics
+            .record_on(turn(1, Some(7), true), date("2026-09-01"))
+            .await;
+        metrics
+            .record_on(turn(1, Some(8), true), date("2026-10-10"))
+            .await;
+
+        let cutoff = Local
+            .with_ymd_and_hms(2026, 10, 1, 12, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(metrics.purge_users_before(cutoff).await.unwrap(), 1);
+        assert_eq!(metrics.purge_users_before(cutoff).await.unwrap(), 0);
+
+        let reloaded = Metrics::load_from(path.clone()).await;
+        assert_eq!(reloaded.user(7).await, Counters::default());
+        assert_eq!(reloaded.user(8).await.prompts, 1);
+        assert_eq!(reloaded.channel(1).await.prompts, 2);
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_next_summary_at_rolls_to_next_day() {
+        let now = chrono::Utc
+            .with_ymd_and_hms(2026, 10, 16, 8, 30, 0)
+            .unwrap();
+        assert_eq!(
+            next_summary_at(&now, 9),
+            chrono::Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_summary_at(&now, 8),
+            chrono::Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_counters_lists_every_count() {
+        let i18n = I18n::new("en");
+        let text = format_counters(
+            &i18n,
+            &Counters {
+                prompts: 1200,
+                completions: 1100,
+                errors: 100,
+                tool_calls: 5,
+                input_tokens: 2_000_000,
+                output_tokens: 3,
+            },
+        );
+        assert!(text.contains("1,200"));
+        assert!(text.contains("2,000,000"));
+    }
+}
@@ -1,15 +1,197 @@
 use serde_json::json;
+use std::fmt;
 use std::path::{Path, PathBuf};
 use tokio::fs;
-use tracing::info;
+use tracing::{info, warn};
 
-const CURRENT_VERSION: u32 = 1;
+pub const CURRENT_VERSION: u32 = 1;
 const OLD_BASE_DIR: &str = ".pi/discord-rs";
 const NEW_BASE_DIR: &str = ".agent-discord-rs";
 pub const BASE_DIR_ENV: &str = "AGENT_DISCORD_BASE_DIR";
 
+/// 遷移中的一項檔案變更
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// 遷移前把整個資料目錄複製一份
+    Backup {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    CreateDir(PathBuf),
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// 新建或改寫檔案；`changes` 說明內容的變動 (例如新增或改寫的設定鍵)
+    Write {
+        path: PathBuf,
+        changes: Vec<String>,
+    },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Backup { from, to } => {
+                write!(f, "backup {} -> {}", from.display(), to.display())
+            }
+            Step::CreateDir(path) => write!(f, "mkdir  {}", path.display()),
+            Step::Copy { from, to } => write!(f, "copy   {} -> {}", from.display(), to.display()),
+            Step::Write { path, changes } => {
+                write!(f, "write  {}", path.display())?;
+                for change in changes {
+                    write!(f, "\n         {}", change)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 執行遷移步驟並記錄下來；dry-run 時只記錄不動檔案
+pub struct Migrator {
+    dry_run: bool,
+    steps: Vec<Step>,
+}
+
+impl Migrator {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    async fn create_dir(&mut self, path: &Path) -> anyhow::Result<()> {
+        let step = Step::CreateDir(path.to_path_buf());
+        if path.exists() || self.steps.contains(&step) {
+            return Ok(());
+        }
+        if !self.dry_run {
+            fs::create_dir_all(path).await?;
+        }
+        self.steps.push(step);
+        Ok(())
+    }
+
+    async fn copy(&mut self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        if !self.dry_run {
+            fs::copy(from, to).await?;
+        }
+        self.steps.push(Step::Copy {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    async fn write(
+        &mut self,
+        path: &Path,
+        content: String,
+        changes: Vec<String>,
+    ) -> anyhow::Result<()> {
+        if !self.dry_run {
+            fs::write(path, content).await?;
+        }
+        self.steps.push(Step::Write {
+            path: path.to_path_buf(),
+            changes,
+        });
+        Ok(())
+    }
+
+    async fn backup(&mut self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        if !self.dry_run {
+            copy_tree(from, to).await?;
+        }
+        self.steps.push(Step::Backup {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+        Ok(())
+    }
+}
+
+fn home_dir() -> anyhow::Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| anyhow::anyhow!("No home directory"))
+}
+
 pub async fn run_migrations() -> anyhow::Result<()> {
-    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("No home directory"))?;
+    migrate_home(&home_dir()?, &mut Migrator::new(false)).await
+}
+
+/// `migrate` 子命令：列出或執行遷移步驟，`backup` 時先備份會被讀寫的資料目錄
+pub async fn run_cli(dry_run: bool, backup: bool) -> anyhow::Result<()> {
+    let home = home_dir()?;
+    let mut plan = Migrator::new(true);
+    migrate_home(&home, &mut plan).await?;
+    if plan.steps.is_empty() {
+        println!("✅ Data directory is up to date (v{}).", CURRENT_VERSION);
+        return Ok(());
+    }
+
+    let mut migrator = Migrator::new(dry_run);
+    if backup {
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        backup_dirs(&home, &stamp, &mut migrator).await?;
+    }
+    migrate_home(&home, &mut migrator).await?;
+
+    if dry_run {
+        println!("Would apply {} step(s):", migrator.steps.len());
+    } else {
+        println!("✅ Applied {} step(s):", migrator.steps.len());
+    }
+    for step in migrator.steps() {
+        println!("  {}", step);
+    }
+    if dry_run {
+        println!("Run again without --dry-run to apply them (stop the bot first).");
+    }
+    Ok(())
+}
+
+/// 舊目錄與新目錄各自複製到同層的 `<目錄>.backup-<stamp>`
+async fn backup_dirs(home: &Path, stamp: &str, migrator: &mut Migrator) -> anyhow::Result<()> {
+    for dir in [home.join(OLD_BASE_DIR), home.join(NEW_BASE_DIR)] {
+        if !dir.exists() {
+            continue;
+        }
+        let mut name = dir.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".backup-{}", stamp));
+        let to = dir.with_file_name(name);
+        if to.exists() {
+            anyhow::bail!("Backup target {} already exists", to.display());
+        }
+        migrator.backup(&dir, &to).await?;
+    }
+    Ok(())
+}
+
+async fn copy_tree(from: &Path, to: &Path) -> anyhow::Result<()> {
+    let mut pending = vec![(from.to_path_buf(), to.to_path_buf())];
+    while let Some((src, dest)) = pending.pop() {
+        fs::create_dir_all(&dest).await?;
+        let mut entries = fs::read_dir(&src).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let target = dest.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                pending.push((path, target));
+            } else if path.is_file() {
+                fs::copy(&path, &target).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn migrate_home(home: &Path, migrator: &mut Migrator) -> anyhow::Result<()> {
     let old_dir = home.join(OLD_BASE_DIR);
     let new_dir = home.join(NEW_BASE_DIR);
     let version_file = new_dir.join(".version");
@@ -56,32 +238,50 @@ pub async fn run_migrations() -> anyhow::Result<()> {
     if needs_migration {
         if !new_dir.exists() {
             info!("🔄 Detected old version data, starting migration...");
-            migrate_v0_to_v1(&old_dir, &new_dir).await?;
+            migrate_v0_to_v1(&old_dir, &new_dir, migrator).await?;
             info!("✅ Data migration completed");
         } else {
             info!("🔄 Updating config from old version...");
-            migrate_config_only(&old_dir, &new_dir).await?;
+            migrate_config_only(&old_dir, &new_dir, migrator).await?;
             info!("✅ Config updated");
         }
     }
 
     // 始終檢查是否需要遷移認證資料（即使 config 不需要遷移）
     if old_dir.exists() && new_dir.exists() {
-        migrate_auth_and_sessions(&old_dir, &new_dir).await?;
+        migrate_auth_and_sessions(&old_dir, &new_dir, migrator).await?;
     }
 
     if !new_dir.exists() {
-        // 全新安裝
-        fs::create_dir_all(&new_dir).await?;
-        fs::create_dir_all(new_dir.join("sessions").join("pi")).await?;
-        fs::create_dir_all(new_dir.join("sessions").join("opencode")).await?;
-        fs::create_dir_all(new_dir.join("sessions").join("copilot")).await?;
-        fs::create_dir_all(new_dir.join("prompts")).await?;
-        fs::create_dir_all(new_dir.join("uploads")).await?;
+        // 全新安裝；dry-run 時已列出的目錄不重複
+        create_layout(&new_dir, migrator).await?;
     }
 
-    write_version(&version_file, CURRENT_VERSION).await?;
-    Ok(())
+    migrator
+        .write(
+            &version_file,
+            CURRENT_VERSION.to_string(),
+            vec![format!(
+                "version {} -> {}",
+                current_version, CURRENT_VERSION
+            )],
+        )
+        .await
+}
+
+async fn create_layout(new_dir: &Path, migrator: &mut Migrator) -> anyhow::Result<()> {
+    migrator.create_dir(new_dir).await?;
+    migrator
+        .create_dir(&new_dir.join("sessions").join("pi"))
+        .await?;
+    migrator
+        .create_dir(&new_dir.join("sessions").join("opencode"))
+        .await?;
+    migrator
+        .create_dir(&new_dir.join("sessions").join("copilot"))
+        .await?;
+    migrator.create_dir(&new_dir.join("prompts")).await?;
+    migrator.create_dir(&new_dir.join("uploads")).await
 }
 
 async fn read_version(path: &PathBuf) -> u32 {
@@ -91,12 +291,11 @@ async fn read_version(path: &PathBuf) -> u32 {
     }
 }
 
-async fn write_version(path: &PathBuf, version: u32) -> anyhow::Result<()> {
-    fs::write(path, version.to_string()).await?;
-    Ok(())
-}
-
-async fn migrate_config_only(old_dir: &Path, new_dir: &Path) -> anyhow::Result<()> {
+async fn migrate_config_only(
+    old_dir: &Path,
+    new_dir: &Path,
+    migrator: &mut Migrator,
+) -> anyhow::Result<()> {
     // 只遷移 config.toml 中的 token
     let old_config = old_dir.join("config.toml");
     let new_config = new_dir.join("config.toml");
@@ -114,7 +313,14 @@ async fn migrate_config_only(old_dir: &Path, new_dir: &Path) -> anyhow::Result<(
                     r#"discord_token = "YOUR_DISCORD_TOKEN_HERE""#,
                     &format!(r#"discord_token = "{}""#, token),
                 );
-                fs::write(&new_config, new_content).await?;
+                // 不列出 token 本身
+                let change = format!(
+                    "discord_token: placeholder -> value from {}",
+                    old_config.display()
+                );
+                migrator
+                    .write(&new_config, new_content, vec![change])
+                    .await?;
             }
         }
     }
@@ -122,7 +328,11 @@ async fn migrate_config_only(old_dir: &Path, new_dir: &Path) -> anyhow::Result<(
     Ok(())
 }
 
-async fn migrate_auth_and_sessions(old_dir: &Path, new_dir: &Path) -> anyhow::Result<()> {
+async fn migrate_auth_and_sessions(
+    old_dir: &Path,
+    new_dir: &Path,
+    migrator: &mut Migrator,
+) -> anyhow::Result<()> {
     // 遷移認證資料
     let old_registry = old_dir.join("registry.json");
     let new_auth = new_dir.join("auth.json");
@@ -179,46 +389,47 @@ async fn migrate_auth_and_sessions(old_dir: &Path, new_dir: &Path) -> anyhow::Re
             }
         }
 
+        let users = old_data.get("users").cloned().unwrap_or(json!({}));
+        let changes = vec![
+            format!(
+                "{} user(s), {} channel(s) from {}",
+                users.as_object().map_or(0, |m| m.len()),
+                new_channels.len(),
+                old_registry.display()
+            ),
+            r#"channels.*.agent_type = "pi""#.to_string(),
+        ];
         let new_data = json!({
-            "users": old_data.get("users").unwrap_or(&json!({})),
+            "users": users,
             "channels": new_channels,
         });
 
-        fs::write(&new_auth, serde_json::to_string_pretty(&new_data)?).await?;
+        migrator
+            .write(&new_auth, serde_json::to_string_pretty(&new_data)?, changes)
+            .await?;
         info!("✅ Authentication data migrated successfully");
     }
 
-    // 遷移 Pi sessions
-    let old_sessions = old_dir.join("sessions");
-    let new_pi_sessions = new_dir.join("sessions").join("pi");
-    if old_sessions.exists() {
-        fs::create_dir_all(&new_pi_sessions).await?;
-        let mut entries = fs::read_dir(&old_sessions).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if path.is_file() {
-                let filename = entry.file_name();
-                let dest = new_pi_sessions.join(&filename);
-                if !dest.exists() {
-                    fs::copy(&path, dest).await?;
-                }
-            }
+    // 遷移 Pi sessions 與 prompts
+    let pairs = [
+        (
+            old_dir.join("sessions"),
+            new_dir.join("sessions").join("pi"),
+        ),
+        (old_dir.join("prompts"), new_dir.join("prompts")),
+    ];
+    for (from_dir, to_dir) in pairs {
+        if !from_dir.exists() {
+            continue;
         }
-    }
-
-    // 遷移 prompts
-    let old_prompts = old_dir.join("prompts");
-    let new_prompts = new_dir.join("prompts");
-    if old_prompts.exists() {
-        fs::create_dir_all(&new_prompts).await?;
-        let mut entries = fs::read_dir(&old_prompts).await?;
+        migrator.create_dir(&to_dir).await?;
+        let mut entries = fs::read_dir(&from_dir).await?;
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
             if path.is_file() {
-                let filename = entry.file_name();
-                let dest = new_prompts.join(&filename);
+                let dest = to_dir.join(entry.file_name());
                 if !dest.exists() {
-                    fs::copy(&path, dest).await?;
+                    migrator.copy(&path, &dest).await?;
                 }
             }
         }
@@ -227,13 +438,12 @@ async fn migrate_auth_and_sessions(old_dir: &Path, new_dir: &Path) -> anyhow::Re
     Ok(())
 }
 
-async fn migrate_v0_to_v1(old_dir: &Path, new_dir: &Path) -> anyhow::Result<()> {
-    fs::create_dir_all(&new_dir).await?;
-    fs::create_dir_all(new_dir.join("sessions").join("pi")).await?;
-    fs::create_dir_all(new_dir.join("sessions").join("opencode")).await?;
-    fs::create_dir_all(new_dir.join("sessions").join("copilot")).await?;
-    fs::create_dir_all(new_dir.join("prompts")).await?;
-    fs::create_dir_all(new_dir.join("uploads")).await?;
+async fn migrate_v0_to_v1(
+    old_dir: &Path,
+    new_dir: &Path,
+    migrator: &mut Migrator,
+) -> anyhow::Result<()> {
+    create_layout(new_dir, migrator).await?;
 
     // 遷移 config.toml
     let old_config = old_dir.join("config.toml");
@@ -241,6 +451,7 @@ async fn migrate_v0_to_v1(old_dir: &Path, new_dir: &Path) -> anyhow::Result<()>
     if old_config.exists() {
         info!("📄 Migrating config.toml...");
         let content = fs::read_to_string(&old_config).await?;
+        let mut changes = vec![format!("from {}", old_config.display())];
 
         // 添加 opencode 配置區塊（如果不存在）
         let final_content = if !content.contains("[opencode]") {
@@ -251,12 +462,14 @@ host = "127.0.0.1"
 port = 4096
 # password = "your-password"  # Uncomment if using OPENCODE_SERVER_PASSWORD
 "#;
+            changes.push(r#"+ opencode.host = "127.0.0.1""#.to_string());
+            changes.push("+ opencode.port = 4096".to_string());
             format!("{}{}", content, opencode_config)
         } else {
             content
         };
 
-        fs::write(&new_config, final_content).await?;
+        migrator.write(&new_config, final_content, changes).await?;
     } else {
         // 創建默認配置
         let default_config = r#"discord_token = "YOUR_DISCORD_TOKEN_HERE"
@@ -269,27 +482,71 @@ host = "127.0.0.1"
 port = 4096
 # password = "your-password"
 "#;
-        fs::write(&new_config, default_config).await?;
+        migrator
+            .write(
+                &new_config,
+                default_config.to_string(),
+                vec!["default config".to_string()],
+            )
+            .await?;
     }
 
     // 遷移認證資料、session 和 prompts
-    migrate_auth_and_sessions(old_dir, new_dir).await?;
+    migrate_auth_and_sessions(old_dir, new_dir, migrator).await?;
 
     // 創建 channel_config.json
     let channel_config = json!({
         "version": 1,
         "channels": {}
     });
-    fs::write(
-        new_dir.join("channel_config.json"),
-        serde_json::to_string_pretty(&channel_config)?,
-    )
-    .await?;
+    migrator
+        .write(
+            &new_dir.join("channel_config.json"),
+            serde_json::to_string_pretty(&channel_config)?,
+            vec!["empty channel config".to_string()],
+        )
+        .await?;
 
     info!("✅ Migration from v0 to v1 completed");
     Ok(())
 }
 
+/// 頻道重建 (ID 改變) 時搬移以頻道 ID 命名的檔案：Pi session 與上傳目錄。
+/// 回傳實際搬移的項目數；新頻道已有同名檔案時保留新的不覆蓋。
+pub async fn migrate_channel_files(old_id: u64, new_id: u64) -> anyhow::Result<usize> {
+    move_channel_files(&get_base_dir(), old_id, new_id).await
+}
+
+async fn move_channel_files(base: &Path, old_id: u64, new_id: u64) -> anyhow::Result<usize> {
+    let pi_dir = base.join("sessions").join("pi");
+    let uploads = base.join("uploads");
+    let pairs = [
+        (
+            pi_dir.join(format!("discord-rs-{}.jsonl", old_id)),
+            pi_dir.join(format!("discord-rs-{}.jsonl", new_id)),
+        ),
+        (
+            uploads.join(old_id.to_string()),
+            uploads.join(new_id.to_string()),
+        ),
+    ];
+
+    let mut moved = 0;
+    for (from, to) in pairs {
+        if !from.exists() {
+            continue;
+        }
+        if to.exists() {
+            warn!("⚠️ Skip migrating {:?}: {:?} already exists", from, to);
+            continue;
+        }
+        fs::rename(&from, &to).await?;
+        info!("📦 Migrated {:?} -> {:?}", from, to);
+        moved += 1;
+    }
+    Ok(moved)
+}
+
 pub fn get_base_dir() -> PathBuf {
     if let Ok(v) = std::env::var(BASE_DIR_ENV) {
         if !v.trim().is_empty() {
@@ -320,32 +577,63 @@ pub fn get_channel_config_path() -> PathBuf {
     get_base_dir().join("channel_config.json")
 }
 
+pub fn get_welcome_template_path() -> PathBuf {
+    get_base_dir().join("welcome.md")
+}
+
+pub fn get_analytics_dir() -> PathBuf {
+    get_base_dir().join("analytics")
+}
+
+pub fn get_user_prefs_path() -> PathBuf {
+    get_base_dir().join("user_prefs.json")
+}
+
+pub fn get_usage_caps_path() -> PathBuf {
+    get_base_dir().join("usage_caps.json")
+}
+
+pub fn get_queue_dir() -> PathBuf {
+    get_base_dir().join("queue")
+}
+
+pub fn get_metrics_path() -> PathBuf {
+    get_base_dir().join("metrics.json")
+}
+
+pub fn get_model_snapshot_path() -> PathBuf {
+    get_base_dir().join("model_snapshot.json")
+}
+
 pub fn get_sessions_dir(agent_type: &str) -> PathBuf {
     get_base_dir().join("sessions").join(agent_type)
 }
 
+pub fn get_skills_dir() -> PathBuf {
+    get_base_dir().join("skills")
+}
+
 pub fn get_prompts_dir() -> PathBuf {
     get_base_dir().join("prompts")
 }
 
-pub fn get_uploads_dir() -> PathBuf {
-    get_base_dir().join("uploads")
+/// 測試改寫 `BASE_DIR_ENV` 前共用的鎖；各模組的測試都取同一把，才不會互相覆蓋環境變數
+#[cfg(test)]
+pub fn env_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::OnceLock<std::sync::Mutex<()>> = std::sync::OnceLock::new();
+    LOCK.get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::{Mutex, OnceLock};
     use tempfile::tempdir;
 
-    fn env_lock() -> &'static Mutex<()> {
-        static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-        LOCK.get_or_init(|| Mutex::new(()))
-    }
-
     #[test]
     fn test_get_base_dir_uses_env_override() {
-        let _guard = env_lock().lock().expect("lock");
+        let _guard = env_lock();
         let dir = tempdir().expect("tempdir");
         // SAFETY: tests serialize env writes via global mutex
         unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
@@ -372,7 +660,7 @@ mod tests {
         .await
         .expect("write new");
 
-        migrate_config_only(old.path(), newd.path())
+        migrate_config_only(old.path(), newd.path(), &mut Migrator::new(false))
             .await
             .expect("migrate config");
         let updated = fs::read_to_string(new_cfg).await.expect("read updated");
@@ -404,7 +692,7 @@ mod tests {
         .await
         .expect("write registry");
 
-        migrate_auth_and_sessions(old.path(), newd.path())
+        migrate_auth_and_sessions(old.path(), newd.path(), &mut Migrator::new(false))
             .await
             .expect("migrate auth");
 
@@ -429,7 +717,7 @@ mod tests {
         let newd = tempdir().expect("new");
         fs::create_dir_all(old.path()).await.expect("mkdir old");
 
-        migrate_v0_to_v1(old.path(), newd.path())
+        migrate_v0_to_v1(old.path(), newd.path(), &mut Migrator::new(false))
             .await
             .expect("migrate");
 
@@ -444,4 +732,108 @@ mod tests {
             .expect("read cfg");
         assert!(cfg.contains("assistant_name = \"Agent\""));
     }
+
+    #[tokio::test]
+    async fn test_dry_run_lists_steps_without_touching_files() {
+        let home = tempdir().expect("home");
+        let old = home.path().join(OLD_BASE_DIR);
+        fs::create_dir_all(old.join("sessions"))
+            .await
+            .expect("mkdir sessions");
+        fs::write(old.join("sessions").join("s1.jsonl"), "abc")
+            .await
+            .expect("write session");
+        fs::write(old.join("config.toml"), "discord_token = \"REAL_TOKEN\"")
+            .await
+            .expect("write cfg");
+        fs::write(old.join("registry.json"), r#"{"users":{},"channels":{}}"#)
+            .await
+            .expect("write registry");
+
+        let mut plan = Migrator::new(true);
+        migrate_home(home.path(), &mut plan).await.expect("plan");
+        let new = home.path().join(NEW_BASE_DIR);
+        assert!(!new.exists());
+
+        let steps = plan.steps();
+        assert!(steps.contains(&Step::Copy {
+            from: old.join("sessions").join("s1.jsonl"),
+            to: new.join("sessions").join("pi").join("s1.jsonl"),
+        }));
+        let config = steps
+            .iter()
+            .find_map(|s| match s {
+                Step::Write { path, changes } if *path == new.join("config.toml") => Some(changes),
+                _ => None,
+            })
+            .expect("config step");
+        assert!(config.contains(&"+ opencode.port = 4096".to_string()));
+        let mkdirs = steps
+            .iter()
+            .filter(|s| **s == Step::CreateDir(new.join("uploads")))
+            .count();
+        assert_eq!(mkdirs, 1);
+
+        let mut applied = Migrator::new(false);
+        migrate_home(home.path(), &mut applied)
+            .await
+            .expect("migrate");
+        assert_eq!(applied.steps(), plan.steps());
+        assert!(new.join("sessions").join("pi").join("s1.jsonl").exists());
+    }
+
+    #[tokio::test]
+    async fn test_backup_dirs_snapshots_existing_dirs() {
+        let home = tempdir().expect("home");
+        let old = home.path().join(OLD_BASE_DIR);
+        fs::create_dir_all(old.join("sessions"))
+            .await
+            .expect("mkdir old");
+        fs::write(old.join("sessions").join("s1.jsonl"), "abc")
+            .await
+            .expect("write session");
+
+        let mut migrator = Migrator::new(false);
+        backup_dirs(home.path(), "20261016-090000", &mut migrator)
+            .await
+            .expect("backup");
+        let snapshot = home.path().join(".pi/discord-rs.backup-20261016-090000");
+        assert_eq!(
+            migrator.steps(),
+            [Step::Backup {
+                from: old,
+                to: snapshot.clone(),
+            }]
+        );
+        let copied = fs::read_to_string(snapshot.join("sessions").join("s1.jsonl"))
+            .await
+            .expect("read copy");
+        assert_eq!(copied, "abc");
+        assert!(backup_dirs(home.path(), "20261016-090000", &mut migrator)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_move_channel_files_renames_and_keeps_existing() {
+        let base = tempdir().expect("base");
+        let pi = base.path().join("sessions").join("pi");
+        fs::create_dir_all(&pi).await.expect("mkdir pi");
+        fs::create_dir_all(base.path().join("uploads").join("1"))
+            .await
+            .expect("mkdir uploads");
+        fs::create_dir_all(base.path().join("uploads").join("2"))
+            .await
+            .expect("mkdir uploads new");
+        fs::write(pi.join("discord-rs-1.jsonl"), "{}")
+            .await
+            .expect("write session");
+
+        let moved = move_channel_files(base.path(), 1, 2).await.expect("move");
+        assert_eq!(moved, 1);
+        assert!(pi.join("discord-rs-2.jsonl").exists());
+        assert!(!pi.join("discord-rs-1.jsonl").exists());
+        // 新頻道已有上傳目錄時不覆蓋
+        assert!(base.path().join("uploads").join("1").exists());
+    }
 }
@@ -0,0 +1,159 @@
+use crate::delivery::{split_content, MESSAGE_MAX_CHARS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serenity::all::{ChannelId, CreateMessage, Http};
+use tracing::{info, warn};
+
+pub const DEFAULT_TEMPLATE: &str = "**{assistant}** · <#{channel}>\n{answer}";
+
+/// 頻道最終回應的鏡像輸出目標 (webhook 或另一個頻道)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MirrorConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub channel_id: Option<u64>,
+    /// 可用 {answer} {channel} {assistant} {backend}；未設定時使用預設格式
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl MirrorConfig {
+    pub fn is_active(&self) -> bool {
+        self.enabled && (self.webhook_url.is_some() || self.channel_id.is_some())
+    }
+}
+
+/// 本輪要鏡像的內容
+pub struct MirrorPayload<'a> {
+    pub answer: &'a str,
+    pub channel_id: u64,
+    pub assistant: &'a str,
+    pub backend: &'a str,
+}
+
+pub fn is_valid_webhook_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|u| u.scheme() == "https" && u.host_str().is_some())
+}
+
+pub fn render_template(template: Option<&str>, payload: &MirrorPayload) -> String {
+    // {answer} 最後替換，避免回答內容中的大括號被當成佔位符
+    template
+        .unwrap_or(DEFAULT_TEMPLATE)
+        .replace("{channel}", &payload.channel_id.to_string())
+        .replace("{assistant}", payload.assistant)
+        .replace("{backend}", payload.backend)
+        .replace("{answer}", payload.answer.trim())
+}
+
+// webhook URL 內含權杖，日誌只記錄主機名稱
+fn webhook_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| "invalid-url".to_string())
+}
+
+/// 回合成功後的輸出掛鉤：依設定轉送到 webhook 與封存頻道，失敗只記錄警告
+pub async fn mirror_response(http: &Http, config: &MirrorConfig, payload: MirrorPayload<'_>) {
+    if !config.is_active() || payload.answer.trim().is_empty() {
+        return;
+    }
+    let text = render_template(config.template.as_deref(), &payload);
+    let chunks = split_content(&text, MESSAGE_MAX_CHARS);
+
+    if let Some(target) = config.channel_id.filter(|id| *id != payload.channel_id) {
+        for chunk in &chunks {
+            if let Err(e) = ChannelId::new(target)
+                .send_message(
+                    http,
+                    CreateMessage::new()
+                        .content(chunk)
+                        .allowed_mentions(crate::mentions::suppressed()),
+                )
+                .await
+            {
+                warn!("⚠️ Mirror to channel {} failed: {}", target, e);
+                break;
+            }
+        }
+        info!(
+            "🪞 Mirrored response of {} to channel {}",
+            payload.channel_id, target
+        );
+    }
+
+    if let Some(url) = &config.webhook_url {
+        let client = reqwest::Client::new();
+        for chunk in &chunks {
+            let body = json!({
+                "content": chunk,
+                "username": payload.assistant,
+                "allowed_mentions": { "parse": [] },
+            });
+            let result = client.post(url).json(&body).send().await;
+            if let Err(e) = result.and_then(|r| r.error_for_status()) {
+                warn!(
+                    "⚠️ Mirror webhook to {} failed: {}",
+                    webhook_host(url),
+                    e.without_url()
+                );
+                break;
+            }
+        }
+        info!(
+            "🪞 Mirrored response of {} to webhook {}",
+            payload.channel_id,
+            webhook_host(url)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(answer: &str) -> MirrorPayload<'_> {
+        MirrorPayload {
+            answer,
+            channel_id: 42,
+            assistant: "Agent",
+            backend: "kilo",
+        }
+    }
+
+    #[test]
+    fn test_render_template_default_and_custom() {
+        assert_eq!(
+            render_template(None, &payload(" hi ")),
+            "**Agent** · <#42>\nhi"
+        );
+        assert_eq!(
+            render_template(Some("[{backend}] {answer}"), &payload("use {channel}")),
+            "[kilo] use {channel}"
+        );
+    }
+
+    #[test]
+    fn test_mirror_config_activation_and_url_validation() {
+        let mut cfg = MirrorConfig {
+            channel_id: Some(7),
+            ..Default::default()
+        };
+        assert!(!cfg.is_active());
+        cfg.enabled = true;
+        assert!(cfg.is_active());
+
+        assert!(is_valid_webhook_url(
+            "https://discord.com/api/webhooks/1/abc"
+        ));
+        assert!(!is_valid_webhook_url("http://example.com/hook"));
+        assert!(!is_valid_webhook_url("not a url"));
+        assert_eq!(
+            webhook_host("https://example.com/hook?t=secret"),
+            "example.com"
+        );
+    }
+}
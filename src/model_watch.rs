@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, Http};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::ModelWatchConfig;
+use crate::i18n::I18n;
+
+/// Embed 欄位的長度上限 (字元)
+const FIELD_LIMIT: usize = 1024;
+
+/// 各後端上次看到的模型清單 (`provider/id`)，重啟後沿用以免把所有模型當成新的
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ModelSnapshot {
+    #[serde(default)]
+    pub backends: HashMap<String, BTreeSet<String>>,
+}
+
+impl ModelSnapshot {
+    pub async fn load() -> Self {
+        let path = crate::migrate::get_model_snapshot_path();
+        let Ok(content) = tokio::fs::read_to_string(&path).await else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("⚠️ Model snapshot is corrupt, starting empty: {}", e);
+            Self::default()
+        })
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let path = crate::migrate::get_model_snapshot_path();
+        tokio::fs::write(&path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// 記錄最新清單並回傳 (新增, 移除)；第一次看到的後端只建立基準，不算變動
+    pub fn update(
+        &mut self,
+        backend: &str,
+        models: BTreeSet<String>,
+    ) -> (Vec<String>, Vec<String>) {
+        let Some(previous) = self.backends.insert(backend.to_string(), models.clone()) else {
+            return (Vec::new(), Vec::new());
+        };
+        (
+            models.difference(&previous).cloned().collect(),
+            previous.difference(&models).cloned().collect(),
+        )
+    }
+}
+
+/// 模型清單放進 embed 欄位，超過長度時註明省略的數量
+fn field_value(models: &[String]) -> String {
+    let mut value = String::new();
+    for (idx, model) in models.iter().enumerate() {
+        let line = format!("`{}`\n", model);
+        if value.chars().count() + line.chars().count() > FIELD_LIMIT - 16 {
+            value.push_str(&format!("… +{}", models.len() - idx));
+            break;
+        }
+        value.push_str(&line);
+    }
+    value
+}
+
+fn change_embed(i18n: &I18n, backend: &str, added: &[String], removed: &[String]) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(i18n.get_args("model_watch_title", &[backend.to_string()]))
+        .color(0x5865F2);
+    if !added.is_empty() {
+        embed = embed.field(i18n.get("model_watch_added"), field_value(added), false);
+    }
+    if !removed.is_empty() {
+        embed = embed.field(i18n.get("model_watch_removed"), field_value(removed), false);
+    }
+    embed
+}
+
+/// 以目前開著的 session 查詢各後端的模型清單，與上次結果比對並通知變動
+async fn check_once(state: &crate::AppState, http: &Http, snapshot: &mut ModelSnapshot) {
+    let channel = ChannelId::new(state.config.model_watch.admin_channel);
+    let mut fetched = false;
+    for agent in state.sessions().one_per_backend().await {
+        let backend = agent.agent_type();
+        let models = match agent.get_available_models().await {
+            Ok(models) if !models.is_empty() => models,
+            // 空清單多半是暫時斷線，不當成所有模型都被移除
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("⚠️ Failed to list models of {}: {}", backend, e);
+                continue;
+            }
+        };
+        let labels = models
+            .iter()
+            .map(|m| format!("{}/{}", m.provider, m.id))
+            .collect();
+        let (added, removed) = snapshot.update(backend, labels);
+        fetched = true;
+        if added.is_empty() && removed.is_empty() {
+            continue;
+        }
+        info!(
+            "🆕 Models of {} changed: +{} -{}",
+            backend,
+            added.len(),
+            removed.len()
+        );
+        let embed = change_embed(&*state.i18n.read().await, backend, &added, &removed);
+        if let Err(e) = channel
+            .send_message(http, CreateMessage::new().embed(embed))
+            .await
+        {
+            warn!("⚠️ Failed to announce model changes: {}", e);
+        }
+    }
+    if fetched {
+        if let Err(e) = snapshot.save().await {
+            warn!("⚠️ Failed to save model snapshot: {}", e);
+        }
+    }
+}
+
+/// 定期比對各後端的模型清單，設定 `[model_watch] admin_channel` 後才啟用
+pub fn spawn_watch_loop(state: Arc<crate::AppState>, http: watch::Receiver<Arc<Http>>) {
+    let ModelWatchConfig {
+        admin_channel,
+        interval_mins,
+    } = state.config.model_watch.clone();
+    if admin_channel == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut snapshot = ModelSnapshot::load().await;
+        let interval = std::time::Duration::from_secs(interval_mins.max(1) * 60);
+        loop {
+            tokio::time::sleep(interval).await;
+            let http = http.borrow().clone();
+            check_once(&state, &http, &mut snapshot).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(models: &[&str]) -> BTreeSet<String> {
+        models.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn test_snapshot_update_reports_added_and_removed() {
+        let mut snapshot = ModelSnapshot::default();
+        assert_eq!(
+            snapshot.update("kilo", set(&["a/1", "a/2"])),
+            (vec![], vec![])
+        );
+        assert_eq!(
+            snapshot.update("kilo", set(&["a/2", "b/1"])),
+            (vec!["b/1".to_string()], vec!["a/1".to_string()])
+        );
+        assert_eq!(
+            snapshot.update("kilo", set(&["a/2", "b/1"])),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn test_field_value_truncates_long_lists() {
+        let models: Vec<String> = (0..200).map(|i| format!("provider/model-{}", i)).collect();
+        let value = field_value(&models);
+        assert!(value.chars().count() <= FIELD_LIMIT);
+        assert!(value.contains("… +"));
+        assert_eq!(field_value(&models[..1]), "`provider/model-0`\n");
+    }
+}
@@ -0,0 +1,138 @@
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, EditMessage, Http, MessageId};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// 尚未送達的最終回應 (Embed 視圖：標題、顏色、內文)
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingDelivery {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub views: Vec<(String, u32, String)>,
+}
+
+/// Gateway 斷線期間暫存已完成的回合輸出，待重新連線後補送
+pub struct Outbox {
+    connected: AtomicBool,
+    items: Mutex<Vec<PendingDelivery>>,
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Outbox {
+    pub fn new() -> Self {
+        Self {
+            connected: AtomicBool::new(true),
+            items: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::SeqCst);
+    }
+
+    /// 同一則佔位訊息只保留最新的輸出
+    pub async fn push(&self, delivery: PendingDelivery) {
+        let mut items = self.items.lock().await;
+        items.retain(|d| d.message_id != delivery.message_id);
+        items.push(delivery);
+    }
+
+    pub async fn take_all(&self) -> Vec<PendingDelivery> {
+        std::mem::take(&mut *self.items.lock().await)
+    }
+
+    /// 補送所有暫存輸出：優先編輯原佔位訊息，失敗時改發新訊息並附上延遲說明
+    pub async fn flush(&self, http: &Http, delayed_note: &str) {
+        let pending = self.take_all().await;
+        if pending.is_empty() {
+            return;
+        }
+        info!("📬 Delivering {} buffered response(s)", pending.len());
+
+        for delivery in pending {
+            let embeds = || -> Vec<CreateEmbed> {
+                delivery
+                    .views
+                    .iter()
+                    .map(|(title, color, body)| {
+                        CreateEmbed::new()
+                            .title(title)
+                            .color(*color)
+                            .description(body)
+                    })
+                    .collect()
+            };
+
+            let edited = delivery
+                .channel_id
+                .edit_message(
+                    http,
+                    delivery.message_id,
+                    EditMessage::new().embeds(embeds()),
+                )
+                .await;
+            if edited.is_ok() {
+                continue;
+            }
+
+            let sent = delivery
+                .channel_id
+                .send_message(
+                    http,
+                    CreateMessage::new().content(delayed_note).embeds(embeds()),
+                )
+                .await;
+            if let Err(e) = sent {
+                warn!(
+                    "⚠️ Failed to deliver buffered response to channel {}: {}",
+                    delivery.channel_id, e
+                );
+                self.push(delivery).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outbox, PendingDelivery};
+    use serenity::all::{ChannelId, MessageId};
+
+    fn delivery(msg: u64, body: &str) -> PendingDelivery {
+        PendingDelivery {
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(msg),
+            views: vec![("t".to_string(), 0, body.to_string())],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_keeps_latest_per_message() {
+        let outbox = Outbox::new();
+        outbox.push(delivery(10, "old")).await;
+        outbox.push(delivery(11, "other")).await;
+        outbox.push(delivery(10, "new")).await;
+
+        let items = outbox.take_all().await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1], delivery(10, "new"));
+        assert!(outbox.take_all().await.is_empty());
+    }
+
+    #[test]
+    fn test_connected_flag_defaults_to_true() {
+        let outbox = Outbox::new();
+        assert!(outbox.is_connected());
+        outbox.set_connected(false);
+        assert!(!outbox.is_connected());
+    }
+}
@@ -0,0 +1,121 @@
+use serenity::all::MessageId;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+/// 頻道間的回合串接：保存各頻道最近一次完成的回答，以及下一輪要補給來源 session 的轉送紀錄
+#[derive(Default)]
+pub struct Pipes {
+    answers: Mutex<HashMap<u64, (MessageId, String)>>,
+    notes: Mutex<HashMap<u64, Vec<String>>>,
+}
+
+/// Discord 訊息連結；私訊沒有伺服器 ID
+pub fn message_link(guild_id: Option<u64>, channel_id: u64, message_id: MessageId) -> String {
+    let guild = guild_id
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| "@me".to_string());
+    format!(
+        "https://discord.com/channels/{}/{}/{}",
+        guild, channel_id, message_id
+    )
+}
+
+/// 送給目標頻道代理的提示，開頭標明來源讓目標的對話紀錄保有出處
+pub fn build_target_prompt(
+    source_channel: u64,
+    link: &str,
+    requester: u64,
+    note: Option<&str>,
+    answer: &str,
+) -> String {
+    let mut prompt = format!(
+        "[Piped from Discord channel {} by user {}: {}]\n",
+        source_channel, requester, link
+    );
+    if let Some(note) = note.map(str::trim).filter(|n| !n.is_empty()) {
+        prompt.push_str(note);
+        prompt.push('\n');
+    }
+    prompt.push_str("\n--- Output of the other channel's agent ---\n");
+    prompt.push_str(answer.trim());
+    prompt.push_str("\n--- End of piped output ---");
+    prompt
+}
+
+/// 加在來源頻道下一輪提示前的轉送紀錄
+pub fn build_source_note(target_channel: u64, requester: u64) -> String {
+    format!(
+        "[Your previous answer was piped by user {} as input to the agent in Discord channel {}]",
+        requester, target_channel
+    )
+}
+
+impl Pipes {
+    pub async fn complete(&self, channel_id: u64, message_id: MessageId, answer: &str) {
+        if answer.trim().is_empty() {
+            return;
+        }
+        self.answers
+            .lock()
+            .await
+            .insert(channel_id, (message_id, answer.to_string()));
+    }
+
+    pub async fn latest(&self, channel_id: u64) -> Option<(MessageId, String)> {
+        self.answers.lock().await.get(&channel_id).cloned()
+    }
+
+    pub async fn add_note(&self, channel_id: u64, note: String) {
+        self.notes
+            .lock()
+            .await
+            .entry(channel_id)
+            .or_default()
+            .push(note);
+    }
+
+    /// 取出並清空待補的轉送紀錄
+    pub async fn take_notes(&self, channel_id: u64) -> Option<String> {
+        let notes = self.notes.lock().await.remove(&channel_id)?;
+        Some(notes.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_prompt_records_provenance() {
+        let link = message_link(Some(1), 22, MessageId::new(333));
+        assert_eq!(link, "https://discord.com/channels/1/22/333");
+        let prompt = build_target_prompt(22, &link, 7, Some(" implement step 2 "), "Plan:\n1. a\n");
+        assert!(prompt.starts_with("[Piped from Discord channel 22 by user 7: https://"));
+        assert!(prompt.contains("\nimplement step 2\n"));
+        assert!(prompt.ends_with("Plan:\n1. a\n--- End of piped output ---"));
+        assert!(!build_target_prompt(22, &link, 7, Some("  "), "x").contains("\n\n\n"));
+        assert_eq!(
+            message_link(None, 5, MessageId::new(6)),
+            "https://discord.com/channels/@me/5/6"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notes_are_taken_once() {
+        let pipes = Pipes::default();
+        assert!(pipes.latest(1).await.is_none());
+        pipes.complete(1, MessageId::new(2), "  ").await;
+        assert!(pipes.latest(1).await.is_none());
+        pipes.complete(1, MessageId::new(2), "answer").await;
+        assert_eq!(
+            pipes.latest(1).await.map(|(_, a)| a).as_deref(),
+            Some("answer")
+        );
+
+        pipes.add_note(1, build_source_note(9, 7)).await;
+        pipes.add_note(1, build_source_note(10, 7)).await;
+        let notes = pipes.take_notes(1).await.expect("notes");
+        assert_eq!(notes.lines().count(), 2);
+        assert!(pipes.take_notes(1).await.is_none());
+    }
+}
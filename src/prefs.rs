@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 個人偏好，跨頻道生效
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct UserPrefs {
+    /// 回應介面語言，None 表示沿用全域設定
+    #[serde(default)]
+    pub language: Option<String>,
+    /// 長回答改以私訊送出完整內容
+    #[serde(default)]
+    pub dm_long_replies: bool,
+    /// 只顯示回答，隱藏思考與工具活動
+    #[serde(default)]
+    pub compact_embeds: bool,
+    /// 回合完成時提及使用者
+    #[serde(default)]
+    pub ping_on_complete: bool,
+    /// 無障礙模式：以不含裝飾表情符號的純文字訊息取代 Embed
+    #[serde(default)]
+    pub accessible: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UserPrefsStore {
+    #[serde(default)]
+    pub users: HashMap<String, UserPrefs>,
+}
+
+impl UserPrefsStore {
+    pub async fn load() -> anyhow::Result<Self> {
+        let path = crate::migrate::get_user_prefs_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = tokio::fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let path = crate::migrate::get_user_prefs_path();
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(&path, content).await?;
+        Ok(())
+    }
+
+    pub fn get(&self, user_id: u64) -> UserPrefs {
+        self.users
+            .get(&user_id.to_string())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn get_mut(&mut self, user_id: u64) -> &mut UserPrefs {
+        self.users.entry(user_id.to_string()).or_default()
+    }
+}
+
+/// 讀取觸發者的偏好；未指定使用者或讀取失敗時回傳預設值
+pub async fn load_for(user_id: Option<u64>) -> UserPrefs {
+    match user_id {
+        Some(id) => UserPrefsStore::load().await.unwrap_or_default().get(id),
+        None => UserPrefs::default(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::await_holding_lock)]
+mod tests {
+    use super::{load_for, UserPrefs, UserPrefsStore};
+    use crate::migrate::BASE_DIR_ENV;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_prefs_roundtrip_and_defaults() {
+        let _guard = crate::migrate::env_lock();
+        let dir = tempdir().expect("tempdir");
+        // SAFETY: serialized by env lock
+        unsafe { std::env::set_var(BASE_DIR_ENV, dir.path()) };
+
+        assert_eq!(load_for(Some(42)).await, UserPrefs::default());
+
+        let mut store = UserPrefsStore::load().await.expect("load");
+        let prefs = store.get_mut(42);
+        prefs.language = Some("en".to_string());
+        prefs.ping_on_complete = true;
+        store.save().await.expect("save");
+
+        let loaded = load_for(Some(42)).await;
+        assert_eq!(loaded.language.as_deref(), Some("en"));
+        assert!(loaded.ping_on_complete);
+        assert!(!loaded.compact_embeds);
+        assert_eq!(load_for(None).await, UserPrefs::default());
+
+        // SAFETY: serialized by env lock
+        unsafe { std::env::remove_var(BASE_DIR_ENV) };
+    }
+}
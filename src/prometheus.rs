@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info, warn};
+
+// 請求標頭的讀取上限；/metrics 只需要第一行
+const MAX_REQUEST_BYTES: usize = 8192;
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// 各處回報的計數；不論是否開啟 HTTP 端點都會累計
+#[derive(Default)]
+struct Registry {
+    prompts_in_flight: AtomicI64,
+    edit_failures: AtomicU64,
+    /// 後端 → 以錯誤或逾時結束的回合數
+    agent_errors: Mutex<BTreeMap<String, u64>>,
+    /// 後端 → 死掉後重新啟動的進程數
+    backend_restarts: Mutex<BTreeMap<String, u64>>,
+}
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::default)
+}
+
+fn bump(map: &Mutex<BTreeMap<String, u64>>, backend: &str) {
+    if let Ok(mut map) = map.lock() {
+        *map.entry(backend.to_string()).or_default() += 1;
+    }
+}
+
+/// 回合執行期間持有；drop 時 (含被中止) 從進行中的數量扣除
+pub struct InFlightTurn(());
+
+impl Drop for InFlightTurn {
+    fn drop(&mut self) {
+        registry().prompts_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub fn turn_started() -> InFlightTurn {
+    registry().prompts_in_flight.fetch_add(1, Ordering::Relaxed);
+    InFlightTurn(())
+}
+
+pub fn agent_error(backend: &str) {
+    bump(&registry().agent_errors, backend);
+}
+
+pub fn edit_failed() {
+    registry().edit_failures.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn backend_restarted(backend: &str) {
+    bump(&registry().backend_restarts, backend);
+}
+
+fn write_family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_by_backend<'a>(
+    out: &mut String,
+    name: &str,
+    values: impl IntoIterator<Item = (&'a String, &'a u64)>,
+) {
+    for (backend, value) in values {
+        let backend = backend.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(out, "{}{{backend=\"{}\"}} {}", name, backend, value);
+    }
+}
+
+impl Registry {
+    /// Prometheus 文字格式；`sessions` 是抓取當下各後端開著的 session 數
+    fn render(&self, sessions: &BTreeMap<String, u64>) -> String {
+        let mut out = String::new();
+        write_family(
+            &mut out,
+            "agent_discord_active_sessions",
+            "gauge",
+            "Open agent sessions by backend.",
+        );
+        write_by_backend(&mut out, "agent_discord_active_sessions", sessions);
+        write_family(
+            &mut out,
+            "agent_discord_prompts_in_flight",
+            "gauge",
+            "Turns currently running.",
+        );
+        let _ = writeln!(
+            out,
+            "agent_discord_prompts_in_flight {}",
+            self.prompts_in_flight.load(Ordering::Relaxed).max(0)
+        );
+        write_family(
+            &mut out,
+            "agent_discord_agent_errors_total",
+            "counter",
+            "Turns that ended with an error or timeout, by backend.",
+        );
+        if let Ok(errors) = self.agent_errors.lock() {
+            write_by_backend(&mut out, "agent_discord_agent_errors_total", errors.iter());
+        }
+        write_family(
+            &mut out,
+            "agent_discord_discord_edit_failures_total",
+            "counter",
+            "Response message edits that the Discord API rejected.",
+        );
+        let _ = writeln!(
+            out,
+            "agent_discord_discord_edit_failures_total {}",
+            self.edit_failures.load(Ordering::Relaxed)
+        );
+        write_family(
+            &mut out,
+            "agent_discord_backend_restarts_total",
+            "counter",
+            "Backend server processes started again after they died, by backend.",
+        );
+        if let Ok(restarts) = self.backend_restarts.lock() {
+            write_by_backend(
+                &mut out,
+                "agent_discord_backend_restarts_total",
+                restarts.iter(),
+            );
+        }
+        out
+    }
+}
+
+/// 依請求的第一行回應：`GET /metrics` 回傳指標，其他路徑 404
+fn response(request: &str, metrics: impl FnOnce() -> String) -> String {
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// 下載檔案回應的標頭；檔名中的引號與控制字元換成底線
+fn download_head(name: &str, len: usize) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c == '"' || c.is_control() { '_' } else { c })
+        .collect();
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Disposition: attachment; filename=\"{}\"\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        name, len
+    )
+}
+
+/// `GET /downloads/...` 的目標路徑；其他請求回傳 None
+fn download_target(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) if target.starts_with(crate::downloads::ROUTE) => Some(target),
+        _ => None,
+    }
+}
+
+async fn serve_connection(mut stream: TcpStream, state: Arc<crate::AppState>) {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_BYTES {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    let request = String::from_utf8_lossy(&buf);
+    // 同一個端點也提供附件過大時改貼的下載連結；連結無效或過期時照常回 404
+    if let Some(target) = download_target(&request) {
+        if let Some((name, bytes)) = crate::downloads::open(target).await {
+            let head = download_head(&name, bytes.len());
+            if let Err(e) = async {
+                stream.write_all(head.as_bytes()).await?;
+                stream.write_all(&bytes).await
+            }
+            .await
+            {
+                warn!("⚠️ Failed to send download {}: {}", name, e);
+            }
+            let _ = stream.shutdown().await;
+            return;
+        }
+    }
+    let mut sessions = BTreeMap::new();
+    for (backend, count) in state.sessions().session_counts().await {
+        sessions.insert(backend.to_string(), count as u64);
+    }
+    let reply = response(&request, || registry().render(&sessions));
+    if let Err(e) = stream.write_all(reply.as_bytes()).await {
+        warn!("⚠️ Failed to answer metrics scrape: {}", e);
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// `[metrics] port` 不為 0 時開啟 Prometheus 抓取端點
+pub fn spawn_server(state: Arc<crate::AppState>) {
+    let port = state.config.metrics.port;
+    if port == 0 {
+        if !state.config.downloads.public_url.trim().is_empty() {
+            warn!("⚠️ [downloads] public_url is set but [metrics] port is 0; download links will not be served");
+        }
+        return;
+    }
+    let addr = format!("{}:{}", state.config.metrics.bind_address, port);
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("❌ Failed to bind metrics endpoint on {}: {}", addr, e);
+                return;
+            }
+        };
+        info!("📈 Serving Prometheus metrics on http://{}/metrics", addr);
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve_connection(stream, Arc::clone(&state)));
+                }
+                Err(e) => warn!("⚠️ Metrics endpoint accept failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_lists_every_family() {
+        let registry = Registry::default();
+        registry.prompts_in_flight.store(2, Ordering::Relaxed);
+        registry.edit_failures.store(3, Ordering::Relaxed);
+        bump(&registry.agent_errors, "kilo");
+        bump(&registry.agent_errors, "kilo");
+        bump(&registry.backend_restarts, "opencode");
+        let sessions = BTreeMap::from([("pi".to_string(), 4)]);
+
+        let text = registry.render(&sessions);
+        assert!(text.contains("# TYPE agent_discord_active_sessions gauge\n"));
+        assert!(text.contains("agent_discord_active_sessions{backend=\"pi\"} 4\n"));
+        assert!(text.contains("agent_discord_prompts_in_flight 2\n"));
+        assert!(text.contains("agent_discord_agent_errors_total{backend=\"kilo\"} 2\n"));
+        assert!(text.contains("agent_discord_discord_edit_failures_total 3\n"));
+        assert!(text.contains("agent_discord_backend_restarts_total{backend=\"opencode\"} 1\n"));
+    }
+
+    #[test]
+    fn test_response_serves_metrics_path_only() {
+        let ok = response("GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n", || {
+            "up 1\n".to_string()
+        });
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.contains("Content-Length: 5\r\n"));
+        assert!(ok.ends_with("\r\n\r\nup 1\n"));
+
+        let missing = response("GET / HTTP/1.1\r\n\r\n", || unreachable!());
+        assert!(missing.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn test_download_requests_are_routed_separately() {
+        assert_eq!(
+            download_target("GET /downloads/abc?expires=1&sig=x HTTP/1.1\r\n\r\n"),
+            Some("/downloads/abc?expires=1&sig=x")
+        );
+        assert_eq!(download_target("GET /metrics HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(
+            download_target("POST /downloads/abc HTTP/1.1\r\n\r\n"),
+            None
+        );
+
+        let head = download_head("a\"b.zip", 3);
+        assert!(head.contains("filename=\"a_b.zip\"\r\n"));
+        assert!(head.contains("Content-Length: 3\r\n"));
+        assert!(head.ends_with("\r\n\r\n"));
+    }
+}
@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateMessage,
+    EditInteractionResponse, Http,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::agent::UserInput;
+use crate::i18n::I18n;
+
+pub const BUTTON_PREFIX: &str = "queue_journal_";
+pub const REPLAY_BUTTON: &str = "queue_journal_replay";
+pub const DISCARD_BUTTON: &str = "queue_journal_discard";
+
+/// 單一頻道的佇列紀錄
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ChannelJournal {
+    /// 從佇列取出、正在執行的批次；回合結束時清除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_flight: Option<UserInput>,
+    /// 仍在佇列中等待的訊息
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub queued: Vec<UserInput>,
+    /// 上次程序結束時未處理完的訊息，等待頻道中確認重送或捨棄
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recovered: Vec<UserInput>,
+}
+
+impl ChannelJournal {
+    fn is_empty(&self) -> bool {
+        self.in_flight.is_none() && self.queued.is_empty() && self.recovered.is_empty()
+    }
+}
+
+/// 排隊中的提示依頻道寫入 `queue/<channel_id>.json`，程序異常結束後可在頻道中確認重送
+pub struct QueueJournal {
+    dir: PathBuf,
+    channels: Mutex<HashMap<u64, ChannelJournal>>,
+}
+
+impl QueueJournal {
+    pub async fn load() -> Self {
+        let journal = Self::load_from(crate::migrate::get_queue_dir()).await;
+        journal
+            .import_legacy(&crate::migrate::get_base_dir().join("queue_journal.json"))
+            .await;
+        journal
+    }
+
+    /// 讀取紀錄，並把上次未完成的訊息移到 recovered 等待確認
+    pub async fn load_from(dir: PathBuf) -> Self {
+        let mut channels = HashMap::new();
+        if let Ok(mut entries) = tokio::fs::read_dir(&dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                let Some(channel_id) = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .and_then(|s| s.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+                let parsed = tokio::fs::read_to_string(&path)
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|c| Ok(serde_json::from_str::<ChannelJournal>(&c)?));
+                match parsed {
+                    Ok(journal) => {
+                        channels.insert(channel_id, journal);
+                    }
+                    Err(e) => warn!("⚠️ Failed to parse queue journal {:?}: {}", path, e),
+                }
+            }
+        }
+        let journal = Self {
+            dir,
+            channels: Mutex::new(HashMap::new()),
+        };
+        journal.recover(channels).await;
+        journal
+    }
+
+    /// 舊版把所有頻道寫在單一 queue_journal.json，匯入後刪除
+    async fn import_legacy(&self, path: &Path) {
+        #[derive(Deserialize)]
+        struct LegacyStore {
+            #[serde(default)]
+            channels: HashMap<String, ChannelJournal>,
+        }
+
+        let Ok(content) = tokio::fs::read_to_string(path).await else {
+            return;
+        };
+        match serde_json::from_str::<LegacyStore>(&content) {
+            Ok(store) => {
+                let channels = store
+                    .channels
+                    .into_iter()
+                    .filter_map(|(id, j)| Some((id.parse().ok()?, j)))
+                    .collect();
+                self.recover(channels).await;
+                info!("📦 Migrated {:?} -> {:?}", path, self.dir);
+            }
+            Err(e) => warn!("⚠️ Failed to parse queue journal {:?}: {}", path, e),
+        }
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
+    async fn recover(&self, loaded: HashMap<u64, ChannelJournal>) {
+        let mut channels = self.channels.lock().await;
+        for (channel_id, mut loaded) in loaded {
+            let unfinished = loaded.in_flight.take().into_iter();
+            let unfinished: Vec<_> = unfinished.chain(loaded.queued.drain(..)).collect();
+            loaded.recovered.extend(unfinished);
+            let journal = channels.entry(channel_id).or_default();
+            journal.recovered.extend(loaded.recovered);
+            let journal = journal.clone();
+            if journal.is_empty() {
+                channels.remove(&channel_id);
+            }
+            self.persist(channel_id, &journal).await;
+        }
+    }
+
+    fn channel_path(&self, channel_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", channel_id))
+    }
+
+    async fn persist(&self, channel_id: u64, journal: &ChannelJournal) {
+        let path = self.channel_path(channel_id);
+        let write = async {
+            if journal.is_empty() {
+                match tokio::fs::remove_file(&path).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => return Ok(()),
+                }
+            }
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let content = serde_json::to_string_pretty(journal)?;
+            let tmp = path.with_extension("json.tmp");
+            tokio::fs::write(&tmp, content).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            anyhow::Ok(())
+        };
+        if let Err(e) = write.await {
+            warn!(
+                "⚠️ Failed to save queue journal for channel {}: {}",
+                channel_id, e
+            );
+        }
+    }
+
+    async fn update(&self, channel_id: u64, f: impl FnOnce(&mut ChannelJournal)) {
+        let mut channels = self.channels.lock().await;
+        let journal = channels.entry(channel_id).or_default();
+        let before = journal.clone();
+        f(journal);
+        let journal = journal.clone();
+        if journal.is_empty() {
+            channels.remove(&channel_id);
+        }
+        if journal != before {
+            self.persist(channel_id, &journal).await;
+        }
+    }
+
+    /// 以頻道佇列目前的內容取代紀錄
+    pub async fn record_queue(&self, channel_id: u64, queued: Vec<UserInput>) {
+        self.update(channel_id, |j| j.queued = queued).await;
+    }
+
+    /// 佇列中的批次開始執行
+    pub async fn start(&self, channel_id: u64, input: &UserInput) {
+        self.update(channel_id, |j| j.in_flight = Some(input.clone()))
+            .await;
+    }
+
+    /// 頻道的回合結束，正在執行的批次視為完成
+    pub async fn finish(&self, channel_id: u64) {
+        self.update(channel_id, |j| j.in_flight = None).await;
+    }
+
+    /// 等待確認的頻道與訊息數
+    pub async fn recovered(&self) -> Vec<(u64, usize)> {
+        let channels = self.channels.lock().await;
+        let mut out: Vec<_> = channels
+            .iter()
+            .filter(|(_, j)| !j.recovered.is_empty())
+            .map(|(id, j)| (*id, j.recovered.len()))
+            .collect();
+        out.sort();
+        out
+    }
+
+    /// 取出頻道等待確認的訊息；已不存在的附件檔案會被略過
+    pub async fn take_recovered(&self, channel_id: u64) -> Vec<UserInput> {
+        let mut inputs = Vec::new();
+        self.update(channel_id, |j| inputs = std::mem::take(&mut j.recovered))
+            .await;
+        for input in &mut inputs {
+            input.files.retain(|f| Path::new(&f.local_path).exists());
+        }
+        inputs
+    }
+}
+
+pub fn confirmation(i18n: &I18n, count: usize) -> CreateMessage {
+    CreateMessage::new()
+        .content(i18n.get_args("queue_journal_recovered", &[count.to_string()]))
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(REPLAY_BUTTON)
+                .label(i18n.get("queue_journal_replay"))
+                .style(ButtonStyle::Primary),
+            CreateButton::new(DISCARD_BUTTON)
+                .label(i18n.get("queue_journal_discard"))
+                .style(ButtonStyle::Secondary),
+        ])])
+}
+
+/// 啟動時在有未完成訊息的頻道詢問是否重送
+pub async fn announce_recovered(state: &crate::AppState, http: &Http) {
+    for (channel_id, count) in state.queue_journal.recovered().await {
+        info!(
+            "📒 Channel {} has {} unprocessed queued prompt(s) from the last run",
+            channel_id, count
+        );
+        let message = {
+            let i18n = state.channel_i18n(channel_id).await;
+            let i18n = i18n.read().await;
+            confirmation(&i18n, count)
+        };
+        let channel = serenity::all::ChannelId::new(channel_id);
+        if let Err(e) = channel.send_message(http, message).await {
+            warn!(
+                "⚠️ Failed to ask channel {} about recovered prompts: {}",
+                channel_id, e
+            );
+        }
+    }
+}
+
+/// 「重送」與「捨棄」按鈕：重送時依序排入佇列並開始處理
+pub async fn handle_button(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !crate::turn_controls::authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction.defer_ephemeral(&ctx.http).await?;
+
+    let channel_id = interaction.channel_id.get();
+    let inputs = state.queue_journal.take_recovered(channel_id).await;
+    let replay = interaction.data.custom_id == REPLAY_BUTTON;
+    let i18n = state.channel_i18n(channel_id).await;
+    let msg = if inputs.is_empty() {
+        i18n.read().await.get("queue_journal_expired")
+    } else if replay {
+        let count = inputs.len();
+        let next = {
+            let mut pending = state.pending_inputs.lock().await;
+            let queue = pending.entry(channel_id).or_default();
+            for input in inputs {
+                queue.push(input);
+            }
+            queue.next_batch(state.config.max_batch_tokens)
+        };
+        state.sync_queue_journal(channel_id).await;
+        if let Some(next) = next {
+            let _ = state.queued_loop_tx.send((channel_id, next));
+        }
+        info!(
+            "📒 Replaying {} recovered prompt(s) on channel {}",
+            count, channel_id
+        );
+        i18n.read()
+            .await
+            .get_args("queue_journal_replaying", &[count.to_string()])
+    } else {
+        info!(
+            "📒 Discarded {} recovered prompt(s) on channel {}",
+            inputs.len(),
+            channel_id
+        );
+        i18n.read().await.get("queue_journal_discarded")
+    };
+    interaction
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        .await?;
+    // 處理過後移除原訊息上的按鈕
+    let _ = interaction
+        .message
+        .clone()
+        .edit(
+            &ctx.http,
+            serenity::all::EditMessage::new().components(Vec::new()),
+        )
+        .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_unfinished_prompts_are_recovered_after_restart() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("queue");
+
+        let journal = QueueJournal::load_from(path.clone()).await;
+        journal
+            .record_queue(1, vec![UserInput::new_text("first".to_string())])
+            .await;
+        journal
+            .start(1, &UserInput::new_text("first".to_string()))
+            .await;
+        journal
+            .record_queue(
+                1,
+                vec![
+                    UserInput::new_text("second".to_string()),
+                    UserInput::new_text("third".to_string()),
+                ],
+            )
+            .await;
+        // 另一個頻道的回合已完成，不會被重送
+        journal
+            .start(2, &UserInput::new_text("done".to_string()))
+            .await;
+        journal.finish(2).await;
+        drop(journal);
+
+        let restarted = QueueJournal::load_from(path.clone()).await;
+        assert_eq!(restarted.recovered().await, vec![(1, 3)]);
+        let texts: Vec<_> = restarted
+            .take_recovered(1)
+            .await
+            .into_iter()
+            .map(|i| i.text)
+            .collect();
+        assert_eq!(texts, vec!["first", "second", "third"]);
+        assert!(restarted.take_recovered(1).await.is_empty());
+        assert_eq!(std::fs::read_dir(&path).expect("queue dir").count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_single_file_journal_is_imported_per_channel() {
+        let dir = tempdir().expect("tempdir");
+        let legacy = dir.path().join("queue_journal.json");
+        std::fs::write(
+            &legacy,
+            r#"{"channels":{"7":{"in_flight":{"text":"old","files":[]}}}}"#,
+        )
+        .expect("write legacy");
+
+        let journal = QueueJournal::load_from(dir.path().join("queue")).await;
+        journal.import_legacy(&legacy).await;
+        assert!(!legacy.exists());
+        assert!(dir.path().join("queue").join("7.json").exists());
+        assert_eq!(journal.recovered().await, vec![(7, 1)]);
+    }
+}
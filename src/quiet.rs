@@ -0,0 +1,233 @@
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, Http, MessageId};
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex, RwLock};
+use tracing::{info, warn};
+
+use crate::i18n::I18n;
+
+const RELEASE_CHECK_SECS: u64 = 60;
+
+/// 每日安靜時段 (伺服器本地時間，與排程相同)；start 晚於 end 表示跨過午夜
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// 解析 `HH:MM`
+pub fn parse_time(raw: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M").ok()
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// 下一次安靜時段結束的時間
+    pub fn next_end<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> DateTime<Tz> {
+        let today = now.date_naive().and_time(self.end);
+        let naive = if today > now.naive_local() {
+            today
+        } else {
+            today + chrono::Duration::days(1)
+        };
+        now.timezone()
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap_or_else(|| now.clone())
+    }
+}
+
+/// 頻道目前若在安靜時段內，回傳時段結束的 Unix 時間
+pub fn quiet_until(hours: Option<&QuietHours>, now: DateTime<Local>) -> Option<i64> {
+    let hours = hours?;
+    hours
+        .contains(now.time())
+        .then(|| hours.next_end(&now).timestamp())
+}
+
+/// 安靜時段內完成、尚未送出的回合結果
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeldDelivery {
+    pub channel_id: ChannelId,
+    /// 回合開始時發出的靜音佔位訊息，送出結果後刪除
+    pub placeholder: MessageId,
+    pub views: Vec<(String, u32, String)>,
+    pub completed_at: i64,
+}
+
+/// 安靜時段暫存區：排程等非使用者觸發的回合在時段內完成時先保留，時段結束後才發出
+#[derive(Default)]
+pub struct QuietQueue {
+    items: Mutex<Vec<HeldDelivery>>,
+}
+
+impl QuietQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn hold(&self, delivery: HeldDelivery) {
+        self.items.lock().await.push(delivery);
+    }
+
+    /// 取出不再處於安靜時段的頻道的暫存結果 (依完成順序)
+    pub async fn take_released(
+        &self,
+        still_quiet: impl Fn(ChannelId) -> bool,
+    ) -> Vec<HeldDelivery> {
+        let mut items = self.items.lock().await;
+        let (held, released): (Vec<_>, Vec<_>) =
+            items.drain(..).partition(|d| still_quiet(d.channel_id));
+        *items = held;
+        released
+    }
+
+    /// 以新訊息送出結果並註明原完成時間，成功後刪除佔位訊息
+    async fn deliver(http: &Http, delivery: &HeldDelivery, note: &str) -> serenity::Result<()> {
+        let embeds: Vec<CreateEmbed> = delivery
+            .views
+            .iter()
+            .map(|(title, color, body)| {
+                CreateEmbed::new()
+                    .title(title)
+                    .color(*color)
+                    .description(body)
+            })
+            .collect();
+        delivery
+            .channel_id
+            .send_message(http, CreateMessage::new().content(note).embeds(embeds))
+            .await?;
+        if let Err(e) = delivery
+            .channel_id
+            .delete_message(http, delivery.placeholder)
+            .await
+        {
+            warn!(
+                "⚠️ Failed to delete quiet hours placeholder in {}: {}",
+                delivery.channel_id, e
+            );
+        }
+        Ok(())
+    }
+
+    /// 每分鐘檢查一次，把安靜時段已結束 (或已取消) 的頻道結果送出；失敗的留待下次重試
+    /// `http` 跟著目前的 Discord 連線 (備用 token 接手後換成新的)
+    pub fn spawn_release_loop(
+        self: Arc<Self>,
+        http: watch::Receiver<Arc<Http>>,
+        i18n: Arc<RwLock<I18n>>,
+    ) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(RELEASE_CHECK_SECS)).await;
+                let http = http.borrow().clone();
+                let channel_config = crate::commands::agent::ChannelConfig::load()
+                    .await
+                    .unwrap_or_default();
+                let now = Local::now();
+                let released = self
+                    .take_released(|channel_id| {
+                        let hours = channel_config
+                            .channels
+                            .get(&channel_id.to_string())
+                            .and_then(|e| e.quiet_hours.as_ref());
+                        quiet_until(hours, now).is_some()
+                    })
+                    .await;
+                for delivery in released {
+                    let note = i18n
+                        .read()
+                        .await
+                        .get_args("quiet_delivered_note", &[delivery.completed_at.to_string()]);
+                    match Self::deliver(&http, &delivery, &note).await {
+                        Ok(_) => info!(
+                            "🌅 Delivered held response in channel {}",
+                            delivery.channel_id
+                        ),
+                        Err(e) => {
+                            warn!(
+                                "⚠️ Failed to deliver held response in {}: {}",
+                                delivery.channel_id, e
+                            );
+                            self.hold(delivery).await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            start: parse_time(start).expect("start"),
+            end: parse_time(end).expect("end"),
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours_contains_handles_midnight_wrap() {
+        let night = hours("22:00", "07:00");
+        assert!(night.contains(parse_time("23:30").unwrap()));
+        assert!(night.contains(parse_time("03:00").unwrap()));
+        assert!(!night.contains(parse_time("07:00").unwrap()));
+        assert!(!night.contains(parse_time("12:00").unwrap()));
+
+        let lunch = hours("12:00", "13:00");
+        assert!(lunch.contains(parse_time("12:30").unwrap()));
+        assert!(!lunch.contains(parse_time("13:30").unwrap()));
+        assert!(parse_time("25:00").is_none());
+    }
+
+    #[test]
+    fn test_next_end_rolls_over_to_tomorrow() {
+        let night = hours("22:00", "07:00");
+        let late = Utc.with_ymd_and_hms(2026, 3, 1, 23, 0, 0).unwrap();
+        assert_eq!(
+            night.next_end(&late),
+            Utc.with_ymd_and_hms(2026, 3, 2, 7, 0, 0).unwrap()
+        );
+        let early = Utc.with_ymd_and_hms(2026, 3, 2, 3, 0, 0).unwrap();
+        assert_eq!(
+            night.next_end(&early),
+            Utc.with_ymd_and_hms(2026, 3, 2, 7, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_take_released_keeps_channels_still_quiet() {
+        let queue = QuietQueue::new();
+        for (channel, msg) in [(1u64, 10u64), (2, 20), (1, 11)] {
+            queue
+                .hold(HeldDelivery {
+                    channel_id: ChannelId::new(channel),
+                    placeholder: MessageId::new(msg),
+                    views: vec![],
+                    completed_at: 0,
+                })
+                .await;
+        }
+        let released = queue
+            .take_released(|channel| channel == ChannelId::new(2))
+            .await;
+        let ids: Vec<u64> = released.iter().map(|d| d.placeholder.get()).collect();
+        assert_eq!(ids, vec![10, 11]);
+        let rest = queue.take_released(|_| false).await;
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].channel_id, ChannelId::new(2));
+    }
+}
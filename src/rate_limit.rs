@@ -0,0 +1,149 @@
+use serenity::all::CreateEmbed;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::LimitsConfig;
+use crate::i18n::I18n;
+
+/// 使用者提示數的計算區間
+const USER_WINDOW: Duration = Duration::from_secs(60);
+/// 全域回合數已滿時建議的重試間隔；無法得知其他回合何時結束
+const CAPACITY_RETRY: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateCheck {
+    Allowed,
+    /// 使用者在一分鐘內送出太多提示
+    UserLimited {
+        retry_after: Duration,
+    },
+    /// 同時執行的回合數已達全域上限
+    AtCapacity {
+        retry_after: Duration,
+    },
+}
+
+/// config.toml `[limits]`：每位使用者每分鐘的提示數與全域同時執行的回合數。
+/// 超過時直接拒絕，不再把訊息堆進頻道佇列
+pub struct RateLimiter {
+    config: LimitsConfig,
+    users: Mutex<HashMap<u64, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: LimitsConfig) -> Self {
+        Self {
+            config,
+            users: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 訊息要交給代理前呼叫；`active_turns` 為目前執行中的回合數，
+    /// `channel_busy` 表示頻道已有回合，新訊息只會併入下一批而不會開新回合
+    pub async fn check(
+        &self,
+        user_id: u64,
+        active_turns: usize,
+        channel_busy: bool,
+        now: Instant,
+    ) -> RateCheck {
+        let max_turns = self.config.global_concurrent_turns;
+        if max_turns > 0 && !channel_busy && active_turns >= max_turns {
+            return RateCheck::AtCapacity {
+                retry_after: CAPACITY_RETRY,
+            };
+        }
+
+        let per_minute = self.config.user_prompts_per_minute;
+        if per_minute == 0 {
+            return RateCheck::Allowed;
+        }
+        let mut users = self.users.lock().await;
+        users.retain(|_, sent| {
+            while sent
+                .front()
+                .is_some_and(|t| now.saturating_duration_since(*t) >= USER_WINDOW)
+            {
+                sent.pop_front();
+            }
+            !sent.is_empty()
+        });
+        let sent = users.entry(user_id).or_default();
+        if sent.len() >= per_minute {
+            let oldest = sent.front().copied().unwrap_or(now);
+            return RateCheck::UserLimited {
+                retry_after: USER_WINDOW.saturating_sub(now.saturating_duration_since(oldest)),
+            };
+        }
+        sent.push_back(now);
+        RateCheck::Allowed
+    }
+}
+
+/// 顯示給使用者的秒數，不足一秒進位
+pub fn retry_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+}
+
+/// 被拒絕時回覆的 embed；允許時回傳 None
+pub fn limited_embed(i18n: &I18n, check: RateCheck) -> Option<CreateEmbed> {
+    let (key, retry_after) = match check {
+        RateCheck::Allowed => return None,
+        RateCheck::UserLimited { retry_after } => ("rate_limited_user", retry_after),
+        RateCheck::AtCapacity { retry_after } => ("rate_limited_capacity", retry_after),
+    };
+    Some(
+        CreateEmbed::new()
+            .title(i18n.get("rate_limited_title"))
+            .description(i18n.get_args(key, &[retry_secs(retry_after).to_string()]))
+            .color(0xFFA500),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(user_prompts_per_minute: usize, global_concurrent_turns: usize) -> RateLimiter {
+        RateLimiter::new(LimitsConfig {
+            user_prompts_per_minute,
+            global_concurrent_turns,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_user_limit_uses_sliding_minute() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let limiter = limiter(2, 0);
+
+        assert_eq!(limiter.check(1, 0, false, at(0)).await, RateCheck::Allowed);
+        assert_eq!(limiter.check(1, 0, true, at(10)).await, RateCheck::Allowed);
+        assert_eq!(
+            limiter.check(1, 0, true, at(20)).await,
+            RateCheck::UserLimited {
+                retry_after: Duration::from_secs(40)
+            }
+        );
+        // 其他使用者各自計算
+        assert_eq!(limiter.check(2, 0, true, at(20)).await, RateCheck::Allowed);
+        assert_eq!(limiter.check(1, 0, true, at(60)).await, RateCheck::Allowed);
+    }
+
+    #[tokio::test]
+    async fn test_global_cap_only_blocks_new_turns() {
+        let now = Instant::now();
+        let limiter = limiter(0, 2);
+        assert_eq!(limiter.check(1, 1, false, now).await, RateCheck::Allowed);
+        assert_eq!(
+            limiter.check(1, 2, false, now).await,
+            RateCheck::AtCapacity {
+                retry_after: CAPACITY_RETRY
+            }
+        );
+        assert_eq!(limiter.check(1, 2, true, now).await, RateCheck::Allowed);
+        assert_eq!(retry_secs(Duration::from_millis(1500)), 2);
+        assert_eq!(retry_secs(Duration::from_secs(3)), 3);
+    }
+}
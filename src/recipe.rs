@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{AgentType, THINKING_LEVELS};
+use crate::commands::agent::ChannelEntry;
+
+/// 目前的配方格式版本；較新版本產生的配方無法套用
+pub const RECIPE_VERSION: u32 = 1;
+
+/// 頻道設定的可攜配方：`/session recipe` 匯出，`/session apply-recipe` 在其他頻道重建相同設定。
+/// 系統提示來自 bot 的 prompts 目錄，所有頻道共用，因此不在配方內
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionRecipe {
+    pub version: u32,
+    pub backend: AgentType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assistant_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turn_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workdir: Option<String>,
+}
+
+/// 套用配方後需要重開 session 的原因
+#[derive(Debug, Default, PartialEq)]
+pub struct RecipeChanges {
+    /// 後端或工作目錄改變，既有 session 無法沿用
+    pub new_session: bool,
+}
+
+impl SessionRecipe {
+    pub fn from_entry(backend: AgentType, entry: Option<&ChannelEntry>) -> Self {
+        let entry = entry.cloned().unwrap_or_default();
+        Self {
+            version: RECIPE_VERSION,
+            backend,
+            model_provider: entry.model_provider,
+            model_id: entry.model_id,
+            thinking_level: entry.thinking_level,
+            assistant_name: entry.assistant_name,
+            disabled_tools: entry.disabled_tools,
+            max_turn_secs: entry.max_turn_secs,
+            workdir: entry.workdir,
+        }
+    }
+
+    /// 可直接貼回 `/session apply-recipe` 的程式碼區塊
+    pub fn to_snippet(&self) -> String {
+        let json = serde_json::to_string_pretty(self).unwrap_or_default();
+        format!("```json\n{}\n```", json)
+    }
+
+    /// 解析貼上的配方；允許外層的 ``` 程式碼區塊，
+    /// 以及 Discord 指令參數把換行變成空白的情況
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let trimmed = input.trim();
+        let body = trimmed
+            .strip_prefix("```")
+            .map(|rest| rest.trim_start_matches("json"))
+            .and_then(|rest| rest.trim_end().strip_suffix("```"))
+            .unwrap_or(trimmed);
+        let recipe: Self = serde_json::from_str(body.trim())?;
+        if recipe.version > RECIPE_VERSION {
+            anyhow::bail!(
+                "recipe version {} is newer than supported version {}",
+                recipe.version,
+                RECIPE_VERSION
+            );
+        }
+        if recipe.model_provider.is_some() != recipe.model_id.is_some() {
+            anyhow::bail!("model_provider and model_id must be set together");
+        }
+        if let Some(level) = &recipe.thinking_level {
+            if !THINKING_LEVELS.contains(&level.as_str()) {
+                anyhow::bail!("unknown thinking level: {}", level);
+            }
+        }
+        Ok(recipe)
+    }
+
+    /// 以配方取代頻道的對應設定；其餘設定 (授權、提及政策等) 保留
+    pub fn apply_to(&self, entry: &mut ChannelEntry) -> RecipeChanges {
+        let new_session = entry.agent_type != self.backend || entry.workdir != self.workdir;
+        entry.agent_type = self.backend.clone();
+        entry.model_provider = self.model_provider.clone();
+        entry.model_id = self.model_id.clone();
+        entry.thinking_level = self.thinking_level.clone();
+        entry.assistant_name = self.assistant_name.clone();
+        entry.disabled_tools = self.disabled_tools.clone();
+        entry.max_turn_secs = self.max_turn_secs;
+        entry.workdir = self.workdir.clone();
+        if new_session {
+            entry.session_id = None;
+        }
+        RecipeChanges { new_session }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured_entry() -> ChannelEntry {
+        ChannelEntry {
+            agent_type: AgentType::Opencode,
+            session_id: Some("ses_1".to_string()),
+            model_provider: Some("openrouter".to_string()),
+            model_id: Some("openai/gpt-4.1".to_string()),
+            thinking_level: Some("high".to_string()),
+            assistant_name: Some("Reviewer".to_string()),
+            disabled_tools: vec!["bash".to_string()],
+            max_turn_secs: Some(600),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_recipe_roundtrip_through_flattened_snippet() {
+        let entry = configured_entry();
+        let recipe = SessionRecipe::from_entry(AgentType::Opencode, Some(&entry));
+        let snippet = recipe.to_snippet();
+        assert!(snippet.starts_with("```json\n{"));
+        assert!(!snippet.contains("workdir"));
+        // Discord 指令參數無法輸入換行，貼上後換行會變成空白
+        let pasted = snippet.replace('\n', " ");
+        assert_eq!(SessionRecipe::parse(&pasted).expect("parse"), recipe);
+        assert_eq!(
+            SessionRecipe::parse(r#"{"version":1,"backend":"pi"}"#)
+                .expect("minimal")
+                .backend,
+            AgentType::Pi
+        );
+    }
+
+    #[test]
+    fn test_recipe_parse_rejects_invalid_input() {
+        assert!(SessionRecipe::parse("not a recipe").is_err());
+        assert!(SessionRecipe::parse(r#"{"version":2,"backend":"pi"}"#).is_err());
+        assert!(
+            SessionRecipe::parse(r#"{"version":1,"backend":"pi","thinking_level":"max"}"#).is_err()
+        );
+        assert!(SessionRecipe::parse(r#"{"version":1,"backend":"pi","model_id":"gpt"}"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_recipe_resets_session_only_when_needed() {
+        let recipe = SessionRecipe::from_entry(AgentType::Opencode, Some(&configured_entry()));
+
+        let mut same_backend = ChannelEntry {
+            agent_type: AgentType::Opencode,
+            session_id: Some("ses_2".to_string()),
+            mention_only: true,
+            ..Default::default()
+        };
+        assert_eq!(recipe.apply_to(&mut same_backend), RecipeChanges::default());
+        assert_eq!(same_backend.session_id.as_deref(), Some("ses_2"));
+        assert_eq!(same_backend.model_id.as_deref(), Some("openai/gpt-4.1"));
+        assert_eq!(same_backend.disabled_tools, vec!["bash".to_string()]);
+        assert!(same_backend.mention_only);
+
+        let mut other_backend = ChannelEntry {
+            agent_type: AgentType::Pi,
+            session_id: Some("pi".to_string()),
+            ..Default::default()
+        };
+        assert!(recipe.apply_to(&mut other_backend).new_session);
+        assert_eq!(other_backend.agent_type, AgentType::Opencode);
+        assert!(other_backend.session_id.is_none());
+    }
+}
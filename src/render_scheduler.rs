@@ -0,0 +1,188 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct EditSlots {
+    in_flight: usize,
+    /// 等待中的最終結果編輯；有任何一筆在等時，中間進度的編輯不得插隊
+    waiting_final: usize,
+}
+
+/// 所有頻道共用的 Discord 編輯排程：限制同時進行的編輯數，最終結果優先於串流中的進度
+pub struct RenderScheduler {
+    max_in_flight: usize,
+    slots: Mutex<EditSlots>,
+    notify: Notify,
+}
+
+/// 佔用一個編輯名額，結束 (drop) 時釋放
+pub struct EditPermit {
+    scheduler: Option<Arc<RenderScheduler>>,
+}
+
+impl Drop for EditPermit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.lock().in_flight -= 1;
+            scheduler.notify.notify_waiters();
+        }
+    }
+}
+
+/// 最終編輯等待中的登記；等待被取消 (例如 /abort 中止渲染任務) 時也會撤銷
+struct WaitingFinal<'a>(&'a RenderScheduler);
+
+impl<'a> WaitingFinal<'a> {
+    fn new(scheduler: &'a RenderScheduler) -> Self {
+        scheduler.lock().waiting_final += 1;
+        Self(scheduler)
+    }
+}
+
+impl Drop for WaitingFinal<'_> {
+    fn drop(&mut self) {
+        self.0.lock().waiting_final -= 1;
+        // 最終編輯不再等待後，被擋住的中間進度可以繼續競爭名額
+        self.0.notify.notify_waiters();
+    }
+}
+
+impl RenderScheduler {
+    /// `max_in_flight` 為 0 表示不限制
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            slots: Mutex::new(EditSlots::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, EditSlots> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn try_take(&self, is_final: bool) -> bool {
+        let mut slots = self.lock();
+        let free = slots.in_flight < self.max_in_flight;
+        if free && (is_final || slots.waiting_final == 0) {
+            slots.in_flight += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 取得編輯名額。最終結果一定等到名額；中間進度最多等 `max_wait`，
+    /// 逾時回傳 None 表示放棄這次渲染，交給下一輪較新的內容取代
+    pub async fn acquire(
+        self: &Arc<Self>,
+        is_final: bool,
+        max_wait: Duration,
+    ) -> Option<EditPermit> {
+        if self.max_in_flight == 0 {
+            return Some(EditPermit { scheduler: None });
+        }
+        let _waiting = is_final.then(|| WaitingFinal::new(self));
+        let deadline = tokio::time::Instant::now() + max_wait;
+        let granted = loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.try_take(is_final) {
+                break true;
+            }
+            if is_final {
+                notified.await;
+            } else if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                break false;
+            }
+        };
+        granted.then(|| EditPermit {
+            scheduler: Some(Arc::clone(self)),
+        })
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.lock().in_flight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_intermediate_edit_is_dropped_when_slots_are_busy() {
+        let scheduler = Arc::new(RenderScheduler::new(1));
+        let held = scheduler
+            .acquire(false, Duration::ZERO)
+            .await
+            .expect("free slot");
+        assert_eq!(scheduler.in_flight(), 1);
+        assert!(scheduler
+            .acquire(false, Duration::from_millis(20))
+            .await
+            .is_none());
+        drop(held);
+        assert_eq!(scheduler.in_flight(), 0);
+        assert!(scheduler.acquire(false, Duration::ZERO).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_final_edit_waits_and_goes_before_intermediate() {
+        let scheduler = Arc::new(RenderScheduler::new(1));
+        let held = scheduler
+            .acquire(false, Duration::ZERO)
+            .await
+            .expect("free slot");
+
+        let final_task = {
+            let scheduler = Arc::clone(&scheduler);
+            tokio::spawn(async move {
+                let permit = scheduler.acquire(true, Duration::ZERO).await;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                permit.is_some()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(held);
+        // 名額空出時先交給等待中的最終編輯，中間進度在期限內拿不到而被放棄
+        assert!(scheduler
+            .acquire(false, Duration::from_millis(10))
+            .await
+            .is_none());
+
+        assert!(final_task.await.expect("final task"));
+        assert_eq!(scheduler.in_flight(), 0);
+        assert!(scheduler.acquire(false, Duration::ZERO).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_final_edit_releases_its_priority() {
+        let scheduler = Arc::new(RenderScheduler::new(1));
+        let held = scheduler
+            .acquire(false, Duration::ZERO)
+            .await
+            .expect("free slot");
+        let waiting = {
+            let scheduler = Arc::clone(&scheduler);
+            tokio::spawn(async move { scheduler.acquire(true, Duration::ZERO).await.is_some() })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        waiting.abort();
+        let _ = waiting.await;
+        drop(held);
+        assert!(scheduler.acquire(false, Duration::ZERO).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_scheduler_never_blocks() {
+        let scheduler = Arc::new(RenderScheduler::new(0));
+        let permits: Vec<_> =
+            futures::future::join_all((0..5).map(|_| scheduler.acquire(false, Duration::ZERO)))
+                .await;
+        assert!(permits.iter().all(Option::is_some));
+        assert_eq!(scheduler.in_flight(), 0);
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+use crate::config::RetentionConfig;
+
+/// 背景清除的檢查間隔
+const PURGE_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// 可設定保留期限的資料類別
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Transcripts,
+    Uploads,
+    Usage,
+}
+
+impl Category {
+    pub const ALL: [Category; 3] = [Self::Transcripts, Self::Uploads, Self::Usage];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Transcripts => "transcripts",
+            Self::Uploads => "uploads",
+            Self::Usage => "usage",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == s)
+    }
+
+    /// 設定的保留天數；0 表示永久保留
+    fn retention_days(self, config: &RetentionConfig) -> u64 {
+        match self {
+            Self::Transcripts => config.transcripts_days,
+            Self::Uploads => config.uploads_days,
+            Self::Usage => config.usage_days,
+        }
+    }
+}
+
+/// 依設定計算清除的截止時間；未設定保留期限時回傳 None
+pub fn retention_cutoff(
+    config: &RetentionConfig,
+    category: Category,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match category.retention_days(config) {
+        0 => None,
+        days => Some(now - chrono::Duration::days(days as i64)),
+    }
+}
+
+/// 解析 `/admin purge` 的日期 (`YYYY-MM-DD`，UTC 當日零時)
+pub fn parse_before(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+/// 刪除 `cutoff` 之前的 pi 對話紀錄；仍在使用中的 session 不動，避免 pi 寫入已刪除的檔案
+async fn purge_transcripts(state: &crate::AppState, cutoff: SystemTime) -> anyhow::Result<usize> {
+    let dir = crate::migrate::get_sessions_dir("pi");
+    let mut removed = 0;
+    for (channel, path) in crate::fsck::pi_session_files(&dir) {
+        let Ok(channel_id) = channel.parse::<u64>() else {
+            continue;
+        };
+        let modified = tokio::fs::metadata(&path).await?.modified()?;
+        if modified >= cutoff || state.sessions().get_session(channel_id).await.is_some() {
+            continue;
+        }
+        tokio::fs::remove_file(&path).await?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// 清除某類別在 `cutoff` 之前的資料，回傳刪除的數量 (檔案或紀錄筆數)
+pub async fn purge(
+    state: &crate::AppState,
+    category: Category,
+    cutoff: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let removed = match category {
+        Category::Transcripts => purge_transcripts(state, cutoff.into()).await?,
+        Category::Uploads => state.upload_manager.purge_before(cutoff.into()).await?,
+        Category::Usage => {
+            state.analytics.purge_before(cutoff).await?
+                + state.metrics.purge_users_before(cutoff).await?
+        }
+    };
+    if removed > 0 {
+        info!(
+            "🧹 Purged {} {} item(s) older than {}",
+            removed,
+            category.name(),
+            cutoff.to_rfc3339()
+        );
+    }
+    Ok(removed)
+}
+
+/// 定期依設定的保留天數清除過期資料
+pub fn spawn_purge_loop(state: Arc<crate::AppState>) {
+    let config = state.config.retention.clone();
+    if Category::ALL
+        .iter()
+        .all(|c| retention_cutoff(&config, *c, Utc::now()).is_none())
+    {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            for category in Category::ALL {
+                let Some(cutoff) = retention_cutoff(&config, category, Utc::now()) else {
+                    continue;
+                };
+                if let Err(e) = purge(&state, category, cutoff).await {
+                    warn!("⚠️ Failed to purge {}: {}", category.name(), e);
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(PURGE_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_cutoff_and_date_parsing() {
+        let config = RetentionConfig {
+            transcripts_days: 30,
+            ..RetentionConfig::default()
+        };
+        let now = parse_before("2026-03-31").expect("date");
+        assert_eq!(
+            retention_cutoff(&config, Category::Transcripts, now),
+            parse_before("2026-03-01")
+        );
+        assert_eq!(retention_cutoff(&config, Category::Uploads, now), None);
+
+        assert_eq!(
+            parse_before(" 2026-01-02 ").map(|d| d.to_rfc3339()),
+            Some("2026-01-02T00:00:00+00:00".to_string())
+        );
+        assert!(parse_before("02/01/2026").is_none());
+        assert_eq!(Category::parse("usage"), Some(Category::Usage));
+        assert_eq!(Category::parse("feedback"), None);
+    }
+}
@@ -0,0 +1,170 @@
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateAttachment, CreateButton,
+    EditInteractionResponse, MessageId,
+};
+use tracing::info;
+
+use crate::agent::UserInput;
+
+pub const BUTTON_PREFIX: &str = "turn_retry_";
+
+/// 接續中斷回答的指示；原本的提問已在後端的對話紀錄中，只需補上已輸出的部分
+const RESUME_INSTRUCTION: &str = "Your previous reply was cut off by an error. Continue from where you left off without repeating what was already written.";
+
+pub fn retry_button(label: String, message_id: MessageId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "{}{}",
+        BUTTON_PREFIX, message_id
+    ))
+    .label(label)
+    .style(ButtonStyle::Primary)])
+}
+
+pub fn parse_button(custom_id: &str) -> Option<u64> {
+    custom_id.strip_prefix(BUTTON_PREFIX)?.parse().ok()
+}
+
+/// 出錯回合已輸出的回答，以 Markdown 附件保留原始格式方便複製；沒有內容時為空
+pub fn partial_attachments(message_id: MessageId, answer: &str) -> Vec<CreateAttachment> {
+    if answer.trim().is_empty() {
+        return Vec::new();
+    }
+    crate::delivery::prepare_upload(
+        &format!("partial-{}.md", message_id),
+        answer.as_bytes().to_vec(),
+        crate::delivery::DEFAULT_UPLOAD_LIMIT_BYTES,
+    )
+    .map(|parts| {
+        parts
+            .into_iter()
+            .map(|(name, bytes)| CreateAttachment::bytes(bytes, name))
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// 重試用的輸入：有部分回答時要求接續，否則原樣重送
+pub fn resume_input(original: &UserInput, partial: &str) -> UserInput {
+    if partial.trim().is_empty() {
+        return original.clone();
+    }
+    UserInput {
+        text: format!(
+            "{}\n\nWhat you had written so far:\n\n{}",
+            RESUME_INSTRUCTION,
+            partial.trim_end()
+        ),
+        // 附件已隨原本的提問送出
+        files: Vec::new(),
+        ..original.clone()
+    }
+}
+
+/// 「重試」按鈕：以接續指示重新執行出錯的回合，新回應標記為原訊息的修訂版
+pub async fn handle_retry(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !crate::turn_controls::authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction.defer_ephemeral(&ctx.http).await?;
+
+    let channel_id = interaction.channel_id;
+    let failed_msg = parse_button(&interaction.data.custom_id).map(MessageId::new);
+    // 只能重試頻道中最近一次出錯的回合，之後已有新回合時按鈕失效
+    let input = state
+        .last_turns
+        .lock()
+        .await
+        .get(&channel_id.get())
+        .filter(|(msg_id, _)| Some(*msg_id) == failed_msg)
+        .map(|(_, input)| input.clone());
+    let partial = state
+        .partial_answers
+        .lock()
+        .await
+        .remove(&channel_id.get())
+        .filter(|(msg_id, _)| Some(*msg_id) == failed_msg)
+        .map(|(_, partial)| partial)
+        .unwrap_or_default();
+    let (Some(previous_msg_id), Some(input)) = (failed_msg, input) else {
+        let msg = state.i18n.read().await.get("turn_retry_expired");
+        interaction
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        return Ok(());
+    };
+
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, is_new) = crate::Handler::open_session(state, channel_id.get(), agent_type).await?;
+
+    let msg = state.i18n.read().await.get("turn_retry_started");
+    interaction
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        .await?;
+
+    info!("🔁 Retrying failed turn on channel {}", channel_id);
+    crate::Handler::start_agent_loop(
+        agent,
+        ctx.http.clone(),
+        channel_id,
+        state.clone(),
+        Some(resume_input(&input, &partial)),
+        is_new,
+        Some(previous_msg_id),
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(text: &str) -> UserInput {
+        UserInput {
+            text: text.to_string(),
+            files: Vec::new(),
+            requester: Some(7),
+            quick: false,
+            on_behalf_of: None,
+            disabled_tools: Vec::new(),
+            continues: None,
+            source_message: None,
+            priority: false,
+        }
+    }
+
+    #[test]
+    fn test_button_roundtrip() {
+        let id = format!("{}{}", BUTTON_PREFIX, 42);
+        assert_eq!(parse_button(&id), Some(42));
+        assert_eq!(parse_button("tool_output_42"), None);
+        assert_eq!(parse_button("turn_retry_x"), None);
+    }
+
+    #[test]
+    fn test_resume_input_continues_partial_answer() {
+        let original = input("explain lifetimes");
+        assert_eq!(resume_input(&original, "  \n").text, "explain lifetimes");
+
+        let resumed = resume_input(&original, "Lifetimes describe **how long**\n");
+        assert!(resumed.text.starts_with(RESUME_INSTRUCTION));
+        assert!(resumed.text.ends_with("Lifetimes describe **how long**"));
+        assert_eq!(resumed.requester, Some(7));
+        assert!(resumed.files.is_empty());
+    }
+
+    #[test]
+    fn test_partial_attachments_skip_empty_answer() {
+        assert!(partial_attachments(MessageId::new(1), " ").is_empty());
+        let parts = partial_attachments(MessageId::new(1), "# partial");
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].filename, "partial-1.md");
+    }
+}
@@ -0,0 +1,367 @@
+use std::path::{Path, PathBuf};
+
+/// 非 systemd 環境的啟動方式
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Docker,
+    Podman,
+    Runit,
+    Launchd,
+}
+
+/// 容器內的資料目錄，對應主機上的 base dir
+const CONTAINER_BASE_DIR: &str = "/data";
+const LAUNCHD_LABEL: &str = "com.github.darkautism.agent-discord-rs";
+
+/// 轉交給 bot 與後端的環境變數：後端執行檔路徑覆寫與模型供應商的金鑰
+pub const PASSTHROUGH_ENV: [&str; 9] = [
+    "PI_BINARY",
+    "COPILOT_BINARY",
+    "CLAUDE_CODE_ACP_BINARY",
+    "ANTHROPIC_API_KEY",
+    "OPENAI_API_KEY",
+    "GEMINI_API_KEY",
+    "OPENROUTER_API_KEY",
+    "GITHUB_TOKEN",
+    "RUST_LOG",
+];
+
+/// 產生檔案所需的主機資訊
+pub struct HostInfo {
+    pub exe_path: String,
+    pub base_dir: PathBuf,
+    pub path: String,
+    pub tz: String,
+    /// 目前已設定的轉交變數 (名稱, 值)；只寫進不在容器內執行的 runit 與 launchd 檔案
+    pub env: Vec<(String, String)>,
+}
+
+impl HostInfo {
+    pub fn detect() -> anyhow::Result<Self> {
+        let current_path = std::env::var("PATH").unwrap_or_default();
+        Ok(Self {
+            exe_path: std::env::current_exe()?.to_string_lossy().to_string(),
+            base_dir: crate::migrate::get_base_dir(),
+            path: crate::agent::runtime::build_augmented_path(&current_path),
+            tz: crate::flow::detect_timezone(),
+            env: PASSTHROUGH_ENV
+                .iter()
+                .filter_map(|k| std::env::var(k).ok().map(|v| (k.to_string(), v)))
+                .collect(),
+        })
+    }
+}
+
+/// 依目標產生 (檔名, 內容, 是否可執行)
+pub fn render(target: Target, host: &HostInfo) -> Vec<(String, String, bool)> {
+    match target {
+        Target::Docker => vec![
+            ("Dockerfile".to_string(), dockerfile(), false),
+            ("compose.yaml".to_string(), compose(host), false),
+        ],
+        Target::Podman => vec![
+            ("Containerfile".to_string(), dockerfile(), false),
+            ("podman-run.sh".to_string(), podman_script(host), true),
+        ],
+        Target::Runit => vec![
+            ("run".to_string(), runit_run(host), true),
+            ("log/run".to_string(), runit_log_run(host), true),
+        ],
+        Target::Launchd => vec![(
+            format!("{}.plist", LAUNCHD_LABEL),
+            launchd_plist(host),
+            false,
+        )],
+    }
+}
+
+/// 寫入 `dir`；已存在的檔案需 `force` 才覆蓋
+pub fn write(
+    dir: &Path,
+    files: &[(String, String, bool)],
+    force: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    if !force {
+        if let Some((name, _, _)) = files.iter().find(|(name, _, _)| dir.join(name).exists()) {
+            anyhow::bail!(
+                "{} already exists; pass --force to overwrite",
+                dir.join(name).display()
+            );
+        }
+    }
+    let mut written = Vec::new();
+    for (name, content, executable) in files {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+        #[cfg(unix)]
+        if *executable {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// 下一步的操作說明
+pub fn next_steps(target: Target, dir: &Path) -> String {
+    let dir = dir.display();
+    match target {
+        Target::Docker => format!(
+            "cd {} && docker compose up -d --build   # run from a checkout of agent-discord-rs, or copy the files there",
+            dir
+        ),
+        Target::Podman => format!(
+            "podman build -t agent-discord-rs -f {}/Containerfile . && {}/podman-run.sh",
+            dir, dir
+        ),
+        Target::Runit => format!(
+            "sudo cp -r {} /etc/sv/agent-discord-rs && sudo ln -s /etc/sv/agent-discord-rs /var/service/",
+            dir
+        ),
+        Target::Launchd => format!(
+            "cp {}/{}.plist ~/Library/LaunchAgents/ && launchctl load -w ~/Library/LaunchAgents/{}.plist",
+            dir, LAUNCHD_LABEL, LAUNCHD_LABEL
+        ),
+    }
+}
+
+fn dockerfile() -> String {
+    format!(
+        r#"# Build from a checkout of agent-discord-rs
+FROM rust:1-bookworm AS build
+WORKDIR /src
+COPY . .
+RUN cargo build --release --locked
+
+# The backends are npm packages, so the runtime image ships Node.js
+FROM node:22-bookworm-slim
+RUN apt-get update \
+    && apt-get install -y --no-install-recommends ca-certificates tesseract-ocr tzdata \
+    && rm -rf /var/lib/apt/lists/*
+# Install the backends you use, e.g.:
+# RUN npm i -g @kilocode/cli @github/copilot @zed-industries/claude-code-acp
+COPY --from=build /src/target/release/agent-discord /usr/local/bin/agent-discord
+ENV {env}={base}
+VOLUME ["{base}"]
+ENTRYPOINT ["agent-discord"]
+CMD ["run"]
+"#,
+        env = crate::migrate::BASE_DIR_ENV,
+        base = CONTAINER_BASE_DIR,
+    )
+}
+
+fn compose(host: &HostInfo) -> String {
+    let mut env = format!("      - TZ={}\n", host.tz);
+    for key in PASSTHROUGH_ENV {
+        // 只寫名稱：compose 從執行 `docker compose` 的 shell 帶入值，未設定的不會傳入
+        env.push_str(&format!("      - {}\n", key));
+    }
+    format!(
+        r#"services:
+  agent-discord:
+    build: .
+    image: agent-discord-rs
+    restart: unless-stopped
+    environment:
+{env}    volumes:
+      - "{host_dir}:{base}"
+"#,
+        env = env,
+        host_dir = host.base_dir.display(),
+        base = CONTAINER_BASE_DIR,
+    )
+}
+
+fn podman_script(host: &HostInfo) -> String {
+    let mut args = format!("  -e TZ={} \\\n", shell_quote(&host.tz));
+    for key in PASSTHROUGH_ENV {
+        // `-e NAME` 沿用目前 shell 的值，未設定時 podman 會略過
+        args.push_str(&format!("  -e {} \\\n", key));
+    }
+    format!(
+        r#"#!/bin/sh
+set -e
+podman rm -f agent-discord-rs 2>/dev/null || true
+exec podman run -d --name agent-discord-rs --restart=unless-stopped \
+{args}  -v {volume} \
+  agent-discord-rs
+"#,
+        args = args,
+        volume = shell_quote(&format!(
+            "{}:{}:Z",
+            host.base_dir.display(),
+            CONTAINER_BASE_DIR
+        )),
+    )
+}
+
+fn runit_run(host: &HostInfo) -> String {
+    let mut exports = vec![
+        ("PATH".to_string(), host.path.clone()),
+        ("TZ".to_string(), host.tz.clone()),
+        (
+            crate::migrate::BASE_DIR_ENV.to_string(),
+            host.base_dir.display().to_string(),
+        ),
+    ];
+    exports.extend(host.env.iter().cloned());
+    let exports: String = exports
+        .iter()
+        .map(|(k, v)| format!("export {}={}\n", k, shell_quote(v)))
+        .collect();
+    let user = std::env::var("USER").unwrap_or_else(|_| "root".to_string());
+    format!(
+        "#!/bin/sh\nexec 2>&1\n{}exec chpst -u {} {} run\n",
+        exports,
+        user,
+        shell_quote(&host.exe_path)
+    )
+}
+
+fn runit_log_run(host: &HostInfo) -> String {
+    let log_dir = host.base_dir.join("logs").join("runit");
+    format!(
+        "#!/bin/sh\nmkdir -p {dir}\nexec svlogd -tt {dir}\n",
+        dir = shell_quote(&log_dir.display().to_string())
+    )
+}
+
+fn launchd_plist(host: &HostInfo) -> String {
+    let mut env = vec![
+        ("PATH".to_string(), host.path.clone()),
+        ("TZ".to_string(), host.tz.clone()),
+        (
+            crate::migrate::BASE_DIR_ENV.to_string(),
+            host.base_dir.display().to_string(),
+        ),
+    ];
+    env.extend(host.env.iter().cloned());
+    let env: String = env
+        .iter()
+        .map(|(k, v)| {
+            format!(
+                "        <key>{}</key>\n        <string>{}</string>\n",
+                xml_escape(k),
+                xml_escape(v)
+            )
+        })
+        .collect();
+    let log = xml_escape(
+        &host
+            .base_dir
+            .join("agent-discord.log")
+            .display()
+            .to_string(),
+    );
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>run</string>
+    </array>
+    <key>EnvironmentVariables</key>
+    <dict>
+{env}    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>5</integer>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        exe = xml_escape(&host.exe_path),
+        env = env,
+        log = log,
+    )
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn host() -> HostInfo {
+        HostInfo {
+            exe_path: "/opt/bin/agent-discord".to_string(),
+            base_dir: PathBuf::from("/home/me/.agent-discord-rs"),
+            path: "/usr/bin:/bin".to_string(),
+            tz: "Asia/Taipei".to_string(),
+            env: vec![("ANTHROPIC_API_KEY".to_string(), "sk-'x'".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_render_mounts_base_dir_and_passes_env() {
+        let docker = render(Target::Docker, &host());
+        let compose = &docker[1].1;
+        assert!(compose.contains("\"/home/me/.agent-discord-rs:/data\""));
+        assert!(compose.contains("      - COPILOT_BINARY\n"));
+        assert!(!compose.contains("sk-"));
+        assert!(docker[0].1.contains("ENV AGENT_DISCORD_BASE_DIR=/data"));
+
+        let podman = render(Target::Podman, &host());
+        assert!(podman[1]
+            .1
+            .contains("-v '/home/me/.agent-discord-rs:/data:Z'"));
+        assert!(podman[1].2);
+
+        let runit = render(Target::Runit, &host());
+        assert_eq!(runit[0].0, "run");
+        assert!(runit[0]
+            .1
+            .contains("export AGENT_DISCORD_BASE_DIR='/home/me/.agent-discord-rs'"));
+        assert!(runit[0]
+            .1
+            .contains("export ANTHROPIC_API_KEY='sk-'\\''x'\\'''"));
+        assert!(runit[0].1.ends_with("'/opt/bin/agent-discord' run\n"));
+
+        let launchd = render(Target::Launchd, &host());
+        assert!(launchd[0].0.ends_with(".plist"));
+        assert!(launchd[0].1.contains("<string>Asia/Taipei</string>"));
+        assert!(launchd[0]
+            .1
+            .contains("<string>/opt/bin/agent-discord</string>"));
+    }
+
+    #[test]
+    fn test_write_refuses_to_overwrite_without_force() {
+        let dir = tempdir().expect("tempdir");
+        let files = render(Target::Runit, &host());
+        let written = write(dir.path(), &files, false).expect("write");
+        assert!(written.iter().any(|p| p.ends_with("log/run")));
+        assert!(write(dir.path(), &files, false).is_err());
+        assert!(write(dir.path(), &files, true).is_ok());
+    }
+}
@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, Context, GuildId, Http};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::agent::manager::{BackendManager, InstanceStatus};
+use crate::agent::{AgentType, AiAgent};
+use crate::auth::AuthManager;
+use crate::config::Config;
+use crate::cron::manager::CronJobInfo;
+use crate::cron::CronManager;
+use crate::session::SessionManager;
+use crate::usage_caps::{Alert, Gate, GuildCap, GuildEntry, UsageCaps};
+
+/// 頻道與伺服器的授權
+#[async_trait]
+pub trait AuthService: Send + Sync {
+    /// (是否授權, mention_only)；頻道未授權時再看討論串的父頻道與伺服器
+    async fn is_authorized_with_thread(
+        &self,
+        ctx: &Context,
+        user_id: &str,
+        channel_id: ChannelId,
+        guild_id: Option<GuildId>,
+    ) -> (bool, bool);
+    fn get_channel_mention_only(&self, channel_id: &str) -> Option<bool>;
+    fn get_guild_mention_only(&self, guild_id: &str) -> Option<bool>;
+    fn create_token(&self, type_: &str, id: &str) -> anyhow::Result<String>;
+    fn migrate_channel(&self, old_id: &str, new_id: &str) -> anyhow::Result<bool>;
+    fn remove_guild(&self, guild_id: &str) -> anyhow::Result<bool>;
+    fn set_guild_mention_only(&self, guild_id: &str, enable: bool) -> anyhow::Result<()>;
+    fn set_mention_only(&self, channel_id: &str, enable: bool) -> anyhow::Result<()>;
+}
+
+/// 各頻道的代理 session
+#[async_trait]
+pub trait SessionService: Send + Sync {
+    /// 回傳 (session, 是否為新建立)
+    async fn get_or_create_session(
+        &self,
+        channel_id: u64,
+        agent_type: AgentType,
+    ) -> anyhow::Result<(Arc<dyn AiAgent>, bool)>;
+    async fn get_session(&self, channel_id: u64) -> Option<Arc<dyn AiAgent>>;
+    async fn one_per_backend(&self) -> Vec<Arc<dyn AiAgent>>;
+    /// 各後端目前開著的 session 數
+    async fn session_counts(&self) -> HashMap<&'static str, usize>;
+    async fn remove_session(&self, channel_id: u64);
+}
+
+/// 由 bot 啟動的 kilo/opencode 後端進程
+#[async_trait]
+pub trait BackendService: Send + Sync {
+    /// 確保頻道分配到的後端實例正在執行，回傳其埠
+    async fn ensure_backend(&self, agent_type: &AgentType, channel_id: u64) -> anyhow::Result<u16>;
+    async fn instance_key(&self, agent_type: &AgentType, channel_id: u64) -> Option<String>;
+    /// 頻道分配到的實例編號
+    async fn assigned_instance(&self, agent_type: &AgentType, channel_id: u64) -> Option<usize>;
+    /// 把頻道固定在指定實例；實例已不在池中時回傳 false
+    async fn pin_channel(&self, agent_type: &AgentType, channel_id: u64, instance: usize) -> bool;
+    /// 改分配到其他實例；沒有其他實例時回傳 None
+    async fn reassign_channel(&self, agent_type: &AgentType, channel_id: u64) -> Option<usize>;
+    async fn release_channel(&self, channel_id: u64);
+    async fn instances(&self) -> Vec<InstanceStatus>;
+}
+
+/// 伺服器的 token 用量上限
+#[async_trait]
+pub trait UsageService: Send + Sync {
+    async fn get(&self, guild_id: u64) -> Option<GuildEntry>;
+    async fn set(&self, guild_id: u64, cap: GuildCap) -> anyhow::Result<()>;
+    async fn remove(&self, guild_id: u64) -> anyhow::Result<bool>;
+    async fn gate(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        current_model: Option<(String, String)>,
+    ) -> Gate;
+    async fn record(&self, guild_id: u64, tokens: u64) -> Option<(Alert, GuildEntry)>;
+}
+
+/// `/cron` 排程
+#[async_trait]
+pub trait SchedulerService: Send + Sync {
+    /// 連上 Discord 後註冊已載入的排程
+    async fn init(&self, http: Arc<Http>, state: Weak<crate::AppState>);
+    async fn set_http(&self, http: Arc<Http>);
+    async fn add_job(&self, info: CronJobInfo) -> anyhow::Result<Uuid>;
+    async fn remove_job(&self, id: Uuid) -> anyhow::Result<()>;
+    async fn set_paused(&self, id: Uuid, paused: bool) -> anyhow::Result<bool>;
+    async fn run_now(&self, id: Uuid) -> bool;
+    async fn get_jobs_for_channel(&self, channel_id: u64) -> Vec<CronJobInfo>;
+    async fn next_run(&self, info: &CronJobInfo) -> Option<DateTime<Utc>>;
+    async fn record_result(&self, channel_id: u64, success: bool, answer: &str);
+}
+
+/// AppState 使用的服務；正式環境由 `wire` 組裝，測試可直接以替身建構
+pub struct Services {
+    pub auth: Arc<dyn AuthService>,
+    pub sessions: Arc<dyn SessionService>,
+    pub backends: Arc<dyn BackendService>,
+    pub usage: Arc<dyn UsageService>,
+    pub scheduler: Arc<dyn SchedulerService>,
+}
+
+impl Services {
+    /// 啟動時組裝正式的服務；相依的服務在建構時注入 (如 session 使用的後端)
+    pub async fn wire(config: Arc<Config>) -> anyhow::Result<Self> {
+        let backends: Arc<dyn BackendService> = Arc::new(BackendManager::new(config.clone()));
+        let sessions: Arc<dyn SessionService> =
+            Arc::new(SessionManager::new(config, backends.clone()));
+        let scheduler = CronManager::new().await?;
+        if let Err(e) = scheduler.load_from_disk().await {
+            error!("❌ Failed to load cron jobs from disk: {}", e);
+        }
+        Ok(Self {
+            auth: Arc::new(AuthManager::new()),
+            sessions,
+            backends,
+            usage: Arc::new(UsageCaps::load().await),
+            scheduler: Arc::new(scheduler),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedBackend;
+
+    #[async_trait]
+    impl BackendService for FixedBackend {
+        async fn ensure_backend(&self, _: &AgentType, _: u64) -> anyhow::Result<u16> {
+            Ok(4096)
+        }
+        async fn instance_key(&self, _: &AgentType, _: u64) -> Option<String> {
+            Some("kilo#0".to_string())
+        }
+        async fn assigned_instance(&self, _: &AgentType, _: u64) -> Option<usize> {
+            Some(0)
+        }
+        async fn pin_channel(&self, _: &AgentType, _: u64, instance: usize) -> bool {
+            instance == 0
+        }
+        async fn reassign_channel(&self, _: &AgentType, _: u64) -> Option<usize> {
+            None
+        }
+        async fn release_channel(&self, _: u64) {}
+        async fn instances(&self) -> Vec<InstanceStatus> {
+            Vec::new()
+        }
+    }
+
+    struct NoCaps;
+
+    #[async_trait]
+    impl UsageService for NoCaps {
+        async fn get(&self, _: u64) -> Option<GuildEntry> {
+            None
+        }
+        async fn set(&self, _: u64, _: GuildCap) -> anyhow::Result<()> {
+            anyhow::bail!("caps are disabled")
+        }
+        async fn remove(&self, _: u64) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+        async fn gate(&self, _: u64, _: u64, _: Option<(String, String)>) -> Gate {
+            Gate::Open
+        }
+        async fn record(&self, _: u64, _: u64) -> Option<(Alert, GuildEntry)> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_services_built_from_test_doubles() {
+        let dir = tempfile::tempdir().unwrap();
+        let backends: Arc<dyn BackendService> = Arc::new(FixedBackend);
+        let services = Services {
+            auth: Arc::new(AuthManager::with_paths(
+                dir.path().join("auth.json"),
+                dir.path().join("pending_tokens.json"),
+            )),
+            sessions: Arc::new(SessionManager::new(
+                Arc::new(Config::default()),
+                backends.clone(),
+            )),
+            backends,
+            usage: Arc::new(NoCaps),
+            scheduler: Arc::new(
+                CronManager::with_config_dir(dir.path().to_path_buf())
+                    .await
+                    .unwrap(),
+            ),
+        };
+        assert_eq!(
+            services
+                .backends
+                .ensure_backend(&AgentType::Kilo, 1)
+                .await
+                .unwrap(),
+            4096
+        );
+        assert!(services.usage.get(1).await.is_none());
+        assert!(matches!(services.usage.gate(1, 2, None).await, Gate::Open));
+        assert!(services.sessions.get_session(1).await.is_none());
+    }
+}
@@ -1,20 +1,27 @@
-use crate::agent::{AgentType, AiAgent, CopilotAgent, KiloAgent, OpencodeAgent, PiAgent};
+use crate::agent::{
+    claude, copilot, AcpAgent, AcpProfile, AgentType, AiAgent, KiloAgent, OpenAiAgent,
+    OpencodeAgent, PiAgent,
+};
 use crate::config::Config;
 use crate::migrate;
+use crate::services::{BackendService, SessionService};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::{info, warn};
 
 pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<u64, Arc<dyn AiAgent>>>>,
     config: Arc<Config>,
+    backends: Arc<dyn BackendService>,
 }
 
 impl SessionManager {
-    pub fn new(config: Arc<Config>) -> Self {
+    pub fn new(config: Arc<Config>, backends: Arc<dyn BackendService>) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             config,
+            backends,
         }
     }
 
@@ -22,8 +29,10 @@ impl SessionManager {
         &self,
         channel_id: u64,
         agent_type: AgentType,
-        backend_manager: &crate::agent::manager::BackendManager,
     ) -> anyhow::Result<(Arc<dyn AiAgent>, bool)> {
+        if !crate::agent::is_backend_enabled(&agent_type) {
+            anyhow::bail!("Backend {} is disabled by enabled_backends", agent_type);
+        }
         {
             let sessions = self.sessions.read().await;
             if let Some(session) = sessions.get(&channel_id) {
@@ -47,18 +56,23 @@ impl SessionManager {
             }
         });
 
-        let existing_sid = entry.and_then(|e| e.session_id.clone());
+        let mut existing_sid = entry.and_then(|e| e.session_id.clone());
+        let pinned = entry.and_then(|e| e.backend_instance);
+        let workdir = entry.and_then(|e| e.workdir.clone());
+        let thinking_level = entry.and_then(|e| e.thinking_level.clone());
 
         let session: Arc<dyn AiAgent> = match agent_type {
             AgentType::Pi => {
                 let session_dir = migrate::get_sessions_dir("pi");
                 std::fs::create_dir_all(&session_dir)?;
-                let (pi_agent, _) = PiAgent::new(channel_id, &session_dir).await?;
+                let (pi_agent, _) =
+                    PiAgent::new(channel_id, &session_dir, workdir.as_deref()).await?;
                 pi_agent
             }
             AgentType::Opencode => {
-                let port = backend_manager.ensure_backend(&AgentType::Opencode).await?;
-                let api_url = format!("http://127.0.0.1:{}", port);
+                let (api_url, instance) = self
+                    .pooled_backend(&AgentType::Opencode, channel_id, pinned, &mut existing_sid)
+                    .await?;
                 let api_key = self.config.opencode.password.clone().unwrap_or_default();
 
                 let agent = OpencodeAgent::new(
@@ -67,32 +81,82 @@ impl SessionManager {
                     api_key,
                     existing_sid,
                     model_opt,
+                    workdir,
                     "opencode",
                 )
                 .await?;
 
-                self.persist_sid(channel_id, AgentType::Opencode, agent.session_id.clone())
-                    .await?;
+                self.persist_sid(
+                    channel_id,
+                    AgentType::Opencode,
+                    agent.session_id.clone(),
+                    Some(instance),
+                )
+                .await?;
                 agent
             }
             AgentType::Copilot => {
-                let agent = CopilotAgent::new(channel_id, existing_sid, model_opt).await?;
-                self.persist_sid(channel_id, AgentType::Copilot, agent.session_id())
+                let profile = copilot::profile(&self.config.copilot);
+                let agent =
+                    AcpAgent::new(&profile, channel_id, existing_sid, model_opt, workdir).await?;
+                self.persist_sid(channel_id, AgentType::Copilot, agent.session_id(), None)
+                    .await?;
+                agent
+            }
+            AgentType::Claude => {
+                let profile = claude::profile(&self.config.claude);
+                let agent =
+                    AcpAgent::new(&profile, channel_id, existing_sid, model_opt, workdir).await?;
+                self.persist_sid(channel_id, AgentType::Claude, agent.session_id(), None)
+                    .await?;
+                agent
+            }
+            AgentType::Acp => {
+                let profile = AcpProfile::from_config(&self.config.acp)?;
+                let agent =
+                    AcpAgent::new(&profile, channel_id, existing_sid, model_opt, workdir).await?;
+                self.persist_sid(channel_id, AgentType::Acp, agent.session_id(), None)
                     .await?;
                 agent
             }
+            AgentType::OpenAi => {
+                OpenAiAgent::new(
+                    channel_id,
+                    &migrate::get_sessions_dir("openai"),
+                    &self.config.openai,
+                    model_opt,
+                )
+                .await?
+            }
             AgentType::Kilo => {
-                let port = backend_manager.ensure_backend(&AgentType::Kilo).await?;
-                let api_url = format!("http://127.0.0.1:{}", port);
+                let (api_url, instance) = self
+                    .pooled_backend(&AgentType::Kilo, channel_id, pinned, &mut existing_sid)
+                    .await?;
 
-                let agent = KiloAgent::new(channel_id, api_url, existing_sid, model_opt).await?;
+                let agent =
+                    KiloAgent::new(channel_id, api_url, existing_sid, model_opt, workdir).await?;
 
-                self.persist_sid(channel_id, AgentType::Kilo, agent.session_id())
-                    .await?;
+                self.persist_sid(
+                    channel_id,
+                    AgentType::Kilo,
+                    agent.session_id(),
+                    Some(instance),
+                )
+                .await?;
                 agent
             }
         };
 
+        // 推理等級只存在後端連線的記憶體中，新 session 需重新套用
+        if let Some(level) = thinking_level {
+            if let Err(e) = session.set_thinking_level(&level).await {
+                warn!(
+                    "Failed to restore thinking level {} for channel {}: {}",
+                    level, channel_id, e
+                );
+            }
+        }
+
         {
             let mut sessions = self.sessions.write().await;
             sessions.insert(channel_id, session.clone());
@@ -112,37 +176,137 @@ impl SessionManager {
         channel_id: &str,
         agent_type: AgentType,
         sid: String,
+        instance: Option<usize>,
     ) {
         let entry = channel_config
             .channels
             .entry(channel_id.to_string())
-            .or_insert_with(|| crate::commands::agent::ChannelEntry {
-                agent_type: agent_type.clone(),
-                authorized_at: chrono::Utc::now().to_rfc3339(),
-                mention_only: true,
-                session_id: None,
-                model_provider: None,
-                model_id: None,
-                assistant_name: None,
-            });
+            .or_insert_with(|| crate::commands::agent::ChannelEntry::new(agent_type));
 
         entry.session_id = Some(sid);
+        entry.backend_instance = instance;
     }
 
+    /// `instance` 是 kilo/opencode session 所在的後端實例，其他後端為 None
     async fn persist_sid(
         &self,
         channel_id: u64,
         agent_type: AgentType,
         sid: String,
+        instance: Option<usize>,
     ) -> anyhow::Result<()> {
         let channel_id_str = channel_id.to_string();
-        let mut channel_config = crate::commands::agent::ChannelConfig::load()
+        crate::commands::agent::ChannelConfig::update(|channel_config| {
+            Self::apply_sid(channel_config, &channel_id_str, agent_type, sid, instance)
+        })
+        .await
+    }
+
+    /// kilo/opencode 的 API 位址與實例編號：已有 session 時固定送往記錄的實例。
+    /// 該實例已不在池中或無法啟動時改用其他實例；新實例不認得舊 session 時清掉 sid 改開新的。
+    async fn pooled_backend(
+        &self,
+        agent_type: &AgentType,
+        channel_id: u64,
+        pinned: Option<usize>,
+        existing_sid: &mut Option<String>,
+    ) -> anyhow::Result<(String, usize)> {
+        let mut moved = false;
+        if let (Some(idx), Some(_)) = (pinned, existing_sid.as_ref()) {
+            if !self.backends.pin_channel(agent_type, channel_id, idx).await {
+                warn!(
+                    "⚠️ {} instance #{} of channel {} is no longer in the pool; migrating its session",
+                    agent_type, idx, channel_id
+                );
+                moved = true;
+            }
+        }
+        let port = match self.backends.ensure_backend(agent_type, channel_id).await {
+            Ok(port) => port,
+            Err(e) if existing_sid.is_some() => {
+                let Some(idx) = self.backends.reassign_channel(agent_type, channel_id).await else {
+                    return Err(e);
+                };
+                warn!(
+                    "⚠️ {} instance of channel {} is unavailable ({}); migrating its session to #{}",
+                    agent_type, channel_id, e, idx
+                );
+                moved = true;
+                self.backends.ensure_backend(agent_type, channel_id).await?
+            }
+            Err(e) => return Err(e),
+        };
+        let api_url = format!("http://127.0.0.1:{}", port);
+
+        if let (true, Some(sid)) = (moved, existing_sid.clone()) {
+            let password = self.config.opencode.password.clone().unwrap_or_default();
+            match OpencodeAgent::session_exists(&api_url, &password, &sid).await {
+                Ok(true) => info!(
+                    "📦 Session {} of channel {} moved to the new {} instance",
+                    sid, channel_id, agent_type
+                ),
+                Ok(false) => {
+                    warn!(
+                        "⚠️ Session {} of channel {} is unknown to the new {} instance; starting a new session",
+                        sid, channel_id, agent_type
+                    );
+                    *existing_sid = None;
+                }
+                // 無法確認時保留 sid，避免誤丟對話
+                Err(e) => warn!(
+                    "⚠️ Could not check session {} on the new {} instance: {}",
+                    sid, agent_type, e
+                ),
+            }
+        }
+
+        let instance = self
+            .backends
+            .assigned_instance(agent_type, channel_id)
             .await
-            .unwrap_or_default();
+            .unwrap_or(0);
+        Ok((api_url, instance))
+    }
 
-        Self::apply_sid(&mut channel_config, &channel_id_str, agent_type, sid);
-        channel_config.save().await?;
-        Ok(())
+    /// 頻道目前快取中的 session，不會建立新的
+    pub async fn get_session(&self, channel_id: u64) -> Option<Arc<dyn AiAgent>> {
+        self.sessions.read().await.get(&channel_id).cloned()
+    }
+
+    /// 每種後端各取一個開著的 session，供不屬於特定頻道的查詢使用 (如模型清單)
+    pub async fn one_per_backend(&self) -> Vec<Arc<dyn AiAgent>> {
+        let mut seen = std::collections::HashSet::new();
+        self.sessions
+            .read()
+            .await
+            .values()
+            .filter(|agent| seen.insert(agent.agent_type()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn session_counts(&self) -> HashMap<&'static str, usize> {
+        let mut counts = HashMap::new();
+        for agent in self.sessions.read().await.values() {
+            *counts.entry(agent.agent_type()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// 頻道 session 檔案在本機的大小；session 存在遠端後端的類型為 None
+    pub fn session_file_size(agent_type: &AgentType, channel_id: u64) -> Option<u64> {
+        match agent_type {
+            AgentType::Pi => {
+                let path = PiAgent::session_file(&migrate::get_sessions_dir("pi"), channel_id);
+                std::fs::metadata(path).ok().map(|m| m.len())
+            }
+            AgentType::OpenAi => {
+                let path =
+                    OpenAiAgent::history_file(&migrate::get_sessions_dir("openai"), channel_id);
+                std::fs::metadata(path).ok().map(|m| m.len())
+            }
+            _ => None,
+        }
     }
 
     pub async fn remove_session(&self, channel_id: u64) {
@@ -151,6 +315,33 @@ impl SessionManager {
     }
 }
 
+#[async_trait::async_trait]
+impl SessionService for SessionManager {
+    async fn get_or_create_session(
+        &self,
+        channel_id: u64,
+        agent_type: AgentType,
+    ) -> anyhow::Result<(Arc<dyn AiAgent>, bool)> {
+        SessionManager::get_or_create_session(self, channel_id, agent_type).await
+    }
+
+    async fn get_session(&self, channel_id: u64) -> Option<Arc<dyn AiAgent>> {
+        SessionManager::get_session(self, channel_id).await
+    }
+
+    async fn one_per_backend(&self) -> Vec<Arc<dyn AiAgent>> {
+        SessionManager::one_per_backend(self).await
+    }
+
+    async fn session_counts(&self) -> HashMap<&'static str, usize> {
+        SessionManager::session_counts(self).await
+    }
+
+    async fn remove_session(&self, channel_id: u64) {
+        SessionManager::remove_session(self, channel_id).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,7 +350,8 @@ mod tests {
     #[tokio::test]
     async fn test_remove_session_clears_cached_agent() {
         let config = Arc::new(Config::default());
-        let manager = SessionManager::new(config);
+        let backends = Arc::new(crate::agent::manager::BackendManager::new(config.clone()));
+        let manager = SessionManager::new(config, backends);
         let channel_id = 42_u64;
         let mock_agent: Arc<dyn AiAgent> = Arc::new(MockAgent::new());
 
@@ -169,16 +361,86 @@ mod tests {
             assert!(sessions.contains_key(&channel_id));
         }
 
+        assert!(manager.get_session(channel_id).await.is_some());
         manager.remove_session(channel_id).await;
+        assert!(manager.get_session(channel_id).await.is_none());
+    }
 
-        let sessions = manager.sessions.read().await;
-        assert!(!sessions.contains_key(&channel_id));
+    /// 記錄的實例已不在池中，改用埠為 `port` 的實例 #1
+    struct ShrunkPool {
+        port: u16,
+    }
+
+    #[async_trait::async_trait]
+    impl BackendService for ShrunkPool {
+        async fn ensure_backend(&self, _: &AgentType, _: u64) -> anyhow::Result<u16> {
+            Ok(self.port)
+        }
+        async fn instance_key(&self, _: &AgentType, _: u64) -> Option<String> {
+            None
+        }
+        async fn assigned_instance(&self, _: &AgentType, _: u64) -> Option<usize> {
+            Some(1)
+        }
+        async fn pin_channel(&self, _: &AgentType, _: u64, _: usize) -> bool {
+            false
+        }
+        async fn reassign_channel(&self, _: &AgentType, _: u64) -> Option<usize> {
+            None
+        }
+        async fn release_channel(&self, _: u64) {}
+        async fn instances(&self) -> Vec<crate::agent::manager::InstanceStatus> {
+            Vec::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pooled_backend_migrates_session_off_gone_instance() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/session/kept"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/session/lost"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let port = server.address().port();
+        let manager =
+            SessionManager::new(Arc::new(Config::default()), Arc::new(ShrunkPool { port }));
+
+        let mut sid = Some("kept".to_string());
+        let (url, instance) = manager
+            .pooled_backend(&AgentType::Kilo, 1, Some(3), &mut sid)
+            .await
+            .expect("backend");
+        assert_eq!(url, format!("http://127.0.0.1:{}", port));
+        assert_eq!(instance, 1);
+        assert_eq!(sid.as_deref(), Some("kept"));
+
+        let mut sid = Some("lost".to_string());
+        manager
+            .pooled_backend(&AgentType::Kilo, 1, Some(3), &mut sid)
+            .await
+            .expect("backend");
+        assert_eq!(sid, None);
     }
 
     #[test]
     fn test_apply_sid_creates_channel_entry_when_missing() {
         let mut cfg = crate::commands::agent::ChannelConfig::default();
-        SessionManager::apply_sid(&mut cfg, "1001", AgentType::Copilot, "sid-1".to_string());
+        SessionManager::apply_sid(
+            &mut cfg,
+            "1001",
+            AgentType::Copilot,
+            "sid-1".to_string(),
+            None,
+        );
         let entry = cfg.channels.get("1001").expect("entry exists");
         assert_eq!(entry.agent_type, AgentType::Copilot);
         assert_eq!(entry.session_id.as_deref(), Some("sid-1"));
@@ -196,14 +458,36 @@ mod tests {
                 authorized_at: "2026-01-01T00:00:00Z".to_string(),
                 mention_only: false,
                 session_id: Some("old".to_string()),
+                backend_instance: None,
                 model_provider: Some("p".to_string()),
                 model_id: Some("m".to_string()),
                 assistant_name: Some("a".to_string()),
+                max_turn_secs: None,
+                mirror: Default::default(),
+                private: None,
+                quiet_hours: None,
+                tool_output_retention: None,
+                faq_cache: false,
+                auto_reply_language: false,
+                mentions: Default::default(),
+                accessible: false,
+                status_reactions: false,
+                disabled_tools: Vec::new(),
+                workdir: None,
+                thinking_level: None,
+                text_commands: false,
             },
         );
-        SessionManager::apply_sid(&mut cfg, "1002", AgentType::Kilo, "new-sid".to_string());
+        SessionManager::apply_sid(
+            &mut cfg,
+            "1002",
+            AgentType::Kilo,
+            "new-sid".to_string(),
+            Some(1),
+        );
         let entry = cfg.channels.get("1002").expect("entry exists");
         assert_eq!(entry.session_id.as_deref(), Some("new-sid"));
+        assert_eq!(entry.backend_instance, Some(1));
         assert_eq!(entry.agent_type, AgentType::Pi);
         assert_eq!(entry.model_provider.as_deref(), Some("p"));
         assert_eq!(entry.model_id.as_deref(), Some("m"));
@@ -0,0 +1,139 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// 中斷回合後，等待 render 工作完成最後一次編輯的秒數
+const FINAL_EDIT_SECS: u64 = 10;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 程序停止時的收尾：先不再接受新提示，寬限期結束後通知仍在執行的回合中斷
+pub struct Shutdown {
+    draining: AtomicBool,
+    interrupt_tx: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            draining: AtomicBool::new(false),
+            interrupt_tx: watch::channel(false).0,
+        }
+    }
+}
+
+impl Shutdown {
+    /// 停止中：新提示改為排入佇列紀錄，重啟後在頻道中確認重送
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    fn begin_drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// 各回合的看守工作以此得知寬限期已過
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.interrupt_tx.subscribe()
+    }
+
+    fn interrupt(&self) {
+        self.interrupt_tx.send_replace(true);
+    }
+}
+
+/// 等到 SIGTERM 或 SIGINT (Ctrl+C)
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = term.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("⚠️ Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// 在 `limit` 內等到 `running` 回報 0；逾時回傳 false
+async fn wait_idle<F, Fut>(limit: Duration, mut running: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = usize>,
+{
+    let deadline = tokio::time::Instant::now() + limit;
+    loop {
+        if running().await == 0 {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// 停止接受新提示並等待進行中的回合；超過 `shutdown_grace_secs` 時中斷剩下的回合
+/// (編輯回應為「因重啟中斷」並 abort 後端)，等它們完成最後一次編輯
+pub async fn drain(state: &crate::AppState) {
+    let shutdown = &state.shutdown;
+    shutdown.begin_drain();
+    let running = || async { state.active_renders.lock().await.len() };
+    let grace = Duration::from_secs(state.config.shutdown_grace_secs);
+    info!(
+        "🛑 Shutting down; waiting up to {}s for {} running turn(s)",
+        grace.as_secs(),
+        running().await
+    );
+    if wait_idle(grace, running).await {
+        return;
+    }
+    warn!(
+        "⚠️ Interrupting {} turn(s) still running after the grace period",
+        running().await
+    );
+    shutdown.interrupt();
+    if !wait_idle(Duration::from_secs(FINAL_EDIT_SECS), running).await {
+        warn!("⚠️ Some interrupted turns did not finish their final edit");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_wait_idle_returns_when_turns_finish_or_times_out() {
+        let remaining = AtomicUsize::new(3);
+        let finished = wait_idle(Duration::from_secs(5), || async {
+            remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .unwrap_or(0)
+        })
+        .await;
+        assert!(finished);
+
+        let stuck = wait_idle(Duration::from_millis(300), || async { 1 }).await;
+        assert!(!stuck);
+    }
+
+    #[test]
+    fn test_interrupt_notifies_subscribers_once_draining() {
+        let shutdown = Shutdown::default();
+        let rx = shutdown.subscribe();
+        assert!(!shutdown.is_draining());
+        shutdown.begin_drain();
+        assert!(shutdown.is_draining());
+        assert!(!*rx.borrow());
+        shutdown.interrupt();
+        assert!(*rx.borrow());
+    }
+}
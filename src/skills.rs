@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// 後端實際載入的技能說明檔
+pub const ENTRY_FILE: &str = "SKILL.md";
+/// 上傳的壓縮檔大小上限
+pub const MAX_BUNDLE_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_UNPACKED_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_ENTRIES: usize = 500;
+
+/// 技能包根目錄的 manifest.json
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SkillManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// 技能名稱即安裝目錄名稱，限制為小寫英數與 `-`
+pub fn is_valid_skill_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// 壓縮檔可以直接包含檔案，也可以整包放在單一頂層目錄中
+fn bundle_prefix(names: &[String]) -> Option<String> {
+    if names.iter().any(|n| n == MANIFEST_FILE) {
+        return Some(String::new());
+    }
+    let (top, _) = names.first()?.split_once('/')?;
+    let prefix = format!("{}/", top);
+    let single_root = names.iter().all(|n| n.starts_with(&prefix));
+    let has_manifest = names
+        .iter()
+        .any(|n| *n == format!("{}{}", prefix, MANIFEST_FILE));
+    (single_root && has_manifest).then_some(prefix)
+}
+
+/// 驗證技能包並解壓到 `skills_dir/<name>`，已存在的同名技能會被取代
+pub fn install_bundle(skills_dir: &Path, bytes: &[u8]) -> anyhow::Result<SkillManifest> {
+    if bytes.len() as u64 > MAX_BUNDLE_BYTES {
+        anyhow::bail!("bundle exceeds {} bytes", MAX_BUNDLE_BYTES);
+    }
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    if archive.len() > MAX_ENTRIES {
+        anyhow::bail!("bundle has more than {} entries", MAX_ENTRIES);
+    }
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let prefix = bundle_prefix(&names)
+        .ok_or_else(|| anyhow::anyhow!("{} not found at the bundle root", MANIFEST_FILE))?;
+    if !names.contains(&format!("{}{}", prefix, ENTRY_FILE)) {
+        anyhow::bail!("{} not found next to {}", ENTRY_FILE, MANIFEST_FILE);
+    }
+
+    let manifest: SkillManifest = {
+        let mut file = archive.by_name(&format!("{}{}", prefix, MANIFEST_FILE))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("invalid {}: {}", MANIFEST_FILE, e))?
+    };
+    if !is_valid_skill_name(&manifest.name) {
+        anyhow::bail!(
+            "invalid skill name `{}` (use lowercase letters, digits and -)",
+            manifest.name
+        );
+    }
+
+    std::fs::create_dir_all(skills_dir)?;
+    let staging = skills_dir.join(format!(".{}.installing", manifest.name));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    let result = extract(&mut archive, &prefix, &staging);
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let target = skills_dir.join(&manifest.name);
+    if target.exists() {
+        std::fs::remove_dir_all(&target)?;
+    }
+    std::fs::rename(&staging, &target)?;
+    Ok(manifest)
+}
+
+fn extract(
+    archive: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    prefix: &str,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    let mut unpacked = 0u64;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        // enclosed_name 會拒絕絕對路徑與 `..`
+        let Some(path) = file.enclosed_name() else {
+            anyhow::bail!("unsafe path in bundle: {}", file.name());
+        };
+        let Ok(rel) = path.strip_prefix(prefix) else {
+            continue;
+        };
+        if rel.as_os_str().is_empty() {
+            continue;
+        }
+        let out = dest.join(rel);
+        if file.is_dir() {
+            std::fs::create_dir_all(&out)?;
+            continue;
+        }
+        if file.is_symlink() {
+            anyhow::bail!("symlinks are not allowed in bundles: {}", file.name());
+        }
+        unpacked += file.size();
+        if unpacked > MAX_UNPACKED_BYTES {
+            anyhow::bail!("bundle unpacks to more than {} bytes", MAX_UNPACKED_BYTES);
+        }
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = std::fs::File::create(&out)?;
+        // 以實際讀出的位元組再限制一次，避免標頭謊報大小
+        std::io::copy(&mut (&mut file).take(MAX_UNPACKED_BYTES), &mut writer)?;
+    }
+    Ok(())
+}
+
+/// 已安裝且含有 SKILL.md 的技能目錄 (依名稱排序)
+pub fn installed_skill_dirs(skills_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(skills_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(is_valid_skill_name)
+                && path.join(ENTRY_FILE).is_file()
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn build_zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buf);
+            let options = zip::write::SimpleFileOptions::default();
+            for (name, content) in files {
+                writer.start_file(*name, options).expect("start file");
+                writer.write_all(content.as_bytes()).expect("write");
+            }
+            writer.finish().expect("finish");
+        }
+        buf.into_inner()
+    }
+
+    #[test]
+    fn test_install_bundle_with_top_level_folder_replaces_existing() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let bundle = build_zip(&[
+            (
+                "review/manifest.json",
+                r#"{"name":"review","description":"Code review","version":"1.0"}"#,
+            ),
+            ("review/SKILL.md", "# Review"),
+            ("review/scripts/run.sh", "echo hi"),
+        ]);
+        std::fs::create_dir_all(dir.path().join("review"))?;
+        std::fs::write(dir.path().join("review/stale.txt"), "old")?;
+
+        let manifest = install_bundle(dir.path(), &bundle)?;
+        assert_eq!(manifest.name, "review");
+        assert_eq!(manifest.version.as_deref(), Some("1.0"));
+        assert!(dir.path().join("review/scripts/run.sh").exists());
+        assert!(!dir.path().join("review/stale.txt").exists());
+        assert_eq!(
+            installed_skill_dirs(dir.path()),
+            vec![dir.path().join("review")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_bundle_rejects_invalid_bundles() {
+        let dir = tempdir().expect("tempdir");
+        let no_entry = build_zip(&[("manifest.json", r#"{"name":"x"}"#)]);
+        assert!(install_bundle(dir.path(), &no_entry).is_err());
+
+        let bad_name = build_zip(&[("manifest.json", r#"{"name":"../etc"}"#), ("SKILL.md", "x")]);
+        assert!(install_bundle(dir.path(), &bad_name).is_err());
+
+        let escaping = build_zip(&[
+            ("manifest.json", r#"{"name":"ok"}"#),
+            ("SKILL.md", "x"),
+            ("../evil.txt", "x"),
+        ]);
+        assert!(install_bundle(dir.path(), &escaping).is_err());
+        assert!(!dir.path().join("ok").exists());
+
+        assert!(install_bundle(dir.path(), b"not a zip").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_skill_name() {
+        assert!(is_valid_skill_name("code-review2"));
+        assert!(!is_valid_skill_name(""));
+        assert!(!is_valid_skill_name("-x"));
+        assert!(!is_valid_skill_name("Upper"));
+        assert!(!is_valid_skill_name("a/b"));
+    }
+}
@@ -0,0 +1,59 @@
+use serenity::all::{ChannelId, Http, MessageId, ReactionType};
+use tracing::warn;
+
+use crate::ExecStatus;
+
+const WORKING: &str = "⏳";
+const SUCCEEDED: &str = "✅";
+const FAILED: &str = "❌";
+
+fn reaction(emoji: &str) -> ReactionType {
+    ReactionType::Unicode(emoji.to_string())
+}
+
+/// 回合結束時標在提示上的反應
+fn outcome(status: &ExecStatus) -> &'static str {
+    match status {
+        ExecStatus::Success => SUCCEEDED,
+        _ => FAILED,
+    }
+}
+
+/// 回合開始：在使用者的提示加上 ⏳
+pub async fn mark_working(http: &Http, channel_id: ChannelId, prompt: MessageId) {
+    if let Err(e) = channel_id
+        .create_reaction(http, prompt, reaction(WORKING))
+        .await
+    {
+        warn!("⚠️ Failed to react to prompt {}: {}", prompt, e);
+    }
+}
+
+/// 回合結束：收回 ⏳，依結果改標 ✅ 或 ❌
+pub async fn mark_finished(
+    http: &Http,
+    channel_id: ChannelId,
+    prompt: MessageId,
+    status: &ExecStatus,
+) {
+    let _ = channel_id
+        .delete_reaction(http, prompt, None, reaction(WORKING))
+        .await;
+    if let Err(e) = channel_id
+        .create_reaction(http, prompt, reaction(outcome(status)))
+        .await
+    {
+        warn!("⚠️ Failed to react to prompt {}: {}", prompt, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_reaction_per_status() {
+        assert_eq!(outcome(&ExecStatus::Success), "✅");
+        assert_eq!(outcome(&ExecStatus::Error("boom".to_string())), "❌");
+    }
+}
@@ -0,0 +1,186 @@
+use async_trait::async_trait;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+static STORAGE: OnceLock<Arc<dyn Storage>> = OnceLock::new();
+
+/// 儲存空間中的一個物件
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    pub key: String,
+    pub modified: SystemTime,
+    pub size: u64,
+}
+
+/// 狀態檔的儲存後端；key 以 `/` 分隔 (如 `uploads/<channel>/<date>/<file>`)，
+/// 目前只有本機實作，之後可加入 S3 等物件儲存讓容器內的狀態不隨磁碟消失
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()>;
+    /// 物件不存在時回傳 None
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+    /// 刪除不存在的物件不算錯誤
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+    /// 列出 prefix 底下的所有物件
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<StoredObject>>;
+    /// 後端程式只讀得到本機檔案；遠端儲存需先下載到本機快取再回傳路徑
+    async fn local_path(&self, key: &str) -> anyhow::Result<PathBuf>;
+}
+
+/// 以 `~/.agent-discord-rs` 為根目錄的本機儲存
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn resolve(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let rel = Path::new(key);
+        let valid = !key.is_empty() && rel.components().all(|c| matches!(c, Component::Normal(_)));
+        if !valid {
+            anyhow::bail!("Invalid storage key: {}", key);
+        }
+        Ok(self.root.join(rel))
+    }
+
+    /// 刪除檔案後一路往上清掉空目錄 (不含根目錄)
+    async fn prune_empty_parents(&self, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if d == self.root || !d.starts_with(&self.root) {
+                break;
+            }
+            if tokio::fs::remove_dir(d).await.is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        let path = self.resolve(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, data).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.resolve(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.resolve(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {
+                self.prune_empty_parents(&path).await;
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<StoredObject>> {
+        let mut out = Vec::new();
+        let mut stack = vec![self.resolve(prefix)?];
+        while let Some(dir) = stack.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    stack.push(path);
+                    continue;
+                }
+                let Ok(rel) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let key = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                out.push(StoredObject {
+                    key,
+                    modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    size: metadata.len(),
+                });
+            }
+        }
+        out.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(out)
+    }
+
+    async fn local_path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        self.resolve(key)
+    }
+}
+
+pub fn install(storage: Arc<dyn Storage>) {
+    let _ = STORAGE.set(storage);
+}
+
+/// 目前使用的儲存後端；未安裝時使用本機資料目錄
+pub fn get() -> Arc<dyn Storage> {
+    STORAGE
+        .get_or_init(|| Arc::new(LocalStorage::new(crate::migrate::get_base_dir())))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_local_storage_put_get_list_delete() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let storage = LocalStorage::new(dir.path());
+        assert_eq!(storage.get("uploads/1/a.txt").await?, None);
+
+        storage.put("uploads/1/20260101/a.txt", b"a").await?;
+        storage.put("uploads/2/b.txt", b"b").await?;
+        assert_eq!(
+            storage.get("uploads/1/20260101/a.txt").await?,
+            Some(b"a".to_vec())
+        );
+        let keys: Vec<_> = storage
+            .list("uploads")
+            .await?
+            .into_iter()
+            .map(|o| o.key)
+            .collect();
+        assert_eq!(keys, vec!["uploads/1/20260101/a.txt", "uploads/2/b.txt"]);
+
+        storage.delete("uploads/1/20260101/a.txt").await?;
+        storage.delete("uploads/1/20260101/a.txt").await?;
+        assert!(!dir.path().join("uploads/1").exists());
+        assert!(dir.path().join("uploads/2/b.txt").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_rejects_escaping_keys() {
+        let dir = tempdir().expect("tempdir");
+        let storage = LocalStorage::new(dir.path());
+        assert!(storage.put("../x", b"x").await.is_err());
+        assert!(storage.put("/etc/x", b"x").await.is_err());
+        assert!(storage.get("").await.is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use serenity::all::{Context, EditMessage, Message};
+use tracing::info;
+
+use crate::agent::THINKING_LEVELS;
+use crate::commands::agent::ChannelConfig;
+
+/// 列出模型時最多顯示的數量，避免超過 Discord 訊息長度
+const MAX_LISTED_MODELS: usize = 25;
+
+/// 斜線指令的文字版，供無法使用斜線指令的環境 (部分行動客戶端、轉發橋接)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextCommand {
+    Help,
+    Abort,
+    Clear,
+    /// 不帶參數時列出可用模型
+    Model(Option<String>),
+    Thinking(Option<String>),
+}
+
+/// 解析以前綴開頭的訊息；開頭的提及 (`@bot !abort`) 會略過，不認得的名稱照常當成提示
+pub fn parse(prefix: &str, content: &str) -> Option<TextCommand> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let mut rest = content.trim_start();
+    while let Some(after) = rest.strip_prefix("<@") {
+        let (_, tail) = after.split_once('>')?;
+        rest = tail.trim_start();
+    }
+    let mut parts = rest
+        .strip_prefix(prefix)?
+        .trim()
+        .splitn(2, char::is_whitespace);
+    let name = parts.next()?.to_lowercase();
+    let arg = parts
+        .next()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    match name.as_str() {
+        "help" => Some(TextCommand::Help),
+        "abort" => Some(TextCommand::Abort),
+        "clear" => Some(TextCommand::Clear),
+        "model" => Some(TextCommand::Model(arg)),
+        "thinking" => Some(TextCommand::Thinking(arg)),
+        _ => None,
+    }
+}
+
+/// 頻道啟用文字指令，且私人 session 允許此使用者時才執行
+pub fn allowed(channel_config: &ChannelConfig, channel_id: &str, user_id: u64) -> bool {
+    channel_config
+        .channels
+        .get(channel_id)
+        .is_some_and(|e| e.text_commands)
+        && channel_config
+            .private_session(channel_id)
+            .is_none_or(|private| private.allows(user_id))
+}
+
+/// 模型清單，超過上限時註明省略的數量
+fn model_lines(models: &[crate::agent::ModelInfo]) -> String {
+    let mut lines: Vec<String> = models
+        .iter()
+        .take(MAX_LISTED_MODELS)
+        .map(|m| format!("`{}/{}`", m.provider, m.id))
+        .collect();
+    if models.len() > MAX_LISTED_MODELS {
+        lines.push(format!("… +{}", models.len() - MAX_LISTED_MODELS));
+    }
+    lines.join("\n")
+}
+
+async fn model_reply(
+    state: &crate::AppState,
+    channel_id: u64,
+    name: Option<String>,
+) -> anyhow::Result<String> {
+    let agent_type = ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, _) = state
+        .sessions()
+        .get_or_create_session(channel_id, agent_type)
+        .await?;
+    let i18n = state.channel_i18n(channel_id).await;
+    let i18n = i18n.read().await;
+    let models = match agent.get_available_models().await {
+        Ok(models) => models,
+        Err(e) => return Ok(i18n.get_args("model_fetch_failed", &[e.to_string()])),
+    };
+    if models.is_empty() {
+        return Ok(i18n.get("model_no_available"));
+    }
+    let Some(name) = name else {
+        return Ok(i18n.get_args(
+            "text_cmd_models",
+            &[
+                models.len().to_string(),
+                state.config.text_command_prefix.clone(),
+                model_lines(&models),
+            ],
+        ));
+    };
+    let Some(model) = models.iter().find(|m| m.matches(&name)) else {
+        return Ok(i18n.get_args(
+            "text_cmd_model_unknown",
+            &[name, state.config.text_command_prefix.clone()],
+        ));
+    };
+    Ok(
+        match crate::commands::model::change_model(
+            agent.as_ref(),
+            &i18n,
+            &model.provider,
+            &model.id,
+        )
+        .await
+        {
+            Ok(confirmation) => confirmation,
+            Err(e) => i18n.get_args("model_failed", &[e.to_string()]),
+        },
+    )
+}
+
+/// 執行文字指令並回覆在原訊息下
+pub async fn handle(
+    ctx: &Context,
+    msg: &Message,
+    command: TextCommand,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    let channel_id = msg.channel_id.get();
+    let prefix = state.config.text_command_prefix.clone();
+    info!(
+        "⌨️ Text command {:?} from {} in channel {}",
+        command, msg.author.name, msg.channel_id
+    );
+    let i18n = state.channel_i18n(channel_id).await;
+    let reply = match command {
+        TextCommand::Help => i18n.read().await.get_args("text_cmd_help", &[prefix]),
+        TextCommand::Abort => {
+            if let Some(aborted) = crate::turn_controls::abort_turn(state, msg.channel_id).await? {
+                let _ = msg
+                    .channel_id
+                    .edit_message(&ctx.http, aborted, EditMessage::new().components(vec![]))
+                    .await;
+            }
+            i18n.read().await.get("abort_success")
+        }
+        TextCommand::Clear => {
+            crate::commands::clear::clear_channel(state, channel_id).await?;
+            i18n.read().await.get("clear_success")
+        }
+        TextCommand::Model(name) => model_reply(state, channel_id, name).await?,
+        TextCommand::Thinking(Some(level)) if THINKING_LEVELS.contains(&level.as_str()) => {
+            crate::commands::thinking::apply_level(state, channel_id, &level).await?
+        }
+        TextCommand::Thinking(_) => i18n.read().await.get_args(
+            "text_cmd_thinking_usage",
+            &[prefix, THINKING_LEVELS.join("|")],
+        ),
+    };
+    msg.reply(&ctx.http, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_commands() {
+        assert_eq!(parse("!", "!abort"), Some(TextCommand::Abort));
+        assert_eq!(parse("!", "  !HELP "), Some(TextCommand::Help));
+        assert_eq!(
+            parse("!", "<@123> !model  kilo/gpt-5 "),
+            Some(TextCommand::Model(Some("kilo/gpt-5".to_string())))
+        );
+        assert_eq!(parse("!", "!model"), Some(TextCommand::Model(None)));
+        assert_eq!(
+            parse("?", "?thinking high"),
+            Some(TextCommand::Thinking(Some("high".to_string())))
+        );
+        // 不認得的名稱與一般訊息照常送給代理
+        assert_eq!(parse("!", "!important: fix the build"), None);
+        assert_eq!(parse("!", "please !abort"), None);
+        assert_eq!(parse("", "abort"), None);
+    }
+
+    #[test]
+    fn test_allowed_requires_flag_and_private_access() {
+        let mut config = ChannelConfig::default();
+        assert!(!allowed(&config, "1", 7));
+        config.entry_mut("1").text_commands = true;
+        assert!(allowed(&config, "1", 7));
+        config.entry_mut("1").private = Some(crate::commands::session::PrivateSession {
+            owner: 8,
+            ..Default::default()
+        });
+        assert!(!allowed(&config, "1", 7));
+        assert!(allowed(&config, "1", 8));
+    }
+}
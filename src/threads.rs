@@ -0,0 +1,98 @@
+use serenity::all::{ChannelId, ChannelType, Context, GuildId};
+use tracing::{info, warn};
+
+use crate::commands::agent::ChannelConfig;
+
+/// Discord 討論串名稱的長度上限 (字元)
+const MAX_NAME_CHARS: usize = 100;
+
+pub fn is_thread(kind: ChannelType) -> bool {
+    matches!(
+        kind,
+        ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+    )
+}
+
+/// 以主題作為討論串名稱，過長時截斷
+pub fn thread_name(topic: &str) -> String {
+    let topic = topic.split_whitespace().collect::<Vec<_>>().join(" ");
+    if topic.chars().count() <= MAX_NAME_CHARS {
+        return topic;
+    }
+    topic.chars().take(MAX_NAME_CHARS - 1).collect::<String>() + "…"
+}
+
+/// 頻道是討論串時回傳其父頻道；優先查快取，快取中沒有的頻道才向 Discord 查詢
+pub async fn parent_channel(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Option<ChannelId> {
+    let cached = ctx.cache.guild(guild_id).and_then(|guild| {
+        if let Some(thread) = guild.threads.iter().find(|t| t.id == channel_id) {
+            return Some(thread.parent_id);
+        }
+        guild.channels.contains_key(&channel_id).then_some(None)
+    });
+    if let Some(parent) = cached {
+        return parent;
+    }
+    let channel = channel_id.to_channel(&ctx.http).await.ok()?.guild()?;
+    channel.parent_id.filter(|_| is_thread(channel.kind))
+}
+
+/// 討論串封存或刪除時結束它的 session：停止回合、清空佇列、釋放後端 session 並移除設定
+pub async fn close_session(state: &crate::AppState, thread_id: u64) {
+    let key = thread_id.to_string();
+    let agent = state.sessions().get_session(thread_id).await;
+    let configured = ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .channels
+        .contains_key(&key);
+    if agent.is_none() && !configured {
+        return;
+    }
+
+    if let Some((_, handles)) = state.active_renders.lock().await.remove(&thread_id) {
+        for handle in handles {
+            handle.abort();
+        }
+    }
+    state.pending_inputs.lock().await.remove(&thread_id);
+    state.queue_journal.finish(thread_id).await;
+    state.sync_queue_journal(thread_id).await;
+
+    if let Some(agent) = agent {
+        if let Err(e) = agent.clear().await {
+            warn!("⚠️ Backend clear failed on thread {}: {}", thread_id, e);
+        }
+        let session_file = crate::migrate::get_sessions_dir(agent.agent_type())
+            .join(format!("discord-rs-{}.jsonl", thread_id));
+        tokio::fs::remove_file(&session_file).await.ok();
+        state.sessions().remove_session(thread_id).await;
+    }
+    crate::memory::remove(thread_id).await;
+    if let Err(e) = ChannelConfig::update(|cfg| cfg.channels.remove(&key)).await {
+        warn!(
+            "⚠️ Failed to remove settings of thread {}: {}",
+            thread_id, e
+        );
+    }
+    info!("🧵 Closed session of archived thread {}", thread_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_name_collapses_whitespace_and_truncates() {
+        assert_eq!(thread_name("  fix   the\nbuild "), "fix the build");
+        let long = thread_name(&"長".repeat(150));
+        assert_eq!(long.chars().count(), MAX_NAME_CHARS);
+        assert!(long.ends_with('…'));
+        assert!(is_thread(ChannelType::PrivateThread));
+        assert!(!is_thread(ChannelType::Text));
+    }
+}
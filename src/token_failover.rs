@@ -0,0 +1,73 @@
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, Http};
+use tracing::warn;
+
+use crate::config::Config;
+
+/// 依序嘗試的 token：主要 token，之後是設定的備用 token (空白或與主要相同時略過)
+pub fn candidates(config: &Config) -> Vec<String> {
+    let mut tokens = vec![config.discord_token.clone()];
+    let backup = config.token_failover.backup_token.trim();
+    if !backup.is_empty() && backup != config.discord_token.trim() {
+        tokens.push(backup.to_string());
+    }
+    tokens
+}
+
+/// gateway 以驗證失敗 (close code 4004) 斷線，表示 token 已被撤銷或更換
+pub fn is_invalid_token(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Gateway(serenity::gateway::GatewayError::InvalidAuthentication)
+    )
+}
+
+/// 以接手的連線通知管理頻道，提醒更換主要 token
+pub async fn alert_admins(state: &crate::AppState, http: &Http) {
+    let channel = state.config.token_failover.admin_channel;
+    if channel == 0 {
+        return;
+    }
+    let embed = {
+        let i18n = state.i18n.read().await;
+        CreateEmbed::new()
+            .title(i18n.get("token_failover_title"))
+            .description(i18n.get("token_failover_body"))
+            .color(0xE74C3C)
+    };
+    if let Err(e) = ChannelId::new(channel)
+        .send_message(http, CreateMessage::new().embed(embed))
+        .await
+    {
+        warn!("⚠️ Failed to alert admins about token failover: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(primary: &str, backup: &str) -> Config {
+        Config {
+            discord_token: primary.to_string(),
+            token_failover: crate::config::TokenFailoverConfig {
+                backup_token: backup.to_string(),
+                admin_channel: 0,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_candidates_and_invalid_token_detection() {
+        assert_eq!(candidates(&config("a", "")), vec!["a"]);
+        assert_eq!(candidates(&config("a", " a ")), vec!["a"]);
+        assert_eq!(candidates(&config("a", "b")), vec!["a", "b"]);
+
+        assert!(is_invalid_token(&serenity::Error::Gateway(
+            serenity::gateway::GatewayError::InvalidAuthentication
+        )));
+        assert!(!is_invalid_token(&serenity::Error::Gateway(
+            serenity::gateway::GatewayError::InvalidGatewayIntents
+        )));
+    }
+}
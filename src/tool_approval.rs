@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    EditMessage, Http,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch, Mutex, RwLock};
+use tracing::{info, warn};
+
+use crate::agent::approval::{Approver, ToolRequest};
+use crate::i18n::I18n;
+
+pub const BUTTON_PREFIX: &str = "tool_approval_";
+const APPROVE_PREFIX: &str = "tool_approval_approve_";
+const DENY_PREFIX: &str = "tool_approval_deny_";
+// 工具參數在 Embed 中只顯示開頭
+const DETAIL_MAX_CHARS: usize = 900;
+
+/// 在頻道中以按鈕詢問管理員是否允許代理執行工具
+pub struct ToolApproval {
+    timeout: Duration,
+    i18n: Arc<RwLock<I18n>>,
+    http: OnceLock<watch::Receiver<Arc<Http>>>,
+    /// 等待按鈕回應的請求 ID → 結果
+    pending: Mutex<HashMap<String, oneshot::Sender<bool>>>,
+}
+
+impl ToolApproval {
+    pub fn new(timeout: Duration, i18n: Arc<RwLock<I18n>>) -> Self {
+        Self {
+            timeout,
+            i18n,
+            http: OnceLock::new(),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 連上 Discord 後提供目前連線的 Http
+    pub fn attach(&self, http: watch::Receiver<Arc<Http>>) {
+        let _ = self.http.set(http);
+    }
+
+    /// 按鈕的決定交給等待中的請求；請求已逾時或已回應過時回傳 false
+    async fn resolve(&self, request_id: &str, approved: bool) -> bool {
+        match self.pending.lock().await.remove(request_id) {
+            Some(tx) => tx.send(approved).is_ok(),
+            None => false,
+        }
+    }
+}
+
+pub fn parse_button(custom_id: &str) -> Option<(&str, bool)> {
+    if let Some(id) = custom_id.strip_prefix(APPROVE_PREFIX) {
+        return Some((id, true));
+    }
+    custom_id.strip_prefix(DENY_PREFIX).map(|id| (id, false))
+}
+
+fn request_embed(i18n: &I18n, request: &ToolRequest) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(i18n.get("tool_approval_title"))
+        .description(i18n.get_args(
+            "tool_approval_desc",
+            &[request.backend.to_string(), request.tool.clone()],
+        ))
+        .color(0xf1c40f);
+    if !request.detail.is_empty() {
+        let mut detail: String = request.detail.chars().take(DETAIL_MAX_CHARS).collect();
+        if detail.len() < request.detail.len() {
+            detail.push('…');
+        }
+        embed = embed.field(
+            i18n.get("tool_approval_detail"),
+            format!("```\n{}\n```", detail.replace("```", "'''")),
+            false,
+        );
+    }
+    embed
+}
+
+#[async_trait]
+impl Approver for ToolApproval {
+    async fn approve(&self, request: ToolRequest) -> bool {
+        let Some(http) = self.http.get().map(|rx| rx.borrow().clone()) else {
+            warn!("⚠️ Tool approval requested before connecting to Discord; denying");
+            return false;
+        };
+        if request.channel_id == 0 {
+            warn!(
+                "⚠️ Tool approval for an unknown channel; denying {}",
+                request.tool
+            );
+            return false;
+        }
+        let request_id = uuid::Uuid::new_v4().simple().to_string();
+        let message = {
+            let i18n = self.i18n.read().await;
+            CreateMessage::new()
+                .embed(request_embed(&i18n, &request))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(format!("{}{}", APPROVE_PREFIX, request_id))
+                        .label(i18n.get("tool_approval_approve"))
+                        .style(ButtonStyle::Success),
+                    CreateButton::new(format!("{}{}", DENY_PREFIX, request_id))
+                        .label(i18n.get("tool_approval_deny"))
+                        .style(ButtonStyle::Danger),
+                ])])
+        };
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(request_id.clone(), tx);
+        let channel = ChannelId::new(request.channel_id);
+        let mut sent = match channel.send_message(&http, message).await {
+            Ok(sent) => sent,
+            Err(e) => {
+                self.pending.lock().await.remove(&request_id);
+                warn!(
+                    "⚠️ Failed to ask for tool approval in channel {}: {}",
+                    request.channel_id, e
+                );
+                return false;
+            }
+        };
+        info!(
+            "🔐 Waiting for approval of {} on channel {}",
+            request.tool, request.channel_id
+        );
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(approved)) => approved,
+            _ => {
+                self.pending.lock().await.remove(&request_id);
+                let note = self.i18n.read().await.get_args(
+                    "tool_approval_timed_out",
+                    &[self.timeout.as_secs().to_string()],
+                );
+                let _ = sent
+                    .edit(
+                        &http,
+                        EditMessage::new().content(note).components(Vec::new()),
+                    )
+                    .await;
+                false
+            }
+        }
+    }
+}
+
+/// 「核准」與「拒絕」按鈕：只有管理員的決定有效
+pub async fn handle_button(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    let Some((request_id, approved)) = parse_button(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+    let i18n = state.i18n.read().await;
+    if !crate::commands::is_admin(interaction.member.as_ref(), interaction.guild_id.is_some()) {
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(i18n.get("tool_approval_not_admin"))
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+    let content = if state.tool_approval.resolve(request_id, approved).await {
+        let key = if approved {
+            "tool_approval_approved"
+        } else {
+            "tool_approval_denied"
+        };
+        info!(
+            "🔐 Tool request {} {} by {}",
+            request_id,
+            if approved { "approved" } else { "denied" },
+            interaction.user.name
+        );
+        i18n.get_args(key, &[format!("<@{}>", interaction.user.id)])
+    } else {
+        i18n.get("tool_approval_expired")
+    };
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_button_resolves_pending_request_once() {
+        let approval = ToolApproval::new(
+            Duration::from_secs(1),
+            Arc::new(RwLock::new(I18n::new("en"))),
+        );
+        let (tx, rx) = oneshot::channel();
+        approval.pending.lock().await.insert("abc".to_string(), tx);
+
+        let (id, approved) = parse_button("tool_approval_deny_abc").expect("button");
+        assert!(approval.resolve(id, approved).await);
+        assert_eq!(rx.await, Ok(false));
+        assert!(!approval.resolve("abc", true).await);
+
+        assert_eq!(
+            parse_button("tool_approval_approve_abc"),
+            Some(("abc", true))
+        );
+        assert!(parse_button("turn_abort_1").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_approve_denies_without_discord_connection() {
+        let approval = ToolApproval::new(
+            Duration::from_secs(1),
+            Arc::new(RwLock::new(I18n::new("en"))),
+        );
+        let request = ToolRequest {
+            channel_id: 1,
+            backend: "copilot",
+            tool: "Run `rm -rf build`".to_string(),
+            detail: String::new(),
+        };
+        assert!(!approval.approve(request).await);
+    }
+}
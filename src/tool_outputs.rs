@@ -0,0 +1,257 @@
+use crate::storage::Storage;
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    EditInteractionResponse, MessageId,
+};
+use tracing::warn;
+
+pub const BUTTON_PREFIX: &str = "tool_output_";
+/// 直接以訊息文字顯示的上限，超過改以附件送出
+pub const INLINE_MAX_CHARS: usize = 1800;
+
+/// 單一工具呼叫的完整輸出 (Embed 內只顯示截斷後的版本)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ToolOutput {
+    pub id: String,
+    pub name: String,
+    pub output: String,
+}
+
+/// 本輪所有工具的完整輸出，依呼叫順序排列
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ToolOutputLog {
+    pub tools: Vec<ToolOutput>,
+}
+
+impl ToolOutputLog {
+    fn entry(&mut self, id: &str) -> &mut ToolOutput {
+        if let Some(pos) = self.tools.iter().position(|t| t.id == id) {
+            return &mut self.tools[pos];
+        }
+        self.tools.push(ToolOutput {
+            id: id.to_string(),
+            ..Default::default()
+        });
+        self.tools.last_mut().expect("just pushed")
+    }
+
+    pub fn start(&mut self, id: &str, name: &str) {
+        self.entry(id).name = name.to_string();
+    }
+
+    /// 與 composer 相同：後端送來的是累積後的輸出，較短的舊快照不覆蓋
+    pub fn update(&mut self, id: &str, output: &str) {
+        let entry = self.entry(id);
+        if output.len() >= entry.output.len() {
+            entry.output = output.to_string();
+        }
+    }
+
+    pub fn has_output(&self) -> bool {
+        self.tools.iter().any(|t| !t.output.trim().is_empty())
+    }
+
+    /// 以純文字呈現，供訊息或附件使用
+    pub fn render(&self) -> String {
+        self.tools
+            .iter()
+            .filter(|t| !t.output.trim().is_empty())
+            .map(|t| {
+                let name = if t.name.is_empty() { &t.id } else { &t.name };
+                format!("### {}\n{}", name, t.output.trim_end())
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+fn channel_prefix(channel_id: u64) -> String {
+    format!("tool_outputs/{}", channel_id)
+}
+
+fn log_key(channel_id: u64, message_id: u64) -> String {
+    format!("{}/{}.json", channel_prefix(channel_id), message_id)
+}
+
+fn message_id_of(key: &str) -> Option<u64> {
+    key.rsplit('/').next()?.strip_suffix(".json")?.parse().ok()
+}
+
+async fn save_to(
+    storage: &dyn Storage,
+    channel_id: u64,
+    message_id: u64,
+    log: &ToolOutputLog,
+    retain: usize,
+) -> anyhow::Result<()> {
+    storage
+        .put(
+            &log_key(channel_id, message_id),
+            serde_json::to_string(log)?.as_bytes(),
+        )
+        .await?;
+    // 訊息 ID 依時間遞增，只保留最新的 retain 輪
+    let mut ids: Vec<u64> = storage
+        .list(&channel_prefix(channel_id))
+        .await?
+        .iter()
+        .filter_map(|o| message_id_of(&o.key))
+        .collect();
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    for old in ids.into_iter().skip(retain) {
+        storage.delete(&log_key(channel_id, old)).await?;
+    }
+    Ok(())
+}
+
+async fn load_from(
+    storage: &dyn Storage,
+    channel_id: u64,
+    message_id: u64,
+) -> Option<ToolOutputLog> {
+    let content = storage.get(&log_key(channel_id, message_id)).await.ok()??;
+    serde_json::from_slice(&content).ok()
+}
+
+/// 保存本輪的完整工具輸出；retain 為 0 或沒有輸出時不保存。回傳是否可顯示「完整輸出」按鈕
+pub async fn store(channel_id: u64, message_id: u64, log: &ToolOutputLog, retain: usize) -> bool {
+    if retain == 0 || !log.has_output() {
+        return false;
+    }
+    match save_to(
+        crate::storage::get().as_ref(),
+        channel_id,
+        message_id,
+        log,
+        retain,
+    )
+    .await
+    {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                "⚠️ Failed to store tool outputs for message {} in {}: {}",
+                message_id, channel_id, e
+            );
+            false
+        }
+    }
+}
+
+pub async fn load(channel_id: u64, message_id: u64) -> Option<ToolOutputLog> {
+    load_from(crate::storage::get().as_ref(), channel_id, message_id).await
+}
+
+pub fn show_button(label: String, message_id: MessageId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "{}{}",
+        BUTTON_PREFIX, message_id
+    ))
+    .label(label)
+    .style(ButtonStyle::Secondary)])
+}
+
+pub fn parse_button(custom_id: &str) -> Option<u64> {
+    custom_id.strip_prefix(BUTTON_PREFIX)?.parse().ok()
+}
+
+/// 「完整輸出」按鈕：短的直接以私密訊息顯示，長的以附件送出 (過大時壓縮分割)
+pub async fn handle_show_button(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    // 完整輸出可能包含嵌入訊息刻意截斷的檔案內容，與其他回合按鈕一樣先檢查權限
+    if !crate::turn_controls::authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction.defer_ephemeral(&ctx.http).await?;
+    let log = match parse_button(&interaction.data.custom_id) {
+        Some(message_id) => load(interaction.channel_id.get(), message_id).await,
+        None => None,
+    };
+    let text = log.map(|l| l.render()).filter(|t| !t.is_empty());
+    let i18n = state.channel_i18n(interaction.channel_id.get()).await;
+    let i18n = i18n.read().await;
+    let Some(text) = text else {
+        let msg = i18n.get("tool_output_missing");
+        drop(i18n);
+        interaction
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        return Ok(());
+    };
+
+    if text.chars().count() <= INLINE_MAX_CHARS && !text.contains("```") {
+        drop(i18n);
+        interaction
+            .edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content(format!("```\n{}\n```", text)),
+            )
+            .await?;
+        return Ok(());
+    }
+    let first = EditInteractionResponse::new().content(i18n.get("tool_output_attached"));
+    drop(i18n);
+    crate::delivery::send_interaction_file_within_limit(
+        &ctx.http,
+        interaction.channel_id,
+        &interaction.token,
+        first,
+        &format!("tool-output-{}.txt", interaction.message.id),
+        text.into_bytes(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_log_keeps_full_output_in_call_order() {
+        let mut log = ToolOutputLog::default();
+        log.start("a", "bash");
+        log.start("b", "read");
+        log.update("a", "line1\nline2");
+        log.update("a", "line1");
+        log.update("b", &"x".repeat(5000));
+        assert_eq!(log.tools[0].output, "line1\nline2");
+        let rendered = log.render();
+        assert!(rendered.starts_with("### bash\nline1\nline2\n\n### read\n"));
+        assert_eq!(rendered.matches('x').count(), 5000);
+
+        let mut empty = ToolOutputLog::default();
+        empty.start("c", "ls");
+        assert!(!empty.has_output());
+    }
+
+    #[tokio::test]
+    async fn test_save_prunes_to_newest_turns() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let storage = LocalStorage::new(dir.path());
+        let mut log = ToolOutputLog::default();
+        log.update("a", "out");
+        for msg in [100u64, 300, 200] {
+            save_to(&storage, 1, msg, &log, 2).await?;
+        }
+        save_to(&storage, 2, 50, &log, 2).await?;
+
+        assert!(load_from(&storage, 1, 100).await.is_none());
+        assert_eq!(load_from(&storage, 1, 200).await, Some(log.clone()));
+        assert!(load_from(&storage, 1, 300).await.is_some());
+        assert!(load_from(&storage, 2, 50).await.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_button_custom_id() {
+        assert_eq!(parse_button("tool_output_123"), Some(123));
+        assert_eq!(parse_button("tool_output_x"), None);
+        assert_eq!(parse_button("model_replay"), None);
+    }
+}
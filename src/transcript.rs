@@ -0,0 +1,131 @@
+use serde::Serialize;
+
+use crate::agent::HistoryEntry;
+
+/// `/export` 支援的輸出格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Json,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "json" => Self::Json,
+            _ => Self::Markdown,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Transcript<'a> {
+    channel_id: u64,
+    backend: &'a str,
+    exported_at: String,
+    messages: &'a [HistoryEntry],
+}
+
+fn role_heading(role: &str) -> &str {
+    match role {
+        "user" => "👤 User",
+        "assistant" => "🤖 Assistant",
+        other => other,
+    }
+}
+
+pub fn to_markdown(channel_id: u64, backend: &str, entries: &[HistoryEntry]) -> String {
+    let mut out = format!(
+        "# Transcript of channel {}\n\n- backend: `{}`\n- exported: {}\n- messages: {}\n",
+        channel_id,
+        backend,
+        chrono::Utc::now().to_rfc3339(),
+        entries.len()
+    );
+    for entry in entries {
+        out.push_str("\n---\n\n## ");
+        out.push_str(role_heading(&entry.role));
+        if let Some(ts) = &entry.timestamp {
+            out.push_str(&format!(" · {}", ts));
+        }
+        out.push_str("\n\n");
+        if !entry.tools.is_empty() {
+            let tools: Vec<String> = entry.tools.iter().map(|t| format!("`{}`", t)).collect();
+            out.push_str(&format!("_Tools: {}_\n\n", tools.join(", ")));
+        }
+        out.push_str(entry.text.trim());
+        out.push('\n');
+    }
+    out
+}
+
+pub fn to_json(channel_id: u64, backend: &str, entries: &[HistoryEntry]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(&Transcript {
+        channel_id,
+        backend,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        messages: entries,
+    })?)
+}
+
+/// 依格式輸出對話紀錄，回傳 (檔名, 內容)
+pub fn render(
+    format: Format,
+    channel_id: u64,
+    backend: &str,
+    entries: &[HistoryEntry],
+) -> anyhow::Result<(String, String)> {
+    let body = match format {
+        Format::Markdown => to_markdown(channel_id, backend, entries),
+        Format::Json => to_json(channel_id, backend, entries)?,
+    };
+    Ok((
+        format!("transcript-{}.{}", channel_id, format.extension()),
+        body,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<HistoryEntry> {
+        vec![
+            HistoryEntry {
+                role: "user".to_string(),
+                text: "fix the build".to_string(),
+                timestamp: Some("2026-01-01T00:00:00Z".to_string()),
+                ..Default::default()
+            },
+            HistoryEntry {
+                role: "assistant".to_string(),
+                text: "done".to_string(),
+                tools: vec!["bash".to_string()],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_markdown_and_json() -> anyhow::Result<()> {
+        let (name, md) = render(Format::Markdown, 7, "pi", &sample())?;
+        assert_eq!(name, "transcript-7.md");
+        assert!(md.contains("## 👤 User · 2026-01-01T00:00:00Z\n\nfix the build"));
+        assert!(md.contains("_Tools: `bash`_\n\ndone"));
+
+        let (name, json) = render(Format::parse("json"), 7, "pi", &sample())?;
+        assert_eq!(name, "transcript-7.json");
+        let val: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(val["backend"], "pi");
+        assert_eq!(val["messages"][1]["tools"][0], "bash");
+        assert!(val["messages"][0].get("tools").is_none());
+        Ok(())
+    }
+}
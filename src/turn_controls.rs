@@ -0,0 +1,270 @@
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    EditInteractionResponse, MessageId,
+};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::agent::AiAgent;
+
+pub const ABORT_PREFIX: &str = "turn_abort_";
+pub const REGENERATE_PREFIX: &str = "turn_regenerate_";
+
+/// 回合進行中顯示在回應訊息下方的「中止」按鈕
+pub fn abort_button(label: String, message_id: MessageId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "{}{}",
+        ABORT_PREFIX, message_id
+    ))
+    .label(label)
+    .style(ButtonStyle::Danger)])
+}
+
+/// 回合完成後的「重新產生」按鈕
+pub fn regenerate_button(label: String, message_id: MessageId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "{}{}",
+        REGENERATE_PREFIX, message_id
+    ))
+    .label(label)
+    .style(ButtonStyle::Secondary)])
+}
+
+pub fn parse_abort(custom_id: &str) -> Option<u64> {
+    custom_id.strip_prefix(ABORT_PREFIX)?.parse().ok()
+}
+
+pub fn parse_regenerate(custom_id: &str) -> Option<u64> {
+    custom_id.strip_prefix(REGENERATE_PREFIX)?.parse().ok()
+}
+
+/// 開始或中止回合的按鈕共用的權限檢查：使用者須能在此頻道使用代理，且不被私人 session 排除。
+/// 不通過時以僅自己可見的訊息回覆並回傳 false，呼叫端不應再回應這次互動
+pub async fn authorize_button(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<bool> {
+    let channel_id = interaction.channel_id;
+    let (is_auth, _) = state
+        .auth()
+        .is_authorized_with_thread(
+            ctx,
+            &interaction.user.id.to_string(),
+            channel_id,
+            interaction.guild_id,
+        )
+        .await;
+    let denied = if !is_auth {
+        Some(("turn_button_not_authorized", Vec::new()))
+    } else {
+        crate::commands::agent::ChannelConfig::load()
+            .await
+            .unwrap_or_default()
+            .private_session(&channel_id.to_string())
+            .filter(|private| !private.allows(interaction.user.id.get()))
+            .map(|private| ("turn_button_private", vec![private.mentions()]))
+    };
+    let Some((key, args)) = denied else {
+        return Ok(true);
+    };
+    let msg = state
+        .channel_i18n(channel_id.get())
+        .await
+        .read()
+        .await
+        .get_args(key, &args);
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(msg)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(false)
+}
+
+/// 停止頻道進行中的回合：自 `active_renders` 取出、中止其 render 工作並要求後端停止，不動佇列。
+/// `should_stop` 收到進行中回合的訊息 ID，回傳 false 時保留不動；回傳被停止回合的訊息 ID
+pub async fn stop_running_turn(
+    state: &crate::AppState,
+    channel_id: ChannelId,
+    agent: &Arc<dyn AiAgent>,
+    should_stop: impl FnOnce(MessageId) -> bool,
+) -> Option<MessageId> {
+    let (msg_id, handles) = {
+        let mut active = state.active_renders.lock().await;
+        let (active_msg_id, _) = active.get(&channel_id.get())?;
+        if !should_stop(*active_msg_id) {
+            return None;
+        }
+        active.remove(&channel_id.get())?
+    };
+    // 不刪除訊息：使用者可能想保留已輸出的部分內容，render 停止後最後的內容會留在 Discord 上
+    for handle in handles {
+        handle.abort();
+    }
+    if let Err(e) = agent.abort().await {
+        warn!("⚠️ Failed to abort turn on channel {}: {}", channel_id, e);
+    }
+    Some(msg_id)
+}
+
+/// 中止頻道進行中的回合並清空佇列；`/abort` 與「中止」按鈕共用。回傳被中止回合的訊息 ID
+pub async fn abort_turn(
+    state: &crate::AppState,
+    channel_id: ChannelId,
+) -> anyhow::Result<Option<MessageId>> {
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, _) = state
+        .sessions()
+        .get_or_create_session(channel_id.get(), agent_type)
+        .await?;
+    let aborted = stop_running_turn(state, channel_id, &agent, |_| true).await;
+    state.pending_inputs.lock().await.remove(&channel_id.get());
+    state.queue_journal.finish(channel_id.get()).await;
+    state.sync_queue_journal(channel_id.get()).await;
+    // 沒有進行中的 render 時仍要求後端停止 (例如畫面已結束但後端還在執行)
+    if aborted.is_none() {
+        agent.abort().await?;
+    }
+    Ok(aborted)
+}
+
+/// 「中止」按鈕：只對按鈕所屬的進行中回合有效，並移除訊息上的按鈕
+pub async fn handle_abort(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    let channel_id = interaction.channel_id;
+    let target = parse_abort(&interaction.data.custom_id).map(MessageId::new);
+    let running = state
+        .active_renders
+        .lock()
+        .await
+        .get(&channel_id.get())
+        .is_some_and(|(msg_id, _)| Some(*msg_id) == target);
+    // render 已停止更新這則訊息，按鈕要由這裡拿掉
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().components(vec![]),
+            ),
+        )
+        .await?;
+    let key = if running {
+        abort_turn(state, channel_id).await?;
+        info!(
+            "⏹ {} aborted the turn on channel {}",
+            interaction.user.name, channel_id
+        );
+        "abort_success"
+    } else {
+        "turn_abort_expired"
+    };
+    let msg = state
+        .channel_i18n(channel_id.get())
+        .await
+        .read()
+        .await
+        .get(key);
+    interaction
+        .create_followup(
+            &ctx.http,
+            CreateInteractionResponseFollowup::new()
+                .content(msg)
+                .ephemeral(true),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 「重新產生」按鈕：以同一個提問重跑頻道最近一次的回合，新回應標記為原訊息的修訂版
+pub async fn handle_regenerate(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    if !authorize_button(ctx, interaction, state).await? {
+        return Ok(());
+    }
+    interaction.defer_ephemeral(&ctx.http).await?;
+
+    let channel_id = interaction.channel_id;
+    let i18n = state.channel_i18n(channel_id.get()).await;
+    let previous = parse_regenerate(&interaction.data.custom_id).map(MessageId::new);
+    // 只能重跑頻道中最近一次的回合，之後已有新回合時按鈕失效
+    let input = state
+        .last_turns
+        .lock()
+        .await
+        .get(&channel_id.get())
+        .filter(|(msg_id, _)| Some(*msg_id) == previous)
+        .map(|(_, input)| input.clone());
+    let (Some(previous_msg_id), Some(input)) = (previous, input) else {
+        let msg = i18n.read().await.get("turn_regenerate_expired");
+        interaction
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        return Ok(());
+    };
+    if state.is_processing(channel_id.get()).await {
+        let msg = i18n.read().await.get("turn_regenerate_busy");
+        interaction
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+            .await?;
+        return Ok(());
+    }
+
+    let agent_type = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(&channel_id.to_string());
+    let (agent, is_new) = crate::Handler::open_session(state, channel_id.get(), agent_type).await?;
+
+    let msg = i18n.read().await.get("turn_regenerate_started");
+    interaction
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(msg))
+        .await?;
+
+    info!("🔁 Regenerating last turn on channel {}", channel_id);
+    crate::Handler::start_agent_loop(
+        agent,
+        ctx.http.clone(),
+        channel_id,
+        state.clone(),
+        Some(input),
+        is_new,
+        Some(previous_msg_id),
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_button_ids_roundtrip() {
+        assert_eq!(parse_abort(&format!("{}{}", ABORT_PREFIX, 42)), Some(42));
+        assert_eq!(
+            parse_regenerate(&format!("{}{}", REGENERATE_PREFIX, 42)),
+            Some(42)
+        );
+        assert_eq!(parse_abort("turn_regenerate_42"), None);
+        assert_eq!(parse_regenerate("turn_retry_42"), None);
+    }
+}
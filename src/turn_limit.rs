@@ -0,0 +1,185 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct BackendSlots {
+    active: usize,
+    waiting: VecDeque<u64>,
+}
+
+/// 每種後端同時執行的回合數上限 (config.toml `[max_concurrent_turns]`)，超過的回合依序排隊
+pub struct TurnLimiter {
+    limits: HashMap<String, usize>,
+    slots: Mutex<HashMap<String, BackendSlots>>,
+    notify: Notify,
+    next_ticket: AtomicU64,
+}
+
+impl TurnLimiter {
+    pub fn new(limits: HashMap<String, usize>) -> Self {
+        Self {
+            limits,
+            slots: Mutex::new(HashMap::new()),
+            notify: Notify::new(),
+            next_ticket: AtomicU64::new(0),
+        }
+    }
+
+    /// 後端設有上限時取得排隊號碼；未設定或為 0 表示不限制
+    pub fn ticket(self: &Arc<Self>, backend: &str) -> Option<TurnTicket> {
+        let limit = self.limits.get(backend).copied().filter(|l| *l > 0)?;
+        let id = self.next_ticket.fetch_add(1, Ordering::SeqCst);
+        self.lock()
+            .entry(backend.to_string())
+            .or_default()
+            .waiting
+            .push_back(id);
+        Some(TurnTicket {
+            limiter: Arc::clone(self),
+            backend: backend.to_string(),
+            id,
+            limit,
+            last_position: None,
+            granted: false,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, BackendSlots>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct TurnTicket {
+    limiter: Arc<TurnLimiter>,
+    backend: String,
+    id: u64,
+    limit: usize,
+    last_position: Option<usize>,
+    granted: bool,
+}
+
+impl TurnTicket {
+    /// 輪到自己且有空位時取得名額；否則回傳排隊位置 (從 1 起算)
+    fn try_acquire(&mut self) -> Result<TurnPermit, usize> {
+        let mut slots = self.limiter.lock();
+        let entry = slots.entry(self.backend.clone()).or_default();
+        let index = entry
+            .waiting
+            .iter()
+            .position(|id| *id == self.id)
+            .unwrap_or(0);
+        if index == 0 && entry.active < self.limit {
+            entry.waiting.pop_front();
+            entry.active += 1;
+            self.granted = true;
+            return Ok(TurnPermit {
+                limiter: Arc::clone(&self.limiter),
+                backend: self.backend.clone(),
+            });
+        }
+        Err(index + 1)
+    }
+
+    /// 等到取得名額，或排隊位置有變動時回傳新位置讓呼叫端更新顯示
+    pub async fn wait(&mut self) -> Result<TurnPermit, usize> {
+        let limiter = Arc::clone(&self.limiter);
+        loop {
+            let notified = limiter.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            match self.try_acquire() {
+                Ok(permit) => return Ok(permit),
+                Err(position) if self.last_position != Some(position) => {
+                    self.last_position = Some(position);
+                    return Err(position);
+                }
+                Err(_) => notified.await,
+            }
+        }
+    }
+}
+
+impl Drop for TurnTicket {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+        // 放棄排隊 (例如 /abort) 時讓出位置
+        if let Some(entry) = self.limiter.lock().get_mut(&self.backend) {
+            entry.waiting.retain(|id| *id != self.id);
+        }
+        self.limiter.notify.notify_waiters();
+    }
+}
+
+/// 持有期間佔用一個名額，回合結束 (drop) 時釋放
+pub struct TurnPermit {
+    limiter: Arc<TurnLimiter>,
+    backend: String,
+}
+
+impl Drop for TurnPermit {
+    fn drop(&mut self) {
+        if let Some(entry) = self.limiter.lock().get_mut(&self.backend) {
+            entry.active = entry.active.saturating_sub(1);
+        }
+        self.limiter.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(limit: usize) -> Arc<TurnLimiter> {
+        Arc::new(TurnLimiter::new(HashMap::from([(
+            "copilot".to_string(),
+            limit,
+        )])))
+    }
+
+    #[tokio::test]
+    async fn test_turn_limiter_queues_in_order_and_reports_positions() {
+        let limiter = limiter(1);
+        assert!(limiter.ticket("kilo").is_none());
+
+        let first = limiter
+            .ticket("copilot")
+            .expect("limited")
+            .wait()
+            .await
+            .expect("free slot");
+        let mut second = limiter.ticket("copilot").expect("limited");
+        let mut third = limiter.ticket("copilot").expect("limited");
+        assert_eq!(second.wait().await.err(), Some(1));
+        assert_eq!(third.wait().await.err(), Some(2));
+
+        drop(first);
+        let second_permit = second.wait().await.expect("second runs next");
+        assert_eq!(third.wait().await.err(), Some(1));
+
+        drop(second_permit);
+        let third_permit = tokio::time::timeout(Duration::from_secs(1), third.wait()).await;
+        assert!(matches!(third_permit, Ok(Ok(_))));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_ticket_leaves_queue() {
+        let limiter = limiter(1);
+        let _running = limiter
+            .ticket("copilot")
+            .expect("limited")
+            .wait()
+            .await
+            .expect("free slot");
+        let mut abandoned = limiter.ticket("copilot").expect("limited");
+        let mut waiting = limiter.ticket("copilot").expect("limited");
+        assert_eq!(abandoned.wait().await.err(), Some(1));
+        assert_eq!(waiting.wait().await.err(), Some(2));
+        drop(abandoned);
+        assert_eq!(waiting.wait().await.err(), Some(1));
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+/// Discord 的輸入中狀態約維持 10 秒，每 5 秒重送一次
+const TYPING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 決定何時送出「輸入中」：只在後端仍有串流活動時顯示，
+/// 超過 `idle_after` 沒有新事件 (例如長時間工具執行) 就停止，收到新 delta 後立即恢復
+pub struct TypingGate {
+    idle_after: Duration,
+    last_sent: Option<Instant>,
+}
+
+impl TypingGate {
+    pub fn new(idle_after: Duration) -> Self {
+        Self {
+            idle_after,
+            last_sent: None,
+        }
+    }
+
+    /// 回傳此刻是否需要送出輸入中狀態
+    pub fn tick(&mut self, now: Instant, last_activity: Instant) -> bool {
+        if now.saturating_duration_since(last_activity) >= self.idle_after {
+            self.last_sent = None;
+            return false;
+        }
+        if self
+            .last_sent
+            .is_some_and(|sent| now.saturating_duration_since(sent) < TYPING_INTERVAL)
+        {
+            return false;
+        }
+        self.last_sent = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypingGate;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_typing_gate_pauses_when_idle_and_resumes_on_activity() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut gate = TypingGate::new(Duration::from_secs(10));
+
+        assert!(gate.tick(at(0), at(0)));
+        // 重送間隔內不重複送出
+        assert!(!gate.tick(at(3), at(2)));
+        assert!(gate.tick(at(6), at(2)));
+        // 工具長時間執行、沒有新事件
+        assert!(!gate.tick(at(13), at(2)));
+        // 新 delta 到達後立即恢復
+        assert!(gate.tick(at(14), at(14)));
+    }
+}
@@ -1,72 +1,210 @@
-use crate::agent::UploadedFile;
-use crate::migrate;
+use crate::agent::{UploadedFile, OCR_CACHE_SUFFIX};
+use crate::i18n::I18n;
+use crate::storage::Storage;
+use serde::{Deserialize, Serialize};
 use serenity::all::Attachment;
-use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::Mutex;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+/// 上傳檔在儲存空間中的 key 前綴
+const UPLOADS_PREFIX: &str = "uploads";
+/// 內容定址快取的目錄；其中的檔案由引用計數管理，不套用一般的 TTL 清理
+const CAS_PREFIX: &str = "uploads/cas";
+const CAS_INDEX_KEY: &str = "uploads/cas/index.json";
+
+/// 內容定址快取索引：相同內容的附件在各頻道共用一份
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct CasIndex {
+    /// sha256 → 快取檔案
+    #[serde(default)]
+    entries: HashMap<String, CasEntry>,
+    /// 附件網址 (去掉簽章參數) → sha256；同一附件再次出現 (如轉傳) 時不必重新下載
+    #[serde(default)]
+    urls: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CasEntry {
+    key: String,
+    size: u64,
+    /// 頻道 ID → 最後引用時間 (unix 秒)；引用逾 TTL 即失效，全部失效後刪除檔案
+    refs: HashMap<u64, i64>,
+}
+
+/// 附件沒有傳給代理的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    TooLarge,
+    MimeNotAllowed(String),
+    QuotaExceeded,
+    DownloadFailed,
+}
+
+/// 一則訊息的附件處理結果
+#[derive(Debug, Default)]
+pub struct StagedAttachments {
+    pub files: Vec<UploadedFile>,
+    /// 檔名與未傳給代理的原因
+    pub rejected: Vec<(String, Rejection)>,
+}
+
 pub struct UploadManager {
     client: reqwest::Client,
-    root: PathBuf,
+    storage: Arc<dyn Storage>,
     max_file_bytes: u64,
     ttl: Duration,
     cleanup_interval: Duration,
     last_cleanup: Mutex<Option<Instant>>,
+    content_cache: bool,
+    cas_index: Mutex<Option<CasIndex>>,
+    allowed_mime: Vec<String>,
+    channel_quota_bytes: u64,
 }
 
 impl UploadManager {
     pub fn new(
+        storage: Arc<dyn Storage>,
         max_file_bytes: u64,
         ttl: Duration,
         cleanup_interval: Duration,
-    ) -> anyhow::Result<Self> {
-        let root = migrate::get_uploads_dir();
-        std::fs::create_dir_all(&root)?;
-        Ok(Self {
+        content_cache: bool,
+    ) -> Self {
+        Self {
             client: reqwest::Client::new(),
-            root,
+            storage,
             max_file_bytes,
             ttl,
             cleanup_interval,
             last_cleanup: Mutex::new(None),
-        })
+            content_cache,
+            cas_index: Mutex::new(None),
+            allowed_mime: Vec::new(),
+            channel_quota_bytes: 0,
+        }
+    }
+
+    /// 只接受清單中的 MIME 類型 (支援 `image/*`)；空白表示不限制
+    pub fn with_mime_allowlist(mut self, allowed: Vec<String>) -> Self {
+        self.allowed_mime = allowed;
+        self
+    }
+
+    /// 每個頻道保留中的上傳檔總量上限；0 表示不限制
+    pub fn with_channel_quota(mut self, bytes: u64) -> Self {
+        self.channel_quota_bytes = bytes;
+        self
     }
 
     pub async fn stage_attachments(
         &self,
         channel_id: u64,
         attachments: &[Attachment],
-    ) -> Vec<UploadedFile> {
+    ) -> StagedAttachments {
         self.maybe_cleanup().await;
 
+        let mut out = StagedAttachments::default();
         if attachments.is_empty() {
-            return Vec::new();
+            return out;
         }
 
-        let mut out = Vec::new();
+        let mut usage = if self.channel_quota_bytes > 0 {
+            self.channel_usage(channel_id).await
+        } else {
+            0
+        };
         for attachment in attachments {
-            if attachment.size > self.max_file_bytes as u32 {
+            let size = attachment.size as u64;
+            let mime = attachment_mime(attachment);
+            let rejection = if size > self.max_file_bytes {
+                Some(Rejection::TooLarge)
+            } else if !mime_allowed(&self.allowed_mime, &mime) {
+                Some(Rejection::MimeNotAllowed(mime))
+            } else if self.channel_quota_bytes > 0 && usage + size > self.channel_quota_bytes {
+                Some(Rejection::QuotaExceeded)
+            } else {
+                None
+            };
+            if let Some(reason) = rejection {
                 warn!(
-                    "Skipping attachment '{}' ({} bytes > max {} bytes)",
-                    attachment.filename, attachment.size, self.max_file_bytes
+                    "Skipping attachment '{}' ({} bytes) on channel {}: {:?}",
+                    attachment.filename, size, channel_id, reason
                 );
+                out.rejected.push((attachment.filename.clone(), reason));
                 continue;
             }
 
             match self.download_one(channel_id, attachment).await {
-                Ok(file) => out.push(file),
-                Err(e) => warn!(
-                    "Failed to stage attachment '{}': {}",
-                    attachment.filename, e
-                ),
+                Ok(file) => {
+                    usage += file.size;
+                    out.files.push(file);
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to stage attachment '{}': {}",
+                        attachment.filename, e
+                    );
+                    out.rejected
+                        .push((attachment.filename.clone(), Rejection::DownloadFailed));
+                }
             }
         }
 
         out
     }
 
+    /// 頻道保留中的上傳檔總量：頻道目錄下的檔案加上仍被此頻道引用的共用快取檔案
+    async fn channel_usage(&self, channel_id: u64) -> u64 {
+        let own: u64 = self
+            .storage
+            .list(&format!("{}/{}", UPLOADS_PREFIX, channel_id))
+            .await
+            .map(|objects| objects.iter().map(|o| o.size).sum())
+            .unwrap_or(0);
+        let mut slot = self.cas_index.lock().await;
+        let shared: u64 = self
+            .load_index(&mut slot)
+            .await
+            .entries
+            .values()
+            .filter(|e| e.refs.contains_key(&channel_id))
+            .map(|e| e.size)
+            .sum();
+        own + shared
+    }
+
+    /// 未傳給代理的附件與原因，回覆在原訊息下
+    pub fn rejection_notice(&self, i18n: &I18n, rejected: &[(String, Rejection)]) -> String {
+        let mib = |bytes: u64| format!("{:.1}", bytes as f64 / (1024.0 * 1024.0));
+        let lines = rejected
+            .iter()
+            .map(|(name, reason)| match reason {
+                Rejection::TooLarge => i18n.get_args(
+                    "uploads_too_large",
+                    &[name.clone(), mib(self.max_file_bytes)],
+                ),
+                Rejection::MimeNotAllowed(mime) => {
+                    i18n.get_args("uploads_mime_blocked", &[name.clone(), mime.clone()])
+                }
+                Rejection::QuotaExceeded => i18n.get_args(
+                    "uploads_quota_exceeded",
+                    &[name.clone(), mib(self.channel_quota_bytes)],
+                ),
+                Rejection::DownloadFailed => {
+                    i18n.get_args("uploads_failed", std::slice::from_ref(name))
+                }
+            })
+            .map(|line| format!("- {}", line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        i18n.get_args("uploads_skipped", &[lines])
+    }
+
     async fn maybe_cleanup(&self) {
         let mut lock = self.last_cleanup.lock().await;
         let should_run = match *lock {
@@ -87,72 +225,193 @@ impl UploadManager {
     }
 
     async fn cleanup_expired(&self) -> anyhow::Result<()> {
-        let mut stack = vec![self.root.clone()];
-        let now = SystemTime::now();
-        let mut removed = 0usize;
+        let removed = self.purge_before(SystemTime::now() - self.ttl).await?;
+        if removed > 0 {
+            info!("🧹 Upload cleanup removed {} expired files", removed);
+        }
+        Ok(())
+    }
 
-        while let Some(dir) = stack.pop() {
-            let mut entries = match tokio::fs::read_dir(&dir).await {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+    /// 刪除 `cutoff` 之前的上傳檔與快取引用，回傳刪除的檔案數
+    pub async fn purge_before(&self, cutoff: SystemTime) -> anyhow::Result<usize> {
+        let mut removed = 0usize;
 
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
-                let metadata = entry.metadata().await?;
+        for object in self.storage.list(UPLOADS_PREFIX).await? {
+            if object.key.starts_with(&format!("{}/", CAS_PREFIX)) {
+                continue;
+            }
+            if object.modified < cutoff && self.storage.delete(&object.key).await.is_ok() {
+                removed += 1;
+            }
+        }
 
-                if metadata.is_dir() {
-                    stack.push(path);
-                    continue;
-                }
+        // 快取停用後仍要清掉之前留下的共用檔案
+        removed += self.cleanup_content_cache(cutoff).await?;
+        Ok(removed)
+    }
 
-                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
-                let age = now
-                    .duration_since(modified)
-                    .unwrap_or_else(|_| Duration::from_secs(0));
+    /// 移除 `cutoff` 之前的頻道引用，並刪除已無任何引用的快取檔案 (含其擷取結果)
+    async fn cleanup_content_cache(&self, cutoff: SystemTime) -> anyhow::Result<usize> {
+        let mut slot = self.cas_index.lock().await;
+        let index = self.load_index(&mut slot).await;
+        let cutoff = chrono::DateTime::<chrono::Utc>::from(cutoff).timestamp();
+
+        let mut changed = false;
+        let mut orphaned = Vec::new();
+        for (hash, entry) in index.entries.iter_mut() {
+            let before = entry.refs.len();
+            entry.refs.retain(|_, at| *at >= cutoff);
+            changed |= entry.refs.len() != before;
+            if entry.refs.is_empty() {
+                orphaned.push(hash.clone());
+            }
+        }
+        if !changed && orphaned.is_empty() {
+            return Ok(0);
+        }
 
-                if age > self.ttl {
-                    if tokio::fs::remove_file(&path).await.is_ok() {
-                        removed += 1;
-                    }
-                }
+        for hash in &orphaned {
+            let Some(entry) = index.entries.remove(hash) else {
+                continue;
+            };
+            if let Ok(path) = self.storage.local_path(&entry.key).await {
+                let sidecar = format!("{}{}", path.to_string_lossy(), OCR_CACHE_SUFFIX);
+                let _ = tokio::fs::remove_file(sidecar).await;
             }
+            self.storage.delete(&entry.key).await?;
         }
+        index
+            .urls
+            .retain(|_, hash| index.entries.contains_key(hash));
+        self.save_index(index).await?;
+        Ok(orphaned.len())
+    }
 
-        self.remove_empty_dirs().await?;
-        if removed > 0 {
-            info!("🧹 Upload cleanup removed {} expired files", removed);
+    async fn load_index<'a>(&self, slot: &'a mut Option<CasIndex>) -> &'a mut CasIndex {
+        if slot.is_none() {
+            let index = match self.storage.get(CAS_INDEX_KEY).await {
+                Ok(Some(raw)) => serde_json::from_slice(&raw).unwrap_or_else(|e| {
+                    warn!("Attachment cache index is corrupt, starting empty: {}", e);
+                    CasIndex::default()
+                }),
+                Ok(None) => CasIndex::default(),
+                Err(e) => {
+                    warn!("Failed to read attachment cache index: {}", e);
+                    CasIndex::default()
+                }
+            };
+            *slot = Some(index);
         }
-        Ok(())
+        slot.get_or_insert_with(CasIndex::default)
     }
 
-    async fn remove_empty_dirs(&self) -> anyhow::Result<()> {
-        let mut stack = vec![self.root.clone()];
-        let mut dirs = Vec::new();
+    async fn save_index(&self, index: &CasIndex) -> anyhow::Result<()> {
+        self.storage
+            .put(CAS_INDEX_KEY, &serde_json::to_vec(index)?)
+            .await
+    }
 
-        while let Some(dir) = stack.pop() {
-            dirs.push(dir.clone());
-            let mut entries = match tokio::fs::read_dir(&dir).await {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            while let Some(entry) = entries.next_entry().await? {
-                if entry.metadata().await?.is_dir() {
-                    stack.push(entry.path());
+    async fn fetch(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let resp = self.client.get(url).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("download failed with status {}", resp.status());
+        }
+
+        let bytes = resp.bytes().await?;
+        if bytes.len() as u64 > self.max_file_bytes {
+            anyhow::bail!("downloaded file too large: {} bytes", bytes.len());
+        }
+        Ok(bytes.to_vec())
+    }
+
+    /// 以內容雜湊共用下載：已知網址直接沿用，否則下載後比對雜湊，相同內容只存一份
+    async fn stage_cached(
+        &self,
+        channel_id: u64,
+        attachment: &Attachment,
+        url: &str,
+    ) -> anyhow::Result<UploadedFile> {
+        let url_key = url.split('?').next().unwrap_or(url).to_string();
+        {
+            let mut slot = self.cas_index.lock().await;
+            let index = self.load_index(&mut slot).await;
+            let known = index.urls.get(&url_key).cloned();
+            if let Some(hash) = known {
+                if let Some(file) = self
+                    .reuse_entry(index, &hash, channel_id, attachment)
+                    .await?
+                {
+                    self.save_index(index).await?;
+                    return Ok(file);
                 }
             }
         }
 
-        dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
-        for dir in dirs {
-            if dir == self.root {
-                continue;
-            }
-            if is_dir_empty(&dir).await? {
-                let _ = tokio::fs::remove_dir(&dir).await;
+        // 下載期間不持有索引鎖，其他頻道的附件可同時處理
+        let bytes = self.fetch(url).await?;
+        let hash = content_hash(&bytes);
+
+        let mut slot = self.cas_index.lock().await;
+        let index = self.load_index(&mut slot).await;
+        index.urls.insert(url_key, hash.clone());
+        let file = match self
+            .reuse_entry(index, &hash, channel_id, attachment)
+            .await?
+        {
+            Some(file) => file,
+            None => {
+                let ext = Path::new(&sanitize_filename(&attachment.filename))
+                    .extension()
+                    .map(|e| format!(".{}", e.to_string_lossy()))
+                    .unwrap_or_default();
+                let key = format!("{}/{}{}", CAS_PREFIX, hash, ext);
+                self.storage.put(&key, &bytes).await?;
+                let local_path = self.storage.local_path(&key).await?;
+                let size = bytes.len() as u64;
+                index.entries.insert(
+                    hash.clone(),
+                    CasEntry {
+                        key,
+                        size,
+                        refs: HashMap::from([(channel_id, chrono::Utc::now().timestamp())]),
+                    },
+                );
+                to_uploaded_file(attachment, size, &local_path, Some(hash))
             }
+        };
+        self.save_index(index).await?;
+        Ok(file)
+    }
+
+    /// 快取檔案仍在時記錄此頻道的引用並回傳；檔案已遺失則移除條目
+    async fn reuse_entry(
+        &self,
+        index: &mut CasIndex,
+        hash: &str,
+        channel_id: u64,
+        attachment: &Attachment,
+    ) -> anyhow::Result<Option<UploadedFile>> {
+        let Some(entry) = index.entries.get_mut(hash) else {
+            return Ok(None);
+        };
+        let local_path = self.storage.local_path(&entry.key).await?;
+        if !tokio::fs::try_exists(&local_path).await.unwrap_or(false) {
+            index.entries.remove(hash);
+            return Ok(None);
         }
-        Ok(())
+        entry
+            .refs
+            .insert(channel_id, chrono::Utc::now().timestamp());
+        info!(
+            "♻️ Reusing cached attachment '{}' ({}) for channel {}",
+            attachment.filename, hash, channel_id
+        );
+        Ok(Some(to_uploaded_file(
+            attachment,
+            entry.size,
+            &local_path,
+            Some(hash.to_string()),
+        )))
     }
 
     async fn download_one(
@@ -166,46 +425,78 @@ impl UploadManager {
             attachment.proxy_url.as_str()
         };
 
-        let resp = self.client.get(url).send().await?;
-        if !resp.status().is_success() {
-            anyhow::bail!("download failed with status {}", resp.status());
-        }
-
-        let bytes = resp.bytes().await?;
-        if bytes.len() as u64 > self.max_file_bytes {
-            anyhow::bail!("downloaded file too large: {} bytes", bytes.len());
+        if self.content_cache {
+            return self.stage_cached(channel_id, attachment, url).await;
         }
 
+        let bytes = self.fetch(url).await?;
         let now = chrono::Utc::now();
-        let channel_dir = self
-            .root
-            .join(channel_id.to_string())
-            .join(now.format("%Y%m%d").to_string());
-        tokio::fs::create_dir_all(&channel_dir).await?;
-
         let safe_name = sanitize_filename(&attachment.filename);
-        let local_name = format!("{}-{}-{}", now.timestamp(), Uuid::new_v4(), safe_name);
-        let local_path = channel_dir.join(local_name);
-
-        tokio::fs::write(&local_path, &bytes).await?;
-
-        Ok(UploadedFile {
-            id: attachment.id.to_string(),
-            name: attachment.filename.clone(),
-            mime: attachment
-                .content_type
-                .clone()
-                .unwrap_or_else(|| guess_mime_from_name(&attachment.filename)),
-            size: bytes.len() as u64,
-            local_path: local_path.to_string_lossy().to_string(),
-            source_url: attachment.url.clone(),
-        })
+        let key = format!(
+            "{}/{}/{}/{}-{}-{}",
+            UPLOADS_PREFIX,
+            channel_id,
+            now.format("%Y%m%d"),
+            now.timestamp(),
+            Uuid::new_v4(),
+            safe_name
+        );
+        self.storage.put(&key, &bytes).await?;
+        let local_path = self.storage.local_path(&key).await?;
+
+        Ok(to_uploaded_file(
+            attachment,
+            bytes.len() as u64,
+            &local_path,
+            None,
+        ))
     }
 }
 
-async fn is_dir_empty(path: &Path) -> anyhow::Result<bool> {
-    let mut rd = tokio::fs::read_dir(path).await?;
-    Ok(rd.next_entry().await?.is_none())
+fn to_uploaded_file(
+    attachment: &Attachment,
+    size: u64,
+    local_path: &Path,
+    content_hash: Option<String>,
+) -> UploadedFile {
+    UploadedFile {
+        id: attachment.id.to_string(),
+        name: attachment.filename.clone(),
+        mime: attachment_mime(attachment),
+        size,
+        local_path: local_path.to_string_lossy().to_string(),
+        source_url: attachment.url.clone(),
+        content_hash,
+    }
+}
+
+fn attachment_mime(attachment: &Attachment) -> String {
+    attachment
+        .content_type
+        .clone()
+        .unwrap_or_else(|| guess_mime_from_name(&attachment.filename))
+}
+
+/// 比對 MIME 類型 (忽略 `; charset=` 等參數)；`image/*` 比對主類型，清單空白表示全部允許
+fn mime_allowed(allowed: &[String], mime: &str) -> bool {
+    let mime = mime
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    allowed.is_empty()
+        || allowed.iter().any(|pattern| {
+            let pattern = pattern.trim().to_ascii_lowercase();
+            match pattern.strip_suffix("/*") {
+                Some(major) => mime.split('/').next() == Some(major),
+                None => pattern == "*" || pattern == mime,
+            }
+        })
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
 }
 
 fn sanitize_filename(name: &str) -> String {
@@ -246,18 +537,42 @@ fn guess_mime_from_name(name: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::LocalStorage;
+    use std::path::PathBuf;
     use std::time::Duration;
     use tempfile::tempdir;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn attachment(id: u64, filename: &str, url: &str) -> Attachment {
+        serde_json::from_value(serde_json::json!({
+            "id": id.to_string(),
+            "filename": filename,
+            "size": 4,
+            "url": url,
+            "proxy_url": url,
+        }))
+        .expect("attachment")
+    }
+
+    fn cached_manager(root: PathBuf) -> UploadManager {
+        UploadManager::new(
+            Arc::new(LocalStorage::new(root)),
+            1024 * 1024,
+            Duration::from_secs(3600),
+            Duration::from_secs(3600),
+            true,
+        )
+    }
 
     fn test_manager(root: PathBuf, ttl: Duration, cleanup_interval: Duration) -> UploadManager {
-        UploadManager {
-            client: reqwest::Client::new(),
-            root,
-            max_file_bytes: 1024 * 1024,
+        UploadManager::new(
+            Arc::new(LocalStorage::new(root)),
+            1024 * 1024,
             ttl,
             cleanup_interval,
-            last_cleanup: Mutex::new(None),
-        }
+            false,
+        )
     }
 
     #[test]
@@ -283,7 +598,7 @@ mod tests {
     #[tokio::test]
     async fn test_cleanup_expired_removes_old_files_and_empty_dirs() {
         let dir = tempdir().expect("tempdir");
-        let nested = dir.path().join("chan").join("date");
+        let nested = dir.path().join("uploads").join("chan").join("date");
         tokio::fs::create_dir_all(&nested).await.expect("mkdir");
         tokio::fs::write(nested.join("old.txt"), "x")
             .await
@@ -296,7 +611,7 @@ mod tests {
         );
         manager.cleanup_expired().await.expect("cleanup");
 
-        assert!(is_dir_empty(dir.path()).await.expect("dir check"));
+        assert!(!dir.path().join("uploads").join("chan").exists());
     }
 
     #[tokio::test]
@@ -315,4 +630,166 @@ mod tests {
         let second = *manager.last_cleanup.lock().await;
         assert_eq!(first, second);
     }
+
+    #[tokio::test]
+    async fn test_content_cache_shares_downloads_across_channels() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"spec".to_vec()))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let dir = tempdir().expect("tempdir");
+        let manager = cached_manager(dir.path().to_path_buf());
+
+        let url = format!("{}/a/spec.pdf?ex=1", server.uri());
+        let first = manager
+            .download_one(1, &attachment(10, "spec.pdf", &url))
+            .await
+            .expect("first");
+        // 同一附件 (簽章參數不同) 再次出現時不重新下載
+        let resigned = format!("{}/a/spec.pdf?ex=2", server.uri());
+        let forwarded = manager
+            .download_one(2, &attachment(10, "spec.pdf", &resigned))
+            .await
+            .expect("forwarded");
+        // 不同網址但內容相同時只存一份
+        let other_url = format!("{}/b/copy.pdf", server.uri());
+        let copy = manager
+            .download_one(3, &attachment(11, "copy.pdf", &other_url))
+            .await
+            .expect("copy");
+
+        assert_eq!(first.local_path, forwarded.local_path);
+        assert_eq!(first.local_path, copy.local_path);
+        assert_eq!(copy.name, "copy.pdf");
+        assert_eq!(first.content_hash.as_deref().map(str::len), Some(64));
+        assert!(first.local_path.ends_with(".pdf"));
+
+        let index = manager.cas_index.lock().await;
+        let entry = &index.as_ref().expect("index").entries[first.content_hash.as_ref().unwrap()];
+        assert_eq!(entry.refs.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_content_cache_cleanup_waits_for_last_reference() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"spec".to_vec()))
+            .mount(&server)
+            .await;
+        let dir = tempdir().expect("tempdir");
+        let manager = cached_manager(dir.path().to_path_buf());
+        let url = format!("{}/spec.png", server.uri());
+        let file = manager
+            .download_one(1, &attachment(10, "spec.png", &url))
+            .await
+            .expect("stage");
+        manager
+            .download_one(2, &attachment(10, "spec.png", &url))
+            .await
+            .expect("stage again");
+        let sidecar = file.ocr_cache_path().expect("sidecar");
+        tokio::fs::write(&sidecar, "text").await.expect("write ocr");
+        let hash = file.content_hash.clone().expect("hash");
+
+        let expire = |channel: u64| {
+            let mut index = manager.cas_index.try_lock().expect("index lock");
+            let entry = index
+                .as_mut()
+                .and_then(|i| i.entries.get_mut(&hash))
+                .expect("entry");
+            entry.refs.insert(channel, 0);
+        };
+
+        // 仍有頻道引用時保留檔案
+        expire(1);
+        assert_eq!(
+            manager
+                .cleanup_content_cache(SystemTime::now() - manager.ttl)
+                .await
+                .expect("cleanup"),
+            0
+        );
+        assert!(Path::new(&file.local_path).exists());
+
+        expire(2);
+        assert_eq!(
+            manager
+                .cleanup_content_cache(SystemTime::now() - manager.ttl)
+                .await
+                .expect("cleanup"),
+            1
+        );
+        assert!(!Path::new(&file.local_path).exists());
+        assert!(!Path::new(&sidecar).exists());
+        let raw = tokio::fs::read(dir.path().join(CAS_INDEX_KEY))
+            .await
+            .expect("index");
+        let index: CasIndex = serde_json::from_slice(&raw).expect("parse index");
+        assert!(index.entries.is_empty());
+        assert!(index.urls.is_empty());
+    }
+
+    #[test]
+    fn test_mime_allowed_supports_wildcards() {
+        assert!(mime_allowed(&[], "application/zip"));
+        let allowed = vec!["image/*".to_string(), "application/pdf".to_string()];
+        assert!(mime_allowed(&allowed, "image/png"));
+        assert!(mime_allowed(&allowed, "Application/PDF"));
+        assert!(mime_allowed(&allowed, "image/jpeg; charset=binary"));
+        assert!(!mime_allowed(&allowed, "application/zip"));
+        assert!(!mime_allowed(&allowed, "text/plain"));
+    }
+
+    #[tokio::test]
+    async fn test_stage_attachments_enforces_mime_and_channel_quota() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"data".to_vec()))
+            .mount(&server)
+            .await;
+        let dir = tempdir().expect("tempdir");
+        let manager = test_manager(
+            dir.path().to_path_buf(),
+            Duration::from_secs(3600),
+            Duration::from_secs(3600),
+        )
+        .with_mime_allowlist(vec!["image/*".to_string()])
+        .with_channel_quota(6);
+
+        let url = |name: &str| format!("{}/{}", server.uri(), name);
+        let attachments = vec![
+            attachment(1, "a.png", &url("a.png")),
+            attachment(2, "notes.zip", &url("notes.zip")),
+            attachment(3, "b.png", &url("b.png")),
+        ];
+        let staged = manager.stage_attachments(1, &attachments).await;
+        assert_eq!(staged.files.len(), 1);
+        assert_eq!(staged.files[0].name, "a.png");
+        assert_eq!(
+            staged.rejected,
+            vec![
+                (
+                    "notes.zip".to_string(),
+                    Rejection::MimeNotAllowed("application/octet-stream".to_string())
+                ),
+                ("b.png".to_string(), Rejection::QuotaExceeded),
+            ]
+        );
+        // 配額依頻道計算，其他頻道不受影響
+        assert_eq!(
+            manager
+                .stage_attachments(2, &attachments[2..])
+                .await
+                .files
+                .len(),
+            1
+        );
+
+        let notice = manager.rejection_notice(&I18n::new("en"), &staged.rejected);
+        assert!(notice.contains("notes.zip"));
+        assert!(notice.contains("application/octet-stream"));
+        assert!(notice.contains("b.png"));
+    }
 }
@@ -0,0 +1,426 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// 用量達上限的此比例時先發出警告
+pub const WARN_RATIO: f64 = 0.8;
+
+/// 伺服器每月的用量上限；兩項上限任一達到即視為超過
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GuildCap {
+    pub max_turns: Option<u64>,
+    pub max_tokens: Option<u64>,
+    /// 超過上限後改用的模型 (`provider/model`)；None 表示暫停使用直到下個月
+    pub fallback_model: Option<String>,
+    /// 警告與超限通知送往的頻道
+    pub alert_channel: u64,
+}
+
+impl GuildCap {
+    /// 備用模型拆成 (provider, model_id)
+    pub fn fallback(&self) -> Option<(String, String)> {
+        let (provider, model_id) = self.fallback_model.as_deref()?.split_once('/')?;
+        Some((provider.to_string(), model_id.to_string()))
+    }
+
+    /// 目前用量佔上限的最高比例
+    pub fn ratio(&self, usage: &GuildUsage) -> f64 {
+        let ratio = |used: u64, limit: Option<u64>| match limit {
+            Some(0) => 1.0,
+            Some(limit) => used as f64 / limit as f64,
+            None => 0.0,
+        };
+        ratio(usage.turns, self.max_turns).max(ratio(usage.tokens, self.max_tokens))
+    }
+}
+
+/// 伺服器本月已用的回合數與估計 token 數
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GuildUsage {
+    /// 計算期間 (`YYYY-MM`，伺服器本地時間)
+    pub period: String,
+    pub turns: u64,
+    pub tokens: u64,
+    #[serde(default)]
+    pub warned: bool,
+    #[serde(default)]
+    pub exceeded: bool,
+    /// 已切換到備用模型的頻道與其原本的模型，期間重置後換回
+    #[serde(default)]
+    pub switched: HashMap<String, Option<(String, String)>>,
+}
+
+/// 回合開始前的用量檢查結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gate {
+    Open,
+    /// 不再超過上限，先把頻道換回切換前的模型 (None 表示原本未指定模型)
+    Restore(Option<(String, String)>),
+    /// 已超過上限，改用備用模型；`switch` 表示本頻道首次切換，需要呼叫後端換模型
+    Fallback {
+        provider: String,
+        model_id: String,
+        switch: bool,
+    },
+    /// 已超過上限且未設定備用模型，到 Unix 時間 `resets_at` 前暫停
+    Paused {
+        resets_at: i64,
+    },
+}
+
+/// 回合完成後需要通知管理員的狀態變化
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alert {
+    Warning,
+    Exceeded,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GuildEntry {
+    pub cap: GuildCap,
+    #[serde(default)]
+    pub usage: GuildUsage,
+}
+
+pub fn period_key<Tz: TimeZone>(now: &DateTime<Tz>) -> String {
+    format!("{:04}-{:02}", now.year(), now.month())
+}
+
+/// 下個月第一天零時 (用量重置的時間)
+pub fn next_reset<Tz: TimeZone>(now: &DateTime<Tz>) -> DateTime<Tz> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .expect("valid first day of month");
+    now.timezone()
+        .from_local_datetime(&first)
+        .earliest()
+        .unwrap_or_else(|| now.clone())
+}
+
+impl GuildEntry {
+    pub fn new(cap: GuildCap) -> Self {
+        Self {
+            cap,
+            usage: GuildUsage::default(),
+        }
+    }
+
+    /// 進入新期間時清空用量；已切換的頻道留待各自下次開始回合時換回
+    fn roll<Tz: TimeZone>(&mut self, now: &DateTime<Tz>) {
+        let period = period_key(now);
+        if self.usage.period == period {
+            return;
+        }
+        self.usage = GuildUsage {
+            period,
+            switched: std::mem::take(&mut self.usage.switched),
+            ..GuildUsage::default()
+        };
+    }
+
+    /// `current_model` 是頻道目前儲存的模型，切換到備用模型時保留以便之後換回
+    pub fn gate<Tz: TimeZone>(
+        &mut self,
+        channel_id: u64,
+        current_model: Option<(String, String)>,
+        now: &DateTime<Tz>,
+    ) -> Gate {
+        let key = channel_id.to_string();
+        self.roll(now);
+        if !self.usage.exceeded {
+            return match self.usage.switched.remove(&key) {
+                Some(previous) => Gate::Restore(previous),
+                None => Gate::Open,
+            };
+        }
+        match self.cap.fallback() {
+            Some((provider, model_id)) => {
+                let switch = !self.usage.switched.contains_key(&key);
+                if switch {
+                    self.usage.switched.insert(key, current_model);
+                }
+                Gate::Fallback {
+                    provider,
+                    model_id,
+                    switch,
+                }
+            }
+            None => Gate::Paused {
+                resets_at: next_reset(now).timestamp(),
+            },
+        }
+    }
+
+    /// 記錄一個完成的回合，跨過警告或上限門檻時回傳需要發出的通知
+    pub fn record<Tz: TimeZone>(&mut self, tokens: u64, now: &DateTime<Tz>) -> Option<Alert> {
+        self.roll(now);
+        self.usage.turns += 1;
+        self.usage.tokens += tokens;
+        let ratio = self.cap.ratio(&self.usage);
+        if ratio >= 1.0 && !self.usage.exceeded {
+            self.usage.exceeded = true;
+            self.usage.warned = true;
+            Some(Alert::Exceeded)
+        } else if ratio >= WARN_RATIO && !self.usage.warned {
+            self.usage.warned = true;
+            Some(Alert::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// 送往通知頻道的訊息
+pub fn alert_message(i18n: &crate::i18n::I18n, alert: Alert, entry: &GuildEntry) -> String {
+    let resets_at = next_reset(&Local::now()).timestamp().to_string();
+    match (alert, &entry.cap.fallback_model) {
+        (Alert::Warning, _) => i18n.get_args(
+            "usage_alert_warning",
+            &[
+                i18n.format_decimal(entry.cap.ratio(&entry.usage) * 100.0, 0),
+                i18n.format_number(entry.usage.turns),
+                i18n.format_number(entry.usage.tokens),
+            ],
+        ),
+        (Alert::Exceeded, Some(model)) => {
+            i18n.get_args("usage_alert_exceeded_fallback", &[model.clone(), resets_at])
+        }
+        (Alert::Exceeded, None) => i18n.get_args("usage_alert_exceeded_paused", &[resets_at]),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UsageCapStore {
+    #[serde(default)]
+    pub guilds: HashMap<String, GuildEntry>,
+    /// 上限移除時仍在使用備用模型的頻道，下次開始回合時換回
+    #[serde(default)]
+    pub restore: HashMap<String, Option<(String, String)>>,
+}
+
+impl UsageCapStore {
+    pub async fn load() -> anyhow::Result<Self> {
+        let path = crate::migrate::get_usage_caps_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = tokio::fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let path = crate::migrate::get_usage_caps_path();
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(&path, content).await?;
+        Ok(())
+    }
+}
+
+/// 伺服器層級的每月用量上限，達 80% 時警告，超過後改用備用模型或暫停
+pub struct UsageCaps {
+    store: Mutex<UsageCapStore>,
+}
+
+impl UsageCaps {
+    pub async fn load() -> Self {
+        let store = UsageCapStore::load().await.unwrap_or_else(|e| {
+            warn!("⚠️ Failed to load usage caps: {}", e);
+            UsageCapStore::default()
+        });
+        Self {
+            store: Mutex::new(store),
+        }
+    }
+
+    async fn persist(store: &UsageCapStore) {
+        if let Err(e) = store.save().await {
+            warn!("⚠️ Failed to save usage caps: {}", e);
+        }
+    }
+
+    pub async fn get(&self, guild_id: u64) -> Option<GuildEntry> {
+        let mut store = self.store.lock().await;
+        let entry = store.guilds.get_mut(&guild_id.to_string())?;
+        entry.roll(&Local::now());
+        Some(entry.clone())
+    }
+
+    /// 設定上限；本月已累積的用量保留，只重新判斷是否超過
+    pub async fn set(&self, guild_id: u64, cap: GuildCap) -> anyhow::Result<()> {
+        let mut store = self.store.lock().await;
+        let entry = store
+            .guilds
+            .entry(guild_id.to_string())
+            .or_insert_with(|| GuildEntry::new(cap.clone()));
+        entry.cap = cap;
+        entry.roll(&Local::now());
+        let ratio = entry.cap.ratio(&entry.usage);
+        entry.usage.exceeded = ratio >= 1.0;
+        entry.usage.warned = ratio >= WARN_RATIO;
+        store.save().await
+    }
+
+    /// 移除上限；回傳是否原本有設定
+    pub async fn remove(&self, guild_id: u64) -> anyhow::Result<bool> {
+        let mut store = self.store.lock().await;
+        let Some(removed) = store.guilds.remove(&guild_id.to_string()) else {
+            return Ok(false);
+        };
+        store.restore.extend(removed.usage.switched);
+        store.save().await?;
+        Ok(true)
+    }
+
+    pub async fn gate(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        current_model: Option<(String, String)>,
+    ) -> Gate {
+        let mut store = self.store.lock().await;
+        let gate = match store.guilds.get_mut(&guild_id.to_string()) {
+            Some(entry) => entry.gate(channel_id, current_model, &Local::now()),
+            None => match store.restore.remove(&channel_id.to_string()) {
+                Some(previous) => Gate::Restore(previous),
+                None => Gate::Open,
+            },
+        };
+        if matches!(gate, Gate::Restore(_) | Gate::Fallback { switch: true, .. }) {
+            Self::persist(&store).await;
+        }
+        gate
+    }
+
+    /// 跨過門檻時回傳通知種類與記錄後的狀態
+    pub async fn record(&self, guild_id: u64, tokens: u64) -> Option<(Alert, GuildEntry)> {
+        let mut store = self.store.lock().await;
+        let entry = store.guilds.get_mut(&guild_id.to_string())?;
+        let alert = entry.record(tokens, &Local::now());
+        let snapshot = entry.clone();
+        Self::persist(&store).await;
+        alert.map(|alert| (alert, snapshot))
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::services::UsageService for UsageCaps {
+    async fn get(&self, guild_id: u64) -> Option<GuildEntry> {
+        UsageCaps::get(self, guild_id).await
+    }
+
+    async fn set(&self, guild_id: u64, cap: GuildCap) -> anyhow::Result<()> {
+        UsageCaps::set(self, guild_id, cap).await
+    }
+
+    async fn remove(&self, guild_id: u64) -> anyhow::Result<bool> {
+        UsageCaps::remove(self, guild_id).await
+    }
+
+    async fn gate(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        current_model: Option<(String, String)>,
+    ) -> Gate {
+        UsageCaps::gate(self, guild_id, channel_id, current_model).await
+    }
+
+    async fn record(&self, guild_id: u64, tokens: u64) -> Option<(Alert, GuildEntry)> {
+        UsageCaps::record(self, guild_id, tokens).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap()
+    }
+
+    fn entry(fallback: Option<&str>) -> GuildEntry {
+        GuildEntry::new(GuildCap {
+            max_turns: Some(10),
+            max_tokens: Some(1000),
+            fallback_model: fallback.map(str::to_string),
+            alert_channel: 1,
+        })
+    }
+
+    #[test]
+    fn test_period_and_reset() {
+        assert_eq!(period_key(&at(2026, 3, 9)), "2026-03");
+        assert_eq!(
+            next_reset(&at(2026, 12, 31)),
+            Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_record_warns_once_then_exceeds() {
+        let mut e = entry(None);
+        let now = at(2026, 3, 9);
+        for _ in 0..7 {
+            assert_eq!(e.record(10, &now), None);
+        }
+        assert_eq!(e.record(10, &now), Some(Alert::Warning));
+        assert_eq!(e.record(10, &now), None);
+        // token 上限先到
+        assert_eq!(e.record(900, &now), Some(Alert::Exceeded));
+        assert_eq!(e.record(10, &now), None);
+        assert_eq!(
+            e.gate(5, None, &now),
+            Gate::Paused {
+                resets_at: next_reset(&now).timestamp()
+            }
+        );
+        // 新的月份重新開放
+        assert_eq!(e.gate(5, None, &at(2026, 4, 1)), Gate::Open);
+        assert_eq!(e.usage.turns, 0);
+    }
+
+    #[test]
+    fn test_fallback_switches_each_channel_once_and_restores() {
+        let mut e = entry(Some("openai/gpt-4o-mini"));
+        let now = at(2026, 3, 9);
+        assert_eq!(e.gate(5, None, &now), Gate::Open);
+        e.record(1000, &now);
+
+        let previous = Some(("anthropic".to_string(), "claude".to_string()));
+        assert_eq!(
+            e.gate(5, previous.clone(), &now),
+            Gate::Fallback {
+                provider: "openai".to_string(),
+                model_id: "gpt-4o-mini".to_string(),
+                switch: true,
+            }
+        );
+        assert!(matches!(
+            e.gate(5, None, &now),
+            Gate::Fallback { switch: false, .. }
+        ));
+
+        let next_month = at(2026, 4, 2);
+        assert_eq!(e.gate(5, None, &next_month), Gate::Restore(previous));
+        assert_eq!(e.gate(5, None, &next_month), Gate::Open);
+    }
+
+    #[test]
+    fn test_raising_cap_restores_switched_channel() {
+        let mut e = entry(Some("openai/gpt-4o-mini"));
+        let now = at(2026, 3, 9);
+        e.record(1000, &now);
+        assert!(matches!(e.gate(5, None, &now), Gate::Fallback { .. }));
+        e.cap.max_tokens = Some(5000);
+        e.usage.exceeded = false;
+        assert_eq!(e.gate(5, None, &now), Gate::Restore(None));
+    }
+}
@@ -0,0 +1,284 @@
+use serde_json::json;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::VoiceConfig;
+
+const TTS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 語音朗讀：記錄各伺服器加入的語音頻道與要朗讀的文字頻道
+pub struct Voice {
+    config: VoiceConfig,
+    client: reqwest::Client,
+    /// guild → (語音頻道, 朗讀其回答的文字頻道)
+    links: Mutex<HashMap<u64, (u64, u64)>>,
+    #[cfg(feature = "voice")]
+    songbird: std::sync::Arc<songbird::Songbird>,
+}
+
+impl Voice {
+    pub fn new(config: VoiceConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            links: Mutex::new(HashMap::new()),
+            #[cfg(feature = "voice")]
+            songbird: songbird::Songbird::serenity(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    /// 註冊到 serenity Client 的語音管理器
+    #[cfg(feature = "voice")]
+    pub fn songbird(&self) -> std::sync::Arc<songbird::Songbird> {
+        self.songbird.clone()
+    }
+
+    /// 加入語音頻道，之後 `text_channel` 的最終回答會在此朗讀
+    pub async fn join(
+        &self,
+        guild_id: u64,
+        voice_channel: u64,
+        text_channel: u64,
+    ) -> anyhow::Result<()> {
+        self.connect(guild_id, voice_channel).await?;
+        self.links
+            .lock()
+            .await
+            .insert(guild_id, (voice_channel, text_channel));
+        info!(
+            "🔊 Joined voice channel {} in guild {} for channel {}",
+            voice_channel, guild_id, text_channel
+        );
+        Ok(())
+    }
+
+    /// 離開語音頻道；原本不在語音中時回傳 false
+    pub async fn leave(&self, guild_id: u64) -> bool {
+        if self.links.lock().await.remove(&guild_id).is_none() {
+            return false;
+        }
+        self.disconnect(guild_id).await;
+        true
+    }
+
+    /// 文字頻道有連結的語音頻道時，合成並播放回答
+    pub async fn speak(&self, text_channel: u64, answer: &str) {
+        let guild_id = self
+            .links
+            .lock()
+            .await
+            .iter()
+            .find(|(_, (_, text))| *text == text_channel)
+            .map(|(guild, _)| *guild);
+        let Some(guild_id) = guild_id else {
+            return;
+        };
+        let text = speech_text(answer, self.config.max_chars);
+        if text.is_empty() {
+            return;
+        }
+        match synthesize(&self.config, &self.client, &text).await {
+            Ok(audio) => self.play(guild_id, audio).await,
+            Err(e) => warn!("⚠️ TTS failed for channel {}: {}", text_channel, e),
+        }
+    }
+
+    #[cfg(feature = "voice")]
+    async fn connect(&self, guild_id: u64, voice_channel: u64) -> anyhow::Result<()> {
+        self.songbird
+            .join(
+                serenity::all::GuildId::new(guild_id),
+                serenity::all::ChannelId::new(voice_channel),
+            )
+            .await?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "voice"))]
+    async fn connect(&self, _guild_id: u64, _voice_channel: u64) -> anyhow::Result<()> {
+        anyhow::bail!("voice playback is not compiled in; rebuild with `--features voice`")
+    }
+
+    #[cfg(feature = "voice")]
+    async fn disconnect(&self, guild_id: u64) {
+        if let Err(e) = self
+            .songbird
+            .remove(serenity::all::GuildId::new(guild_id))
+            .await
+        {
+            warn!("⚠️ Failed to leave voice in guild {}: {}", guild_id, e);
+        }
+    }
+
+    #[cfg(not(feature = "voice"))]
+    async fn disconnect(&self, _guild_id: u64) {}
+
+    #[cfg(feature = "voice")]
+    async fn play(&self, guild_id: u64, audio: Vec<u8>) {
+        let Some(call) = self.songbird.get(serenity::all::GuildId::new(guild_id)) else {
+            // 連線已被踢出或中斷，不再朗讀此伺服器的回答
+            self.links.lock().await.remove(&guild_id);
+            return;
+        };
+        let _ = call.lock().await.play_input(audio.into());
+    }
+
+    #[cfg(not(feature = "voice"))]
+    async fn play(&self, _guild_id: u64, _audio: Vec<u8>) {}
+}
+
+/// 朗讀用的純文字：略過程式碼區塊與 Markdown 符號，超過上限時在句尾截斷
+pub fn speech_text(answer: &str, max_chars: usize) -> String {
+    let mut in_code = false;
+    let mut lines = Vec::new();
+    for line in answer.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let line: String = line
+            .chars()
+            .filter(|c| !matches!(c, '*' | '_' | '`' | '#' | '>' | '~' | '|'))
+            .collect();
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    let text = lines.join("\n");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let sentence_end = cut
+        .char_indices()
+        .rfind(|(_, c)| matches!(c, '.' | '!' | '?' | '。' | '！' | '？' | '\n'))
+        .map(|(i, c)| i + c.len_utf8());
+    match sentence_end {
+        Some(end) => cut[..end].trim_end().to_string(),
+        None => cut,
+    }
+}
+
+/// 以 piper (有設定模型時) 或 HTTP 端點合成 WAV 音訊
+pub async fn synthesize(
+    config: &VoiceConfig,
+    client: &reqwest::Client,
+    text: &str,
+) -> anyhow::Result<Vec<u8>> {
+    if !config.piper_model.trim().is_empty() {
+        return synthesize_piper(config, text).await;
+    }
+    if config.http_endpoint.trim().is_empty() {
+        anyhow::bail!("TTS is not configured: set [voice] piper_model or http_endpoint");
+    }
+    let mut req = client
+        .post(config.http_endpoint.trim())
+        .timeout(TTS_TIMEOUT)
+        .json(&json!({
+            "model": config.http_model,
+            "voice": config.http_voice,
+            "input": text,
+            "response_format": "wav",
+        }));
+    if !config.http_api_key.is_empty() {
+        req = req.bearer_auth(&config.http_api_key);
+    }
+    let resp = req.send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("TTS request failed: {}", resp.status());
+    }
+    Ok(resp.bytes().await?.to_vec())
+}
+
+async fn synthesize_piper(config: &VoiceConfig, text: &str) -> anyhow::Result<Vec<u8>> {
+    let output = tempfile::Builder::new().suffix(".wav").tempfile()?;
+    let binary = crate::agent::runtime::resolve_binary_path(&config.piper_binary);
+    let mut child = tokio::process::Command::new(binary)
+        .arg("--model")
+        .arg(config.piper_model.trim())
+        .arg("--output_file")
+        .arg(output.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await?;
+    }
+    let result = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow::anyhow!("piper timed out"))??;
+    if !result.status.success() {
+        anyhow::bail!(
+            "piper exited with {}: {}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(tokio::fs::read(output.path()).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_speech_text_skips_code_and_cuts_at_sentence() {
+        let answer = "## Result\nThe **build** passed.\n```rust\nfn main() {}\n```\n> Run `cargo test` next!";
+        assert_eq!(
+            speech_text(answer, 1000),
+            "Result\nThe build passed.\nRun cargo test next!"
+        );
+        assert_eq!(
+            speech_text("First sentence. Second sentence is long", 25),
+            "First sentence."
+        );
+        assert_eq!(speech_text("no stop here", 5), "no st");
+        assert_eq!(speech_text("```\ncode only\n```", 100), "");
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_posts_to_http_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer k"))
+            .and(body_partial_json(
+                json!({ "input": "hello", "response_format": "wav" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"RIFF".to_vec()))
+            .mount(&server)
+            .await;
+        let config = VoiceConfig {
+            http_endpoint: format!("{}/v1/audio/speech", server.uri()),
+            http_api_key: "k".to_string(),
+            ..Default::default()
+        };
+        let audio = synthesize(&config, &reqwest::Client::new(), "hello")
+            .await
+            .expect("audio");
+        assert_eq!(audio, b"RIFF");
+
+        let voice = Voice::new(VoiceConfig::default());
+        assert!(!voice.is_enabled());
+        assert!(
+            synthesize(&VoiceConfig::default(), &reqwest::Client::new(), "x")
+                .await
+                .is_err()
+        );
+    }
+}
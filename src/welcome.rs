@@ -0,0 +1,187 @@
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, Http,
+    UserId,
+};
+use tracing::info;
+
+use crate::i18n::I18n;
+use crate::services::AuthService;
+
+/// 以 `{name}` 形式替換模板變數
+pub fn render_template(template: &str, vars: &[(&str, String)]) -> String {
+    vars.iter()
+        .fold(template.to_string(), |acc, (name, value)| {
+            acc.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// 營運者可放置 welcome.md 自訂內容，否則使用語系預設模板
+pub async fn load_template(i18n: &I18n) -> String {
+    let path = crate::migrate::get_welcome_template_path();
+    match tokio::fs::read_to_string(&path).await {
+        Ok(content) if !content.trim().is_empty() => content,
+        _ => i18n.get("welcome_body"),
+    }
+}
+
+pub fn command_list(i18n: &I18n) -> String {
+    crate::commands::get_all_commands()
+        .iter()
+        .map(|cmd| format!("`/{}` — {}", cmd.name(), cmd.description(i18n)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn build_welcome_message(i18n: &I18n, body: String) -> CreateMessage {
+    CreateMessage::new()
+        .embed(
+            CreateEmbed::new()
+                .title(i18n.get("welcome_title"))
+                .color(0x5865F2)
+                .description(body),
+        )
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new("welcome_config")
+                .label(i18n.get("welcome_btn_config"))
+                .style(ButtonStyle::Primary),
+            CreateButton::new("welcome_agent")
+                .label(i18n.get("welcome_btn_agent"))
+                .style(ButtonStyle::Secondary),
+        ])])
+}
+
+/// 授權對象目前的 mention_only 設定；使用者授權 (私訊) 不需提及
+fn mention_only(auth: &dyn AuthService, type_: &str, id: &str) -> bool {
+    match type_ {
+        "channel" => auth.get_channel_mention_only(id),
+        "guild" => auth.get_guild_mention_only(id),
+        _ => None,
+    }
+    .unwrap_or(false)
+}
+
+/// CLI 完成授權後呼叫：頻道授權貼在該頻道，使用者授權則私訊
+pub async fn post_after_auth(auth: &dyn AuthService, type_: &str, id: &str) -> anyhow::Result<()> {
+    let config = crate::config::Config::load().await?;
+    if !config.welcome_message {
+        return Ok(());
+    }
+    let i18n = I18n::new(&config.language);
+    let backend = crate::commands::agent::ChannelConfig::load()
+        .await
+        .unwrap_or_default()
+        .get_agent_type(id);
+    let template = load_template(&i18n).await;
+    let body = render_template(
+        &template,
+        &[
+            ("assistant_name", config.assistant_name.clone()),
+            ("backend", backend.to_string()),
+            ("mention_only", mention_only(auth, type_, id).to_string()),
+            ("commands", command_list(&i18n)),
+        ],
+    );
+
+    let http = Http::new(&config.discord_token);
+    let target = match type_ {
+        "channel" => ChannelId::new(id.parse()?),
+        "user" => UserId::new(id.parse()?).create_dm_channel(&http).await?.id,
+        _ => return Ok(()),
+    };
+    target
+        .send_message(&http, build_welcome_message(&i18n, body))
+        .await?;
+    info!("👋 Posted welcome message to {} {}", type_, id);
+    Ok(())
+}
+
+/// 按鈕無法直接觸發 Slash Command，改回覆可點擊的指令提及
+pub async fn handle_welcome_button(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    state: &crate::AppState,
+) -> anyhow::Result<()> {
+    let name = match interaction.data.custom_id.as_str() {
+        "welcome_config" => "config",
+        "welcome_agent" => "agent",
+        _ => return Ok(()),
+    };
+    let mention = serenity::all::Command::get_global_commands(&ctx.http)
+        .await
+        .ok()
+        .and_then(|cmds| cmds.into_iter().find(|c| c.name == name))
+        .map(|c| format!("</{}:{}>", name, c.id))
+        .unwrap_or_else(|| format!("`/{}`", name));
+    let msg = state
+        .i18n
+        .read()
+        .await
+        .get_args("welcome_btn_hint", &[mention]);
+
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(msg)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_list, mention_only, render_template};
+    use crate::auth::AuthManager;
+    use crate::i18n::I18n;
+
+    #[test]
+    fn test_render_template_replaces_named_vars() {
+        let out = render_template(
+            "Hi {assistant_name} on {backend} ({backend})",
+            &[
+                ("assistant_name", "Agent".to_string()),
+                ("backend", "kilo".to_string()),
+            ],
+        );
+        assert_eq!(out, "Hi Agent on kilo (kilo)");
+    }
+
+    #[test]
+    fn test_mention_only_reads_auth_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let auth = AuthManager::with_paths(
+            dir.path().join("auth.json"),
+            dir.path().join("pending_tokens.json"),
+        );
+        for (type_, id) in [("channel", "1"), ("guild", "2"), ("user", "3")] {
+            let token = auth.create_token(type_, id).unwrap();
+            auth.redeem_token(&token).unwrap();
+        }
+        assert!(mention_only(&auth, "channel", "1"));
+        assert!(mention_only(&auth, "guild", "2"));
+        assert!(!mention_only(&auth, "user", "3"));
+
+        auth.set_mention_only("1", false).unwrap();
+        assert!(!mention_only(&auth, "channel", "1"));
+        assert!(!mention_only(&auth, "channel", "404"));
+    }
+
+    #[test]
+    fn test_default_template_uses_known_vars() {
+        let i18n = I18n::new("en");
+        let body = i18n.get("welcome_body");
+        for var in [
+            "{assistant_name}",
+            "{backend}",
+            "{mention_only}",
+            "{commands}",
+        ] {
+            assert!(body.contains(var), "missing {}", var);
+        }
+        assert!(command_list(&i18n).contains("`/config`"));
+    }
+}
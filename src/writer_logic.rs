@@ -7,6 +7,16 @@ pub fn apply_agent_event(
     status: &mut ExecStatus,
     event: AgentEvent,
 ) -> bool {
+    if matches!(
+        event,
+        AgentEvent::MessageUpdate { .. }
+            | AgentEvent::ContentSync { .. }
+            | AgentEvent::ToolExecutionStart { .. }
+            | AgentEvent::ToolExecutionUpdate { .. }
+            | AgentEvent::ToolExecutionEnd { .. }
+    ) {
+        comp.touch();
+    }
     match event {
         AgentEvent::MessageUpdate {
             thinking,
@@ -45,9 +55,15 @@ pub fn apply_agent_event(
                     ContentType::Thinking => Block::new(BlockType::Thinking, i.content),
                     ContentType::Text => Block::new(BlockType::Text, i.content),
                     ContentType::ToolCall(name) => {
+                        if let Some(id) = &i.id {
+                            comp.tool_log.start(id, &name);
+                        }
                         Block::with_label(BlockType::ToolCall, name, i.id)
                     }
                     ContentType::ToolOutput => {
+                        if let Some(id) = &i.id {
+                            comp.tool_log.update(id, &i.content);
+                        }
                         let mut b = Block::new(BlockType::ToolOutput, i.content);
                         b.id = i.id;
                         b
@@ -57,11 +73,16 @@ pub fn apply_agent_event(
             comp.sync_content(mapped);
         }
         AgentEvent::ToolExecutionStart { id, name } => {
+            comp.tool_log.start(&id, &name);
             comp.set_tool_call(id, name);
         }
         AgentEvent::ToolExecutionUpdate { id, output } => {
+            comp.tool_log.update(&id, &output);
             comp.update_block_by_id(&id, BlockType::ToolOutput, output);
         }
+        AgentEvent::ToolExecutionEnd { id, .. } => {
+            comp.finish_tool_call(&id);
+        }
         AgentEvent::AgentEnd { success, error } => {
             *status = if success {
                 ExecStatus::Success
@@ -72,16 +93,43 @@ pub fn apply_agent_event(
         AgentEvent::Error { message } => {
             *status = ExecStatus::Error(message);
         }
+        AgentEvent::ImageInput { mode } => {
+            comp.image_mode = Some(mode);
+        }
+        AgentEvent::SteeringAdded => {
+            comp.steered += 1;
+        }
+        AgentEvent::OutputTruncated => {
+            comp.output_truncated = true;
+        }
+        AgentEvent::Usage {
+            input_tokens,
+            output_tokens,
+        } => {
+            let (input, output) = comp.token_usage.unwrap_or_default();
+            comp.token_usage = Some((input + input_tokens, output + output_tokens));
+        }
+        AgentEvent::Citations { items } => {
+            for item in items {
+                if !comp.citations.contains(&item) {
+                    comp.citations.push(item);
+                }
+            }
+        }
         _ => {}
     }
 
-    *status != ExecStatus::Running
+    let finished = *status != ExecStatus::Running;
+    if finished {
+        comp.finish_all_tool_calls();
+    }
+    finished
 }
 
 #[cfg(test)]
 mod tests {
     use super::apply_agent_event;
-    use crate::agent::{AgentEvent, ContentItem, ContentType};
+    use crate::agent::{AgentEvent, ContentItem, ContentType, ImageInputMode};
     use crate::composer::{BlockType, EmbedComposer};
     use crate::ExecStatus;
 
@@ -168,4 +216,120 @@ mod tests {
         assert!(done2);
         assert_eq!(status, ExecStatus::Error("bad".to_string()));
     }
+
+    #[test]
+    fn test_apply_tool_end_records_elapsed_and_turn_end_closes_rest() {
+        let mut comp = EmbedComposer::new(2000);
+        let mut status = ExecStatus::Running;
+        for id in ["a", "b"] {
+            apply_agent_event(
+                &mut comp,
+                &mut status,
+                AgentEvent::ToolExecutionStart {
+                    id: id.to_string(),
+                    name: "bash".to_string(),
+                },
+            );
+        }
+        apply_agent_event(
+            &mut comp,
+            &mut status,
+            AgentEvent::ToolExecutionEnd {
+                id: "a".to_string(),
+                name: "bash".to_string(),
+            },
+        );
+        let elapsed = |comp: &EmbedComposer, id: &str| {
+            comp.blocks
+                .iter()
+                .find(|b| b.id.as_deref() == Some(id))
+                .and_then(|b| b.timing)
+                .and_then(|t| t.elapsed)
+        };
+        assert!(elapsed(&comp, "a").is_some());
+        assert!(elapsed(&comp, "b").is_none());
+
+        apply_agent_event(
+            &mut comp,
+            &mut status,
+            AgentEvent::AgentEnd {
+                success: true,
+                error: None,
+            },
+        );
+        assert!(elapsed(&comp, "b").is_some());
+    }
+
+    #[test]
+    fn test_tool_log_keeps_outputs_pruned_from_blocks() {
+        let mut comp = EmbedComposer::new(2000);
+        let mut status = ExecStatus::Running;
+        for i in 0..12 {
+            let id = format!("t{}", i);
+            apply_agent_event(
+                &mut comp,
+                &mut status,
+                AgentEvent::ToolExecutionStart {
+                    id: id.clone(),
+                    name: "bash".to_string(),
+                },
+            );
+            apply_agent_event(
+                &mut comp,
+                &mut status,
+                AgentEvent::ToolExecutionUpdate {
+                    id,
+                    output: "y".repeat(1000),
+                },
+            );
+        }
+        assert!(comp.blocks.len() <= 10);
+        assert_eq!(comp.tool_log.tools.len(), 12);
+        assert_eq!(comp.tool_log.tools[0].output.len(), 1000);
+    }
+
+    #[test]
+    fn test_apply_image_input_sets_mode_without_finishing() {
+        let mut comp = EmbedComposer::new(2000);
+        let mut status = ExecStatus::Running;
+        let done = apply_agent_event(
+            &mut comp,
+            &mut status,
+            AgentEvent::ImageInput {
+                mode: ImageInputMode::OcrFallback,
+            },
+        );
+        assert!(!done);
+        assert_eq!(comp.image_mode, Some(ImageInputMode::OcrFallback));
+    }
+
+    #[test]
+    fn test_apply_output_truncated_marks_composer_without_finishing() {
+        let mut comp = EmbedComposer::new(2000);
+        let mut status = ExecStatus::Running;
+        assert!(!apply_agent_event(
+            &mut comp,
+            &mut status,
+            AgentEvent::OutputTruncated
+        ));
+        assert!(comp.output_truncated);
+        assert_eq!(status, ExecStatus::Running);
+    }
+
+    #[test]
+    fn test_apply_usage_accumulates_reported_tokens() {
+        let mut comp = EmbedComposer::new(2000);
+        let mut status = ExecStatus::Running;
+        for (input_tokens, output_tokens) in [(100, 20), (30, 5)] {
+            assert!(!apply_agent_event(
+                &mut comp,
+                &mut status,
+                AgentEvent::Usage {
+                    input_tokens,
+                    output_tokens,
+                },
+            ));
+        }
+        assert_eq!(comp.token_usage, Some((130, 25)));
+    }
 }